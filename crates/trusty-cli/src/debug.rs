@@ -0,0 +1,146 @@
+//! Files written by `trusty build --debug`: the line map and gdb/lldb helpers that set breakpoints
+//! by TRUST line (`trust-break main.trs:12`).
+
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use trusty_compiler::LineMapping;
+
+/// JSON form of the line map: the generated Rust file and one entry per tracked statement.
+fn map_json(rust_file: &Path, line_map: &[LineMapping]) -> Value {
+    let mappings: Vec<Value> = line_map
+        .iter()
+        .map(|m| {
+            json!({
+                "rust_line": m.rust_line,
+                "file": m.file,
+                "line": m.line,
+                "column": m.column,
+            })
+        })
+        .collect();
+    json!({ "rust_file": rust_file.display().to_string(), "mappings": mappings })
+}
+
+/// Python shared by both helpers: finds the Rust file and line of the first statement at `FILE:LINE`.
+fn lookup_script(map_name: &str) -> String {
+    format!(
+        r#"import json
+import os
+
+MAP = os.path.join(os.path.dirname(os.path.abspath(__file__)), "{map_name}")
+
+
+def trust_lookup(spec):
+    file, _, line = spec.strip().rpartition(":")
+    with open(MAP) as f:
+        data = json.load(f)
+    for m in data["mappings"]:
+        source = (m["file"] or "").replace("\\", "/")
+        if m["line"] == int(line) and (not file or source == file or source.endswith("/" + file)):
+            return os.path.basename(data["rust_file"]), m["rust_line"]
+    return None
+"#
+    )
+}
+
+fn gdb_script(stem: &str, map_name: &str) -> String {
+    format!(
+        r#"# Generated by `trusty build --debug`.
+# gdb -x build/{stem}_gdb.py build/{stem}, then `trust-break main.trs:12`.
+import gdb
+
+{lookup}
+
+class TrustBreak(gdb.Command):
+    """trust-break FILE:LINE -- set a breakpoint at a TRUST source line."""
+
+    def __init__(self):
+        super().__init__("trust-break", gdb.COMMAND_BREAKPOINTS)
+
+    def invoke(self, arg, from_tty):
+        found = trust_lookup(arg)
+        if found is None:
+            raise gdb.GdbError("no TRUST statement at " + arg)
+        gdb.execute("break %s:%d" % found)
+
+
+TrustBreak()
+"#,
+        lookup = lookup_script(map_name)
+    )
+}
+
+fn lldb_script(stem: &str, map_name: &str) -> String {
+    format!(
+        r#"# Generated by `trusty build --debug`.
+# lldb build/{stem} -o "command script import build/{stem}_lldb.py", then `trust-break main.trs:12`.
+import lldb
+
+{lookup}
+
+def trust_break(debugger, command, result, internal_dict):
+    found = trust_lookup(command)
+    if found is None:
+        result.SetError("no TRUST statement at " + command)
+        return
+    debugger.HandleCommand("breakpoint set --file %s --line %d" % found)
+
+
+def __lldb_init_module(debugger, internal_dict):
+    debugger.HandleCommand("command script add -f %s.trust_break trust-break" % __name__)
+"#,
+        lookup = lookup_script(map_name)
+    )
+}
+
+/// Writes `<stem>.map.json`, `<stem>_gdb.py` and `<stem>_lldb.py` into `build`; returns their paths.
+pub fn write_debug_files(
+    build: &Path,
+    stem: &str,
+    rust_file: &Path,
+    line_map: &[LineMapping],
+) -> Result<Vec<PathBuf>> {
+    // Python imports the lldb helper as a module, so its name must be an identifier.
+    let stem: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let map_name = format!("{}.map.json", stem);
+    let files = [
+        (
+            map_name.clone(),
+            serde_json::to_string_pretty(&map_json(rust_file, line_map))?,
+        ),
+        (format!("{}_gdb.py", stem), gdb_script(&stem, &map_name)),
+        (format!("{}_lldb.py", stem), lldb_script(&stem, &map_name)),
+    ];
+    let mut paths = Vec::new();
+    for (name, contents) in files {
+        let path = build.join(name);
+        fs::write(&path, contents)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_json_lists_statements() {
+        let map = [LineMapping {
+            rust_line: 7,
+            file: Some("src/main.trs".to_string()),
+            line: 3,
+            column: 5,
+        }];
+        let json = map_json(Path::new("build/main_cargo/src/main.rs"), &map);
+        assert_eq!(json["rust_file"], "build/main_cargo/src/main.rs");
+        assert_eq!(json["mappings"][0]["rust_line"], 7);
+        assert_eq!(json["mappings"][0]["file"], "src/main.trs");
+        assert_eq!(json["mappings"][0]["line"], 3);
+    }
+}
@@ -1,11 +1,23 @@
+mod debug;
+mod output;
+mod repl;
+mod templates;
+mod toolchain;
+
 use anyhow::{bail, Context, Result};
 use clap::{Parser, Subcommand};
+use output::{ColorChoice, MessageFormat, Reporter};
 use serde_json::Value;
-use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
 use std::fs;
+use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
-use std::time::{SystemTime, UNIX_EPOCH};
-use trusty_compiler;
+use std::process::{ExitStatus, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use templates::Template;
 
 #[derive(Parser)]
 #[command(name = "trusty")]
@@ -15,6 +27,26 @@ struct Cli {
     command: Option<Commands>,
 
     input: Option<PathBuf>,
+
+    /// Only print errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Print extra details (generated files, external crates, timings)
+    #[arg(short, long, global = true)]
+    verbose: bool,
+
+    /// Print the time spent bundling, preprocessing, parsing, transpiling and compiling
+    #[arg(long, global = true)]
+    timings: bool,
+
+    /// Output format for CLI messages
+    #[arg(long, global = true, value_enum, default_value_t = MessageFormat::Human)]
+    message_format: MessageFormat,
+
+    /// When to color errors and diagnostics
+    #[arg(long, global = true, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
 }
 
 #[derive(Subcommand)]
@@ -22,10 +54,15 @@ enum Commands {
     /// Create a new TRUST project
     New {
         name: String,
+
+        /// Project scaffold to generate
+        #[arg(short, long, value_enum, default_value_t = Template::Cli)]
+        template: Template,
     },
 
     Build {
-        input: PathBuf,
+        /// Source file (defaults to `main` from trusty.json)
+        input: Option<PathBuf>,
 
         #[arg(short, long)]
         output: Option<PathBuf>,
@@ -35,17 +72,69 @@ enum Commands {
 
         #[arg(short, long)]
         release: bool,
+
+        /// Report panics and thrown errors at their TRUST file and line
+        #[arg(long)]
+        track_locations: bool,
+
+        /// Build a cargo project with debug info, a TRUST line map and gdb/lldb helper scripts
+        #[arg(long)]
+        debug: bool,
+
+        /// Features enabling `@cfg(feature = "...")` code, e.g. `--features metrics,tls`
+        #[arg(long, value_delimiter = ',')]
+        features: Vec<String>,
+
+        /// Compile constructs TRUST cannot translate to placeholders, with a warning, instead of failing
+        #[arg(long)]
+        allow_unknown: bool,
     },
 
     Run {
-        input: PathBuf,
+        /// Source file (defaults to `main` from trusty.json)
+        input: Option<PathBuf>,
 
         #[arg(short, long)]
         release: bool,
+
+        /// Report panics and thrown errors at their TRUST file and line
+        #[arg(long)]
+        track_locations: bool,
+
+        /// Features enabling `@cfg(feature = "...")` code, e.g. `--features metrics,tls`
+        #[arg(long, value_delimiter = ',')]
+        features: Vec<String>,
+
+        /// Compile constructs TRUST cannot translate to placeholders, with a warning, instead of failing
+        #[arg(long)]
+        allow_unknown: bool,
+
+        /// Arguments passed to the program (after `--`)
+        #[arg(last = true)]
+        args: Vec<String>,
     },
 
+    /// Check a file, or every `.trs` file of a project, without producing a binary
     Check {
-        input: PathBuf,
+        /// Source file, or a project directory (defaults to the project of the working directory)
+        input: Option<PathBuf>,
+
+        /// Validate the project's trusty.json instead of compiling
+        #[arg(long)]
+        manifest: bool,
+
+        /// Compile constructs TRUST cannot translate to placeholders, with a warning, instead of failing
+        #[arg(long)]
+        allow_unknown: bool,
+    },
+
+    /// Run lint rules on a file, or on every `.trs` file of the project
+    Lint {
+        input: Option<PathBuf>,
+
+        /// Apply machine-applicable fixes (e.g. `number` → `int32`) in place
+        #[arg(long)]
+        fix: bool,
     },
 
     /// Format a TRUST source file
@@ -57,89 +146,252 @@ enum Commands {
         check: bool,
     },
 
+    /// Start an interactive TRUST session
+    Repl,
+
+    /// Compile and run a snippet, e.g. `trusty eval 'console.write(1 + 2);'`
+    Eval {
+        code: String,
+    },
+
+    /// Check that the Rust toolchain and build directories needed to compile programs are usable
+    Doctor,
+
     Version,
 }
 
-fn main() -> Result<()> {
+fn main() {
     let cli = Cli::parse();
+    let reporter = Reporter::new(cli.quiet, cli.verbose, cli.message_format)
+        .timings(cli.timings)
+        .color(cli.color);
+
+    let started = Instant::now();
+    let code = match run_command(&cli, &reporter) {
+        Ok(code) => code,
+        Err(err) => {
+            reporter.error(&err);
+            error_exit_code(&err)
+        }
+    };
+    reporter.finished(command_name(&cli), code, started.elapsed());
+    if code != 0 {
+        std::process::exit(code);
+    }
+}
+
+/// Exit status of a command that failed: the program does not compile, or any other error.
+/// `run` and `eval` exit with the program's own status once it runs.
+const EXIT_FAILURE: i32 = 1;
+/// Invalid command line, as for the errors clap reports itself.
+const EXIT_USAGE: i32 = 2;
+/// `rustc` or `cargo` is not installed.
+const EXIT_TOOLCHAIN: i32 = 3;
+
+/// An invalid command line that clap cannot detect (a malformed feature name, no file to build).
+#[derive(Debug)]
+struct UsageError(String);
+
+impl std::fmt::Display for UsageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for UsageError {}
+
+fn error_exit_code(err: &anyhow::Error) -> i32 {
+    if err.is::<UsageError>() {
+        EXIT_USAGE
+    } else if err.is::<toolchain::Missing>() {
+        EXIT_TOOLCHAIN
+    } else {
+        EXIT_FAILURE
+    }
+}
 
+/// Error of a command run without a file outside of any project.
+fn no_input(cwd: &Path) -> anyhow::Error {
+    UsageError(format!(
+        "No input file given and no trusty.json found in {} or its parents",
+        cwd.display()
+    ))
+    .into()
+}
+
+/// Runs the selected command and returns the process exit code.
+fn run_command(cli: &Cli, reporter: &Reporter) -> Result<i32> {
     match &cli.command {
-        Some(Commands::New { name }) => {
-            create_project(name)?;
+        Some(Commands::New { name, template }) => {
+            create_project(name, *template, reporter)?;
         }
         Some(Commands::Build {
             input,
             output,
             compile,
             release,
+            track_locations,
+            debug,
+            features,
+            allow_unknown,
+        }) => {
+            let input = resolve_entry(input.as_deref())?;
+            let project = project_options(&input);
+            let mode = BuildMode {
+                release: *release,
+                track_locations: *track_locations,
+                debug: *debug,
+                features,
+                allow_unknown: *allow_unknown,
+            };
+            build_file(
+                &input,
+                output.as_ref(),
+                *compile || *debug,
+                mode,
+                &project.compile,
+                project.target.as_deref(),
+                reporter,
+            )?;
+        }
+        Some(Commands::Run {
+            input,
+            release,
+            track_locations,
+            features,
+            allow_unknown,
+            args,
+        }) => {
+            let input = resolve_entry(input.as_deref())?;
+            let mode = BuildMode {
+                release: *release,
+                track_locations: *track_locations,
+                features,
+                allow_unknown: *allow_unknown,
+                ..BuildMode::default()
+            };
+            return run_file(&input, mode, args, reporter);
+        }
+        Some(Commands::Check {
+            input,
+            manifest: true,
+            ..
         }) => {
-            build_file(input, output.as_ref(), *compile, *release)?;
+            return check_manifest(input.as_deref(), reporter);
+        }
+        Some(Commands::Check {
+            input: Some(input),
+            manifest: false,
+            allow_unknown,
+        }) if !input.is_dir() => {
+            check_file(input, *allow_unknown, reporter)?;
         }
-        Some(Commands::Run { input, release }) => {
-            run_file(input, *release)?;
+        Some(Commands::Check {
+            input,
+            manifest: false,
+            allow_unknown,
+        }) => {
+            return check_project(input.as_deref(), *allow_unknown, reporter);
         }
-        Some(Commands::Check { input }) => {
-            check_file(input)?;
+        Some(Commands::Lint { input, fix }) => {
+            return lint_files(input.as_deref(), *fix, reporter);
         }
         Some(Commands::Format { input, check }) => {
-            format_file(input, *check)?;
+            format_file(input, *check, reporter)?;
+        }
+        Some(Commands::Repl) => {
+            toolchain::require("rustc")?;
+            repl::run_repl(reporter)?;
+        }
+        Some(Commands::Eval { code }) => {
+            return eval_snippet(code, reporter);
+        }
+        Some(Commands::Doctor) => {
+            return Ok(doctor(reporter));
         }
         Some(Commands::Version) => {
             println!("trusty {}", env!("CARGO_PKG_VERSION"));
         }
         None => {
             if let Some(input) = &cli.input {
-                build_file(input, None, false, false)?;
+                let project = project_options(input);
+                build_file(
+                    input,
+                    None,
+                    false,
+                    BuildMode::default(),
+                    &project.compile,
+                    None,
+                    reporter,
+                )?;
             } else {
                 println!("Usage: trusty <file.trs> or trusty --help");
             }
         }
     }
 
-    Ok(())
+    Ok(0)
+}
+
+fn command_name(cli: &Cli) -> &'static str {
+    match &cli.command {
+        Some(Commands::New { .. }) => "new",
+        Some(Commands::Build { .. }) | None => "build",
+        Some(Commands::Run { .. }) => "run",
+        Some(Commands::Check { .. }) => "check",
+        Some(Commands::Lint { .. }) => "lint",
+        Some(Commands::Format { .. }) => "format",
+        Some(Commands::Repl) => "repl",
+        Some(Commands::Eval { .. }) => "eval",
+        Some(Commands::Doctor) => "doctor",
+        Some(Commands::Version) => "version",
+    }
 }
 
 // ─── trusty new ──────────────────────────────────────────────────────────────
 
-fn create_project(name: &str) -> Result<()> {
+fn create_project(name: &str, template: Template, reporter: &Reporter) -> Result<()> {
     let root = PathBuf::from(name);
     if root.exists() {
         bail!("Directory '{}' already exists", name);
     }
 
-    fs::create_dir_all(root.join("src"))?;
-
-    let manifest = serde_json::json!({
-        "name": name,
-        "version": "0.1.0",
-        "dependencies": {}
-    });
+    let manifest = templates::manifest(template, name);
+    fs::create_dir_all(&root)?;
     fs::write(
         root.join("trusty.json"),
         serde_json::to_string_pretty(&manifest)?,
     )?;
 
-    fs::write(
-        root.join("src").join("main.trs"),
-        "function main() {\n    console.write(\"Hello from TRUST!\");\n}\n",
-    )?;
+    for (relative, contents) in templates::files(template) {
+        let path = root.join(relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, contents)?;
+        reporter.detail(&format!("created {}", path.display()));
+    }
 
     fs::write(root.join(".gitignore"), "build/\n")?;
 
-    println!("✅ Created project '{}'", name);
-    println!("   cd {} && trusty run src/main.trs", name);
+    reporter.status(&format!("✅ Created project '{}'", name));
+    reporter.status(&format!("   cd {} && trusty run", name));
 
     Ok(())
 }
 
 // ─── build helpers ───────────────────────────────────────────────────────────
 
-/// Returns the project-level `build/` directory (next to `src/`) when `trusty.json` exists.
+/// Returns the project-level `build/` directory (next to `src/`) when `trusty.json` exists,
+/// or `compilerOptions.outDir` relative to the manifest when set.
 /// Falls back to a local `build/` next to the input file otherwise.
 fn build_dir(input: &Path) -> Result<PathBuf> {
     let parent = input.parent().unwrap_or_else(|| Path::new("."));
     let dir = find_manifest(parent)
-        .and_then(|manifest| manifest.parent().map(|p| p.join("build")))
+        .and_then(|manifest| {
+            let out_dir = read_compiler_options(&manifest).out_dir;
+            manifest.parent().map(|p| p.join(out_dir))
+        })
         .unwrap_or_else(|| parent.join("build"));
     fs::create_dir_all(&dir)
         .with_context(|| format!("Failed to create build directory: {}", dir.display()))?;
@@ -157,7 +409,13 @@ fn stem(input: &Path) -> String {
 
 /// Walk up from `start` looking for `trusty.json`. Returns its path if found.
 fn find_manifest(start: &Path) -> Option<PathBuf> {
-    let mut dir = start.canonicalize().ok()?;
+    // `Path::new("main.trs").parent()` is the empty path, which does not canonicalize.
+    let start = if start.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        start
+    };
+    let mut dir = canonical(start).ok()?;
     loop {
         let candidate = dir.join("trusty.json");
         if candidate.exists() {
@@ -169,6 +427,56 @@ fn find_manifest(start: &Path) -> Option<PathBuf> {
     }
 }
 
+/// `path` canonicalized, without the `\\?\` prefix Windows adds to it: the build directories come
+/// from it, and the prefix would end up in every message and in the paths given to cargo.
+fn canonical(path: &Path) -> std::io::Result<PathBuf> {
+    let path = path.canonicalize()?;
+    #[cfg(windows)]
+    {
+        if let Some(plain) = path
+            .to_str()
+            .and_then(|p| p.strip_prefix(r"\\?\"))
+            .filter(|p| !p.starts_with("UNC\\"))
+        {
+            return Ok(PathBuf::from(plain));
+        }
+    }
+    Ok(path)
+}
+
+/// Entry point used when `trusty build`/`trusty run` get no file argument:
+/// the `main` field of the nearest `trusty.json` (default `src/main.trs`), relative to the manifest.
+fn resolve_entry(input: Option<&Path>) -> Result<PathBuf> {
+    if let Some(input) = input {
+        return Ok(input.to_path_buf());
+    }
+
+    let cwd = std::env::current_dir()?;
+    let manifest = find_manifest(&cwd).ok_or_else(|| no_input(&cwd))?;
+    manifest_entry(&manifest)
+}
+
+/// The `main` file of `manifest` (default `src/main.trs`), which must exist.
+fn manifest_entry(manifest: &Path) -> Result<PathBuf> {
+    let text = fs::read_to_string(manifest)?;
+    let json: Value = serde_json::from_str(&text)
+        .with_context(|| format!("Invalid JSON in {}", manifest.display()))?;
+    let main = json
+        .get("main")
+        .and_then(|v| v.as_str())
+        .unwrap_or("src/main.trs");
+    let root = manifest.parent().unwrap_or_else(|| Path::new("."));
+    let entry = root.join(main);
+    if !entry.exists() {
+        bail!(
+            "Entry point '{}' from {} does not exist",
+            main,
+            manifest.display()
+        );
+    }
+    Ok(entry)
+}
+
 /// Read `dependencies` map from `trusty.json`.
 fn read_dependencies(manifest_path: &Path) -> Result<HashMap<String, String>> {
     let text = fs::read_to_string(manifest_path)?;
@@ -183,19 +491,210 @@ fn read_dependencies(manifest_path: &Path) -> Result<HashMap<String, String>> {
     Ok(deps)
 }
 
+/// `compilerOptions` block of `trusty.json`, read once per command.
+struct CompilerOptions {
+    /// Build directory, relative to the manifest (default `build`).
+    out_dir: String,
+    /// Rust target triple passed to rustc/cargo (`--target`), if any.
+    target: Option<String>,
+    /// How the compiler translates the project's files, its `dependencies` included.
+    compile: trusty_compiler::CompileOptions,
+}
+
+fn read_compiler_options(manifest_path: &Path) -> CompilerOptions {
+    let json: Value = fs::read_to_string(manifest_path)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or(Value::Null);
+    compiler_options(&json)
+}
+
+/// Options of the project containing `path` (a file or a directory), the defaults outside a
+/// project. The language of compiler messages is `compilerOptions.locale`, else the first of
+/// `TRUSTY_LOCALE`, `LC_ALL`, `LC_MESSAGES` and `LANG` that is set, English when none names a
+/// language TRUST speaks.
+fn project_options(path: &Path) -> CompilerOptions {
+    let dir = if path.is_dir() {
+        path
+    } else {
+        path.parent().unwrap_or_else(|| Path::new("."))
+    };
+    match find_manifest(dir) {
+        Some(manifest) => read_compiler_options(&manifest),
+        None => compiler_options(&Value::Null),
+    }
+}
+
+fn compiler_options(json: &Value) -> CompilerOptions {
+    let options = json.get("compilerOptions");
+    let out_dir = options
+        .and_then(|o| o.get("outDir"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("build")
+        .to_string();
+    let target = options
+        .and_then(|o| o.get("target"))
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+    let mut compile = trusty_compiler::manifest::compile_options(json);
+    let locale = options
+        .and_then(|o| o.get("locale"))
+        .and_then(|v| v.as_str())
+        .and_then(trusty_compiler::Locale::parse);
+    if locale.is_none() {
+        compile.locale = ["TRUSTY_LOCALE", "LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()))
+            .and_then(|value| trusty_compiler::Locale::parse(&value))
+            .unwrap_or_default();
+    }
+    CompilerOptions {
+        out_dir,
+        target,
+        compile,
+    }
+}
+
+/// Lint rule levels from the manifest's `lint` block, e.g. `{ "magic-number": "warn", "maxFunctionLines": 80 }`.
+fn read_lint_config(manifest_path: &Path) -> Result<trusty_compiler::LintConfig> {
+    let text = fs::read_to_string(manifest_path)?;
+    let json: Value = serde_json::from_str(&text)
+        .with_context(|| format!("Invalid JSON in {}", manifest_path.display()))?;
+    let mut config = trusty_compiler::LintConfig::default();
+    for (key, value) in json
+        .get("lint")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+    {
+        let value = match value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        config
+            .configure(key, &value)
+            .map_err(|message| anyhow::anyhow!("{} (in {})", message, manifest_path.display()))?;
+    }
+    Ok(config)
+}
+
+/// File name of the compiled binary; wasm targets get a `.wasm` extension and Windows ones (the
+/// host, without a target) `.exe`.
+fn binary_name(stem: &str, target: Option<&str>) -> String {
+    format!("{}{}", stem, binary_suffix(target))
+}
+
+/// Extension rustc and cargo give the binaries they build for `target`, or for the host.
+fn binary_suffix(target: Option<&str>) -> &'static str {
+    match target {
+        Some(t) if t.starts_with("wasm32") => ".wasm",
+        Some(t) if t.contains("windows") => ".exe",
+        Some(_) => "",
+        None => std::env::consts::EXE_SUFFIX,
+    }
+}
+
+/// The binary `-o output` names: rustc writes exactly the path it is given, so one without an
+/// extension gets the target's (`app` → `app.exe` on Windows) to stay runnable.
+fn output_binary(output: &Path, target: Option<&str>) -> PathBuf {
+    if output.extension().is_some() {
+        return output.to_path_buf();
+    }
+    let mut path = output.as_os_str().to_owned();
+    path.push(binary_suffix(target));
+    PathBuf::from(path)
+}
+
+/// Command running the binary at `bin_path`, made absolute: a path without a directory would be
+/// looked up on `PATH`, and Windows does not resolve relative ones like Unix does.
+fn program_command(bin_path: &Path) -> std::process::Command {
+    std::process::Command::new(
+        std::path::absolute(bin_path).unwrap_or_else(|_| bin_path.to_path_buf()),
+    )
+}
+
 // ─── trusty build ────────────────────────────────────────────────────────────
 
+/// How `build`, `run` and the snippet commands compile a program.
+#[derive(Clone, Copy, Default)]
+struct BuildMode<'a> {
+    release: bool,
+    /// `--track-locations`; the project's `trackLocations` also enables it.
+    track_locations: bool,
+    /// `build --debug`: always a cargo project with debug info, plus the files from [`debug`].
+    debug: bool,
+    /// `--features`: the `feature = "..."` names `@cfg` conditions see as set.
+    features: &'a [String],
+    /// `--allow-unknown`: untranslatable constructs become placeholders with a warning.
+    allow_unknown: bool,
+}
+
 fn build_file(
-    input: &PathBuf,
+    input: &Path,
     output: Option<&PathBuf>,
     compile: bool,
-    release: bool,
+    mode: BuildMode,
+    options: &trusty_compiler::CompileOptions,
+    target: Option<&str>,
+    reporter: &Reporter,
 ) -> Result<PathBuf> {
-    println!("🔨 Building {}...", input.display());
+    reporter.status(&format!("🔨 Building {}...", input.display()));
+    let valid_feature = |f: &String| {
+        !f.is_empty()
+            && f.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    };
+    if let Some(feature) = mode.features.iter().find(|f| !valid_feature(f)) {
+        return Err(UsageError(format!(
+            "Invalid feature name '{}': use letters, digits, '_' and '-'",
+            feature
+        ))
+        .into());
+    }
+    if compile {
+        // Before the program is compiled at all: without rustc, the build cannot end well.
+        toolchain::require("rustc")?;
+    }
 
-    let source = resolve_and_bundle_modules(input)?;
+    let mut compiler = trusty_compiler::Compiler::new()
+        .options(options.clone())
+        .track_locations(mode.track_locations || options.track_locations)
+        .allow_unknown(mode.allow_unknown)
+        .require_main(true);
+    if mode.debug {
+        compiler = compiler.artifacts(&[
+            trusty_compiler::Artifact::Rust,
+            trusty_compiler::Artifact::LineMap,
+        ]);
+    }
+    let started = Instant::now();
+    let source = compiler.bundle(input)?;
+    reporter.timing("bundle", started.elapsed());
 
-    let transpile_output = trusty_compiler::compile_full(&source)?;
+    let mut compiled = compiler.compile(&source);
+    for (phase, elapsed) in &compiled.timings {
+        reporter.timing(phase, *elapsed);
+    }
+    for diag in &compiled.diagnostics {
+        reporter.located_diagnostic(diag, input, &source);
+    }
+    let errors = compiled.errors().count();
+    if errors > 0 {
+        bail!(
+            "Could not compile {} ({} error{})",
+            input.display(),
+            errors,
+            if errors == 1 { "" } else { "s" }
+        );
+    }
+    let line_map = compiled.line_map.take();
+    let transpile_output = compiled.into_result()?;
+    if !transpile_output.required_crates.is_empty() {
+        reporter.detail(&format!(
+            "external crates: {}",
+            transpile_output.required_crates.join(", ")
+        ));
+    }
 
     let build = build_dir(input)?;
     let stem = stem(input);
@@ -203,48 +702,72 @@ fn build_file(
     // Always write the intermediate .rs into build/
     let rs_path = build.join(format!("{}.rs", stem));
     fs::write(&rs_path, &transpile_output.rust_code)?;
+    reporter.detail(&format!("wrote {}", rs_path.display()));
 
     if compile {
-        let bin_path = output.cloned().unwrap_or_else(|| build.join(&stem));
+        let bin_path = match output {
+            Some(output) => output_binary(output, target),
+            None => build.join(binary_name(&stem, target)),
+        };
+        // A failed build must not leave the previous binary behind, to be run as if it were this one.
+        fs::remove_file(&bin_path).ok();
 
-        if transpile_output.required_crates.is_empty() {
+        let started = Instant::now();
+        if transpile_output.required_crates.is_empty() && !mode.debug {
             // No external crates → fast rustc path
-            compile_with_rustc(&rs_path, &bin_path, release)?;
+            compile_with_rustc(&rs_path, &bin_path, mode, target, reporter)?;
+            reporter.timing("rustc", started.elapsed());
         } else {
-            // External crates → generate a Cargo project and use cargo build
-            compile_with_cargo(
-                input,
-                &transpile_output.rust_code,
-                &transpile_output.required_crates,
-                &bin_path,
-                release,
-            )?;
+            // External crates or a debug build → generate a Cargo project and use cargo build
+            compile_with_cargo(input, &transpile_output, &bin_path, mode, target, reporter)?;
+            reporter.timing("cargo", started.elapsed());
+        }
+        if let Some(line_map) = &line_map {
+            let rust_file = cargo_project_dir(&build, &stem).join("src").join("main.rs");
+            for path in debug::write_debug_files(&build, &stem, &rust_file, line_map)? {
+                reporter.artifact("debug", &path);
+            }
         }
 
         fs::remove_file(&rs_path).ok();
+        reporter.artifact("binary", &bin_path);
         Ok(bin_path)
     } else {
         let final_path = output.cloned().unwrap_or(rs_path);
-        println!("✅ Generated {}", final_path.display());
+        reporter.status(&format!("✅ Generated {}", final_path.display()));
+        reporter.artifact("rust-source", &final_path);
         Ok(final_path)
     }
 }
 
 // ─── rustc (no external deps) ────────────────────────────────────────────────
 
-fn compile_with_rustc(rs_file: &Path, bin_path: &Path, release: bool) -> Result<()> {
-    println!("🦀 Compiling with rustc...");
+fn compile_with_rustc(
+    rs_file: &Path,
+    bin_path: &Path,
+    mode: BuildMode,
+    target: Option<&str>,
+    reporter: &Reporter,
+) -> Result<()> {
+    reporter.status("🦀 Compiling with rustc...");
 
     let mut cmd = std::process::Command::new("rustc");
     cmd.arg(rs_file);
     cmd.arg("-o").arg(bin_path);
-    if release {
+    if mode.release {
         cmd.arg("-C").arg("opt-level=3");
     }
+    for feature in mode.features {
+        cmd.arg("--cfg").arg(format!("feature=\"{}\"", feature));
+    }
+    if let Some(target) = target {
+        cmd.arg("--target").arg(target);
+    }
 
-    let out = cmd.output()?;
+    reporter.detail(&format!("running {:?}", cmd));
+    let out = toolchain::output(&mut cmd)?;
     if out.status.success() {
-        println!("✅ Binary ready: {}", bin_path.display());
+        reporter.status(&format!("✅ Binary ready: {}", bin_path.display()));
     } else {
         let stderr = String::from_utf8_lossy(&out.stderr);
         bail!("❌ Compilation failed:\n{}", stderr.trim());
@@ -254,91 +777,474 @@ fn compile_with_rustc(rs_file: &Path, bin_path: &Path, release: bool) -> Result<
 
 // ─── cargo (with external deps) ──────────────────────────────────────────────
 
+/// Cargo.toml requirement for `crate_name`: the project's own pin from `trusty.json`, else the
+/// version the stdlib code was generated for, else any version.
+fn dependency_version<'a>(
+    crate_name: &str,
+    manifest_deps: &'a HashMap<String, String>,
+    stdlib_versions: &'a [(String, String)],
+) -> &'a str {
+    manifest_deps
+        .get(crate_name)
+        .or_else(|| {
+            stdlib_versions
+                .iter()
+                .find(|(name, _)| name == crate_name)
+                .map(|(_, version)| version)
+        })
+        .map(String::as_str)
+        .unwrap_or("*")
+}
+
+/// Cargo.toml line for the runtime crate with `features`: the checkout named by
+/// `TRUSTY_RUNTIME_PATH`, else the one next to this CLI's sources when present, else the
+/// published release.
+fn runtime_dependency(features: &[String]) -> String {
+    let local = std::env::var_os("TRUSTY_RUNTIME_PATH")
+        .map(PathBuf::from)
+        .or_else(|| Some(Path::new(env!("CARGO_MANIFEST_DIR")).join("../trusty-runtime")))
+        .filter(|path| path.join("Cargo.toml").is_file());
+    let source = match local {
+        Some(path) => format!("path = {:?}", path.display().to_string()),
+        None => format!("version = \"{}\"", trusty_compiler::stdlib::RUNTIME_VERSION),
+    };
+    let features: Vec<String> = features
+        .iter()
+        .map(|feature| format!("{:?}", feature))
+        .collect();
+    format!(
+        "{} = {{ package = \"trusty-runtime\", {}, features = [{}] }}\n",
+        trusty_compiler::stdlib::RUNTIME_CRATE,
+        source,
+        features.join(", ")
+    )
+}
+
 fn compile_with_cargo(
     input: &Path,
-    rust_code: &str,
-    required_crates: &[String],
+    transpiled: &trusty_compiler::TranspileOutput,
     bin_path: &Path,
-    release: bool,
+    mode: BuildMode,
+    target: Option<&str>,
+    reporter: &Reporter,
 ) -> Result<()> {
-    println!("📦 External crates detected, building with cargo...");
+    toolchain::require("cargo")?;
+    let required_crates = &transpiled.required_crates;
+    if required_crates.is_empty() {
+        reporter.status("📦 Building debug cargo project...");
+    } else {
+        reporter.status("📦 External crates detected, building with cargo...");
+    }
 
     // Resolve dependency versions from trusty.json (if present)
     let manifest_deps = input
         .parent()
-        .and_then(|p| find_manifest(p))
+        .and_then(find_manifest)
         .map(|m| read_dependencies(&m).unwrap_or_default())
         .unwrap_or_default();
 
     let build = build_dir(input)?;
     let stem = stem(input);
-    let cargo_project = build.join(format!("{}_cargo", stem));
+    let cargo_project = cargo_project_dir(&build, &stem);
 
     fs::create_dir_all(cargo_project.join("src"))?;
 
     // Generate Cargo.toml
     let mut deps_toml = String::new();
     for crate_name in required_crates {
-        let version = manifest_deps
-            .get(crate_name)
-            .map(String::as_str)
-            .unwrap_or("*");
-        deps_toml.push_str(&format!("{} = \"{}\"\n", crate_name, version));
+        if crate_name == trusty_compiler::stdlib::RUNTIME_CRATE {
+            deps_toml.push_str(&runtime_dependency(&transpiled.runtime_features));
+            continue;
+        }
+        deps_toml.push_str(&format!(
+            "{} = \"{}\"\n",
+            crate_name,
+            dependency_version(crate_name, &manifest_deps, &transpiled.crate_versions)
+        ));
     }
 
-    let cargo_toml = format!(
-        "[package]\nname = \"{stem}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\n{deps_toml}\n[workspace]\n"
+    let features_toml: String = mode
+        .features
+        .iter()
+        .map(|feature| format!("{} = []\n", feature))
+        .collect();
+    let package = package_name(&stem);
+    let mut cargo_toml = format!(
+        "[package]\nname = \"{package}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\n{deps_toml}\n[features]\n{features_toml}\n[workspace]\n"
     );
+    if mode.debug {
+        // Full debug info, even with `--release`.
+        cargo_toml.push_str("\n[profile.dev]\ndebug = 2\n\n[profile.release]\ndebug = 2\n");
+    }
     fs::write(cargo_project.join("Cargo.toml"), &cargo_toml)?;
 
     // Write generated Rust source
-    fs::write(cargo_project.join("src").join("main.rs"), rust_code)?;
+    fs::write(
+        cargo_project.join("src").join("main.rs"),
+        &transpiled.rust_code,
+    )?;
 
-    // cargo build
+    // cargo build, into the target directory every program of the build directory shares
+    let target_root = cargo_target_dir(&build);
     let mut cmd = std::process::Command::new("cargo");
     cmd.arg("build");
+    cmd.env("CARGO_TARGET_DIR", &target_root);
     cmd.arg("--manifest-path")
         .arg(cargo_project.join("Cargo.toml"));
-    if release {
+    if mode.release {
         cmd.arg("--release");
     }
+    if !mode.features.is_empty() {
+        cmd.arg("--features").arg(mode.features.join(","));
+    }
+    if let Some(target) = target {
+        cmd.arg("--target").arg(target);
+    }
 
-    let out = cmd.output()?;
+    reporter.detail(&format!("running {:?}", cmd));
+    let out = toolchain::output(&mut cmd)?;
     if !out.status.success() {
         let stderr = String::from_utf8_lossy(&out.stderr);
         bail!("❌ Compilation failed:\n{}", stderr.trim());
     }
 
     // Copy binary to the expected bin_path
-    let profile = if release { "release" } else { "debug" };
-    let cargo_bin = cargo_project.join("target").join(profile).join(&stem);
+    let profile = if mode.release { "release" } else { "debug" };
+    let mut target_dir = target_root;
+    if let Some(target) = target {
+        target_dir.push(target);
+    }
+    let cargo_bin = target_dir.join(profile).join(binary_name(&package, target));
 
     fs::copy(&cargo_bin, bin_path)
         .with_context(|| format!("Failed to copy binary from {}", cargo_bin.display()))?;
 
-    println!("✅ Binary ready: {}", bin_path.display());
+    reporter.status(&format!("✅ Binary ready: {}", bin_path.display()));
     Ok(())
 }
 
+/// Cargo project generated for `stem` inside the build directory.
+fn cargo_project_dir(build: &Path, stem: &str) -> PathBuf {
+    build.join(format!("{}_cargo", stem))
+}
+
+/// Target directory of the cargo projects in `build`: `build/target`, shared by every program of
+/// the project (or of the directory, for loose files) so that their crates (serde, ureq, ...) are
+/// compiled once. A `CARGO_TARGET_DIR` set by the user, e.g. one cache for all projects, wins.
+fn cargo_target_dir(build: &Path) -> PathBuf {
+    std::env::var_os("CARGO_TARGET_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| build.join("target"))
+}
+
+/// Cargo package (and binary) name for the program `stem`: characters cargo rejects become `_`,
+/// and names it reserves (keywords, `test`, `build`, ...) or that start with a digit get a
+/// `trusty_` prefix. A file always gets the same name, so that rebuilding it in the shared target
+/// directory reuses its own artifacts.
+fn package_name(stem: &str) -> String {
+    const RESERVED: &[&str] = &[
+        "abstract",
+        "alloc",
+        "as",
+        "async",
+        "await",
+        "become",
+        "box",
+        "break",
+        "build",
+        "const",
+        "continue",
+        "core",
+        "crate",
+        "deps",
+        "do",
+        "dyn",
+        "else",
+        "enum",
+        "examples",
+        "extern",
+        "false",
+        "final",
+        "fn",
+        "for",
+        "gen",
+        "if",
+        "impl",
+        "in",
+        "incremental",
+        "let",
+        "loop",
+        "macro",
+        "match",
+        "mod",
+        "move",
+        "mut",
+        "override",
+        "priv",
+        "proc_macro",
+        "pub",
+        "ref",
+        "return",
+        "self",
+        "static",
+        "std",
+        "struct",
+        "super",
+        "test",
+        "trait",
+        "true",
+        "try",
+        "type",
+        "typeof",
+        "unsafe",
+        "unsized",
+        "use",
+        "virtual",
+        "where",
+        "while",
+        "yield",
+    ];
+    let name: String = stem
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let starts_with_digit = name.chars().next().is_none_or(|c| c.is_ascii_digit());
+    if starts_with_digit || RESERVED.contains(&name.to_lowercase().as_str()) {
+        format!("trusty_{}", name)
+    } else {
+        name
+    }
+}
+
 // ─── trusty run ──────────────────────────────────────────────────────────────
 
-fn run_file(input: &PathBuf, release: bool) -> Result<()> {
-    println!("🚀 Running {}...", input.display());
+/// Builds and executes `input`, forwarding `args` and the terminal's stdio.
+/// Returns the child's exit code so the CLI can exit with it.
+fn run_file(input: &Path, mode: BuildMode, args: &[String], reporter: &Reporter) -> Result<i32> {
+    reporter.status(&format!("🚀 Running {}...", input.display()));
 
-    let bin_path = build_file(input, None, true, release)?;
+    // Always build for the host: `compilerOptions.target` only applies to `trusty build`.
+    let project = project_options(input);
+    let bin_path = build_file(input, None, true, mode, &project.compile, None, reporter)?;
 
-    std::process::Command::new(&bin_path)
+    let status = program_command(&bin_path)
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stdout(reporter.program_stdout())
+        .stderr(Stdio::inherit())
         .spawn()
         .with_context(|| format!("Failed to run {}", bin_path.display()))?
         .wait()?;
 
-    Ok(())
+    Ok(exit_code_of(status))
+}
+
+/// Maps a child exit status to a shell-style code (`128 + signal` when killed on Unix).
+fn exit_code_of(status: ExitStatus) -> i32 {
+    if let Some(code) = status.code() {
+        return code;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+    1
+}
+
+// ─── trusty doctor ───────────────────────────────────────────────────────────
+
+/// Runs the toolchain checks for the project of the working directory (its `outDir` and
+/// `target`), or for a plain `build/` there, plus the `trusty eval` cache.
+fn doctor(reporter: &Reporter) -> i32 {
+    let cwd = PathBuf::from(".");
+    let (build, target) = match find_manifest(&cwd) {
+        Some(manifest) => {
+            let options = read_compiler_options(&manifest);
+            let root = manifest.parent().map(Path::to_path_buf).unwrap_or(cwd);
+            (root.join(options.out_dir), options.target)
+        }
+        None => (cwd.join("build"), None),
+    };
+    let eval_cache = std::env::temp_dir().join("trusty-eval-cache");
+    toolchain::doctor(target.as_deref(), &[build, eval_cache], reporter)
+}
+
+// ─── trusty eval ─────────────────────────────────────────────────────────────
+
+/// Compiles and runs `code` as a program, its top-level statements forming `main`; a lone
+/// expression is printed. Sources, build files and binaries are named after a hash of the
+/// generated Rust, so concurrent evals do not overwrite each other, repeated ones skip the
+/// toolchain entirely, new ones reuse the crates compiled in the shared cargo target directory,
+/// and a compiler whose codegen or runtime changed builds afresh.
+fn eval_snippet(code: &str, reporter: &Reporter) -> Result<i32> {
+    let program = match repl::classify(code) {
+        repl::Entry::Expression(expr) if !expr.contains(';') => {
+            format!("console.write({});\n", expr)
+        }
+        _ => code.to_string(),
+    };
+
+    // The snippet compiles like a file of the working directory's project.
+    let project = project_options(Path::new("."));
+    let rust_code = trusty_compiler::Compiler::new()
+        .options(project.compile.clone())
+        .compile(&program)
+        .rust_code;
+    let cache = std::env::temp_dir().join("trusty-eval-cache");
+    fs::create_dir_all(&cache)?;
+    let mut hasher = DefaultHasher::new();
+    // The runtime crate and the project's dependencies are not in the Rust source.
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    project.compile.dependencies.hash(&mut hasher);
+    project.compile.runtime_crate.hash(&mut hasher);
+    match &rust_code {
+        Some(rust_code) => rust_code.hash(&mut hasher),
+        // The build reports the errors; nothing is cached.
+        None => program.hash(&mut hasher),
+    }
+    let name = format!("eval-{:016x}", hasher.finish());
+    let bin_path = cache.join(binary_name(&name, None));
+
+    if bin_path.exists() {
+        reporter.detail(&format!("cached {}", bin_path.display()));
+    } else {
+        let source_path = cache.join(format!("{}.trs", name));
+        fs::write(&source_path, &program)?;
+        let quiet = Reporter::new(true, false, MessageFormat::Human);
+        build_file(
+            &source_path,
+            Some(&bin_path),
+            true,
+            BuildMode::default(),
+            &project.compile,
+            None,
+            &quiet,
+        )?;
+    }
+
+    let status = program_command(&bin_path)
+        .stdin(Stdio::inherit())
+        .stdout(reporter.program_stdout())
+        .stderr(Stdio::inherit())
+        .status()
+        .with_context(|| format!("Failed to run {}", bin_path.display()))?;
+    Ok(exit_code_of(status))
+}
+
+// ─── trusty lint ─────────────────────────────────────────────────────────────
+
+/// Lints `input`, or every `.trs` file under the project root, first applying fixes with `--fix`;
+/// exits with 1 if a rule set to
+/// `error` fired.
+fn lint_files(input: Option<&Path>, fix: bool, reporter: &Reporter) -> Result<i32> {
+    let cwd = std::env::current_dir()?;
+    let manifest = find_manifest(input.and_then(Path::parent).unwrap_or(&cwd));
+    let config = match &manifest {
+        Some(manifest) => read_lint_config(manifest)?,
+        None => trusty_compiler::LintConfig::default(),
+    };
+    let files = match (input, &manifest) {
+        (Some(input), _) => vec![input.to_path_buf()],
+        (None, Some(manifest)) => trs_files(manifest.parent().unwrap_or_else(|| Path::new("."))),
+        (None, None) => return Err(no_input(&cwd)),
+    };
+
+    let (mut warnings, mut errors) = (0, 0);
+    for file in &files {
+        let mut source = fs::read_to_string(file)
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        if fix {
+            let (fixed, applied) = fix_source(&source, &config);
+            if applied > 0 {
+                fs::write(file, &fixed)
+                    .with_context(|| format!("Failed to write {}", file.display()))?;
+                reporter.status(&format!(
+                    "🔧 Applied {} fix(es) to {}",
+                    applied,
+                    file.display()
+                ));
+                source = fixed;
+            }
+        }
+        // A file that does not parse is reported with its parse error, as an error finding.
+        let findings =
+            trusty_compiler::lint::lint(&source, &config).unwrap_or_else(|error| vec![*error]);
+        for diag in findings {
+            if diag.is_error() {
+                errors += 1;
+            } else {
+                warnings += 1;
+            }
+            reporter.located_diagnostic(&diag, file, &source);
+        }
+    }
+
+    if warnings + errors == 0 {
+        reporter.status(&format!("✅ No lint findings in {} file(s)", files.len()));
+    } else {
+        reporter.status(&format!("🔎 {} warning(s), {} error(s)", warnings, errors));
+    }
+    Ok(if errors > 0 { 1 } else { 0 })
+}
+
+/// Applies the fixes of lint findings and compiler diagnostics (e.g. `number` → `int32`) to
+/// `source`; returns the new text and the number of fixes applied.
+fn fix_source(source: &str, config: &trusty_compiler::LintConfig) -> (String, usize) {
+    let mut source = source.to_string();
+    let mut total = 0;
+    // A fix skipped for overlapping another one is offered again on the next pass.
+    for _ in 0..10 {
+        let mut diagnostics = trusty_compiler::lint::lint(&source, config).unwrap_or_default();
+        diagnostics.extend(
+            trusty_compiler::Compiler::new()
+                .compile(&source)
+                .diagnostics,
+        );
+        let (fixed, applied) = trusty_compiler::apply_fixes(
+            &source,
+            diagnostics.iter().filter_map(|d| d.fix.as_ref()),
+        );
+        if applied == 0 {
+            break;
+        }
+        source = fixed;
+        total += applied;
+    }
+    (source, total)
+}
+
+/// `.trs` files under `root`, skipping build output and hidden directories, sorted.
+fn trs_files(root: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = walkdir::WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            entry.depth() == 0 || !(name.starts_with('.') || name == "build" || name == "target")
+        })
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry.file_type().is_file() && entry.path().extension().is_some_and(|ext| ext == "trs")
+        })
+        .map(|entry| entry.into_path())
+        .collect();
+    files.sort();
+    files
 }
 
 // ─── trusty check ────────────────────────────────────────────────────────────
 
-fn check_file(input: &PathBuf) -> Result<()> {
-    println!("🔍 Checking {}...", input.display());
+fn check_file(input: &Path, allow_unknown: bool, reporter: &Reporter) -> Result<()> {
+    reporter.status(&format!("🔍 Checking {}...", input.display()));
 
     // `build --compile` catches parser/transpiler errors and Rust type errors.
     let mut out = std::env::temp_dir();
@@ -352,23 +1258,203 @@ fn check_file(input: &PathBuf) -> Result<()> {
         std::process::id(),
         nonce
     ));
-    let _ = build_file(input, Some(&out), true, false)?;
+    let mode = BuildMode {
+        allow_unknown,
+        ..BuildMode::default()
+    };
+    let project = project_options(input);
+    let _ = build_file(
+        input,
+        Some(&out),
+        true,
+        mode,
+        &project.compile,
+        None,
+        reporter,
+    )?;
     let _ = fs::remove_file(out);
 
-    println!("✅ No errors found");
+    reporter.status("✅ No errors found");
     Ok(())
 }
 
+/// Checks every `.trs` file under the project's `src/` (the whole project without one), reporting
+/// all diagnostics rather than stopping at the first; the entry is then checked by rustc. Exits
+/// with 1 if any file has errors.
+fn check_project(input: Option<&Path>, allow_unknown: bool, reporter: &Reporter) -> Result<i32> {
+    let cwd = std::env::current_dir()?;
+    let root = match input {
+        Some(dir) => dir.to_path_buf(),
+        None => find_manifest(&cwd)
+            .and_then(|manifest| manifest.parent().map(Path::to_path_buf))
+            .ok_or_else(|| no_input(&cwd))?,
+    };
+    let src = root.join("src");
+    let files = trs_files(if src.is_dir() { &src } else { &root });
+    if files.is_empty() {
+        bail!("No .trs files found in {}", root.display());
+    }
+    reporter.status(&format!(
+        "🔍 Checking {} file(s) in {}...",
+        files.len(),
+        root.display()
+    ));
+
+    let mut options = project_options(&root).compile;
+    options.allow_unknown = allow_unknown;
+    // Files are checked independently, so one worker per core takes them from a shared queue;
+    // findings are reported in file order afterwards.
+    let workers = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(files.len());
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<Result<CheckedFile>>>> =
+        Mutex::new(files.iter().map(|_| None).collect());
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(file) = files.get(index) else {
+                        break;
+                    };
+                    let result = check_project_file(file, &options);
+                    results.lock().unwrap_or_else(|e| e.into_inner())[index] = Some(result);
+                })
+            })
+            .collect();
+        // Joined here, a panicking worker leaves its file without a result instead of
+        // aborting the command.
+        for handle in handles {
+            let _ = handle.join();
+        }
+    });
+    // A file without a result panicked its worker; the other workers drained the queue.
+    let checked = results
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner())
+        .into_iter()
+        .map(|result| result.unwrap_or_else(|| Err(anyhow::anyhow!("Checking a file panicked"))));
+
+    let (mut warnings, mut errors) = (0, 0);
+    for (file, checked) in files.iter().zip(checked) {
+        let checked = checked?;
+        if let Some(err) = &checked.module_error {
+            errors += 1;
+            reporter.error(err);
+        }
+        for diag in &checked.output.diagnostics {
+            if diag.is_error() {
+                errors += 1;
+            } else {
+                warnings += 1;
+            }
+            reporter.located_diagnostic(diag, file, &checked.source);
+        }
+        for (phase, elapsed) in &checked.output.timings {
+            reporter.timing(&format!("{} {}", phase, file.display()), *elapsed);
+        }
+    }
+
+    // rustc only sees the program the entry bundles, once every file transpiles.
+    let manifest = root.join("trusty.json");
+    let entry = if manifest.exists() {
+        Some(manifest_entry(&manifest)?)
+    } else {
+        Some(src.join("main.trs")).filter(|main| main.exists())
+    };
+    if let (0, Some(entry)) = (errors, entry) {
+        if let Err(err) = check_file(&entry, allow_unknown, reporter) {
+            errors += 1;
+            reporter.error(&err);
+        }
+    }
+
+    if warnings + errors == 0 {
+        reporter.status(&format!("✅ No errors found in {} file(s)", files.len()));
+    } else {
+        reporter.status(&format!(
+            "🔎 {} file(s): {} warning(s), {} error(s)",
+            files.len(),
+            warnings,
+            errors
+        ));
+    }
+    Ok(if errors > 0 { 1 } else { 0 })
+}
+
+/// One file of `trusty check` on a project.
+struct CheckedFile {
+    source: String,
+    /// Why the file's imports could not be bundled.
+    module_error: Option<anyhow::Error>,
+    output: trusty_compiler::CompileOutput,
+}
+
+fn check_project_file(
+    file: &Path,
+    options: &trusty_compiler::CompileOptions,
+) -> Result<CheckedFile> {
+    let source =
+        fs::read_to_string(file).with_context(|| format!("Failed to read {}", file.display()))?;
+    let compiler = trusty_compiler::Compiler::new().options(options.clone());
+    // Imports are resolved here; the file's own code is checked on its own.
+    let module_error = compiler.bundle(file).err();
+    let output = compiler.compile(&source);
+    Ok(CheckedFile {
+        source,
+        module_error,
+        output,
+    })
+}
+
+/// Validates the `trusty.json` at or above `input` (or the working directory); exits with 1 if
+/// it has errors.
+fn check_manifest(input: Option<&Path>, reporter: &Reporter) -> Result<i32> {
+    let cwd = std::env::current_dir()?;
+    let start = match input {
+        Some(path) if path.is_dir() => path,
+        Some(path) => path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new(".")),
+        None => &cwd,
+    };
+    let manifest = find_manifest(start).ok_or_else(|| {
+        anyhow::anyhow!("No trusty.json found in {} or its parents", start.display())
+    })?;
+    reporter.status(&format!("🔍 Checking {}...", manifest.display()));
+    let text = fs::read_to_string(&manifest)
+        .with_context(|| format!("Failed to read {}", manifest.display()))?;
+    let root = manifest.parent().unwrap_or_else(|| Path::new("."));
+
+    let diagnostics = trusty_compiler::manifest::check(&text, root);
+    for diag in &diagnostics {
+        reporter.located_diagnostic(diag, &manifest, &text);
+    }
+    let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+    if diagnostics.is_empty() {
+        reporter.status("✅ No problems found");
+    } else {
+        reporter.status(&format!(
+            "🔎 {} warning(s), {} error(s)",
+            diagnostics.len() - errors,
+            errors
+        ));
+    }
+    Ok(if errors > 0 { 1 } else { 0 })
+}
+
 // ─── trusty format ───────────────────────────────────────────────────────────
 
-fn format_file(input: &PathBuf, check: bool) -> Result<()> {
+fn format_file(input: &Path, check: bool, reporter: &Reporter) -> Result<()> {
     let source =
         fs::read_to_string(input).with_context(|| format!("Failed to read {}", input.display()))?;
     let formatted = format_trust_source(&source);
 
     if check {
         if source == formatted {
-            println!("✅ Already formatted: {}", input.display());
+            reporter.status(&format!("✅ Already formatted: {}", input.display()));
             return Ok(());
         }
         bail!("❌ Needs formatting: {}", input.display());
@@ -377,9 +1463,9 @@ fn format_file(input: &PathBuf, check: bool) -> Result<()> {
     if source != formatted {
         fs::write(input, formatted)
             .with_context(|| format!("Failed to write {}", input.display()))?;
-        println!("✅ Formatted {}", input.display());
+        reporter.status(&format!("✅ Formatted {}", input.display()));
     } else {
-        println!("✅ Already formatted: {}", input.display());
+        reporter.status(&format!("✅ Already formatted: {}", input.display()));
     }
 
     Ok(())
@@ -424,6 +1510,12 @@ fn format_trust_source(source: &str) -> String {
             None
         };
 
+        if (in_single || in_double) && matches!(c, '\n' | '\r') {
+            // An unterminated string ends with its line, as in the compiler's tokenizer.
+            in_single = false;
+            in_double = false;
+        }
+
         if in_single {
             prev_input_was_newline = false;
             ensure_line(&mut out, &mut at_line_start, indent);
@@ -466,6 +1558,32 @@ fn format_trust_source(source: &str) -> String {
             continue;
         }
 
+        if c == 'r'
+            && (i == 0 || !(chars[i - 1].is_alphanumeric() || matches!(chars[i - 1], '_' | '$')))
+        {
+            let hashes = chars[i + 1..].iter().take_while(|&&c| c == '#').count();
+            if chars.get(i + 1 + hashes) == Some(&'"') {
+                // Raw string, copied as written up to the closing `"` and as many `#`.
+                prev_input_was_newline = false;
+                ensure_line(&mut out, &mut at_line_start, indent);
+                let body = i + hashes + 2;
+                let end = (body..chars.len())
+                    .find(|&j| {
+                        chars[j] == '"'
+                            && chars[j + 1..]
+                                .iter()
+                                .take(hashes)
+                                .filter(|&&c| c == '#')
+                                .count()
+                                == hashes
+                    })
+                    .map_or(chars.len(), |j| j + 1 + hashes);
+                out.extend(&chars[i..end]);
+                i = end;
+                continue;
+            }
+        }
+
         if c == '/' && next == Some('/') {
             prev_input_was_newline = false;
             ensure_line(&mut out, &mut at_line_start, indent);
@@ -501,6 +1619,9 @@ fn format_trust_source(source: &str) -> String {
             continue;
         }
 
+        if !matches!(c, '\n' | '\r') {
+            prev_input_was_newline = false;
+        }
         match c {
             '\'' => {
                 ensure_line(&mut out, &mut at_line_start, indent);
@@ -575,20 +1696,17 @@ fn format_trust_source(source: &str) -> String {
                 at_line_start = true;
                 prev_input_was_newline = true;
             }
-            _ => {
-                prev_input_was_newline = false;
-                match c {
-                    ' ' | '\t' => {
-                        if !at_line_start && !out.ends_with(' ') && !out.ends_with('\n') {
-                            out.push(' ');
-                        }
-                    }
-                    _ => {
-                        ensure_line(&mut out, &mut at_line_start, indent);
-                        out.push(c);
+            _ => match c {
+                ' ' | '\t' => {
+                    if !at_line_start && !out.ends_with(' ') && !out.ends_with('\n') {
+                        out.push(' ');
                     }
                 }
-            }
+                _ => {
+                    ensure_line(&mut out, &mut at_line_start, indent);
+                    out.push(c);
+                }
+            },
         }
 
         i += 1;
@@ -670,154 +1788,262 @@ fn reflow_named_import_decl(decl: &str, print_width: usize) -> Option<Vec<String
     Some(out)
 }
 
-// ─── local module resolver (TRUST files) ────────────────────────────────────
+#[cfg(test)]
+mod tests {
+    use super::{
+        binary_name, check_project, compiler_options, dependency_version, error_exit_code,
+        fix_source, format_trust_source, lint_files, no_input, output_binary, package_name,
+        project_options, read_lint_config, templates, toolchain, Cli, Commands, MessageFormat,
+        Reporter, Template,
+    };
+    use clap::Parser;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::Path;
 
-fn resolve_and_bundle_modules(entry: &Path) -> Result<String> {
-    let entry = entry
-        .canonicalize()
-        .with_context(|| format!("Failed to resolve {}", entry.display()))?;
+    #[test]
+    fn test_cli_global_output_flags_after_subcommand() {
+        let cli = Cli::try_parse_from([
+            "trusty",
+            "build",
+            "main.trs",
+            "--quiet",
+            "--message-format",
+            "json",
+        ])
+        .unwrap();
+        assert!(cli.quiet);
+        assert_eq!(cli.message_format, MessageFormat::Json);
+        assert!(matches!(cli.command, Some(Commands::Build { .. })));
+        assert!(Cli::try_parse_from(["trusty", "-q", "-v", "check", "main.trs"]).is_err());
+    }
 
-    let mut seen = HashSet::new();
-    let mut stack = Vec::new();
-    resolve_module_file(&entry, &mut seen, &mut stack)
-}
+    #[test]
+    fn test_exit_codes_of_errors() {
+        assert_eq!(
+            error_exit_code(&anyhow::anyhow!("❌ Compilation failed")),
+            1
+        );
+        assert_eq!(error_exit_code(&no_input(Path::new("/tmp"))), 2);
+        // Also under context added on the way up.
+        let missing =
+            toolchain::output(&mut std::process::Command::new("trusty-no-such-tool")).unwrap_err();
+        assert_eq!(error_exit_code(&missing.context("building main.trs")), 3);
+    }
 
-fn resolve_module_file(
-    file: &Path,
-    seen: &mut HashSet<PathBuf>,
-    stack: &mut Vec<PathBuf>,
-) -> Result<String> {
-    let canonical = file
-        .canonicalize()
-        .with_context(|| format!("Failed to resolve {}", file.display()))?;
+    #[test]
+    fn test_binary_names_per_target() {
+        assert_eq!(
+            binary_name("main", Some("x86_64-pc-windows-msvc")),
+            "main.exe"
+        );
+        assert_eq!(
+            binary_name("main", Some("x86_64-pc-windows-gnu")),
+            "main.exe"
+        );
+        assert_eq!(
+            binary_name("main", Some("wasm32-unknown-unknown")),
+            "main.wasm"
+        );
+        assert_eq!(binary_name("main", Some("aarch64-apple-darwin")), "main");
+        assert_eq!(
+            binary_name("main", None),
+            format!("main{}", std::env::consts::EXE_SUFFIX)
+        );
 
-    if seen.contains(&canonical) {
-        return Ok(String::new());
+        let windows = Some("x86_64-pc-windows-msvc");
+        assert_eq!(
+            output_binary(Path::new("out/app"), windows),
+            Path::new("out/app.exe")
+        );
+        assert_eq!(
+            output_binary(Path::new("out/app.exe"), windows),
+            Path::new("out/app.exe")
+        );
+        assert_eq!(
+            output_binary(Path::new("app"), Some("x86_64-unknown-linux-gnu")),
+            Path::new("app")
+        );
     }
 
-    if stack.contains(&canonical) {
-        let chain = stack
-            .iter()
-            .map(|p| p.display().to_string())
-            .collect::<Vec<_>>()
-            .join(" -> ");
-        bail!(
-            "Circular local import detected: {} -> {}",
-            chain,
-            canonical.display()
-        );
+    #[test]
+    fn test_cargo_package_names() {
+        assert_eq!(package_name("main"), "main");
+        assert_eq!(package_name("my-api"), "my-api");
+        assert_eq!(package_name("hello world.v2"), "hello_world_v2");
+        assert_eq!(package_name("2048"), "trusty_2048");
+        assert_eq!(package_name("test"), "trusty_test");
+        assert_eq!(package_name("Self"), "trusty_Self");
     }
-    stack.push(canonical.clone());
 
-    let source = fs::read_to_string(&canonical)
-        .with_context(|| format!("Failed to read {}", canonical.display()))?;
+    #[test]
+    fn test_cli_doctor_command() {
+        let cli = Cli::try_parse_from(["trusty", "doctor", "--message-format", "json"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Doctor)));
+        assert!(Cli::try_parse_from(["trusty", "doctor", "main.trs"]).is_err());
+    }
 
-    let mut dep_code = String::new();
-    let mut body_lines = Vec::new();
-    let base_dir = canonical.parent().unwrap_or_else(|| Path::new("."));
+    #[test]
+    fn test_cli_run_passes_trailing_args() {
+        let cli = Cli::try_parse_from(["trusty", "run", "main.trs", "--", "--port", "8080", "-x"])
+            .unwrap();
+        match cli.command {
+            Some(Commands::Run { args, .. }) => assert_eq!(args, vec!["--port", "8080", "-x"]),
+            _ => panic!("expected run command"),
+        }
+    }
 
-    for line in source.lines() {
-        if let Some(import_path) = parse_local_import_path(line) {
-            let dep_file = resolve_local_import_target(base_dir, &import_path)?;
-            let child = resolve_module_file(&dep_file, seen, stack)?;
-            if !child.trim().is_empty() {
-                dep_code.push_str(&child);
-                if !dep_code.ends_with('\n') {
-                    dep_code.push('\n');
-                }
+    #[test]
+    fn test_cli_bare_run_uses_manifest_entry() {
+        let cli = Cli::try_parse_from(["trusty", "run", "--", "--port", "8080"]).unwrap();
+        match cli.command {
+            Some(Commands::Run { input, args, .. }) => {
+                assert!(input.is_none());
+                assert_eq!(args, vec!["--port", "8080"]);
             }
-            continue;
+            _ => panic!("expected run command"),
         }
-        body_lines.push(line.to_string());
     }
 
-    let body = body_lines.join("\n");
-    let rewritten = rewrite_export_declarations(&body)
-        .with_context(|| format!("In module {}", canonical.display()))?;
-
-    stack.pop();
-    seen.insert(canonical.clone());
-
-    let mut out = String::new();
-    out.push_str(&dep_code);
-    out.push_str(&format!("// --- module: {} ---\n", canonical.display()));
-    out.push_str(&rewritten);
-    out.push('\n');
-    Ok(out)
-}
-
-fn parse_local_import_path(line: &str) -> Option<String> {
-    let trimmed = line.trim();
-    if !trimmed.starts_with("import ") {
-        return None;
-    }
-    let from_idx = trimmed.find(" from ")?;
-    let after_from = trimmed[from_idx + " from ".len()..].trim();
-    let quote = after_from.chars().next()?;
-    if quote != '"' && quote != '\'' {
-        return None;
-    }
-    let rest = &after_from[1..];
-    let end = rest.find(quote)?;
-    let path = &rest[..end];
-    if path.starts_with("./") || path.starts_with("../") {
-        Some(path.to_string())
-    } else {
-        None
+    #[test]
+    fn test_cli_run_accepts_track_locations() {
+        let cli = Cli::try_parse_from(["trusty", "run", "main.trs", "--track-locations"]).unwrap();
+        match cli.command {
+            Some(Commands::Run {
+                track_locations, ..
+            }) => assert!(track_locations),
+            _ => panic!("expected run command"),
+        }
     }
-}
 
-fn resolve_local_import_target(base_dir: &Path, import_path: &str) -> Result<PathBuf> {
-    let candidate = base_dir.join(import_path);
-    let mut tries = Vec::new();
+    #[test]
+    fn test_cli_accepts_allow_unknown() {
+        let cli = Cli::try_parse_from(["trusty", "check", "main.trs", "--allow-unknown"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Check {
+                allow_unknown: true,
+                ..
+            })
+        ));
+        let cli = Cli::try_parse_from(["trusty", "build", "main.trs"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Build {
+                allow_unknown: false,
+                ..
+            })
+        ));
+    }
 
-    tries.push(candidate.clone());
-    if candidate.extension().is_none() {
-        tries.push(candidate.with_extension("trs"));
-        tries.push(candidate.join("index.trs"));
+    #[test]
+    fn test_lint_config_reads_manifest_block() {
+        let dir = std::env::temp_dir().join(format!("trusty-lint-config-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let manifest = dir.join("trusty.json");
+        fs::write(
+            &manifest,
+            r#"{ "lint": { "magic-number": "warn", "maxFunctionLines": 80 } }"#,
+        )
+        .unwrap();
+        let config = read_lint_config(&manifest).unwrap();
+        assert_eq!(
+            config.level("magic-number"),
+            trusty_compiler::lint::RuleLevel::Warn
+        );
+        assert_eq!(config.max_function_lines, 80);
+
+        fs::write(&manifest, r#"{ "lint": { "magic-numbers": "warn" } }"#).unwrap();
+        assert!(read_lint_config(&manifest)
+            .unwrap_err()
+            .to_string()
+            .contains("Unknown lint rule `magic-numbers`"));
+        fs::remove_dir_all(&dir).ok();
     }
 
-    for t in tries {
-        if t.exists() {
-            return Ok(t);
+    #[test]
+    fn test_cli_check_manifest_and_template_manifests_are_valid() {
+        let cli = Cli::try_parse_from(["trusty", "check", "--manifest"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Check {
+                input: None,
+                manifest: true,
+                ..
+            })
+        ));
+
+        let dir =
+            std::env::temp_dir().join(format!("trusty-template-manifest-{}", std::process::id()));
+        for template in [
+            Template::Cli,
+            Template::HttpServer,
+            Template::Lib,
+            Template::Wasm,
+        ] {
+            for (relative, contents) in templates::files(template) {
+                let path = dir.join(relative);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, contents).unwrap();
+            }
+            let manifest =
+                serde_json::to_string_pretty(&templates::manifest(template, "demo")).unwrap();
+            assert_eq!(
+                trusty_compiler::manifest::check(&manifest, &dir),
+                Vec::new(),
+                "{:?}",
+                template
+            );
+            fs::remove_dir_all(&dir).ok();
         }
     }
 
-    bail!(
-        "Cannot resolve local import '{}' from {}",
-        import_path,
-        base_dir.display()
-    )
-}
+    #[test]
+    fn test_cli_eval_takes_snippet() {
+        let cli = Cli::try_parse_from(["trusty", "eval", "console.write(1 + 2);"]).unwrap();
+        match cli.command {
+            Some(Commands::Eval { code }) => assert_eq!(code, "console.write(1 + 2);"),
+            _ => panic!("expected eval command"),
+        }
+    }
 
-fn rewrite_export_declarations(source: &str) -> Result<String> {
-    let mut out = Vec::new();
-    for line in source.lines() {
-        let trimmed = line.trim_start();
-        if let Some(rest) = trimmed.strip_prefix("export ") {
-            let allowed = rest.starts_with("function ")
-                || rest.starts_with("const ")
-                || rest.starts_with("struct ")
-                || rest.starts_with("enum ")
-                || rest.starts_with("implements ");
-            if !allowed {
-                bail!(
-                    "Unsupported export syntax: '{}'. Supported: export function/const/struct/enum/implements",
-                    trimmed
-                );
-            }
-            let indent = &line[..line.len() - trimmed.len()];
-            out.push(format!("{}{}", indent, rest));
-        } else {
-            out.push(line.to_string());
+    #[test]
+    fn test_templates_have_entry_tests_and_compiler_options() {
+        for template in [
+            Template::Cli,
+            Template::HttpServer,
+            Template::Lib,
+            Template::Wasm,
+        ] {
+            let files = templates::files(template);
+            assert!(files.iter().any(|(path, _)| *path == "src/main.trs"));
+            assert!(files.iter().any(|(path, _)| path.starts_with("tests/")));
+            let manifest = templates::manifest(template, "demo");
+            assert_eq!(manifest["main"], "src/main.trs");
+            assert_eq!(manifest["compilerOptions"]["outDir"], "build");
         }
+        let wasm = templates::manifest(Template::Wasm, "demo");
+        assert_eq!(wasm["compilerOptions"]["target"], "wasm32-unknown-unknown");
+        // The written-out options are the defaults.
+        let options = compiler_options(&templates::manifest(Template::Cli, "demo"));
+        let defaults = trusty_compiler::CompileOptions::default();
+        assert_eq!(options.compile.auto_clone, defaults.auto_clone);
+        assert_eq!(options.compile.overflow, defaults.overflow);
+        assert_eq!(options.compile.derives, defaults.derives);
+        assert_eq!(options.compile.enum_utilities, defaults.enum_utilities);
+        assert_eq!(options.compile.track_locations, defaults.track_locations);
+        assert_eq!(options.compile.runtime_crate, defaults.runtime_crate);
     }
-    Ok(out.join("\n"))
-}
 
-#[cfg(test)]
-mod tests {
-    use super::format_trust_source;
+    #[test]
+    fn test_cli_new_accepts_template() {
+        let cli =
+            Cli::try_parse_from(["trusty", "new", "api", "--template", "http-server"]).unwrap();
+        match cli.command {
+            Some(Commands::New { template, .. }) => assert_eq!(template, Template::HttpServer),
+            _ => panic!("expected new command"),
+        }
+    }
 
     #[test]
     fn test_format_trust_source_basic() {
@@ -853,4 +2079,204 @@ mod tests {
         assert!(got.contains("    Instant,\n"));
         assert!(got.contains("} from \"trusty:time\";"));
     }
+
+    #[test]
+    fn test_format_trust_source_is_idempotent_and_reparses() {
+        let programs = [
+            include_str!("../../../examples/mon-projet/src/fibo.trs"),
+            include_str!("../../../examples/mon-projet/src/json_demo.trs"),
+            include_str!("../../../examples/mon-projet/src/http_server.trs"),
+        ];
+        const FRAGMENTS: &[&str] = &[
+            "{",
+            "}",
+            "(",
+            ")",
+            "\"",
+            "'",
+            "`",
+            "${",
+            "/*",
+            "*/",
+            "//",
+            "\\",
+            "\n",
+            "\r",
+            " ",
+            "\t",
+            ";",
+            ",",
+            "é",
+            "🦀",
+            "x",
+            "val",
+            "import {",
+            "} from \"a\";",
+            "function main() {",
+            "\n\n\n",
+            "/* c */",
+            "// c\n",
+        ];
+        // xorshift64: deterministic, so a failure always reproduces.
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = |below: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % below as u64) as usize
+        };
+
+        // Unbalanced braces, unterminated strings, templates and comments never panic.
+        for _ in 0..2_000 {
+            let source: String = (0..next(40))
+                .map(|_| FRAGMENTS[next(FRAGMENTS.len())])
+                .collect();
+            format_trust_source(&source);
+        }
+
+        // Programs that compile, including ones with fragments spliced in, format to code that still
+        // compiles and that a second pass leaves alone.
+        let mut sources: Vec<String> = programs.iter().map(|program| program.to_string()).collect();
+        for n in 0..600 {
+            let mut source = programs[n % programs.len()].to_string();
+            let mut at = next(source.len() + 1);
+            while !source.is_char_boundary(at) {
+                at -= 1;
+            }
+            source.insert_str(at, FRAGMENTS[next(FRAGMENTS.len())]);
+            sources.push(source);
+        }
+        for source in sources {
+            if trusty_compiler::Compiler::new()
+                .compile(&source)
+                .has_errors()
+            {
+                continue;
+            }
+            let formatted = format_trust_source(&source);
+            assert_eq!(
+                format_trust_source(&formatted),
+                formatted,
+                "input {:?}",
+                source
+            );
+            let output = trusty_compiler::Compiler::new().compile(&formatted);
+            assert!(
+                !output.has_errors(),
+                "{:?}\n{}",
+                output.diagnostics,
+                formatted
+            );
+        }
+    }
+
+    #[test]
+    fn test_fix_source_applies_lint_and_deprecation_fixes() {
+        let source = "struct my_point { x: number }\n\nfunction main() {\n    val unused = 1;\n    val p: my_point = my_point({ x: 2 });\n    console.write(p.x);\n}\n";
+        let (fixed, applied) = fix_source(source, &trusty_compiler::LintConfig::default());
+        assert_eq!(applied, 3);
+        assert!(fixed.contains("struct MyPoint { x: int32 }"));
+        assert!(fixed.contains("val _unused = 1;"));
+        assert!(fixed.contains("val p: MyPoint = MyPoint({ x: 2 });"));
+        assert!(
+            trusty_compiler::lint::lint(&fixed, &trusty_compiler::LintConfig::default())
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_dependency_version_prefers_manifest_then_stdlib_pin() {
+        let manifest = HashMap::from([("rand".to_string(), "0.9".to_string())]);
+        let stdlib = vec![
+            ("rand".to_string(), "0.8".to_string()),
+            ("ureq".to_string(), "3".to_string()),
+        ];
+        assert_eq!(dependency_version("rand", &manifest, &stdlib), "0.9");
+        assert_eq!(dependency_version("ureq", &manifest, &stdlib), "3");
+        assert_eq!(dependency_version("regex", &manifest, &stdlib), "*");
+    }
+
+    #[test]
+    fn test_cli_project_options_read_once_for_every_command() {
+        let dir =
+            std::env::temp_dir().join(format!("trusty-project-options-{}", std::process::id()));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(
+            dir.join("trusty.json"),
+            r#"{ "name": "demo", "dependencies": { "rand": "0.8" },
+  "compilerOptions": { "outDir": "out", "runtimeCrate": true, "trackLocations": true, "locale": "fr" } }"#,
+        )
+        .unwrap();
+        fs::write(dir.join("src/main.trs"), "function main() {}\n").unwrap();
+
+        // `trusty check` starts from the project directory, `build` and `run` from the file.
+        for path in [dir.clone(), dir.join("src/main.trs")] {
+            let options = project_options(&path);
+            assert_eq!(options.out_dir, "out");
+            assert!(options.compile.runtime_crate && options.compile.track_locations);
+            assert_eq!(options.compile.locale, trusty_compiler::Locale::Fr);
+            assert_eq!(
+                options.compile.dependencies,
+                [("rand".to_string(), "0.8".to_string())]
+            );
+        }
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_cli_check_project_reports_every_file() {
+        let cli = Cli::try_parse_from(["trusty", "check", "demo", "--timings"]).unwrap();
+        assert!(cli.timings);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Check {
+                input: Some(_),
+                manifest: false,
+                ..
+            })
+        ));
+
+        let dir = std::env::temp_dir().join(format!("trusty-check-project-{}", std::process::id()));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(
+            dir.join("src/a.trs"),
+            "function a() {\n    while (true) {}\n}\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("src/b.trs"),
+            "function b() {\n    while (true) {}\n}\n",
+        )
+        .unwrap();
+        let reporter = Reporter::new(true, false, MessageFormat::Human);
+        assert_eq!(check_project(Some(&dir), false, &reporter).unwrap(), 1);
+
+        fs::write(
+            dir.join("src/a.trs"),
+            "function a(): int32 {\n    return 1;\n}\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("src/b.trs"),
+            "function b(): int32 {\n    return 2;\n}\n",
+        )
+        .unwrap();
+        assert_eq!(check_project(Some(&dir), false, &reporter).unwrap(), 0);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_cli_lint_fails_on_files_that_do_not_parse() {
+        let dir = std::env::temp_dir().join(format!("trusty-lint-parse-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("broken.trs");
+        fs::write(&file, "function main() {\n    val x = ;\n}\n").unwrap();
+        let reporter = Reporter::new(true, false, MessageFormat::Human);
+        assert_eq!(lint_files(Some(&file), false, &reporter).unwrap(), 1);
+
+        fs::write(&file, "function main() {\n    console.write(1);\n}\n").unwrap();
+        assert_eq!(lint_files(Some(&file), false, &reporter).unwrap(), 0);
+        fs::remove_dir_all(&dir).ok();
+    }
 }
@@ -0,0 +1,366 @@
+use clap::ValueEnum;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use trusty_compiler::Diagnostic;
+
+/// How the CLI renders its own messages (`--message-format`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum MessageFormat {
+    /// Emoji-prefixed status lines meant for humans.
+    Human,
+    /// One JSON object per line on stdout, meant for editors and CI. Programs run by
+    /// `trusty run` and `trusty eval` then write to stderr.
+    Json,
+}
+
+/// When diagnostics are colored (`--color`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// When stderr is a terminal and `NO_COLOR` is not set.
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                    && std::io::IsTerminal::is_terminal(&std::io::stderr())
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+/// Central sink for everything the CLI prints about itself.
+///
+/// Program output (the child process in `trusty run`) never goes through here.
+pub struct Reporter {
+    verbosity: Verbosity,
+    format: MessageFormat,
+    timings: bool,
+    color: bool,
+}
+
+impl Reporter {
+    pub fn new(quiet: bool, verbose: bool, format: MessageFormat) -> Self {
+        let verbosity = if quiet {
+            Verbosity::Quiet
+        } else if verbose {
+            Verbosity::Verbose
+        } else {
+            Verbosity::Normal
+        };
+        Self {
+            verbosity,
+            format,
+            timings: false,
+            color: false,
+        }
+    }
+
+    /// Report the cost of each build phase (`--timings`).
+    pub fn timings(mut self, timings: bool) -> Self {
+        self.timings = timings;
+        self
+    }
+
+    /// Color errors and diagnostics in human mode (`--color`).
+    pub fn color(mut self, choice: ColorChoice) -> Self {
+        self.color = choice.enabled();
+        self
+    }
+
+    /// Regular progress line (`🔨 Building ...`). Hidden with `--quiet` and in JSON mode.
+    pub fn status(&self, message: &str) {
+        if self.format == MessageFormat::Human && self.verbosity >= Verbosity::Normal {
+            println!("{}", message);
+        }
+    }
+
+    /// Where a program run by the CLI writes its stdout: the terminal, or stderr in JSON mode so
+    /// that stdout only holds the JSON lines.
+    pub fn program_stdout(&self) -> Stdio {
+        match self.format {
+            MessageFormat::Human => Stdio::inherit(),
+            MessageFormat::Json => std::io::stderr().into(),
+        }
+    }
+
+    /// Extra detail only shown with `--verbose`.
+    pub fn detail(&self, message: &str) {
+        if self.format == MessageFormat::Human && self.verbosity >= Verbosity::Verbose {
+            println!("   {}", message);
+        }
+    }
+
+    /// Reports a failure. Always shown, even with `--quiet`.
+    pub fn error(&self, err: &anyhow::Error) {
+        match self.format {
+            MessageFormat::Human => eprintln!("{} {:?}", paint(self.color, RED, "Error:"), err),
+            MessageFormat::Json => {
+                let causes: Vec<String> = err.chain().skip(1).map(|c| c.to_string()).collect();
+                self.emit(json!({
+                    "reason": "diagnostic",
+                    "level": "error",
+                    "message": err.to_string(),
+                    "causes": causes,
+                }));
+            }
+        }
+    }
+
+    /// Diagnostic with its position in `source`, the text of `file` or the bundle of the program
+    /// it is the entry of, whose modules the position is then reported in.
+    /// Warnings are hidden with `--quiet` in human mode; errors are always shown.
+    pub fn located_diagnostic(&self, diag: &Diagnostic, file: &Path, source: &str) {
+        let located = diag
+            .span
+            .map(|span| trusty_compiler::modules::locate(source, span.start));
+        let file = match located {
+            Some((Some(module), _, _)) => relative_to_cwd(Path::new(module)),
+            _ => file.display().to_string(),
+        };
+        match self.format {
+            MessageFormat::Human => {
+                if diag.is_error() || self.verbosity >= Verbosity::Normal {
+                    eprintln!("{}", render(diag, &file, source, self.color));
+                }
+            }
+            MessageFormat::Json => {
+                let (_, line, column) = located.unwrap_or((None, 1, 1));
+                self.emit(json!({
+                    "reason": "diagnostic",
+                    "level": diag.severity.as_str(),
+                    "code": diag.code,
+                    "message": diag.message,
+                    "file": file,
+                    "line": line,
+                    "column": column,
+                }))
+            }
+        }
+    }
+
+    /// Outcome of one `trusty doctor` check. Failures are shown even with `--quiet`.
+    pub fn check(&self, name: &str, ok: bool, detail: &str) {
+        match self.format {
+            MessageFormat::Human => {
+                if !ok || self.verbosity >= Verbosity::Normal {
+                    let icon = if ok { "✅" } else { "❌" };
+                    println!("{} {:<14} {}", icon, name, detail);
+                }
+            }
+            MessageFormat::Json => self.emit(json!({
+                "reason": "check",
+                "name": name,
+                "ok": ok,
+                "message": detail,
+            })),
+        }
+    }
+
+    /// A file produced by the build (`rust-source` or `binary`).
+    pub fn artifact(&self, kind: &str, path: &Path) {
+        if self.format == MessageFormat::Json {
+            self.emit(json!({
+                "reason": "artifact",
+                "kind": kind,
+                "path": path.display().to_string(),
+            }));
+        }
+    }
+
+    /// Time spent in one build phase, shown with `--timings`.
+    pub fn timing(&self, phase: &str, elapsed: Duration) {
+        if !self.timings {
+            return;
+        }
+        match self.format {
+            MessageFormat::Human => println!(
+                "   ⏱  {:<10} {:>8.2} ms",
+                phase,
+                elapsed.as_secs_f64() * 1000.0
+            ),
+            MessageFormat::Json => self.emit(json!({
+                "reason": "timing",
+                "phase": phase,
+                "duration_ms": elapsed.as_secs_f64() * 1000.0,
+            })),
+        }
+    }
+
+    /// Final line of a command, with its wall-clock duration.
+    pub fn finished(&self, command: &str, exit_code: i32, elapsed: Duration) {
+        match self.format {
+            MessageFormat::Human => {
+                self.detail(&format!(
+                    "⏱  {} finished in {} ms",
+                    command,
+                    elapsed.as_millis()
+                ));
+            }
+            MessageFormat::Json => self.emit(json!({
+                "reason": "finished",
+                "command": command,
+                "success": exit_code == 0,
+                "exit_code": exit_code,
+                "duration_ms": elapsed.as_millis() as u64,
+            })),
+        }
+    }
+
+    fn emit(&self, value: Value) {
+        println!("{}", value);
+    }
+}
+
+const RED: &str = "1;31";
+const YELLOW: &str = "1;33";
+const BLUE: &str = "1;34";
+const BOLD: &str = "1";
+
+/// `text` in the SGR `style` when `color` is set.
+fn paint(color: bool, style: &str, text: &str) -> String {
+    if color {
+        format!("\x1b[{}m{}\x1b[0m", style, text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// `path` relative to the working directory when it is inside it, as the user typed it.
+fn relative_to_cwd(path: &Path) -> String {
+    let cwd = std::env::current_dir()
+        .ok()
+        .and_then(|cwd| cwd.canonicalize().ok());
+    match cwd.as_deref().and_then(|cwd| path.strip_prefix(cwd).ok()) {
+        Some(relative) => relative.display().to_string(),
+        None => path.display().to_string(),
+    }
+}
+
+/// `diag` the way rustc shows its errors: the message, `file:line:column`, then the line of
+/// `source` it points at with its span underlined, and the fix it suggests.
+///
+/// ```text
+/// error[parse-error]: Parse error: Expression expected
+///  --> main.trs:3:16
+///   |
+/// 3 |     val y = x +;
+///   |                ^
+/// ```
+pub fn render(diag: &Diagnostic, file: &str, source: &str, color: bool) -> String {
+    let level = if diag.is_error() { RED } else { YELLOW };
+    let mut out = format!(
+        "{}{}",
+        paint(
+            color,
+            level,
+            &format!("{}[{}]", diag.severity.as_str(), diag.code)
+        ),
+        paint(color, BOLD, &format!(": {}", diag.message))
+    );
+    let Some(span) = diag.span else {
+        out.push_str(&format!("\n {} {}", paint(color, BLUE, "-->"), file));
+        return out;
+    };
+    let (_, line, column) = trusty_compiler::modules::locate(source, span.start);
+    let start = span.start.min(source.len());
+    let line_start = source[..start].rfind('\n').map_or(0, |newline| newline + 1);
+    let line_end = source[start..]
+        .find('\n')
+        .map_or(source.len(), |newline| start + newline);
+    let text = source[line_start..line_end].trim_end_matches('\r');
+    // Tabs are kept so that the carets line up under them; a span running past the line is
+    // underlined to its end.
+    let indent: String = source[line_start..start]
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let width = source[start..span.end.clamp(start, line_end)]
+        .chars()
+        .count()
+        .max(1);
+
+    let number = line.to_string();
+    let gutter = " ".repeat(number.len());
+    let bar = paint(color, BLUE, "|");
+    out.push_str(&format!(
+        "\n{}{} {}:{}:{}",
+        gutter,
+        paint(color, BLUE, "-->"),
+        file,
+        line,
+        column
+    ));
+    out.push_str(&format!("\n{} {}", gutter, bar));
+    out.push_str(&format!(
+        "\n{} {} {}",
+        paint(color, BLUE, &number),
+        bar,
+        text
+    ));
+    out.push_str(&format!(
+        "\n{} {} {}{}",
+        gutter,
+        bar,
+        indent,
+        paint(color, level, &"^".repeat(width))
+    ));
+    if let Some(fix) = &diag.fix {
+        out.push_str(&format!(
+            "\n{} {} help: {}",
+            gutter,
+            paint(color, BLUE, "="),
+            fix.title
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+    use trusty_compiler::{Diagnostic, Span};
+
+    #[test]
+    fn test_render_underlines_the_span() {
+        let source = "function main() {\n    val y = x +;\n}\n";
+        let start = source.find(';').unwrap();
+        let diag = Diagnostic::error("parse-error", "Parse error: Expression expected")
+            .with_span(Span::new(start, start + 1));
+        assert_eq!(
+            render(&diag, "main.trs", source, false),
+            "error[parse-error]: Parse error: Expression expected\n --> main.trs:2:16\n  |\n2 |     val y = x +;\n  |                ^"
+        );
+
+        // In a bundle, the line is the module's.
+        let bundle = "// --- module: /src/lib.trs ---\nexport val a = 1;\n// --- module: /src/main.trs ---\n\nval number: number = 3;\n";
+        let start = bundle.rfind("number").unwrap();
+        let diag = Diagnostic::warning("deprecated-number-alias", "Deprecated type alias `number`")
+            .with_span(Span::new(start, start + 6));
+        let rendered = render(&diag, "main.trs", bundle, false);
+        assert!(rendered.contains(" --> main.trs:2:13\n"), "{}", rendered);
+        assert!(
+            rendered.ends_with("2 | val number: number = 3;\n  |             ^^^^^^"),
+            "{}",
+            rendered
+        );
+
+        assert!(render(&diag, "main.trs", bundle, true)
+            .contains("\x1b[1;33mwarning[deprecated-number-alias]\x1b[0m"));
+    }
+}
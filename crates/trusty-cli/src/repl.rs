@@ -0,0 +1,287 @@
+use anyhow::Result;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use crate::output::{MessageFormat, Reporter};
+
+const HELP: &str = "Commands: :help, :show (print the session program), :reset, :quit";
+
+/// Keywords that start a top-level declaration rather than a statement inside `main`.
+const ITEM_KEYWORDS: &[&str] = &[
+    "function ",
+    "async function ",
+    "export ",
+    "import ",
+    "struct ",
+    "interface ",
+    "enum ",
+    "type ",
+    "implements ",
+    "class ",
+];
+
+/// Accumulated REPL state: every accepted declaration and statement so far.
+///
+/// Each input recompiles the whole session and reruns it; only the output produced
+/// past what was already shown is printed, so earlier side effects are not repeated on screen.
+#[derive(Default)]
+pub struct Session {
+    items: Vec<String>,
+    statements: Vec<String>,
+    shown_output: usize,
+}
+
+/// What a single REPL input contributes to the session.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Entry {
+    /// Top-level declaration (function, struct, import, ...).
+    Item(String),
+    /// Statement appended to `main` (vals, calls, loops, ...).
+    Statement(String),
+    /// Bare expression: printed once, not kept in the session.
+    Expression(String),
+}
+
+impl Session {
+    /// Program made of the session plus `entry`, in TRUST source form.
+    pub fn program_with(&self, entry: &Entry) -> String {
+        let mut items = self.items.clone();
+        let mut statements = self.statements.clone();
+        match entry {
+            Entry::Item(item) => items.push(item.clone()),
+            Entry::Statement(stmt) => statements.push(stmt.clone()),
+            Entry::Expression(expr) => statements.push(format!("console.write({});", expr)),
+        }
+        render_program(&items, &statements)
+    }
+
+    fn program(&self) -> String {
+        render_program(&self.items, &self.statements)
+    }
+
+    fn accept(&mut self, entry: Entry) {
+        match entry {
+            Entry::Item(item) => self.items.push(item),
+            Entry::Statement(stmt) => self.statements.push(stmt),
+            Entry::Expression(_) => {}
+        }
+    }
+}
+
+fn render_program(items: &[String], statements: &[String]) -> String {
+    let mut program = String::new();
+    for item in items {
+        program.push_str(item);
+        program.push_str("\n\n");
+    }
+    program.push_str("function main() {\n");
+    for stmt in statements {
+        for line in stmt.lines() {
+            program.push_str("    ");
+            program.push_str(line);
+            program.push('\n');
+        }
+    }
+    program.push_str("}\n");
+    program
+}
+
+/// Classifies one complete input.
+pub fn classify(input: &str) -> Entry {
+    let trimmed = input.trim();
+    if ITEM_KEYWORDS.iter().any(|kw| trimmed.starts_with(kw)) {
+        return Entry::Item(trimmed.to_string());
+    }
+    if trimmed.ends_with(';') || trimmed.ends_with('}') {
+        return Entry::Statement(trimmed.to_string());
+    }
+    Entry::Expression(trimmed.to_string())
+}
+
+/// True while `input` still has unclosed `{`, `(` or `[` (outside string literals).
+pub fn needs_more_input(input: &str) -> bool {
+    let mut depth = 0i32;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for ch in input.chars() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if ch == '\\' {
+                escaped = true;
+            } else if ch == q {
+                quote = None;
+            }
+            continue;
+        }
+        match ch {
+            '"' | '\'' | '`' => quote = Some(ch),
+            '{' | '(' | '[' => depth += 1,
+            '}' | ')' | ']' => depth -= 1,
+            _ => {}
+        }
+    }
+    depth > 0 || quote.is_some()
+}
+
+// ─── Interactive loop ───────────────────────────────────────────────────────────
+
+pub fn run_repl(reporter: &Reporter) -> Result<()> {
+    let workdir = std::env::temp_dir().join(format!("trusty-repl-{}", std::process::id()));
+    fs::create_dir_all(&workdir)?;
+    let source_path = workdir.join("repl.trs");
+
+    reporter.status(&format!(
+        "TRUST {} REPL. {}",
+        env!("CARGO_PKG_VERSION"),
+        HELP
+    ));
+
+    let mut session = Session::default();
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut buffer = String::new();
+
+    loop {
+        print!(
+            "{}",
+            if buffer.is_empty() {
+                "trusty> "
+            } else {
+                "   ...> "
+            }
+        );
+        io::stdout().flush()?;
+
+        let Some(line) = lines.next() else { break };
+        let line = line?;
+
+        if buffer.is_empty() {
+            match line.trim() {
+                "" => continue,
+                ":quit" | ":exit" | ":q" => break,
+                ":help" => {
+                    println!("{}", HELP);
+                    continue;
+                }
+                ":show" => {
+                    print!("{}", session.program());
+                    continue;
+                }
+                ":reset" => {
+                    session = Session::default();
+                    println!("Session cleared.");
+                    continue;
+                }
+                _ => {}
+            }
+        }
+
+        buffer.push_str(&line);
+        buffer.push('\n');
+        if needs_more_input(&buffer) {
+            continue;
+        }
+
+        let entry = classify(&buffer);
+        buffer.clear();
+        let program = session.program_with(&entry);
+
+        match execute(&program, &source_path) {
+            Ok(run) => {
+                let new_output = run.stdout.get(session.shown_output..).unwrap_or("");
+                print!("{}", new_output);
+                eprint!("{}", run.stderr);
+                if run.success {
+                    if !matches!(entry, Entry::Expression(_)) {
+                        session.shown_output = run.stdout.len();
+                    }
+                    session.accept(entry);
+                } else {
+                    eprintln!("(program exited with an error; input discarded)");
+                }
+            }
+            Err(err) => eprintln!("Error: {:#}", err),
+        }
+    }
+
+    let _ = fs::remove_dir_all(&workdir);
+    Ok(())
+}
+
+/// Captured result of one compiled-and-run snippet.
+pub struct Execution {
+    pub stdout: String,
+    pub stderr: String,
+    pub success: bool,
+}
+
+/// Writes `program` to `source_path`, builds it and runs it with captured output.
+pub fn execute(program: &str, source_path: &Path) -> Result<Execution> {
+    fs::write(source_path, program)?;
+    let bin_path: PathBuf = source_path.with_extension(if cfg!(windows) { "exe" } else { "bin" });
+    let quiet = Reporter::new(true, false, MessageFormat::Human);
+    crate::build_file(
+        source_path,
+        Some(&bin_path),
+        true,
+        crate::BuildMode::default(),
+        &crate::project_options(Path::new(".")).compile,
+        None,
+        &quiet,
+    )?;
+
+    let output = crate::program_command(&bin_path).output()?;
+    Ok(Execution {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        success: output.status.success(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{classify, needs_more_input, Entry, Session};
+
+    #[test]
+    fn test_classify_items_statements_and_expressions() {
+        assert!(matches!(
+            classify("function add(a: int32, b: int32): int32 { return a + b; }"),
+            Entry::Item(_)
+        ));
+        assert!(matches!(
+            classify("import { now } from \"trusty:time\";"),
+            Entry::Item(_)
+        ));
+        assert!(matches!(classify("val x = 1;"), Entry::Statement(_)));
+        assert!(matches!(
+            classify("for (var i = 0; i < 3; i = i + 1) { console.write(i); }"),
+            Entry::Statement(_)
+        ));
+        assert_eq!(classify("1 + 2\n"), Entry::Expression("1 + 2".to_string()));
+    }
+
+    #[test]
+    fn test_needs_more_input_tracks_open_blocks() {
+        assert!(needs_more_input("function f() {\n"));
+        assert!(!needs_more_input("function f() {\n}\n"));
+        assert!(!needs_more_input("console.write(\"{\");"));
+    }
+
+    #[test]
+    fn test_session_keeps_items_and_statements_but_not_expressions() {
+        let mut session = Session::default();
+        session.accept(classify(
+            "function double(x: int32): int32 { return x * 2; }",
+        ));
+        session.accept(classify("val n = 21;"));
+        session.accept(classify("double(n)"));
+
+        let program = session.program_with(&classify("double(n)"));
+        assert!(program.starts_with("function double(x: int32): int32"));
+        assert!(program
+            .contains("function main() {\n    val n = 21;\n    console.write(double(n));\n}"));
+        assert_eq!(program.matches("console.write").count(), 1);
+    }
+}
@@ -0,0 +1,189 @@
+use clap::ValueEnum;
+use serde_json::{json, Value};
+
+/// Project scaffolds available to `trusty new --template <name>`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Template {
+    /// Command-line program with a helper module
+    Cli,
+    /// HTTP server using `trusty:http`
+    HttpServer,
+    /// Reusable module with exported functions
+    Lib,
+    /// Program compiled for `wasm32-unknown-unknown`
+    Wasm,
+}
+
+/// `trusty.json` contents for a new project. Every compiler option is written out with its
+/// default so the project shows what it can change; `locale` is left out, as without it the
+/// messages follow the environment's language.
+pub fn manifest(template: Template, name: &str) -> Value {
+    let mut compiler_options = json!({
+        "outDir": "build",
+        "autoClone": "reused",
+        "overflow": "wrap",
+        "derive": [],
+        "enumUtilities": false,
+        "trackLocations": false,
+        "runtimeCrate": false,
+    });
+    if template == Template::Wasm {
+        compiler_options["target"] = json!("wasm32-unknown-unknown");
+    }
+
+    json!({
+        "name": name,
+        "version": "0.1.0",
+        "main": "src/main.trs",
+        "dependencies": {},
+        "compilerOptions": compiler_options,
+        "lint": {
+            "maxFunctionLines": 50,
+        },
+    })
+}
+
+/// Source files for a new project as `(relative path, contents)` pairs.
+pub fn files(template: Template) -> Vec<(&'static str, &'static str)> {
+    match template {
+        Template::Cli => vec![
+            ("src/main.trs", CLI_MAIN),
+            ("src/greet.trs", CLI_GREET),
+            ("tests/greet_test.trs", CLI_TEST),
+        ],
+        Template::HttpServer => vec![
+            ("src/main.trs", HTTP_MAIN),
+            ("tests/routes_test.trs", HTTP_TEST),
+        ],
+        Template::Lib => vec![
+            ("src/lib.trs", LIB_LIB),
+            ("src/main.trs", LIB_MAIN),
+            ("tests/lib_test.trs", LIB_TEST),
+        ],
+        Template::Wasm => vec![
+            ("src/main.trs", WASM_MAIN),
+            ("tests/main_test.trs", WASM_TEST),
+        ],
+    }
+}
+
+const CLI_MAIN: &str = r#"import { greet } from "./greet";
+
+function main() {
+    val name = console.read("Your name: ");
+    console.write(greet(name));
+}
+"#;
+
+const CLI_GREET: &str = r#"export function greet(name: string): string {
+    if (name == "") {
+        return "Hello from TRUST!";
+    }
+    return `Hello, ${name}!`;
+}
+"#;
+
+const CLI_TEST: &str = r#"import { greet } from "../src/greet";
+
+function main() {
+    if (greet("TRUST") == "Hello, TRUST!") {
+        console.write("ok: greet");
+    } else {
+        console.write("FAIL: greet");
+    }
+}
+"#;
+
+const HTTP_MAIN: &str = r#"import { HttpServer } from "trusty:http";
+import { toJSON } from "trusty:json";
+
+function main() {
+    val app = HttpServer.create();
+
+    app.get("/", function(req, res) {
+        res.status(200).send("Hello from TRUST!");
+    });
+
+    app.get("/users/:id", function(req, res) {
+        val id = req.params.getOr("id", "unknown");
+        res.status(200).json(toJSON({ ok: true, id: id }));
+    });
+
+    console.write("Listening on http://localhost:3000");
+    val started = app.listen(3000);
+    if (started == false) {
+        console.write(app.lastError());
+    }
+}
+"#;
+
+const HTTP_TEST: &str = r#"import { fetch } from "trusty:http";
+
+function main() {
+    val res = fetch("http://localhost:3000/users/42");
+    if (res.ok) {
+        console.write("ok: GET /users/42");
+    } else {
+        console.write("FAIL: start the server first (trusty run src/main.trs)");
+    }
+}
+"#;
+
+const LIB_LIB: &str = r#"export const VERSION: string = "0.1.0";
+
+export function clampPercent(value: int32): int32 {
+    if (value < 0) {
+        return 0;
+    }
+    if (value > 100) {
+        return 100;
+    }
+    return value;
+}
+"#;
+
+const LIB_MAIN: &str = r#"import { VERSION, clampPercent } from "./lib";
+
+function main() {
+    console.write(`lib ${VERSION}: ${clampPercent(120)}`);
+}
+"#;
+
+const LIB_TEST: &str = r#"import { clampPercent } from "../src/lib";
+
+function main() {
+    if (clampPercent(0) == 0 and clampPercent(50) == 50 and clampPercent(500) == 100) {
+        console.write("ok: clampPercent");
+    } else {
+        console.write("FAIL: clampPercent");
+    }
+}
+"#;
+
+const WASM_MAIN: &str = r#"function fibonacci(n: int32): int32 {
+    if (n <= 1) {
+        return n;
+    }
+    return fibonacci(n - 1) + fibonacci(n - 2);
+}
+
+function main() {
+    console.write(fibonacci(10));
+}
+"#;
+
+const WASM_TEST: &str = r#"function fibonacci(n: int32): int32 {
+    if (n <= 1) {
+        return n;
+    }
+    return fibonacci(n - 1) + fibonacci(n - 2);
+}
+
+function main() {
+    if (fibonacci(10) == 55) {
+        console.write("ok: fibonacci");
+    } else {
+        console.write("FAIL: fibonacci");
+    }
+}
+"#;
@@ -0,0 +1,208 @@
+//! The Rust toolchain TRUST programs are compiled with: finding `rustc` and `cargo` before a build
+//! needs them, so a missing one is reported with install guidance rather than a raw io error, and
+//! the checks of `trusty doctor`.
+
+use crate::output::Reporter;
+use anyhow::{anyhow, bail, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// Oldest `rustc` the generated code builds with (`OnceLock`, `IsTerminal`, `Option::is_some_and`).
+const MIN_RUSTC: (u32, u32) = (1, 70);
+
+const INSTALL_HINT: &str =
+    "Install Rust from https://rustup.rs, then open a new terminal; `trusty doctor` checks the setup.";
+
+/// `tool`'s executable on `PATH`, as `Command::new(tool)` would run it.
+fn find_on_path(tool: &str) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .map(|dir| dir.join(format!("{}{}", tool, std::env::consts::EXE_SUFFIX)))
+        .find(|candidate| candidate.is_file())
+}
+
+/// A toolchain executable that is not installed; `trusty` exits with [`crate::EXIT_TOOLCHAIN`].
+#[derive(Debug)]
+pub struct Missing(String);
+
+impl std::fmt::Display for Missing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "`{}` was not found on PATH: TRUST needs the Rust toolchain to compile programs.\n   {}",
+            self.0, INSTALL_HINT
+        )
+    }
+}
+
+impl std::error::Error for Missing {}
+
+fn missing(tool: &str) -> anyhow::Error {
+    Missing(tool.to_string()).into()
+}
+
+/// Fails with install guidance when `tool` (`rustc` or `cargo`) is not on `PATH`.
+pub fn require(tool: &str) -> Result<()> {
+    if find_on_path(tool).is_none() {
+        return Err(missing(tool));
+    }
+    Ok(())
+}
+
+/// Runs `cmd` to completion; a toolchain executable that cannot be found gets the guidance of
+/// [`require`].
+pub fn output(cmd: &mut Command) -> Result<Output> {
+    cmd.output().map_err(|e| {
+        let tool = cmd.get_program().to_string_lossy().to_string();
+        if e.kind() == std::io::ErrorKind::NotFound {
+            missing(&tool)
+        } else {
+            anyhow!("Failed to run `{}`: {}", tool, e)
+        }
+    })
+}
+
+/// First line `tool args` prints, when it runs and succeeds.
+fn query(tool: &str, args: &[&str]) -> Result<String> {
+    require(tool)?;
+    let out = output(Command::new(tool).args(args))?;
+    if !out.status.success() {
+        bail!(
+            "`{} {}` failed: {}",
+            tool,
+            args.join(" "),
+            String::from_utf8_lossy(&out.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
+
+/// `(major, minor)` of a `rustc 1.83.0 (90b35a623 2024-11-26)` version line.
+fn parse_version(line: &str) -> Option<(u32, u32)> {
+    let version = line.split_whitespace().nth(1)?;
+    let mut parts = version.split(['.', '-']);
+    Some((parts.next()?.parse().ok()?, parts.next()?.parse().ok()?))
+}
+
+/// Whether a file can be created in `dir`, creating the directory if needed.
+fn check_writable(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir).map_err(|e| anyhow!("cannot create {}: {}", dir.display(), e))?;
+    let probe = dir.join(".trusty-doctor");
+    fs::write(&probe, b"").map_err(|e| anyhow!("cannot write in {}: {}", dir.display(), e))?;
+    fs::remove_file(&probe).ok();
+    Ok(())
+}
+
+/// Checks what building TRUST programs needs: `rustc` (recent enough) and `cargo`, the host target
+/// and the project's `target`, and the directories builds write to. Returns the exit code: 1 when
+/// a check failed.
+pub fn doctor(target: Option<&str>, build_dirs: &[PathBuf], reporter: &Reporter) -> i32 {
+    let mut failures = 0;
+    let mut check = |name: &str, result: Result<String>| match result {
+        Ok(detail) => reporter.check(name, true, &detail),
+        Err(err) => {
+            failures += 1;
+            reporter.check(name, false, &err.to_string());
+        }
+    };
+
+    let rustc = query("rustc", &["--version"]);
+    let rustc_found = rustc.is_ok();
+    check(
+        "rustc",
+        rustc.and_then(|version| match parse_version(&version) {
+            Some(found) if found < MIN_RUSTC => bail!(
+                "{} is too old: TRUST needs {}.{} or newer (`rustup update`)",
+                version,
+                MIN_RUSTC.0,
+                MIN_RUSTC.1
+            ),
+            _ => Ok(version),
+        }),
+    );
+    check("cargo", query("cargo", &["--version"]));
+
+    if rustc_found {
+        let host = query("rustc", &["-vV"]).map(|info| {
+            info.lines()
+                .find_map(|line| line.strip_prefix("host: "))
+                .unwrap_or("unknown")
+                .to_string()
+        });
+        check("host target", host);
+        if let Some(target) = target {
+            check(&format!("target {}", target), check_target(target));
+        }
+    }
+
+    for dir in build_dirs {
+        check(
+            &format!("{}", dir.display()),
+            check_writable(dir).map(|_| "writable".to_string()),
+        );
+    }
+
+    if failures == 0 {
+        reporter.status("✅ Ready to build TRUST programs");
+        0
+    } else {
+        reporter.status(&format!(
+            "❌ {} problem{} found",
+            failures,
+            if failures == 1 { "" } else { "s" }
+        ));
+        1
+    }
+}
+
+/// Whether `rustc` knows `target` and its standard library is installed.
+fn check_target(target: &str) -> Result<String> {
+    let known = query("rustc", &["--print", "target-list"])?;
+    if !known.lines().any(|line| line.trim() == target) {
+        bail!("rustc does not know this target (see `rustc --print target-list`)");
+    }
+    let sysroot = query("rustc", &["--print", "sysroot"])?;
+    if !Path::new(&sysroot)
+        .join("lib")
+        .join("rustlib")
+        .join(target)
+        .join("lib")
+        .is_dir()
+    {
+        bail!(
+            "its standard library is not installed (`rustup target add {}`)",
+            target
+        );
+    }
+    Ok("installed".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_writable, parse_version};
+
+    #[test]
+    fn test_parse_rustc_version() {
+        assert_eq!(
+            parse_version("rustc 1.83.0 (90b35a623 2024-11-26)"),
+            Some((1, 83))
+        );
+        assert_eq!(
+            parse_version("rustc 1.86.0-nightly (f3d1d47fd 2025-01-20)"),
+            Some((1, 86))
+        );
+        assert_eq!(parse_version("cargo"), None);
+    }
+
+    #[test]
+    fn test_check_writable_creates_the_directory() {
+        let dir = std::env::temp_dir()
+            .join(format!("trusty-doctor-test-{}", std::process::id()))
+            .join("build");
+        assert!(check_writable(&dir).is_ok());
+        assert!(dir.is_dir());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        std::fs::remove_dir_all(dir.parent().unwrap()).ok();
+    }
+}
@@ -0,0 +1,201 @@
+//! Compiler benchmarks: time spent preprocessing, parsing and transpiling representative
+//! programs, from a handful of lines to a 10k-line bundle.
+//!
+//! `cargo bench -p trusty-compiler` prints the median of each phase and compares it with the
+//! previous run (kept in `target/trusty-bench/`); a phase more than 20% slower is reported as a
+//! regression. `cargo bench -p trusty-compiler -- large` only runs corpora whose name contains
+//! `large`.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use trusty_compiler::Compiler;
+
+/// Slowdown over the previous run reported as a regression.
+const THRESHOLD: f64 = 1.2;
+
+/// How long each corpus is compiled for, after one warm-up run.
+const BUDGET: Duration = Duration::from_secs(2);
+
+/// A block of typical code (struct, methods, match, loops, templates); `{n}` keeps the names of
+/// each copy unique.
+const BLOCK: &str = r#"
+struct Item{n} {
+    name: string;
+    count: int32;
+}
+
+implements Item{n} {
+    function label(): string {
+        return `${this.name} x${this.count}`;
+    }
+}
+
+enum Size{n} {
+    Small,
+    Large,
+}
+
+function size{n}(item: Item{n}): Size{n} {
+    if (item.count > 10 && item.name != "") {
+        return Size{n}.Large;
+    }
+    return Size{n}.Small;
+}
+
+function describe{n}(item: Item{n}): string {
+    val kind = match (size{n}(item)) {
+        Size{n}.Small => "small",
+        Size{n}.Large => "large",
+    };
+    return `${item.label()} is ${kind}`;
+}
+
+function total{n}(items: Item{n}[]): int32 {
+    var sum: int32 = 0;
+    for (val item of items) {
+        sum += item.count;
+    }
+    val doubled = items.map((item) => item.count * 2);
+    return sum + doubled.length;
+}
+"#;
+
+fn synthetic(blocks: usize) -> String {
+    let mut source: String = (0..blocks)
+        .map(|n| BLOCK.replace("{n}", &n.to_string()))
+        .collect();
+    source.push_str("\nfunction main() {\n    val item = Item0({ name: \"box\", count: 12 });\n    console.write(describe0(item));\n}\n");
+    source
+}
+
+fn corpora() -> Vec<(&'static str, String)> {
+    vec![
+        (
+            "fibo",
+            include_str!("../../../examples/mon-projet/src/fibo.trs").to_string(),
+        ),
+        (
+            "json_demo",
+            include_str!("../../../examples/mon-projet/src/json_demo.trs").to_string(),
+        ),
+        (
+            "http_server",
+            include_str!("../../../examples/mon-projet/src/http_server.trs").to_string(),
+        ),
+        ("medium", synthetic(20)),
+        ("large", synthetic(10_000 / BLOCK.lines().count())),
+    ]
+}
+
+fn median(mut samples: Vec<Duration>) -> Duration {
+    samples.sort();
+    samples[samples.len() / 2]
+}
+
+/// Median time of each phase of compiling `source`.
+#[allow(clippy::disallowed_methods)] // Benchmarks only run natively.
+fn measure(source: &str) -> Vec<(&'static str, Duration)> {
+    let compiler = Compiler::new();
+    let warm_up = compiler.compile(source);
+    assert!(
+        !warm_up.has_errors(),
+        "corpus does not compile: {:?}",
+        warm_up.diagnostics
+    );
+
+    let mut samples: Vec<(&'static str, Vec<Duration>)> = Vec::new();
+    let started = Instant::now();
+    while started.elapsed() < BUDGET || samples.first().is_none_or(|(_, s)| s.len() < 5) {
+        for (phase, elapsed) in compiler.compile(source).timings {
+            match samples.iter_mut().find(|(p, _)| *p == phase) {
+                Some((_, phase_samples)) => phase_samples.push(elapsed),
+                None => samples.push((phase, vec![elapsed])),
+            }
+        }
+    }
+    samples
+        .into_iter()
+        .map(|(phase, s)| (phase, median(s)))
+        .collect()
+}
+
+fn baseline_path() -> PathBuf {
+    let target = std::env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../target"));
+    target.join("trusty-bench").join("compile.txt")
+}
+
+/// `corpus/phase` → nanoseconds, one `key nanos` pair per line.
+fn read_baseline(path: &PathBuf) -> HashMap<String, u128> {
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let (key, nanos) = line.split_once(' ')?;
+            Some((key.to_string(), nanos.parse().ok()?))
+        })
+        .collect()
+}
+
+fn main() {
+    // `cargo bench` passes `--bench`; any other argument filters corpora by name.
+    let filter: Option<String> = std::env::args().skip(1).find(|arg| !arg.starts_with("--"));
+    let path = baseline_path();
+    let mut baseline = read_baseline(&path);
+    let mut regressions = 0;
+
+    for (name, source) in corpora() {
+        if filter
+            .as_deref()
+            .is_some_and(|filter| !name.contains(filter))
+        {
+            continue;
+        }
+        println!("{} ({} lines)", name, source.lines().count());
+        for (phase, elapsed) in measure(&source) {
+            let key = format!("{}/{}", name, phase);
+            let change = match baseline.get(&key) {
+                Some(&previous) if previous > 0 => {
+                    let ratio = elapsed.as_nanos() as f64 / previous as f64;
+                    if ratio > THRESHOLD {
+                        regressions += 1;
+                    }
+                    format!(
+                        "  {:+.1}%{}",
+                        (ratio - 1.0) * 100.0,
+                        if ratio > THRESHOLD { "  REGRESSED" } else { "" }
+                    )
+                }
+                _ => String::new(),
+            };
+            println!(
+                "    {:<12}{:>12.3} ms{}",
+                phase,
+                elapsed.as_secs_f64() * 1000.0,
+                change
+            );
+            baseline.insert(key, elapsed.as_nanos());
+        }
+    }
+
+    let mut lines: Vec<String> = baseline
+        .iter()
+        .map(|(key, nanos)| format!("{} {}", key, nanos))
+        .collect();
+    lines.sort();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).ok();
+    }
+    fs::write(&path, lines.join("\n")).ok();
+
+    if regressions > 0 {
+        println!(
+            "{} phase(s) more than {:.0}% slower than the previous run",
+            regressions,
+            (THRESHOLD - 1.0) * 100.0
+        );
+    }
+}
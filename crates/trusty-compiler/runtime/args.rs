@@ -0,0 +1,232 @@
+//! Runtime of `trusty:args`, pasted into programs that import it (or linked from `trusty-runtime`):
+//! a declarative command-line parser on `std`, with a `--help` built from the declarations.
+
+#[derive(Clone, Copy, PartialEq)]
+enum ArgKind {
+    Flag,
+    Option,
+    Positional,
+}
+
+#[derive(Clone)]
+struct ArgSpec {
+    name: String,
+    kind: ArgKind,
+    default: String,
+    help: String,
+}
+
+/// The flags, options and positional arguments a program accepts, declared one call at a time:
+/// `ArgParser.create("serve").flag("verbose").option("port", "8080").parse()`.
+#[derive(Clone)]
+pub struct ArgParser {
+    program: String,
+    about: String,
+    specs: Vec<ArgSpec>,
+}
+
+/// The arguments a program was given, once parsed against its `ArgParser`.
+#[derive(Clone, Debug)]
+pub struct ParsedArgs {
+    flags: Vec<String>,
+    values: Vec<(String, String)>,
+    rest: Vec<String>,
+}
+
+/// Why `parseFrom` stopped: `--help` was asked for, or the arguments do not match the declarations.
+enum ArgsOutcome {
+    Help,
+    Invalid(String),
+}
+
+#[allow(dead_code, non_snake_case)]
+impl ArgParser {
+    pub fn create(program: String) -> ArgParser {
+        ArgParser { program, about: String::new(), specs: Vec::new() }
+    }
+
+    /// One line describing the program, shown at the top of `--help`.
+    pub fn about(mut self, text: String) -> ArgParser {
+        self.about = text;
+        self
+    }
+
+    /// `--name`, true when given.
+    pub fn flag(self, name: String) -> ArgParser {
+        self.declare(name, ArgKind::Flag, String::new())
+    }
+
+    /// `--name value` or `--name=value`, `default` when not given.
+    pub fn option(self, name: String, default: String) -> ArgParser {
+        self.declare(name, ArgKind::Option, default)
+    }
+
+    /// A required argument without a dash, read in the order they are declared.
+    pub fn positional(self, name: String) -> ArgParser {
+        self.declare(name, ArgKind::Positional, String::new())
+    }
+
+    /// Describes the argument declared last in `--help`.
+    pub fn help(mut self, text: String) -> ArgParser {
+        if let Some(spec) = self.specs.last_mut() {
+            spec.help = text;
+        }
+        self
+    }
+
+    fn declare(mut self, name: String, kind: ArgKind, default: String) -> ArgParser {
+        self.specs.retain(|spec| spec.name != name);
+        self.specs.push(ArgSpec { name, kind, default, help: String::new() });
+        self
+    }
+
+    /// The text `--help` prints.
+    pub fn usage(&self) -> String {
+        let mut line = format!("Usage: {} [options]", self.program);
+        let mut rows = Vec::new();
+        for spec in &self.specs {
+            let left = match spec.kind {
+                ArgKind::Flag => format!("--{}", spec.name),
+                ArgKind::Option => format!("--{} <value>", spec.name),
+                ArgKind::Positional => {
+                    line.push_str(&format!(" <{}>", spec.name));
+                    format!("<{}>", spec.name)
+                }
+            };
+            let mut help = spec.help.clone();
+            if spec.kind == ArgKind::Option && !spec.default.is_empty() {
+                help = format!("{} (default: {})", help, spec.default).trim_start().to_string();
+            }
+            rows.push((spec.kind == ArgKind::Positional, left, help));
+        }
+        rows.push((false, "-h, --help".to_string(), "Show this help".to_string()));
+        let width = rows.iter().map(|(_, left, _)| left.len()).max().unwrap_or(0) + 2;
+        let section = |positional: bool| {
+            rows.iter()
+                .filter(|(kind, _, _)| *kind == positional)
+                .map(|(_, left, help)| format!("  {:<width$}{}", left, help, width = width).trim_end().to_string())
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        let mut text = String::new();
+        if !self.about.is_empty() {
+            text.push_str(&format!("{}\n\n", self.about));
+        }
+        text.push_str(&line);
+        if rows.iter().any(|(positional, _, _)| *positional) {
+            text.push_str(&format!("\n\nArguments:\n{}", section(true)));
+        }
+        text.push_str(&format!("\n\nOptions:\n{}", section(false)));
+        text
+    }
+
+    /// Parses the program's command-line arguments. `--help` prints the usage and exits; invalid
+    /// arguments are reported with it on stderr, exiting with status 2.
+    pub fn parse(&self) -> ParsedArgs {
+        self.parseFrom(std::env::args().skip(1).collect())
+    }
+
+    /// Like `parse`, on the given arguments rather than the program's.
+    pub fn parseFrom(&self, args: Vec<String>) -> ParsedArgs {
+        match self.try_parse(args) {
+            Ok(parsed) => parsed,
+            Err(ArgsOutcome::Help) => {
+                println!("{}", self.usage());
+                std::process::exit(0)
+            }
+            Err(ArgsOutcome::Invalid(message)) => {
+                eprintln!("error: {}\n\n{}", message, self.usage());
+                std::process::exit(2)
+            }
+        }
+    }
+
+    fn spec(&self, name: &str, kind: ArgKind) -> Option<&ArgSpec> {
+        self.specs.iter().find(|spec| spec.name == name && spec.kind == kind)
+    }
+
+    fn try_parse(&self, args: Vec<String>) -> Result<ParsedArgs, ArgsOutcome> {
+        let mut parsed = ParsedArgs { flags: Vec::new(), values: Vec::new(), rest: Vec::new() };
+        let mut words = Vec::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                words.extend(args.by_ref());
+                break;
+            }
+            if arg == "-h" || arg == "--help" {
+                return Err(ArgsOutcome::Help);
+            }
+            // `-` alone (stdin, by convention) and negative numbers are words too.
+            let Some(body) = arg.strip_prefix("--") else {
+                if arg.len() > 1 && arg.starts_with('-') && arg.parse::<f64>().is_err() {
+                    return Err(ArgsOutcome::Invalid(format!("unknown option `{}`", arg)));
+                }
+                words.push(arg);
+                continue;
+            };
+            let (name, inline) = match body.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (body, None),
+            };
+            if self.spec(name, ArgKind::Flag).is_some() {
+                if inline.is_some() {
+                    return Err(ArgsOutcome::Invalid(format!("`--{}` is a flag and takes no value", name)));
+                }
+                parsed.flags.push(name.to_string());
+            } else if self.spec(name, ArgKind::Option).is_some() {
+                let value = match inline {
+                    Some(value) => value,
+                    None => args.next().ok_or_else(|| ArgsOutcome::Invalid(format!("`--{}` needs a value", name)))?,
+                };
+                parsed.values.retain(|(key, _)| key != name);
+                parsed.values.push((name.to_string(), value));
+            } else {
+                return Err(ArgsOutcome::Invalid(format!("unknown option `--{}`", name)));
+            }
+        }
+        for spec in &self.specs {
+            if spec.kind == ArgKind::Option && !parsed.values.iter().any(|(key, _)| *key == spec.name) {
+                parsed.values.push((spec.name.clone(), spec.default.clone()));
+            }
+        }
+        let mut words = words.into_iter();
+        for spec in self.specs.iter().filter(|spec| spec.kind == ArgKind::Positional) {
+            let word = words.next().ok_or_else(|| ArgsOutcome::Invalid(format!("missing <{}>", spec.name)))?;
+            parsed.values.push((spec.name.clone(), word));
+        }
+        parsed.rest.extend(words);
+        Ok(parsed)
+    }
+}
+
+#[allow(dead_code, non_snake_case)]
+impl ParsedArgs {
+    /// Whether the flag `--name` was given.
+    pub fn flag(&self, name: String) -> bool {
+        self.flags.contains(&name)
+    }
+
+    /// The value of the option or positional argument `name`; `""` for an undeclared name.
+    pub fn get(&self, name: String) -> String {
+        self.values
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.clone())
+            .unwrap_or_default()
+    }
+
+    /// `get(name)` as a whole number; a value that is not one is reported like an invalid argument.
+    pub fn getInt(&self, name: String) -> i32 {
+        let value = self.get(name.clone());
+        value.trim().parse::<i32>().unwrap_or_else(|_| {
+            eprintln!("error: `{}` expects a whole number, got `{}`", name, value);
+            std::process::exit(2)
+        })
+    }
+
+    /// The words after the declared positional arguments.
+    pub fn rest(&self) -> Vec<String> {
+        self.rest.clone()
+    }
+}
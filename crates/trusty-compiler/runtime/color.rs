@@ -0,0 +1,180 @@
+//! Runtime of `trusty:color`, pasted into programs that import it (or linked from
+//! `trusty-runtime`): ANSI styling for terminal output, on `std`. Colors are only written when
+//! stdout is a terminal and `NO_COLOR` is not set (`FORCE_COLOR` forces them), so piped output
+//! stays plain text.
+
+/// 0: decided from the environment, 1: forced on, 2: forced off.
+static __TRUST_COLOR_OVERRIDE: std::sync::atomic::AtomicU8 = std::sync::atomic::AtomicU8::new(0);
+
+/// How a `styled` text looks; `style()` gives the plain one. Colors are named (`"red"`, `"gray"`...).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Style {
+    pub color: String,
+    pub background: String,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+pub fn style() -> Style {
+    Style::default()
+}
+
+/// Whether the helpers below write colors: `setColorEnabled` decides if it was called, then
+/// `NO_COLOR`, `FORCE_COLOR` and whether stdout is a terminal.
+#[allow(non_snake_case)]
+pub fn colorEnabled() -> bool {
+    match __TRUST_COLOR_OVERRIDE.load(std::sync::atomic::Ordering::Relaxed) {
+        1 => true,
+        2 => false,
+        _ => {
+            static DETECTED: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+            *DETECTED.get_or_init(|| {
+                let set = |name: &str| std::env::var_os(name).is_some_and(|value| !value.is_empty());
+                if set("NO_COLOR") {
+                    return false;
+                }
+                if set("FORCE_COLOR") {
+                    return true;
+                }
+                std::io::IsTerminal::is_terminal(&std::io::stdout())
+                    && std::env::var("TERM").map_or(true, |term| term != "dumb")
+            })
+        }
+    }
+}
+
+/// Turns colors on or off for the rest of the program, e.g. from a `--no-color` flag.
+#[allow(non_snake_case)]
+pub fn setColorEnabled(enabled: bool) {
+    __TRUST_COLOR_OVERRIDE.store(if enabled { 1 } else { 2 }, std::sync::atomic::Ordering::Relaxed);
+}
+
+/// SGR code of the foreground color `name`; backgrounds are 10 more.
+fn __trust_color_code(name: &str) -> Option<u8> {
+    Some(match name.to_lowercase().as_str() {
+        "black" => 30,
+        "red" => 31,
+        "green" => 32,
+        "yellow" => 33,
+        "blue" => 34,
+        "magenta" => 35,
+        "cyan" => 36,
+        "white" => 37,
+        "gray" | "grey" => 90,
+        _ => return None,
+    })
+}
+
+/// `text` between the SGR `open` codes and their `close` codes, which only undo this styling, so
+/// ``red(`a ${bold("b")} c`)`` stays red after the bold part.
+fn __trust_paint(text: String, open: &[u8], close: &[u8]) -> String {
+    if open.is_empty() || !colorEnabled() {
+        return text;
+    }
+    let codes = |codes: &[u8]| codes.iter().map(|code| code.to_string()).collect::<Vec<_>>().join(";");
+    format!("\x1b[{}m{}\x1b[{}m", codes(open), text, codes(close))
+}
+
+pub fn styled(text: String, style: Style) -> String {
+    let (mut open, mut close) = (Vec::new(), Vec::new());
+    if style.bold {
+        open.push(1);
+    }
+    if style.dim {
+        open.push(2);
+    }
+    if style.bold || style.dim {
+        close.push(22);
+    }
+    if style.italic {
+        open.push(3);
+        close.push(23);
+    }
+    if style.underline {
+        open.push(4);
+        close.push(24);
+    }
+    if let Some(code) = __trust_color_code(&style.color) {
+        open.push(code);
+        close.push(39);
+    }
+    if let Some(code) = __trust_color_code(&style.background) {
+        open.push(code + 10);
+        close.push(49);
+    }
+    __trust_paint(text, &open, &close)
+}
+
+pub fn black(text: String) -> String {
+    __trust_paint(text, &[30], &[39])
+}
+
+pub fn red(text: String) -> String {
+    __trust_paint(text, &[31], &[39])
+}
+
+pub fn green(text: String) -> String {
+    __trust_paint(text, &[32], &[39])
+}
+
+pub fn yellow(text: String) -> String {
+    __trust_paint(text, &[33], &[39])
+}
+
+pub fn blue(text: String) -> String {
+    __trust_paint(text, &[34], &[39])
+}
+
+pub fn magenta(text: String) -> String {
+    __trust_paint(text, &[35], &[39])
+}
+
+pub fn cyan(text: String) -> String {
+    __trust_paint(text, &[36], &[39])
+}
+
+pub fn white(text: String) -> String {
+    __trust_paint(text, &[37], &[39])
+}
+
+pub fn gray(text: String) -> String {
+    __trust_paint(text, &[90], &[39])
+}
+
+pub fn bold(text: String) -> String {
+    __trust_paint(text, &[1], &[22])
+}
+
+pub fn dim(text: String) -> String {
+    __trust_paint(text, &[2], &[22])
+}
+
+pub fn italic(text: String) -> String {
+    __trust_paint(text, &[3], &[23])
+}
+
+pub fn underline(text: String) -> String {
+    __trust_paint(text, &[4], &[24])
+}
+
+/// `text` without the ANSI escape sequences in it, e.g. to measure its width.
+#[allow(non_snake_case)]
+pub fn stripColors(text: String) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch == '\x1b' && chars.peek() == Some(&'[') {
+            chars.next();
+            for ch in chars.by_ref() {
+                if ch.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            plain.push(ch);
+        }
+    }
+    plain
+}
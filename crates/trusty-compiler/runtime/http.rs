@@ -0,0 +1,1209 @@
+//! Runtime of `trusty:http`, pasted into programs that import it (or linked from `trusty-runtime`):
+//! a blocking client on `ureq` and a router on `tiny_http`.
+
+use std::collections::HashMap;
+use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use serde_json::Value;
+use tiny_http::{Header, Response as TinyResponse, Server as TinyServer, StatusCode};
+
+#[derive(Debug, Clone, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct HttpRequestOptions {
+    pub method: String,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+    /// Sent instead of `body` when not empty.
+    pub bodyBytes: Vec<u8>,
+    pub timeoutMs: i32,
+    /// Appended to the URL, percent-encoded: `?page=2&q=rust%20lang`.
+    pub query: HashMap<String, String>,
+    /// Sent instead of `body` and `bodyBytes` when it has entries.
+    pub form: FormData,
+}
+
+#[allow(non_snake_case)]
+pub fn requestOptions() -> HttpRequestOptions {
+    HttpRequestOptions {
+        method: "GET".to_string(),
+        headers: HashMap::new(),
+        body: String::new(),
+        bodyBytes: Vec::new(),
+        timeoutMs: 30_000,
+        query: HashMap::new(),
+        form: FormData::default(),
+    }
+}
+
+#[derive(Debug, Clone, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct HttpResponse {
+    pub status: i32,
+    pub ok: bool,
+    pub body: String,
+    pub headers: HashMap<String, String>,
+    pub error: String,
+    #[serde(skip)]
+    raw: Vec<u8>,
+}
+
+#[allow(non_snake_case)]
+impl HttpResponse {
+    pub fn text(&self) -> String {
+        self.body.clone()
+    }
+
+    pub fn bytes(&self) -> Vec<u8> {
+        self.raw.clone()
+    }
+
+    pub fn json(&self) -> Value {
+        serde_json::from_str(&self.body).unwrap_or(Value::Null)
+    }
+
+    pub fn jsonAs<T: serde::de::DeserializeOwned>(&self) -> Option<T> {
+        serde_json::from_str(&self.body).ok()
+    }
+
+    pub fn header(&self, name: String) -> String {
+        self.headers.get(&name).cloned().unwrap_or_default()
+    }
+}
+
+#[allow(non_snake_case)]
+pub fn fetch(url: String) -> HttpResponse {
+    fetchWith(url, requestOptions())
+}
+
+#[allow(non_snake_case)]
+pub fn fetchWith(url: String, options: HttpRequestOptions) -> HttpResponse {
+    match __trust_send(&url, &options) {
+        Ok(mut resp) => {
+            let raw = resp.body_mut().read_to_vec().unwrap_or_default();
+            __trust_http_response(&resp, raw)
+        }
+        Err(error) => __trust_http_failure(error),
+    }
+}
+
+/// Like `fetch`, streaming the body into the file at `path` instead of `body`.
+#[allow(non_snake_case)]
+pub fn downloadTo(url: String, path: String) -> HttpResponse {
+    downloadWith(url, path, requestOptions())
+}
+
+/// Like `fetchWith`, streaming the body into the file at `path` instead of `body`. The file is only
+/// written for a `2xx` response; `error` tells why it could not be.
+#[allow(non_snake_case)]
+pub fn downloadWith(url: String, path: String, options: HttpRequestOptions) -> HttpResponse {
+    let mut resp = match __trust_send(&url, &options) {
+        Ok(resp) => resp,
+        Err(error) => return __trust_http_failure(error),
+    };
+    if !resp.status().is_success() {
+        let raw = resp.body_mut().read_to_vec().unwrap_or_default();
+        return __trust_http_response(&resp, raw);
+    }
+    let written = std::fs::File::create(&path)
+        .and_then(|mut file| std::io::copy(&mut resp.body_mut().as_reader(), &mut file));
+    let mut response = __trust_http_response(&resp, Vec::new());
+    if let Err(e) = written {
+        response.ok = false;
+        response.error = format!("cannot write {}: {}", path, e);
+    }
+    response
+}
+
+fn __trust_http_response(resp: &ureq::http::Response<ureq::Body>, raw: Vec<u8>) -> HttpResponse {
+    let status = resp.status().as_u16() as i32;
+    let mut headers = HashMap::new();
+    for (name, value) in resp.headers().iter() {
+        headers.insert(name.as_str().to_string(), value.to_str().unwrap_or("").to_string());
+    }
+    HttpResponse {
+        status,
+        ok: (200..300).contains(&status),
+        body: String::from_utf8_lossy(&raw).to_string(),
+        headers,
+        error: String::new(),
+        raw,
+    }
+}
+
+fn __trust_http_failure(error: String) -> HttpResponse {
+    HttpResponse { status: 0, ok: false, body: String::new(), headers: HashMap::new(), error, raw: Vec::new() }
+}
+
+/// Sends the request `options` describe to `url`, with `options.query` appended.
+fn __trust_send(url: &str, options: &HttpRequestOptions) -> Result<ureq::http::Response<ureq::Body>, String> {
+    let timeout_ms = if options.timeoutMs <= 0 { 30_000 } else { options.timeoutMs as u64 };
+    let config = ureq::Agent::config_builder()
+        .http_status_as_error(false)
+        .timeout_global(Some(Duration::from_millis(timeout_ms)))
+        .build();
+    let agent: ureq::Agent = config.into();
+
+    let method = if options.method.trim().is_empty() {
+        "GET".to_string()
+    } else {
+        options.method.to_uppercase()
+    };
+    let url = __trust_with_query(url, &options.query);
+
+    // `form` when it has entries, else `bodyBytes` when not empty, else `body`.
+    let (body, content_type) = if !options.form.isEmpty() {
+        let (body, content_type) = options.form.encode()?;
+        (body, Some(content_type))
+    } else if !options.bodyBytes.is_empty() {
+        (options.bodyBytes.clone(), None)
+    } else {
+        (options.body.clone().into_bytes(), None)
+    };
+    let content_type = content_type.filter(|_| !options.headers.keys().any(|k| k.eq_ignore_ascii_case("content-type")));
+
+    let without_body = |mut req: ureq::RequestBuilder<ureq::typestate::WithoutBody>| {
+        for (k, v) in options.headers.iter() {
+            req = req.header(k, v);
+        }
+        req.call()
+    };
+    let with_body = |mut req: ureq::RequestBuilder<ureq::typestate::WithBody>| {
+        for (k, v) in options.headers.iter() {
+            req = req.header(k, v);
+        }
+        if let Some(content_type) = &content_type {
+            req = req.header("Content-Type", content_type);
+        }
+        if body.is_empty() {
+            req.send_empty()
+        } else {
+            req.send(body.as_slice())
+        }
+    };
+    let sent = match method.as_str() {
+        "GET" => without_body(agent.get(&url)),
+        "DELETE" => without_body(agent.delete(&url)),
+        "POST" => with_body(agent.post(&url)),
+        "PUT" => with_body(agent.put(&url)),
+        "PATCH" => with_body(agent.patch(&url)),
+        _ => return Err(format!("unsupported HTTP method: {}", method)),
+    };
+    sent.map_err(|e| e.to_string())
+}
+
+/// `text` percent-encoded for a URL query or an `application/x-www-form-urlencoded` body.
+fn __trust_url_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn __trust_url_encode_pairs<'a>(pairs: impl Iterator<Item = (&'a String, &'a String)>) -> String {
+    pairs
+        .map(|(key, value)| format!("{}={}", __trust_url_encode(key), __trust_url_encode(value)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// `url` with `query` encoded into it (sorted by key), after any query it has and before its `#fragment`.
+fn __trust_with_query(url: &str, query: &HashMap<String, String>) -> String {
+    if query.is_empty() {
+        return url.to_string();
+    }
+    let mut pairs: Vec<(&String, &String)> = query.iter().collect();
+    pairs.sort();
+    let (base, fragment) = url.split_at(url.find('#').unwrap_or(url.len()));
+    let separator = match base.find('?') {
+        None => "?",
+        Some(_) if base.ends_with('?') || base.ends_with('&') => "",
+        Some(_) => "&",
+    };
+    format!("{}{}{}{}", base, separator, __trust_url_encode_pairs(pairs.into_iter()), fragment)
+}
+
+/// A form body for `HttpRequestOptions.form`: sent `application/x-www-form-urlencoded`, or as
+/// `multipart/form-data` once it has a file.
+#[derive(Debug, Clone, Default, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct FormData {
+    fields: Vec<(String, String)>,
+    files: Vec<(String, String)>,
+}
+
+#[allow(non_snake_case)]
+impl FormData {
+    pub fn create() -> FormData {
+        FormData::default()
+    }
+
+    pub fn field(mut self, name: String, value: String) -> FormData {
+        self.fields.push((name, value));
+        self
+    }
+
+    /// Uploads the file at `path` (read when the request is sent) under its file name.
+    pub fn file(mut self, name: String, path: String) -> FormData {
+        self.files.push((name, path));
+        self
+    }
+
+    pub fn isEmpty(&self) -> bool {
+        self.fields.is_empty() && self.files.is_empty()
+    }
+
+    /// The body and its `Content-Type`.
+    fn encode(&self) -> Result<(Vec<u8>, String), String> {
+        if self.files.is_empty() {
+            let body = __trust_url_encode_pairs(self.fields.iter().map(|(name, value)| (name, value)));
+            return Ok((body.into_bytes(), "application/x-www-form-urlencoded".to_string()));
+        }
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let boundary = format!("----TrustFormBoundary{:x}", nanos);
+        // Quotes and line breaks in names are escaped the way browsers do.
+        let quote = |text: &str| text.replace('"', "%22").replace('\r', "%0D").replace('\n', "%0A");
+        let mut body = Vec::new();
+        for (name, value) in &self.fields {
+            body.extend_from_slice(
+                format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n", boundary, quote(name)).as_bytes(),
+            );
+            body.extend_from_slice(value.as_bytes());
+            body.extend_from_slice(b"\r\n");
+        }
+        for (name, path) in &self.files {
+            let data = std::fs::read(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+            let file_name = std::path::Path::new(path)
+                .file_name()
+                .map(|file_name| file_name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            body.extend_from_slice(
+                format!(
+                    "--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+                    boundary,
+                    quote(name),
+                    quote(&file_name)
+                )
+                .as_bytes(),
+            );
+            body.extend_from_slice(&data);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+        Ok((body, format!("multipart/form-data; boundary={}", boundary)))
+    }
+}
+
+#[derive(Debug, Clone, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct Params {
+    pub values: HashMap<String, String>,
+}
+
+impl Default for Params {
+    fn default() -> Params {
+        Params::new()
+    }
+}
+
+#[allow(non_snake_case)]
+impl Params {
+    pub fn new() -> Params {
+        Params { values: HashMap::new() }
+    }
+
+    pub fn getOr(&self, key: String, fallback: String) -> String {
+        self.values.get(&key).cloned().unwrap_or(fallback)
+    }
+}
+
+#[derive(Debug, Clone, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: String,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+    pub params: Params,
+    #[serde(skip)]
+    raw: Vec<u8>,
+    #[serde(skip)]
+    session: Session,
+}
+
+#[allow(non_snake_case)]
+impl Request {
+    pub fn text(&self) -> String {
+        self.body.clone()
+    }
+
+    pub fn bytes(&self) -> Vec<u8> {
+        self.raw.clone()
+    }
+
+    pub fn json(&self) -> Value {
+        serde_json::from_str(&self.body).unwrap_or(Value::Null)
+    }
+
+    pub fn jsonAs<T: serde::de::DeserializeOwned>(&self) -> Option<T> {
+        serde_json::from_str(&self.body).ok()
+    }
+
+    pub fn header(&self, name: String) -> String {
+        self.headers.get(&name).cloned().unwrap_or_default()
+    }
+
+    /// The cookie `name` the client sent, `""` when it sent none.
+    pub fn cookie(&self, name: String) -> String {
+        self.cookies().remove(&name).unwrap_or_default()
+    }
+
+    /// Every cookie the client sent, by name.
+    pub fn cookies(&self) -> HashMap<String, String> {
+        let header = self.headers.iter().find(|(k, _)| k.eq_ignore_ascii_case("Cookie")).map(|(_, v)| v.as_str());
+        let mut cookies = HashMap::new();
+        for pair in header.unwrap_or_default().split(';') {
+            if let Some((name, value)) = pair.split_once('=') {
+                let value = value.trim();
+                let value = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(value);
+                cookies.entry(name.trim().to_string()).or_insert_with(|| percent_decode(value));
+            }
+        }
+        cookies
+    }
+
+    /// The client's session, kept between its requests once `app.useSessions(secret)` is on.
+    pub fn session(&self) -> Session {
+        self.session.clone()
+    }
+}
+
+/// How `res.setCookie` sends a cookie; `cookieOptions()` gives the defaults.
+#[derive(Debug, Clone, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct CookieOptions {
+    /// Seconds until the cookie expires; negative (the default) keeps it until the browser closes.
+    pub maxAgeSeconds: i32,
+    pub path: String,
+    /// Empty for the host that set it only.
+    pub domain: String,
+    /// Only sent over HTTPS.
+    pub secure: bool,
+    /// Hidden from the page's JavaScript.
+    pub httpOnly: bool,
+    /// `"Strict"`, `"Lax"` or `"None"`; empty leaves the attribute out.
+    pub sameSite: String,
+}
+
+/// A cookie for the whole site (`path` `/`), `httpOnly`, `SameSite=Lax`, until the browser closes.
+#[allow(non_snake_case)]
+pub fn cookieOptions() -> CookieOptions {
+    CookieOptions {
+        maxAgeSeconds: -1,
+        path: "/".to_string(),
+        domain: String::new(),
+        secure: false,
+        httpOnly: true,
+        sameSite: "Lax".to_string(),
+    }
+}
+
+/// Name of the cookie holding the signed session id.
+const SESSION_COOKIE: &str = "trust_session";
+
+/// The values a client's session holds (`req.session()`), strings by key. With `app.useSessions`
+/// on, a session is stored on the server the first time a value is set in it, and found again
+/// from a signed cookie; otherwise it only lasts for the request.
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    id: String,
+    values: Arc<Mutex<HashMap<String, String>>>,
+    destroyed: Arc<std::sync::atomic::AtomicBool>,
+}
+
+#[allow(dead_code, non_snake_case)]
+impl Session {
+    pub fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    /// The value of `key`, `""` when it has none.
+    pub fn get(&self, key: String) -> String {
+        self.values.lock().ok().and_then(|values| values.get(&key).cloned()).unwrap_or_default()
+    }
+
+    pub fn has(&self, key: String) -> bool {
+        self.values.lock().map(|values| values.contains_key(&key)).unwrap_or(false)
+    }
+
+    pub fn set(&self, key: String, value: String) {
+        if let Ok(mut values) = self.values.lock() {
+            values.insert(key, value);
+        }
+    }
+
+    pub fn remove(&self, key: String) {
+        if let Ok(mut values) = self.values.lock() {
+            values.remove(&key);
+        }
+    }
+
+    /// Forgets the session and its cookie once the response is sent, e.g. to log out.
+    pub fn destroy(&self) {
+        self.destroyed.store(true, std::sync::atomic::Ordering::SeqCst);
+        if let Ok(mut values) = self.values.lock() {
+            values.clear();
+        }
+    }
+}
+
+/// The sessions of a server, by id, and the secret their cookies are signed with.
+struct SessionStore {
+    secret: String,
+    sessions: HashMap<String, Arc<Mutex<HashMap<String, String>>>>,
+}
+
+/// A new session id: 128 bits from the standard library's randomly seeded hasher.
+fn __trust_session_id() -> String {
+    use std::hash::{BuildHasher, Hasher};
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    (0..2)
+        .map(|half| {
+            let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+            hasher.write_u128(nanos);
+            hasher.write_usize(half);
+            format!("{:016x}", hasher.finish())
+        })
+        .collect()
+}
+
+/// `value.signature`, where the signature is the hex HMAC-SHA256 of `value` keyed with `secret`.
+fn __trust_sign(value: &str, secret: &str) -> String {
+    let mac = __trust_hmac_sha256(secret.as_bytes(), value.as_bytes());
+    format!("{}.{}", value, mac.iter().map(|b| format!("{:02x}", b)).collect::<String>())
+}
+
+/// The value `signed` was made from by `__trust_sign`, unless its signature does not match.
+fn __trust_unsign(signed: &str, secret: &str) -> Option<String> {
+    let (value, _) = signed.rsplit_once('.')?;
+    let expected = __trust_sign(value, secret);
+    // Compares every byte, so the time taken tells nothing about where a forged signature differs.
+    let same = expected.len() == signed.len()
+        && expected.bytes().zip(signed.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0;
+    same.then(|| value.to_string())
+}
+
+fn __trust_hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; 64];
+    if key.len() > 64 {
+        block[..32].copy_from_slice(&__trust_sha256(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let mut inner: Vec<u8> = block.iter().map(|b| b ^ 0x36).collect();
+    inner.extend_from_slice(message);
+    let mut outer: Vec<u8> = block.iter().map(|b| b ^ 0x5c).collect();
+    outer.extend_from_slice(&__trust_sha256(&inner));
+    __trust_sha256(&outer)
+}
+
+/// SHA-256 (FIPS 180-4), so signing sessions needs no crate.
+fn __trust_sha256(data: &[u8]) -> [u8; 32] {
+    const K: [u32; 64] = [
+        0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+        0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+        0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+        0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+        0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+        0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+        0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+        0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+    ];
+    let mut hash: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+    ];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+    for chunk in message.chunks(64) {
+        let mut w = [0u32; 64];
+        for i in 0..16 {
+            w[i] = u32::from_be_bytes([chunk[4 * i], chunk[4 * i + 1], chunk[4 * i + 2], chunk[4 * i + 3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+        let mut v = hash;
+        for i in 0..64 {
+            let s1 = v[4].rotate_right(6) ^ v[4].rotate_right(11) ^ v[4].rotate_right(25);
+            let ch = (v[4] & v[5]) ^ (!v[4] & v[6]);
+            let t1 = v[7].wrapping_add(s1).wrapping_add(ch).wrapping_add(K[i]).wrapping_add(w[i]);
+            let s0 = v[0].rotate_right(2) ^ v[0].rotate_right(13) ^ v[0].rotate_right(22);
+            let maj = (v[0] & v[1]) ^ (v[0] & v[2]) ^ (v[1] & v[2]);
+            v = [t1.wrapping_add(s0.wrapping_add(maj)), v[0], v[1], v[2], v[3].wrapping_add(t1), v[4], v[5], v[6]];
+        }
+        for (h, x) in hash.iter_mut().zip(v) {
+            *h = h.wrapping_add(x);
+        }
+    }
+    let mut out = [0u8; 32];
+    for (bytes, h) in out.chunks_mut(4).zip(hash) {
+        bytes.copy_from_slice(&h.to_be_bytes());
+    }
+    out
+}
+
+#[derive(Debug, Clone)]
+pub struct Response {
+    status: Arc<Mutex<i32>>,
+    headers: Arc<Mutex<HashMap<String, String>>>,
+    body: Arc<Mutex<Vec<u8>>>,
+    /// `Set-Cookie` values, each sent as a header of its own.
+    cookies: Arc<Mutex<Vec<String>>>,
+}
+
+impl Default for Response {
+    fn default() -> Response {
+        Response::new()
+    }
+}
+
+#[allow(non_snake_case)]
+impl Response {
+    pub fn new() -> Response {
+        Response {
+            status: Arc::new(Mutex::new(200)),
+            headers: Arc::new(Mutex::new(HashMap::new())),
+            body: Arc::new(Mutex::new(Vec::new())),
+            cookies: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn status(&self, code: i32) -> Response {
+        if let Ok(mut s) = self.status.lock() {
+            *s = code;
+        }
+        self.clone()
+    }
+
+    pub fn header(&self, name: String, value: String) -> Response {
+        if let Ok(mut h) = self.headers.lock() {
+            h.insert(name, value);
+        }
+        self.clone()
+    }
+
+    pub fn send(&self, body: String) -> Response {
+        if let Ok(mut b) = self.body.lock() {
+            *b = body.into_bytes();
+        }
+        self.clone()
+    }
+
+    /// A binary body; `Content-Type` defaults to `application/octet-stream`.
+    pub fn sendBytes(&self, body: Vec<u8>) -> Response {
+        if let Ok(mut h) = self.headers.lock() {
+            if !h.keys().any(|k| k.eq_ignore_ascii_case("Content-Type")) {
+                h.insert("Content-Type".to_string(), "application/octet-stream".to_string());
+            }
+        }
+        if let Ok(mut b) = self.body.lock() {
+            *b = body;
+        }
+        self.clone()
+    }
+
+    pub fn json(&self, value: String) -> Response {
+        let _ = self.header("Content-Type".to_string(), "application/json".to_string());
+        self.send(value)
+    }
+
+    pub fn jsonValue(&self, value: Value) -> Response {
+        let body = serde_json::to_string(&value).unwrap_or("null".to_string());
+        self.json(body)
+    }
+
+    pub fn jsonText(&self, json: String) -> Response {
+        let _ = self.header("Content-Type".to_string(), "application/json".to_string());
+        self.send(json)
+    }
+
+    /// Sets the cookie `name` on the client: `res.setCookie("theme", "dark", cookieOptions())`.
+    /// The value is percent-encoded, and `req.cookie` decodes it.
+    pub fn setCookie(&self, name: String, value: String, options: CookieOptions) -> Response {
+        let mut cookie = format!("{}={}", name, __trust_url_encode(&value));
+        if options.maxAgeSeconds >= 0 {
+            cookie.push_str(&format!("; Max-Age={}", options.maxAgeSeconds));
+        }
+        if !options.path.is_empty() {
+            cookie.push_str(&format!("; Path={}", options.path));
+        }
+        if !options.domain.is_empty() {
+            cookie.push_str(&format!("; Domain={}", options.domain));
+        }
+        if options.secure {
+            cookie.push_str("; Secure");
+        }
+        if options.httpOnly {
+            cookie.push_str("; HttpOnly");
+        }
+        if !options.sameSite.is_empty() {
+            cookie.push_str(&format!("; SameSite={}", options.sameSite));
+        }
+        if let Ok(mut cookies) = self.cookies.lock() {
+            cookies.retain(|set| set.split('=').next() != Some(name.as_str()));
+            cookies.push(cookie);
+        }
+        self.clone()
+    }
+
+    /// Removes the cookie `name` (set with path `/`) from the client.
+    pub fn clearCookie(&self, name: String) -> Response {
+        self.setCookie(name, String::new(), CookieOptions { maxAgeSeconds: 0, ..cookieOptions() })
+    }
+
+    fn snapshot(&self) -> (i32, HashMap<String, String>, Vec<u8>, Vec<String>) {
+        let status = match self.status.lock() {
+            Ok(s) => *s,
+            Err(_) => 500,
+        };
+        let headers = match self.headers.lock() {
+            Ok(h) => h.clone(),
+            Err(_) => HashMap::new(),
+        };
+        let body = match self.body.lock() {
+            Ok(b) => b.clone(),
+            Err(_) => Vec::new(),
+        };
+        let cookies = match self.cookies.lock() {
+            Ok(c) => c.clone(),
+            Err(_) => Vec::new(),
+        };
+        (status, headers, body, cookies)
+    }
+}
+
+type RouteHandler = Arc<dyn Fn(Request, Response) + Send + Sync>;
+type Middleware = Arc<dyn Fn(Request) -> Request + Send + Sync>;
+
+#[derive(Clone)]
+struct Route {
+    method: String,
+    pattern: String,
+    handler: RouteHandler,
+}
+
+/// A server, or a group of its routes (`app.group("/api", ...)`): the routes, static mounts and
+/// middlewares are shared, and a group's `prefix` starts the patterns declared on it.
+#[derive(Clone)]
+pub struct HttpServer {
+    routes: Arc<Mutex<Vec<Route>>>,
+    middlewares: Arc<Mutex<Vec<Middleware>>>,
+    lastError: Arc<Mutex<String>>,
+    prefix: String,
+    /// `(url prefix, directory)` pairs, in the order they were mounted.
+    statics: Arc<Mutex<Vec<(String, String)>>>,
+    not_found: Arc<Mutex<Option<RouteHandler>>>,
+    /// Threads answering requests; 0 for one per CPU.
+    workers: Arc<std::sync::atomic::AtomicUsize>,
+    stopping: Arc<std::sync::atomic::AtomicBool>,
+    /// Set by `useSessions`.
+    sessions: Arc<Mutex<Option<SessionStore>>>,
+}
+
+/// A server listening on a thread of its own (`app.listenBackground(port)`): `ok` is false, with
+/// the reason in `error`, when it could not bind. `await handle` waits for the server to stop.
+#[derive(Clone)]
+pub struct ServerHandle {
+    pub ok: bool,
+    /// The port bound, also when `0` asked for a free one.
+    pub port: i32,
+    pub error: String,
+    server: HttpServer,
+    thread: Arc<Mutex<Option<std::thread::JoinHandle<()>>>>,
+}
+
+#[allow(non_snake_case)]
+impl ServerHandle {
+    /// Stops the server and waits for the requests being handled to be answered.
+    pub fn stop(&self) {
+        self.server.stop();
+        let _ = self.join();
+    }
+
+    /// Waits for the server to stop; `Err` when it panicked.
+    pub fn join(&self) -> Result<bool, String> {
+        let thread = match self.thread.lock() {
+            Ok(mut thread) => thread.take(),
+            Err(_) => None,
+        };
+        match thread {
+            Some(thread) => thread.join().map(|_| self.ok).map_err(|_| "the server thread panicked".to_string()),
+            None => Ok(self.ok),
+        }
+    }
+}
+
+#[allow(non_snake_case)]
+impl HttpServer {
+    pub fn create() -> HttpServer {
+        HttpServer {
+            routes: Arc::new(Mutex::new(Vec::new())),
+            middlewares: Arc::new(Mutex::new(Vec::new())),
+            lastError: Arc::new(Mutex::new(String::new())),
+            prefix: String::new(),
+            statics: Arc::new(Mutex::new(Vec::new())),
+            not_found: Arc::new(Mutex::new(None)),
+            workers: Arc::new(std::sync::atomic::AtomicUsize::new(0)),
+            stopping: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            sessions: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Declares routes under `prefix`: `define` gets a server whose patterns start with it
+    /// (`app.group("/api/v1", function(api) { api.get("/users", ...); })`). Groups nest.
+    pub fn group<F>(&self, prefix: String, define: F)
+    where
+        F: FnOnce(HttpServer),
+    {
+        let mut group = self.clone();
+        group.prefix = join_route(&self.prefix, &prefix);
+        define(group);
+    }
+
+    /// Serves the files under `dir` for `GET`s below `prefix` that no route matches:
+    /// `app.static("/assets", "./public")` answers `/assets/app.css` with `./public/app.css`, and a
+    /// directory with its `index.html`. Paths leaving `dir` (`..`) are not served.
+    pub fn serveStatic(&self, prefix: String, dir: String) {
+        if let Ok(mut statics) = self.statics.lock() {
+            statics.push((join_route(&self.prefix, &prefix), dir));
+        }
+    }
+
+    /// Answers the requests nothing else matches, with a response whose status is already 404.
+    pub fn notFound<F>(&self, handler: F)
+    where
+        F: Fn(Request, Response) + Send + Sync + 'static,
+    {
+        if let Ok(mut not_found) = self.not_found.lock() {
+            *not_found = Some(Arc::new(handler));
+        }
+    }
+
+    /// Keeps a session per client (`req.session()`) in memory, identified by a cookie signed with
+    /// `secret` so that clients cannot pick another's id. Sessions are lost when the program exits.
+    pub fn useSessions(&self, secret: String) {
+        if let Ok(mut sessions) = self.sessions.lock() {
+            *sessions = Some(SessionStore { secret, sessions: HashMap::new() });
+        }
+    }
+
+    pub fn addMiddleware<F>(&self, middleware: F)
+    where
+        F: Fn(Request) -> Request + Send + Sync + 'static,
+    {
+        if let Ok(mut mw) = self.middlewares.lock() {
+            mw.push(Arc::new(middleware));
+        }
+    }
+
+    pub fn get<F>(&self, pattern: String, handler: F)
+    where
+        F: Fn(Request, Response) + Send + Sync + 'static,
+    {
+        self.add_route("GET".to_string(), pattern, handler);
+    }
+
+    pub fn post<F>(&self, pattern: String, handler: F)
+    where
+        F: Fn(Request, Response) + Send + Sync + 'static,
+    {
+        self.add_route("POST".to_string(), pattern, handler);
+    }
+
+    pub fn put<F>(&self, pattern: String, handler: F)
+    where
+        F: Fn(Request, Response) + Send + Sync + 'static,
+    {
+        self.add_route("PUT".to_string(), pattern, handler);
+    }
+
+    pub fn delete<F>(&self, pattern: String, handler: F)
+    where
+        F: Fn(Request, Response) + Send + Sync + 'static,
+    {
+        self.add_route("DELETE".to_string(), pattern, handler);
+    }
+
+    /// Serves on `0.0.0.0:port` until `stop()` is called; `false` when the port cannot be bound.
+    pub fn listen(&self, port: i32) -> bool {
+        self.listenOn(format!("0.0.0.0:{}", port))
+    }
+
+    pub fn listenOn(&self, bind: String) -> bool {
+        match self.bind(&bind) {
+            Some(server) => {
+                self.serve(&server);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Like `listen`, on a thread of its own: the returned handle stops the server or waits for it.
+    pub fn listenBackground(&self, port: i32) -> ServerHandle {
+        self.listenBackgroundOn(format!("0.0.0.0:{}", port))
+    }
+
+    /// Like `listenOn`, on a thread of its own; port `0` picks a free one (`handle.port`).
+    pub fn listenBackgroundOn(&self, bind: String) -> ServerHandle {
+        let Some(server) = self.bind(&bind) else {
+            return ServerHandle { ok: false, port: 0, error: self.lastError(), server: self.clone(), thread: Arc::new(Mutex::new(None)) };
+        };
+        let port = server.server_addr().to_ip().map(|addr| addr.port() as i32).unwrap_or(0);
+        let serving = self.clone();
+        let thread = std::thread::spawn(move || serving.serve(&server));
+        ServerHandle { ok: true, port, error: String::new(), server: self.clone(), thread: Arc::new(Mutex::new(Some(thread))) }
+    }
+
+    /// How many requests are handled at once (each on its own thread); defaults to the number of CPUs.
+    pub fn workers(&self, count: i32) {
+        self.workers.store(count.max(1) as usize, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    /// Makes `listen` return once the requests being handled are answered.
+    pub fn stop(&self) {
+        self.stopping.store(true, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    fn bind(&self, bind: &str) -> Option<TinyServer> {
+        if let Ok(mut last) = self.lastError.lock() {
+            *last = String::new();
+        }
+        match TinyServer::http(bind) {
+            Ok(server) => {
+                self.stopping.store(false, std::sync::atomic::Ordering::SeqCst);
+                Some(server)
+            }
+            Err(e) => {
+                if let Ok(mut last) = self.lastError.lock() {
+                    *last = e.to_string();
+                }
+                None
+            }
+        }
+    }
+
+    /// Answers requests on `workers` threads until `stop()`: each checks the flag between requests.
+    fn serve(&self, server: &TinyServer) {
+        let workers = match self.workers.load(std::sync::atomic::Ordering::SeqCst) {
+            0 => std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4),
+            n => n,
+        };
+        let work = || {
+            while !self.stopping.load(std::sync::atomic::Ordering::SeqCst) {
+                if let Ok(Some(incoming)) = server.recv_timeout(Duration::from_millis(50)) {
+                    self.respond(incoming);
+                }
+            }
+        };
+        std::thread::scope(|scope| {
+            for _ in 1..workers {
+                scope.spawn(work);
+            }
+            work();
+        });
+    }
+
+    fn respond(&self, mut incoming: tiny_http::Request) {
+        let url = incoming.url().to_string();
+        let (path, query) = split_path_query(&url);
+        let method = incoming.method().as_str().to_string();
+
+        let mut headers = HashMap::new();
+        for h in incoming.headers() {
+            headers.insert(h.field.to_string(), h.value.to_string());
+        }
+
+        let mut raw = Vec::new();
+        let _ = incoming.as_reader().read_to_end(&mut raw);
+        let body = String::from_utf8_lossy(&raw).to_string();
+
+        let mut selected: Option<(RouteHandler, Params)> = None;
+        let routes = match self.routes.lock() {
+            Ok(r) => r.clone(),
+            Err(_) => Vec::new(),
+        };
+
+        for route in routes {
+            if route.method != method {
+                continue;
+            }
+            if let Some(params) = match_route(&route.pattern, &path) {
+                selected = Some((route.handler.clone(), params));
+                break;
+            }
+        }
+
+        if selected.is_none() && (method == "GET" || method == "HEAD") {
+            if let Some((file, content_type)) = self.static_file(&path) {
+                let mut tiny_resp = TinyResponse::from_file(file);
+                if let Ok(h) = Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes()) {
+                    tiny_resp = tiny_resp.with_header(h);
+                }
+                let _ = incoming.respond(tiny_resp);
+                return;
+            }
+        }
+
+        let not_found = match self.not_found.lock() {
+            Ok(handler) => handler.clone(),
+            Err(_) => None,
+        };
+        let (handler, params, status) = match (selected, not_found) {
+            (Some((handler, params)), _) => (handler, params, 200),
+            (None, Some(handler)) => (handler, Params::new(), 404),
+            (None, None) => {
+                let tiny_resp = TinyResponse::from_string("Not Found".to_string())
+                    .with_status_code(StatusCode(404));
+                let _ = incoming.respond(tiny_resp);
+                return;
+            }
+        };
+
+        let mut req = Request {
+            method: method.clone(),
+            path: path.clone(),
+            query: query.clone(),
+            headers,
+            body,
+            params,
+            raw,
+            session: Session::default(),
+        };
+        req.session = self.load_session(&req);
+        let session = req.session.clone();
+
+        let middlewares = match self.middlewares.lock() {
+            Ok(m) => m.clone(),
+            Err(_) => Vec::new(),
+        };
+        for middleware in middlewares {
+            req = middleware(req);
+        }
+
+        let res = Response::new().status(status);
+        // A panicking handler answers 500 instead of taking its worker down.
+        if std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| handler(req, res.clone()))).is_err() {
+            let _ = res.status(500).send("Internal Server Error".to_string());
+        }
+        self.save_session(&session, &res);
+
+        let (status, mut out_headers, out_body, cookies) = res.snapshot();
+        let status_u16 = if !(100..=599).contains(&status) {
+            500
+        } else {
+            status as u16
+        };
+        if !out_headers.keys().any(|k| k.eq_ignore_ascii_case("Content-Type")) {
+            out_headers.insert("Content-Type".to_string(), "text/plain; charset=UTF-8".to_string());
+        }
+        let mut tiny_resp = TinyResponse::from_data(out_body)
+            .with_status_code(StatusCode(status_u16));
+        for (k, v) in out_headers {
+            if let Ok(h) = Header::from_bytes(k.as_bytes(), v.as_bytes()) {
+                tiny_resp = tiny_resp.with_header(h);
+            }
+        }
+        for cookie in cookies {
+            if let Ok(h) = Header::from_bytes(&b"Set-Cookie"[..], cookie.as_bytes()) {
+                tiny_resp = tiny_resp.with_header(h);
+            }
+        }
+        let _ = incoming.respond(tiny_resp);
+    }
+
+    /// The stored session `req`'s cookie names, or a new one (stored by `save_session` if it gets values).
+    fn load_session(&self, req: &Request) -> Session {
+        let Ok(store) = self.sessions.lock() else {
+            return Session::default();
+        };
+        let Some(store) = store.as_ref() else {
+            return Session::default();
+        };
+        let id = __trust_unsign(&req.cookie(SESSION_COOKIE.to_string()), &store.secret);
+        match id.and_then(|id| store.sessions.get(&id).map(|values| (id, values.clone()))) {
+            Some((id, values)) => Session { id, values, ..Session::default() },
+            None => Session { id: __trust_session_id(), ..Session::default() },
+        }
+    }
+
+    /// Stores a new session that was given values, or forgets a destroyed one, setting its cookie.
+    fn save_session(&self, session: &Session, res: &Response) {
+        let Ok(mut store) = self.sessions.lock() else {
+            return;
+        };
+        let Some(store) = store.as_mut() else {
+            return;
+        };
+        let stored = store.sessions.contains_key(&session.id);
+        if session.destroyed.load(std::sync::atomic::Ordering::SeqCst) {
+            if store.sessions.remove(&session.id).is_some() {
+                let _ = res.clearCookie(SESSION_COOKIE.to_string());
+            }
+        } else if !stored && session.values.lock().map(|values| !values.is_empty()).unwrap_or(false) {
+            store.sessions.insert(session.id.clone(), session.values.clone());
+            let _ = res.setCookie(SESSION_COOKIE.to_string(), __trust_sign(&session.id, &store.secret), cookieOptions());
+        }
+    }
+
+    pub fn lastError(&self) -> String {
+        match self.lastError.lock() {
+            Ok(v) => v.clone(),
+            Err(_) => "unknown server error".to_string(),
+        }
+    }
+
+    fn add_route<F>(&self, method: String, pattern: String, handler: F)
+    where
+        F: Fn(Request, Response) + Send + Sync + 'static,
+    {
+        if let Ok(mut routes) = self.routes.lock() {
+            routes.push(Route {
+                method,
+                pattern: join_route(&self.prefix, &pattern),
+                handler: Arc::new(handler),
+            });
+        }
+    }
+
+    /// The file a static mount has for `path`, with its `Content-Type`.
+    fn static_file(&self, path: &str) -> Option<(std::fs::File, &'static str)> {
+        let statics = match self.statics.lock() {
+            Ok(s) => s.clone(),
+            Err(_) => Vec::new(),
+        };
+        let requested = normalize_segments(path);
+        for (prefix, dir) in statics {
+            let mount = normalize_segments(&prefix);
+            if !requested.starts_with(&mount) {
+                continue;
+            }
+            let mut file = std::path::PathBuf::from(&dir);
+            for segment in &requested[mount.len()..] {
+                let segment = percent_decode(segment);
+                if segment.is_empty() || segment == "." || segment == ".." || segment.contains(['/', '\\']) {
+                    return None;
+                }
+                file.push(segment);
+            }
+            if file.is_dir() {
+                file.push("index.html");
+            }
+            if let Ok(opened) = std::fs::File::open(&file) {
+                if opened.metadata().map(|m| m.is_file()).unwrap_or(false) {
+                    return Some((opened, content_type_of(&file)));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// `prefix` and `pattern` as one route pattern: `/api` and `/users` give `/api/users`.
+fn join_route(prefix: &str, pattern: &str) -> String {
+    if prefix.is_empty() {
+        return pattern.to_string();
+    }
+    format!("{}/{}", prefix.trim_end_matches('/'), pattern.trim_start_matches('/'))
+}
+
+/// `%20` and the other escapes of a URL path segment decoded (kept as is when malformed).
+fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = segment.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// `Content-Type` of a static file, from its extension.
+fn content_type_of(file: &std::path::Path) -> &'static str {
+    let extension = file.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=UTF-8",
+        "css" => "text/css; charset=UTF-8",
+        "js" | "mjs" => "text/javascript; charset=UTF-8",
+        "json" => "application/json",
+        "txt" => "text/plain; charset=UTF-8",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        _ => "application/octet-stream",
+    }
+}
+
+fn split_path_query(url: &str) -> (String, String) {
+    if let Some((path, query)) = url.split_once('?') {
+        (path.to_string(), query.to_string())
+    } else {
+        (url.to_string(), String::new())
+    }
+}
+
+fn normalize_segments(path: &str) -> Vec<&str> {
+    let trimmed = path.trim_matches('/');
+    if trimmed.is_empty() {
+        Vec::new()
+    } else {
+        trimmed.split('/').collect::<Vec<_>>()
+    }
+}
+
+fn match_route(pattern: &str, path: &str) -> Option<Params> {
+    let p = normalize_segments(pattern);
+    let r = normalize_segments(path);
+    if p.len() != r.len() {
+        return None;
+    }
+    let mut params = HashMap::new();
+    for (pp, rr) in p.iter().zip(r.iter()) {
+        if let Some(name) = pp.strip_prefix(':') {
+            params.insert(name.to_string(), rr.to_string());
+            continue;
+        }
+        if pp != rr {
+            return None;
+        }
+    }
+    Some(Params { values: params })
+}
@@ -0,0 +1,23 @@
+//! Runtime of `trusty:json`, pasted into programs that import it (or linked from `trusty-runtime`).
+
+use serde_json::Value;
+
+#[allow(non_snake_case)]
+pub fn parseToJSON(json: String) -> Value {
+    serde_json::from_str(&json).unwrap_or(Value::Null)
+}
+
+#[allow(non_snake_case)]
+pub fn stringify<T: serde::Serialize>(value: T) -> String {
+    serde_json::to_string(&value).unwrap_or("null".to_string())
+}
+
+#[allow(non_snake_case)]
+pub fn toJSON<T: serde::Serialize>(value: T) -> String {
+    stringify(value)
+}
+
+#[allow(non_snake_case)]
+pub fn fromJSON<T: serde::de::DeserializeOwned>(json: String) -> T {
+    serde_json::from_str(&json).unwrap()
+}
@@ -0,0 +1,209 @@
+//! Runtime of `trusty:math`, pasted into programs that import it (or linked from `trusty-runtime`).
+
+pub const PI: f64 = std::f64::consts::PI;
+pub const E: f64 = std::f64::consts::E;
+
+pub trait __TrustMathAbs {
+    fn __trust_abs(self) -> Self;
+}
+
+impl __TrustMathAbs for i8 {
+    fn __trust_abs(self) -> Self { self.abs() }
+}
+impl __TrustMathAbs for i16 {
+    fn __trust_abs(self) -> Self { self.abs() }
+}
+impl __TrustMathAbs for i32 {
+    fn __trust_abs(self) -> Self { self.abs() }
+}
+impl __TrustMathAbs for i64 {
+    fn __trust_abs(self) -> Self { self.abs() }
+}
+impl __TrustMathAbs for isize {
+    fn __trust_abs(self) -> Self { self.abs() }
+}
+impl __TrustMathAbs for f32 {
+    fn __trust_abs(self) -> Self { self.abs() }
+}
+impl __TrustMathAbs for f64 {
+    fn __trust_abs(self) -> Self { self.abs() }
+}
+
+#[allow(non_snake_case)]
+pub fn sqrt<T: Into<f64>>(x: T) -> f64 {
+    x.into().sqrt()
+}
+
+#[allow(non_snake_case)]
+pub fn pow<A: Into<f64>, B: Into<f64>>(base: A, exp: B) -> f64 {
+    base.into().powf(exp.into())
+}
+
+#[allow(non_snake_case)]
+pub fn log<T: Into<f64>>(value: T) -> f64 {
+    value.into().ln()
+}
+
+#[allow(non_snake_case)]
+pub fn log_base<V: Into<f64>, B: Into<f64>>(value: V, base: B) -> f64 {
+    value.into().log(base.into())
+}
+
+#[allow(non_snake_case)]
+pub fn abs<T: __TrustMathAbs>(x: T) -> T {
+    x.__trust_abs()
+}
+
+#[allow(non_snake_case)]
+pub fn min<T: PartialOrd + Copy>(a: T, b: T) -> T {
+    if a <= b { a } else { b }
+}
+
+#[allow(non_snake_case)]
+pub fn max<T: PartialOrd + Copy>(a: T, b: T) -> T {
+    if a >= b { a } else { b }
+}
+
+#[allow(non_snake_case)]
+pub fn clamp<T: PartialOrd + Copy>(x: T, lo: T, hi: T) -> T {
+    if x < lo {
+        lo
+    } else if x > hi {
+        hi
+    } else {
+        x
+    }
+}
+
+#[allow(non_snake_case)]
+pub fn sin<T: Into<f64>>(x: T) -> f64 {
+    x.into().sin()
+}
+
+#[allow(non_snake_case)]
+pub fn cos<T: Into<f64>>(x: T) -> f64 {
+    x.into().cos()
+}
+
+#[allow(non_snake_case)]
+pub fn tan<T: Into<f64>>(x: T) -> f64 {
+    x.into().tan()
+}
+
+#[allow(non_snake_case)]
+pub fn asin<T: Into<f64>>(x: T) -> f64 {
+    x.into().asin()
+}
+
+#[allow(non_snake_case)]
+pub fn acos<T: Into<f64>>(x: T) -> f64 {
+    x.into().acos()
+}
+
+#[allow(non_snake_case)]
+pub fn atan<T: Into<f64>>(x: T) -> f64 {
+    x.into().atan()
+}
+
+#[allow(non_snake_case)]
+pub fn atan2<Y: Into<f64>, X: Into<f64>>(y: Y, x: X) -> f64 {
+    y.into().atan2(x.into())
+}
+
+#[allow(non_snake_case)]
+pub fn hypot<A: Into<f64>, B: Into<f64>>(a: A, b: B) -> f64 {
+    a.into().hypot(b.into())
+}
+
+#[allow(non_snake_case)]
+pub fn exp<T: Into<f64>>(x: T) -> f64 {
+    x.into().exp()
+}
+
+#[allow(non_snake_case)]
+pub fn log2<T: Into<f64>>(x: T) -> f64 {
+    x.into().log2()
+}
+
+#[allow(non_snake_case)]
+pub fn log10<T: Into<f64>>(x: T) -> f64 {
+    x.into().log10()
+}
+
+#[allow(non_snake_case)]
+pub fn floor<T: Into<f64>>(x: T) -> f64 {
+    x.into().floor()
+}
+
+#[allow(non_snake_case)]
+pub fn ceil<T: Into<f64>>(x: T) -> f64 {
+    x.into().ceil()
+}
+
+/// Halfway cases round away from zero (`round(2.5) == 3`, `round(-2.5) == -3`).
+#[allow(non_snake_case)]
+pub fn round<T: Into<f64>>(x: T) -> f64 {
+    x.into().round()
+}
+
+#[allow(non_snake_case)]
+pub fn trunc<T: Into<f64>>(x: T) -> f64 {
+    x.into().trunc()
+}
+
+/// `-1`, `0` or `1` in the argument's type; `NaN` stays `NaN`.
+#[allow(non_snake_case)]
+pub fn sign<T: PartialOrd + Default + From<i8> + Copy>(x: T) -> T {
+    let zero = T::default();
+    if x > zero {
+        T::from(1)
+    } else if x < zero {
+        T::from(-1)
+    } else {
+        x
+    }
+}
+
+#[allow(non_snake_case)]
+pub fn isNaN<T: Into<f64>>(x: T) -> bool {
+    x.into().is_nan()
+}
+
+#[allow(non_snake_case)]
+pub fn isFinite<T: Into<f64>>(x: T) -> bool {
+    x.into().is_finite()
+}
+
+/// Greatest common divisor, always non-negative; `gcd(0, 0) == 0`.
+#[allow(non_snake_case)]
+pub fn gcd<T: __TrustMathAbs + Copy + PartialEq + Default + std::ops::Rem<Output = T>>(a: T, b: T) -> T {
+    let (mut a, mut b) = (a.__trust_abs(), b.__trust_abs());
+    while b != T::default() {
+        let rest = a % b;
+        a = b;
+        b = rest;
+    }
+    a
+}
+
+/// Least common multiple, always non-negative; `0` when either argument is `0`.
+#[allow(non_snake_case)]
+pub fn lcm<T>(a: T, b: T) -> T
+where
+    T: __TrustMathAbs + Copy + PartialEq + Default + std::ops::Rem<Output = T> + std::ops::Div<Output = T> + std::ops::Mul<Output = T>,
+{
+    if a == T::default() || b == T::default() {
+        return T::default();
+    }
+    (a / gcd(a, b) * b).__trust_abs()
+}
+
+/// `n!` as an `int64`; panics when it does not fit (`n > 20`) or `n` is negative.
+#[allow(non_snake_case)]
+pub fn factorial<T: Into<i64>>(n: T) -> i64 {
+    let n = n.into();
+    assert!(n >= 0, "factorial of a negative number ({})", n);
+    (1..=n)
+        .try_fold(1i64, |product, k| product.checked_mul(k))
+        .unwrap_or_else(|| panic!("factorial({}) does not fit in int64", n))
+}
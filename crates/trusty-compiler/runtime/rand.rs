@@ -0,0 +1,159 @@
+//! Runtime of `trusty:rand`, pasted into programs that import it (or linked from `trusty-runtime`).
+//! Written against `rand` 0.9; the compiler rewrites the pasted text for 0.8.
+
+use rand::Rng as _;
+use rand::SeedableRng as _;
+use rand::distr::{weighted::WeightedIndex, Bernoulli, Distribution};
+use rand::seq::{IndexedRandom, SliceRandom};
+
+#[allow(non_snake_case)]
+pub fn random() -> f64 {
+    let mut rng = rand::rng();
+    rng.random::<f64>()
+}
+
+#[allow(non_snake_case)]
+pub fn randomInt(min: i32, max: i32) -> i32 {
+    let mut rng = rand::rng();
+    if min <= max {
+        rng.random_range(min..=max)
+    } else {
+        rng.random_range(max..=min)
+    }
+}
+
+#[allow(non_snake_case)]
+pub fn randomFloat(min: f64, max: f64) -> f64 {
+    let mut rng = rand::rng();
+    let lo = min.min(max);
+    let hi = min.max(max);
+    if (hi - lo).abs() < f64::EPSILON {
+        lo
+    } else {
+        rng.random_range(lo..hi)
+    }
+}
+
+#[allow(non_snake_case)]
+pub fn bernoulli(p: f64) -> bool {
+    let mut rng = rand::rng();
+    let prob = p.clamp(0.0, 1.0);
+    Bernoulli::new(prob).map(|d| d.sample(&mut rng)).unwrap_or(false)
+}
+
+#[allow(non_snake_case)]
+pub fn weightedIndex(weights: Vec<f64>) -> i32 {
+    let mut rng = rand::rng();
+    match WeightedIndex::new(weights) {
+        Ok(dist) => dist.sample(&mut rng) as i32,
+        Err(_) => -1,
+    }
+}
+
+#[allow(non_snake_case)]
+pub fn chooseOne<T: Clone>(items: Vec<T>) -> Option<T> {
+    let mut rng = rand::rng();
+    items.choose(&mut rng).cloned()
+}
+
+#[allow(non_snake_case)]
+pub fn shuffle<T: Clone>(items: Vec<T>) -> Vec<T> {
+    let mut rng = rand::rng();
+    let mut out = items.clone();
+    out.shuffle(&mut rng);
+    out
+}
+
+fn __trust_normal<R: rand::Rng + ?Sized>(rng: &mut R, mean: f64, std: f64) -> f64 {
+    // Box-Muller: `1 - u` keeps the logarithm finite.
+    let u: f64 = 1.0 - rng.random::<f64>();
+    let v: f64 = rng.random::<f64>();
+    mean + std * (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+}
+
+fn __trust_exponential<R: rand::Rng + ?Sized>(rng: &mut R, lambda: f64) -> f64 {
+    if lambda <= 0.0 {
+        return f64::NAN;
+    }
+    -(1.0 - rng.random::<f64>()).ln() / lambda
+}
+
+fn __trust_random_hex<R: rand::Rng + ?Sized>(rng: &mut R, length: i32) -> String {
+    (0..length.max(0)).map(|_| std::char::from_digit(rng.random_range(0..16), 16).unwrap()).collect()
+}
+
+#[allow(non_snake_case)]
+pub fn normal(mean: impl Into<f64>, std: impl Into<f64>) -> f64 {
+    __trust_normal(&mut rand::rng(), mean.into(), std.into())
+}
+
+#[allow(non_snake_case)]
+pub fn exponential(lambda: impl Into<f64>) -> f64 {
+    __trust_exponential(&mut rand::rng(), lambda.into())
+}
+
+#[allow(non_snake_case)]
+pub fn randomHex(length: i32) -> String {
+    __trust_random_hex(&mut rand::rng(), length)
+}
+
+/// A generator of its own; `Rng.withSeed(seed)` gives the same sequence on every run.
+pub struct Rng {
+    inner: std::cell::RefCell<rand::rngs::StdRng>,
+}
+
+impl Default for Rng {
+    fn default() -> Rng {
+        Rng::new()
+    }
+}
+
+#[allow(non_snake_case)]
+impl Rng {
+    pub fn new() -> Rng {
+        Rng { inner: std::cell::RefCell::new(rand::rngs::StdRng::from_os_rng()) }
+    }
+
+    pub fn withSeed(seed: impl Into<i64>) -> Rng {
+        Rng { inner: std::cell::RefCell::new(rand::rngs::StdRng::seed_from_u64(seed.into() as u64)) }
+    }
+
+    /// A float in `[0, 1)`.
+    pub fn next(&self) -> f64 {
+        self.inner.borrow_mut().random::<f64>()
+    }
+
+    pub fn float(&self) -> f64 {
+        self.next()
+    }
+
+    pub fn int(&self, min: i32, max: i32) -> i32 {
+        self.inner.borrow_mut().random_range(min.min(max)..=min.max(max))
+    }
+
+    pub fn bernoulli(&self, p: impl Into<f64>) -> bool {
+        self.next() < p.into().clamp(0.0, 1.0)
+    }
+
+    pub fn chooseOne<T: Clone>(&self, items: Vec<T>) -> Option<T> {
+        items.choose(&mut *self.inner.borrow_mut()).cloned()
+    }
+
+    pub fn shuffle<T: Clone>(&self, items: Vec<T>) -> Vec<T> {
+        let mut out = items;
+        out.shuffle(&mut *self.inner.borrow_mut());
+        out
+    }
+
+    pub fn normal(&self, mean: impl Into<f64>, std: impl Into<f64>) -> f64 {
+        __trust_normal(&mut *self.inner.borrow_mut(), mean.into(), std.into())
+    }
+
+    pub fn exponential(&self, lambda: impl Into<f64>) -> f64 {
+        __trust_exponential(&mut *self.inner.borrow_mut(), lambda.into())
+    }
+
+    pub fn randomHex(&self, length: i32) -> String {
+        __trust_random_hex(&mut *self.inner.borrow_mut(), length)
+    }
+}
@@ -0,0 +1,241 @@
+//! Runtime of `trusty:tcp`, pasted into programs that import it (or linked from `trusty-runtime`):
+//! TCP listeners and connections on `std::net`, for protocols beyond HTTP. Nothing panics: a
+//! listener or connection that could not be opened has `ok` false and the reason in `error`, and
+//! a read or write that fails leaves its reason in `lastError()`.
+
+use std::sync::{Arc, Mutex};
+
+/// A socket accepting TCP connections: `TcpListener.bind(7000)`.
+#[derive(Clone, Debug)]
+pub struct TcpListener {
+    pub ok: bool,
+    /// The port bound, also when `0` asked for a free one.
+    pub port: i32,
+    pub error: String,
+    inner: Option<Arc<std::net::TcpListener>>,
+}
+
+/// A TCP connection, from `TcpStream.connect(host, port)` or `listener.accept()`. Copies share
+/// the connection, so one can be handed to a thread while another reads.
+#[derive(Clone, Debug)]
+pub struct TcpStream {
+    pub ok: bool,
+    pub error: String,
+    /// `host:port` of the other end.
+    pub peer: String,
+    inner: Option<Arc<std::net::TcpStream>>,
+    /// Bytes received after the last line `readLine` returned.
+    pending: Arc<Mutex<Vec<u8>>>,
+    last_error: Arc<Mutex<String>>,
+    closed: Arc<std::sync::atomic::AtomicBool>,
+}
+
+#[allow(dead_code, non_snake_case)]
+impl TcpListener {
+    /// Listens on `0.0.0.0:port`.
+    pub fn bind(port: i32) -> TcpListener {
+        TcpListener::bindOn(format!("0.0.0.0:{}", port))
+    }
+
+    /// Listens on `address` (`"127.0.0.1:7000"`); port `0` picks a free one.
+    pub fn bindOn(address: String) -> TcpListener {
+        match std::net::TcpListener::bind(&address) {
+            Ok(listener) => TcpListener {
+                ok: true,
+                port: listener.local_addr().map(|addr| addr.port() as i32).unwrap_or(0),
+                error: String::new(),
+                inner: Some(Arc::new(listener)),
+            },
+            Err(e) => TcpListener { ok: false, port: 0, error: format!("cannot bind {}: {}", address, e), inner: None },
+        }
+    }
+
+    /// Waits for the next connection; its `ok` is false when none could be accepted.
+    pub fn accept(&self) -> TcpStream {
+        let Some(listener) = &self.inner else {
+            return TcpStream::failed(self.error.clone());
+        };
+        match listener.accept() {
+            Ok((stream, _)) => TcpStream::opened(stream),
+            Err(e) => TcpStream::failed(format!("cannot accept a connection: {}", e)),
+        }
+    }
+}
+
+#[allow(dead_code, non_snake_case)]
+impl TcpStream {
+    /// Connects to `host` (a name or an address) on `port`, waiting as long as the system does.
+    pub fn connect(host: String, port: i32) -> TcpStream {
+        TcpStream::connectTimeout(host, port, 0)
+    }
+
+    /// Like `connect`, giving up after `timeoutMs` milliseconds (`0`: no limit).
+    pub fn connectTimeout(host: String, port: i32, timeoutMs: i32) -> TcpStream {
+        if !(0..=65_535).contains(&port) {
+            return TcpStream::failed(format!("invalid port {}", port));
+        }
+        let port = port as u16;
+        let addresses = match std::net::ToSocketAddrs::to_socket_addrs(&(host.as_str(), port)) {
+            Ok(addresses) => addresses.collect::<Vec<_>>(),
+            Err(e) => return TcpStream::failed(format!("cannot resolve {}: {}", host, e)),
+        };
+        let mut error = format!("cannot resolve {}", host);
+        for address in addresses {
+            let connected = if timeoutMs > 0 {
+                std::net::TcpStream::connect_timeout(&address, std::time::Duration::from_millis(timeoutMs as u64))
+            } else {
+                std::net::TcpStream::connect(address)
+            };
+            match connected {
+                Ok(stream) => return TcpStream::opened(stream),
+                Err(e) => error = format!("cannot connect to {}:{}: {}", host, port, e),
+            }
+        }
+        TcpStream::failed(error)
+    }
+
+    fn opened(stream: std::net::TcpStream) -> TcpStream {
+        TcpStream {
+            ok: true,
+            error: String::new(),
+            peer: stream.peer_addr().map(|addr| addr.to_string()).unwrap_or_default(),
+            inner: Some(Arc::new(stream)),
+            pending: Arc::new(Mutex::new(Vec::new())),
+            last_error: Arc::new(Mutex::new(String::new())),
+            closed: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+        }
+    }
+
+    fn failed(error: String) -> TcpStream {
+        TcpStream {
+            ok: false,
+            error: error.clone(),
+            peer: String::new(),
+            inner: None,
+            pending: Arc::new(Mutex::new(Vec::new())),
+            last_error: Arc::new(Mutex::new(error)),
+            closed: Arc::new(std::sync::atomic::AtomicBool::new(true)),
+        }
+    }
+
+    /// How long a read or a write may wait, in milliseconds, before giving up with a "timed out"
+    /// `lastError()`; `0` (the default) waits for as long as it takes.
+    pub fn setTimeout(&self, ms: i32) -> bool {
+        let Some(stream) = &self.inner else {
+            return false;
+        };
+        let timeout = (ms > 0).then(|| std::time::Duration::from_millis(ms as u64));
+        self.check(stream.set_read_timeout(timeout).and_then(|_| stream.set_write_timeout(timeout)))
+            .is_some()
+    }
+
+    /// Sends `text` (UTF-8), all of it; `false` when the connection failed.
+    pub fn write(&self, text: String) -> bool {
+        self.writeBytes(text.into_bytes())
+    }
+
+    pub fn writeBytes(&self, data: Vec<u8>) -> bool {
+        let Some(stream) = &self.inner else {
+            return false;
+        };
+        self.check(std::io::Write::write_all(&mut &**stream, &data)).is_some()
+    }
+
+    /// What has arrived, up to 64 KiB, waiting for at least one byte: `""` once the other end has
+    /// closed the connection (`isClosed()`), or on a timeout.
+    pub fn read(&self) -> String {
+        String::from_utf8_lossy(&self.readBytes(65_536)).to_string()
+    }
+
+    /// Like `read`, at most `max` bytes of them, as they are.
+    pub fn readBytes(&self, max: i32) -> Vec<u8> {
+        let max = max.max(1) as usize;
+        if let Ok(mut pending) = self.pending.lock() {
+            if !pending.is_empty() {
+                let count = max.min(pending.len());
+                return pending.drain(..count).collect();
+            }
+        }
+        self.receive(max)
+    }
+
+    /// The next line, without its `\n` (or `\r\n`); `""` on a timeout. At the end of the
+    /// connection, the text left after the last line break, then `""`.
+    pub fn readLine(&self) -> String {
+        let Ok(mut pending) = self.pending.lock() else {
+            return String::new();
+        };
+        loop {
+            if let Some(end) = pending.iter().position(|byte| *byte == b'\n') {
+                let line: Vec<u8> = pending.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line[..end]).to_string();
+                return line.strip_suffix('\r').map(str::to_string).unwrap_or(line);
+            }
+            let received = self.receive(65_536);
+            if received.is_empty() && !self.isClosed() {
+                // Timed out: what arrived of the line waits for the next call.
+                return String::new();
+            }
+            if received.is_empty() {
+                let rest: Vec<u8> = pending.drain(..).collect();
+                return String::from_utf8_lossy(&rest).to_string();
+            }
+            pending.extend(received);
+        }
+    }
+
+    /// Whether the other end closed the connection (or `close()` was called, or it never opened).
+    pub fn isClosed(&self) -> bool {
+        self.closed.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    /// Shuts the connection down both ways; the other end reads `""`.
+    pub fn close(&self) {
+        if let Some(stream) = &self.inner {
+            let _ = stream.shutdown(std::net::Shutdown::Both);
+        }
+        self.closed.store(true, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    /// Why the last read or write failed, `""` when it did not.
+    pub fn lastError(&self) -> String {
+        self.last_error.lock().map(|error| error.clone()).unwrap_or_default()
+    }
+
+    /// Reads at most `max` bytes from the socket; none at the end of the connection or on an error.
+    fn receive(&self, max: usize) -> Vec<u8> {
+        let Some(stream) = &self.inner else {
+            return Vec::new();
+        };
+        if self.isClosed() {
+            return Vec::new();
+        }
+        let mut buffer = vec![0; max];
+        match self.check(std::io::Read::read(&mut &**stream, &mut buffer)) {
+            Some(0) => {
+                self.closed.store(true, std::sync::atomic::Ordering::SeqCst);
+                Vec::new()
+            }
+            Some(count) => {
+                buffer.truncate(count);
+                buffer
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// `result`'s value, recording its error (or that there was none) for `lastError()`.
+    fn check<T>(&self, result: std::io::Result<T>) -> Option<T> {
+        let error = match &result {
+            Ok(_) => String::new(),
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {
+                "timed out".to_string()
+            }
+            Err(e) => e.to_string(),
+        };
+        if let Ok(mut last) = self.last_error.lock() {
+            *last = error;
+        }
+        result.ok()
+    }
+}
@@ -0,0 +1,916 @@
+//! Runtime of `trusty:time`, pasted into programs that import it (or linked from `trusty-runtime`):
+//! dates, times, time zones as fixed offsets, stopwatches and timers, all on `std`.
+
+use std::time::{Instant, Duration, SystemTime as RustSystemTime};
+use std::thread::sleep;
+
+const TRUST_MILLIS_PER_SECOND: i64 = 1_000;
+const TRUST_MILLIS_PER_MINUTE: i64 = 60_000;
+const TRUST_MILLIS_PER_HOUR: i64 = 3_600_000;
+const TRUST_MILLIS_PER_DAY: i64 = 86_400_000;
+
+fn __trust_days_from_civil(year: i32, month: u32, day: u32) -> i64 {
+    let y = year as i64 - if month <= 2 { 1 } else { 0 };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let doy = (153 * (m + if m > 2 { -3 } else { 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn __trust_civil_from_days(days: i64) -> (i32, u32, u32) {
+    let z = days + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let y = yoe + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = mp + if mp < 10 { 3 } else { -9 };
+    let year = y + if month <= 2 { 1 } else { 0 };
+    (year as i32, month as u32, day as u32)
+}
+
+fn __trust_time_from_millis_of_day(millis: i64) -> Time {
+    let clamped = millis.clamp(0, TRUST_MILLIS_PER_DAY - 1);
+    let hour = (clamped / TRUST_MILLIS_PER_HOUR) as u32;
+    let minute = ((clamped % TRUST_MILLIS_PER_HOUR) / TRUST_MILLIS_PER_MINUTE) as u32;
+    let second = ((clamped % TRUST_MILLIS_PER_MINUTE) / TRUST_MILLIS_PER_SECOND) as u32;
+    let millisecond = (clamped % TRUST_MILLIS_PER_SECOND) as u32;
+    Time { hour, minute, second, millisecond }
+}
+
+fn __trust_system_time_to_millis(st: RustSystemTime) -> i64 {
+    match st.duration_since(RustSystemTime::UNIX_EPOCH) {
+        Ok(d) => d.as_millis() as i64,
+        Err(e) => -(e.duration().as_millis() as i64),
+    }
+}
+
+fn __trust_millis_to_system_time(ms: i64) -> RustSystemTime {
+    if ms >= 0 {
+        RustSystemTime::UNIX_EPOCH + Duration::from_millis(ms as u64)
+    } else {
+        RustSystemTime::UNIX_EPOCH - Duration::from_millis(ms.unsigned_abs())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+#[allow(non_snake_case)]
+impl Date {
+    pub fn fromYmd(year: i32, month: i32, day: i32) -> Date {
+        let month_u = month.clamp(1, 12) as u32;
+        let max_day = Date::daysInMonth(year, month_u as i32) as i32;
+        let day_u = day.clamp(1, max_day) as u32;
+        Date { year, month: month_u, day: day_u }
+    }
+
+    pub fn today() -> Date {
+        Date::fromSystemTime(RustSystemTime::now())
+    }
+
+    pub fn now() -> Date {
+        Date::today()
+    }
+
+    pub fn fromSystemTime(st: RustSystemTime) -> Date {
+        let millis = __trust_system_time_to_millis(st);
+        let days = millis.div_euclid(TRUST_MILLIS_PER_DAY);
+        let (year, month, day) = __trust_civil_from_days(days);
+        Date { year, month, day }
+    }
+
+    pub fn toSystemTime(&self) -> RustSystemTime {
+        let days = __trust_days_from_civil(self.year, self.month, self.day);
+        let millis = days.saturating_mul(TRUST_MILLIS_PER_DAY);
+        __trust_millis_to_system_time(millis)
+    }
+
+    pub fn toUnixDays(&self) -> i64 {
+        __trust_days_from_civil(self.year, self.month, self.day)
+    }
+
+    pub fn dayOfWeek(&self) -> i32 {
+        ((self.toUnixDays() + 4).rem_euclid(7)) as i32
+    }
+
+    pub fn isLeapYear(year: i32) -> bool {
+        (year % 4 == 0 && year % 100 != 0) || (year % 400 == 0)
+    }
+
+    pub fn daysInMonth(year: i32, month: i32) -> u32 {
+        match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 => if Date::isLeapYear(year) { 29 } else { 28 },
+            _ => 30,
+        }
+    }
+
+    pub fn addDays(&self, days: i32) -> Date {
+        let next_days = self.toUnixDays().saturating_add(days as i64);
+        let (year, month, day) = __trust_civil_from_days(next_days);
+        Date { year, month, day }
+    }
+
+    pub fn addMonths(&self, months: i32) -> Date {
+        let total_months = (self.year as i64)
+            .saturating_mul(12)
+            .saturating_add(self.month as i64 - 1)
+            .saturating_add(months as i64);
+        let new_year_i64 = total_months.div_euclid(12);
+        let new_month_i64 = total_months.rem_euclid(12) + 1;
+        let new_year = new_year_i64.clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+        let new_month = new_month_i64 as i32;
+        let max_day = Date::daysInMonth(new_year, new_month);
+        Date {
+            year: new_year,
+            month: new_month as u32,
+            day: self.day.min(max_day),
+        }
+    }
+
+    pub fn addYears(&self, years: i32) -> Date {
+        self.addMonths(years.saturating_mul(12))
+    }
+
+    pub fn subDays(&self, days: i32) -> Date {
+        self.addDays(days.saturating_neg())
+    }
+
+    pub fn subMonths(&self, months: i32) -> Date {
+        self.addMonths(months.saturating_neg())
+    }
+
+    pub fn subYears(&self, years: i32) -> Date {
+        self.addYears(years.saturating_neg())
+    }
+
+    pub fn compare(a: Date, b: Date) -> i32 {
+        use std::cmp::Ordering;
+        match a.cmp(&b) {
+            Ordering::Less => -1,
+            Ordering::Equal => 0,
+            Ordering::Greater => 1,
+        }
+    }
+
+    pub fn toString(&self) -> String {
+        format!("{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+
+    pub fn toIsoString(&self) -> String {
+        self.toString()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Time {
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub millisecond: u32,
+}
+
+#[allow(non_snake_case)]
+impl Time {
+    pub fn fromHms(hour: i32, minute: i32, second: i32) -> Time {
+        Time::fromHmsMilli(hour, minute, second, 0)
+    }
+
+    pub fn fromHmsMilli(hour: i32, minute: i32, second: i32, millisecond: i32) -> Time {
+        let mut total = (hour as i64).saturating_mul(TRUST_MILLIS_PER_HOUR);
+        total = total.saturating_add((minute as i64).saturating_mul(TRUST_MILLIS_PER_MINUTE));
+        total = total.saturating_add((second as i64).saturating_mul(TRUST_MILLIS_PER_SECOND));
+        total = total.saturating_add(millisecond as i64);
+        let normalized = total.rem_euclid(TRUST_MILLIS_PER_DAY);
+        __trust_time_from_millis_of_day(normalized)
+    }
+
+    pub fn midnight() -> Time {
+        Time { hour: 0, minute: 0, second: 0, millisecond: 0 }
+    }
+
+    pub fn now() -> Time {
+        Time::fromSystemTime(RustSystemTime::now())
+    }
+
+    pub fn fromSystemTime(st: RustSystemTime) -> Time {
+        let millis = __trust_system_time_to_millis(st);
+        let day_millis = millis.rem_euclid(TRUST_MILLIS_PER_DAY);
+        __trust_time_from_millis_of_day(day_millis)
+    }
+
+    pub fn toMillisOfDay(&self) -> i64 {
+        (self.hour as i64).saturating_mul(TRUST_MILLIS_PER_HOUR)
+            .saturating_add((self.minute as i64).saturating_mul(TRUST_MILLIS_PER_MINUTE))
+            .saturating_add((self.second as i64).saturating_mul(TRUST_MILLIS_PER_SECOND))
+            .saturating_add(self.millisecond as i64)
+    }
+
+    pub fn addSeconds(&self, seconds: i32) -> Time {
+        let delta = (seconds as i64).saturating_mul(TRUST_MILLIS_PER_SECOND);
+        let normalized = self.toMillisOfDay().saturating_add(delta).rem_euclid(TRUST_MILLIS_PER_DAY);
+        __trust_time_from_millis_of_day(normalized)
+    }
+
+    pub fn addMinutes(&self, minutes: i32) -> Time {
+        self.addSeconds(minutes.saturating_mul(60))
+    }
+
+    pub fn addHours(&self, hours: i32) -> Time {
+        self.addMinutes(hours.saturating_mul(60))
+    }
+
+    pub fn subSeconds(&self, seconds: i32) -> Time {
+        self.addSeconds(seconds.saturating_neg())
+    }
+
+    pub fn subMinutes(&self, minutes: i32) -> Time {
+        self.addMinutes(minutes.saturating_neg())
+    }
+
+    pub fn subHours(&self, hours: i32) -> Time {
+        self.addHours(hours.saturating_neg())
+    }
+
+    pub fn compare(a: Time, b: Time) -> i32 {
+        use std::cmp::Ordering;
+        match a.cmp(&b) {
+            Ordering::Less => -1,
+            Ordering::Equal => 0,
+            Ordering::Greater => 1,
+        }
+    }
+
+    pub fn toString(&self) -> String {
+        format!("{:02}:{:02}:{:02}.{:03}", self.hour, self.minute, self.second, self.millisecond)
+    }
+
+    pub fn toIsoString(&self) -> String {
+        self.toString()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct DateTime {
+    pub date: Date,
+    pub time: Time,
+}
+
+#[allow(non_snake_case)]
+impl DateTime {
+    pub fn fromParts(date: Date, time: Time) -> DateTime {
+        DateTime { date, time }
+    }
+
+    pub fn now() -> DateTime {
+        DateTime::fromSystemTime(RustSystemTime::now())
+    }
+
+    pub fn fromSystemTime(st: RustSystemTime) -> DateTime {
+        DateTime { date: Date::fromSystemTime(st), time: Time::fromSystemTime(st) }
+    }
+
+    pub fn toSystemTime(&self) -> RustSystemTime {
+        __trust_millis_to_system_time(self.toTimestampMillis())
+    }
+
+    pub fn fromTimestampMillis(ms: i64) -> DateTime {
+        let days = ms.div_euclid(TRUST_MILLIS_PER_DAY);
+        let day_millis = ms.rem_euclid(TRUST_MILLIS_PER_DAY);
+        let (year, month, day) = __trust_civil_from_days(days);
+        DateTime {
+            date: Date { year, month, day },
+            time: __trust_time_from_millis_of_day(day_millis),
+        }
+    }
+
+    pub fn toTimestampMillis(&self) -> i64 {
+        let days = self.date.toUnixDays();
+        days.saturating_mul(TRUST_MILLIS_PER_DAY)
+            .saturating_add(self.time.toMillisOfDay())
+    }
+
+    pub fn addSeconds(&self, seconds: i32) -> DateTime {
+        let delta = (seconds as i64).saturating_mul(TRUST_MILLIS_PER_SECOND);
+        DateTime::fromTimestampMillis(self.toTimestampMillis().saturating_add(delta))
+    }
+
+    pub fn addMinutes(&self, minutes: i32) -> DateTime {
+        self.addSeconds(minutes.saturating_mul(60))
+    }
+
+    pub fn addHours(&self, hours: i32) -> DateTime {
+        self.addMinutes(hours.saturating_mul(60))
+    }
+
+    pub fn addDays(&self, days: i32) -> DateTime {
+        let delta = (days as i64).saturating_mul(TRUST_MILLIS_PER_DAY);
+        DateTime::fromTimestampMillis(self.toTimestampMillis().saturating_add(delta))
+    }
+
+    pub fn addMonths(&self, months: i32) -> DateTime {
+        DateTime {
+            date: self.date.addMonths(months),
+            time: self.time,
+        }
+    }
+
+    pub fn addYears(&self, years: i32) -> DateTime {
+        self.addMonths(years.saturating_mul(12))
+    }
+
+    pub fn subSeconds(&self, seconds: i32) -> DateTime {
+        self.addSeconds(seconds.saturating_neg())
+    }
+
+    pub fn subMinutes(&self, minutes: i32) -> DateTime {
+        self.addMinutes(minutes.saturating_neg())
+    }
+
+    pub fn subHours(&self, hours: i32) -> DateTime {
+        self.addHours(hours.saturating_neg())
+    }
+
+    pub fn subDays(&self, days: i32) -> DateTime {
+        self.addDays(days.saturating_neg())
+    }
+
+    pub fn subMonths(&self, months: i32) -> DateTime {
+        self.addMonths(months.saturating_neg())
+    }
+
+    pub fn subYears(&self, years: i32) -> DateTime {
+        self.addYears(years.saturating_neg())
+    }
+
+    pub fn startOfDay(&self) -> DateTime {
+        DateTime { date: self.date, time: Time::midnight() }
+    }
+
+    pub fn endOfDay(&self) -> DateTime {
+        DateTime {
+            date: self.date,
+            time: Time::fromHmsMilli(23, 59, 59, 999),
+        }
+    }
+
+    pub fn compare(a: DateTime, b: DateTime) -> i32 {
+        use std::cmp::Ordering;
+        match a.cmp(&b) {
+            Ordering::Less => -1,
+            Ordering::Equal => 0,
+            Ordering::Greater => 1,
+        }
+    }
+
+    pub fn toString(&self) -> String {
+        format!("{}T{}Z", self.date.toString(), self.time.toString())
+    }
+
+    pub fn toIsoString(&self) -> String {
+        self.toString()
+    }
+}
+
+/// Pattern tokens, longest first: `YYYY-MM-DD HH:mm:ss.SSS`; any other character is literal.
+const TRUST_TIME_PATTERN_TOKENS: [&str; 11] = ["YYYY", "SSS", "YY", "MM", "DD", "HH", "mm", "ss", "M", "D", "H"];
+
+fn __trust_pattern_token(pattern: &str) -> Option<&'static str> {
+    TRUST_TIME_PATTERN_TOKENS.iter().copied().find(|token| pattern.starts_with(token))
+}
+
+fn __trust_format_pattern(value: DateTime, pattern: &str) -> String {
+    let (date, time) = (value.date, value.time);
+    let mut out = String::new();
+    let mut rest = pattern;
+    while let Some(c) = rest.chars().next() {
+        let Some(token) = __trust_pattern_token(rest) else {
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        };
+        out.push_str(&match token {
+            "YYYY" => format!("{:04}", date.year),
+            "YY" => format!("{:02}", date.year.rem_euclid(100)),
+            "MM" => format!("{:02}", date.month),
+            "M" => date.month.to_string(),
+            "DD" => format!("{:02}", date.day),
+            "D" => date.day.to_string(),
+            "HH" => format!("{:02}", time.hour),
+            "H" => time.hour.to_string(),
+            "mm" => format!("{:02}", time.minute),
+            "ss" => format!("{:02}", time.second),
+            _ => format!("{:03}", time.millisecond),
+        });
+        rest = &rest[token.len()..];
+    }
+    out
+}
+
+/// `text` read with `pattern`; fields the pattern leaves out default to 1970-01-01 00:00:00.000.
+fn __trust_parse_pattern(text: &str, pattern: &str) -> Option<DateTime> {
+    let (mut year, mut month, mut day) = (1970i64, 1i64, 1i64);
+    let (mut hour, mut minute, mut second, mut millisecond) = (0i64, 0i64, 0i64, 0i64);
+    let mut input = text;
+    let mut rest = pattern;
+    while let Some(c) = rest.chars().next() {
+        let Some(token) = __trust_pattern_token(rest) else {
+            input = input.strip_prefix(c)?;
+            rest = &rest[c.len_utf8()..];
+            continue;
+        };
+        let (min_digits, max_digits) = match token {
+            "YYYY" => (4, 4),
+            "SSS" => (3, 3),
+            "M" | "D" | "H" => (1, 2),
+            _ => (2, 2),
+        };
+        let digits = input.bytes().take(max_digits).take_while(u8::is_ascii_digit).count();
+        if digits < min_digits {
+            return None;
+        }
+        let value: i64 = input[..digits].parse().ok()?;
+        input = &input[digits..];
+        match token {
+            "YYYY" => year = value,
+            "YY" => year = 2000 + value,
+            "MM" | "M" => month = value,
+            "DD" | "D" => day = value,
+            "HH" | "H" => hour = value,
+            "mm" => minute = value,
+            "ss" => second = value,
+            _ => millisecond = value,
+        }
+        rest = &rest[token.len()..];
+    }
+    if !input.is_empty()
+        || !(1..=12).contains(&month)
+        || day < 1
+        || day > Date::daysInMonth(year as i32, month as i32) as i64
+        || hour > 23
+        || minute > 59
+        || second > 59
+    {
+        return None;
+    }
+    Some(DateTime {
+        date: Date { year: year as i32, month: month as u32, day: day as u32 },
+        time: Time { hour: hour as u32, minute: minute as u32, second: second as u32, millisecond: millisecond as u32 },
+    })
+}
+
+/// Anything `format(value, pattern)` and `durationBetween(a, b)` accept.
+#[allow(non_snake_case)]
+pub trait __TrustTimePoint {
+    fn toDateTime(&self) -> DateTime;
+}
+
+impl __TrustTimePoint for Date {
+    fn toDateTime(&self) -> DateTime {
+        DateTime { date: *self, time: Time::midnight() }
+    }
+}
+
+impl __TrustTimePoint for Time {
+    fn toDateTime(&self) -> DateTime {
+        DateTime { date: Date { year: 1970, month: 1, day: 1 }, time: *self }
+    }
+}
+
+impl __TrustTimePoint for DateTime {
+    fn toDateTime(&self) -> DateTime {
+        *self
+    }
+}
+
+#[allow(non_snake_case)]
+impl Date {
+    pub fn parse(text: impl AsRef<str>, pattern: impl AsRef<str>) -> Option<Date> {
+        __trust_parse_pattern(text.as_ref(), pattern.as_ref()).map(|value| value.date)
+    }
+
+    pub fn parseIso(text: impl AsRef<str>) -> Option<Date> {
+        Date::parse(text.as_ref().trim(), "YYYY-MM-DD")
+    }
+
+    pub fn format(&self, pattern: impl AsRef<str>) -> String {
+        __trust_format_pattern(self.toDateTime(), pattern.as_ref())
+    }
+}
+
+#[allow(non_snake_case)]
+impl Time {
+    pub fn parse(text: impl AsRef<str>, pattern: impl AsRef<str>) -> Option<Time> {
+        __trust_parse_pattern(text.as_ref(), pattern.as_ref()).map(|value| value.time)
+    }
+
+    pub fn format(&self, pattern: impl AsRef<str>) -> String {
+        __trust_format_pattern(self.toDateTime(), pattern.as_ref())
+    }
+}
+
+#[allow(non_snake_case)]
+impl DateTime {
+    pub fn parse(text: impl AsRef<str>, pattern: impl AsRef<str>) -> Option<DateTime> {
+        __trust_parse_pattern(text.as_ref(), pattern.as_ref())
+    }
+
+    /// `2026-03-01`, `2026-03-01T10:30`, `2026-03-01T10:30:15.250Z`, `2026-03-01 10:30:15+02:00`, ...;
+    /// an offset converts the result to UTC.
+    pub fn parseIso(text: impl AsRef<str>) -> Option<DateTime> {
+        let (wall, offset) = __trust_parse_iso(text.as_ref())?;
+        Some(wall.subMinutes(offset.unwrap_or(0)))
+    }
+
+    pub fn format(&self, pattern: impl AsRef<str>) -> String {
+        __trust_format_pattern(*self, pattern.as_ref())
+    }
+}
+
+/// An ISO 8601 date or date-time: its wall-clock value and UTC offset in minutes, if it has one.
+fn __trust_parse_iso(text: &str) -> Option<(DateTime, Option<i32>)> {
+    let text = text.trim();
+    let Some(split) = text.find(['T', 't', ' ']) else {
+        return Date::parseIso(text).map(|date| (date.toDateTime(), None));
+    };
+    let date = Date::parseIso(&text[..split])?;
+    let clock = &text[split + 1..];
+
+    let (clock, offset) = match clock.find(['Z', 'z', '+', '-']) {
+        Some(at) => (&clock[..at], Some(__trust_parse_offset(&clock[at..])?)),
+        None => (clock, None),
+    };
+    let (clock, fraction) = match clock.split_once('.') {
+        Some((clock, fraction)) => (clock, Some(fraction)),
+        None => (clock, None),
+    };
+    let mut time = Time::parse(clock, "HH:mm:ss").or_else(|| Time::parse(clock, "HH:mm"))?;
+    if let Some(fraction) = fraction {
+        if fraction.is_empty() || fraction.len() > 9 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        time.millisecond = format!("{:0<3}", &fraction[..fraction.len().min(3)]).parse().ok()?;
+    }
+    Some((DateTime { date, time }, offset))
+}
+
+/// `Z`, `UTC`, `+02:00`, `+0200`, `-05`, `UTC+5:30`, ... → minutes east of UTC.
+fn __trust_parse_offset(text: &str) -> Option<i32> {
+    let text = text.trim();
+    let rest = text.strip_prefix("UTC").or_else(|| text.strip_prefix("GMT")).unwrap_or(text);
+    if rest.is_empty() || rest.eq_ignore_ascii_case("z") {
+        return Some(0);
+    }
+    let (sign, digits) = match rest.as_bytes()[0] {
+        b'+' => (1, &rest[1..]),
+        b'-' => (-1, &rest[1..]),
+        _ => return None,
+    };
+    let offset = ["HH:mm", "HHmm", "H:mm", "H"]
+        .iter()
+        .find_map(|pattern| Time::parse(digits, pattern))?;
+    let minutes = (offset.hour * 60 + offset.minute) as i32;
+    if minutes > 18 * 60 {
+        return None;
+    }
+    Some(sign * minutes)
+}
+
+/// An instant and the UTC offset (in minutes) it is shown in. Comparisons look at the instant
+/// only, so `10:00+02:00 == 08:00Z`.
+#[derive(Clone, Copy, Debug)]
+pub struct ZonedDateTime {
+    pub utc: DateTime,
+    pub offset: i32,
+}
+
+impl PartialEq for ZonedDateTime {
+    fn eq(&self, other: &ZonedDateTime) -> bool {
+        self.utc == other.utc
+    }
+}
+
+impl Eq for ZonedDateTime {}
+
+impl PartialOrd for ZonedDateTime {
+    fn partial_cmp(&self, other: &ZonedDateTime) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ZonedDateTime {
+    fn cmp(&self, other: &ZonedDateTime) -> std::cmp::Ordering {
+        self.utc.cmp(&other.utc)
+    }
+}
+
+impl __TrustTimePoint for ZonedDateTime {
+    fn toDateTime(&self) -> DateTime {
+        self.utc
+    }
+}
+
+/// The system's current UTC offset, read once from `date +%z` (which follows `TZ`); UTC where
+/// that is unavailable.
+fn __trust_local_offset() -> i32 {
+    static OFFSET: std::sync::OnceLock<i32> = std::sync::OnceLock::new();
+    *OFFSET.get_or_init(|| {
+        std::process::Command::new("date")
+            .arg("+%z")
+            .output()
+            .ok()
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+            .and_then(|text| __trust_parse_offset(&text))
+            .unwrap_or(0)
+    })
+}
+
+#[allow(non_snake_case)]
+impl ZonedDateTime {
+    pub fn fromUtc(utc: DateTime, offsetMinutes: i32) -> ZonedDateTime {
+        ZonedDateTime { utc, offset: offsetMinutes }
+    }
+
+    /// `local` is the wall-clock time at `offsetMinutes` east of UTC.
+    pub fn fromLocal(local: DateTime, offsetMinutes: i32) -> ZonedDateTime {
+        ZonedDateTime { utc: local.subMinutes(offsetMinutes), offset: offsetMinutes }
+    }
+
+    pub fn now() -> ZonedDateTime {
+        ZonedDateTime::fromUtc(DateTime::now(), __trust_local_offset())
+    }
+
+    pub fn nowUtc() -> ZonedDateTime {
+        ZonedDateTime::fromUtc(DateTime::now(), 0)
+    }
+
+    /// Like `DateTime.parseIso`, keeping the offset; text without one is UTC.
+    pub fn parseIso(text: impl AsRef<str>) -> Option<ZonedDateTime> {
+        let (wall, offset) = __trust_parse_iso(text.as_ref())?;
+        Some(ZonedDateTime::fromLocal(wall, offset.unwrap_or(0)))
+    }
+
+    /// Minutes east of UTC for `Z`, `UTC`, `+02:00`, `-0530`, `UTC+1`, ...
+    pub fn parseOffset(text: impl AsRef<str>) -> Option<i32> {
+        __trust_parse_offset(text.as_ref())
+    }
+
+    pub fn offsetMinutes(&self) -> i32 {
+        self.offset
+    }
+
+    /// The same instant shown at another offset.
+    pub fn withOffset(&self, offsetMinutes: i32) -> ZonedDateTime {
+        ZonedDateTime::fromUtc(self.utc, offsetMinutes)
+    }
+
+    pub fn toUtc(&self) -> ZonedDateTime {
+        self.withOffset(0)
+    }
+
+    pub fn toLocal(&self) -> ZonedDateTime {
+        self.withOffset(__trust_local_offset())
+    }
+
+    /// Wall-clock date and time at this offset.
+    pub fn localDateTime(&self) -> DateTime {
+        self.utc.addMinutes(self.offset)
+    }
+
+    pub fn addSeconds(&self, seconds: i32) -> ZonedDateTime {
+        ZonedDateTime::fromUtc(self.utc.addSeconds(seconds), self.offset)
+    }
+
+    pub fn addMinutes(&self, minutes: i32) -> ZonedDateTime {
+        ZonedDateTime::fromUtc(self.utc.addMinutes(minutes), self.offset)
+    }
+
+    pub fn addHours(&self, hours: i32) -> ZonedDateTime {
+        ZonedDateTime::fromUtc(self.utc.addHours(hours), self.offset)
+    }
+
+    pub fn addDays(&self, days: i32) -> ZonedDateTime {
+        ZonedDateTime::fromUtc(self.utc.addDays(days), self.offset)
+    }
+
+    pub fn subSeconds(&self, seconds: i32) -> ZonedDateTime {
+        self.addSeconds(seconds.saturating_neg())
+    }
+
+    pub fn subMinutes(&self, minutes: i32) -> ZonedDateTime {
+        self.addMinutes(minutes.saturating_neg())
+    }
+
+    pub fn subHours(&self, hours: i32) -> ZonedDateTime {
+        self.addHours(hours.saturating_neg())
+    }
+
+    pub fn subDays(&self, days: i32) -> ZonedDateTime {
+        self.addDays(days.saturating_neg())
+    }
+
+    pub fn compare(a: ZonedDateTime, b: ZonedDateTime) -> i32 {
+        use std::cmp::Ordering;
+        match a.cmp(&b) {
+            Ordering::Less => -1,
+            Ordering::Equal => 0,
+            Ordering::Greater => 1,
+        }
+    }
+
+    /// The wall-clock time at this offset.
+    pub fn format(&self, pattern: impl AsRef<str>) -> String {
+        __trust_format_pattern(self.localDateTime(), pattern.as_ref())
+    }
+
+    pub fn toString(&self) -> String {
+        let local = self.localDateTime();
+        let offset = if self.offset == 0 {
+            "Z".to_string()
+        } else {
+            let sign = if self.offset < 0 { '-' } else { '+' };
+            format!("{}{:02}:{:02}", sign, self.offset.abs() / 60, self.offset.abs() % 60)
+        };
+        format!("{}T{}{}", local.date.toString(), local.time.toString(), offset)
+    }
+
+    pub fn toIsoString(&self) -> String {
+        self.toString()
+    }
+}
+
+/// Measures elapsed time from `Stopwatch.start()`, with laps in between.
+#[derive(Debug)]
+pub struct Stopwatch {
+    started: std::cell::Cell<Instant>,
+    last_lap: std::cell::Cell<Instant>,
+}
+
+#[allow(non_snake_case)]
+impl Stopwatch {
+    pub fn start() -> Stopwatch {
+        let now = Instant::now();
+        Stopwatch { started: std::cell::Cell::new(now), last_lap: std::cell::Cell::new(now) }
+    }
+
+    /// Time since the previous lap (or the start), starting a new lap.
+    pub fn lap(&self) -> Duration {
+        let now = Instant::now();
+        now.duration_since(self.last_lap.replace(now))
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.get().elapsed()
+    }
+
+    pub fn elapsedMillis(&self) -> i64 {
+        self.elapsed().as_millis() as i64
+    }
+
+    pub fn reset(&self) {
+        let now = Instant::now();
+        self.started.set(now);
+        self.last_lap.set(now);
+    }
+}
+
+/// Returned by `setTimeout` and `setInterval`: `cancel()` stops the timer, `wait()` blocks until
+/// it has finished (a timeout ran or was cancelled, an interval was cancelled).
+#[derive(Clone)]
+pub struct TimerHandle {
+    cancelled: std::sync::Arc<(std::sync::Mutex<bool>, std::sync::Condvar)>,
+    thread: std::sync::Arc<std::sync::Mutex<Option<std::thread::JoinHandle<()>>>>,
+}
+
+#[allow(non_snake_case)]
+impl TimerHandle {
+    fn spawn(body: impl FnOnce(&(std::sync::Mutex<bool>, std::sync::Condvar)) + Send + 'static) -> TimerHandle {
+        let cancelled = std::sync::Arc::new((std::sync::Mutex::new(false), std::sync::Condvar::new()));
+        let state = cancelled.clone();
+        let thread = std::thread::spawn(move || body(&state));
+        TimerHandle { cancelled, thread: std::sync::Arc::new(std::sync::Mutex::new(Some(thread))) }
+    }
+
+    pub fn cancel(&self) {
+        let (flag, wake) = &*self.cancelled;
+        *flag.lock().unwrap() = true;
+        wake.notify_all();
+    }
+
+    pub fn isCancelled(&self) -> bool {
+        *self.cancelled.0.lock().unwrap()
+    }
+
+    pub fn wait(&self) {
+        if let Some(thread) = self.thread.lock().unwrap().take() {
+            thread.join().ok();
+        }
+    }
+}
+
+/// Sleeps `ms` unless cancelled first; true when the time ran out.
+fn __trust_timer_sleep(state: &(std::sync::Mutex<bool>, std::sync::Condvar), ms: i32) -> bool {
+    let (flag, wake) = state;
+    let timeout = Duration::from_millis(ms.max(0) as u64);
+    let (cancelled, _) = wake.wait_timeout_while(flag.lock().unwrap(), timeout, |cancelled| !*cancelled).unwrap();
+    !*cancelled
+}
+
+pub type SystemTime = DateTime;
+
+#[allow(non_snake_case)]
+pub fn compare(a: DateTime, b: DateTime) -> i32 {
+    DateTime::compare(a, b)
+}
+
+#[allow(non_snake_case)]
+pub fn addSeconds(dateTime: DateTime, seconds: i32) -> DateTime {
+    dateTime.addSeconds(seconds)
+}
+
+#[allow(non_snake_case)]
+pub fn addMinutes(dateTime: DateTime, minutes: i32) -> DateTime {
+    dateTime.addMinutes(minutes)
+}
+
+#[allow(non_snake_case)]
+pub fn addDays(dateTime: DateTime, days: i32) -> DateTime {
+    dateTime.addDays(days)
+}
+
+#[allow(non_snake_case)]
+pub fn addMonths(dateTime: DateTime, months: i32) -> DateTime {
+    dateTime.addMonths(months)
+}
+
+#[allow(non_snake_case)]
+pub fn addYears(dateTime: DateTime, years: i32) -> DateTime {
+    dateTime.addYears(years)
+}
+
+#[allow(non_snake_case)]
+pub fn subSeconds(dateTime: DateTime, seconds: i32) -> DateTime {
+    dateTime.subSeconds(seconds)
+}
+
+#[allow(non_snake_case)]
+pub fn subMinutes(dateTime: DateTime, minutes: i32) -> DateTime {
+    dateTime.subMinutes(minutes)
+}
+
+#[allow(non_snake_case)]
+pub fn subDays(dateTime: DateTime, days: i32) -> DateTime {
+    dateTime.subDays(days)
+}
+
+#[allow(non_snake_case)]
+pub fn subMonths(dateTime: DateTime, months: i32) -> DateTime {
+    dateTime.subMonths(months)
+}
+
+#[allow(non_snake_case)]
+pub fn subYears(dateTime: DateTime, years: i32) -> DateTime {
+    dateTime.subYears(years)
+}
+
+#[allow(non_snake_case)]
+pub fn format<T: __TrustTimePoint>(value: T, pattern: impl AsRef<str>) -> String {
+    __trust_format_pattern(value.toDateTime(), pattern.as_ref())
+}
+
+/// Time from `a` to `b`, or back when `b` comes first (a `Duration` is never negative).
+#[allow(non_snake_case)]
+pub fn durationBetween<A: __TrustTimePoint, B: __TrustTimePoint>(a: A, b: B) -> Duration {
+    let millis = b.toDateTime().toTimestampMillis() - a.toDateTime().toTimestampMillis();
+    Duration::from_millis(millis.unsigned_abs())
+}
+
+/// Runs `callback` once after `ms` milliseconds on its own thread.
+#[allow(non_snake_case)]
+pub fn setTimeout<F: FnOnce() + Send + 'static>(callback: F, ms: i32) -> TimerHandle {
+    TimerHandle::spawn(move |state| {
+        if __trust_timer_sleep(state, ms) {
+            callback();
+        }
+    })
+}
+
+/// Runs `callback` every `ms` milliseconds on its own thread until cancelled.
+#[allow(non_snake_case)]
+pub fn setInterval<F: FnMut() + Send + 'static>(mut callback: F, ms: i32) -> TimerHandle {
+    TimerHandle::spawn(move |state| {
+        while __trust_timer_sleep(state, ms) {
+            callback();
+        }
+    })
+}
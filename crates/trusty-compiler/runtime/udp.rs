@@ -0,0 +1,135 @@
+//! Runtime of `trusty:udp`, pasted into programs that import it (or linked from `trusty-runtime`):
+//! UDP sockets on `std::net`, sending and receiving datagrams. Nothing panics: a socket that could
+//! not be bound has `ok` false and the reason in `error`, and a send or receive that fails leaves
+//! its reason in `lastError()`.
+
+use std::sync::{Arc, Mutex};
+
+/// A UDP socket: `UdpSocket.bind(9000)` to receive on a known port, `UdpSocket.bind(0)` to only send.
+#[derive(Clone, Debug)]
+pub struct UdpSocket {
+    pub ok: bool,
+    /// The port bound, also when `0` asked for a free one.
+    pub port: i32,
+    pub error: String,
+    inner: Option<Arc<std::net::UdpSocket>>,
+    last_error: Arc<Mutex<String>>,
+}
+
+/// A datagram `receive` got; `ok` is false when none arrived (see `lastError()`).
+#[derive(Clone, Debug, Default)]
+pub struct Datagram {
+    pub ok: bool,
+    /// The payload as UTF-8 text.
+    pub text: String,
+    pub bytes: Vec<u8>,
+    /// Address and port of the sender, to answer with `sendTo`.
+    pub host: String,
+    pub port: i32,
+}
+
+#[allow(dead_code, non_snake_case)]
+impl UdpSocket {
+    /// Binds `0.0.0.0:port`.
+    pub fn bind(port: i32) -> UdpSocket {
+        UdpSocket::bindOn(format!("0.0.0.0:{}", port))
+    }
+
+    /// Binds `address` (`"127.0.0.1:9000"`); port `0` picks a free one.
+    pub fn bindOn(address: String) -> UdpSocket {
+        match std::net::UdpSocket::bind(&address) {
+            Ok(socket) => UdpSocket {
+                ok: true,
+                port: socket.local_addr().map(|addr| addr.port() as i32).unwrap_or(0),
+                error: String::new(),
+                inner: Some(Arc::new(socket)),
+                last_error: Arc::new(Mutex::new(String::new())),
+            },
+            Err(e) => {
+                let error = format!("cannot bind {}: {}", address, e);
+                UdpSocket { ok: false, port: 0, error: error.clone(), inner: None, last_error: Arc::new(Mutex::new(error)) }
+            }
+        }
+    }
+
+    /// Sends `text` (UTF-8) as one datagram to `host` on `port`; `false` when it could not be sent.
+    pub fn sendTo(&self, text: String, host: String, port: i32) -> bool {
+        self.sendBytesTo(text.into_bytes(), host, port)
+    }
+
+    pub fn sendBytesTo(&self, data: Vec<u8>, host: String, port: i32) -> bool {
+        let Some(socket) = &self.inner else {
+            return false;
+        };
+        if !(0..=65_535).contains(&port) {
+            self.set_last_error(format!("invalid port {}", port));
+            return false;
+        }
+        let port = port as u16;
+        match socket.send_to(&data, (host.as_str(), port)) {
+            Ok(sent) if sent == data.len() => self.set_last_error(String::new()),
+            Ok(sent) => {
+                self.set_last_error(format!("only {} of {} bytes sent", sent, data.len()));
+                return false;
+            }
+            Err(e) => {
+                self.set_last_error(format!("cannot send to {}:{}: {}", host, port, e));
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Waits for the next datagram (up to 64 KiB), for as long as `setTimeout` allows.
+    pub fn receive(&self) -> Datagram {
+        let Some(socket) = &self.inner else {
+            return Datagram::default();
+        };
+        let mut buffer = vec![0; 65_536];
+        match socket.recv_from(&mut buffer) {
+            Ok((count, from)) => {
+                self.set_last_error(String::new());
+                buffer.truncate(count);
+                Datagram {
+                    ok: true,
+                    text: String::from_utf8_lossy(&buffer).to_string(),
+                    bytes: buffer,
+                    host: from.ip().to_string(),
+                    port: from.port() as i32,
+                }
+            }
+            Err(e) => {
+                self.set_last_error(match e.kind() {
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => "timed out".to_string(),
+                    _ => e.to_string(),
+                });
+                Datagram::default()
+            }
+        }
+    }
+
+    /// How long `receive` waits, in milliseconds; `0` (the default) waits until a datagram arrives.
+    pub fn setTimeout(&self, ms: i32) -> bool {
+        let Some(socket) = &self.inner else {
+            return false;
+        };
+        let timeout = (ms > 0).then(|| std::time::Duration::from_millis(ms as u64));
+        socket.set_read_timeout(timeout).is_ok() && socket.set_write_timeout(timeout).is_ok()
+    }
+
+    /// Allows sending to broadcast addresses such as `255.255.255.255`.
+    pub fn setBroadcast(&self, enabled: bool) -> bool {
+        self.inner.as_ref().is_some_and(|socket| socket.set_broadcast(enabled).is_ok())
+    }
+
+    /// Why the last send or receive failed, `""` when it did not.
+    pub fn lastError(&self) -> String {
+        self.last_error.lock().map(|error| error.clone()).unwrap_or_default()
+    }
+
+    fn set_last_error(&self, error: String) {
+        if let Ok(mut last) = self.last_error.lock() {
+            *last = error;
+        }
+    }
+}
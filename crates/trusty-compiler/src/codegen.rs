@@ -1,7 +1,11 @@
+#[cfg(feature = "fs")]
 use anyhow::Result;
+#[cfg(feature = "fs")]
 use std::fs;
+#[cfg(feature = "fs")]
 use std::path::Path;
 
+#[cfg(feature = "fs")]
 pub fn write_rust_file(rust_code: &str, output_path: &Path) -> Result<()> {
     fs::write(output_path, rust_code)?;
     Ok(())
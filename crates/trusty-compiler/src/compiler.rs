@@ -0,0 +1,422 @@
+use crate::diagnostics::{Diagnostic, Severity, Span};
+use crate::fix::{Fix, TextEdit};
+use crate::messages::{msg, Locale};
+use crate::modules::{self, LoaderResolver, ModuleResolver};
+use crate::transpiler::locations::{line_map, LineMapping};
+use crate::transpiler::overflow::Overflow;
+use crate::transpiler::ownership::AutoClone;
+use crate::transpiler::TranspileOutput;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Outputs a caller can ask [`Compiler`] to keep in [`CompileOutput`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Artifact {
+    /// Generated Rust source (selected by default).
+    Rust,
+    /// Source after TRUST keyword rewrites, as handed to the TypeScript parser.
+    Preprocessed,
+    /// Generated Rust lines mapped to TRUST statements; compiles with location tracking.
+    LineMap,
+}
+
+/// Options that change how source is compiled.
+#[derive(Clone, Debug, Default)]
+pub struct CompileOptions {
+    /// Report every warning as an error.
+    pub deny_warnings: bool,
+    /// When owned arguments are cloned at call sites so the caller can keep using them.
+    pub auto_clone: AutoClone,
+    /// Report a program without a `main` function (or top-level statements) as an error.
+    pub require_main: bool,
+    /// Record the TRUST location of each statement at runtime, so panics and thrown messages
+    /// name the TRUST file and line instead of the generated Rust.
+    pub track_locations: bool,
+    /// Give every enum `values()`, `fromString(s)` and `count()`.
+    pub enum_utilities: bool,
+    /// What integer `+`, `-` and `*` do on overflow (`compilerOptions.overflow`).
+    pub overflow: Overflow,
+    /// Traits derived by every struct and enum besides `Debug` and `Clone` (`compilerOptions.derive`).
+    pub derives: Vec<String>,
+    /// `(crate, version requirement)` pairs from `trusty.json` `dependencies`; `trusty:*` modules
+    /// generate code for the versions pinned here.
+    pub dependencies: Vec<(String, String)>,
+    /// Link `trusty:*` modules against the `trusty-runtime` crate instead of pasting their code
+    /// into the output (`compilerOptions.runtimeCrate`).
+    pub runtime_crate: bool,
+    /// Language of the diagnostics' messages (`compilerOptions.locale`); their codes stay the same.
+    pub locale: Locale,
+    /// Compile constructs TRUST cannot translate to placeholders, with a warning, instead of
+    /// failing (`--allow-unknown`); the placeholders do not compile as Rust.
+    pub allow_unknown: bool,
+}
+
+/// Reads source files for [`Compiler::compile_file`]; the simple form of a [`ModuleResolver`].
+///
+/// Implemented for closures, so an editor can serve unsaved buffers:
+/// `Compiler::new().file_loader(|path: &Path| buffers.get(path).cloned().ok_or(...))`.
+pub trait FileLoader {
+    fn load(&self, path: &Path) -> std::io::Result<String>;
+}
+
+impl<F> FileLoader for F
+where
+    F: Fn(&Path) -> std::io::Result<String>,
+{
+    fn load(&self, path: &Path) -> std::io::Result<String> {
+        self(path)
+    }
+}
+
+/// Result of a [`Compiler`] run. Artifacts are `None` when not selected or when compilation failed.
+#[derive(Debug, Default)]
+pub struct CompileOutput {
+    pub rust_code: Option<String>,
+    pub preprocessed: Option<String>,
+    pub line_map: Option<Vec<LineMapping>>,
+    /// External crate names required by the program (from `trusty:*` imports).
+    pub required_crates: Vec<String>,
+    /// `(crate, version)` the `trusty:*` modules were generated for; Cargo.toml should use these
+    /// unless the project pins its own.
+    pub crate_versions: Vec<(String, String)>,
+    /// Features of the `trusty_runtime` crate the program needs (see [`CompileOptions::runtime_crate`]).
+    pub runtime_features: Vec<String>,
+    pub diagnostics: Vec<Diagnostic>,
+    /// Time spent in each phase that ran: `preprocess`, `parse`, `transpile`. Empty on wasm32.
+    pub timings: Vec<(&'static str, Duration)>,
+}
+
+/// Start of a timed phase. `Instant::now()` panics on wasm32-unknown-unknown (the playground),
+/// where phases are not timed; `clippy.toml` keeps every other clock out of the crate.
+struct Stopwatch(Option<Instant>);
+
+impl Stopwatch {
+    #[cfg(not(target_arch = "wasm32"))]
+    #[allow(clippy::disallowed_methods)]
+    fn start() -> Self {
+        Self(Some(Instant::now()))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn start() -> Self {
+        Self(None)
+    }
+
+    /// Records the time since [`Stopwatch::start`] as `phase`'s.
+    fn record(self, phase: &'static str, output: &mut CompileOutput) {
+        if let Some(started) = self.0 {
+            output.timings.push((phase, started.elapsed()));
+        }
+    }
+}
+
+impl CompileOutput {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(Diagnostic::is_error)
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter().filter(|d| d.is_error())
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter().filter(|d| !d.is_error())
+    }
+
+    /// Converts to the `compile_full` result shape; errors are joined into one message.
+    pub fn into_result(self) -> anyhow::Result<TranspileOutput> {
+        if self.has_errors() {
+            let messages: Vec<&str> = self.errors().map(|d| d.message.as_str()).collect();
+            anyhow::bail!("{}", messages.join("\n"));
+        }
+        Ok(TranspileOutput {
+            rust_code: self.rust_code.unwrap_or_default(),
+            required_crates: self.required_crates,
+            crate_versions: self.crate_versions,
+            runtime_features: self.runtime_features,
+            warnings: self.diagnostics,
+        })
+    }
+}
+
+/// Embeddable entry point to the TRUST compiler.
+///
+/// ```
+/// use trusty_compiler::{Artifact, Compiler};
+///
+/// let output = Compiler::new()
+///     .artifacts(&[Artifact::Rust, Artifact::Preprocessed])
+///     .compile("function main() {\n    val x = 1;\n    console.write(x);\n}");
+/// assert!(!output.has_errors());
+/// assert!(output.rust_code.unwrap().contains("fn main()"));
+/// assert!(output.preprocessed.unwrap().contains("let x = 1;"));
+/// ```
+pub struct Compiler {
+    options: CompileOptions,
+    artifacts: Vec<Artifact>,
+    resolver: Option<Box<dyn ModuleResolver>>,
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Self {
+            options: CompileOptions::default(),
+            artifacts: vec![Artifact::Rust],
+            resolver: None,
+        }
+    }
+
+    pub fn options(mut self, options: CompileOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn deny_warnings(mut self, deny: bool) -> Self {
+        self.options.deny_warnings = deny;
+        self
+    }
+
+    pub fn auto_clone(mut self, mode: AutoClone) -> Self {
+        self.options.auto_clone = mode;
+        self
+    }
+
+    /// Requires an entry point, as when building an executable.
+    pub fn require_main(mut self, require: bool) -> Self {
+        self.options.require_main = require;
+        self
+    }
+
+    /// Injects runtime location tracking (see [`CompileOptions::track_locations`]).
+    pub fn track_locations(mut self, track: bool) -> Self {
+        self.options.track_locations = track;
+        self
+    }
+
+    /// Generates enum utilities (see [`CompileOptions::enum_utilities`]).
+    pub fn enum_utilities(mut self, enable: bool) -> Self {
+        self.options.enum_utilities = enable;
+        self
+    }
+
+    /// Lowers integer arithmetic for `mode` (see [`CompileOptions::overflow`]).
+    pub fn overflow(mut self, mode: Overflow) -> Self {
+        self.options.overflow = mode;
+        self
+    }
+
+    /// Derives `traits` on every struct and enum (see [`CompileOptions::derives`]).
+    pub fn derives<S: Into<String>>(mut self, traits: impl IntoIterator<Item = S>) -> Self {
+        self.options.derives = traits.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Crate versions the project pins (see [`CompileOptions::dependencies`]).
+    pub fn dependencies<N: Into<String>, V: Into<String>>(
+        mut self,
+        dependencies: impl IntoIterator<Item = (N, V)>,
+    ) -> Self {
+        self.options.dependencies = dependencies
+            .into_iter()
+            .map(|(name, version)| (name.into(), version.into()))
+            .collect();
+        self
+    }
+
+    /// Links `trusty:*` modules against the runtime crate (see [`CompileOptions::runtime_crate`]).
+    pub fn runtime_crate(mut self, link: bool) -> Self {
+        self.options.runtime_crate = link;
+        self
+    }
+
+    /// Writes diagnostics in `locale` (see [`CompileOptions::locale`]).
+    pub fn locale(mut self, locale: Locale) -> Self {
+        self.options.locale = locale;
+        self
+    }
+
+    /// Warns about unsupported constructs instead of failing (see [`CompileOptions::allow_unknown`]).
+    pub fn allow_unknown(mut self, allow: bool) -> Self {
+        self.options.allow_unknown = allow;
+        self
+    }
+
+    /// Replaces the set of artifacts kept in the output (default: `[Artifact::Rust]`).
+    pub fn artifacts(mut self, artifacts: &[Artifact]) -> Self {
+        self.artifacts = artifacts.to_vec();
+        self
+    }
+
+    /// Reads files through `loader` instead of the filesystem.
+    pub fn file_loader(self, loader: impl FileLoader + 'static) -> Self {
+        self.resolver(LoaderResolver(loader))
+    }
+
+    /// Resolves and reads local modules through `resolver` (default: [`modules::FsResolver`]).
+    pub fn resolver(mut self, resolver: impl ModuleResolver + 'static) -> Self {
+        self.resolver = Some(Box::new(resolver));
+        self
+    }
+
+    /// Bundles `entry` with every local module it imports, dependencies first.
+    pub fn bundle(&self, entry: &Path) -> anyhow::Result<String> {
+        if let Some(resolver) = &self.resolver {
+            return modules::bundle_modules(entry, resolver.as_ref());
+        }
+        #[cfg(feature = "fs")]
+        {
+            modules::bundle_modules(entry, &modules::FsResolver)
+        }
+        #[cfg(not(feature = "fs"))]
+        {
+            anyhow::bail!("No module resolver configured to read {}", entry.display())
+        }
+    }
+
+    /// Bundles `entry` (see [`Compiler::bundle`]) and compiles the result.
+    pub fn compile_file(&self, entry: &Path) -> CompileOutput {
+        match self.bundle(entry) {
+            Ok(source) => self.compile(&source),
+            Err(err) => CompileOutput {
+                diagnostics: vec![Diagnostic::error("module-error", format!("{:#}", err))],
+                ..CompileOutput::default()
+            },
+        }
+    }
+
+    pub fn compile(&self, source: &str) -> CompileOutput {
+        let mut output = CompileOutput::default();
+        self.run(source, &mut output);
+        for diag in &mut output.diagnostics {
+            diag.localize(self.options.locale);
+            if self.options.deny_warnings {
+                diag.severity = Severity::Error;
+            }
+        }
+        if output.has_errors() {
+            output.rust_code = None;
+        }
+        output
+    }
+
+    fn run(&self, source: &str, output: &mut CompileOutput) {
+        // One scan of the source serves these checks and the preprocessor.
+        let started = Stopwatch::start();
+        let tokens = crate::preprocessor::tokenize(source);
+        let first =
+            |needle| crate::preprocessor::identifier_offsets(source, &tokens, needle).next();
+        if let Some(offset) = first("while") {
+            output.diagnostics.push(
+                Diagnostic::error("unsupported-while", msg!("unsupported-while"))
+                    .with_span(Span::new(offset, offset + "while".len()))
+                    .with_fix(Fix::rename(
+                        "Replace `while` with `loop`",
+                        source,
+                        "while",
+                        "loop",
+                    )),
+            );
+            return;
+        }
+        let classes = crate::preprocessor::class_declarations(source, &tokens);
+        if !classes.is_empty() {
+            for class in classes {
+                let mut diag =
+                    Diagnostic::error("unsupported-class", msg!("unsupported-class", class.name))
+                        .with_span(class.span);
+                if let Some((span, rewrite)) = class.rewrite {
+                    let title = format!(
+                        "Rewrite `class {}` as `struct` and `implements`",
+                        class.name
+                    );
+                    diag = diag.with_fix(Fix::new(title, vec![TextEdit::new(span, rewrite)]));
+                }
+                output.diagnostics.push(diag);
+            }
+            return;
+        }
+        let unterminated = tokens.iter().find(|token| {
+            let text = &source[token.span.start..token.span.end];
+            token.kind == crate::preprocessor::TokenKind::Str
+                && text.starts_with('r')
+                && crate::preprocessor::raw_string_body(text).is_none()
+        });
+        if let Some(token) = unterminated {
+            output.diagnostics.push(
+                Diagnostic::error("unterminated-raw-string", msg!("unterminated-raw-string"))
+                    .with_span(Span::new(token.span.start, token.span.start + 2)),
+            );
+            return;
+        }
+        if let Some(offset) = first("number") {
+            output.diagnostics.push(
+                Diagnostic::warning("deprecated-number-alias", msg!("deprecated-number-alias"))
+                    .with_span(Span::new(offset, offset + "number".len()))
+                    .with_fix(Fix::rename(
+                        "Replace `number` with `int32`",
+                        source,
+                        "number",
+                        "int32",
+                    )),
+            );
+        }
+
+        let preprocessed = crate::preprocessor::preprocess_tokens(source, tokens);
+        started.record("preprocess", output);
+        if self.artifacts.contains(&Artifact::Preprocessed) {
+            output.preprocessed = Some(preprocessed.text.clone());
+        }
+        // Parse and transpile errors point into the preprocessed text; report them in `source`.
+        let located = |mut diag: Diagnostic| {
+            diag.span = diag.span.map(|span| preprocessed.original_span(span));
+            diag
+        };
+
+        let started = Stopwatch::start();
+        let parsed = crate::parser::parse_module(&preprocessed.text);
+        started.record("parse", output);
+        let module = match parsed {
+            Ok(module) => module,
+            Err(diag) => {
+                output.diagnostics.push(located(*diag));
+                return;
+            }
+        };
+
+        let mut options = self.options.clone();
+        if self.artifacts.contains(&Artifact::LineMap) {
+            options.track_locations = true;
+        }
+        let started = Stopwatch::start();
+        let transpiled = crate::transpiler::transpile_to_rust(&module, &preprocessed, &options);
+        started.record("transpile", output);
+        match transpiled {
+            Ok(transpiled) => {
+                output.required_crates = transpiled.required_crates;
+                output.crate_versions = transpiled.crate_versions;
+                output.runtime_features = transpiled.runtime_features;
+                output
+                    .diagnostics
+                    .extend(transpiled.warnings.into_iter().map(located));
+                if self.artifacts.contains(&Artifact::LineMap) {
+                    output.line_map = Some(line_map(&transpiled.rust_code));
+                }
+                if self.artifacts.contains(&Artifact::Rust) {
+                    output.rust_code = Some(transpiled.rust_code);
+                }
+            }
+            Err(err) => match err.downcast::<Diagnostic>() {
+                Ok(diag) => output.diagnostics.push(located(diag)),
+                Err(err) => output
+                    .diagnostics
+                    .push(Diagnostic::error("transpile-error", format!("{:#}", err))),
+            },
+        }
+    }
+}
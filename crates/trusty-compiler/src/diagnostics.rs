@@ -0,0 +1,149 @@
+use crate::fix::Fix;
+use crate::messages::{Locale, Message};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// Byte range `start..end` in the source passed to the compiler.
+///
+/// Parse and transpile errors are found in the preprocessed text and mapped back, so a span inside
+/// rewritten syntax (`match`, `and`/`or`) covers the TRUST construct it came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// 1-based `(line, column)` of `start` in `source`.
+    pub fn line_col(&self, source: &str) -> (usize, usize) {
+        let upto = &source[..self.start.min(source.len())];
+        let line = upto.matches('\n').count() + 1;
+        let column = upto
+            .rsplit('\n')
+            .next()
+            .map(|l| l.chars().count())
+            .unwrap_or(0)
+            + 1;
+        (line, column)
+    }
+}
+
+/// A compiler message with a stable `code` (e.g. `deprecated-number-alias`) tools can match on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    /// The text in the locale of the last [`Diagnostic::localize`], English until then.
+    pub message: String,
+    pub span: Option<Span>,
+    /// Machine-applicable fix, when the problem has a mechanical solution.
+    pub fix: Option<Fix>,
+    text: Box<Message>,
+}
+
+impl Diagnostic {
+    pub fn error(code: &'static str, message: impl Into<Message>) -> Self {
+        let text = message.into();
+        Self {
+            severity: Severity::Error,
+            code,
+            message: text.text(Locale::En),
+            span: None,
+            fix: None,
+            text: Box::new(text),
+        }
+    }
+
+    pub fn warning(code: &'static str, message: impl Into<Message>) -> Self {
+        let text = message.into();
+        Self {
+            severity: Severity::Warning,
+            code,
+            message: text.text(Locale::En),
+            span: None,
+            fix: None,
+            text: Box::new(text),
+        }
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn with_fix(mut self, fix: Fix) -> Self {
+        self.fix = Some(fix);
+        self
+    }
+
+    /// Rewrites `message` in `locale`; text outside the catalog stays as it is.
+    pub fn localize(&mut self, locale: Locale) {
+        self.message = self.text.text(locale);
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}[{}]: {}",
+            self.severity.as_str(),
+            self.code,
+            self.message
+        )
+    }
+}
+
+/// Lets the transpiler `bail!` with a located diagnostic; [`crate::Compiler`] reports it as-is.
+impl std::error::Error for Diagnostic {}
+
+/// A known name within two edits of `key`, ignoring case.
+pub(crate) fn closest<'k>(key: &str, known: &[&'k str]) -> Option<&'k str> {
+    let key = key.to_lowercase();
+    known
+        .iter()
+        .map(|name| (distance(&key, &name.to_lowercase()), *name))
+        .filter(|(d, _)| *d <= 2)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, name)| name)
+}
+
+/// Levenshtein distance between `a` and `b`.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = (previous + usize::from(ca != *cb))
+                .min(row[j] + 1)
+                .min(current + 1);
+            previous = current;
+        }
+    }
+    row[b.len()]
+}
@@ -0,0 +1,110 @@
+//! Machine-applicable fixes: text edits attached to a [`Diagnostic`](crate::Diagnostic), applied by
+//! `trusty lint --fix` and offered by the LSP as quick fixes.
+
+use crate::diagnostics::Span;
+
+/// Replaces the bytes of `span` in the original source with `replacement` (empty span = insert).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextEdit {
+    pub span: Span,
+    pub replacement: String,
+}
+
+impl TextEdit {
+    pub fn new(span: Span, replacement: impl Into<String>) -> Self {
+        Self {
+            span,
+            replacement: replacement.into(),
+        }
+    }
+}
+
+/// A set of edits applied together, with a title for editors (e.g. "Replace `number` with `int32`").
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fix {
+    pub title: String,
+    pub edits: Vec<TextEdit>,
+}
+
+impl Fix {
+    pub fn new(title: impl Into<String>, edits: Vec<TextEdit>) -> Self {
+        Self {
+            title: title.into(),
+            edits,
+        }
+    }
+
+    /// Replaces every code occurrence (outside strings and comments) of identifier `name` with `replacement`.
+    pub fn rename(title: impl Into<String>, source: &str, name: &str, replacement: &str) -> Self {
+        let edits = crate::find_identifiers_in_code(source, name)
+            .into_iter()
+            .map(|offset| TextEdit::new(Span::new(offset, offset + name.len()), replacement))
+            .collect();
+        Self::new(title, edits)
+    }
+
+    /// Renames type `name` to `replacement` where it is used as a type (see
+    /// [`type_name_offsets`](crate::preprocessor::type_name_offsets)), leaving fields and
+    /// variables of the same spelling alone.
+    pub fn rename_type(
+        title: impl Into<String>,
+        source: &str,
+        name: &str,
+        replacement: &str,
+    ) -> Self {
+        let tokens = crate::preprocessor::tokenize(source);
+        let edits = crate::preprocessor::type_name_offsets(source, &tokens, name)
+            .into_iter()
+            .map(|offset| TextEdit::new(Span::new(offset, offset + name.len()), replacement))
+            .collect();
+        Self::new(title, edits)
+    }
+}
+
+/// Applies `fixes` to `source`; returns the new text and how many fixes were applied.
+///
+/// A fix whose edits overlap one already taken is skipped whole (running again picks it up);
+/// edits identical to taken ones are shared, so two diagnostics may carry the same fix.
+pub fn apply_fixes<'a>(source: &str, fixes: impl IntoIterator<Item = &'a Fix>) -> (String, usize) {
+    let mut taken: Vec<&TextEdit> = Vec::new();
+    let mut applied = 0;
+    for fix in fixes {
+        let fresh: Vec<&TextEdit> = fix
+            .edits
+            .iter()
+            .filter(|edit| !taken.contains(edit))
+            .collect();
+        let overlaps = fresh.iter().enumerate().any(|(i, edit)| {
+            taken
+                .iter()
+                .chain(&fresh[..i])
+                .any(|other| conflicts(edit, other))
+        });
+        let in_bounds = fresh.iter().all(|edit| {
+            edit.span.start <= edit.span.end
+                && source.is_char_boundary(edit.span.start)
+                && source.is_char_boundary(edit.span.end)
+        });
+        if overlaps || !in_bounds {
+            continue;
+        }
+        taken.extend(fresh);
+        applied += 1;
+    }
+
+    taken.sort_by_key(|edit| (edit.span.start, edit.span.end));
+    let mut output = String::with_capacity(source.len());
+    let mut cursor = 0;
+    for edit in taken {
+        output.push_str(&source[cursor..edit.span.start]);
+        output.push_str(&edit.replacement);
+        cursor = edit.span.end;
+    }
+    output.push_str(&source[cursor..]);
+    (output, applied)
+}
+
+/// Two edits conflict when their spans overlap or both start at the same byte (e.g. two inserts).
+fn conflicts(a: &TextEdit, b: &TextEdit) -> bool {
+    a.span.start == b.span.start || a.span.start < b.span.end && b.span.start < a.span.end
+}
@@ -1,10 +1,31 @@
 pub mod codegen;
+pub mod compiler;
+pub mod diagnostics;
+pub mod fix;
+pub mod lint;
+pub mod manifest;
+pub mod messages;
+pub mod modules;
 pub mod parser;
+pub mod preprocessor;
 pub mod stdlib;
 pub mod transpiler;
-
-use anyhow::{bail, Result};
-
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+use anyhow::Result;
+
+pub use compiler::{Artifact, CompileOptions, CompileOutput, Compiler, FileLoader};
+pub use diagnostics::{Diagnostic, Severity, Span};
+pub use fix::{apply_fixes, Fix, TextEdit};
+pub use lint::LintConfig;
+pub use messages::Locale;
+#[cfg(feature = "fs")]
+pub use modules::FsResolver;
+pub use modules::{MemoryResolver, ModuleResolver};
+pub use transpiler::locations::LineMapping;
+pub use transpiler::overflow::Overflow;
+pub use transpiler::ownership::AutoClone;
 pub use transpiler::TranspileOutput;
 
 /// Transpile TRUST source to Rust source code.
@@ -13,731 +34,19 @@ pub fn compile(source: &str) -> Result<String> {
 }
 
 /// Transpile TRUST source and return Rust code + required external crates.
+/// Warnings are printed to stderr; use [`Compiler`] to collect them as diagnostics instead.
 pub fn compile_full(source: &str) -> Result<TranspileOutput> {
-    reject_unsupported_while(source)?;
-    warn_on_deprecated_number_alias(source);
-    let preprocessed = preprocess(source);
-    let ast = parser::parse_typescript(&preprocessed)?;
-    transpiler::transpile_to_rust(&ast)
-}
-
-fn warn_on_deprecated_number_alias(source: &str) {
-    if !contains_identifier_in_code(source, "number") {
-        return;
-    }
-    eprintln!("⚠️  Deprecated type alias `number` detected. Prefer `int` (or `int32`) / `float`.");
-}
-
-fn reject_unsupported_while(source: &str) -> Result<()> {
-    if contains_identifier_in_code(source, "while") {
-        bail!("`while` is not supported in TRUST. Use `loop (condition) {{ ... }}` instead.");
-    }
-    Ok(())
-}
-
-fn contains_identifier_in_code(source: &str, needle: &str) -> bool {
-    let mut ident = String::new();
-    let chars: Vec<char> = source.chars().collect();
-    let mut i = 0usize;
-    let mut in_single = false;
-    let mut in_double = false;
-    let mut in_template = false;
-
-    while i < chars.len() {
-        let ch = chars[i];
-        let next = if i + 1 < chars.len() {
-            Some(chars[i + 1])
-        } else {
-            None
-        };
-
-        if in_single {
-            if ch == '\\' && next.is_some() {
-                i += 2;
-                continue;
-            }
-            if ch == '\'' {
-                in_single = false;
-            }
-            i += 1;
-            continue;
-        }
-        if in_double {
-            if ch == '\\' && next.is_some() {
-                i += 2;
-                continue;
-            }
-            if ch == '"' {
-                in_double = false;
-            }
-            i += 1;
-            continue;
-        }
-        if in_template {
-            if ch == '\\' && next.is_some() {
-                i += 2;
-                continue;
-            }
-            if ch == '`' {
-                in_template = false;
-            }
-            i += 1;
-            continue;
-        }
-
-        if ch == '/' && next == Some('/') {
-            i += 2;
-            while i < chars.len() && chars[i] != '\n' {
-                i += 1;
-            }
-            continue;
-        }
-        if ch == '/' && next == Some('*') {
-            i += 2;
-            while i + 1 < chars.len() && !(chars[i] == '*' && chars[i + 1] == '/') {
-                i += 1;
-            }
-            i += 2;
-            continue;
-        }
-        if ch == '\'' {
-            in_single = true;
-            i += 1;
-            continue;
-        }
-        if ch == '"' {
-            in_double = true;
-            i += 1;
-            continue;
-        }
-        if ch == '`' {
-            in_template = true;
-            i += 1;
-            continue;
-        }
-
-        if ch.is_ascii_alphanumeric() || ch == '_' {
-            ident.push(ch);
-        } else {
-            if ident == needle {
-                return true;
-            }
-            ident.clear();
-        }
-        i += 1;
-    }
-
-    ident == needle
-}
-
-/// Rewrite TRUST-specific keywords to valid TypeScript before SWC parsing.
-fn preprocess(source: &str) -> String {
-    rewrite_word_boolean_ops(&rewrite_val_declarations(&rewrite_implements_blocks(&rewrite_match_blocks(source))))
-        .replace("struct ", "interface ")
-        .lines()
-        .map(|line| {
-            let trimmed = line.trim_start();
-            // `wait expr;` → `(expr).join().unwrap();`
-            if let Some(rest) = trimmed.strip_prefix("wait ") {
-                let indent = &line[..line.len() - trimmed.len()];
-                let rest = rest.trim_end_matches(';').trim();
-                format!("{}({}).join().unwrap();", indent, rest)
-            } else {
-                line.to_string()
-            }
-        })
-        .collect::<Vec<_>>()
-        .join("\n")
-}
-
-fn rewrite_match_blocks(source: &str) -> String {
-    fn is_ident_char(c: char) -> bool {
-        c.is_ascii_alphanumeric() || c == '_'
-    }
-
-    let chars: Vec<char> = source.chars().collect();
-    let mut out = String::with_capacity(source.len());
-    let mut i = 0usize;
-    let mut match_id = 0usize;
-
-    while i < chars.len() {
-        let can_start = i + 5 <= chars.len()
-            && chars[i..i + 5].iter().collect::<String>() == "match"
-            && (i == 0 || !is_ident_char(chars[i - 1]))
-            && (i + 5 >= chars.len() || !is_ident_char(chars[i + 5]));
-
-        if !can_start {
-            out.push(chars[i]);
-            i += 1;
-            continue;
-        }
-
-        let mut j = i + 5;
-        while j < chars.len() && chars[j].is_whitespace() {
-            j += 1;
-        }
-        if j >= chars.len() || chars[j] != '(' {
-            out.push(chars[i]);
-            i += 1;
-            continue;
-        }
-
-        let Some(subject_end) = find_matching(&chars, j, '(', ')') else {
-            out.push(chars[i]);
-            i += 1;
-            continue;
-        };
-        let subject = chars[j + 1..subject_end].iter().collect::<String>().trim().to_string();
-
-        let mut k = subject_end + 1;
-        while k < chars.len() && chars[k].is_whitespace() {
-            k += 1;
-        }
-        if k >= chars.len() || chars[k] != '{' {
-            out.push(chars[i]);
-            i += 1;
-            continue;
-        }
-
-        let Some(body_end) = find_matching(&chars, k, '{', '}') else {
-            out.push(chars[i]);
-            i += 1;
-            continue;
-        };
-
-        let body = chars[k + 1..body_end].iter().collect::<String>();
-        if let Some(rewritten) = build_match_expr(&subject, &body, match_id) {
-            out.push_str(&rewritten);
-            match_id += 1;
-            i = body_end + 1;
-            continue;
-        }
-
-        out.push(chars[i]);
-        i += 1;
-    }
-
-    out
-}
-
-fn find_matching(chars: &[char], open_pos: usize, open: char, close: char) -> Option<usize> {
-    let mut depth = 0i32;
-    let mut i = open_pos;
-    let mut in_single = false;
-    let mut in_double = false;
-    let mut in_template = false;
-    while i < chars.len() {
-        let c = chars[i];
-        let next = if i + 1 < chars.len() { Some(chars[i + 1]) } else { None };
-
-        if in_single {
-            if c == '\\' {
-                i += 2;
-                continue;
-            }
-            if c == '\'' {
-                in_single = false;
-            }
-            i += 1;
-            continue;
-        }
-        if in_double {
-            if c == '\\' {
-                i += 2;
-                continue;
-            }
-            if c == '"' {
-                in_double = false;
-            }
-            i += 1;
-            continue;
-        }
-        if in_template {
-            if c == '\\' {
-                i += 2;
-                continue;
-            }
-            if c == '`' {
-                in_template = false;
-            }
-            i += 1;
-            continue;
-        }
-
-        if c == '\'' {
-            in_single = true;
-            i += 1;
-            continue;
-        }
-        if c == '"' {
-            in_double = true;
-            i += 1;
-            continue;
-        }
-        if c == '`' {
-            in_template = true;
-            i += 1;
-            continue;
-        }
-
-        if c == '/' && next == Some('/') {
-            while i < chars.len() && chars[i] != '\n' {
-                i += 1;
-            }
-            continue;
-        }
-        if c == '/' && next == Some('*') {
-            i += 2;
-            while i + 1 < chars.len() && !(chars[i] == '*' && chars[i + 1] == '/') {
-                i += 1;
-            }
-            i += 2;
-            continue;
-        }
-
-        if c == open {
-            depth += 1;
-        } else if c == close {
-            depth -= 1;
-            if depth == 0 {
-                return Some(i);
-            }
-        }
-        i += 1;
-    }
-    None
-}
-
-fn split_top_level_csv(input: &str) -> Vec<String> {
-    let chars: Vec<char> = input.chars().collect();
-    let mut parts = Vec::new();
-    let mut start = 0usize;
-    let mut i = 0usize;
-    let mut par = 0i32;
-    let mut brk = 0i32;
-    let mut brc = 0i32;
-    let mut in_single = false;
-    let mut in_double = false;
-    let mut in_template = false;
-
-    while i < chars.len() {
-        let c = chars[i];
-        let next = if i + 1 < chars.len() { Some(chars[i + 1]) } else { None };
-
-        if in_single {
-            if c == '\\' {
-                i += 2;
-                continue;
-            }
-            if c == '\'' {
-                in_single = false;
-            }
-            i += 1;
-            continue;
-        }
-        if in_double {
-            if c == '\\' {
-                i += 2;
-                continue;
-            }
-            if c == '"' {
-                in_double = false;
-            }
-            i += 1;
-            continue;
-        }
-        if in_template {
-            if c == '\\' {
-                i += 2;
-                continue;
-            }
-            if c == '`' {
-                in_template = false;
-            }
-            i += 1;
-            continue;
-        }
-
-        if c == '/' && next == Some('/') {
-            while i < chars.len() && chars[i] != '\n' {
-                i += 1;
-            }
-            continue;
-        }
-        if c == '/' && next == Some('*') {
-            i += 2;
-            while i + 1 < chars.len() && !(chars[i] == '*' && chars[i + 1] == '/') {
-                i += 1;
-            }
-            i += 2;
-            continue;
-        }
-
-        match c {
-            '\'' => in_single = true,
-            '"' => in_double = true,
-            '`' => in_template = true,
-            '(' => par += 1,
-            ')' => par -= 1,
-            '[' => brk += 1,
-            ']' => brk -= 1,
-            '{' => brc += 1,
-            '}' => brc -= 1,
-            ',' if par == 0 && brk == 0 && brc == 0 => {
-                let part = chars[start..i].iter().collect::<String>().trim().to_string();
-                if !part.is_empty() {
-                    parts.push(part);
-                }
-                start = i + 1;
-            }
-            _ => {}
-        }
-        i += 1;
-    }
-
-    let tail = chars[start..].iter().collect::<String>().trim().to_string();
-    if !tail.is_empty() {
-        parts.push(tail);
-    }
-    parts
-}
-
-fn find_top_level_arrow(input: &str) -> Option<usize> {
-    let chars: Vec<char> = input.chars().collect();
-    let mut i = 0usize;
-    let mut par = 0i32;
-    let mut brk = 0i32;
-    let mut brc = 0i32;
-    let mut in_single = false;
-    let mut in_double = false;
-    let mut in_template = false;
-    while i < chars.len() {
-        let c = chars[i];
-        let next = if i + 1 < chars.len() { Some(chars[i + 1]) } else { None };
-
-        if in_single {
-            if c == '\\' {
-                i += 2;
-                continue;
-            }
-            if c == '\'' {
-                in_single = false;
-            }
-            i += 1;
-            continue;
-        }
-        if in_double {
-            if c == '\\' {
-                i += 2;
-                continue;
-            }
-            if c == '"' {
-                in_double = false;
-            }
-            i += 1;
-            continue;
-        }
-        if in_template {
-            if c == '\\' {
-                i += 2;
-                continue;
-            }
-            if c == '`' {
-                in_template = false;
-            }
-            i += 1;
-            continue;
-        }
-
-        match c {
-            '\'' => in_single = true,
-            '"' => in_double = true,
-            '`' => in_template = true,
-            '(' => par += 1,
-            ')' => par -= 1,
-            '[' => brk += 1,
-            ']' => brk -= 1,
-            '{' => brc += 1,
-            '}' => brc -= 1,
-            '=' if next == Some('>') && par == 0 && brk == 0 && brc == 0 => return Some(i),
-            _ => {}
-        }
-        i += 1;
-    }
-    None
-}
-
-fn build_match_expr(subject: &str, body: &str, match_id: usize) -> Option<String> {
-    let arms = split_top_level_csv(body);
-    if arms.is_empty() {
-        return None;
-    }
-
-    let mut conditions: Vec<(String, String)> = Vec::new();
-    let mut default_expr: Option<String> = None;
-
-    for arm in arms {
-        let arrow = find_top_level_arrow(&arm)?;
-        let pattern = arm[..arrow].trim();
-        let expr = arm[arrow + 2..].trim();
-        if pattern.is_empty() || expr.is_empty() {
-            return None;
-        }
-        if pattern == "default" {
-            default_expr = Some(expr.to_string());
-            continue;
-        }
-        if pattern.starts_with('[') && pattern.ends_with(']') {
-            let list_inner = pattern[1..pattern.len() - 1].trim();
-            let cond = format!("[{}].contains(&__trust_match_{})", list_inner, match_id);
-            conditions.push((cond, expr.to_string()));
-        } else {
-            let cond = format!("__trust_match_{} == ({})", match_id, pattern);
-            conditions.push((cond, expr.to_string()));
-        }
-    }
-
-    let mut out = format!("({{ let __trust_match_{} = {}; ", match_id, subject);
-    if conditions.is_empty() {
-        let d = default_expr?;
-        out.push_str(&format!("{} }})", d));
-        return Some(out);
-    }
-
-    for (idx, (cond, expr)) in conditions.iter().enumerate() {
-        if idx == 0 {
-            out.push_str(&format!("if {} {{ {} }} ", cond, expr));
-        } else {
-            out.push_str(&format!("else if {} {{ {} }} ", cond, expr));
-        }
-    }
-
-    if let Some(d) = default_expr {
-        out.push_str(&format!("else {{ {} }} ", d));
-    } else {
-        out.push_str("else { panic!(\"non-exhaustive match\") } ");
-    }
-    out.push_str("})");
-    Some(out)
-}
-
-fn rewrite_word_boolean_ops(source: &str) -> String {
-    #[derive(Clone, Copy, PartialEq, Eq)]
-    enum Mode {
-        Normal,
-        LineComment,
-        BlockComment,
-        SingleString,
-        DoubleString,
-        TemplateString,
-    }
-
-    fn is_ident_char(c: char) -> bool {
-        c.is_ascii_alphanumeric() || c == '_'
-    }
-
-    let chars: Vec<char> = source.chars().collect();
-    let mut out = String::with_capacity(source.len());
-    let mut i = 0;
-    let mut mode = Mode::Normal;
-
-    while i < chars.len() {
-        let c = chars[i];
-        let next = if i + 1 < chars.len() { Some(chars[i + 1]) } else { None };
-
-        match mode {
-            Mode::Normal => {
-                if c == '/' && next == Some('/') {
-                    out.push(c);
-                    out.push('/');
-                    i += 2;
-                    mode = Mode::LineComment;
-                    continue;
-                }
-                if c == '/' && next == Some('*') {
-                    out.push(c);
-                    out.push('*');
-                    i += 2;
-                    mode = Mode::BlockComment;
-                    continue;
-                }
-                if c == '\'' {
-                    out.push(c);
-                    i += 1;
-                    mode = Mode::SingleString;
-                    continue;
-                }
-                if c == '"' {
-                    out.push(c);
-                    i += 1;
-                    mode = Mode::DoubleString;
-                    continue;
-                }
-                if c == '`' {
-                    out.push(c);
-                    i += 1;
-                    mode = Mode::TemplateString;
-                    continue;
-                }
-
-                // Replace standalone `and`/`or` identifiers
-                if c == 'a' && i + 2 < chars.len() && chars[i + 1] == 'n' && chars[i + 2] == 'd' {
-                    let prev_ok = i == 0 || !is_ident_char(chars[i - 1]);
-                    let next_ok = i + 3 >= chars.len() || !is_ident_char(chars[i + 3]);
-                    if prev_ok && next_ok {
-                        out.push_str("&&");
-                        i += 3;
-                        continue;
-                    }
-                }
-                if c == 'o' && i + 1 < chars.len() && chars[i + 1] == 'r' {
-                    let prev_ok = i == 0 || !is_ident_char(chars[i - 1]);
-                    let next_ok = i + 2 >= chars.len() || !is_ident_char(chars[i + 2]);
-                    if prev_ok && next_ok {
-                        out.push_str("||");
-                        i += 2;
-                        continue;
-                    }
-                }
-                if c == 'l'
-                    && i + 3 < chars.len()
-                    && chars[i + 1] == 'o'
-                    && chars[i + 2] == 'o'
-                    && chars[i + 3] == 'p'
-                {
-                    let prev_ok = i == 0 || !is_ident_char(chars[i - 1]);
-                    let mut k = i + 4;
-                    while k < chars.len() && chars[k].is_whitespace() {
-                        k += 1;
-                    }
-                    let next_ok = (i + 4 >= chars.len() || !is_ident_char(chars[i + 4]))
-                        && k < chars.len()
-                        && chars[k] == '(';
-                    if prev_ok && next_ok {
-                        out.push_str("while");
-                        i += 4;
-                        continue;
-                    }
-                }
-
-                out.push(c);
-                i += 1;
-            }
-            Mode::LineComment => {
-                out.push(c);
-                i += 1;
-                if c == '\n' {
-                    mode = Mode::Normal;
-                }
-            }
-            Mode::BlockComment => {
-                out.push(c);
-                i += 1;
-                if c == '*' && next == Some('/') {
-                    out.push('/');
-                    i += 1;
-                    mode = Mode::Normal;
-                }
-            }
-            Mode::SingleString => {
-                out.push(c);
-                i += 1;
-                if c == '\\' {
-                    if let Some(n) = next {
-                        out.push(n);
-                        i += 1;
-                    }
-                } else if c == '\'' {
-                    mode = Mode::Normal;
-                }
-            }
-            Mode::DoubleString => {
-                out.push(c);
-                i += 1;
-                if c == '\\' {
-                    if let Some(n) = next {
-                        out.push(n);
-                        i += 1;
-                    }
-                } else if c == '"' {
-                    mode = Mode::Normal;
-                }
-            }
-            Mode::TemplateString => {
-                out.push(c);
-                i += 1;
-                if c == '\\' {
-                    if let Some(n) = next {
-                        out.push(n);
-                        i += 1;
-                    }
-                } else if c == '`' {
-                    mode = Mode::Normal;
-                }
-            }
-        }
+    let output = Compiler::new().compile(source);
+    for warning in output.warnings() {
+        eprintln!("⚠️  {}", warning.message);
     }
-
-    out
-}
-
-fn rewrite_val_declarations(source: &str) -> String {
-    source
-        .lines()
-        .map(|line| {
-            let trimmed = line.trim_start();
-            if trimmed.starts_with("val ") || trimmed.starts_with("val\t") {
-                let indent = &line[..line.len() - trimmed.len()];
-                let rest = trimmed[3..].trim_start();
-                format!("{}let {}", indent, rest)
-            } else {
-                line.to_string()
-            }
-        })
-        .collect::<Vec<_>>()
-        .join("\n")
+    output.into_result()
 }
 
-fn rewrite_implements_blocks(source: &str) -> String {
-    let mut out = Vec::new();
-    let mut in_impl = false;
-    let mut brace_depth: i32 = 0;
-
-    for line in source.lines() {
-        let trimmed = line.trim_start();
-        let indent = &line[..line.len() - trimmed.len()];
-
-        if !in_impl {
-            if let Some(rest) = trimmed.strip_prefix("implements ") {
-                if let Some((target, _)) = rest.split_once('{') {
-                    let target = target.trim();
-                    out.push(format!("{}class {} {{", indent, target));
-                    in_impl = true;
-                    brace_depth = 1;
-                    continue;
-                }
-            }
-            out.push(line.to_string());
-            continue;
-        }
-
-        let rewritten = if let Some(rest) = trimmed.strip_prefix("function ") {
-            format!("{}{}", indent, rest)
-        } else {
-            line.to_string()
-        };
-
-        brace_depth += rewritten.matches('{').count() as i32;
-        brace_depth -= rewritten.matches('}').count() as i32;
-        out.push(rewritten);
-
-        if brace_depth <= 0 {
-            in_impl = false;
-            brace_depth = 0;
-        }
-    }
-
-    out.join("\n")
+/// Byte offsets of every code occurrence (outside strings and comments) of identifier `needle`.
+fn find_identifiers_in_code(source: &str, needle: &str) -> Vec<usize> {
+    let tokens = preprocessor::tokenize(source);
+    preprocessor::identifier_offsets(source, &tokens, needle).collect()
 }
 
 pub fn compile_formatted(source: &str) -> Result<String> {
@@ -958,10 +267,15 @@ mod tests {
         assert!(result.contains("name.ends_with((\"z\".to_string()).as_str())"));
         assert!(result.contains("name.contains((\"x\".to_string()).as_str())"));
         assert!(result.contains("match __trust_s.find((\"x\".to_string()).as_str())"));
-        assert!(result.contains("char_indices().take_while(|(i, _)| *i < __trust_byte).count() as i32"));
+        assert!(
+            result.contains("char_indices().take_while(|(i, _)| *i < __trust_byte).count() as i32")
+        );
         assert!(result.contains("match __trust_s.rfind((\"x\".to_string()).as_str())"));
-        assert!(result.contains("name.replacen((\"a\".to_string()).as_str(), (\"b\".to_string()).as_str(), 1)"));
-        assert!(result.contains("name.replace((\"a\".to_string()).as_str(), (\"b\".to_string()).as_str())"));
+        assert!(result.contains(
+            "name.replacen((\"a\".to_string()).as_str(), (\"b\".to_string()).as_str(), 1)"
+        ));
+        assert!(result
+            .contains("name.replace((\"a\".to_string()).as_str(), (\"b\".to_string()).as_str())"));
         assert!(result.contains("name.trim().to_string()"));
         assert!(result.contains("name.trim_start().to_string()"));
         assert!(result.contains("name.trim_end().to_string()"));
@@ -1173,11 +487,101 @@ mod tests {
         "#;
 
         let result = compile(trust_code).unwrap();
-        assert!(result.contains("fn compute(n: i32) -> std::thread::JoinHandle<i32>"));
-        assert!(result.contains("std::thread::spawn(move || {"));
+        assert!(result.contains("fn compute(n: i32) -> TrustTask<i32>"));
+        assert!(result.contains("TrustTask::spawn(move || {"));
+        assert!(result.contains("struct TrustTask<T> {"));
         assert!(result.contains("let out = (handle).join().unwrap();"));
     }
 
+    #[test]
+    fn test_compile_wait_inside_expressions() {
+        let trust_code = r#"
+            async function compute(n: int32): int32 {
+                return n * 2;
+            }
+
+            function show(n: int32) {
+                console.write(n);
+            }
+
+            function main() {
+                val a = compute(1);
+                val b = compute(2);
+                val c = compute(3);
+                val x = wait a;
+                show(wait b);
+                wait c;
+                val wait = x + 1;
+                console.write(wait);
+            }
+        "#;
+
+        let result = compile(trust_code).unwrap();
+        assert!(result.contains("let x = (a).join().unwrap();"));
+        assert!(result.contains("show((b).join().unwrap());"));
+        assert!(result.contains("(c).join().unwrap();"));
+        assert!(result.contains("let wait = x + 1;"));
+    }
+
+    #[test]
+    fn test_compile_chained_awaits_and_await_all() {
+        let trust_code = r#"
+            async function compute(n: int32): int32 {
+                return n * 2;
+            }
+
+            async function both(n: int32): int32 {
+                return await compute(n) + await compute(n + 1);
+            }
+
+            function main() {
+                val handles = [compute(1), both(2)];
+                val results = awaitAll(handles);
+                for (val r of results) {
+                    console.write(r);
+                }
+            }
+        "#;
+
+        let result = compile(trust_code).unwrap();
+        assert!(result
+            .contains("return (compute(n)).join().unwrap() + (compute(n + 1)).join().unwrap();"));
+        assert!(result.contains(
+            "let results = handles.into_iter().map(|__trust_task| __trust_task.join().unwrap()).collect::<Vec<_>>();"
+        ));
+        assert!(result.contains("for r in (results).iter().cloned() {"));
+    }
+
+    #[test]
+    fn test_compile_task_cancellation_and_timeouts() {
+        let trust_code = r#"
+            async function worker(): int32 {
+                var steps = 0;
+                loop {
+                    if (isCancelled()) {
+                        return steps;
+                    }
+                    steps += 1;
+                }
+            }
+
+            function main() {
+                val task = worker();
+                val early = awaitTimeout(task, 10);
+                task.cancel();
+                val steps = wait task;
+                console.write(steps);
+            }
+        "#;
+
+        let result = compile(trust_code).unwrap();
+        assert!(result.contains("if isCancelled() {"));
+        assert!(result.contains("let early = awaitTimeout(&task, (10) as i32);"));
+        assert!(result.contains("task.cancel();"));
+        assert!(result.contains("let steps = (task).join().unwrap();"));
+        assert!(result.contains("fn awaitTimeout<T>(task: &TrustTask<T>, ms: i32) -> Option<T>"));
+    }
+
     #[test]
     fn test_compile_try_catch_finally() {
         let trust_code = r#"
@@ -1226,6 +630,62 @@ mod tests {
         assert!(result.contains("return p;"));
     }
 
+    #[test]
+    fn test_compile_optional_and_readonly_fields() {
+        let trust_code = r#"
+            struct User {
+                readonly id: int32;
+                name: string;
+                nickname?: string;
+            }
+
+            function main() {
+                var ann = User({ id: 1, name: "Ann" });
+                ann.nickname = "annie";
+                val bob = User({ id: 2, name: "Bob", nickname: "bobby" });
+                console.write(ann.nickname ?? ann.name, bob.id);
+            }
+        "#;
+
+        let result = compile(trust_code).unwrap();
+        assert!(
+            result.contains("    nickname: Option<String>,\n"),
+            "{}",
+            result
+        );
+        assert!(
+            result.contains("User { id: 1, name: \"Ann\".to_string(), nickname: None }"),
+            "{}",
+            result
+        );
+        assert!(
+            result.contains("ann.nickname = Some(\"annie\".to_string());"),
+            "{}",
+            result
+        );
+        assert!(
+            result.contains("nickname: Some(\"bobby\".to_string())"),
+            "{}",
+            result
+        );
+        assert!(
+            result.contains("unwrap_or_else(|| ann.name.clone())"),
+            "{}",
+            result
+        );
+
+        let output =
+            Compiler::new().compile(&trust_code.replace("ann.nickname = \"annie\"", "ann.id = 3"));
+        let errors: Vec<_> = output
+            .errors()
+            .map(|d| (d.code, d.message.as_str()))
+            .collect();
+        assert_eq!(
+            errors,
+            [("readonly-field", "`id` is `readonly` in `User`: it is set when the struct is built and cannot be assigned afterwards")]
+        );
+    }
+
     #[test]
     fn test_compile_import() {
         let trust_code = r#"
@@ -1233,7 +693,9 @@ mod tests {
         "#;
 
         let output = compile_full(trust_code).unwrap();
-        assert!(output.rust_code.contains("use serde::{Serialize, Deserialize};"));
+        assert!(output
+            .rust_code
+            .contains("use serde::{Serialize, Deserialize};"));
         assert!(output.required_crates.contains(&"serde".to_string()));
     }
 
@@ -1256,10 +718,12 @@ mod tests {
         "#;
 
         let output = compile_full(trust_code).unwrap();
+        assert!(output.rust_code.contains(
+            "#[derive(Debug, Clone, serde_derive::Serialize, serde_derive::Deserialize)]"
+        ));
         assert!(output
             .rust_code
-            .contains("#[derive(Debug, Clone, serde_derive::Serialize, serde_derive::Deserialize)]"));
-        assert!(output.rust_code.contains("pub fn toJSON<T: serde::Serialize>(value: T) -> String"));
+            .contains("pub fn toJSON<T: serde::Serialize>(value: T) -> String"));
         assert!(output
             .rust_code
             .contains("pub fn fromJSON<T: serde::de::DeserializeOwned>(json: String) -> T"));
@@ -1292,25 +756,202 @@ mod tests {
         let output = compile_full(trust_code).unwrap();
         assert!(output.rust_code.contains("pub struct HttpRequestOptions"));
         assert!(output.rust_code.contains("pub struct HttpResponse"));
-        assert!(output.rust_code.contains("pub fn fetch(url: String) -> HttpResponse"));
-        assert!(output.rust_code.contains("pub fn fetchWith(url: String, options: HttpRequestOptions) -> HttpResponse"));
-        assert!(output.rust_code.contains("pub fn requestOptions() -> HttpRequestOptions"));
+        assert!(output
+            .rust_code
+            .contains("pub fn fetch(url: String) -> HttpResponse"));
+        assert!(output.rust_code.contains(
+            "pub fn fetchWith(url: String, options: HttpRequestOptions) -> HttpResponse"
+        ));
+        assert!(output
+            .rust_code
+            .contains("pub fn requestOptions() -> HttpRequestOptions"));
         assert!(output.rust_code.contains("pub fn json(&self) -> Value"));
         assert!(output.required_crates.contains(&"ureq".to_string()));
     }
 
     #[test]
-    fn test_compile_trusty_http_server_router_and_json_as() {
+    fn test_compile_trusty_http_query_form_and_download() {
         let trust_code = r#"
-            import { HttpServer } from "trusty:http";
-            import { toJSON } from "trusty:json";
+            import { fetchWith, downloadTo, requestOptions, FormData } from "trusty:http";
 
-            struct UserBody {
-                id: string;
+            function main() {
+                var opts = requestOptions();
+                opts.query.set("q", "rust lang");
+                opts.method = "POST";
+                opts.form = FormData.create().field("name", "Ada").file("avatar", "ada.png");
+                console.write(fetchWith("https://example.com/search", opts).status);
+
+                val saved = downloadTo("https://example.com/big.zip", "big.zip");
+                console.write(saved.ok);
             }
+        "#;
 
-            function boot(): int32 {
-                val app = HttpServer.create();
+        let output = compile_full(trust_code).unwrap();
+        assert!(
+            output
+                .rust_code
+                .contains("opts.query.insert(\"q\".to_string(), \"rust lang\".to_string());"),
+            "{}",
+            output.rust_code
+        );
+        assert!(
+            output
+                .rust_code
+                .contains("opts.form = FormData::create().field("),
+            "{}",
+            output.rust_code
+        );
+        assert!(output.rust_code.contains("pub fn downloadWith(url: String, path: String, options: HttpRequestOptions) -> HttpResponse"));
+
+        assert_eq!(
+            stdlib::field_type("HttpRequestOptions", "query").as_deref(),
+            Some("HashMap<String, String>")
+        );
+        assert_eq!(
+            stdlib::field_type("Request", "params").as_deref(),
+            Some("Params")
+        );
+        // `raw` is private.
+        assert_eq!(stdlib::field_type("HttpResponse", "raw"), None);
+    }
+
+    #[test]
+    fn test_compile_trusty_http_static_files_groups_and_not_found() {
+        let trust_code = r#"
+            import { HttpServer } from "trusty:http";
+
+            function main() {
+                val app = HttpServer.create();
+                app.static("/assets", "./public");
+                app.group("/api/v1", function(api) {
+                    api.get("/users/:id", function(req, res) {
+                        res.send(req.params.getOr("id", "?"));
+                    });
+                });
+                app.notFound(function(req, res) {
+                    res.send(`nothing at ${req.path}`);
+                });
+                app.listen(8080);
+            }
+        "#;
+
+        let output = compile_full(trust_code).unwrap();
+        assert!(
+            output
+                .rust_code
+                .contains("app.serveStatic(\"/assets\".to_string(), \"./public\".to_string());"),
+            "{}",
+            output.rust_code
+        );
+        assert!(
+            output
+                .rust_code
+                .contains("app.group(\"/api/v1\".to_string(), move |api| {"),
+            "{}",
+            output.rust_code
+        );
+        assert!(
+            output.rust_code.contains("app.notFound(move |req, res| {"),
+            "{}",
+            output.rust_code
+        );
+    }
+
+    #[test]
+    fn test_compile_trusty_http_background_server_and_stop() {
+        let trust_code = r#"
+            import { HttpServer, fetch } from "trusty:http";
+
+            function main() {
+                val app = HttpServer.create();
+                app.workers(4);
+                app.get("/hello", function(req, res) {
+                    res.send("hi");
+                });
+                val handle = app.listenBackgroundOn("127.0.0.1:0");
+                val port = handle.port + 0;
+                console.write(fetch(`http://127.0.0.1:${port}/hello`).body);
+                handle.stop();
+                val stopped = await handle;
+                console.write(stopped);
+            }
+        "#;
+
+        let output = compile_full(trust_code).unwrap();
+        assert!(
+            output
+                .rust_code
+                .contains("let stopped = (handle).join().unwrap();"),
+            "{}",
+            output.rust_code
+        );
+        assert!(output
+            .rust_code
+            .contains("pub fn listenBackgroundOn(&self, bind: String) -> ServerHandle"));
+        assert_eq!(
+            stdlib::method_return_type("HttpServer", "listenBackground", true).as_deref(),
+            Some("ServerHandle")
+        );
+        assert_eq!(
+            stdlib::field_type("ServerHandle", "port").as_deref(),
+            Some("i32")
+        );
+    }
+
+    #[test]
+    fn test_compile_trusty_http_cookies_and_sessions() {
+        let trust_code = r#"
+            import { HttpServer, cookieOptions } from "trusty:http";
+
+            function main() {
+                val app = HttpServer.create();
+                app.useSessions("secret");
+                app.get("/login", function(req, res) {
+                    req.session().set("user", req.cookie("name"));
+                    var opts = cookieOptions();
+                    opts.maxAgeSeconds = 60;
+                    res.setCookie("seen", "yes", opts);
+                });
+                app.get("/logout", function(req, res) {
+                    req.session().destroy();
+                    res.clearCookie("seen");
+                });
+            }
+        "#;
+
+        let output = compile_full(trust_code).unwrap();
+        assert!(
+            output.rust_code.contains("let mut opts = cookieOptions();"),
+            "{}",
+            output.rust_code
+        );
+        assert!(output.rust_code.contains("opts.maxAgeSeconds = 60;"));
+        assert!(output
+            .rust_code
+            .contains("res.setCookie(\"seen\".to_string(), \"yes\".to_string(), opts);"));
+        assert!(output.rust_code.contains("fn __trust_hmac_sha256("));
+        assert_eq!(
+            stdlib::method_return_type("Request", "session", true).as_deref(),
+            Some("Session")
+        );
+        assert_eq!(
+            stdlib::field_type("CookieOptions", "httpOnly").as_deref(),
+            Some("bool")
+        );
+    }
+
+    #[test]
+    fn test_compile_trusty_http_server_router_and_json_as() {
+        let trust_code = r#"
+            import { HttpServer } from "trusty:http";
+            import { toJSON } from "trusty:json";
+
+            struct UserBody {
+                id: string;
+            }
+
+            function boot(): int32 {
+                val app = HttpServer.create();
 
                 app.get("/users/:id", function(req, res) {
                     val id = req.params.getOr("id", "unknown");
@@ -1332,14 +973,28 @@ mod tests {
 
         let output = compile_full(trust_code).unwrap();
         assert!(output.rust_code.contains("pub struct HttpServer"));
-        assert!(output.rust_code.contains("pub fn get<F>(&self, pattern: String, handler: F)"));
-        assert!(output.rust_code.contains("pub fn post<F>(&self, pattern: String, handler: F)"));
-        assert!(output.rust_code.contains("pub fn listen(&self, port: i32) -> bool"));
-        assert!(output.rust_code.contains("pub fn listenOn(&self, bind: String) -> bool"));
-        assert!(output.rust_code.contains("pub fn lastError(&self) -> String"));
-        assert!(output.rust_code.contains("pub fn jsonAs<T: serde::de::DeserializeOwned>(&self) -> Option<T>"));
+        assert!(output
+            .rust_code
+            .contains("pub fn get<F>(&self, pattern: String, handler: F)"));
+        assert!(output
+            .rust_code
+            .contains("pub fn post<F>(&self, pattern: String, handler: F)"));
+        assert!(output
+            .rust_code
+            .contains("pub fn listen(&self, port: i32) -> bool"));
+        assert!(output
+            .rust_code
+            .contains("pub fn listenOn(&self, bind: String) -> bool"));
+        assert!(output
+            .rust_code
+            .contains("pub fn lastError(&self) -> String"));
+        assert!(output
+            .rust_code
+            .contains("pub fn jsonAs<T: serde::de::DeserializeOwned>(&self) -> Option<T>"));
         assert!(output.rust_code.contains(".jsonAs::<UserBody>()"));
-        assert!(output.rust_code.contains("pub fn getOr(&self, key: String, fallback: String) -> String"));
+        assert!(output
+            .rust_code
+            .contains("pub fn getOr(&self, key: String, fallback: String) -> String"));
         assert!(output.required_crates.contains(&"tiny_http".to_string()));
     }
 
@@ -1423,7 +1078,9 @@ mod tests {
         "#;
 
         let result = compile(trust_code).unwrap();
-        assert!(result.contains("use std::time::{Instant, Duration, SystemTime as RustSystemTime};"));
+        assert!(
+            result.contains("use std::time::{Instant, Duration, SystemTime as RustSystemTime};")
+        );
         assert!(result.contains("fn compare(a: DateTime, b: DateTime) -> i32"));
         assert!(result.contains("fn addDays(dateTime: DateTime, days: i32) -> DateTime"));
         assert!(result.contains("fn addMonths(dateTime: DateTime, months: i32) -> DateTime"));
@@ -1441,6 +1098,54 @@ mod tests {
         assert!(result.contains("return compare(now, j);"));
     }
 
+    #[test]
+    fn test_compile_tree_shakes_unused_stdlib_items() {
+        let trust_code = r#"
+            import { DateTime, addDays, compare } from "trusty:time";
+
+            function main() {
+                val now = DateTime.now();
+                console.write(compare(now, addDays(now, 1)));
+            }
+        "#;
+
+        let result = compile(trust_code).unwrap();
+        assert!(result.contains("fn addDays(dateTime: DateTime, days: i32) -> DateTime"));
+        assert!(result.contains("fn compare(a: DateTime, b: DateTime) -> i32"));
+        assert!(result.contains("pub struct DateTime {"));
+        // Reached through `DateTime`'s methods rather than the program.
+        assert!(result.contains("fn __trust_days_from_civil("));
+        assert!(!result.contains("fn subDays(dateTime: DateTime"));
+        assert!(!result.contains("fn setInterval<"));
+        assert!(!result.contains("pub struct Stopwatch {"));
+        assert!(!result.contains("fn format<"));
+        assert!(result.contains("#[allow(unused_imports)]\nuse std::thread::sleep;"));
+
+        // `x.toDateTime()` keeps the trait declaring it.
+        let trust_code = r#"
+            import { Date } from "trusty:time";
+
+            function main() {
+                console.write(Date.today().toDateTime().toIsoString());
+            }
+        "#;
+        assert!(compile(trust_code)
+            .unwrap()
+            .contains("pub trait __TrustTimePoint {"));
+
+        // `CookieOptions { maxAgeSeconds: 0, ..cookieOptions() }` in `clearCookie` calls the function.
+        let trust_code = r#"
+            import { fetch } from "trusty:http";
+
+            function main() {
+                console.write(fetch("http://localhost:1/").status);
+            }
+        "#;
+        assert!(compile(trust_code)
+            .unwrap()
+            .contains("pub fn cookieOptions() -> CookieOptions {"));
+    }
+
     #[test]
     fn test_compile_trusty_time_date_time_datetime_helpers() {
         let trust_code = r#"
@@ -1495,7 +1200,8 @@ mod tests {
         assert!(result.contains("pub fn sqrt<T: Into<f64>>(x: T) -> f64"));
         assert!(result.contains("pub fn pow<A: Into<f64>, B: Into<f64>>(base: A, exp: B) -> f64"));
         assert!(result.contains("pub fn log<T: Into<f64>>(value: T) -> f64"));
-        assert!(result.contains("pub fn log_base<V: Into<f64>, B: Into<f64>>(value: V, base: B) -> f64"));
+        assert!(result
+            .contains("pub fn log_base<V: Into<f64>, B: Into<f64>>(value: V, base: B) -> f64"));
         assert!(result.contains("pub fn abs<T: __TrustMathAbs>(x: T) -> T"));
         assert!(result.contains("pub fn min<T: PartialOrd + Copy>(a: T, b: T) -> T"));
         assert!(result.contains("pub fn max<T: PartialOrd + Copy>(a: T, b: T) -> T"));
@@ -1542,8 +1248,10 @@ mod tests {
 
         let output = compile_full(trust_code).unwrap();
         let result = output.rust_code;
-        assert!(result.contains("use rand::Rng;"));
-        assert!(result.contains("use rand::distributions::{Bernoulli, Distribution, WeightedIndex};"));
+        assert!(result.contains("use rand::Rng as _;"));
+        assert!(
+            result.contains("use rand::distr::{weighted::WeightedIndex, Bernoulli, Distribution};")
+        );
         assert!(result.contains("pub fn random() -> f64"));
         assert!(result.contains("pub fn randomInt(min: i32, max: i32) -> i32"));
         assert!(result.contains("pub fn randomFloat(min: f64, max: f64) -> f64"));
@@ -1551,4 +1259,2814 @@ mod tests {
         assert!(result.contains("pub fn weightedIndex(weights: Vec<f64>) -> i32"));
         assert!(output.required_crates.contains(&"rand".to_string()));
     }
+
+    #[test]
+    fn test_compiler_collects_warning_with_span() {
+        let source = "function f(x: number): number { return x; }";
+        let output = Compiler::new().compile(source);
+        assert!(!output.has_errors());
+        let warning = output.warnings().next().unwrap();
+        assert_eq!(warning.code, "deprecated-number-alias");
+        let span = warning.span.unwrap();
+        assert_eq!(&source[span.start..span.end], "number");
+        assert!(output.rust_code.is_some());
+    }
+
+    #[test]
+    fn test_compiler_deny_warnings_fails_build() {
+        let output = Compiler::new()
+            .deny_warnings(true)
+            .compile("function f(x: number): number { return x; }");
+        assert!(output.has_errors());
+        assert!(output.rust_code.is_none());
+    }
+
+    #[test]
+    fn test_compiler_parse_error_has_span() {
+        let source = "function main() {\n    val x = ;\n}";
+        let output = Compiler::new().compile(source);
+        let error = output.errors().next().unwrap();
+        assert_eq!(error.code, "parse-error");
+        assert_eq!(error.span.unwrap().line_col(source).0, 2);
+    }
+
+    #[test]
+    fn test_compiler_uses_file_loader() {
+        let output = Compiler::new()
+            .artifacts(&[Artifact::Preprocessed])
+            .file_loader(|path: &std::path::Path| {
+                assert_eq!(path, std::path::Path::new("virtual/main.trs"));
+                Ok("function main() {\n    val ok = true and false;\n}".to_string())
+            })
+            .compile_file(std::path::Path::new("virtual/main.trs"));
+        assert!(!output.has_errors());
+        assert!(output.rust_code.is_none());
+        assert!(output.preprocessed.unwrap().contains("true && false"));
+    }
+
+    #[test]
+    fn test_memory_resolver_bundles_virtual_tree() {
+        let resolver = MemoryResolver::new()
+            .with_file("app/src/main.trs", "import { greet } from \"./util/greet\";\nfunction main() {\n    console.write(greet());\n}")
+            .with_file("app/src/util/greet.trs", "import { NAME } from \"../names\";\nexport function greet(): string {\n    return NAME;\n}")
+            .with_file("app/src/names/index.trs", "export const NAME: string = \"TRUST\";");
+        let bundled = Compiler::new()
+            .resolver(resolver.clone())
+            .bundle(std::path::Path::new("app/src/main.trs"))
+            .unwrap();
+        let names = bundled.find("const NAME").unwrap();
+        let greet = bundled.find("function greet").unwrap();
+        let main = bundled.find("function main").unwrap();
+        assert!(names < greet && greet < main);
+        assert!(!bundled.contains("export "));
+
+        let output = Compiler::new()
+            .resolver(resolver)
+            .compile_file(std::path::Path::new("app/src/main.trs"));
+        assert!(!output.has_errors(), "{:?}", output.diagnostics);
+    }
+
+    #[test]
+    fn test_memory_resolver_reports_missing_and_circular_imports() {
+        let missing = MemoryResolver::new()
+            .with_file("main.trs", "import { a } from \"./a\";\nfunction main() {}");
+        let output = Compiler::new()
+            .resolver(missing)
+            .compile_file(std::path::Path::new("main.trs"));
+        assert!(output
+            .errors()
+            .any(|d| d.code == "module-error"
+                && d.message.contains("Cannot resolve local import './a'")));
+
+        let circular = MemoryResolver::new()
+            .with_file(
+                "a.trs",
+                "import { b } from \"./b\";\nexport function a() {}",
+            )
+            .with_file(
+                "b.trs",
+                "import { a } from \"./a\";\nval count = 1;\nexport function b() {}",
+            );
+        let err = Compiler::new()
+            .resolver(circular)
+            .bundle(std::path::Path::new("a.trs"))
+            .unwrap_err();
+        assert!(err.to_string().contains("Circular local import detected: a.trs -> b.trs -> a.trs (b.trs has statements outside functions"));
+        let chain = err.downcast_ref::<modules::ImportChainError>().unwrap();
+        let sites: Vec<(&str, usize, &str)> = chain
+            .imports
+            .iter()
+            .map(|site| {
+                (
+                    site.module.to_str().unwrap(),
+                    site.line,
+                    site.specifier.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(sites, [("a.trs", 0, "./b"), ("b.trs", 0, "./a")]);
+        assert_eq!(chain.imports[1].width, "import { a } from \"./a\";".len());
+    }
+
+    #[test]
+    fn test_bundle_allows_import_cycles_between_declarations() {
+        let resolver = MemoryResolver::new()
+            .with_file(
+                "main.trs",
+                "import { isEven } from \"./even\";\nfunction main() {\n    console.write(isEven(10));\n}",
+            )
+            .with_file(
+                "even.trs",
+                "import { isOdd, Parity } from \"./odd\";\nexport function isEven(n: int32): bool {\n    if (n == 0) {\n        return true;\n    }\n    return isOdd(n - 1);\n}\nexport function parityOf(n: int32): Parity {\n    return Parity({ even: isEven(n) });\n}",
+            )
+            .with_file(
+                "odd.trs",
+                "import { isEven, parityOf } from \"./even\";\nexport struct Parity {\n    even: bool;\n}\nexport function isOdd(n: int32): bool {\n    if (n == 0) {\n        return false;\n    }\n    return isEven(n - 1);\n}\nexport function describe(n: int32): bool {\n    return parityOf(n).even;\n}",
+            );
+        let bundled = Compiler::new()
+            .resolver(resolver.clone())
+            .bundle(std::path::Path::new("main.trs"))
+            .unwrap();
+        assert_eq!(bundled.matches("function isEven").count(), 1);
+        assert!(bundled.find("struct Parity").unwrap() < bundled.find("function isEven").unwrap());
+
+        let output = Compiler::new()
+            .resolver(resolver)
+            .compile_file(std::path::Path::new("main.trs"));
+        assert!(!output.has_errors(), "{:?}", output.diagnostics);
+    }
+
+    #[test]
+    fn test_compile_block_scoped_shadowing() {
+        let trust_code = r#"
+            function count(): int32 {
+                val items: int32[] = [1, 2];
+                val label: string = "abc";
+                if (true) {
+                    val items: string = "abc";
+                    val label = [1, 2, 3];
+                    console.write(items.length);
+                    console.write(label.length);
+                }
+                for (var label of [[1]]) {
+                    console.write(label.length);
+                }
+                console.write(label.length);
+                return items.length;
+            }
+        "#;
+
+        let result = compile(trust_code).unwrap();
+        assert!(result.contains("println!(\"{}\", items.chars().count() as i32);"));
+        assert_eq!(result.matches("println!(\"{}\", label.len());").count(), 2);
+        assert!(result.contains("println!(\"{}\", label.chars().count() as i32);"));
+        assert!(result.contains("return items.len();"));
+    }
+
+    #[test]
+    fn test_compile_infers_unannotated_let_types() {
+        let trust_code = r#"
+            import { sqrt } from "trusty:math";
+
+            struct User {
+                name: string;
+            }
+
+            implements User {
+                function title(): string {
+                    return this.name;
+                }
+            }
+
+            function main() {
+                val s = greet();
+                val u = User({ name: "ann" });
+                val n = u.name;
+                val t = u.title();
+                val root = sqrt(2);
+                val words = "a b".split(" ");
+                val loud = s.toUpperCase();
+                console.write(s.toUpperCase());
+                console.write(n.length);
+                console.write(t.toLowerCase());
+                console.write(loud.length);
+                console.write(root ** 2);
+                console.write(words.length);
+            }
+
+            function greet(): string {
+                return "hi";
+            }
+        "#;
+
+        let result = compile(trust_code).unwrap();
+        assert!(result.contains("let s = greet();"));
+        assert!(result.contains("println!(\"{}\", s.to_uppercase());"));
+        assert!(result.contains("println!(\"{}\", n.chars().count() as i32);"));
+        assert!(result.contains("println!(\"{}\", t.to_lowercase());"));
+        assert!(result.contains("println!(\"{}\", loud.chars().count() as i32);"));
+        assert!(result.contains("println!(\"{}\", (root as f64).powf(2 as f64));"));
+        assert!(result.contains("println!(\"{}\", words.len());"));
+    }
+
+    #[test]
+    fn test_compile_converts_arguments_to_declared_parameter_types() {
+        let trust_code = r#"
+            function scale(value: float, factor: int64): float {
+                return value;
+            }
+
+            function shout(text: string): string {
+                return text.toUpperCase();
+            }
+
+            function main() {
+                val count: int32 = 3;
+                val name: Pointer<string> = "ann";
+                console.write(scale(2, count));
+                console.write(scale(1.5, 4));
+                console.write(shout(name));
+            }
+        "#;
+
+        let result = compile(trust_code).unwrap();
+        assert!(result.contains("scale((2) as f64, (count) as i64)"));
+        assert!(result.contains("scale(1.5, 4)"));
+        assert!(result.contains("shout(name.borrow().clone())"));
+    }
+
+    #[test]
+    fn test_compiler_reports_arity_mismatch() {
+        let source = "function add(a: int32, b: int32): int32 {\n    return a + b;\n}\n\nfunction main() {\n    console.write(add(1));\n}\n";
+        let output = Compiler::new().compile(source);
+        let diag = output.errors().next().expect("arity error");
+        assert_eq!(diag.code, "arity-mismatch");
+        assert_eq!(diag.message, "`add` expects 2 arguments but 1 was given");
+        let span = diag.span.expect("call span");
+        assert_eq!(&source[span.start..span.end], "add(1)");
+
+        let method_source = "struct User {\n    name: string;\n}\n\nimplements User {\n    function rename(newName: string): void {\n        this.name = newName;\n    }\n}\n\nfunction main() {\n    val u = User({ name: \"a\" });\n    u.rename();\n}\n";
+        let output = Compiler::new().compile(method_source);
+        assert!(output.errors().any(|d| d.code == "arity-mismatch"
+            && d.message == "`User.rename` expects 1 argument but 0 were given"));
+    }
+
+    #[test]
+    fn test_compile_clones_owned_arguments_used_again() {
+        let trust_code = r#"
+            struct User {
+                name: string;
+            }
+
+            function greet(name: string): void {
+                console.write(name);
+            }
+
+            function save(user: User): void {
+                console.write(user.name);
+            }
+
+            function main() {
+                val name: string = "ann";
+                val user = User({ name: "bob" });
+                val last: string = "zed";
+                greet(name);
+                save(user);
+                console.write(name);
+                for (var i = 0; i < 2; i += 1) {
+                    greet(last);
+                }
+                save(user);
+            }
+        "#;
+
+        let result = compile(trust_code).unwrap();
+        assert!(result.contains("greet(name.clone());"));
+        assert!(result.contains("save(user.clone());"));
+        assert!(result.contains("greet(last.clone());"));
+        assert!(result.contains("save(user);"));
+
+        let never = Compiler::new()
+            .auto_clone(AutoClone::Never)
+            .compile(trust_code);
+        assert!(never.rust_code.unwrap().contains("greet(name);"));
+        let always = Compiler::new()
+            .auto_clone(AutoClone::Always)
+            .compile(trust_code);
+        assert!(!always.rust_code.unwrap().contains("save(user);"));
+    }
+
+    #[test]
+    fn test_compile_clones_struct_field_values_used_again() {
+        let trust_code = r#"
+            struct Point {
+                x: int32;
+            }
+
+            struct Line {
+                a: Point;
+                b: Point;
+            }
+
+            function main() {
+                val p1 = Point({ x: 1 });
+                val p2 = Point({ x: 2 });
+                val first = Line({ a: p1, b: p2 });
+                val b = Point({ x: 3 });
+                val second = Line({ a: p1, b });
+                console.write(first.a.x, second.b.x);
+            }
+        "#;
+
+        let result = compile(trust_code).unwrap();
+        assert!(
+            result.contains("Line { a: p1.clone(), b: p2 }"),
+            "{}",
+            result
+        );
+        assert!(result.contains("Line { a: p1, b: b }"), "{}", result);
+
+        let never = Compiler::new()
+            .auto_clone(AutoClone::Never)
+            .compile(trust_code);
+        assert!(never.rust_code.unwrap().contains("Line { a: p1, b: p2 }"));
+    }
+
+    #[test]
+    fn test_compile_ref_parameters_borrow_at_call_sites() {
+        let trust_code = r#"
+            struct User {
+                visits: int32;
+            }
+
+            function visit(user: mut ref User, label: ref string): void {
+                user.visits = user.visits + label.length;
+            }
+
+            function show(items: ref Map<string, int32>, shared: ref User): void {
+                console.write(shared.visits);
+            }
+
+            function main() {
+                var user = User({ visits: 0 });
+                val label: string = "ann";
+                val counts: Map<string, int32> = new Map();
+                val shared: Pointer<User> = User({ visits: 1 });
+                visit(user, label);
+                show(counts, shared);
+            }
+        "#;
+
+        let result = compile(trust_code).unwrap();
+        assert!(result.contains("fn visit(user: &mut User, label: &String) -> ()"));
+        assert!(result.contains("user.visits = user.visits + label.chars().count() as i32;"));
+        assert!(result.contains("fn show(items: &HashMap<String, i32>, shared: &User) -> ()"));
+        assert!(result.contains("visit(&mut user, &label);"));
+        assert!(result.contains("show(&counts, &*shared.borrow());"));
+    }
+
+    #[test]
+    fn test_compile_borrows_shared_handles_in_member_chains() {
+        let trust_code = r#"
+            struct Counter {
+                count: int32;
+            }
+
+            implements Counter {
+                function bump(): void {
+                    this.count = this.count + 1;
+                }
+            }
+
+            struct Holder {
+                counter: Pointer<Counter>;
+                names: Threaded<string[]>;
+            }
+
+            function shared(): Pointer<Counter> {
+                val c: Pointer<Counter> = Counter({ count: 10 });
+                return c;
+            }
+
+            function run(h: Holder) {
+                val list: Pointer<Counter>[] = [shared()];
+                h.counter.bump();
+                h.counter.count = 5;
+                h.names.push("a");
+                list[0].bump();
+                console.write(shared().count);
+                console.write(h.names.length);
+            }
+        "#;
+
+        let result = compile(trust_code).unwrap();
+        assert!(result.contains("h.counter.borrow_mut().bump();"));
+        assert!(result.contains("h.counter.borrow_mut().count = 5;"));
+        assert!(result.contains("h.names.lock().unwrap().push(\"a\".to_string());"));
+        assert!(result.contains("list[0 as usize].borrow_mut().bump();"));
+        assert!(result.contains("println!(\"{}\", shared().borrow().count);"));
+        assert!(result.contains("println!(\"{}\", h.names.lock().unwrap().len());"));
+    }
+
+    #[test]
+    fn test_compile_weak_pointer() {
+        let trust_code = r#"
+            struct Node {
+                name: string;
+                parent: WeakPointer<Node>;
+                children: Pointer<Node>[];
+            }
+
+            function main() {
+                val root: Pointer<Node> = Node({ name: "root", parent: null, children: [] });
+                val child: Pointer<Node> = Node({ name: "leaf", parent: null, children: [] });
+                child.parent = downgrade(root);
+                root.children.push(child);
+                val parent = upgrade(child.parent).unwrap();
+                console.write(parent.name);
+            }
+        "#;
+
+        let result = compile(trust_code).unwrap();
+        assert!(result.contains("use std::rc::Weak;"));
+        assert!(result.contains("parent: Weak<RefCell<Node>>,"));
+        assert!(result.contains(
+            "Node { name: \"root\".to_string(), parent: Weak::new(), children: vec![] }"
+        ));
+        assert!(result.contains("child.borrow_mut().parent = Rc::downgrade(&root);"));
+        assert!(result.contains("root.borrow_mut().children.push(child.clone());"));
+        assert!(result.contains("let parent = child.borrow().parent.upgrade().unwrap();"));
+        assert!(result.contains("println!(\"{}\", parent.borrow().name);"));
+
+        let downgrade_only = compile(
+            "struct Node {\n    name: string;\n}\n\nfunction main() {\n    val root: Pointer<Node> = Node({ name: \"root\" });\n    val weak = downgrade(root);\n    console.write(upgrade(weak).unwrap().name);\n}\n",
+        )
+        .unwrap();
+        assert!(
+            !downgrade_only.contains("use std::rc::Weak;"),
+            "unused import: {}",
+            downgrade_only
+        );
+    }
+
+    #[test]
+    fn test_compile_hoists_reads_that_conflict_with_mutable_borrows() {
+        let trust_code = r#"
+            struct Counter {
+                count: int32;
+            }
+
+            implements Counter {
+                function add(n: int32): void {
+                    this.count = this.count + n;
+                }
+
+                function get(): int32 {
+                    return this.count;
+                }
+            }
+
+            function main() {
+                val p: Pointer<Counter> = Counter({ count: 1 });
+                p.count = p.count + 1;
+                p.count += 10;
+                p.add(p.get());
+                val t: Threaded<Counter> = Counter({ count: 3 });
+                t.count = t.count + 1;
+            }
+        "#;
+
+        let result = compile(trust_code).unwrap();
+        assert!(result.contains(
+            "{ let __trust_value = p.borrow().count + 1; p.borrow_mut().count = __trust_value; };"
+        ));
+        assert!(result.contains("p.borrow_mut().count += 10;"));
+        assert!(result.contains(
+            "{ let __trust_arg0 = p.borrow().get(); p.borrow_mut().add(__trust_arg0) };"
+        ));
+        assert!(result.contains(
+            "{ let __trust_value = t.lock().unwrap().count + 1; t.lock().unwrap().count = __trust_value; };"
+        ));
+    }
+
+    #[test]
+    fn test_compile_match_on_enum_lowers_to_rust_match() {
+        let trust_code = r#"
+            enum Color {
+                Red,
+                Green,
+                Blue,
+            }
+
+            function describe(c: Color): string {
+                return match (c) {
+                    Color.Red => "warm",
+                    [Color.Green, Color.Blue] => "cool",
+                };
+            }
+
+            function main() {
+                val x: int32 = 3;
+                val n = match (x) {
+                    1 => 10,
+                    [2, 3] => 20,
+                    default => 0,
+                };
+                console.write(describe(Color.Blue));
+            }
+        "#;
+
+        let result = compile(trust_code).unwrap();
+        assert!(result.contains(
+            "match c { Color::Red => \"warm\".to_string(), Color::Green | Color::Blue => \"cool\".to_string() }"
+        ));
+        assert!(result.contains(
+            "let n = { let __trust_match = x; if __trust_match == (1) { 10 } else if vec![2, 3].contains(&__trust_match) { 20 } else { 0 } };"
+        ));
+        assert!(result.contains("describe(Color::Blue)"));
+    }
+
+    #[test]
+    fn test_compiler_reports_non_exhaustive_enum_match() {
+        let source = "enum Color {\n    Red,\n    Green,\n    Blue,\n}\n\nfunction main() {\n    val c: Color = Color.Red;\n    val name = match (c) {\n        Color.Red => \"red\",\n    };\n}\n";
+        let output = Compiler::new().compile(source);
+        let diag = output.errors().next().expect("exhaustiveness error");
+        assert_eq!(diag.code, "non-exhaustive-match");
+        assert_eq!(
+            diag.message,
+            "`match` on `Color` does not cover `Color.Green`, `Color.Blue`; add the missing variants or a `default` arm"
+        );
+
+        let unknown = source.replace(
+            "Color.Red => \"red\",",
+            "Color.Red => \"red\",\n        Color.Purple => \"?\",\n        default => \"other\",",
+        );
+        let output = Compiler::new().compile(&unknown);
+        assert!(output
+            .errors()
+            .any(|d| d.code == "unknown-variant"
+                && d.message == "`Purple` is not a variant of `Color`"));
+    }
+
+    #[test]
+    fn test_compile_labeled_break_and_continue() {
+        let trust_code = r#"
+            function main() {
+                var found = 0;
+                outer: for (var i = 0; i < 5; i += 1) {
+                    for (var j = 0; j < 5; j += 1) {
+                        if (j > i) {
+                            continue outer;
+                        }
+                        if (i * j == 6) {
+                            found = i * 10 + j;
+                            break outer;
+                        }
+                    }
+                }
+                rows: for (row of [[1, 2], [3, 4]]) {
+                    var n = 0;
+                    retry: loop (n < 3) {
+                        n += 1;
+                        break rows;
+                    }
+                }
+            }
+        "#;
+
+        let result = compile(trust_code).unwrap();
+        assert!(result.contains(
+            "'outer: while { if !__trust_first { i += 1; } __trust_first = false; i < 5 } {"
+        ));
+        assert!(result.contains("continue 'outer;"));
+        assert!(result.contains("break 'outer;"));
+        assert!(result.contains("while j < 5 {"));
+        assert!(
+            result.contains("'rows: for row in (vec![vec![1, 2], vec![3, 4]]).iter().cloned() {")
+        );
+        assert!(result.contains("'retry: while n < 3 {"));
+        assert!(result.contains("break 'rows;"));
+    }
+
+    #[test]
+    fn test_compile_range_loops() {
+        let trust_code = r#"
+            function main() {
+                val items = [4, 5, 6];
+                var total = 0;
+                for (i in 0..10) {
+                    total += i;
+                }
+                for (i of 1..=3) {
+                    total += i;
+                }
+                for (i in 0..items.length) {
+                    total += items[i];
+                }
+                val n: int64 = 3;
+                for (k in 0..n) {
+                    console.write(k);
+                }
+            }
+        "#;
+
+        let result = compile(trust_code).unwrap();
+        assert!(result.contains("for i in 0..10 {"));
+        assert!(result.contains("for i in 1..=3 {"));
+        assert!(result.contains("for i in 0..(items.len()) as i32 {"));
+        assert!(result.contains("total += items[i as usize];"));
+        assert!(result.contains("for k in 0..n {"));
+    }
+
+    #[test]
+    fn test_compile_do_loop() {
+        let trust_code = r#"
+            function main() {
+                var n = 0;
+                do {
+                    n += 1;
+                } loop (n < 5);
+                var i = 0;
+                do {
+                    i += 1;
+                    if (i % 2 == 0) {
+                        continue;
+                    }
+                    n += i;
+                } loop (i < 7);
+            }
+        "#;
+
+        let result = compile(trust_code).unwrap();
+        assert!(
+            result.contains("loop {\n        n += 1;\n    if !(n < 5) {\n        break;\n    }\n}")
+        );
+        assert!(result.contains("let mut __trust_first = true;\nwhile __trust_first || (i < 7) {\n    __trust_first = false;"));
+    }
+
+    #[test]
+    fn test_compile_infinite_loop() {
+        let trust_code = r#"
+            function main() {
+                var n = 0;
+                outer: loop {
+                    n += 1;
+                    loop {
+                        break outer;
+                    }
+                }
+                loop (n < 3) {
+                    n += 1;
+                }
+            }
+        "#;
+
+        let result = compile(trust_code).unwrap();
+        assert!(result.contains("'outer: loop {"));
+        assert!(result.contains("        loop {\n            break 'outer;"));
+        assert!(result.contains("while n < 3 {"));
+    }
+
+    #[test]
+    fn test_compile_top_level_statements_form_implicit_main() {
+        let trust_code = "const GREETING: string = \"hi\";\nval name = \"TRUST\";\nconsole.write(`${GREETING} ${name}`);\n\nfunction twice(x: int32): int32 {\n    return x * 2;\n}\n\nconsole.write(twice(21));\n";
+
+        let result = compile(trust_code).unwrap();
+        assert!(result.contains("const GREETING: &'static str = \"hi\";"));
+        assert!(result.contains("fn twice(x: i32) -> i32 {"));
+        assert!(result.contains(
+            "fn main() -> () {\n    let name = \"TRUST\".to_string();\n    println!(\"{}\", format!(\"{} {}\", GREETING, name));\n    println!(\"{}\", twice(21));\n}"
+        ));
+    }
+
+    #[test]
+    fn test_compiler_rejects_top_level_statements_beside_main() {
+        let source = "console.write(1);\n\nfunction main() {\n    console.write(2);\n}\n";
+        let output = Compiler::new().compile(source);
+        let diag = output.errors().next().expect("top-level statement error");
+        assert_eq!(diag.code, "top-level-statement");
+        let span = diag.span.expect("statement span");
+        assert_eq!(&source[span.start..span.end], "console.write(1);");
+    }
+
+    #[test]
+    fn test_compile_main_returning_exit_code() {
+        let trust_code =
+            "function main(): int32 {\n    console.write(\"bye\");\n    return 3;\n}\n";
+
+        let result = compile(trust_code).unwrap();
+        assert!(result.contains("fn __trust_main() -> i32 {\n    println!(\"{}\", \"bye\".to_string());\n    return 3;\n}"));
+        assert!(result.contains("fn main() {\n    let code = __trust_main();"));
+        assert!(result.contains("std::process::exit(code);"));
+    }
+
+    #[test]
+    fn test_compiler_validates_entry_point() {
+        let code_of = |compiler: Compiler, source: &str| {
+            compiler.compile(source).errors().next().map(|d| d.code)
+        };
+
+        let duplicate = "function main() {\n}\n\nfunction main() {\n}\n";
+        assert_eq!(code_of(Compiler::new(), duplicate), Some("duplicate-main"));
+
+        let with_params = "function main(args: string[]) {\n}\n";
+        assert_eq!(code_of(Compiler::new(), with_params), Some("invalid-main"));
+
+        let returns_string = "function main(): string {\n    return \"x\";\n}\n";
+        assert_eq!(
+            code_of(Compiler::new(), returns_string),
+            Some("invalid-main")
+        );
+
+        let library = "function helper(): int32 {\n    return 1;\n}\n";
+        assert_eq!(code_of(Compiler::new(), library), None);
+        assert_eq!(
+            code_of(Compiler::new().require_main(true), library),
+            Some("missing-main")
+        );
+        assert_eq!(
+            code_of(Compiler::new().require_main(true), "console.write(1);\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_compile_typed_throw_and_catch() {
+        let trust_code = r#"
+            struct NetworkError {
+                code: int32;
+            }
+
+            enum Fault {
+                Disk,
+            }
+
+            function fetch(code: int32): Result<int32, TrustError> {
+                if (code == 500) {
+                    throw Fault.Disk;
+                }
+                if (code == 0) {
+                    throw "empty";
+                }
+                return Ok(code);
+            }
+
+            function probe(code: int32): Result<int32, NetworkError> {
+                throw NetworkError({ code: code });
+            }
+
+            function main() {
+                try {
+                    throw NetworkError({ code: 503 });
+                } catch (e: NetworkError) {
+                    console.write(e.code);
+                }
+            }
+        "#;
+
+        let result = compile(trust_code).unwrap();
+        assert!(result.contains("enum TrustError {\n    Message(String),\n    Fault(Fault),\n    NetworkError(NetworkError),\n}"));
+        assert!(result.contains("impl std::fmt::Display for TrustError {"));
+        assert!(result.contains("return Err(TrustError::Fault(Fault::Disk));"));
+        assert!(result.contains("return Err(TrustError::Message(\"empty\".to_string()));"));
+        assert!(result.contains("return Err(NetworkError { code: code });"));
+        assert!(result.contains(
+            "let __trust_try_result: Result<(), TrustError> = (|| -> Result<(), TrustError> {"
+        ));
+        assert!(result
+            .contains("Err(TrustError::NetworkError(e)) => {\n        println!(\"{}\", e.code);"));
+        assert!(result.contains("Err(__trust_error) => panic!(\"{}\", __trust_error),"));
+    }
+
+    #[test]
+    fn test_compile_try_expression() {
+        let trust_code = r#"
+            struct NetworkError {
+                code: int32;
+            }
+
+            function parse(s: string): Result<int32, string> {
+                if (s == "") {
+                    throw "empty";
+                }
+                return Ok(s.length);
+            }
+
+            function fetch(code: int32): Result<int32, TrustError> {
+                throw NetworkError({ code: code });
+            }
+
+            function main() {
+                val x = try { parse("42") } catch (e) { 0 };
+                val y = try {
+                    console.write("parsing");
+                    parse("")
+                } catch (e) {
+                    console.write(e);
+                    0 - 1
+                };
+                val z = try { fetch(503) } catch (e: NetworkError) { e.code };
+            }
+        "#;
+        let result = compile(trust_code).unwrap();
+        assert!(result.contains("let x = match (|| -> Result<i32, String> {"));
+        assert!(result.contains("Ok(parse(\"42\".to_string())?)"));
+        assert!(result.contains("Ok(__trust_value) => __trust_value,"));
+        assert!(result.contains("Err(e) => 0,"));
+        assert!(result.contains("println!(\"{}\", \"parsing\".to_string());"));
+        assert!(result.contains("0 - 1"));
+        assert!(result.contains("Err(TrustError::NetworkError(e)) => e.code,"));
+        assert!(result.contains("Err(__trust_error) => panic!(\"{}\", __trust_error),"));
+        assert!(result.contains("impl From<NetworkError> for TrustError {"));
+    }
+
+    #[test]
+    fn test_compile_abort_builtins_report_trust_location() {
+        let trust_code = "function pick(n: int32): string {\n    if (n == 1) {\n        return \"one\";\n    }\n    val kind = match (n) {\n        2 => \"two\",\n        default => \"many\",\n    };\n    if (kind == \"two\") {\n        todo();\n    }\n    unreachable(kind);\n}\n\nfunction main() {\n    panic(\"stop\");\n}";
+        let result = compile(trust_code).unwrap();
+        assert!(result.contains("todo!(\"at line 10:9\")"));
+        assert!(result.contains("unreachable!(\"{} (at line 12:5)\", kind)"));
+        assert!(result.contains("panic!(\"{} (at line 16:5)\", \"stop\".to_string())"));
+
+        let resolver = MemoryResolver::new()
+            .with_file(
+                "app/main.trs",
+                "import { check } from \"./check\";\n\nfunction main() {\n    check(1);\n}",
+            )
+            .with_file(
+                "app/check.trs",
+                "export function check(n: int32) {\n    if (n > 0) {\n        panic();\n    }\n}",
+            );
+        let output = Compiler::new()
+            .resolver(resolver)
+            .compile_file(std::path::Path::new("app/main.trs"));
+        let result = output.rust_code.unwrap();
+        assert!(result.contains("panic!(\"explicit panic (at app/check.trs:3:9)\")"));
+    }
+
+    #[test]
+    fn test_compiler_rejects_throw_outside_result_function() {
+        let source = "function check(n: int32): int32 {\n    if (n < 0) {\n        throw \"negative\";\n    }\n    return n;\n}\n";
+        let output = Compiler::new().compile(source);
+        let diag = output.errors().next().expect("throw error");
+        assert_eq!(diag.code, "throw-outside-result");
+        assert!(diag.message.contains("`Result<T, E>`"));
+        assert_eq!(diag.span.map(|span| span.line_col(source)), Some((3, 9)));
+
+        let caught = source.replace("        throw \"negative\";", "        try {\n            throw \"negative\";\n        } catch (e) {\n            return 0;\n        }");
+        assert!(!Compiler::new().compile(&caught).has_errors());
+    }
+
+    #[test]
+    fn test_compile_tracks_trust_locations_when_enabled() {
+        let source = "function check(n: int32): Result<int32, string> {\n    if (n < 0) {\n        throw \"negative\";\n    }\n    return Ok(n);\n}\n\nfunction main() {\n    val items: int32[] = [1];\n    console.write(items[0]);\n}\n";
+        let plain = Compiler::new().compile(source).rust_code.unwrap();
+        assert!(!plain.contains("__trust_at"));
+        assert!(!plain.contains("set_hook"));
+
+        let tracked = Compiler::new()
+            .track_locations(true)
+            .compile(source)
+            .rust_code
+            .unwrap();
+        assert!(tracked.contains("static __TRUST_LOCATION: std::cell::Cell<&'static str>"));
+        assert!(tracked.contains("__trust_at(\"line 3:9\"); return Err(format!(\"{} (at line 3:9)\", \"negative\".to_string()));"));
+        assert!(tracked.contains("fn main() -> () {\n    __trust_install_panic_hook();\n    __trust_at(\"line 9:5\"); let items"));
+        assert!(tracked.contains("__trust_at(\"line 10:5\"); println!"));
+    }
+
+    #[test]
+    fn test_compiler_line_map_points_at_trust_statements() {
+        let source = "function main() {\n    val x = 1;\n    if (x > 0) {\n        console.write(x);\n    }\n}\n";
+        let output = Compiler::new()
+            .artifacts(&[Artifact::Rust, Artifact::LineMap])
+            .compile(source);
+        let rust = output.rust_code.unwrap();
+        let map = output.line_map.unwrap();
+        assert_eq!(
+            map.iter().map(|m| m.line).collect::<Vec<_>>(),
+            vec![2, 3, 4]
+        );
+        let print = &map[2];
+        assert_eq!((print.file.as_deref(), print.column), (None, 9));
+        assert!(rust
+            .lines()
+            .nth(print.rust_line - 1)
+            .unwrap()
+            .contains("println!(\"{}\", x);"));
+
+        let resolver = MemoryResolver::new().with_file("src/main.trs", source);
+        let output = Compiler::new()
+            .resolver(resolver)
+            .artifacts(&[Artifact::LineMap])
+            .compile_file(std::path::Path::new("src/main.trs"));
+        assert_eq!(
+            output.line_map.unwrap()[0].file.as_deref(),
+            Some("src/main.trs")
+        );
+    }
+
+    #[test]
+    fn test_lint_rules_and_config() {
+        let source = "struct my_point {\n    x: int32;\n}\n\nexport function area(w: int32, h: int32) {\n    val unused = w * 3;\n    val h = 4;\n    return w * h;\n}\n\nfunction main() {\n    val _ignored = 1;\n    for (i of [1, 2]) {\n        console.write(i);\n    }\n}\n";
+        let findings = lint::lint(source, &LintConfig::default()).unwrap();
+        let codes: Vec<&str> = findings.iter().map(|d| d.code).collect();
+        assert_eq!(
+            codes,
+            vec![
+                "struct-name-case",
+                "missing-return-type",
+                "unused-variable",
+                "shadowed-variable"
+            ]
+        );
+        assert!(findings.iter().all(|d| !d.is_error()));
+        let struct_span = findings[0].span.unwrap();
+        assert_eq!(&source[struct_span.start..struct_span.end], "my_point");
+        assert_eq!(
+            findings[0].fix.as_ref().unwrap().title,
+            "Rename `my_point` to `MyPoint`"
+        );
+        let exported = lint::lint(
+            "export struct my_point {\n    x: int32;\n}\n",
+            &LintConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(exported[0].code, "struct-name-case");
+        assert!(
+            exported[0].fix.is_none(),
+            "renaming would break the modules importing it"
+        );
+        assert_eq!(findings[2].span.unwrap().line_col(source), (6, 9));
+        assert_eq!(
+            findings[2].message,
+            "`unused` is never read; remove it or rename it `_unused`"
+        );
+
+        let mut config = LintConfig::default();
+        config.configure("magic-number", "error").unwrap();
+        config.configure("shadowed-variable", "off").unwrap();
+        config.configure("maxFunctionLines", "3").unwrap();
+        assert!(config.configure("no-such-rule", "warn").is_err());
+        assert!(config.configure("unused-variable", "loud").is_err());
+        let findings = lint::lint(source, &config).unwrap();
+        let magic: Vec<_> = findings
+            .iter()
+            .filter(|d| d.code == "magic-number")
+            .collect();
+        assert_eq!(magic.len(), 1);
+        assert!(magic[0].is_error() && magic[0].message.contains("`3`"));
+        assert!(!findings.iter().any(|d| d.code == "shadowed-variable"));
+        assert!(findings
+            .iter()
+            .any(|d| d.code == "long-function"
+                && d.message.starts_with("Function `area` is 5 lines")));
+
+        let broken = "function main() {\n    val x = ;\n}\n";
+        let error = lint::lint(broken, &LintConfig::default()).unwrap_err();
+        assert_eq!(error.code, "parse-error");
+        assert_eq!(error.span.unwrap().line_col(broken).0, 2);
+    }
+
+    #[test]
+    fn test_struct_name_fix_leaves_fields_of_the_same_name() {
+        let source = "struct my_point {\n    x: int32;\n}\n\nstruct Shape {\n    my_point: my_point;\n    label: string;\n}\n\nimplements my_point {\n    function shifted(dx: int32): my_point {\n        return my_point({ x: this.x + dx });\n    }\n}\n\nfunction main() {\n    val p: my_point = my_point({ x: 1 });\n    val ps: my_point[] = [p.shifted(1)];\n    val s = Shape({ my_point: p, label: \"my_point\" });\n    console.write(s.my_point.x, ps.length);\n}\n";
+        let findings = lint::lint(source, &LintConfig::default()).unwrap();
+        let fix = findings
+            .iter()
+            .find(|d| d.code == "struct-name-case")
+            .and_then(|d| d.fix.as_ref())
+            .expect("struct rename");
+        let (fixed, _) = apply_fixes(source, [fix]);
+        assert!(fixed.contains("struct MyPoint {"), "{}", fixed);
+        assert!(fixed.contains("    my_point: MyPoint;\n"), "{}", fixed);
+        assert!(fixed.contains("implements MyPoint {"), "{}", fixed);
+        assert!(fixed.contains("shifted(dx: int32): MyPoint {"), "{}", fixed);
+        assert!(
+            fixed.contains("return MyPoint({ x: this.x + dx });"),
+            "{}",
+            fixed
+        );
+        assert!(
+            fixed.contains("val p: MyPoint = MyPoint({ x: 1 });"),
+            "{}",
+            fixed
+        );
+        assert!(fixed.contains("val ps: MyPoint[] ="), "{}", fixed);
+        assert!(
+            fixed.contains("Shape({ my_point: p, label: \"my_point\" })"),
+            "{}",
+            fixed
+        );
+        assert!(fixed.contains("s.my_point.x"), "{}", fixed);
+        assert!(!Compiler::new().compile(&fixed).has_errors());
+    }
+
+    #[test]
+    fn test_fixes_rewrite_deprecations_and_lint_findings() {
+        let source = "function half(n: number): number {\n    val label = \"number\";\n    return n / 2;\n}\n";
+        let output = Compiler::new().compile(source);
+        let fix = output.diagnostics[0]
+            .fix
+            .as_ref()
+            .expect("deprecation carries a fix");
+        assert_eq!(fix.title, "Replace `number` with `int32`");
+        let (fixed, applied) = apply_fixes(source, [fix]);
+        assert_eq!(applied, 1);
+        assert!(fixed.contains("function half(n: int32): int32 {"));
+        assert!(
+            fixed.contains("val label = \"number\";"),
+            "strings are left alone"
+        );
+
+        let lints = lint::lint(&fixed, &LintConfig::default()).unwrap();
+        assert_eq!(lints[0].code, "unused-variable");
+        let (fixed, _) = apply_fixes(&fixed, lints.iter().filter_map(|d| d.fix.as_ref()));
+        assert!(fixed.contains("val _label = \"number\";"));
+
+        let overlapping = [
+            Fix::new("first", vec![TextEdit::new(Span::new(0, 8), "fn")]),
+            Fix::new("second", vec![TextEdit::new(Span::new(4, 12), "x")]),
+        ];
+        let (fixed, applied) = apply_fixes(source, &overlapping);
+        assert_eq!(applied, 1);
+        assert!(fixed.starts_with("fn half("));
+    }
+
+    #[test]
+    fn test_class_is_rewritten_as_struct_and_implements() {
+        let source = "@derive(PartialEq)\nclass Point {\n    x: int32; // across\n    y: int32\n\n    // Grid distance.\n    manhattan(): int32 {\n        return this.x + this.y;\n    }\n}\n\nclass Empty {}\n\nclass Person {\n    constructor(name: string) {}\n}\n\nfunction main() {\n    val p = Point({ x: 3, y: 4 });\n    console.write(p.manhattan(), Empty({}));\n}\n";
+        let output = Compiler::new().compile(source);
+        let classes: Vec<_> = output
+            .errors()
+            .map(|d| {
+                (
+                    d.code,
+                    &source[d.span.unwrap().start..d.span.unwrap().end],
+                    d.fix.is_some(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            classes,
+            [
+                ("unsupported-class", "class Point", true),
+                ("unsupported-class", "class Empty", true),
+                ("unsupported-class", "class Person", false)
+            ]
+        );
+        assert!(output.diagnostics[0]
+            .message
+            .contains("`struct Point { ... }`"));
+
+        let (fixed, applied) = apply_fixes(
+            source,
+            output.diagnostics.iter().filter_map(|d| d.fix.as_ref()),
+        );
+        assert_eq!(applied, 2);
+        assert!(fixed.starts_with("@derive(PartialEq)\nstruct Point {\n    x: int32; // across\n    y: int32\n}\n\nimplements Point {\n    // Grid distance.\n    function manhattan(): int32 {\n        return this.x + this.y;\n    }\n}\n\nstruct Empty {}\n"), "{}", fixed);
+        let fixed = fixed.replace("class Person {\n    constructor(name: string) {}\n}\n", "");
+        let rust = compile(&fixed).unwrap();
+        assert!(rust.contains("struct Empty {\n}"), "{}", rust);
+        assert!(rust.contains("fn manhattan(&self) -> i32"), "{}", rust);
+    }
+
+    #[test]
+    fn test_preprocessor_rewrites_only_code_tokens() {
+        let source = "struct Point { x: int32 }\n\nfunction main() {\n    val note = \"struct val and or\"; // val or\n    val total = 1 +\n        2;\n    val key = { val: 1, or: 2 };\n    wait worker;\n    val ok = total > 2 and note.and != `${total or 0}`;\n}\n";
+        let pre = preprocessor::preprocess(source);
+        assert!(pre.text.starts_with("interface Point {"));
+        assert!(pre
+            .text
+            .contains("let note = \"struct val and or\"; // val or"));
+        assert!(pre.text.contains("let total = 1 +\n        2;"));
+        assert!(pre.text.contains("let key = { val: 1, or: 2 };"));
+        assert!(pre.text.contains("await worker;"));
+        assert!(pre
+            .text
+            .contains("let ok = total > 2 && note.and != `${total || 0}`;"));
+        assert_eq!(pre.text.lines().count(), source.lines().count());
+
+        let out = pre.text.find("total >").unwrap();
+        let span = pre.original_span(Span::new(out, out + "total".len()));
+        assert_eq!(&source[span.start..span.end], "total");
+
+        // Parse errors are reported in the original source, past the shifted `struct` line.
+        let source = "struct P { x: int32 }; val q = );";
+        let output = Compiler::new().compile(source);
+        let span = output
+            .errors()
+            .next()
+            .and_then(|d| d.span)
+            .expect("parse error span");
+        assert_eq!(&source[span.start..span.start + 1], ")");
+    }
+
+    #[test]
+    fn test_compile_struct_rewrite_is_identifier_aware() {
+        let source = r#"
+            export struct Pair<T> {
+                first: T,
+                second: T
+            }
+
+            implements Pair<T> {
+                function label(): string {
+                    return "a struct value";
+                }
+            }
+
+            function destruct(data: int32): int32 {
+                return data;
+            }
+
+            function main() {
+                val p: Pair<int32> = Pair({ first: destruct(1), second: 2 });
+                console.write(p.second);
+            }
+        "#;
+        let result = compile(source).unwrap();
+        assert!(result.contains("struct Pair<T> {"));
+        assert!(result.contains("first: T,"));
+        assert!(result.contains("impl<T: Clone> Pair<T> {"));
+        assert!(result.contains("\"a struct value\""));
+        assert!(result.contains("fn destruct(data: i32) -> i32 {"));
+        assert!(result.contains("let p: Pair<i32> = Pair { first: destruct(1), second: 2 };"));
+    }
+
+    #[test]
+    fn test_compile_multi_declarations_and_late_initialization() {
+        let source = r#"
+            function classify(n: int32): string {
+                val label: string;
+                if (n > 0) {
+                    label = "positive";
+                } else {
+                    label = "other";
+                }
+                return label;
+            }
+
+            function main() {
+                var a, b: int32;
+                a = 1;
+                b = a + 1;
+                val total: int32;
+                loop {
+                    total = a + b;
+                    break;
+                }
+                console.write(classify(total));
+            }
+        "#;
+        let result = compile(source).unwrap();
+        assert!(result.contains("let label: String;"));
+        assert!(result.contains("let mut a: i32;\n"));
+        assert!(result.contains("let mut b: i32;"));
+        assert!(result.contains("let total: i32;"));
+
+        let maybe_unassigned = "function main() {\n    val x: int32;\n    if (true) {\n        x = 1;\n    }\n    console.write(x);\n}\n";
+        let output = Compiler::new().compile(maybe_unassigned);
+        let diag = output.errors().next().expect("read before assignment");
+        assert_eq!(diag.code, "unassigned-read");
+        let span = diag.span.unwrap();
+        assert_eq!(span.line_col(maybe_unassigned), (6, 19));
+
+        let assigned_in_loop = "function main() {\n    val x: int32;\n    for (i in 0..3) {\n        x = i;\n    }\n}\n";
+        let output = Compiler::new().compile(assigned_in_loop);
+        assert_eq!(
+            output.errors().next().map(|d| d.code),
+            Some("val-reassigned")
+        );
+    }
+
+    #[test]
+    fn test_compile_static_var_globals() {
+        let source = r#"
+            struct Config {
+                port: int32,
+            }
+
+            static var hits: int32 = 0;
+            static var seen: int32[] = [];
+            static var config = Config({ port: 80 });
+
+            function record(): int32 {
+                hits = hits + 1;
+                seen.push(hits);
+                config.port += 1;
+                return hits;
+            }
+
+            function main() {
+                val hits = record();
+                console.write(hits);
+            }
+        "#;
+        let result = compile(source).unwrap();
+        assert!(result.contains(
+            "static __trust_static_hits: std::sync::Mutex<i32> = std::sync::Mutex::new(0);"
+        ));
+        assert!(
+            result.contains("fn __trust_static_config() -> &'static std::sync::Mutex<Config> {")
+        );
+        assert!(result.contains("CELL.get_or_init(|| std::sync::Mutex::new(vec![]))"));
+        assert!(result.contains(
+            "{ let __trust_value = __trust_static_hits.lock().map(|value| value.clone()).unwrap() + 1; *__trust_static_hits.lock().unwrap() = __trust_value; }"
+        ));
+        assert!(result.contains("__trust_static_seen().lock().unwrap().push(__trust_static_hits.lock().map(|value| value.clone()).unwrap())"));
+        assert!(result.contains("__trust_static_config().lock().unwrap().port += 1"));
+        // A local of the same name shadows the static.
+        assert!(result.contains("let hits = record();"));
+        assert!(result.contains("println!(\"{}\", hits)"));
+
+        let nested = "function main() {\n    static var x = 1;\n}\n";
+        let output = Compiler::new().compile(nested);
+        assert_eq!(
+            output.errors().next().map(|d| d.code),
+            Some("nested-static")
+        );
+    }
+
+    #[test]
+    fn test_compile_static_var_reads_inside_the_lock() {
+        let source = r#"
+            struct Config {
+                name: string,
+                port: int32,
+            }
+
+            static var names: string[] = [];
+            static var config = Config({ name: "srv", port: 80 });
+
+            function main() {
+                val count = names.length + names.length;
+                val label = config.name;
+                val port = config.port;
+                val upper = config.name.toUpperCase();
+                val all = names;
+                console.write(count, label, port, upper, all.length);
+            }
+        "#;
+        let result = compile(source).unwrap();
+        // Only the field or result leaves the lock, not a clone of the whole value.
+        assert!(result.contains(
+            "let count = __trust_static_names().lock().map(|__trust_locked| __trust_locked.len()).unwrap() + __trust_static_names().lock().map(|__trust_locked| __trust_locked.len()).unwrap();"
+        ));
+        assert!(result.contains("let label = __trust_static_config().lock().map(|__trust_locked| __trust_locked.name.clone()).unwrap();"));
+        assert!(result.contains("let port = __trust_static_config().lock().map(|__trust_locked| __trust_locked.port).unwrap();"));
+        assert!(result.contains("let upper = __trust_static_config().lock().map(|__trust_locked| __trust_locked.name.to_uppercase()).unwrap();"));
+        // Taking the whole value still clones it.
+        assert!(result.contains(
+            "let all = __trust_static_names().lock().map(|value| value.clone()).unwrap();"
+        ));
+    }
+
+    #[test]
+    fn test_compile_const_expressions_and_const_functions() {
+        let source = r#"
+            const WIDTH = 8;
+            const HEIGHT: int32 = 4;
+            const AREA = WIDTH * HEIGHT;
+            const RATIO = WIDTH / 2.5;
+            const TOTAL: int64 = AREA * 1000;
+            const SCALE: float64 = 2;
+            const WIDE = WIDTH > HEIGHT;
+            const CELLS = cells(WIDTH, HEIGHT) + 1;
+
+            function cells(w: int32, h: int32): int32 {
+                return w * h;
+            }
+
+            function main() {
+                console.write(CELLS);
+            }
+        "#;
+        let result = compile(source).unwrap();
+        assert!(result.contains("const WIDTH: i32 = 8;"));
+        assert!(result.contains("const AREA: i32 = WIDTH * HEIGHT;"));
+        assert!(result.contains("const RATIO: f64 = (WIDTH as f64) / 2.5;"));
+        assert!(result.contains("const TOTAL: i64 = ((AREA * 1000) as i64);"));
+        assert!(result.contains("const SCALE: f64 = 2.0;"));
+        assert!(result.contains("const WIDE: bool = WIDTH > HEIGHT;"));
+        assert!(result.contains("const CELLS: i32 = cells(WIDTH, HEIGHT) + 1;"));
+        assert!(result.contains("const fn cells(w: i32, h: i32) -> i32 {\n    w * h\n}"));
+        assert!(result.contains("fn main() -> () {"));
+
+        let runtime_call =
+            "function now(): string {\n    return \"t\";\n}\nconst STARTED = now();\n";
+        let output = Compiler::new().compile(runtime_call);
+        let diag = output.errors().next().expect("not const");
+        assert_eq!(diag.code, "non-const-expression");
+        assert_eq!(diag.span.unwrap().line_col(runtime_call), (4, 17));
+
+        let forward = "const A = B + 1;\nconst B = 2;\n";
+        let output = Compiler::new().compile(forward);
+        assert_eq!(
+            output.errors().next().map(|d| d.code),
+            Some("non-const-expression")
+        );
+    }
+
+    #[test]
+    fn test_compile_enum_utilities() {
+        let source = r#"
+            enum Color {
+                Red = "red",
+                Green = "green",
+            }
+
+            enum Level {
+                Low,
+                High,
+            }
+
+            function main() {
+                for (c of Color.values()) {
+                    console.write(c);
+                }
+                val name = "High";
+                val level = Level.fromString(name);
+                console.write(Level.count());
+            }
+        "#;
+        let result = Compiler::new()
+            .enum_utilities(true)
+            .compile(source)
+            .into_result()
+            .unwrap()
+            .rust_code;
+        assert!(result.contains(
+            "pub fn values() -> Vec<Color> {\n        vec![Color::Red, Color::Green]\n    }"
+        ));
+        assert!(result.contains("\"green\" => Some(Color::Green),"));
+        assert!(result.contains("\"High\" => Some(Level::High),"));
+        assert!(result.contains("pub fn count() -> i32 {\n        2\n    }"));
+        assert!(result.contains("let level = Level::fromString(&name);"));
+        assert!(result.contains("Level::count()"));
+
+        let output = Compiler::new().compile(source);
+        let diag = output.errors().next().expect("utilities are opt-in");
+        assert_eq!(diag.code, "enum-utilities-disabled");
+        assert!(!compile("enum Level { Low }\n")
+            .unwrap()
+            .contains("fn values()"));
+    }
+
+    #[test]
+    fn test_compile_derive_annotations() {
+        let source = r#"
+            @derive(PartialEq, Eq, Hash)
+            struct Point {
+                x: int32,
+            }
+
+            @derive(Default)
+            export enum Level {
+                Low,
+                High,
+            }
+
+            @derive(Ord) struct Version { major: int32 }
+        "#;
+        let result = compile(source).unwrap();
+        assert!(result.contains("#[derive(Debug, Clone, PartialEq, Eq, Hash)]\nstruct Point {"));
+        assert!(result
+            .contains("#[derive(Debug, Clone, Default)]\nenum Level {\n    #[default]\n    Low,"));
+        assert!(result
+            .contains("#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]\nstruct Version {"));
+
+        let defaults = Compiler::new()
+            .derives(["PartialEq"])
+            .compile("struct A { x: int32 }\n");
+        assert!(defaults
+            .rust_code
+            .unwrap()
+            .contains("#[derive(Debug, Clone, PartialEq)]"));
+
+        let unknown = "@derive(Display)\nstruct A { x: int32 }\n";
+        let diag = Compiler::new()
+            .compile(unknown)
+            .errors()
+            .next()
+            .cloned()
+            .expect("not derivable");
+        assert_eq!(diag.code, "unknown-derive");
+        assert_eq!(diag.span.unwrap().line_col(unknown), (1, 1));
+
+        let misplaced = "@derive(Hash)\nfunction f() {}\n";
+        let output = Compiler::new().compile(misplaced);
+        assert_eq!(
+            output.errors().next().map(|d| d.code),
+            Some("misplaced-annotation")
+        );
+        let output = Compiler::new().compile("@inline\nstruct A { x: int32 }\n");
+        assert_eq!(
+            output.errors().next().map(|d| d.code),
+            Some("unknown-annotation")
+        );
+    }
+
+    #[test]
+    fn test_compile_rust_attr_annotations() {
+        let source = r##"
+            @rust_attr("#[inline(always)]")
+            function double(x: int32): int32 {
+                return x * 2;
+            }
+
+            @derive(PartialEq)
+            @rust_attr('#[repr(C)]')
+            struct Point {
+                x: int32,
+            }
+
+            @rust_attr("#[allow(dead_code)]")
+            enum Level { Low, High }
+
+            implements Point {
+                @rust_attr("#[must_use]")
+                function sum(): int32 {
+                    return this.x;
+                }
+            }
+        "##;
+        let result = compile(source).unwrap();
+        assert!(result.contains("#[inline(always)]\nconst fn double("));
+        assert!(result.contains("#[repr(C)]\n#[derive(Debug, Clone, PartialEq)]\nstruct Point {"));
+        assert!(result.contains("#[allow(dead_code)]\n#[derive(Debug, Clone)]\nenum Level {"));
+        assert!(result.contains("    #[must_use]\n    fn sum(&self)"));
+
+        let invalid = "@rust_attr(inline)\nfunction f() {}\n";
+        let diag = Compiler::new()
+            .compile(invalid)
+            .errors()
+            .next()
+            .cloned()
+            .expect("not an attribute");
+        assert_eq!(diag.code, "invalid-rust-attr");
+        assert_eq!(diag.span.unwrap().line_col(invalid), (1, 1));
+        let misplaced = "function main() {\n    @rust_attr(\"#[inline]\")\n    val x = 1;\n}\n";
+        let output = Compiler::new().compile(misplaced);
+        assert_eq!(
+            output.errors().next().map(|d| d.code),
+            Some("misplaced-annotation")
+        );
+    }
+
+    #[test]
+    fn test_compile_cfg_annotations() {
+        let source = r#"
+            @cfg(target_os = "windows")
+            function separator(): string {
+                return "\\";
+            }
+
+            @cfg(not(target_os = "windows"))
+            function separator(): string {
+                return "/";
+            }
+
+            @cfg(debug)
+            struct Trace {
+                label: string,
+            }
+
+            implements Trace {
+                function show(): void {
+                    console.write(this.label);
+                }
+            }
+
+            @cfg(feature = "metrics")
+            const SAMPLE_RATE = 10;
+
+            function main() {
+                @cfg(debug)
+                {
+                    val trace = Trace({ label: "start" });
+                    trace.show();
+                }
+                @cfg(all(unix, release))
+                console.write("optimized");
+                console.write(separator());
+            }
+        "#;
+        let result = compile(source).unwrap();
+        assert!(result.contains("#[cfg(target_os = \"windows\")]\nfn separator() -> String {"));
+        assert!(result.contains("#[cfg(not(target_os = \"windows\"))]\nfn separator() -> String {"));
+        assert!(
+            result.contains("#[cfg(debug_assertions)]\n#[derive(Debug, Clone)]\nstruct Trace {")
+        );
+        assert!(result.contains("#[cfg(debug_assertions)]\nimpl std::fmt::Display for Trace {"));
+        assert!(result.contains("#[cfg(debug_assertions)]\nimpl Trace {"));
+        assert!(result.contains("#[cfg(feature = \"metrics\")]\nconst SAMPLE_RATE: i32 = 10;"));
+        assert!(result.contains("#[cfg(debug_assertions)] {\n"));
+        assert!(result.contains("#[cfg(all(unix, not(debug_assertions)))] println!"));
+
+        let invalid = "@cfg(target_os)\nfunction f() {}\n";
+        let diag = Compiler::new()
+            .compile(invalid)
+            .errors()
+            .next()
+            .cloned()
+            .expect("no value");
+        assert_eq!(diag.code, "invalid-cfg");
+        assert_eq!(diag.span.unwrap().line_col(invalid), (1, 1));
+        let output = Compiler::new().compile("@cfg(not(debug, test))\nfunction f() {}\n");
+        assert_eq!(output.errors().next().map(|d| d.code), Some("invalid-cfg"));
+        let output =
+            Compiler::new().compile("function f() {\n    return 1 +\n        @cfg(debug) 2;\n}\n");
+        assert!(output.errors().next().is_some());
+    }
+
+    #[test]
+    fn test_compile_equality_derives_partial_eq() {
+        let source = r#"
+            struct Inner {
+                v: int32,
+            }
+
+            struct Point {
+                inner: Inner,
+            }
+
+            struct Node {
+                v: int32,
+            }
+
+            enum Color {
+                Red,
+                Blue,
+            }
+
+            struct Unused {
+                n: int32,
+            }
+
+            function main() {
+                val a = Point({ inner: Inner({ v: 2 }) });
+                val b = Point({ inner: Inner({ v: 2 }) });
+                console.write(a == b);
+                val c = Color.Red;
+                console.write(c != Color.Blue);
+                val p: Pointer<Node> = Node({ v: 1 });
+                val q: Pointer<Node> = Node({ v: 1 });
+                console.write(p == q);
+            }
+        "#;
+        let result = compile(source).unwrap();
+        assert!(result.contains("#[derive(Debug, Clone, PartialEq)]\nstruct Point {"));
+        assert!(result.contains("#[derive(Debug, Clone, PartialEq)]\nstruct Inner {"));
+        assert!(result.contains("#[derive(Debug, Clone, PartialEq)]\nstruct Node {"));
+        assert!(result.contains("#[derive(Debug, Clone, PartialEq)]\nenum Color {"));
+        assert!(result.contains("#[derive(Debug, Clone)]\nstruct Unused {"));
+        assert!(result.contains("println!(\"{}\", p == q)"));
+    }
+
+    #[test]
+    fn test_compile_struct_and_enum_display() {
+        let source = r#"
+            struct Point {
+                x: int32,
+            }
+
+            struct Money {
+                cents: int32,
+            }
+
+            implements Money {
+                function toString(): string {
+                    return `${this.cents} cents`;
+                }
+            }
+
+            struct Wrapper<T> {
+                value: T,
+            }
+
+            enum Level {
+                Low,
+            }
+
+            function main() {
+                val p = Point({ x: 1 });
+                console.write(p);
+                val label = string(Money({ cents: 5 }));
+                val n = 42;
+                val text = n.toString();
+            }
+        "#;
+        let result = compile(source).unwrap();
+        assert!(result.contains("impl std::fmt::Display for Point {\n    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {\n        write!(f, \"{:?}\", self)\n    }\n}"));
+        assert!(result.contains("impl std::fmt::Display for Money {\n    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {\n        f.write_str(&self.toString())\n    }\n}"));
+        assert!(result.contains("impl<T: std::fmt::Debug> std::fmt::Display for Wrapper<T> {"));
+        assert!(result.contains("impl std::fmt::Display for Level {"));
+        assert!(result.contains("println!(\"{}\", p)"));
+        assert!(result.contains("let text = n.to_string();"));
+    }
+
+    #[test]
+    fn test_compile_console_write_arities() {
+        let source = r#"
+            function main() {
+                val x: int32 = 4;
+                val items: int32[] = [1, 2];
+                console.write();
+                console.write("x =", x);
+                console.write("x = {}, padded = {:>4}", x, x);
+                console.write(items);
+                console.writeInline("no newline");
+            }
+        "#;
+        let result = compile(source).unwrap();
+        assert!(result.contains("println!();"));
+        assert!(result.contains("println!(\"{} {}\", \"x =\".to_string(), x)"));
+        assert!(result.contains("println!(\"x = {}, padded = {:>4}\", x, x)"));
+        assert!(result.contains("println!(\"{:?}\", items)"));
+        assert!(result.contains("{ print!(\"{}\", \"no newline\""));
+        assert!(result.contains("std::io::Write::flush(&mut std::io::stdout()).ok(); }"));
+
+        let error = compile("function main() { console.write(\"{} and {}\", 1); }").unwrap_err();
+        assert!(error.to_string().contains("2 placeholders but 1 value"));
+    }
+
+    #[test]
+    fn test_compile_trusty_time_parse_and_format() {
+        let trust_code = r#"
+            import { Date, DateTime, format, durationBetween } from "trusty:time";
+
+            function main() {
+                val start = Date.parse("01/02/2026", "DD/MM/YYYY");
+                val end = DateTime.parseIso("2026-02-03T12:00:00+01:00").unwrap();
+                val text = format(end, "DD.MM.YYYY HH:mm");
+                val gap = durationBetween(start.unwrap(), end);
+                console.write(start);
+            }
+        "#;
+
+        let result = compile(trust_code).unwrap();
+        assert!(result.contains(
+            "pub fn parse(text: impl AsRef<str>, pattern: impl AsRef<str>) -> Option<Date>"
+        ));
+        assert!(result.contains("pub fn parseIso(text: impl AsRef<str>) -> Option<DateTime>"));
+        assert!(result.contains(
+            "fn format<T: __TrustTimePoint>(value: T, pattern: impl AsRef<str>) -> String"
+        ));
+        assert!(result.contains(
+            "fn durationBetween<A: __TrustTimePoint, B: __TrustTimePoint>(a: A, b: B) -> Duration"
+        ));
+        assert!(result.contains(
+            "let start = Date::parse(\"01/02/2026\".to_string(), \"DD/MM/YYYY\".to_string());"
+        ));
+        assert!(result.contains("let text = format(end, \"DD.MM.YYYY HH:mm\".to_string());"));
+        assert!(result.contains("println!(\"{:?}\", start)"));
+    }
+
+    #[test]
+    fn test_compile_trusty_time_zoned_date_time() {
+        let trust_code = r#"
+            import { ZonedDateTime } from "trusty:time";
+
+            function main() {
+                val paris = ZonedDateTime.parseIso("2026-03-01T10:30:00+01:00");
+                val utc = paris.unwrap().toUtc();
+                val local = utc.toLocal();
+                val same = local == utc;
+                console.write(paris);
+            }
+        "#;
+
+        let result = compile(trust_code).unwrap();
+        assert!(result.contains(
+            "pub struct ZonedDateTime {\n    pub utc: DateTime,\n    pub offset: i32,\n}"
+        ));
+        assert!(result.contains("impl Ord for ZonedDateTime {\n    fn cmp(&self, other: &ZonedDateTime) -> std::cmp::Ordering {\n        self.utc.cmp(&other.utc)"));
+        assert!(result.contains("pub fn toLocal(&self) -> ZonedDateTime"));
+        assert!(result.contains(
+            "let paris = ZonedDateTime::parseIso(\"2026-03-01T10:30:00+01:00\".to_string());"
+        ));
+        assert!(result.contains("let local = utc.toLocal();"));
+        assert!(result.contains("println!(\"{:?}\", paris)"));
+    }
+
+    #[test]
+    fn test_compile_trusty_time_timers_and_stopwatch() {
+        let trust_code = r#"
+            import { Stopwatch, setTimeout, setInterval } from "trusty:time";
+
+            function main() {
+                val watch = Stopwatch.start();
+                val ticker = setInterval(() => {
+                    console.write("tick");
+                }, 100);
+                val once = setTimeout(() => {
+                    ticker.cancel();
+                }, 350);
+                once.wait();
+                val lap = watch.lap();
+            }
+        "#;
+
+        let result = compile(trust_code).unwrap();
+        assert!(result.contains(
+            "fn setTimeout<F: FnOnce() + Send + 'static>(callback: F, ms: i32) -> TimerHandle"
+        ));
+        assert!(result.contains(
+            "fn setInterval<F: FnMut() + Send + 'static>(mut callback: F, ms: i32) -> TimerHandle"
+        ));
+        assert!(result.contains("pub fn lap(&self) -> Duration"));
+        assert!(result.contains("let watch = Stopwatch::start();"));
+        assert!(result.contains("let ticker = setInterval(move || {"));
+        assert!(result.contains("once.wait();"));
+    }
+
+    #[test]
+    fn test_compile_trusty_rand_seeded_rng_and_distributions() {
+        let trust_code = r#"
+            import { Rng, normal, randomHex } from "trusty:rand";
+
+            function main() {
+                val rng = Rng.withSeed(42);
+                val roll = rng.int(1, 6);
+                val order = rng.shuffle([1, 2, 3]);
+                val noise = normal(0, 1);
+                console.write(order, randomHex(8));
+            }
+        "#;
+
+        let result = compile(trust_code).unwrap();
+        assert!(result.contains("use rand::Rng as _;"));
+        assert!(result
+            .contains("pub struct Rng {\n    inner: std::cell::RefCell<rand::rngs::StdRng>,\n}"));
+        assert!(result.contains("rand::rngs::StdRng::seed_from_u64(seed.into() as u64)"));
+        assert!(result.contains("pub fn normal(mean: impl Into<f64>, std: impl Into<f64>) -> f64"));
+        assert!(result.contains("let rng = Rng::withSeed(42);"));
+        assert!(result.contains("let roll = rng.int(1, 6);"));
+        assert!(result.contains("println!(\"{:?} {}\", order, randomHex(8))"));
+    }
+
+    #[test]
+    fn test_compile_trusty_rand_follows_pinned_rand_version() {
+        let source = r#"
+            import { randomInt, Rng } from "trusty:rand";
+
+            function main() {
+                console.write(randomInt(1, 6), Rng.withSeed(1).float());
+            }
+        "#;
+
+        let output = Compiler::new().compile(source);
+        let result = output.rust_code.unwrap();
+        assert!(
+            result.contains("use rand::distr::{weighted::WeightedIndex, Bernoulli, Distribution};")
+        );
+        assert!(result.contains("let mut rng = rand::rng();"));
+        assert!(result.contains("rng.random_range(min..=max)"));
+        assert!(result.contains("rand::rngs::StdRng::from_os_rng()"));
+        assert!(!result.contains("thread_rng"));
+
+        let output = Compiler::new()
+            .dependencies([("rand", "^0.8.5")])
+            .compile(source);
+        let result = output.rust_code.unwrap();
+        assert!(
+            result.contains("use rand::distributions::{Bernoulli, Distribution, WeightedIndex};")
+        );
+        assert!(result.contains("let mut rng = rand::thread_rng();"));
+        assert!(result.contains("rng.gen_range(min..=max)"));
+        assert!(result.contains("rand::rngs::StdRng::from_entropy()"));
+        assert_eq!(
+            Compiler::new()
+                .dependencies([("rand", "0.9")])
+                .compile(source)
+                .rust_code
+                .unwrap(),
+            Compiler::new().compile(source).rust_code.unwrap()
+        );
+
+        let output = Compiler::new()
+            .dependencies([("rand", "0.7")])
+            .compile(source);
+        let error = output.errors().next().unwrap();
+        assert_eq!(error.code, "stdlib-version");
+        assert!(error.span.is_some());
+    }
+
+    #[test]
+    fn test_compile_reports_stdlib_crate_versions() {
+        let source = r#"
+            import { randomInt } from "trusty:rand";
+            import { stringify } from "trusty:json";
+
+            function main() {
+                console.write(randomInt(1, 6));
+            }
+        "#;
+
+        let output = compile_full(source).unwrap();
+        assert!(output
+            .crate_versions
+            .contains(&("rand".to_string(), "0.9".to_string())));
+        assert!(output
+            .crate_versions
+            .contains(&("serde_json".to_string(), "1".to_string())));
+
+        let output = Compiler::new()
+            .dependencies([("rand", "0.8")])
+            .compile(source);
+        assert!(output
+            .crate_versions
+            .contains(&("rand".to_string(), "0.8".to_string())));
+    }
+
+    #[test]
+    fn test_compile_rejects_unknown_stdlib_imports() {
+        let output = Compiler::new()
+            .compile("import { sqrrt } from \"trusty:math\";\n\nfunction main() {\n}\n");
+        let error = output.errors().next().unwrap();
+        assert_eq!(error.code, "unknown-import");
+        assert_eq!(
+            error.message,
+            "`trusty:math` has no export named `sqrrt`; did you mean `sqrt`?"
+        );
+
+        let output = Compiler::new()
+            .compile("import { teleport } from \"trusty:time\";\n\nfunction main() {\n}\n");
+        assert_eq!(
+            output.errors().next().unwrap().message,
+            "`trusty:time` has no export named `teleport`"
+        );
+
+        let source = "import { Duration, sleep } from \"trusty:time\";\nimport { sqrt as root } from \"trusty:math\";\n\nfunction main() {\n    console.write(root(16));\n}\n";
+        assert!(!Compiler::new().compile(source).has_errors());
+
+        for module in stdlib::MODULES {
+            assert!(
+                stdlib::exports(module).is_some_and(|exports| !exports.is_empty()),
+                "{}",
+                module
+            );
+        }
+        assert!(stdlib::exports("http").unwrap().contains(&"HttpServer"));
+        assert!(stdlib::exports("json").unwrap().contains(&"stringify"));
+    }
+
+    #[test]
+    fn test_compile_warns_about_unknown_string_methods() {
+        let source = "function main() {\n    val name = \"ada\";\n    console.write(name.toUppercase(), name.startWith(\"a\"), name.chars().count(), name.toUpperCase());\n}\n";
+        let output = Compiler::new().compile(source);
+        assert!(!output.has_errors());
+        let warnings: Vec<_> = output.warnings().collect();
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].code, "unknown-method");
+        assert_eq!(
+            warnings[0].message,
+            "unknown method `toUppercase` for type string; did you mean `toUpperCase`?"
+        );
+        assert_eq!(
+            warnings[0].span.map(|span| &source[span.start..span.end]),
+            Some("toUppercase")
+        );
+        assert_eq!(
+            warnings[1].message,
+            "unknown method `startWith` for type string; did you mean `startsWith`?"
+        );
+
+        let output = Compiler::new().compile(
+            "function main() {\n    val name = \"ada\";\n    console.write(name.shoutLoud());\n}\n",
+        );
+        assert_eq!(
+            output.warnings().next().unwrap().message,
+            "unknown method `shoutLoud` for type string"
+        );
+    }
+
+    #[test]
+    fn test_compile_diagnostics_in_french() {
+        let source = "function main() {\n    val name = \"ada\";\n    console.write(name.toUppercase());\n}\n";
+        let output = Compiler::new().locale(Locale::Fr).compile(source);
+        let warning = output.warnings().next().unwrap();
+        assert_eq!(warning.code, "unknown-method");
+        assert_eq!(warning.message, "méthode `toUppercase` inconnue pour le type string ; vouliez-vous dire `toUpperCase` ?");
+
+        let output = Compiler::new()
+            .locale(Locale::Fr)
+            .compile("function main() {\n    val x = 1 +;\n}\n");
+        assert_eq!(
+            output.errors().next().unwrap().message,
+            "Erreur de syntaxe : Expression expected"
+        );
+    }
+
+    #[test]
+    fn test_compile_rejects_unsupported_constructs() {
+        let source = "function main() {\n    var x = 1;\n    [x] = [2];\n    switch (x) { case 1: break; }\n    val r = /ab+c/;\n    ;\n}\n";
+        let output = Compiler::new().compile(source);
+        let error = output.errors().next().unwrap();
+        assert_eq!(error.code, "unsupported-assignment");
+        assert!(output.rust_code.is_none());
+
+        // `--allow-unknown`: every construct is reported, and the rest of the program still compiles.
+        let output = Compiler::new().allow_unknown(true).compile(source);
+        let warnings: Vec<_> = output
+            .warnings()
+            .map(|w| {
+                (
+                    w.code,
+                    w.message.as_str(),
+                    &source[w.span.unwrap().start..w.span.unwrap().end],
+                )
+            })
+            .collect();
+        assert_eq!(
+            warnings,
+            [
+                (
+                    "unsupported-assignment",
+                    "Only a variable or a field can be assigned to, not `[x]`",
+                    "[x]"
+                ),
+                (
+                    "unsupported-statement",
+                    "`switch` is not supported in TRUST",
+                    "switch (x) { case 1: break; }"
+                ),
+                (
+                    "unsupported-expression",
+                    "This expression is not supported in TRUST: `/ab+c/`",
+                    "/ab+c/"
+                ),
+            ]
+        );
+        assert!(!output.rust_code.unwrap().contains("non supporté"));
+    }
+
+    #[test]
+    fn test_compile_nullish_operators() {
+        let source = "function main() {\n    val limit: Option<int32> = null;\n    var done = false;\n    done ||= (limit ?? 0) > 2;\n    console.write(done, (1 + 2) * 3);\n}\n";
+        let result = compile(source).unwrap();
+        assert!(
+            result.contains("done = done || ((limit.unwrap_or_else(|| 0)) > 2);"),
+            "{}",
+            result
+        );
+        assert!(result.contains("(1 + 2) * 3"), "{}", result);
+
+        let errors = |source: &str| {
+            let output = Compiler::new().compile(source);
+            output
+                .errors()
+                .map(|d| (d.code, d.message.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            errors("function main() {\n    var name = \"a\";\n    name ||= \"b\";\n}\n"),
+            [("logical-assign-non-bool", "`||=` needs a `bool` on its left, but this is a `String`; use `??=` to fill in a missing optional value".to_string())]
+        );
+        assert_eq!(
+            errors("function main() {\n    val n = 1;\n    console.write(n?.toString());\n}\n")[0]
+                .0,
+            "optional-chain-non-optional"
+        );
+        assert_eq!(
+            errors("function main() {\n    val n = 1;\n    console.write(n >>> 2);\n}\n"),
+            [(
+                "unsupported-operator",
+                "The `>>>` operator is not supported".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_compile_infers_structs_for_object_literals() {
+        let source = "struct Config { name: string }\n\nfunction main() {\n    val config = { host: \"x\", port: 8080 };\n    val other = { host: \"y\", port: 1 };\n    val loose = { \"content-type\": \"json\" };\n    console.write(config.port, other.host == config.host, loose);\n}\n";
+        let result = compile(source).unwrap();
+        // `Config` is taken by a struct with other fields; `other` reuses the first literal's struct.
+        assert!(
+            result.contains("struct Config2 {\n    host: String,\n    port: i32,\n}"),
+            "{}",
+            result
+        );
+        assert!(!result.contains("struct Other"), "{}", result);
+        assert!(
+            result.contains("let config = Config2 { host: \"x\".to_string(), port: 8080 };"),
+            "{}",
+            result
+        );
+        assert!(result.contains("let other = Config2 {"), "{}", result);
+        assert!(
+            result.contains(
+                "let loose = serde_json::json!({\"content-type\": \"json\".to_string()});"
+            ),
+            "{}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_compile_types_array_callback_parameters() {
+        let source = "struct User { name: string }\n\nfunction main() {\n    val users: User[] = [User({ name: \"ada\" })];\n    val lengths = users.map(u => u.name.length);\n    console.write(lengths, users.filter(u => u.name.startsWith(\"a\")).length);\n}\n";
+        let result = compile(source).unwrap();
+        assert!(
+            result.contains("let lengths = users.iter().cloned().map(move |u: User| u.name.chars().count() as i32).collect::<Vec<_>>();"),
+            "{}",
+            result
+        );
+        // `lengths` is known to be a `Vec<i32>`, printed with `{:?}`.
+        assert!(
+            result.contains("println!(\"{:?} {}\", lengths,"),
+            "{}",
+            result
+        );
+        assert!(
+            result.contains("(move |u: User| u.name.starts_with("),
+            "{}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_compile_sorts_arrays() {
+        let source = "struct User {\n    name: string,\n}\n\nfunction main() {\n    var xs: float64[] = [2.5, 0.5];\n    xs.sort();\n    var ns = [3, 1];\n    ns.sort((a, b) => b - a);\n    var users = [User({ name: \"bo\" })];\n    users.sort((a, b) => a.name.length - b.name.length);\n    console.write(xs, ns, -ns[0], !(ns.length > 1), users.length);\n}\n";
+        let result = compile(source).unwrap();
+        assert!(result.contains("let mut xs: Vec<f64>"), "{}", result);
+        assert!(result.contains("xs.sort_by(|__trust_a, __trust_b| __trust_a.partial_cmp(__trust_b).unwrap_or(std::cmp::Ordering::Equal));"), "{}", result);
+        assert!(
+            result.contains("{ let __trust_compare = move |a: i32, b: i32| b - a; ns.sort_by(|__trust_a, __trust_b| __trust_compare(*__trust_a, *__trust_b).cmp(&0)) };"),
+            "{}",
+            result
+        );
+        // Elements that are not `Copy` are compared through the references `sort_by` gives.
+        assert!(
+            result.contains("{ let __trust_compare = move |a: &User, b: &User| a.name.chars().count() as i32 - b.name.chars().count() as i32; users.sort_by(|__trust_a, __trust_b| __trust_compare(__trust_a, __trust_b).cmp(&0)) };"),
+            "{}",
+            result
+        );
+        assert!(
+            result.contains("-ns[0 as usize], !(ns.len() > 1)"),
+            "{}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_compile_picks_string_or_array_methods_by_inferred_type() {
+        let source = "function greet(name: string): string {\n    return `hi ${name}`;\n}\n\nfunction tags(): string[] {\n    return [\"a\"];\n}\n\nfunction main() {\n    console.write(greet(\"x\").trim().includes(\"hi\"), tags().includes(\"a\"), (greet(\"y\")).length, tags().slice(1));\n}\n";
+        let result = compile(source).unwrap();
+        assert!(result.contains("greet(\"x\".to_string()).trim().to_string().contains((\"hi\".to_string()).as_str())"), "{}", result);
+        assert!(
+            result.contains("tags().contains(&\"a\".to_string())"),
+            "{}",
+            result
+        );
+        assert!(
+            result.contains("(greet(\"y\".to_string())).chars().count() as i32"),
+            "{}",
+            result
+        );
+        assert!(
+            result.contains(
+                "{ let __trust_items = &tags(); let __trust_len = __trust_items.len() as isize;"
+            ),
+            "{}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_compile_types_stdlib_method_chains() {
+        let source = "import { Date } from \"trusty:time\";\n\nfunction main() {\n    val iso = Date.fromYmd(2024, 2, 28).addDays(1).toIsoString();\n    console.write(iso.length, Date.today().toIsoString().includes(\"-\"), string(7).toUpperCase());\n}\n";
+        let result = compile(source).unwrap();
+        assert!(result.contains("iso.chars().count() as i32"), "{}", result);
+        assert!(
+            result.contains("Date::today().toIsoString().contains((\"-\".to_string()).as_str())"),
+            "{}",
+            result
+        );
+        assert!(
+            result.contains("(7).to_string().to_uppercase()"),
+            "{}",
+            result
+        );
+
+        assert_eq!(
+            stdlib::method_return_type("HttpResponse", "json", true).as_deref(),
+            Some("Value")
+        );
+        assert_eq!(
+            stdlib::method_return_type("Rng", "withSeed", false).as_deref(),
+            Some("Rng")
+        );
+        // `addDays` takes `self`; `get` is generic over its handler and returns nothing.
+        assert_eq!(stdlib::method_return_type("Date", "addDays", false), None);
+        assert_eq!(stdlib::method_return_type("HttpServer", "get", true), None);
+    }
+
+    #[test]
+    fn test_compile_parses_arguments_with_trusty_args() {
+        let source = "import { ArgParser } from \"trusty:args\";\n\nfunction main() {\n    val args = ArgParser.create(\"serve\").flag(\"verbose\").option(\"port\", \"8080\").help(\"Port to listen on\").parse();\n    val port = args.getInt(\"port\");\n    console.write(port + 1, args.flag(\"verbose\"));\n}\n";
+        let result = compile(source).unwrap();
+        assert!(
+            result.contains("ArgParser::create(\"serve\".to_string()).flag(\"verbose\".to_string()).option(\"port\".to_string(), \"8080\".to_string())"),
+            "{}",
+            result
+        );
+        assert!(result.contains("pub struct ParsedArgs"), "{}", result);
+        assert!(!result.contains("extern crate"), "{}", result);
+
+        assert_eq!(
+            stdlib::exports("args").unwrap(),
+            vec!["ArgParser", "ParsedArgs"]
+        );
+        assert_eq!(
+            stdlib::method_return_type("ArgParser", "parse", true).as_deref(),
+            Some("ParsedArgs")
+        );
+    }
+
+    #[test]
+    fn test_compile_styles_text_with_trusty_color() {
+        let source = "import { red, bold, styled, style } from \"trusty:color\";\n\nfunction main() {\n    var warn = style();\n    warn.bold = true;\n    val line = red(\"error:\");\n    console.write(line.length, bold(\"ok\"), styled(\"careful\", warn));\n}\n";
+        let result = compile(source).unwrap();
+        assert!(result.contains("let mut warn = style();"), "{}", result);
+        assert!(result.contains("line.chars().count() as i32"), "{}", result);
+        assert!(
+            result.contains("pub fn colorEnabled() -> bool"),
+            "{}",
+            result
+        );
+        // Tree-shaken: only the helpers the program calls are emitted.
+        assert!(!result.contains("pub fn green("), "{}", result);
+
+        assert!(stdlib::exports("color")
+            .unwrap()
+            .contains(&"setColorEnabled"));
+    }
+
+    #[test]
+    fn test_compile_trusty_tcp_and_udp_sockets() {
+        let source = "import { TcpListener, TcpStream } from \"trusty:tcp\";\nimport { UdpSocket } from \"trusty:udp\";\n\nfunction main() {\n    val listener = TcpListener.bind(7000);\n    val conn = listener.accept();\n    conn.setTimeout(5000);\n    val line = conn.readLine();\n    conn.write(`${line.toUpperCase()}\\n`);\n    val socket = UdpSocket.bind(0);\n    socket.sendTo(\"ping\", \"127.0.0.1\", 9000);\n    console.write(socket.receive().text.length);\n}\n";
+        let result = compile(source).unwrap();
+        assert!(result.contains("let line = conn.readLine();"), "{}", result);
+        assert!(result.contains("line.to_uppercase()"), "{}", result);
+        assert!(
+            result.contains("socket.receive().text.chars().count() as i32"),
+            "{}",
+            result
+        );
+        assert!(!result.contains("extern crate"), "{}", result);
+
+        assert_eq!(
+            stdlib::method_return_type("TcpListener", "accept", true).as_deref(),
+            Some("TcpStream")
+        );
+        assert_eq!(
+            stdlib::method_return_type("UdpSocket", "bind", false).as_deref(),
+            Some("UdpSocket")
+        );
+        assert_eq!(
+            stdlib::exports("udp").unwrap(),
+            vec!["UdpSocket", "Datagram"]
+        );
+    }
+
+    #[test]
+    fn test_compile_links_stdlib_from_runtime_crate() {
+        let source = r#"
+            import { Date } from "trusty:time";
+            import { randomInt } from "trusty:rand";
+            import math from "trusty:math";
+
+            function main() {
+                console.write(Date.fromYmd(2024, 2, 29).toIsoString());
+                console.write(randomInt(1, 6));
+                console.write(math.sqrt(16));
+            }
+        "#;
+
+        let output = Compiler::new().runtime_crate(true).compile(source);
+        let rust_code = output.rust_code.unwrap();
+        assert!(
+            rust_code.contains("use std::time::{Instant, Duration, SystemTime as RustSystemTime};")
+        );
+        assert!(rust_code.contains("use trusty_runtime::time::*;"));
+        assert!(rust_code.contains("use trusty_runtime::rand::*;"));
+        assert!(rust_code.contains("use trusty_runtime::math as math;"));
+        assert!(!rust_code.contains("pub struct Date"));
+        assert!(output
+            .required_crates
+            .contains(&"trusty_runtime".to_string()));
+        assert!(output
+            .crate_versions
+            .contains(&("trusty_runtime".to_string(), "0.1".to_string())));
+        assert_eq!(output.runtime_features, ["rand"]);
+
+        // The crate is built against rand 0.9, so a project on 0.8 still gets the code pasted.
+        let output = Compiler::new()
+            .runtime_crate(true)
+            .dependencies([("rand", "0.8")])
+            .compile(source);
+        let rust_code = output.rust_code.unwrap();
+        assert!(rust_code.contains("pub fn randomInt(min: i32, max: i32) -> i32"));
+        assert!(output.runtime_features.is_empty());
+    }
+
+    #[test]
+    fn test_compile_trusty_math_rounding_and_integer_helpers() {
+        let trust_code = r#"
+            import { floor, round, sign, hypot, atan2, log10, gcd, lcm, factorial, isNaN } from "trusty:math";
+
+            function main() {
+                val n: int32 = 12;
+                val r = round(2.5);
+                val s = sign(n);
+                val g = gcd(n, 18);
+                val f = factorial(5);
+                console.write(floor(r), hypot(3, 4), atan2(1, 1), log10(100), lcm(g, 4), isNaN(r));
+            }
+        "#;
+
+        let result = compile(trust_code).unwrap();
+        assert!(result.contains("pub fn round<T: Into<f64>>(x: T) -> f64"));
+        assert!(
+            result.contains("pub fn sign<T: PartialOrd + Default + From<i8> + Copy>(x: T) -> T")
+        );
+        assert!(result.contains("pub fn gcd<T: __TrustMathAbs + Copy + PartialEq + Default + std::ops::Rem<Output = T>>(a: T, b: T) -> T"));
+        assert!(result.contains("pub fn factorial<T: Into<i64>>(n: T) -> i64"));
+        assert!(result.contains("pub fn isNaN<T: Into<f64>>(x: T) -> bool"));
+        assert!(result.contains("let g = gcd(n, 18);"));
+        assert!(result.contains("println!(\"{} {} {} {} {} {}\", floor(r), hypot(3, 4), atan2(1, 1), log10(100), lcm(g, 4), isNaN(r))"));
+    }
+
+    #[test]
+    fn test_compile_decimal_type_and_literals() {
+        let trust_code = r#"
+            function withTax(amount: decimal): decimal {
+                return amount * 1.2d;
+            }
+
+            function main() {
+                var total: decimal = 0;
+                total += 19.99d;
+                val taxed = withTax(total + 1);
+                console.write(taxed.toFixed(2), 0x1d, float64(taxed));
+            }
+        "#;
+
+        let result = compile(trust_code).unwrap();
+        assert!(
+            result.contains("fn withTax(amount: rust_decimal::Decimal) -> rust_decimal::Decimal")
+        );
+        assert!(
+            result.contains("return amount * rust_decimal::Decimal::from_i128_with_scale(12, 1);")
+        );
+        assert!(result.contains("let mut total: rust_decimal::Decimal = rust_decimal::Decimal::from_i128_with_scale(0, 0);"));
+        assert!(result.contains("total += rust_decimal::Decimal::from_i128_with_scale(1999, 2);"));
+        assert!(
+            result.contains("withTax(total + rust_decimal::Decimal::from_i128_with_scale(1, 0))")
+        );
+        assert!(result.contains("format!(\"{:.*}\", (2) as usize, taxed.round_dp_with_strategy((2) as u32, rust_decimal::RoundingStrategy::MidpointAwayFromZero))"));
+        assert!(result.contains(
+            ", 29, (rust_decimal::prelude::ToPrimitive::to_f64(&taxed).unwrap_or(0.0)) as f64)"
+        ));
+
+        let float_mix =
+            compile("val f = float64(2);\nval d = 1.5d;\nconsole.write(d * f);").unwrap_err();
+        assert!(float_mix
+            .to_string()
+            .contains("A float does not convert to `decimal` implicitly"));
+    }
+
+    #[test]
+    fn test_compile_overflow_modes() {
+        let trust_code = r#"
+            static var total: int32 = 0;
+
+            function main() {
+                var x: int8 = int8(120);
+                x += 5;
+                val y = 2147483600 + 100;
+                total *= 2;
+                val f = float64(1) + 2;
+                console.write(x, y, f, (1 + 2) * 3);
+            }
+        "#;
+
+        let wrap = Compiler::new()
+            .compile(trust_code)
+            .into_result()
+            .unwrap()
+            .rust_code;
+        assert!(wrap.contains("x += 5;"));
+        assert!(wrap.contains("let y = 2147483600 + 100;"));
+
+        let checked = Compiler::new()
+            .overflow(Overflow::Checked)
+            .compile(trust_code)
+            .into_result()
+            .unwrap()
+            .rust_code;
+        assert!(
+            checked.contains("x = i8::checked_add(x, 5).expect(\"attempt to add with overflow\");")
+        );
+        assert!(checked.contains(
+            "let y = i32::checked_add(2147483600, 100).expect(\"attempt to add with overflow\");"
+        ));
+        assert!(checked.contains("let __trust_place = &mut *__trust_static_total.lock().unwrap(); *__trust_place = i32::checked_mul(*__trust_place, __trust_value).expect(\"attempt to multiply with overflow\");"));
+        assert!(checked.contains("let f = (1) as f64 + 2;"));
+
+        let saturating = Compiler::new()
+            .overflow(Overflow::Saturating)
+            .compile(trust_code)
+            .into_result()
+            .unwrap()
+            .rust_code;
+        assert!(saturating.contains("x = i8::saturating_add(x, 5);"));
+        assert!(saturating.contains("i32::saturating_mul(i32::saturating_add(1, 2), 3)"));
+    }
+
+    #[test]
+    fn test_compile_bytes_type_and_literals() {
+        let trust_code = r#"
+            function main() {
+                val magic = hex"89 50 4e 47";
+                val raw = b"hi\x00\"";
+                var buf: bytes = [1, 2, 3];
+                buf[0] = 255;
+                val text = bytes.fromString("aGk=", "base64");
+                console.write(magic.slice(1), raw.toString(), text.toString("hex"), magic[0]);
+            }
+        "#;
+
+        let result = compile(trust_code).unwrap();
+        assert!(result.contains("let magic = b\"\\x89PNG\".to_vec();"));
+        assert!(result.contains("let raw = b\"hi\\x00\\\"\".to_vec();"));
+        assert!(result.contains("let mut buf: Vec<u8> = vec![1, 2, 3];"));
+        assert!(result.contains(
+            "let text = __trust_bytes_from_string(&\"aGk=\".to_string(), &\"base64\".to_string());"
+        ));
+        assert!(result.contains(
+            "{ let __trust_len = magic.len() as isize; let __trust_from = (1) as isize;"
+        ));
+        assert!(result.contains("String::from_utf8_lossy(&raw).to_string()"));
+        assert!(result.contains("__trust_bytes_to_string(&text, &\"hex\".to_string())"));
+        assert!(
+            result.contains("fn __trust_bytes_to_string(bytes: &[u8], encoding: &str) -> String")
+        );
+
+        let odd_hex = compile("val x = hex\"abc\";").unwrap_err();
+        assert!(odd_hex.to_string().contains("needs pairs of hex digits"));
+        let encoding = compile("val x = b\"a\".toString(\"utf16\");").unwrap_err();
+        assert!(encoding.to_string().contains("Unknown encoding `utf16`"));
+    }
+
+    #[test]
+    fn test_compile_iterable_protocol_and_lazy_iter() {
+        let src = r#"
+interface Countdown {
+    current: int32;
+}
+
+implements Countdown: Iterable<int32> {
+    function hasNext(): boolean {
+        return this.current > 0;
+    }
+
+    function next(): int32 {
+        this.current = this.current - 1;
+        return this.current + 1;
+    }
+}
+
+function main() {
+    val c = Countdown({ current: 3 });
+    for (val n of c) {
+        console.write(n + 1);
+    }
+    val xs: int32[] = [1, 2, 3, 4];
+    val big = iter(xs).map((x) => x * 10).filter((x) => x > 10).take(2).collect();
+    for (val x of iter(xs).skip(1)) {
+        console.write(x);
+    }
+    console.write(big.length, iter(c).sum());
+}
+"#;
+        let out = compile(src).unwrap();
+        assert!(out.contains("impl Iterator for Countdown {"), "{}", out);
+        assert!(out.contains("type Item = i32;"), "{}", out);
+        assert!(
+            out.contains(
+                "if Countdown::hasNext(self) { Some(Countdown::next(self)) } else { None }"
+            ),
+            "{}",
+            out
+        );
+        assert!(out.contains("for n in (c).clone() {"), "{}", out);
+        assert!(out.contains(
+            "(xs).iter().cloned().map(move |x: i32| x * 10).filter(|__trust_item| (move |x: i32| x > 10)(__trust_item.clone())).take((2) as usize).collect::<Vec<_>>()"
+        ), "{}", out);
+        assert!(
+            out.contains("for x in (xs).iter().cloned().skip((1) as usize) {"),
+            "{}",
+            out
+        );
+        assert!(out.contains("(c).clone().sum::<i32>()"), "{}", out);
+
+        let err = compile("interface A { n: int32; }\nimplements A: Iterable<int32> {\n    function hasNext(): boolean { return false; }\n}\n")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("needs `hasNext(): boolean` and a `next()`"),
+            "{}",
+            err
+        );
+        let err = compile("interface A { n: int32; }\nimplements A: Sortable<int32> {\n    function f(): int32 { return 1; }\n}\n")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("`Sortable` is not a trait"), "{}", err);
+    }
+
+    #[test]
+    fn test_compile_string_appends_and_string_builder() {
+        let src = r#"
+function main() {
+    var s = "";
+    for (val i of 0..3) {
+        s = s + "x" + i;
+        s += "y";
+    }
+    val sb = StringBuilder.new();
+    sb.push("a").push(1);
+    sb.pushLine();
+    console.write(s, sb.toString(), sb.length);
+}
+"#;
+        let out = compile(src).unwrap();
+        assert!(
+            out.contains(r#"{ s.push_str("x"); s.push_str(&(i).to_string()); }"#),
+            "{}",
+            out
+        );
+        assert!(out.contains(r#"s.push_str("y");"#), "{}", out);
+        assert!(out.contains("struct StringBuilder {"), "{}", out);
+        assert!(
+            out.contains("#[allow(dead_code, non_snake_case)]\nimpl StringBuilder {"),
+            "unused and camelCase methods compile without warnings: {}",
+            out
+        );
+        assert!(out.contains(r#"let sb = StringBuilder::new();"#), "{}", out);
+        assert!(out.contains(r#"sb.push("a").push(1);"#), "{}", out);
+        assert!(out.contains(r"sb.push('\n');"), "{}", out);
+        assert!(out.contains("sb.to_string(), sb.len()"), "{}", out);
+
+        let own = compile("interface StringBuilder { n: int32; }\nfunction main() { val b = StringBuilder({ n: 1 }); console.write(b.n); }\n").unwrap();
+        assert!(!own.contains("text: std::cell::RefCell<String>"), "{}", own);
+    }
+
+    #[test]
+    fn test_manifest_check_reports_keys_versions_and_imports() {
+        let dir =
+            std::env::temp_dir().join(format!("trusty-manifest-check-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(
+            dir.join("src/main.trs"),
+            "import { Regex } from \"regex\";\nimport { sqrt } from \"trusty:math\";\n",
+        )
+        .unwrap();
+        let text = r#"{
+  "name": "demo",
+  "main": "src/app.trs",
+  "dependecies": {},
+  "dependencies": { "rand": "^0.8", "serde": "latest" },
+  "compilerOptions": { "overflow": "wrapping", "enumUtilities": true, "locale": "fr" }
+}"#;
+        let diagnostics = crate::manifest::check(text, &dir);
+        let find = |code: &str| {
+            diagnostics
+                .iter()
+                .find(|d| d.code == code)
+                .unwrap_or_else(|| panic!("{:?}", diagnostics))
+        };
+
+        let typo = find("unknown-manifest-key");
+        assert!(
+            typo.message.contains("did you mean `dependencies`?"),
+            "{}",
+            typo.message
+        );
+        assert_eq!(
+            &text[typo.span.unwrap().start..typo.span.unwrap().end],
+            "\"dependecies\""
+        );
+        assert!(find("missing-entry")
+            .message
+            .contains("`src/app.trs` does not exist"));
+        let version = find("manifest-version");
+        assert!(
+            version.is_error()
+                && version
+                    .message
+                    .contains("`serde` has an invalid version requirement `latest`")
+        );
+        assert!(find("manifest-value")
+            .message
+            .contains("`compilerOptions.overflow` must be one of"));
+        let undeclared = find("undeclared-dependency");
+        assert!(
+            undeclared.message.starts_with("`regex` is imported by"),
+            "{}",
+            undeclared.message
+        );
+        assert_eq!(diagnostics.len(), 5, "{:?}", diagnostics);
+
+        let invalid = crate::manifest::check("{ \"name\": \"x\", }", &dir);
+        assert_eq!(invalid[0].code, "manifest-json");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_manifest_compile_options() {
+        let json: serde_json::Value = serde_json::from_str(
+            r#"{
+  "dependencies": { "rand": "^0.8", "chrono": "0.4" },
+  "compilerOptions": {
+    "overflow": "checked", "derive": ["PartialEq"], "trackLocations": true,
+    "runtimeCrate": true, "locale": "fr", "autoClone": "never"
+  }
+}"#,
+        )
+        .unwrap();
+        let options = crate::manifest::compile_options(&json);
+        assert_eq!(options.overflow, Overflow::Checked);
+        assert_eq!(options.derives, ["PartialEq"]);
+        assert!(options.track_locations && options.runtime_crate && !options.enum_utilities);
+        assert_eq!(options.locale, Locale::Fr);
+        assert_eq!(options.auto_clone, AutoClone::Never);
+        assert_eq!(
+            options.dependencies,
+            [
+                ("chrono".to_string(), "0.4".to_string()),
+                ("rand".to_string(), "^0.8".to_string())
+            ]
+        );
+
+        let defaults = crate::manifest::compile_options(&serde_json::Value::Null);
+        assert_eq!(defaults.overflow, Overflow::Wrap);
+        assert!(defaults.dependencies.is_empty() && !defaults.runtime_crate);
+    }
+
+    #[test]
+    fn test_compile_output_records_phase_timings() {
+        let output = Compiler::new().compile("function main() {\n    console.write(1);\n}");
+        let phases: Vec<&str> = output.timings.iter().map(|(phase, _)| *phase).collect();
+        assert_eq!(phases, ["preprocess", "parse", "transpile"]);
+
+        let output = Compiler::new().compile("function main() {\n    val x = ;\n}");
+        let phases: Vec<&str> = output.timings.iter().map(|(phase, _)| *phase).collect();
+        assert_eq!(phases, ["preprocess", "parse"]);
+    }
+
+    #[test]
+    fn test_identifier_checks_use_byte_offsets_and_skip_strings() {
+        let source = "function main() {\n    val s = \"é while\"; // while\n    val n: number = 1;\n    console.write(`${s} number`);\n}";
+        let output = Compiler::new().compile(source);
+        assert!(!output.has_errors());
+        let warning = output.warnings().next().unwrap();
+        assert_eq!(warning.code, "deprecated-number-alias");
+        assert_eq!(
+            warning.span.map(|span| &source[span.start..span.end]),
+            Some("number")
+        );
+        assert_eq!(find_identifiers_in_code(source, "number").len(), 1);
+
+        let line = "    val total: int32 = values.reduce((a, b) => a + b, 0);\n";
+        let large = format!(
+            "function main() {{\n    val values = [1, 2, 3];\n{}}}",
+            line.repeat(10_000)
+        );
+        assert!(!Compiler::new().compile(&large).has_errors());
+    }
+
+    #[test]
+    fn test_compile_escapes_string_literals() {
+        let source = r#"
+            enum Quote { Open = "{\"", Close = "\\}" }
+            const BRACES: string = "{} \"x\"";
+            function main() {
+                val n = 1;
+                console.write(`{n} = ${n} "q" \\ \` done`);
+                console.write(`{plain} "q"`);
+                val obj = { "we\"ird": 1 };
+            }
+        "#;
+        let result = compile(source).unwrap();
+        assert!(result.contains(r#"Quote::Open => "{\"""#), "{}", result);
+        assert!(result.contains(r#"Quote::Close => "\\}""#), "{}", result);
+        assert!(
+            result.contains(r#"const BRACES: &'static str = "{} \"x\"";"#),
+            "{}",
+            result
+        );
+        assert!(
+            result.contains(r#"format!("{{n}} = {} \"q\" \\ ` done", n)"#),
+            "{}",
+            result
+        );
+        assert!(
+            result.contains(r#""{plain} \"q\"".to_string()"#),
+            "{}",
+            result
+        );
+        assert!(result.contains(r#"json!({"we\"ird": 1})"#), "{}", result);
+    }
+
+    #[test]
+    fn test_compile_raw_strings() {
+        let source = "const PATTERN: string = r\"\\d+\";\nfunction main() {\n    val json = r#\"{ \"a\": \"\\n\" }\"#;\n    \
+                      val block = r\"one\ntwo\";\n    val n: number = block.length;\n    console.write(PATTERN, json, n);\n}";
+        let output = Compiler::new().compile(source);
+        let result = output.rust_code.as_deref().unwrap_or_default();
+        assert!(
+            result.contains(r#"const PATTERN: &'static str = r"\d+";"#),
+            "{}",
+            result
+        );
+        assert!(
+            result.contains(r###"let json = r#"{ "a": "\n" }"#.to_string();"###),
+            "{}",
+            result
+        );
+        assert!(
+            result.contains("let block = r\"one\ntwo\".to_string();"),
+            "{}",
+            result
+        );
+
+        // Lines after a multi-line raw string keep their numbers.
+        let warning = output.warnings().next().unwrap();
+        assert_eq!(warning.span.unwrap().line_col(source).0, 6);
+    }
+
+    #[test]
+    fn test_compile_unicode_and_dollar_identifiers() {
+        let source = "struct Café { année: int32; }\nfunction main() {\n    val été = Café({ année: 1 });\n    \
+                      val 名前 = été.année > 0 and true;\n    val $x = 1;\n    val a$b = $x + 1;\n    console.write(a$b, \"$x\");\n}";
+        let result = compile(source).unwrap();
+        assert!(result.contains("struct Café"), "{}", result);
+        assert!(
+            result.contains("let été = Café { année: 1 };"),
+            "{}",
+            result
+        );
+        assert!(
+            result.contains("let 名前 = été.année > 0 && true;"),
+            "{}",
+            result
+        );
+        assert!(result.contains("let _dollar_x = 1;"), "{}", result);
+        assert!(
+            result.contains("let a_dollar_b = _dollar_x + 1;"),
+            "{}",
+            result
+        );
+        assert!(result.contains("\"$x\""), "{}", result);
+    }
+
+    /// Adversarial sources glued from fragments: unbalanced brackets, unterminated strings,
+    /// templates and comments, multi-byte characters and half-written TRUST syntax.
+    fn adversarial_sources(count: usize) -> Vec<String> {
+        const FRAGMENTS: &[&str] = &[
+            "{",
+            "}",
+            "(",
+            ")",
+            "[",
+            "]",
+            "\"",
+            "'",
+            "`",
+            "${",
+            "/*",
+            "*/",
+            "//",
+            "\\",
+            "\n",
+            " ",
+            ";",
+            ",",
+            ".",
+            "..",
+            "=>",
+            "=",
+            "?",
+            ":",
+            "@",
+            "#",
+            "é",
+            "日本",
+            "🦀",
+            "0",
+            "1.5",
+            "0x",
+            "1..",
+            "_",
+            "$",
+            "val",
+            "var",
+            "function",
+            "match",
+            "if",
+            "and",
+            "or",
+            "not",
+            "try",
+            "static",
+            "implements",
+            "struct",
+            "enum",
+            "new",
+            "return",
+            "for",
+            "of",
+            "x",
+            "main",
+            "\"abc",
+            "`a${b",
+            "}c`",
+            "@derive(",
+            "b\"",
+            "d\"1.",
+            "r\"",
+            "function main() {",
+            "match (x) {",
+            "x => 1,",
+            "val s = \"",
+            "console.write(",
+        ];
+        // Half are fragment soup, half a valid program with a few fragments spliced in, which gets
+        // past the parser more often.
+        const BASE: &str = "struct P { x: int32; }\nfunction f(p: P): string {\n    val s = `${p.x} é`;\n    \
+                            return match (p.x) { 0 => \"zero\", default => s };\n}\nfunction main() {\n    \
+                            if (f(P({ x: 1 })) != \"\" and true) { console.write(\"{}\"); }\n}\n";
+        // xorshift64: deterministic, so a failure always reproduces.
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = |below: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % below as u64) as usize
+        };
+        (0..count)
+            .map(|n| {
+                if n % 2 == 0 {
+                    return (0..next(40))
+                        .map(|_| FRAGMENTS[next(FRAGMENTS.len())])
+                        .collect();
+                }
+                let mut source = BASE.to_string();
+                for _ in 0..1 + next(3) {
+                    let mut at = next(source.len() + 1);
+                    while !source.is_char_boundary(at) {
+                        at -= 1;
+                    }
+                    source.insert_str(at, FRAGMENTS[next(FRAGMENTS.len())]);
+                }
+                source
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_preprocessor_and_compiler_survive_adversarial_input() {
+        for source in adversarial_sources(3_000) {
+            let tokens = preprocessor::tokenize(&source);
+            let mut end = 0;
+            for token in &tokens {
+                assert_eq!(token.span.start, end, "gap in tokens of {:?}", source);
+                assert!(
+                    token.span.end > token.span.start && source.is_char_boundary(token.span.end),
+                    "{:?}",
+                    source
+                );
+                end = token.span.end;
+            }
+            assert_eq!(end, source.len(), "tokens of {:?} stop early", source);
+
+            let preprocessed = preprocessor::preprocess(&source);
+            for offset in 0..=preprocessed.text.len() {
+                assert!(
+                    preprocessed.original_offset(offset, false) <= source.len(),
+                    "{:?}",
+                    source
+                );
+                assert!(
+                    preprocessed.original_offset(offset, true) <= source.len(),
+                    "{:?}",
+                    source
+                );
+            }
+            Compiler::new().compile(&source);
+        }
+    }
 }
@@ -0,0 +1,571 @@
+//! Style and hygiene checks behind `trusty lint` and the editor's lint warnings.
+//!
+//! Lints run on one file's parsed source and never affect compilation. Each rule has a stable code
+//! and a level from [`LintConfig`] (the `lint` block of `trusty.json`).
+
+use crate::diagnostics::{Diagnostic, Span};
+use crate::fix::{Fix, TextEdit};
+use crate::parser::source_span;
+use std::collections::{HashMap, HashSet};
+use swc_common::Spanned;
+use swc_ecma_ast::*;
+
+/// How a lint rule is reported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RuleLevel {
+    Off,
+    Warn,
+    Error,
+}
+
+impl RuleLevel {
+    /// Parses a `trusty.json` level (`off`, `warn`, `error`).
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "off" => Some(RuleLevel::Off),
+            "warn" => Some(RuleLevel::Warn),
+            "error" => Some(RuleLevel::Error),
+            _ => None,
+        }
+    }
+}
+
+/// A lint rule: its diagnostic code, default level and a one-line summary.
+pub struct Rule {
+    pub code: &'static str,
+    pub default_level: RuleLevel,
+    pub summary: &'static str,
+}
+
+pub const RULES: &[Rule] = &[
+    Rule {
+        code: "unused-variable",
+        default_level: RuleLevel::Warn,
+        summary: "a `val`/`var` that is never read",
+    },
+    Rule {
+        code: "shadowed-variable",
+        default_level: RuleLevel::Warn,
+        summary: "a declaration that hides another binding of the same name",
+    },
+    Rule {
+        code: "struct-name-case",
+        default_level: RuleLevel::Warn,
+        summary: "a struct name that is not UpperCamelCase",
+    },
+    Rule {
+        code: "magic-number",
+        default_level: RuleLevel::Off,
+        summary: "a numeric literal other than 0, 1 or 2 outside a declaration that names it",
+    },
+    Rule {
+        code: "long-function",
+        default_level: RuleLevel::Warn,
+        summary: "a function longer than `maxFunctionLines` lines (default 50)",
+    },
+    Rule {
+        code: "missing-return-type",
+        default_level: RuleLevel::Warn,
+        summary: "an exported function without a return type annotation",
+    },
+];
+
+/// Levels per rule plus rule options.
+#[derive(Clone, Debug)]
+pub struct LintConfig {
+    levels: HashMap<&'static str, RuleLevel>,
+    pub max_function_lines: usize,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            levels: RULES
+                .iter()
+                .map(|rule| (rule.code, rule.default_level))
+                .collect(),
+            max_function_lines: 50,
+        }
+    }
+}
+
+impl LintConfig {
+    pub fn level(&self, code: &str) -> RuleLevel {
+        self.levels.get(code).copied().unwrap_or(RuleLevel::Off)
+    }
+
+    /// Applies one `trusty.json` `lint` entry: a rule code with a level, or `maxFunctionLines`.
+    pub fn configure(&mut self, key: &str, value: &str) -> Result<(), String> {
+        if key == "maxFunctionLines" {
+            self.max_function_lines = value.parse().map_err(|_| {
+                format!(
+                    "`maxFunctionLines` must be a positive number, got `{}`",
+                    value
+                )
+            })?;
+            return Ok(());
+        }
+        let Some(rule) = RULES.iter().find(|rule| rule.code == key) else {
+            return Err(format!("Unknown lint rule `{}`", key));
+        };
+        let level = RuleLevel::parse(value).ok_or_else(|| {
+            format!(
+                "Lint level for `{}` must be `off`, `warn` or `error`, got `{}`",
+                key, value
+            )
+        })?;
+        self.levels.insert(rule.code, level);
+        Ok(())
+    }
+}
+
+/// Lints `source`, or returns its parse error when it does not parse. Spans are byte ranges in
+/// `source` itself.
+pub fn lint(source: &str, config: &LintConfig) -> Result<Vec<Diagnostic>, Box<Diagnostic>> {
+    let preprocessed = crate::preprocessor::preprocess(source);
+    let module = crate::parser::parse_module(&preprocessed.text).map_err(|mut error| {
+        error.span = error.span.map(|span| preprocessed.original_span(span));
+        error
+    })?;
+    let mut linter = Linter {
+        config,
+        source: &preprocessed.text,
+        diagnostics: Vec::new(),
+        frames: Vec::new(),
+        in_const: false,
+        exported_structs: HashSet::new(),
+    };
+    linter.module(&module);
+    let mut diagnostics = std::mem::take(&mut linter.diagnostics);
+    for diag in &mut diagnostics {
+        diag.span = diag.span.map(|span| preprocessed.original_span(span));
+        diag.fix = fix(diag, source, &linter.exported_structs);
+    }
+    diagnostics.sort_by_key(|d| d.span.map(|s| s.start));
+    Ok(diagnostics)
+}
+
+/// Mechanical fix for a finding, in `source` coordinates: `_`-prefix an unused binding, or rename a
+/// struct (and every use of it as a type) to UpperCamelCase. Exported structs get no fix, since the
+/// rename would break the modules importing them.
+fn fix(diag: &Diagnostic, source: &str, exported_structs: &HashSet<String>) -> Option<Fix> {
+    let span = diag.span?;
+    let name = source.get(span.start..span.end)?;
+    match diag.code {
+        "unused-variable" => Some(Fix::new(
+            format!("Rename `{}` to `_{}`", name, name),
+            vec![TextEdit::new(span, format!("_{}", name))],
+        )),
+        "struct-name-case" if !exported_structs.contains(name) => {
+            let camel = upper_camel_case(name);
+            Some(Fix::rename_type(
+                format!("Rename `{}` to `{}`", name, camel),
+                source,
+                name,
+                &camel,
+            ))
+        }
+        _ => None,
+    }
+}
+
+struct Binding {
+    name: String,
+    span: swc_common::Span,
+    used: bool,
+    /// Only `val`/`var` declarations are reported as unused; parameters and catch bindings are not.
+    reportable: bool,
+}
+
+struct Linter<'a> {
+    config: &'a LintConfig,
+    source: &'a str,
+    diagnostics: Vec<Diagnostic>,
+    /// Bindings per block of the function being linted, innermost last.
+    frames: Vec<Vec<Binding>>,
+    /// Inside a `const` initializer, whose numbers are named by the constant.
+    in_const: bool,
+    /// Names of `export struct`s.
+    exported_structs: HashSet<String>,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, code: &'static str, message: String, span: swc_common::Span) {
+        let diagnostic = match self.config.level(code) {
+            RuleLevel::Off => return,
+            RuleLevel::Warn => Diagnostic::warning(code, message),
+            RuleLevel::Error => Diagnostic::error(code, message),
+        };
+        self.diagnostics
+            .push(diagnostic.with_span(source_span(span)));
+    }
+
+    fn module(&mut self, module: &Module) {
+        // Top-level statements form the implicit `main`.
+        self.frames.push(Vec::new());
+        for item in &module.body {
+            match item {
+                ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(export)) => {
+                    if let Decl::Fn(func) = &export.decl {
+                        if func.function.return_type.is_none() {
+                            self.report(
+                                "missing-return-type",
+                                format!(
+                                    "Exported function `{}` has no return type; annotate it (e.g. `: void`)",
+                                    func.ident.sym
+                                ),
+                                func.ident.span,
+                            );
+                        }
+                    }
+                    if let Decl::TsInterface(interface) = &export.decl {
+                        self.exported_structs.insert(interface.id.sym.to_string());
+                    }
+                    self.decl(&export.decl);
+                }
+                ModuleItem::Stmt(Stmt::Decl(
+                    decl @ (Decl::Fn(_) | Decl::Class(_) | Decl::TsInterface(_)),
+                )) => {
+                    self.decl(decl);
+                }
+                ModuleItem::Stmt(Stmt::Decl(Decl::Var(var_decl)))
+                    if var_decl.kind == VarDeclKind::Const =>
+                {
+                    self.var_decl(var_decl, false);
+                }
+                ModuleItem::Stmt(stmt) => self.stmt(stmt),
+                _ => {}
+            }
+        }
+        self.pop_frame();
+    }
+
+    fn decl(&mut self, decl: &Decl) {
+        match decl {
+            Decl::Fn(func) => self.function(&func.ident, &func.function),
+            // `implements` blocks parse as classes.
+            Decl::Class(class_decl) => {
+                for member in &class_decl.class.body {
+                    if let ClassMember::Method(method) = member {
+                        if let PropName::Ident(name) = &method.key {
+                            self.function(&Ident::from(name.clone()), &method.function);
+                        }
+                    }
+                }
+            }
+            Decl::TsInterface(interface) => self.struct_name(&interface.id),
+            Decl::Var(var_decl) => self.var_decl(var_decl, true),
+            _ => {}
+        }
+    }
+
+    fn struct_name(&mut self, id: &Ident) {
+        let name = id.sym.as_ref();
+        let camel = upper_camel_case(name);
+        if camel != name {
+            self.report(
+                "struct-name-case",
+                format!("Struct `{}` should be UpperCamelCase: `{}`", name, camel),
+                id.span,
+            );
+        }
+    }
+
+    fn function(&mut self, name: &Ident, function: &Function) {
+        let span = source_span(function.span);
+        let (start, _) = span.line_col(self.source);
+        let (end, _) = Span::new(span.end, span.end).line_col(self.source);
+        let lines = end - start + 1;
+        if lines > self.config.max_function_lines {
+            self.report(
+                "long-function",
+                format!(
+                    "Function `{}` is {} lines long (max {}); split it into smaller functions",
+                    name.sym, lines, self.config.max_function_lines
+                ),
+                name.span,
+            );
+        }
+
+        // Functions see only program-level names, which are not tracked.
+        let outer = std::mem::take(&mut self.frames);
+        self.frames.push(Vec::new());
+        for param in &function.params {
+            self.declare_pat(&param.pat, false);
+        }
+        if let Some(body) = &function.body {
+            body.stmts.iter().for_each(|stmt| self.stmt(stmt));
+        }
+        self.pop_frame();
+        self.frames = outer;
+    }
+
+    fn declare(&mut self, ident: &Ident, reportable: bool) {
+        let name = ident.sym.to_string();
+        if self
+            .frames
+            .iter()
+            .flatten()
+            .any(|binding| binding.name == name)
+        {
+            self.report(
+                "shadowed-variable",
+                format!("`{}` shadows an earlier binding with the same name", name),
+                ident.span,
+            );
+        }
+        if let Some(frame) = self.frames.last_mut() {
+            frame.push(Binding {
+                name,
+                span: ident.span,
+                used: false,
+                reportable,
+            });
+        }
+    }
+
+    fn declare_pat(&mut self, pat: &Pat, reportable: bool) {
+        match pat {
+            Pat::Ident(binding) => self.declare(&binding.id, reportable),
+            Pat::Assign(assign) => {
+                self.expr(&assign.right);
+                self.declare_pat(&assign.left, reportable);
+            }
+            _ => {}
+        }
+    }
+
+    fn use_ident(&mut self, ident: &Ident) {
+        let name = ident.sym.as_ref();
+        let mut innermost = self
+            .frames
+            .iter_mut()
+            .rev()
+            .flat_map(|frame| frame.iter_mut().rev());
+        if let Some(binding) = innermost.find(|binding| binding.name == name) {
+            binding.used = true;
+        }
+    }
+
+    fn pop_frame(&mut self) {
+        for binding in self.frames.pop().unwrap_or_default() {
+            if binding.reportable && !binding.used && !binding.name.starts_with('_') {
+                self.report(
+                    "unused-variable",
+                    format!(
+                        "`{}` is never read; remove it or rename it `_{}`",
+                        binding.name, binding.name
+                    ),
+                    binding.span,
+                );
+            }
+        }
+    }
+
+    fn block(&mut self, stmts: &[Stmt]) {
+        self.frames.push(Vec::new());
+        stmts.iter().for_each(|stmt| self.stmt(stmt));
+        self.pop_frame();
+    }
+
+    fn var_decl(&mut self, var_decl: &VarDecl, reportable: bool) {
+        let in_const = std::mem::replace(&mut self.in_const, var_decl.kind == VarDeclKind::Const);
+        for decl in &var_decl.decls {
+            match &decl.init {
+                // `val timeout = 30;` names its number.
+                Some(init) if matches!(&**init, Expr::Lit(Lit::Num(_))) => {}
+                Some(init) => self.expr(init),
+                None => {}
+            }
+            self.declare_pat(&decl.name, reportable);
+        }
+        self.in_const = in_const;
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Expr(expr_stmt) => self.expr(&expr_stmt.expr),
+            Stmt::Decl(Decl::Var(var_decl)) => self.var_decl(var_decl, true),
+            Stmt::Return(ret) => {
+                if let Some(arg) = &ret.arg {
+                    self.expr(arg);
+                }
+            }
+            Stmt::Throw(throw_stmt) => self.expr(&throw_stmt.arg),
+            Stmt::Block(block) => self.block(&block.stmts),
+            Stmt::If(if_stmt) => {
+                self.expr(&if_stmt.test);
+                self.stmt(&if_stmt.cons);
+                if let Some(alt) = &if_stmt.alt {
+                    self.stmt(alt);
+                }
+            }
+            Stmt::While(while_stmt) => {
+                self.expr(&while_stmt.test);
+                self.stmt(&while_stmt.body);
+            }
+            Stmt::DoWhile(do_while) => {
+                self.stmt(&do_while.body);
+                self.expr(&do_while.test);
+            }
+            Stmt::For(for_stmt) => {
+                self.frames.push(Vec::new());
+                match &for_stmt.init {
+                    Some(VarDeclOrExpr::VarDecl(var_decl)) => self.var_decl(var_decl, true),
+                    Some(VarDeclOrExpr::Expr(expr)) => self.expr(expr),
+                    None => {}
+                }
+                for expr in for_stmt.test.iter().chain(for_stmt.update.iter()) {
+                    self.expr(expr);
+                }
+                self.stmt(&for_stmt.body);
+                self.pop_frame();
+            }
+            Stmt::ForOf(ForOfStmt {
+                left, right, body, ..
+            })
+            | Stmt::ForIn(ForInStmt {
+                left, right, body, ..
+            }) => {
+                self.expr(right);
+                self.frames.push(Vec::new());
+                match left {
+                    ForHead::VarDecl(var_decl) => self.var_decl(var_decl, true),
+                    ForHead::Pat(pat) => self.declare_pat(pat, true),
+                    _ => {}
+                }
+                self.stmt(body);
+                self.pop_frame();
+            }
+            Stmt::Try(try_stmt) => {
+                self.block(&try_stmt.block.stmts);
+                if let Some(handler) = &try_stmt.handler {
+                    self.frames.push(Vec::new());
+                    if let Some(param) = &handler.param {
+                        self.declare_pat(param, false);
+                    }
+                    handler.body.stmts.iter().for_each(|stmt| self.stmt(stmt));
+                    self.pop_frame();
+                }
+                if let Some(finalizer) = &try_stmt.finalizer {
+                    self.block(&finalizer.stmts);
+                }
+            }
+            Stmt::Labeled(labeled) => self.stmt(&labeled.body),
+            _ => {}
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Ident(ident) => self.use_ident(ident),
+            Expr::Lit(Lit::Num(num)) => self.number(num),
+            Expr::Bin(bin) => {
+                self.expr(&bin.left);
+                self.expr(&bin.right);
+            }
+            Expr::Unary(unary) => self.expr(&unary.arg),
+            Expr::Update(update) => self.expr(&update.arg),
+            // Assigning to a variable does not read it.
+            Expr::Assign(assign) => {
+                if let AssignTarget::Simple(SimpleAssignTarget::Member(member)) = &assign.left {
+                    self.member(member);
+                }
+                self.expr(&assign.right);
+            }
+            Expr::Call(call) => {
+                if let Callee::Expr(callee) = &call.callee {
+                    self.expr(callee);
+                }
+                call.args.iter().for_each(|arg| self.expr(&arg.expr));
+            }
+            Expr::New(new_expr) => new_expr
+                .args
+                .iter()
+                .flatten()
+                .for_each(|arg| self.expr(&arg.expr)),
+            Expr::Member(member) => self.member(member),
+            Expr::Cond(cond) => {
+                self.expr(&cond.test);
+                self.expr(&cond.cons);
+                self.expr(&cond.alt);
+            }
+            Expr::Paren(paren) => self.expr(&paren.expr),
+            Expr::Seq(seq) => seq.exprs.iter().for_each(|e| self.expr(e)),
+            Expr::Await(await_expr) => self.expr(&await_expr.arg),
+            Expr::Array(array) => array
+                .elems
+                .iter()
+                .flatten()
+                .for_each(|e| self.expr(&e.expr)),
+            Expr::Tpl(tpl) => tpl.exprs.iter().for_each(|e| self.expr(e)),
+            Expr::Object(obj) => {
+                for prop in &obj.props {
+                    match prop {
+                        PropOrSpread::Prop(prop) => match &**prop {
+                            Prop::KeyValue(kv) => self.expr(&kv.value),
+                            Prop::Shorthand(ident) => self.use_ident(ident),
+                            _ => {}
+                        },
+                        PropOrSpread::Spread(spread) => self.expr(&spread.expr),
+                    }
+                }
+            }
+            Expr::Arrow(arrow) => {
+                self.frames.push(Vec::new());
+                for param in &arrow.params {
+                    self.declare_pat(param, false);
+                }
+                match &*arrow.body {
+                    BlockStmtOrExpr::BlockStmt(block) => {
+                        block.stmts.iter().for_each(|stmt| self.stmt(stmt))
+                    }
+                    BlockStmtOrExpr::Expr(body) => self.expr(body),
+                }
+                self.pop_frame();
+            }
+            Expr::TsAs(ts_as) => self.expr(&ts_as.expr),
+            Expr::TsNonNull(non_null) => self.expr(&non_null.expr),
+            _ => {}
+        }
+    }
+
+    fn member(&mut self, member: &MemberExpr) {
+        self.expr(&member.obj);
+        if let MemberProp::Computed(computed) = &member.prop {
+            self.expr(&computed.expr);
+        }
+    }
+
+    fn number(&mut self, num: &Number) {
+        if !self.in_const && ![0.0, 1.0, 2.0].contains(&num.value) {
+            self.report(
+                "magic-number",
+                format!(
+                    "Magic number `{}`; give it a name with `const` or `val`",
+                    source_text(self.source, num.span())
+                ),
+                num.span,
+            );
+        }
+    }
+}
+
+fn source_text(source: &str, span: swc_common::Span) -> &str {
+    let span = source_span(span);
+    source.get(span.start..span.end).unwrap_or_default()
+}
+
+/// `my_point` / `point` → `MyPoint` / `Point`.
+pub(crate) fn upper_camel_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect()
+}
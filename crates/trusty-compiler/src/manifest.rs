@@ -0,0 +1,455 @@
+//! Checks for `trusty.json`: unknown keys, malformed values and versions, crates imported
+//! without being declared, and a missing entry file. Spans are byte ranges in the manifest text.
+
+use crate::compiler::CompileOptions;
+use crate::diagnostics::{closest, Diagnostic, Span};
+use crate::lint::LintConfig;
+use crate::messages::Locale;
+use crate::transpiler::annotations::DERIVABLE;
+use crate::transpiler::overflow::Overflow;
+use crate::transpiler::ownership::AutoClone;
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+
+/// Top-level keys of `trusty.json`.
+pub const KEYS: [&str; 7] = [
+    "name",
+    "version",
+    "description",
+    "main",
+    "dependencies",
+    "compilerOptions",
+    "lint",
+];
+
+/// Keys of the `compilerOptions` block.
+pub const COMPILER_OPTIONS: [&str; 9] = [
+    "outDir",
+    "target",
+    "autoClone",
+    "trackLocations",
+    "enumUtilities",
+    "derive",
+    "overflow",
+    "runtimeCrate",
+    "locale",
+];
+
+/// Checks the manifest `text` of the project in `root`, reading its `.trs` files for imports.
+pub fn check(text: &str, root: &Path) -> Vec<Diagnostic> {
+    let json: Value = match serde_json::from_str(text) {
+        Ok(json) => json,
+        Err(err) => {
+            let offset = offset_of(text, err.line(), err.column());
+            return vec![Diagnostic::error(
+                "manifest-json",
+                format!("Invalid JSON in trusty.json: {}", err),
+            )
+            .with_span(Span::new(offset, offset + 1))];
+        }
+    };
+    let Some(manifest) = json.as_object() else {
+        return vec![
+            Diagnostic::error("manifest-json", "trusty.json must hold a JSON object")
+                .with_span(Span::new(0, 1)),
+        ];
+    };
+    let mut checker = Checker {
+        text,
+        diagnostics: Vec::new(),
+    };
+    checker.unknown_keys(manifest, &[], &KEYS);
+    for key in ["name", "description", "main"] {
+        checker.string(manifest, &[key]);
+    }
+    if let Some(version) = checker.string(manifest, &["version"]) {
+        if !is_version(version) {
+            checker.warning(
+                "manifest-version",
+                format!(
+                    "`version` should be a version like `1.2.3`, got `{}`",
+                    version
+                ),
+                &["version"],
+            );
+        }
+    }
+    if let Some(main) = checker.string(manifest, &["main"]) {
+        if !root.join(main).is_file() {
+            checker.error(
+                "missing-entry",
+                format!("Entry file `{}` does not exist", main),
+                &["main"],
+            );
+        }
+    }
+    checker.dependencies(manifest.get("dependencies"), root);
+    if let Some(options) = checker.object(manifest, "compilerOptions") {
+        checker.compiler_options(options);
+    }
+    if let Some(lint) = checker.object(manifest, "lint") {
+        let mut config = LintConfig::default();
+        for (key, value) in lint {
+            let value = match value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            if let Err(message) = config.configure(key, &value) {
+                checker.error("manifest-value", message, &["lint", key]);
+            }
+        }
+    }
+    checker.diagnostics.sort_by_key(|d| d.span.map(|s| s.start));
+    checker.diagnostics
+}
+
+/// The [`CompileOptions`] a parsed `trusty.json` asks for: its `compilerOptions` and its
+/// `dependencies`, sorted by crate name. Missing or malformed values keep their defaults, which
+/// [`check`] reports.
+pub fn compile_options(json: &Value) -> CompileOptions {
+    let options = json.get("compilerOptions");
+    let option = |key: &str| options.and_then(|o| o.get(key));
+    let flag = |key: &str| option(key).and_then(Value::as_bool).unwrap_or(false);
+    let mut dependencies: Vec<(String, String)> = json
+        .get("dependencies")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+        .map(|(name, version)| (name.clone(), version.as_str().unwrap_or("*").to_string()))
+        .collect();
+    dependencies.sort();
+    CompileOptions {
+        auto_clone: option("autoClone")
+            .and_then(Value::as_str)
+            .and_then(AutoClone::parse)
+            .unwrap_or_default(),
+        track_locations: flag("trackLocations"),
+        enum_utilities: flag("enumUtilities"),
+        overflow: option("overflow")
+            .and_then(Value::as_str)
+            .and_then(Overflow::parse)
+            .unwrap_or_default(),
+        derives: option("derive")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .map(str::to_string)
+            .collect(),
+        dependencies,
+        runtime_crate: flag("runtimeCrate"),
+        locale: option("locale")
+            .and_then(Value::as_str)
+            .and_then(Locale::parse)
+            .unwrap_or_default(),
+        ..CompileOptions::default()
+    }
+}
+
+struct Checker<'a> {
+    text: &'a str,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker<'_> {
+    fn error(&mut self, code: &'static str, message: String, path: &[&str]) {
+        self.push(Diagnostic::error(code, message), path);
+    }
+
+    fn warning(&mut self, code: &'static str, message: String, path: &[&str]) {
+        self.push(Diagnostic::warning(code, message), path);
+    }
+
+    fn push(&mut self, diagnostic: Diagnostic, path: &[&str]) {
+        let span = key_span(self.text, path).unwrap_or(Span::new(0, 1));
+        self.diagnostics.push(diagnostic.with_span(span));
+    }
+
+    /// Warns about keys of `object` (at `path`) outside `known`, suggesting a close match.
+    fn unknown_keys(&mut self, object: &Map<String, Value>, path: &[&str], known: &[&str]) {
+        for key in object.keys().filter(|key| !known.contains(&key.as_str())) {
+            let place = match path {
+                [] => "trusty.json".to_string(),
+                _ => format!("`{}`", path.join(".")),
+            };
+            let hint = match closest(key, known) {
+                Some(close) => format!("did you mean `{}`?", close),
+                None => format!("expected one of {}", known.join(", ")),
+            };
+            let mut key_path = path.to_vec();
+            key_path.push(key);
+            self.warning(
+                "unknown-manifest-key",
+                format!("Unknown key `{}` in {}; {}", key, place, hint),
+                &key_path,
+            );
+        }
+    }
+
+    /// The string at `path`, reporting any other kind of value.
+    fn string<'v>(&mut self, object: &'v Map<String, Value>, path: &[&str]) -> Option<&'v str> {
+        let key = path.last()?;
+        match object.get(*key)? {
+            Value::String(s) => Some(s),
+            _ => {
+                self.error(
+                    "manifest-value",
+                    format!("`{}` must be a string", path.join(".")),
+                    path,
+                );
+                None
+            }
+        }
+    }
+
+    /// The object under top-level `key`, reporting any other kind of value.
+    fn object<'v>(
+        &mut self,
+        manifest: &'v Map<String, Value>,
+        key: &str,
+    ) -> Option<&'v Map<String, Value>> {
+        match manifest.get(key)? {
+            Value::Object(object) => Some(object),
+            _ => {
+                self.error(
+                    "manifest-value",
+                    format!("`{}` must be an object", key),
+                    &[key],
+                );
+                None
+            }
+        }
+    }
+
+    fn dependencies(&mut self, dependencies: Option<&Value>, root: &Path) {
+        let declared = match dependencies {
+            None => Map::new(),
+            Some(Value::Object(declared)) => declared.clone(),
+            Some(_) => {
+                self.error(
+                    "manifest-value",
+                    "`dependencies` must be an object".to_string(),
+                    &["dependencies"],
+                );
+                return;
+            }
+        };
+        for (name, requirement) in &declared {
+            match requirement.as_str() {
+                Some(requirement) if is_requirement(requirement) => {}
+                Some(requirement) => self.error(
+                    "manifest-version",
+                    format!(
+                        "`{}` has an invalid version requirement `{}`; expected e.g. `\"1.10\"`, `\"^0.8\"` or `\"*\"`",
+                        name, requirement
+                    ),
+                    &["dependencies", name],
+                ),
+                None => self.error(
+                    "manifest-version",
+                    format!("The version of `{}` must be a string like `\"1.10\"`", name),
+                    &["dependencies", name],
+                ),
+            }
+        }
+        let mut reported = Vec::new();
+        for (file, source) in project_sources(root) {
+            for crate_name in imported_crates(&source) {
+                if declared.contains_key(&crate_name) || reported.contains(&crate_name) {
+                    continue;
+                }
+                let file = file
+                    .strip_prefix(root)
+                    .unwrap_or(&file)
+                    .display()
+                    .to_string();
+                self.warning(
+                    "undeclared-dependency",
+                    format!(
+                        "`{}` is imported by {} but not listed in `dependencies`, so any version may be used",
+                        crate_name, file
+                    ),
+                    &["dependencies"],
+                );
+                reported.push(crate_name);
+            }
+        }
+    }
+
+    fn compiler_options(&mut self, options: &Map<String, Value>) {
+        self.unknown_keys(options, &["compilerOptions"], &COMPILER_OPTIONS);
+        for key in ["outDir", "target"] {
+            self.string(options, &["compilerOptions", key]);
+        }
+        for key in ["trackLocations", "enumUtilities", "runtimeCrate"] {
+            if options.get(key).is_some_and(|value| !value.is_boolean()) {
+                self.error(
+                    "manifest-value",
+                    format!("`compilerOptions.{}` must be `true` or `false`", key),
+                    &["compilerOptions", key],
+                );
+            }
+        }
+        let choices: [(&str, &[&str]); 3] = [
+            ("autoClone", &["reused", "always", "never"]),
+            ("overflow", &["wrap", "checked", "saturating"]),
+            ("locale", &["en", "fr"]),
+        ];
+        for (key, expected) in choices {
+            let Some(value) = options.get(key) else {
+                continue;
+            };
+            let valid = value.as_str().is_some_and(|v| match key {
+                "autoClone" => AutoClone::parse(v).is_some(),
+                "locale" => Locale::parse(v).is_some(),
+                _ => Overflow::parse(v).is_some(),
+            });
+            if !valid {
+                self.error(
+                    "manifest-value",
+                    format!(
+                        "`compilerOptions.{}` must be one of {}, got {}",
+                        key,
+                        quoted(expected),
+                        value
+                    ),
+                    &["compilerOptions", key],
+                );
+            }
+        }
+        if let Some(derive) = options.get("derive") {
+            let traits = derive
+                .as_array()
+                .map(|traits| traits.iter().map(Value::as_str).collect::<Vec<_>>());
+            let unknown = traits.as_ref().and_then(|traits| {
+                traits
+                    .iter()
+                    .find(|t| !t.is_some_and(|t| DERIVABLE.contains(&t)))
+                    .copied()
+            });
+            match (traits, unknown) {
+                (Some(_), None) => {}
+                (Some(_), Some(Some(name))) => self.error(
+                    "manifest-value",
+                    format!("`{}` cannot be derived; expected one of {}", name, DERIVABLE.join(", ")),
+                    &["compilerOptions", "derive"],
+                ),
+                _ => self.error(
+                    "manifest-value",
+                    "`compilerOptions.derive` must be a list of trait names, e.g. `[\"PartialEq\"]`".to_string(),
+                    &["compilerOptions", "derive"],
+                ),
+            }
+        }
+    }
+}
+
+/// `["a", "b"]` → `` `a`, `b` ``.
+fn quoted(values: &[&str]) -> String {
+    values
+        .iter()
+        .map(|v| format!("`{}`", v))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Byte offset of 1-based `line` and `column` in `text`.
+fn offset_of(text: &str, line: usize, column: usize) -> usize {
+    let line_start: usize = text
+        .split_inclusive('\n')
+        .take(line.saturating_sub(1))
+        .map(str::len)
+        .sum();
+    (line_start + column.saturating_sub(1)).min(text.len().saturating_sub(1))
+}
+
+/// Span of the quoted key at `path` (`["compilerOptions", "overflow"]`), each key searched after
+/// the previous one.
+fn key_span(text: &str, path: &[&str]) -> Option<Span> {
+    let mut from = 0;
+    let mut span = None;
+    for key in path {
+        let quoted = serde_json::to_string(key).ok()?;
+        let mut search = from;
+        let start = loop {
+            let start = search + text[search..].find(&quoted)?;
+            let end = start + quoted.len();
+            if text[end..].trim_start().starts_with(':') {
+                break start;
+            }
+            search = end;
+        };
+        from = start + quoted.len();
+        span = Some(Span::new(start, from));
+    }
+    span
+}
+
+/// `1.2.3`, with an optional `-pre` or `+build` suffix.
+fn is_version(version: &str) -> bool {
+    let core = version.split(['-', '+']).next().unwrap_or_default();
+    let parts: Vec<&str> = core.split('.').collect();
+    parts.len() == 3
+        && parts
+            .iter()
+            .all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// A Cargo version requirement: `*`, or comma-separated `1`, `1.2`, `^1.2.3`, `~0.8`, `>=1, <2`,
+/// `1.*`, ...
+fn is_requirement(requirement: &str) -> bool {
+    requirement.split(',').all(|part| {
+        let part = part.trim();
+        let version = ["^", "~", ">=", "<=", ">", "<", "="]
+            .iter()
+            .find_map(|op| part.strip_prefix(op))
+            .unwrap_or(part)
+            .trim();
+        let core = version.split(['-', '+']).next().unwrap_or_default();
+        let parts: Vec<&str> = core.split('.').collect();
+        (1..=3).contains(&parts.len())
+            && parts.iter().enumerate().all(|(i, p)| {
+                let wildcard = matches!(*p, "*" | "x" | "X") && (i > 0 || parts.len() == 1);
+                wildcard || (!p.is_empty() && p.chars().all(|c| c.is_ascii_digit()))
+            })
+    })
+}
+
+/// External crates named by the `import ... from "crate/path"` lines of `source`.
+pub fn imported_crates(source: &str) -> Vec<String> {
+    let mut crates: Vec<String> = source
+        .lines()
+        .filter_map(crate::modules::parse_import_path)
+        .filter(|path| !path.starts_with('.') && !path.starts_with("trusty:"))
+        .filter_map(|path| path.split('/').next().map(str::to_string))
+        .filter(|name| !name.is_empty() && !matches!(name.as_str(), "std" | "core" | "alloc"))
+        .collect();
+    crates.dedup();
+    crates
+}
+
+/// `.trs` files under `root` with their text, skipping build output and hidden directories.
+fn project_sources(root: &Path) -> Vec<(PathBuf, String)> {
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            if path.is_dir() {
+                if !(name.starts_with('.') || name == "build" || name == "target") {
+                    pending.push(path);
+                }
+            } else if path.extension().is_some_and(|ext| ext == "trs") {
+                if let Ok(source) = std::fs::read_to_string(&path) {
+                    files.push((path, source));
+                }
+            }
+        }
+    }
+    files.sort();
+    files
+}
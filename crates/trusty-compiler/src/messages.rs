@@ -0,0 +1,492 @@
+//! Texts of compiler diagnostics, in every language they are translated to.
+//!
+//! A diagnostic keeps the key of its text and the values put in it, so the same diagnostic can be
+//! shown in English (the default) or in the [`Locale`] a project asks for. Templates name the
+//! values by position, `{0}`, and pick a word by a count with `{0|argument|arguments}`.
+
+/// Language diagnostics are written in (`compilerOptions.locale`, or `LANG` in the CLI).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    En,
+    Fr,
+}
+
+impl Locale {
+    /// Parses a language tag or a POSIX locale: `fr`, `fr-CA`, `fr_FR.UTF-8`. `C` and `POSIX` are
+    /// English.
+    pub fn parse(value: &str) -> Option<Self> {
+        let language = value
+            .split(['-', '_', '.', '@'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match language.as_str() {
+            "en" | "c" | "posix" => Some(Locale::En),
+            "fr" => Some(Locale::Fr),
+            _ => None,
+        }
+    }
+
+    /// Whether `count` takes the singular: French counts 0 as singular, English does not.
+    fn singular(self, count: &str) -> bool {
+        match self {
+            Locale::En => count == "1",
+            Locale::Fr => count == "0" || count == "1",
+        }
+    }
+}
+
+/// The text of a diagnostic: a catalog key with its values, or text shown as it is.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Message {
+    key: Option<&'static str>,
+    args: Vec<String>,
+}
+
+impl Message {
+    /// The catalog text `key` with `args` in its placeholders.
+    pub fn new(key: &'static str, args: Vec<String>) -> Self {
+        debug_assert!(
+            CATALOG.iter().any(|(k, _, _)| *k == key),
+            "no message `{}` in the catalog",
+            key
+        );
+        Self {
+            key: Some(key),
+            args,
+        }
+    }
+
+    /// The text in `locale`; English when it has no translation.
+    pub fn text(&self, locale: Locale) -> String {
+        let Some(key) = self.key else {
+            return self.args.concat();
+        };
+        let template = CATALOG
+            .iter()
+            .find(|(k, _, _)| *k == key)
+            .map(|(_, en, fr)| match locale {
+                Locale::Fr if !fr.is_empty() => *fr,
+                _ => *en,
+            })
+            .unwrap_or(key);
+        fill(template, &self.args, locale)
+    }
+}
+
+/// Text not in the catalog (lint and manifest messages, errors of other libraries) is shown as it is.
+impl From<String> for Message {
+    fn from(text: String) -> Self {
+        Self {
+            key: None,
+            args: vec![text],
+        }
+    }
+}
+
+impl From<&str> for Message {
+    fn from(text: &str) -> Self {
+        Self::from(text.to_string())
+    }
+}
+
+/// `template` with `{n}` replaced by `args[n]` and `{n|one|other}` by the form for that count.
+fn fill(template: &str, args: &[String], locale: Locale) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let placeholder = after.find('}').map(|close| &after[..close]);
+        let mut parts = placeholder.unwrap_or_default().split('|');
+        let arg = parts
+            .next()
+            .and_then(|index| index.parse::<usize>().ok())
+            .and_then(|index| args.get(index));
+        match (placeholder, arg) {
+            (Some(placeholder), Some(arg)) => {
+                match (parts.next(), parts.next()) {
+                    (Some(one), Some(other)) => {
+                        out.push_str(if locale.singular(arg) { one } else { other })
+                    }
+                    _ => out.push_str(arg),
+                }
+                rest = &after[placeholder.len() + 1..];
+            }
+            // `{ ... }` of a code example in the text.
+            _ => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// `msg!("key", a, b)`: the catalog message `key` with the values `a` and `b`, as shown by `Display`.
+macro_rules! msg {
+    ($key:expr $(, $arg:expr)* $(,)?) => {
+        $crate::messages::Message::new($key, vec![$($arg.to_string()),*])
+    };
+}
+pub(crate) use msg;
+
+/// `(key, English, French)`. A key is the diagnostic code, with a suffix when the code has several
+/// texts; an empty translation falls back to English.
+const CATALOG: &[(&str, &str, &str)] = &[
+    (
+        "readonly-field",
+        "`{1}` is `readonly` in `{0}`: it is set when the struct is built and cannot be assigned afterwards",
+        "`{1}` est `readonly` dans `{0}` : il est fixé à la création de la structure et ne peut plus être affecté",
+    ),
+    (
+        "unknown-struct-field",
+        "`{0}` has no field `{1}`; its fields are {2}",
+        "`{0}` n'a pas de champ `{1}` ; ses champs sont {2}",
+    ),
+    (
+        "unknown-struct-field.suggestion",
+        "`{0}` has no field `{1}`; did you mean `{2}`?",
+        "`{0}` n'a pas de champ `{1}` ; vouliez-vous dire `{2}` ?",
+    ),
+    (
+        "unknown-struct-field.key",
+        "An object literal typed `{0}` can only name its fields, without spreads or computed keys",
+        "Un littéral objet de type `{0}` ne peut que nommer ses champs, sans décomposition ni clé calculée",
+    ),
+    (
+        "unsupported-class",
+        "TRUST has no classes: declare the fields of `{0}` in `struct {0} { ... }` and its methods in `implements {0} { ... }`, and build values with `{0}({ ... })`",
+        "TRUST n'a pas de classes : déclarez les champs de `{0}` dans `struct {0} { ... }` et ses méthodes dans `implements {0} { ... }`, puis créez les valeurs avec `{0}({ ... })`",
+    ),
+    (
+        "unsupported-while",
+        "`while` is not supported in TRUST. Use `loop (condition) { ... }` instead.",
+        "`while` n'existe pas en TRUST. Utilisez `loop (condition) { ... }` à la place.",
+    ),
+    (
+        "unterminated-raw-string",
+        "Unterminated raw string: close it with `\"` followed by as many `#` as it opens with",
+        "Chaîne brute non terminée : fermez-la par `\"` suivi d'autant de `#` qu'à l'ouverture",
+    ),
+    (
+        "deprecated-number-alias",
+        "Deprecated type alias `number` detected. Prefer `int` (or `int32`) / `float`.",
+        "L'alias de type `number` est obsolète. Préférez `int` (ou `int32`) / `float`.",
+    ),
+    ("parse-error", "Parse error: {0}", "Erreur de syntaxe : {0}"),
+    (
+        "missing-main",
+        "No entry point: add `function main() { ... }` or top-level statements",
+        "Aucun point d'entrée : ajoutez `function main() { ... }` ou des instructions hors des fonctions",
+    ),
+    ("duplicate-main", "`main` is defined more than once", "`main` est défini plusieurs fois"),
+    (
+        "invalid-main",
+        "`main` must be a non-async function without parameters returning `void` or `int32`",
+        "`main` doit être une fonction non async, sans paramètres, qui renvoie `void` ou `int32`",
+    ),
+    (
+        "top-level-statement",
+        "Statements outside functions are only allowed in files without a `main` function",
+        "Les instructions hors des fonctions ne sont permises que dans un fichier sans fonction `main`",
+    ),
+    ("nested-static", "`static var` is only allowed at module level", "`static var` n'est permis qu'au niveau du module"),
+    (
+        "nullable-union",
+        "`{0}` is not a TRUST type; an optional value is written `Option<{1}>`",
+        "`{0}` n'est pas un type TRUST ; une valeur optionnelle s'écrit `Option<{1}>`",
+    ),
+    ("static-initializer", "`static var {0}` needs an initializer", "`static var {0}` doit être initialisée"),
+    (
+        "static-pointer",
+        "`static var {0}` cannot hold a `Pointer`; use `Threaded<T>` for shared state",
+        "`static var {0}` ne peut pas contenir de `Pointer` ; utilisez `Threaded<T>` pour un état partagé",
+    ),
+    (
+        "throw-outside-result",
+        "`throw` needs an enclosing `try` or a function returning `Result<T, E>`; change the return type (e.g. \
+         `Result<int32, string>`), wrap the code in `try { ... } catch (e) { ... }`, or use `panic(msg)`",
+        "`throw` doit être dans un `try` ou dans une fonction qui renvoie `Result<T, E>` ; changez le type de \
+         retour (par ex. `Result<int32, string>`), entourez le code de `try { ... } catch (e) { ... }`, ou utilisez \
+         `panic(msg)`",
+    ),
+    (
+        "non-const-expression",
+        "This expression cannot be evaluated at compile time; a `const` may only use literals, earlier consts, \
+         arithmetic and calls to functions that only return such an expression",
+        "Cette expression ne peut pas être évaluée à la compilation ; une `const` ne peut utiliser que des \
+         littéraux, des constantes déclarées avant elle, de l'arithmétique et des appels de fonctions qui ne \
+         renvoient qu'une telle expression",
+    ),
+    (
+        "non-const-expression.name",
+        "`{0}` is not a constant declared before this one",
+        "`{0}` n'est pas une constante déclarée avant celle-ci",
+    ),
+    (
+        "non-exhaustive-match",
+        "`match` on `{0}` does not cover {1}; add the missing variants or a `default` arm",
+        "Le `match` sur `{0}` ne couvre pas {1} ; ajoutez les variantes manquantes ou une branche `default`",
+    ),
+    ("unknown-variant", "`{0}` is not a variant of `{1}`", "`{0}` n'est pas une variante de `{1}`"),
+    (
+        "invalid-match-pattern",
+        "`match` on `{0}` expects `{0}.<Variant>` patterns",
+        "Un `match` sur `{0}` attend des motifs `{0}.<Variante>`",
+    ),
+    ("unknown-annotation", "Unknown annotation `@{0}`", "Annotation `@{0}` inconnue"),
+    (
+        "misplaced-annotation.derive",
+        "`@derive` only applies to a `struct` or `enum` declaration",
+        "`@derive` ne s'applique qu'à une déclaration `struct` ou `enum`",
+    ),
+    (
+        "misplaced-annotation.rust-attr",
+        "`@rust_attr` only applies to a function, method, `struct` or `enum` declaration",
+        "`@rust_attr` ne s'applique qu'à une fonction, une méthode ou une déclaration `struct` ou `enum`",
+    ),
+    (
+        "misplaced-annotation",
+        "`@{0}` only applies to a function, method, `struct`, `enum` or statement",
+        "`@{0}` ne s'applique qu'à une fonction, une méthode, un `struct`, un `enum` ou une instruction",
+    ),
+    (
+        "invalid-rust-attr",
+        "`@rust_attr` takes one Rust attribute as a string, e.g. `@rust_attr(\"#[inline(always)]\")`, not `{0}`",
+        "`@rust_attr` prend un attribut Rust dans une chaîne, par ex. `@rust_attr(\"#[inline(always)]\")`, et non \
+         `{0}`",
+    ),
+    (
+        "invalid-cfg",
+        "Invalid `@cfg` condition `{0}`: expected `test`, `debug`, `release`, `unix`, `windows`, \
+         `target_os = \"...\"`, `feature = \"...\"`, or `not`/`all`/`any` of those",
+        "Condition `@cfg` invalide `{0}` : attendu `test`, `debug`, `release`, `unix`, `windows`, \
+         `target_os = \"...\"`, `feature = \"...\"`, ou `not`/`all`/`any` de celles-ci",
+    ),
+    (
+        "unknown-derive",
+        "`{0}` cannot be derived; supported traits are {1}",
+        "`{0}` ne peut pas être dérivé ; les traits possibles sont {1}",
+    ),
+    (
+        "unknown-derive.option",
+        "`{0}` in `compilerOptions.derive` cannot be derived; supported traits are {1}",
+        "`{0}` dans `compilerOptions.derive` ne peut pas être dérivé ; les traits possibles sont {1}",
+    ),
+    (
+        "unknown-trait",
+        "`{0}` is not a trait TRUST can implement; expected `Iterable<T>`",
+        "`{0}` n'est pas un trait que TRUST sait implémenter ; attendu `Iterable<T>`",
+    ),
+    (
+        "unknown-trait.item",
+        "`Iterable` needs its item type, as in `Iterable<int32>`",
+        "`Iterable` attend le type de ses éléments, comme dans `Iterable<int32>`",
+    ),
+    (
+        "iterable-methods",
+        "`{0}` implements `Iterable`, so it needs `hasNext(): boolean` and a `next()` returning its item type",
+        "`{0}` implémente `Iterable` : il lui faut `hasNext(): boolean` et un `next()` qui renvoie le type de ses \
+         éléments",
+    ),
+    ("bytes-literal", "A bytes literal needs a string", "Un littéral d'octets attend une chaîne"),
+    (
+        "bytes-literal.hex",
+        "`hex\"{0}\"` needs pairs of hex digits",
+        "`hex\"{0}\"` attend des paires de chiffres hexadécimaux",
+    ),
+    ("bytes-literal.escape", "Invalid escape in `b{0}`", "Séquence d'échappement invalide dans `b{0}`"),
+    (
+        "unknown-encoding",
+        "Unknown encoding `{0}`; expected one of {1}",
+        "Encodage `{0}` inconnu ; attendu l'un de {1}",
+    ),
+    (
+        "non-optional",
+        "`{0}` needs an optional value (`Option<T>`), but this is a `{1}`, which is never null",
+        "`{0}` attend une valeur optionnelle (`Option<T>`), mais ceci est un `{1}`, qui n'est jamais nul",
+    ),
+    (
+        "unsupported-optional-call",
+        "Optional calls (`f?.()`) are not supported; optional method calls (`a?.m()`) are",
+        "Les appels optionnels (`f?.()`) ne sont pas pris en charge ; les appels de méthode optionnels \
+         (`a?.m()`) le sont",
+    ),
+    (
+        "unsupported-assignment.operator",
+        "`{0}` needs a variable or a field on its left",
+        "`{0}` attend une variable ou un champ à sa gauche",
+    ),
+    (
+        "logical-assign-non-bool",
+        "`{0}` needs a `bool` on its left, but this is a `{1}`; use `??=` to fill in a missing optional value",
+        "`{0}` attend un `bool` à sa gauche, mais ceci est un `{1}` ; utilisez `??=` pour remplir une valeur \
+         optionnelle absente",
+    ),
+    (
+        "decimal-literal",
+        "`{0}d` does not fit in a `decimal` (at most 28 digits after the point)",
+        "`{0}d` ne tient pas dans un `decimal` (28 chiffres au plus après la virgule)",
+    ),
+    (
+        "decimal-float",
+        "A float does not convert to `decimal` implicitly; use `decimal(x)`, which may round",
+        "Un flottant ne se convertit pas implicitement en `decimal` ; utilisez `decimal(x)`, qui peut arrondir",
+    ),
+    (
+        "unsupported-operator",
+        "The `{0}` operator is not supported",
+        "L'opérateur `{0}` n'est pas pris en charge",
+    ),
+    (
+        "format-arguments",
+        "The format string has {0} {0|placeholder|placeholders} but {1} {1|value is|values are} given",
+        "La chaîne de format a {0} {0|emplacement|emplacements} mais {1} {1|valeur est donnée|valeurs sont données}",
+    ),
+    (
+        "arity-mismatch",
+        "{0} expects {1} {1|argument|arguments} but {2} {2|was|were} given",
+        "{0} attend {1} {1|argument|arguments} mais en reçoit {2}",
+    ),
+    (
+        "arity-mismatch.abort",
+        "`{0}` expects at most 1 argument but {1} were given",
+        "`{0}` attend au plus 1 argument mais en reçoit {1}",
+    ),
+    (
+        "arity-mismatch.from-string",
+        "`{0}.fromString` expects 1 argument but {1} were given",
+        "`{0}.fromString` attend 1 argument mais en reçoit {1}",
+    ),
+    (
+        "enum-utilities-disabled",
+        "`{0}.{1}()` needs `compilerOptions.enumUtilities` to be enabled",
+        "`{0}.{1}()` demande d'activer `compilerOptions.enumUtilities`",
+    ),
+    (
+        "unknown-method",
+        "unknown method `{0}` for type string",
+        "méthode `{0}` inconnue pour le type string",
+    ),
+    (
+        "unknown-method.suggestion",
+        "unknown method `{0}` for type string; did you mean `{1}`?",
+        "méthode `{0}` inconnue pour le type string ; vouliez-vous dire `{1}` ?",
+    ),
+    (
+        "unassigned-read",
+        "`{0}` is read before it is assigned on every path; assign it first or give it an initializer",
+        "`{0}` est lu avant d'être affecté sur tous les chemins ; affectez-le d'abord ou donnez-lui une valeur \
+         initiale",
+    ),
+    (
+        "val-reassigned",
+        "`{0}` is a `val` and may already be assigned here; declare it with `var` to assign it again",
+        "`{0}` est un `val` et peut déjà être affecté ici ; déclarez-le avec `var` pour l'affecter à nouveau",
+    ),
+    (
+        "sort-needs-comparator",
+        "`{0}` values have no natural order; pass a comparator (`sort((a, b) => ...)`) or a sort key \
+         (`sortBy(x => x.field)`)",
+        "Les valeurs `{0}` n'ont pas d'ordre naturel ; passez un comparateur (`sort((a, b) => ...)`) ou une clé \
+         de tri (`sortBy(x => x.field)`)",
+    ),
+    (
+        "unknown-import",
+        "`trusty:{0}` has no export named `{1}`",
+        "`trusty:{0}` n'exporte rien qui s'appelle `{1}`",
+    ),
+    (
+        "unknown-import.suggestion",
+        "`trusty:{0}` has no export named `{1}`; did you mean `{2}`?",
+        "`trusty:{0}` n'exporte rien qui s'appelle `{1}` ; vouliez-vous dire `{2}` ?",
+    ),
+    (
+        "stdlib-version",
+        "`trusty:rand` works with `rand` 0.8 or 0.9, but trusty.json pins `rand = \"{0}\"`",
+        "`trusty:rand` fonctionne avec `rand` 0.8 ou 0.9, mais trusty.json fixe `rand = \"{0}\"`",
+    ),
+    (
+        "unsupported-statement",
+        "`{0}` is not supported in TRUST",
+        "`{0}` n'est pas pris en charge en TRUST",
+    ),
+    (
+        "unsupported-expression",
+        "This expression is not supported in TRUST: `{0}`",
+        "Cette expression n'est pas prise en charge en TRUST : `{0}`",
+    ),
+    (
+        "unsupported-pattern",
+        "Destructuring is not supported in TRUST: `{0}`; bind a single name",
+        "La déstructuration n'est pas prise en charge en TRUST : `{0}` ; liez un seul nom",
+    ),
+    (
+        "unsupported-assignment",
+        "Only a variable or a field can be assigned to, not `{0}`",
+        "Seule une variable ou un champ peut être affecté, pas `{0}`",
+    ),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages_in_each_locale() {
+        let message = msg!("arity-mismatch", "`add`", 2, 1);
+        assert_eq!(
+            message.text(Locale::En),
+            "`add` expects 2 arguments but 1 was given"
+        );
+        assert_eq!(
+            message.text(Locale::Fr),
+            "`add` attend 2 arguments mais en reçoit 1"
+        );
+        let format = msg!("format-arguments", 1, 0);
+        assert_eq!(
+            format.text(Locale::En),
+            "The format string has 1 placeholder but 0 values are given"
+        );
+        assert_eq!(
+            format.text(Locale::Fr),
+            "La chaîne de format a 1 emplacement mais 0 valeur est donnée"
+        );
+        // Braces of code in a text are not placeholders.
+        assert!(msg!("unsupported-while")
+            .text(Locale::En)
+            .contains("`loop (condition) { ... }`"));
+        assert_eq!(Message::from("as it is").text(Locale::Fr), "as it is");
+    }
+
+    #[test]
+    fn test_parse_locale() {
+        assert_eq!(Locale::parse("fr_FR.UTF-8"), Some(Locale::Fr));
+        assert_eq!(Locale::parse("fr-CA"), Some(Locale::Fr));
+        assert_eq!(Locale::parse("en"), Some(Locale::En));
+        assert_eq!(Locale::parse("C"), Some(Locale::En));
+        assert_eq!(Locale::parse("de_DE"), None);
+    }
+
+    #[test]
+    fn test_every_translation_has_the_placeholders_of_its_english_text() {
+        let placeholders = |text: &str| {
+            let mut found: Vec<String> = (0..4)
+                .map(|i| format!("{{{}", i))
+                .filter(|p| text.contains(p.as_str()))
+                .collect();
+            found.sort();
+            found
+        };
+        for (key, en, fr) in CATALOG {
+            assert!(
+                fr.is_empty() || placeholders(en) == placeholders(fr),
+                "{}",
+                key
+            );
+        }
+    }
+}
@@ -0,0 +1,428 @@
+//! Local module resolution (`import { x } from "./other"`) and bundling.
+//!
+//! Local modules are concatenated, dependencies first, into one source before compiling.
+
+use crate::compiler::FileLoader;
+use anyhow::{bail, Context, Result};
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use swc_ecma_ast::{Decl, ModuleItem, Stmt, VarDeclKind};
+
+/// Line opening each module in a bundle, followed by the module id.
+const MODULE_MARKER: &str = "// --- module: ";
+
+/// Locates and reads TRUST modules.
+///
+/// Module ids are the paths returned by [`ModuleResolver::resolve`]; two imports of the same file
+/// must resolve to the same id so it is bundled once.
+pub trait ModuleResolver {
+    /// Resolves `specifier` imported from the module `importer`, or the entry file when `importer` is `None`.
+    fn resolve(&self, importer: Option<&Path>, specifier: &str) -> Result<PathBuf>;
+
+    /// Reads the source of a resolved module.
+    fn load(&self, id: &Path) -> Result<String>;
+}
+
+/// Paths tried for `specifier` relative to `base_dir`: as written, then `.trs`, then `index.trs`.
+pub fn import_candidates(base_dir: &Path, specifier: &str) -> Vec<PathBuf> {
+    let candidate = base_dir.join(specifier);
+    let mut tries = vec![candidate.clone()];
+    if candidate.extension().is_none() {
+        tries.push(candidate.with_extension("trs"));
+        tries.push(candidate.join("index.trs"));
+    }
+    tries
+}
+
+/// Resolves `.` and `..` components without touching the filesystem.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !out.pop() {
+                    out.push("..");
+                }
+            }
+            other => out.push(other.as_os_str()),
+        }
+    }
+    out
+}
+
+fn importer_dir(importer: &Path) -> &Path {
+    importer.parent().unwrap_or_else(|| Path::new("."))
+}
+
+/// Resolves modules on disk; ids are canonical paths.
+#[cfg(feature = "fs")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FsResolver;
+
+#[cfg(feature = "fs")]
+impl ModuleResolver for FsResolver {
+    fn resolve(&self, importer: Option<&Path>, specifier: &str) -> Result<PathBuf> {
+        let Some(importer) = importer else {
+            return Path::new(specifier)
+                .canonicalize()
+                .with_context(|| format!("Failed to resolve {}", specifier));
+        };
+        let base_dir = importer_dir(importer);
+        for candidate in import_candidates(base_dir, specifier) {
+            if candidate.exists() {
+                return candidate
+                    .canonicalize()
+                    .with_context(|| format!("Failed to resolve {}", candidate.display()));
+            }
+        }
+        bail!(
+            "Cannot resolve local import '{}' from {}",
+            specifier,
+            base_dir.display()
+        )
+    }
+
+    fn load(&self, id: &Path) -> Result<String> {
+        std::fs::read_to_string(id).with_context(|| format!("Failed to read {}", id.display()))
+    }
+}
+
+/// Virtual file tree for tests, playgrounds and editors; ids are normalized paths.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryResolver {
+    files: HashMap<PathBuf, String>,
+}
+
+impl MemoryResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_file(mut self, path: impl AsRef<Path>, source: impl Into<String>) -> Self {
+        self.insert(path, source);
+        self
+    }
+
+    pub fn insert(&mut self, path: impl AsRef<Path>, source: impl Into<String>) {
+        self.files
+            .insert(normalize_path(path.as_ref()), source.into());
+    }
+}
+
+impl ModuleResolver for MemoryResolver {
+    fn resolve(&self, importer: Option<&Path>, specifier: &str) -> Result<PathBuf> {
+        let candidates = match importer {
+            Some(importer) => import_candidates(importer_dir(importer), specifier),
+            None => vec![PathBuf::from(specifier)],
+        };
+        for candidate in candidates {
+            let id = normalize_path(&candidate);
+            if self.files.contains_key(&id) {
+                return Ok(id);
+            }
+        }
+        match importer {
+            Some(importer) => bail!(
+                "Cannot resolve local import '{}' from {}",
+                specifier,
+                importer_dir(importer).display()
+            ),
+            None => bail!("Failed to resolve {}", specifier),
+        }
+    }
+
+    fn load(&self, id: &Path) -> Result<String> {
+        self.files
+            .get(id)
+            .cloned()
+            .with_context(|| format!("Failed to read {}", id.display()))
+    }
+}
+
+/// Adapts a [`FileLoader`] (see [`crate::Compiler::file_loader`]): a candidate resolves when it loads.
+pub struct LoaderResolver<L>(pub L);
+
+impl<L: FileLoader> ModuleResolver for LoaderResolver<L> {
+    fn resolve(&self, importer: Option<&Path>, specifier: &str) -> Result<PathBuf> {
+        let candidates = match importer {
+            Some(importer) => import_candidates(importer_dir(importer), specifier),
+            None => vec![PathBuf::from(specifier)],
+        };
+        for candidate in candidates {
+            let id = normalize_path(&candidate);
+            if self.0.load(&id).is_ok() {
+                return Ok(id);
+            }
+        }
+        bail!("Cannot resolve local import '{}'", specifier)
+    }
+
+    fn load(&self, id: &Path) -> Result<String> {
+        self.0
+            .load(id)
+            .with_context(|| format!("Failed to read {}", id.display()))
+    }
+}
+
+// ─── Bundling ────────────────────────────────────────────────────────────────
+
+/// An import line on the way from the entry file to a module that failed to bundle.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImportSite {
+    /// Module containing the import.
+    pub module: PathBuf,
+    /// 0-based line of the import in `module`.
+    pub line: usize,
+    /// Length of that line in characters.
+    pub width: usize,
+    pub specifier: String,
+}
+
+/// A bundling failure (unresolved path, circular import, bad module) with the imports that led to
+/// it, entry file first; its message is the underlying error's.
+#[derive(Debug)]
+pub struct ImportChainError {
+    pub imports: Vec<ImportSite>,
+    error: anyhow::Error,
+}
+
+impl std::fmt::Display for ImportChainError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#}", self.error)
+    }
+}
+
+impl std::error::Error for ImportChainError {}
+
+/// Records that `err` happened while bundling the import `site`, an outer link of its chain.
+fn through_import(err: anyhow::Error, site: ImportSite) -> anyhow::Error {
+    match err.downcast::<ImportChainError>() {
+        Ok(mut chained) => {
+            chained.imports.insert(0, site);
+            chained.into()
+        }
+        Err(error) => ImportChainError {
+            imports: vec![site],
+            error,
+        }
+        .into(),
+    }
+}
+
+/// A module read during the first bundling phase.
+struct LoadedModule {
+    /// Source with `export` stripped and local imports blanked, so lines keep their numbers.
+    body: String,
+    /// Each local import with the module it resolved to, in source order.
+    imports: Vec<(ImportSite, PathBuf)>,
+    /// Only declarations (functions, types, `const`s, `static var`s) at the top level: nothing that
+    /// runs in order, so the module can be part of an import cycle.
+    declarations_only: bool,
+}
+
+/// Bundles `entry` and every local module it imports (transitively) into one source.
+///
+/// The first phase reads the whole import graph; the second emits each module once, after the
+/// modules it imports. Everything lands in one Rust file, where item order does not matter, so
+/// modules may import each other in a cycle as long as none of them has top-level statements.
+pub fn bundle_modules(entry: &Path, resolver: &dyn ModuleResolver) -> Result<String> {
+    let entry_id = resolver.resolve(None, &entry.to_string_lossy())?;
+    let mut graph = HashMap::new();
+    load_module(&entry_id, resolver, &mut graph)?;
+    let mut emitted = HashSet::new();
+    let mut stack = Vec::new();
+    let mut out = String::new();
+    emit_module(&entry_id, &graph, &mut emitted, &mut stack, &mut out)?;
+    Ok(out)
+}
+
+fn load_module(
+    id: &Path,
+    resolver: &dyn ModuleResolver,
+    graph: &mut HashMap<PathBuf, LoadedModule>,
+) -> Result<()> {
+    if graph.contains_key(id) {
+        return Ok(());
+    }
+    let source = resolver.load(id)?;
+    let mut imports = Vec::new();
+    let mut body_lines = Vec::new();
+    for (line_no, line) in source.lines().enumerate() {
+        if let Some(import_path) = parse_local_import_path(line) {
+            let site = ImportSite {
+                module: id.to_path_buf(),
+                line: line_no,
+                width: line.chars().count(),
+                specifier: import_path.clone(),
+            };
+            let dep_id = resolver
+                .resolve(Some(id), &import_path)
+                .map_err(|err| through_import(err, site.clone()))?;
+            imports.push((site, dep_id));
+            body_lines.push(String::new());
+            continue;
+        }
+        body_lines.push(line.to_string());
+    }
+    let body = rewrite_export_declarations(&body_lines.join("\n"))
+        .with_context(|| format!("In module {}", id.display()))?;
+    let declarations_only = declarations_only(&body);
+    graph.insert(
+        id.to_path_buf(),
+        LoadedModule {
+            body,
+            imports: imports.clone(),
+            declarations_only,
+        },
+    );
+
+    for (site, dep_id) in imports {
+        load_module(&dep_id, resolver, graph).map_err(|err| through_import(err, site))?;
+    }
+    Ok(())
+}
+
+fn emit_module(
+    id: &Path,
+    graph: &HashMap<PathBuf, LoadedModule>,
+    emitted: &mut HashSet<PathBuf>,
+    stack: &mut Vec<PathBuf>,
+    out: &mut String,
+) -> Result<()> {
+    if emitted.contains(id) {
+        return Ok(());
+    }
+    if let Some(start) = stack.iter().position(|p| p == id) {
+        // `id` is being emitted further up: a cycle, fine between declaration-only modules.
+        let cycle = &stack[start..];
+        if let Some(module) = cycle
+            .iter()
+            .find(|module| !graph[*module].declarations_only)
+        {
+            let chain = stack
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            bail!(
+                "Circular local import detected: {} -> {} ({} has statements outside functions; modules in an import cycle may only declare functions, types and constants)",
+                chain,
+                id.display(),
+                module.display()
+            );
+        }
+        return Ok(());
+    }
+    stack.push(id.to_path_buf());
+    let module = &graph[id];
+    for (site, dep_id) in &module.imports {
+        emit_module(dep_id, graph, emitted, stack, out)
+            .map_err(|err| through_import(err, site.clone()))?;
+    }
+    stack.pop();
+    emitted.insert(id.to_path_buf());
+
+    out.push_str(&format!("{}{} ---\n", MODULE_MARKER, id.display()));
+    out.push_str(&module.body);
+    out.push('\n');
+    Ok(())
+}
+
+/// Whether `body` has only declarations at the top level. A module that does not parse counts as
+/// one, so compiling the bundle reports the syntax error rather than a cycle.
+fn declarations_only(body: &str) -> bool {
+    let preprocessed = crate::preprocessor::preprocess(body);
+    let Ok(module) = crate::parser::parse_module(&preprocessed.text) else {
+        return true;
+    };
+    module.body.iter().all(|item| match item {
+        ModuleItem::Stmt(Stmt::Decl(Decl::Var(var_decl))) => {
+            var_decl.kind == VarDeclKind::Const
+                || preprocessed.is_static(crate::parser::source_span(var_decl.span).start)
+        }
+        ModuleItem::Stmt(Stmt::Decl(_) | Stmt::Empty(_)) | ModuleItem::ModuleDecl(_) => true,
+        ModuleItem::Stmt(_) => false,
+    })
+}
+
+/// `file:line:column` of byte `offset` in a bundle, or `line <line>:<column>` outside bundled modules.
+pub fn source_location(bundle: &str, offset: usize) -> String {
+    match locate(bundle, offset) {
+        (Some(id), line, column) => format!("{}:{}:{}", id, line, column),
+        (None, line, column) => format!("line {}:{}", line, column),
+    }
+}
+
+/// Module of byte `offset` in a bundle (`None` outside bundled modules, e.g. in a single file) and
+/// the 1-based line and column there.
+pub fn locate(bundle: &str, offset: usize) -> (Option<&str>, usize, usize) {
+    let mut offset = offset.min(bundle.len());
+    while !bundle.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let upto = &bundle[..offset];
+    let column = upto
+        .rsplit('\n')
+        .next()
+        .map_or(0, |line| line.chars().count())
+        + 1;
+    let module = upto.rfind(MODULE_MARKER).and_then(|start| {
+        let (header, body) = upto[start + MODULE_MARKER.len()..].split_once('\n')?;
+        Some((header.strip_suffix(" ---")?, body))
+    });
+    match module {
+        // The module's first line follows its marker.
+        Some((id, body)) => (Some(id), body.matches('\n').count() + 1, column),
+        None => (None, upto.matches('\n').count() + 1, column),
+    }
+}
+
+/// Specifier of a local import line (`import { a } from "./a";` → `./a`).
+pub fn parse_local_import_path(line: &str) -> Option<String> {
+    parse_import_path(line).filter(|path| path.starts_with("./") || path.starts_with("../"))
+}
+
+/// Module path of an `import ... from "path"` line, local or not.
+pub fn parse_import_path(line: &str) -> Option<String> {
+    let trimmed = line.trim();
+    if !trimmed.starts_with("import ") {
+        return None;
+    }
+    let from_idx = trimmed.find(" from ")?;
+    let after_from = trimmed[from_idx + " from ".len()..].trim();
+    let quote = after_from.chars().next()?;
+    if quote != '"' && quote != '\'' {
+        return None;
+    }
+    let rest = &after_from[1..];
+    let end = rest.find(quote)?;
+    Some(rest[..end].to_string())
+}
+
+fn rewrite_export_declarations(source: &str) -> Result<String> {
+    let mut out = Vec::new();
+    for line in source.lines() {
+        let trimmed = line.trim_start();
+        if let Some(rest) = trimmed.strip_prefix("export ") {
+            let allowed = rest.starts_with("function ")
+                || rest.starts_with("const ")
+                || rest.starts_with("struct ")
+                || rest.starts_with("enum ")
+                || rest.starts_with("implements ")
+                // Reported by the compiler, with its `struct` + `implements` rewrite.
+                || rest.starts_with("class ");
+            if !allowed {
+                bail!(
+                    "Unsupported export syntax: '{}'. Supported: export function/const/struct/enum/implements",
+                    trimmed
+                );
+            }
+            let indent = &line[..line.len() - trimmed.len()];
+            out.push(format!("{}{}", indent, rest));
+        } else {
+            out.push(line.to_string());
+        }
+    }
+    Ok(out.join("\n"))
+}
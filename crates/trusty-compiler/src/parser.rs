@@ -1,14 +1,18 @@
+use crate::diagnostics::{Diagnostic, Span};
+use crate::messages::msg;
 use anyhow::Result;
-use swc_common::{sync::Lrc, FileName, SourceMap};
+use swc_common::{sync::Lrc, FileName, SourceMap, Spanned};
 use swc_ecma_ast::Module;
 use swc_ecma_parser::{Parser, StringInput, Syntax, TsSyntax};
 
 pub fn parse_typescript(code: &str) -> Result<Module> {
+    parse_module(code).map_err(|diag| anyhow::anyhow!(diag.message))
+}
+
+/// Parses preprocessed source, keeping the error location as a [`Diagnostic`] span.
+pub fn parse_module(code: &str) -> std::result::Result<Module, Box<Diagnostic>> {
     let cm: Lrc<SourceMap> = Default::default();
-    let fm = cm.new_source_file(
-        FileName::Custom("input.trs".into()).into(),
-        code.to_owned(),
-    );
+    let fm = cm.new_source_file(FileName::Custom("input.trs".into()).into(), code.to_owned());
 
     let mut parser = Parser::new(
         Syntax::Typescript(TsSyntax {
@@ -20,11 +24,23 @@ pub fn parse_typescript(code: &str) -> Result<Module> {
         None,
     );
 
-    let module = parser
-        .parse_module()
-        .map_err(|e| anyhow::anyhow!("Parse error: {:?}", e))?;
+    parser.parse_module().map_err(|e| {
+        let lo = (e.span().lo.0 - fm.start_pos.0) as usize;
+        let hi = (e.span().hi.0 - fm.start_pos.0) as usize;
+        Box::new(
+            Diagnostic::error("parse-error", msg!("parse-error", e.kind().msg()))
+                .with_span(Span::new(lo, hi.max(lo))),
+        )
+    })
+}
 
-    Ok(module)
+/// Converts a span from a module returned by [`parse_module`] to a byte range in its source.
+///
+/// Each parse uses a fresh `SourceMap`, whose only file starts at `BytePos(1)`.
+pub fn source_span(span: swc_common::Span) -> Span {
+    let lo = span.lo.0.saturating_sub(1) as usize;
+    let hi = span.hi.0.saturating_sub(1) as usize;
+    Span::new(lo, hi.max(lo))
 }
 
 #[cfg(test)]
@@ -0,0 +1,1115 @@
+//! Rewrites TRUST-only syntax into TypeScript before SWC parsing.
+//!
+//! Source is split into tokens first, so strings, comments and object keys are never rewritten. Each
+//! rewrite is a small edit on the original text (`val` → `let`, inserted call parentheses, ...);
+//! whitespace is kept, so lines keep their numbers, and [`Preprocessed`] maps output offsets back
+//! to the original source.
+
+use crate::diagnostics::Span;
+use crate::transpiler::{bytes, decimal, errors, expressions, matching, strings};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    Ident,
+    Number,
+    /// `'...'`, `"..."`, or a raw string `r"..."` / `r#"..."#`.
+    Str,
+    /// A template literal piece: `` `text${ ``, `}text${` or `` }text` ``; substitutions are code.
+    Template,
+    Comment,
+    Whitespace,
+    /// A single punctuation character; multi-character operators are consecutive tokens.
+    Punct,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || c == '$'
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+fn scan_while(source: &str, start: usize, pred: impl Fn(char) -> bool) -> usize {
+    source[start..]
+        .find(|c: char| !pred(c))
+        .map_or(source.len(), |n| start + n)
+}
+
+/// End of a quoted string whose body starts at `start`; unterminated strings run to the line end.
+fn scan_string(source: &str, start: usize, quote: char) -> usize {
+    let mut chars = source[start..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '\n' => return start + i,
+            c if c == quote => return start + i + 1,
+            _ => {}
+        }
+    }
+    source.len()
+}
+
+/// End of the raw string `r"..."` / `r#"..."#` starting at `start`, if one does; it may span lines,
+/// and an unterminated one runs to the end of the source.
+fn scan_raw_string(source: &str, start: usize) -> Option<usize> {
+    let rest = source[start..].strip_prefix('r')?;
+    let hashes = rest.len() - rest.trim_start_matches('#').len();
+    let body = rest[hashes..].strip_prefix('"')?;
+    let body_start = source.len() - body.len();
+    let closing = format!("\"{}", "#".repeat(hashes));
+    Some(
+        body.find(&closing)
+            .map_or(source.len(), |n| body_start + n + closing.len()),
+    )
+}
+
+/// End of a template piece starting at `start`: after the closing backtick, or after `${`, in which
+/// case the brace depth `depth` is recorded so the matching `}` resumes the template.
+fn scan_template(source: &str, start: usize, templates: &mut Vec<usize>, depth: usize) -> usize {
+    let mut chars = source[start..].char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '`' => return start + i + 1,
+            '$' if chars.peek().is_some_and(|&(_, next)| next == '{') => {
+                templates.push(depth);
+                return start + i + 2;
+            }
+            _ => {}
+        }
+    }
+    source.len()
+}
+
+/// `1`, `1_000`, `0xff`, `1.5e3`; `0..n` stops before the dots.
+fn scan_number(source: &str, start: usize) -> usize {
+    let mut end = scan_while(source, start, is_ident_char);
+    while source[end..].starts_with('.')
+        && source[end + 1..].starts_with(|c: char| c.is_ascii_digit())
+    {
+        end = scan_while(source, end + 1, is_ident_char);
+    }
+    end
+}
+
+/// Splits `source` into tokens covering every byte.
+pub fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    // Brace depth of each open `${`, innermost last.
+    let mut templates: Vec<usize> = Vec::new();
+    let mut depth = 0usize;
+    let mut i = 0;
+    while let Some(c) = source[i..].chars().next() {
+        let start = i;
+        let rest = &source[i..];
+        let kind = if c.is_whitespace() {
+            i = scan_while(source, i, char::is_whitespace);
+            TokenKind::Whitespace
+        } else if rest.starts_with("//") {
+            i = rest.find('\n').map_or(source.len(), |n| i + n);
+            TokenKind::Comment
+        } else if let Some(body) = rest.strip_prefix("/*") {
+            i = body.find("*/").map_or(source.len(), |n| i + 2 + n + 2);
+            TokenKind::Comment
+        } else if c == '"' || c == '\'' {
+            i = scan_string(source, i + 1, c);
+            TokenKind::Str
+        } else if c == '`' {
+            i = scan_template(source, i + 1, &mut templates, depth);
+            TokenKind::Template
+        } else if c == '}' && templates.last() == Some(&depth) {
+            templates.pop();
+            i = scan_template(source, i + 1, &mut templates, depth);
+            TokenKind::Template
+        } else if c.is_ascii_digit() {
+            i = scan_number(source, i);
+            TokenKind::Number
+        } else if let Some(end) = scan_raw_string(source, i) {
+            i = end;
+            TokenKind::Str
+        } else if is_ident_start(c) {
+            i = scan_while(source, i, is_ident_char);
+            TokenKind::Ident
+        } else {
+            match c {
+                '{' => depth += 1,
+                '}' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            i += c.len_utf8();
+            TokenKind::Punct
+        };
+        tokens.push(Token {
+            kind,
+            span: Span::new(start, i),
+        });
+    }
+    tokens
+}
+
+/// `@name(args)` written before a declaration, e.g. `@derive(PartialEq, Hash)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Annotation {
+    pub name: String,
+    /// Source text between the parentheses, trimmed; empty without them.
+    pub args: String,
+    /// Source span of the whole annotation.
+    pub span: Span,
+    /// Source offset of the declaration it applies to (after any `export`).
+    pub target: usize,
+}
+
+/// Preprocessed text plus the edits that produced it, for mapping spans back to the source.
+#[derive(Clone, Debug, Default)]
+pub struct Preprocessed {
+    pub text: String,
+    source: String,
+    /// `(original, output)` span of each edit, in order.
+    edits: Vec<(Span, Span)>,
+    /// Source offsets of the `var` keywords of `static var` declarations.
+    statics: Vec<usize>,
+    annotations: Vec<Annotation>,
+}
+
+impl Preprocessed {
+    /// The source that was preprocessed.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Offset in the source of output byte `offset`. An offset inside rewritten text maps to the start
+    /// of what it replaced, or to its end when `end` is set.
+    pub fn original_offset(&self, offset: usize, end: bool) -> usize {
+        let before = self.edits.partition_point(|(_, output)| {
+            if end {
+                output.start < offset
+            } else {
+                output.start <= offset
+            }
+        });
+        match before.checked_sub(1).map(|i| self.edits[i]) {
+            None => offset,
+            Some((original, output)) if offset >= output.end => {
+                original.end + (offset - output.end)
+            }
+            Some((original, _)) if end => original.end,
+            Some((original, _)) => original.start,
+        }
+    }
+
+    /// Output offset of source byte `offset`; inverse of [`Preprocessed::original_offset`].
+    pub fn output_offset(&self, offset: usize) -> usize {
+        let before = self
+            .edits
+            .partition_point(|(original, _)| original.start <= offset);
+        match before.checked_sub(1).map(|i| self.edits[i]) {
+            None => offset,
+            Some((original, output)) if offset >= original.end => {
+                output.end + (offset - original.end)
+            }
+            Some((_, output)) => output.start,
+        }
+    }
+
+    /// `span` of the source moved to the output, for reporting a source span like a parser one.
+    pub fn output_span(&self, span: Span) -> Span {
+        let start = self.output_offset(span.start);
+        Span::new(start, self.output_offset(span.end).max(start))
+    }
+
+    /// `span` of the output moved to the source.
+    pub fn original_span(&self, span: Span) -> Span {
+        let start = self.original_offset(span.start, false);
+        Span::new(start, self.original_offset(span.end, true).max(start))
+    }
+
+    /// The declaration starting at output byte `offset` was written `static var`.
+    pub fn is_static(&self, offset: usize) -> bool {
+        self.statics.contains(&self.original_offset(offset, false))
+    }
+
+    /// Every annotation in the source, in order.
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+
+    /// Annotations on the declaration starting at output byte `offset`. A keyword the preprocessor
+    /// dropped there (`function` of a method) still counts as the start.
+    pub fn annotations_at(&self, offset: usize) -> impl Iterator<Item = &Annotation> {
+        let target = self.original_offset(offset, true);
+        self.annotations
+            .iter()
+            .filter(move |annotation| annotation.target == target)
+    }
+
+    /// TRUST location (`file:line:column`, or `line L:C` outside bundles) of output byte `offset`.
+    pub fn location(&self, offset: usize) -> String {
+        crate::modules::source_location(&self.source, self.original_offset(offset, false))
+    }
+}
+
+/// Rust spelling of identifier `name` when it differs: TRUST identifiers follow JavaScript's rules,
+/// which also allow `$` and the zero-width joiners U+200C / U+200D. Other Unicode letters and digits
+/// are valid Rust identifiers as they are.
+pub fn rust_identifier(name: &str) -> Option<String> {
+    if !name.contains(['$', '\u{200c}', '\u{200d}']) {
+        return None;
+    }
+    Some(
+        name.replace('$', "_dollar_")
+            .replace('\u{200c}', "_zwnj_")
+            .replace('\u{200d}', "_zwj_"),
+    )
+}
+
+/// Text between the quotes of the raw string token `text` (`r"..."` / `r#"..."#`), or `None` when it
+/// is unterminated.
+pub fn raw_string_body(text: &str) -> Option<&str> {
+    let after_r = text.strip_prefix('r')?;
+    let hashes = after_r.len() - after_r.trim_start_matches('#').len();
+    let quoted = after_r[hashes..].strip_prefix('"')?;
+    quoted.strip_suffix(&format!("\"{}", "#".repeat(hashes)))
+}
+
+/// A TypeScript `class` in TRUST source, which TRUST writes as a `struct` plus `implements`.
+pub struct ClassDeclaration {
+    pub name: String,
+    /// `class Name`.
+    pub span: Span,
+    /// The whole declaration and its `struct` + `implements` spelling, when it only has plain
+    /// fields and methods (no constructor, modifiers, initializers or `extends`).
+    pub rewrite: Option<(Span, String)>,
+}
+
+/// Every `class Name` declaration or expression of `source`.
+pub fn class_declarations(source: &str, tokens: &[Token]) -> Vec<ClassDeclaration> {
+    let code: Vec<&Token> = tokens
+        .iter()
+        .filter(|token| !matches!(token.kind, TokenKind::Whitespace | TokenKind::Comment))
+        .collect();
+    let text = |k: usize| {
+        code.get(k)
+            .map_or("", |token| &source[token.span.start..token.span.end])
+    };
+    let is_ident = |k: usize| {
+        code.get(k)
+            .is_some_and(|token| token.kind == TokenKind::Ident)
+    };
+    (0..code.len())
+        .filter(|&k| {
+            text(k) == "class" && is_ident(k) && is_ident(k + 1) && (k == 0 || text(k - 1) != ".")
+        })
+        .map(|k| ClassDeclaration {
+            name: text(k + 1).to_string(),
+            span: Span::new(code[k].span.start, code[k + 1].span.end),
+            rewrite: class_rewrite(source, &code, k),
+        })
+        .collect()
+}
+
+/// `class Point { x: int32; area(): int32 {...} }` →
+/// `struct Point { x: int32; }` and `implements Point { function area(): int32 {...} }`.
+fn class_rewrite(source: &str, code: &[&Token], class: usize) -> Option<(Span, String)> {
+    let text = |k: usize| {
+        code.get(k)
+            .map_or("", |token| &source[token.span.start..token.span.end])
+    };
+    let depth_change = |k: usize| match (code[k].kind, text(k)) {
+        (TokenKind::Punct, "(" | "[" | "{") => 1,
+        (TokenKind::Punct, ")" | "]" | "}") => -1,
+        _ => 0,
+    };
+    // Index of the bracket closing the one at `open`.
+    let matching = |open: usize| {
+        let mut depth = 0isize;
+        (open..code.len()).find(|&k| {
+            depth += depth_change(k);
+            depth == 0
+        })
+    };
+    let new_line =
+        |k: usize| k > 0 && source[code[k - 1].span.end..code[k].span.start].contains('\n');
+
+    // Only a declaration can be rewritten, not `val A = class A {}`.
+    let export = class > 0 && text(class - 1) == "export";
+    let start = if export { class - 1 } else { class };
+    if start > 0 && !matches!(text(start - 1), ";" | "{" | "}" | ")") && !new_line(start) {
+        return None;
+    }
+    let open = (class + 1..code.len()).find(|&k| text(k) == "{")?;
+    if (class + 1..open).any(|k| matches!(text(k), "extends" | "implements")) {
+        return None;
+    }
+    let close = matching(open)?;
+    let header = &source[code[class + 1].span.start..code[open - 1].span.end];
+    let export = if export { "export " } else { "" };
+
+    // Member text with the comment lines above it and the comment ending its line, indented as
+    // written; also returns where it ends.
+    let member = |from: usize, first: usize, last: usize, keyword: &str| {
+        let mut lead = source[from..code[first].span.start]
+            .split_once('\n')
+            .map_or("    ", |(_, below)| below);
+        while let Some((_, below)) = lead
+            .split_once('\n')
+            .filter(|(line, _)| line.trim().is_empty())
+        {
+            lead = below;
+        }
+        let mut end = code[last].span.end;
+        let line_end = source[end..].find('\n').map_or(source.len(), |n| end + n);
+        if source[end..line_end].trim_start().starts_with("//") {
+            end = line_end;
+        }
+        (
+            format!(
+                "{}{}{}",
+                lead,
+                keyword,
+                source[code[first].span.start..end].trim_end()
+            ),
+            end,
+        )
+    };
+
+    let (mut fields, mut methods) = (Vec::new(), Vec::new());
+    let mut from = code[open].span.end;
+    let mut k = open + 1;
+    while k < close {
+        if text(k) == ";" {
+            k += 1;
+            continue;
+        }
+        if code[k].kind != TokenKind::Ident
+            || !matches!(text(k + 1), "(" | "<" | ":" | "?" | ";")
+            || text(k) == "constructor"
+        {
+            return None;
+        }
+        let last = if matches!(text(k + 1), "(" | "<") {
+            matching((k + 1..close).find(|&j| text(j) == "{")?)?
+        } else {
+            // A field runs to its `;`, or to the end of its line.
+            let mut depth = 0;
+            let mut end = k;
+            while end + 1 < close
+                && !(depth == 0 && new_line(end + 1))
+                && !(depth == 0 && text(end) == ";")
+            {
+                end += 1;
+                depth += depth_change(end);
+                if depth == 0 && text(end) == "=" {
+                    return None;
+                }
+            }
+            end
+        };
+        if matches!(text(k + 1), "(" | "<") {
+            let (method, end) = member(from, k, last, &format!("{}function ", export));
+            methods.push(method);
+            from = end;
+        } else {
+            let (field, end) = member(from, k, last, "");
+            fields.push(field);
+            from = end;
+        }
+        k = last + 1;
+    }
+
+    let fields = if fields.is_empty() {
+        String::new()
+    } else {
+        format!("\n{}\n", fields.join("\n"))
+    };
+    let mut rewrite = format!("{}struct {} {{{}}}", export, header, fields);
+    if !methods.is_empty() {
+        rewrite.push_str(&format!(
+            "\n\n{}implements {} {{\n{}\n}}",
+            export,
+            header,
+            methods.join("\n\n")
+        ));
+    }
+    Some((
+        Span::new(code[start].span.start, code[close].span.end),
+        rewrite,
+    ))
+}
+
+/// Byte offsets of the identifier tokens of `source` spelled `needle`; strings and comments are
+/// never matched.
+pub fn identifier_offsets<'a>(
+    source: &'a str,
+    tokens: &'a [Token],
+    needle: &'a str,
+) -> impl Iterator<Item = usize> + 'a {
+    tokens
+        .iter()
+        .filter(move |token| {
+            token.kind == TokenKind::Ident && &source[token.span.start..token.span.end] == needle
+        })
+        .map(|token| token.span.start)
+}
+
+/// Byte offsets where `name` is used as a type: its `struct` declaration and `implements` blocks,
+/// type annotations and arguments, constructor calls and imports. Fields, object keys, member
+/// accesses and variables spelled the same are left out.
+pub fn type_name_offsets(source: &str, tokens: &[Token], name: &str) -> Vec<usize> {
+    let code: Vec<&Token> = tokens
+        .iter()
+        .filter(|token| !matches!(token.kind, TokenKind::Whitespace | TokenKind::Comment))
+        .collect();
+    let text = |i: usize| {
+        code.get(i)
+            .map_or("", |t| &source[t.span.start..t.span.end])
+    };
+    let before = |i: usize, n: usize| i.checked_sub(n).map_or("", text);
+    // Open brackets, innermost last, each marked when it is a struct body (whose `field: T`
+    // entries are annotations, unlike an object literal's `key: value`).
+    let mut brackets: Vec<(&str, bool)> = Vec::new();
+    let mut in_import = false;
+    let mut offsets = Vec::new();
+    for (i, token) in code.iter().enumerate() {
+        match text(i) {
+            "import" => in_import = true,
+            "from" | ";" => in_import = false,
+            open @ ("(" | "[" | "{") => {
+                let body = open == "{" && matches!(before(i, 2), "struct" | "interface");
+                brackets.push((open, body));
+            }
+            ")" | "]" | "}" => {
+                brackets.pop();
+            }
+            _ => {}
+        }
+        if token.kind != TokenKind::Ident || text(i) != name {
+            continue;
+        }
+        let (prev, next) = (before(i, 1), text(i + 1));
+        if prev == "." || next == ":" || (next == "?" && text(i + 2) == ":") {
+            continue;
+        }
+        let annotation = prev == ":"
+            && match (before(i, 2), brackets.last()) {
+                // `): T` and `x?: T`.
+                (")" | "?", _) => true,
+                (_, Some((_, true))) => true,
+                (_, Some(("(", _))) => matches!(before(i, 3), "(" | ","),
+                _ => matches!(before(i, 3), "val" | "var" | "const" | "let"),
+            };
+        let type_position = matches!(prev, "struct" | "interface" | "implements" | "new" | "as")
+            || (next == "(" && prev != "function")
+            || in_import
+            || annotation
+            || (prev == "<" && matches!(next, ">" | "," | "["))
+            || (prev == "," && next == ">")
+            || (prev == "|" && before(i, 2) != "|" && next != "|");
+        if type_position {
+            offsets.push(token.span.start);
+        }
+    }
+    offsets
+}
+
+/// Rewrites TRUST-only syntax in `source` to TypeScript SWC can parse.
+pub fn preprocess(source: &str) -> Preprocessed {
+    preprocess_tokens(source, tokenize(source))
+}
+
+/// [`preprocess`] with the [`tokenize`]d `source`, for callers that scanned it already.
+pub fn preprocess_tokens(source: &str, tokens: Vec<Token>) -> Preprocessed {
+    let tokens: Vec<Token> = tokens
+        .into_iter()
+        .filter(|token| !matches!(token.kind, TokenKind::Whitespace | TokenKind::Comment))
+        .collect();
+    let mut rewriter = Rewriter {
+        source,
+        tokens,
+        edits: Vec::new(),
+        statics: Vec::new(),
+        annotations: Vec::new(),
+        after_annotations: Vec::new(),
+    };
+    rewriter.run();
+    rewriter.apply()
+}
+
+/// One replacement of `span` in the source; an empty span inserts.
+struct Edit {
+    span: Span,
+    text: String,
+}
+
+/// Walks the code tokens (no whitespace or comments) and records edits.
+struct Rewriter<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    edits: Vec<Edit>,
+    statics: Vec<usize>,
+    annotations: Vec<Annotation>,
+    /// Indices of tokens right after an annotation, which start a declaration.
+    after_annotations: Vec<usize>,
+}
+
+impl Rewriter<'_> {
+    fn text(&self, k: usize) -> &str {
+        self.tokens
+            .get(k)
+            .map_or("", |token| &self.source[token.span.start..token.span.end])
+    }
+
+    fn kind(&self, k: usize) -> Option<TokenKind> {
+        self.tokens.get(k).map(|token| token.kind)
+    }
+
+    fn is_ident(&self, k: usize) -> bool {
+        self.kind(k) == Some(TokenKind::Ident)
+    }
+
+    /// Source between tokens `k` and `k + 1`.
+    fn gap_after(&self, k: usize) -> &str {
+        match (self.tokens.get(k), self.tokens.get(k + 1)) {
+            (Some(a), Some(b)) => &self.source[a.span.end..b.span.start],
+            (Some(a), None) => &self.source[a.span.end..],
+            _ => "",
+        }
+    }
+
+    /// Tokens `k` and `k + 1` form a two-character operator such as `=>`.
+    fn is_pair(&self, k: usize, first: &str, second: &str) -> bool {
+        self.text(k) == first && self.text(k + 1) == second && self.gap_after(k).is_empty()
+    }
+
+    /// Token `k` is a property name (`a.and`) rather than a keyword.
+    fn is_member(&self, k: usize) -> bool {
+        k > 0 && self.text(k - 1) == "."
+    }
+
+    /// Token `k` follows a statement or block boundary.
+    fn after_boundary(&self, k: usize) -> bool {
+        k == 0 || matches!(self.text(k - 1), ";" | "{" | "}")
+    }
+
+    /// Token `k` begins a statement, including one after a line without `;` or an annotation.
+    fn at_statement_start(&self, k: usize) -> bool {
+        self.after_boundary(k)
+            || self.gap_after(k - 1).contains('\n')
+            || self.after_annotations.contains(&k)
+    }
+
+    /// Token `k` is where an expression may start, for `try` expressions.
+    fn at_expression_start(&self, k: usize) -> bool {
+        k > 0
+            && (matches!(self.text(k - 1), "=" | "(" | "," | ":" | "?" | "return")
+                || k > 1 && self.is_pair(k - 2, "=", ">"))
+    }
+
+    /// Index of the bracket closing the one at `open`.
+    fn matching(&self, open: usize) -> Option<usize> {
+        let (open_text, close_text) = match self.text(open) {
+            "(" => ("(", ")"),
+            "[" => ("[", "]"),
+            "{" => ("{", "}"),
+            _ => return None,
+        };
+        let mut depth = 0usize;
+        for k in open..self.tokens.len() {
+            if self.kind(k) != Some(TokenKind::Punct) {
+                continue;
+            }
+            if self.text(k) == open_text {
+                depth += 1;
+            } else if self.text(k) == close_text {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(k);
+                }
+            }
+        }
+        None
+    }
+
+    /// Change in bracket depth caused by token `k`.
+    fn depth_change(&self, k: usize) -> isize {
+        match (self.kind(k), self.text(k)) {
+            (Some(TokenKind::Punct), "(" | "[" | "{") => 1,
+            (Some(TokenKind::Punct), ")" | "]" | "}") => -1,
+            _ => 0,
+        }
+    }
+
+    fn replace(&mut self, k: usize, text: impl Into<String>) {
+        let span = self.tokens[k].span;
+        self.edits.push(Edit {
+            span,
+            text: text.into(),
+        });
+    }
+
+    fn insert(&mut self, offset: usize, text: impl Into<String>) {
+        self.edits.push(Edit {
+            span: Span::new(offset, offset),
+            text: text.into(),
+        });
+    }
+
+    fn start(&self, k: usize) -> usize {
+        self.tokens[k].span.start
+    }
+
+    fn end(&self, k: usize) -> usize {
+        self.tokens[k].span.end
+    }
+
+    fn run(&mut self) {
+        for k in 0..self.tokens.len() {
+            match (self.kind(k), self.text(k)) {
+                (Some(TokenKind::Punct), ":") => self.ref_type(k),
+                (Some(TokenKind::Punct), "@") => self.annotation(k),
+                (Some(TokenKind::Number), _) => self.decimal_literal(k),
+                (Some(TokenKind::Str), text) if text.starts_with('r') => self.raw_string(k),
+                (Some(TokenKind::Ident), text) if rust_identifier(text).is_some() => self.mangle(k),
+                (Some(TokenKind::Ident), _) if self.is_member(k) => {}
+                (Some(TokenKind::Ident), "val") => self.val(k),
+                (Some(TokenKind::Ident), "b" | "hex") => self.bytes_literal(k),
+                (Some(TokenKind::Ident), "struct") => self.struct_decl(k),
+                (Some(TokenKind::Ident), "static") => self.static_var(k),
+                (Some(TokenKind::Ident), "and") if self.text(k + 1) != ":" => self.replace(k, "&&"),
+                (Some(TokenKind::Ident), "or") if self.text(k + 1) != ":" => self.replace(k, "||"),
+                (Some(TokenKind::Ident), "loop") => self.loop_keyword(k),
+                (Some(TokenKind::Ident), "implements") => self.implements(k),
+                (Some(TokenKind::Ident), "wait") => self.wait(k),
+                (Some(TokenKind::Ident), "match") => self.match_expr(k),
+                (Some(TokenKind::Ident), "try") => self.try_expr(k),
+                (Some(TokenKind::Ident), "for") => self.range_loop(k),
+                _ => {}
+            }
+        }
+    }
+
+    /// `val x = 1;` → `let x = 1;`, also in a `for (val x of xs)` header.
+    fn val(&mut self, k: usize) {
+        let declares = self.is_ident(k + 1) || matches!(self.text(k + 1), "{" | "[");
+        let in_for_header = k > 1 && self.text(k - 1) == "(" && self.text(k - 2) == "for";
+        if declares
+            && (self.at_statement_start(k)
+                || in_for_header
+                || k > 0 && self.text(k - 1) == "export")
+        {
+            self.replace(k, "let");
+        }
+    }
+
+    /// `12.34d` → `__trust_decimal("12.34")`, so the digits reach the transpiler unrounded.
+    fn decimal_literal(&mut self, k: usize) {
+        if let Some(digits) = decimal::literal_digits(self.text(k)) {
+            let marker = format!("{}(\"{}\")", decimal::DECIMAL_CALLEE, digits);
+            self.replace(k, marker);
+        }
+    }
+
+    /// `$count` → `_dollar_count`, the same everywhere in the file, members and object keys included.
+    fn mangle(&mut self, k: usize) {
+        if let Some(name) = rust_identifier(self.text(k)) {
+            self.replace(k, name);
+        }
+    }
+
+    /// `r"C:\dir"` → `__trust_raw("C:\\dir")`. Line breaks become `\n` plus a line continuation, so
+    /// lines keep their numbers.
+    fn raw_string(&mut self, k: usize) {
+        let Some(body) = raw_string_body(self.text(k)) else {
+            return;
+        };
+        let mut escaped = String::new();
+        for c in body.chars() {
+            match c {
+                '\\' => escaped.push_str("\\\\"),
+                '"' => escaped.push_str("\\\""),
+                '\r' => escaped.push_str("\\r"),
+                '\n' => escaped.push_str("\\n\\\n"),
+                c => escaped.push(c),
+            }
+        }
+        self.replace(k, format!("{}(\"{}\")", strings::RAW_CALLEE, escaped));
+    }
+
+    /// `b"..."` / `hex"..."` → `__trust_bytes("...")` / `__trust_hex("...")`.
+    fn bytes_literal(&mut self, k: usize) {
+        if self.kind(k + 1) != Some(TokenKind::Str) || !self.gap_after(k).is_empty() {
+            return;
+        }
+        let callee = if self.text(k) == "b" {
+            bytes::BYTES_CALLEE
+        } else {
+            bytes::HEX_CALLEE
+        };
+        self.replace(k, format!("{}(", callee));
+        self.insert(self.end(k + 1), ")");
+    }
+
+    /// Index of the last token of an annotation starting at `k` (`@name` or `@name(...)`).
+    fn annotation_end(&self, k: usize) -> Option<usize> {
+        if self.text(k) != "@" || !self.is_ident(k + 1) || !self.gap_after(k).is_empty() {
+            return None;
+        }
+        if self.text(k + 2) == "(" {
+            self.matching(k + 2)
+        } else {
+            Some(k + 1)
+        }
+    }
+
+    /// `@derive(Hash) struct Key { ... }`: the annotation is blanked out (keeping columns, so its span
+    /// can still be reported) and recorded against the declaration that follows.
+    fn annotation(&mut self, k: usize) {
+        if !self.at_statement_start(k) {
+            return;
+        }
+        let Some(end) = self.annotation_end(k) else {
+            return;
+        };
+        self.after_annotations.push(end + 1);
+        let mut target = end + 1;
+        while let Some(next) = self.annotation_end(target) {
+            target = next + 1;
+        }
+        if self.text(target) == "export" {
+            target += 1;
+        }
+        let Some(target) = self.tokens.get(target).map(|token| token.span.start) else {
+            return;
+        };
+        let args = if end > k + 1 {
+            &self.source[self.end(k + 2)..self.start(end)]
+        } else {
+            ""
+        };
+        let span = Span::new(self.start(k), self.end(end));
+        let blank: String = self.source[span.start..span.end]
+            .chars()
+            .map(|c| {
+                if c == '\n' {
+                    "\n".to_string()
+                } else {
+                    " ".repeat(c.len_utf8())
+                }
+            })
+            .collect();
+        self.annotations.push(Annotation {
+            name: self.text(k + 1).to_string(),
+            args: args.trim().to_string(),
+            span,
+            target,
+        });
+        self.edits.push(Edit { span, text: blank });
+    }
+
+    /// `static var count = 0;` → `var count = 0;`, remembering the declaration was `static`.
+    fn static_var(&mut self, k: usize) {
+        let declares = self.text(k + 1) == "var" && self.is_ident(k + 2);
+        if declares && (self.at_statement_start(k) || k > 0 && self.text(k - 1) == "export") {
+            self.edits.push(Edit {
+                span: Span::new(self.start(k), self.start(k + 1)),
+                text: String::new(),
+            });
+            self.statics.push(self.start(k + 1));
+        }
+    }
+
+    /// `struct Point { ... }` → `interface Point { ... }`.
+    fn struct_decl(&mut self, k: usize) {
+        if self.is_ident(k + 1)
+            && (self.at_statement_start(k) || k > 0 && self.text(k - 1) == "export")
+        {
+            self.replace(k, "interface");
+        }
+    }
+
+    /// `loop (cond) { ... }` → `while (cond) { ... }`; `loop { ... }` → `while (true) { ... }`.
+    fn loop_keyword(&mut self, k: usize) {
+        match self.text(k + 1) {
+            "(" => self.replace(k, "while"),
+            "{" => self.replace(k, "while (true)"),
+            _ => {}
+        }
+    }
+
+    /// `implements Point { function area() {} }` → `class Point { area() {} }`.
+    fn implements(&mut self, k: usize) {
+        if !self.after_boundary(k) || !self.is_ident(k + 1) {
+            return;
+        }
+        let Some(open) =
+            (k + 1..self.tokens.len()).find(|&j| matches!(self.text(j), "{" | ";" | "}"))
+        else {
+            return;
+        };
+        let Some(close) = self.matching(open) else {
+            return;
+        };
+        self.replace(k, "class");
+        // `implements Countdown: Iterable<int32> {` → `class Countdown implements Iterable<int32> {`.
+        if self.text(k + 2) == ":" && k + 2 < open {
+            self.replace(k + 2, " implements");
+        }
+        let mut depth = 0isize;
+        for j in open + 1..close {
+            if depth == 0 && self.text(j) == "function" && self.is_ident(j + 1) {
+                // Drop the keyword and the spaces after it, unless they span lines.
+                let end = if self.gap_after(j).contains('\n') {
+                    self.end(j)
+                } else {
+                    self.start(j + 1)
+                };
+                self.edits.push(Edit {
+                    span: Span::new(self.start(j), end),
+                    text: String::new(),
+                });
+            }
+            depth += self.depth_change(j);
+        }
+    }
+
+    /// `wait handle` → `await handle`, anywhere an expression can start; the transpiler lowers
+    /// the await. `wait(x)`, `wait = 1`, `{ wait: 1 }` and `wait of waits` keep naming a variable
+    /// or function.
+    fn wait(&mut self, k: usize) {
+        let gap = self.gap_after(k);
+        if gap.is_empty() || gap.contains('\n') || k + 1 >= self.tokens.len() {
+            return;
+        }
+        let operand = match self.kind(k + 1) {
+            Some(TokenKind::Punct) => self.text(k + 1) == "(",
+            Some(TokenKind::Ident) => !matches!(
+                self.text(k + 1),
+                "of" | "in" | "as" | "instanceof" | "and" | "or"
+            ),
+            Some(_) => true,
+            None => false,
+        };
+        if operand {
+            self.replace(k, "await");
+        }
+    }
+
+    /// `match (s) { p => v, default => d }` → `__trust_match((s), [p, v], [__trust_default, d])`,
+    /// lowered by `transpiler::matching` once the subject's type is known.
+    fn match_expr(&mut self, k: usize) {
+        if self.text(k + 1) != "(" {
+            return;
+        }
+        let Some(subject_end) = self.matching(k + 1) else {
+            return;
+        };
+        let open = subject_end + 1;
+        if self.text(open) != "{" {
+            return;
+        }
+        let Some(close) = self.matching(open) else {
+            return;
+        };
+
+        // Arms split at top-level commas, each as (first token, arrow, last token).
+        let mut arms = Vec::new();
+        let mut depth = 0isize;
+        let mut first = open + 1;
+        let mut arrow = None;
+        for j in open + 1..=close {
+            let arm_ends = j == close || depth == 0 && self.text(j) == ",";
+            if arm_ends {
+                if first < j {
+                    match arrow.filter(|&a| a > first && a + 2 < j) {
+                        Some(arrow) => arms.push((first, arrow, j - 1)),
+                        None => return,
+                    }
+                }
+                first = j + 1;
+                arrow = None;
+                continue;
+            }
+            if depth == 0 && arrow.is_none() && self.is_pair(j, "=", ">") {
+                arrow = Some(j);
+            }
+            depth += self.depth_change(j);
+        }
+        if arms.is_empty() {
+            return;
+        }
+
+        self.replace(k, matching::MATCH_CALLEE);
+        self.replace(k + 1, "((");
+        self.replace(open, ",");
+        for (first, arrow, last) in arms {
+            self.insert(self.start(first), "[");
+            if arrow == first + 1 && self.text(first) == "default" {
+                self.replace(first, matching::DEFAULT_PATTERN);
+            }
+            self.edits.push(Edit {
+                span: Span::new(self.start(arrow), self.end(arrow + 1)),
+                text: ",".to_string(),
+            });
+            self.insert(self.end(last), "]");
+        }
+        self.replace(close, ")");
+    }
+
+    /// `val x = try { a } catch (e) { b };` → `val x = __trust_try(() => { a }, (e) => { b });`.
+    /// Only `try` in expression position is rewritten; statements stay `try`/`catch`.
+    fn try_expr(&mut self, k: usize) {
+        if !self.at_expression_start(k) || self.text(k + 1) != "{" {
+            return;
+        }
+        let Some(body_end) = self.matching(k + 1) else {
+            return;
+        };
+        let catch = body_end + 1;
+        if self.text(catch) != "catch" {
+            return;
+        }
+        let (param, handler) = match self.text(catch + 1) {
+            "(" => match self.matching(catch + 1) {
+                Some(end) => (true, end + 1),
+                None => return,
+            },
+            _ => (false, catch + 1),
+        };
+        if self.text(handler) != "{" {
+            return;
+        }
+        let Some(handler_end) = self.matching(handler) else {
+            return;
+        };
+
+        self.replace(k, format!("{}(() =>", errors::TRY_CALLEE));
+        self.replace(catch, if param { "," } else { ", ()" });
+        self.insert(self.start(handler), "=> ");
+        self.replace(handler_end, "})");
+    }
+
+    /// `for (i in 0..n)` / `for (i of 0..=n)` → `for (i in __trust_range(0, n))`; the transpiler turns
+    /// the marker back into a Rust range.
+    fn range_loop(&mut self, k: usize) {
+        if self.text(k + 1) != "(" {
+            return;
+        }
+        let Some(close) = self.matching(k + 1) else {
+            return;
+        };
+        let Some(keyword) = (k + 2..close).find(|&j| matches!(self.text(j), "in" | "of")) else {
+            return;
+        };
+        let iterable = keyword + 1..close;
+        if iterable
+            .clone()
+            .any(|j| matches!(self.kind(j), Some(TokenKind::Str | TokenKind::Template)))
+        {
+            return;
+        }
+        let Some(dots) = iterable.clone().find(|&j| self.is_pair(j, ".", ".")) else {
+            return;
+        };
+        if self.is_pair(dots + 1, ".", ".") {
+            return;
+        }
+        let inclusive = self.is_pair(dots + 1, ".", "=");
+        let end_first = if inclusive { dots + 3 } else { dots + 2 };
+        if dots == keyword + 1 || end_first >= close {
+            return;
+        }
+
+        let callee = if inclusive {
+            expressions::RANGE_INCLUSIVE_CALLEE
+        } else {
+            expressions::RANGE_CALLEE
+        };
+        self.insert(self.start(keyword + 1), format!("{}(", callee));
+        self.edits.push(Edit {
+            span: Span::new(self.start(dots), self.end(end_first - 1)),
+            text: ", ".to_string(),
+        });
+        self.insert(self.end(close - 1), ")");
+    }
+
+    /// `name: ref T` → `name: Ref<T>` and `name: mut ref T` → `name: MutRef<T>`, so the TypeScript
+    /// parser accepts borrow annotations; the type runs to the next top-level `,`, `)`, `=`, `;` or line end.
+    fn ref_type(&mut self, colon: usize) {
+        let word = |k: usize| self.is_ident(k) && !self.gap_after(k).is_empty();
+        let (wrapper, first) = if word(colon + 1)
+            && self.text(colon + 1) == "mut"
+            && word(colon + 2)
+            && self.text(colon + 2) == "ref"
+        {
+            ("MutRef", colon + 3)
+        } else if word(colon + 1) && self.text(colon + 1) == "ref" {
+            ("Ref", colon + 2)
+        } else {
+            return;
+        };
+        if !(self.is_ident(first) || self.text(first) == "(") {
+            return;
+        }
+
+        let mut depth = 0isize;
+        let mut last = first;
+        for j in first..self.tokens.len() {
+            let text = self.text(j);
+            if depth == 0 && matches!(text, "," | ")" | "=" | ";" | "}") {
+                break;
+            }
+            match text {
+                "<" | "[" | "(" | "{" => depth += 1,
+                ">" | "]" | ")" | "}" if depth > 0 => depth -= 1,
+                _ => {}
+            }
+            last = j;
+            if depth == 0 && self.gap_after(j).contains('\n') {
+                break;
+            }
+        }
+        self.edits.push(Edit {
+            span: Span::new(self.start(colon + 1), self.start(first)),
+            text: format!("{}<", wrapper),
+        });
+        self.insert(self.end(last), ">");
+    }
+
+    /// Applies the edits in source order, recording where each one landed.
+    fn apply(mut self) -> Preprocessed {
+        // Stable: inserts at one offset keep the order they were recorded in (outer first).
+        self.edits
+            .sort_by_key(|edit| (edit.span.start, edit.span.end));
+        let mut text = String::with_capacity(self.source.len() + self.edits.len() * 8);
+        let mut edits = Vec::with_capacity(self.edits.len());
+        let mut cursor = 0;
+        for edit in &self.edits {
+            if edit.span.start < cursor {
+                continue;
+            }
+            text.push_str(&self.source[cursor..edit.span.start]);
+            let output_start = text.len();
+            text.push_str(&edit.text);
+            edits.push((edit.span, Span::new(output_start, text.len())));
+            cursor = edit.span.end;
+        }
+        text.push_str(&self.source[cursor..]);
+        Preprocessed {
+            text,
+            source: self.source.to_string(),
+            edits,
+            statics: self.statics,
+            annotations: self.annotations,
+        }
+    }
+}
@@ -0,0 +1,27 @@
+pub(super) const RUNTIME: &str = include_str!("../../runtime/color.rs");
+
+/// `use` statements injected when `import ... from "trusty:color"` is detected.
+pub fn use_statements() -> Vec<&'static str> {
+    super::runtime_statements(RUNTIME)
+}
+
+/// Names `import { ... } from "trusty:color"` may bring in.
+pub fn exports() -> Vec<&'static str> {
+    super::runtime_exports(RUNTIME, &[])
+}
+
+/// No external crates needed — terminal detection is in `std`.
+pub fn required_crates() -> Vec<(&'static str, &'static str)> {
+    vec![]
+}
+
+/// Rust return type of a `trusty:color` function.
+pub fn function_return_type(name: &str) -> Option<&'static str> {
+    match name {
+        "styled" | "black" | "red" | "green" | "yellow" | "blue" | "magenta" | "cyan" | "white"
+        | "gray" | "bold" | "dim" | "italic" | "underline" | "stripColors" => Some("String"),
+        "style" => Some("Style"),
+        "colorEnabled" => Some("bool"),
+        _ => None,
+    }
+}
@@ -1,27 +1,13 @@
+pub(super) const RUNTIME: &str = include_str!("../../runtime/json.rs");
+
 /// `use` statements injected when `import ... from "trusty:json"` is detected.
 pub fn use_statements() -> Vec<&'static str> {
-    vec![
-        "use serde_json::Value;",
-        r#"#[allow(non_snake_case)]
-pub fn parseToJSON(json: String) -> Value {
-    serde_json::from_str(&json).unwrap_or(Value::Null)
-}
-
-#[allow(non_snake_case)]
-pub fn stringify<T: serde::Serialize>(value: T) -> String {
-    serde_json::to_string(&value).unwrap_or("null".to_string())
-}
-
-#[allow(non_snake_case)]
-pub fn toJSON<T: serde::Serialize>(value: T) -> String {
-    stringify(value)
+    super::runtime_statements(RUNTIME)
 }
 
-#[allow(non_snake_case)]
-pub fn fromJSON<T: serde::de::DeserializeOwned>(json: String) -> T {
-    serde_json::from_str(&json).unwrap()
-}"#,
-    ]
+/// Names `import { ... } from "trusty:json"` may bring in.
+pub fn exports() -> Vec<&'static str> {
+    super::runtime_exports(RUNTIME, &[])
 }
 
 /// External crates needed.
@@ -1,19 +1,215 @@
-pub mod time;
+pub mod args;
+pub mod color;
+pub mod http;
+pub mod json;
 pub mod math;
 pub mod rand;
-pub mod json;
-pub mod http;
+pub mod tcp;
+pub mod time;
+pub mod udp;
+
+use crate::diagnostics::Diagnostic;
+
+/// The pieces of a runtime module's source as injected: each leading `use` line on its own (so the
+/// imports the transpiler adds itself are not repeated), then the rest of the code. The file's
+/// `//!` header is dropped.
+fn runtime_statements(source: &'static str) -> Vec<&'static str> {
+    let mut statements = Vec::new();
+    let mut rest = source;
+    loop {
+        rest = rest.trim_start();
+        let (line, after) = rest.split_once('\n').unwrap_or((rest, ""));
+        if line.starts_with("//!") {
+            rest = after;
+        } else if line.starts_with("use ") {
+            statements.push(line);
+            rest = after;
+        } else {
+            break;
+        }
+    }
+    if !rest.trim().is_empty() {
+        statements.push(rest.trim_end());
+    }
+    statements
+}
+
+/// Name of the runtime crate in the generated code (`use trusty_runtime::time::*;`).
+pub const RUNTIME_CRATE: &str = "trusty_runtime";
+
+/// Release of the runtime crate the generated code is written against.
+pub const RUNTIME_VERSION: &str = "0.1";
+
+/// Names a runtime module's source exports to `import { ... }`: its top-level `pub` items (not the
+/// `__Trust*` helper traits), then `reexports`, the `std` items it imports for programs to use.
+fn runtime_exports(source: &'static str, reexports: &[&'static str]) -> Vec<&'static str> {
+    let mut exports: Vec<&'static str> = source
+        .lines()
+        .filter_map(|line| {
+            let rest = line.strip_prefix("pub ")?;
+            let rest = rest.strip_prefix("const fn ").or_else(|| {
+                [
+                    "fn ", "struct ", "enum ", "const ", "static ", "trait ", "type ",
+                ]
+                .iter()
+                .find_map(|keyword| rest.strip_prefix(keyword))
+            })?;
+            let name = &rest[..rest
+                .find(|c: char| !c.is_alphanumeric() && c != '_')
+                .unwrap_or(rest.len())];
+            (!name.starts_with("__")).then_some(name)
+        })
+        .collect();
+    exports.extend_from_slice(reexports);
+    exports
+}
+
+/// Rust type returned by `method` of the runtime type `type_name`: a static one (`Date.fromYmd(...)`)
+/// or, with `instance`, one taking `self` (`date.addDays(1)`). Read from the `pub fn` signatures in
+/// the modules' `impl` blocks; `None` for generic methods and methods returning nothing.
+pub fn method_return_type(type_name: &str, method: &str, instance: bool) -> Option<String> {
+    [
+        time::RUNTIME,
+        http::RUNTIME,
+        json::RUNTIME,
+        rand::RUNTIME,
+        math::RUNTIME,
+        args::RUNTIME,
+        color::RUNTIME,
+        tcp::RUNTIME,
+        udp::RUNTIME,
+    ]
+    .into_iter()
+    .find_map(|source| runtime_method_type(source, type_name, method, instance))
+}
+
+/// Rust type of the `pub` field `field` of the runtime struct `type_name` (`options.headers` is a
+/// `HashMap<String, String>`), read from the modules' struct definitions.
+pub fn field_type(type_name: &str, field: &str) -> Option<String> {
+    let header = format!("pub struct {} {{", type_name);
+    let prefix = format!("    pub {}: ", field);
+    [
+        time::RUNTIME,
+        http::RUNTIME,
+        json::RUNTIME,
+        rand::RUNTIME,
+        math::RUNTIME,
+        args::RUNTIME,
+        color::RUNTIME,
+        tcp::RUNTIME,
+        udp::RUNTIME,
+    ]
+    .into_iter()
+    .find_map(|source| {
+        let mut lines = source
+            .lines()
+            .skip_while(|line| line.trim_end() != header)
+            .skip(1);
+        lines
+            .by_ref()
+            .take_while(|line| *line != "}")
+            .find_map(|line| {
+                line.strip_prefix(&prefix)?
+                    .strip_suffix(',')
+                    .map(str::to_string)
+            })
+    })
+}
+
+fn runtime_method_type(
+    source: &str,
+    type_name: &str,
+    method: &str,
+    instance: bool,
+) -> Option<String> {
+    let prefix = format!("    pub fn {}(", method);
+    let mut in_impl = false;
+    let mut offset = 0;
+    for line in source.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        if let Some(header) = line.strip_prefix("impl ") {
+            // Trait impls (`impl X for Y`) declare no `pub fn`s of their own.
+            in_impl = header.trim_end().strip_suffix(" {") == Some(type_name);
+            continue;
+        }
+        if !in_impl || !line.starts_with(&prefix) {
+            continue;
+        }
+        // The parameters may span lines; the return type ends at the body's `{` or a `where`.
+        let signature = &source[start + prefix.len() - 1..];
+        let mut depth = 0;
+        let close = signature.char_indices().find_map(|(i, c)| {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => return None,
+            }
+            (depth == 0).then_some(i)
+        })?;
+        let takes_self = signature[1..close]
+            .split(',')
+            .next()
+            .is_some_and(|first| first.trim().ends_with("self"));
+        if takes_self != instance {
+            return None;
+        }
+        let ret = signature[close + 1..].trim_start().strip_prefix("->")?;
+        let ret = ret[..ret.find(['{', '\n']).unwrap_or(ret.len())].trim();
+        return Some(if ret == "Self" {
+            type_name.to_string()
+        } else {
+            ret.to_string()
+        });
+    }
+    None
+}
+
+/// Every stdlib module, as imported from `"trusty:<name>"`.
+pub const MODULES: [&str; 9] = [
+    "math", "rand", "time", "json", "http", "args", "color", "tcp", "udp",
+];
+
+/// Names `import { ... } from "trusty:<module_name>"` may bring in, or `None` for a module that
+/// is not part of the stdlib.
+pub fn exports(module_name: &str) -> Option<Vec<&'static str>> {
+    match module_name {
+        "math" => Some(math::exports()),
+        "rand" => Some(rand::exports()),
+        "time" => Some(time::exports()),
+        "json" => Some(json::exports()),
+        "http" => Some(http::exports()),
+        "args" => Some(args::exports()),
+        "color" => Some(color::exports()),
+        "tcp" => Some(tcp::exports()),
+        "udp" => Some(udp::exports()),
+        _ => None,
+    }
+}
 
 pub struct StdlibModule {
     pub use_statements: Vec<String>,
     /// (crate_name, version) pairs for Cargo.toml
     pub required_crates: Vec<(String, String)>,
+    /// Features of the runtime crate the module needs when linked rather than pasted.
+    pub runtime_features: Vec<String>,
 }
 
-/// Resolves a `trusty:*` module name to its stdlib definition.
-/// Returns `None` if the module is not a known stdlib module.
-pub fn resolve(module_name: &str) -> Option<StdlibModule> {
-    match module_name {
+/// Resolves a `trusty:*` module name to its stdlib definition, written against the crate versions
+/// the project pins in `dependencies` (`(name, requirement)` pairs from `trusty.json`).
+/// Returns `None` if the module is not a known stdlib module, and an error when a pinned version
+/// is one the module cannot target.
+pub fn resolve(
+    module_name: &str,
+    dependencies: &[(String, String)],
+) -> Result<Option<StdlibModule>, Box<Diagnostic>> {
+    let pinned = |crate_name: &str| {
+        dependencies
+            .iter()
+            .find(|(name, _)| name == crate_name)
+            .map(|(_, version)| version.as_str())
+    };
+    Ok(match module_name {
         "math" => Some(StdlibModule {
             use_statements: math::use_statements()
                 .iter()
@@ -23,17 +219,19 @@ pub fn resolve(module_name: &str) -> Option<StdlibModule> {
                 .iter()
                 .map(|(n, v)| (n.to_string(), v.to_string()))
                 .collect(),
+            runtime_features: Vec::new(),
         }),
-        "rand" => Some(StdlibModule {
-            use_statements: rand::use_statements()
-                .iter()
-                .map(|s| s.to_string())
-                .collect(),
-            required_crates: rand::required_crates()
-                .iter()
-                .map(|(n, v)| (n.to_string(), v.to_string()))
-                .collect(),
-        }),
+        "rand" => {
+            let api = rand::RandApi::for_requirement(pinned("rand"))?;
+            Some(StdlibModule {
+                use_statements: rand::use_statements(api),
+                required_crates: rand::required_crates(api)
+                    .iter()
+                    .map(|(n, v)| (n.to_string(), v.to_string()))
+                    .collect(),
+                runtime_features: Vec::new(),
+            })
+        }
         "time" => Some(StdlibModule {
             use_statements: time::use_statements()
                 .iter()
@@ -43,6 +241,7 @@ pub fn resolve(module_name: &str) -> Option<StdlibModule> {
                 .iter()
                 .map(|(n, v)| (n.to_string(), v.to_string()))
                 .collect(),
+            runtime_features: Vec::new(),
         }),
         "json" => Some(StdlibModule {
             use_statements: json::use_statements()
@@ -53,6 +252,7 @@ pub fn resolve(module_name: &str) -> Option<StdlibModule> {
                 .iter()
                 .map(|(n, v)| (n.to_string(), v.to_string()))
                 .collect(),
+            runtime_features: Vec::new(),
         }),
         "http" => Some(StdlibModule {
             use_statements: http::use_statements()
@@ -63,7 +263,86 @@ pub fn resolve(module_name: &str) -> Option<StdlibModule> {
                 .iter()
                 .map(|(n, v)| (n.to_string(), v.to_string()))
                 .collect(),
+            runtime_features: Vec::new(),
+        }),
+        "args" => Some(StdlibModule {
+            use_statements: args::use_statements()
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            required_crates: args::required_crates()
+                .iter()
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect(),
+            runtime_features: Vec::new(),
+        }),
+        "color" => Some(StdlibModule {
+            use_statements: color::use_statements()
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            required_crates: color::required_crates()
+                .iter()
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect(),
+            runtime_features: Vec::new(),
+        }),
+        "tcp" => Some(StdlibModule {
+            use_statements: tcp::use_statements()
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            required_crates: tcp::required_crates()
+                .iter()
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect(),
+            runtime_features: Vec::new(),
+        }),
+        "udp" => Some(StdlibModule {
+            use_statements: udp::use_statements()
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            required_crates: udp::required_crates()
+                .iter()
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect(),
+            runtime_features: Vec::new(),
         }),
         _ => None,
+    })
+}
+
+/// Like [`resolve`], but links the module from the runtime crate instead of pasting its code:
+/// the runtime's own `use` lines stay (programs name `Duration` or `Value` through them), followed
+/// by a glob import of the module. A `rand` pinned to 0.8 is still pasted, as the crate is built
+/// against 0.9.
+pub fn resolve_linked(
+    module_name: &str,
+    dependencies: &[(String, String)],
+) -> Result<Option<StdlibModule>, Box<Diagnostic>> {
+    let Some(mut module) = resolve(module_name, dependencies)? else {
+        return Ok(None);
+    };
+    if module_name == "rand"
+        && module
+            .required_crates
+            .iter()
+            .any(|(_, version)| version != rand::RandApi::V09.version())
+    {
+        return Ok(Some(module));
+    }
+    module
+        .use_statements
+        .retain(|statement| statement.starts_with("use "));
+    module
+        .use_statements
+        .push(format!("use {}::{}::*;", RUNTIME_CRATE, module_name));
+    module
+        .required_crates
+        .push((RUNTIME_CRATE.to_string(), RUNTIME_VERSION.to_string()));
+    if matches!(module_name, "json" | "http" | "rand") {
+        module.runtime_features.push(module_name.to_string());
     }
+    Ok(Some(module))
 }
@@ -1,69 +1,108 @@
-/// `use` statements injected when `import ... from "trusty:rand"` is detected.
-pub fn use_statements() -> Vec<&'static str> {
-    vec![r#"use rand::Rng;
-use rand::distributions::{Bernoulli, Distribution, WeightedIndex};
-use rand::seq::SliceRandom;
+use crate::diagnostics::Diagnostic;
+use crate::messages::msg;
 
-#[allow(non_snake_case)]
-pub fn random() -> f64 {
-    let mut rng = rand::thread_rng();
-    rng.gen::<f64>()
+/// The `rand` release whose API the injected code is written against.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RandApi {
+    V08,
+    V09,
 }
 
-#[allow(non_snake_case)]
-pub fn randomInt(min: i32, max: i32) -> i32 {
-    let mut rng = rand::thread_rng();
-    if min <= max {
-        rng.gen_range(min..=max)
-    } else {
-        rng.gen_range(max..=min)
+impl RandApi {
+    /// The API matching the project's `rand` requirement (`"0.9"`, `"^0.8.5"`, `"=0.9.1"`, ...);
+    /// without a pin, `0.9`.
+    pub fn for_requirement(requirement: Option<&str>) -> Result<RandApi, Box<Diagnostic>> {
+        let Some(requirement) = requirement else {
+            return Ok(RandApi::V09);
+        };
+        let version = requirement
+            .trim()
+            .trim_start_matches(['^', '~', '=', '>', '<', ' ']);
+        let mut parts = version.split('.');
+        match (parts.next(), parts.next()) {
+            (Some("*") | Some(""), _) => Ok(RandApi::V09),
+            (Some("0"), Some("8")) => Ok(RandApi::V08),
+            (Some("0"), Some("9")) => Ok(RandApi::V09),
+            _ => Err(Box::new(Diagnostic::error(
+                "stdlib-version",
+                msg!("stdlib-version", requirement),
+            ))),
+        }
     }
-}
 
-#[allow(non_snake_case)]
-pub fn randomFloat(min: f64, max: f64) -> f64 {
-    let mut rng = rand::thread_rng();
-    let lo = min.min(max);
-    let hi = min.max(max);
-    if (hi - lo).abs() < f64::EPSILON {
-        lo
-    } else {
-        rng.gen_range(lo..hi)
+    /// Version written to Cargo.toml when the project does not pin `rand` itself.
+    pub fn version(self) -> &'static str {
+        match self {
+            RandApi::V08 => "0.8",
+            RandApi::V09 => "0.9",
+        }
     }
 }
 
-#[allow(non_snake_case)]
-pub fn bernoulli(p: f64) -> bool {
-    let mut rng = rand::thread_rng();
-    let prob = p.clamp(0.0, 1.0);
-    Bernoulli::new(prob).map(|d| d.sample(&mut rng)).unwrap_or(false)
+/// `use` statements injected when `import ... from "trusty:rand"` is detected, written against
+/// `api`: rand 0.9 renamed `thread_rng`/`gen`/`gen_range` and moved the distributions.
+pub fn use_statements(api: RandApi) -> Vec<String> {
+    let statements = super::runtime_statements(RUNTIME)
+        .into_iter()
+        .map(str::to_string);
+    if api == RandApi::V09 {
+        return statements.collect();
+    }
+    let renames = [
+        (
+            "rand::distr::{weighted::WeightedIndex, Bernoulli, Distribution}",
+            "rand::distributions::{Bernoulli, Distribution, WeightedIndex}",
+        ),
+        (
+            "use rand::seq::{IndexedRandom, SliceRandom};",
+            "use rand::seq::SliceRandom;",
+        ),
+        ("rand::rng()", "rand::thread_rng()"),
+        (".random::<", ".gen::<"),
+        (".random_range(", ".gen_range("),
+        ("StdRng::from_os_rng()", "StdRng::from_entropy()"),
+    ];
+    statements
+        .map(|code| {
+            renames
+                .iter()
+                .fold(code, |code, (from, to)| code.replace(from, to))
+        })
+        .collect()
 }
 
-#[allow(non_snake_case)]
-pub fn weightedIndex(weights: Vec<f64>) -> i32 {
-    let mut rng = rand::thread_rng();
-    match WeightedIndex::new(weights) {
-        Ok(dist) => dist.sample(&mut rng) as i32,
-        Err(_) => -1,
-    }
+/// Names `import { ... } from "trusty:rand"` may bring in, the same for both `rand` releases.
+pub fn exports() -> Vec<&'static str> {
+    super::runtime_exports(RUNTIME, &[])
 }
 
-#[allow(non_snake_case)]
-pub fn chooseOne<T: Clone>(items: Vec<T>) -> Option<T> {
-    let mut rng = rand::thread_rng();
-    items.choose(&mut rng).cloned()
+/// The runtime for `rand` 0.9.
+pub(super) const RUNTIME: &str = include_str!("../../runtime/rand.rs");
+
+/// External crate needed.
+pub fn required_crates(api: RandApi) -> Vec<(&'static str, &'static str)> {
+    vec![("rand", api.version())]
 }
 
-#[allow(non_snake_case)]
-pub fn shuffle<T: Clone>(items: Vec<T>) -> Vec<T> {
-    let mut rng = rand::thread_rng();
-    let mut out = items.clone();
-    out.shuffle(&mut rng);
-    out
-}"#]
+/// Rust return type of a non-generic `trusty:rand` function.
+pub fn function_return_type(name: &str) -> Option<&'static str> {
+    match name {
+        "random" | "randomFloat" | "normal" | "exponential" => Some("f64"),
+        "randomInt" | "weightedIndex" => Some("i32"),
+        "bernoulli" => Some("bool"),
+        "randomHex" => Some("String"),
+        _ => None,
+    }
 }
 
-/// External crate needed.
-pub fn required_crates() -> Vec<(&'static str, &'static str)> {
-    vec![("rand", "0.8")]
+/// Rust return type of `Rng.withSeed(...)` and the non-generic `Rng` methods.
+pub fn method_return_type(receiver: &str, method: &str) -> Option<&'static str> {
+    match (receiver, method) {
+        ("Rng", "new" | "withSeed") => Some("Rng"),
+        ("Rng", "next" | "float" | "normal" | "exponential") => Some("f64"),
+        ("Rng", "int") => Some("i32"),
+        ("Rng", "bernoulli") => Some("bool"),
+        ("Rng", "randomHex") => Some("String"),
+        _ => None,
+    }
 }
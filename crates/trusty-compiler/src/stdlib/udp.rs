@@ -0,0 +1,16 @@
+pub(super) const RUNTIME: &str = include_str!("../../runtime/udp.rs");
+
+/// `use` statements injected when `import ... from "trusty:udp"` is detected.
+pub fn use_statements() -> Vec<&'static str> {
+    super::runtime_statements(RUNTIME)
+}
+
+/// Names `import { ... } from "trusty:udp"` may bring in.
+pub fn exports() -> Vec<&'static str> {
+    super::runtime_exports(RUNTIME, &[])
+}
+
+/// No external crates needed — sockets are in `std::net`.
+pub fn required_crates() -> Vec<(&'static str, &'static str)> {
+    vec![]
+}
@@ -0,0 +1,356 @@
+use crate::compiler::CompileOptions;
+use crate::diagnostics::Diagnostic;
+use crate::messages::{msg, Message};
+use crate::parser::source_span;
+use crate::preprocessor::{Annotation, Preprocessed};
+use anyhow::Result;
+use swc_common::Spanned;
+use swc_ecma_ast::*;
+
+/// Traits `@derive(...)` and `compilerOptions.derive` accept.
+pub const DERIVABLE: [&str; 9] = [
+    "Debug",
+    "Clone",
+    "Copy",
+    "PartialEq",
+    "Eq",
+    "PartialOrd",
+    "Ord",
+    "Hash",
+    "Default",
+];
+
+/// Keys of `@cfg(key = "value")`, passed to Rust's `#[cfg]` as they are.
+const CFG_KEYS: [&str; 7] = [
+    "target_os",
+    "target_family",
+    "target_arch",
+    "target_env",
+    "target_pointer_width",
+    "target_endian",
+    "feature",
+];
+
+fn error(
+    code: &'static str,
+    message: Message,
+    annotation: &Annotation,
+    source: &Preprocessed,
+) -> anyhow::Error {
+    Diagnostic::error(code, message)
+        .with_span(source.output_span(annotation.span))
+        .into()
+}
+
+/// Every annotation must be a known one placed on a declaration that accepts it: `@derive` on a
+/// top-level `struct` or `enum`, `@rust_attr` also on a function or an `implements` method, and
+/// `@cfg` on any of those or a statement.
+pub fn check_placement(module: &Module, source: &Preprocessed) -> Result<()> {
+    let start = |span: swc_common::Span| source.original_offset(source_span(span).start, true);
+    let mut types = Vec::new();
+    let mut functions = Vec::new();
+    let mut statements = Vec::new();
+    for item in &module.body {
+        match item {
+            ModuleItem::Stmt(Stmt::Decl(Decl::TsInterface(_) | Decl::TsEnum(_))) => {
+                types.push(start(item.span()))
+            }
+            ModuleItem::Stmt(Stmt::Decl(Decl::Fn(fn_decl))) => {
+                functions.push(start(item.span()));
+                if let Some(body) = &fn_decl.function.body {
+                    statement_starts(&body.stmts, &start, &mut statements);
+                }
+            }
+            ModuleItem::Stmt(Stmt::Decl(Decl::Class(class_decl))) => {
+                for member in &class_decl.class.body {
+                    if let ClassMember::Method(method) = member {
+                        functions.push(start(method.span));
+                        if let Some(body) = &method.function.body {
+                            statement_starts(&body.stmts, &start, &mut statements);
+                        }
+                    }
+                }
+            }
+            ModuleItem::Stmt(stmt) => {
+                statement_starts(std::slice::from_ref(stmt), &start, &mut statements)
+            }
+            _ => {}
+        }
+    }
+
+    for annotation in source.annotations() {
+        let placed = match annotation.name.as_str() {
+            "derive" => types.contains(&annotation.target),
+            "rust_attr" => {
+                rust_attribute(annotation, source)?;
+                types.contains(&annotation.target) || functions.contains(&annotation.target)
+            }
+            "cfg" => {
+                cfg_predicate(annotation, source)?;
+                [&types, &functions, &statements]
+                    .iter()
+                    .any(|starts| starts.contains(&annotation.target))
+            }
+            _ => {
+                return Err(error(
+                    "unknown-annotation",
+                    msg!("unknown-annotation", annotation.name),
+                    annotation,
+                    source,
+                ))
+            }
+        };
+        if !placed {
+            let message = match annotation.name.as_str() {
+                "derive" => msg!("misplaced-annotation.derive"),
+                "rust_attr" => msg!("misplaced-annotation.rust-attr"),
+                _ => msg!("misplaced-annotation", annotation.name),
+            };
+            return Err(error("misplaced-annotation", message, annotation, source));
+        }
+    }
+    Ok(())
+}
+
+/// Start of every statement in `stmts`, including those nested in blocks, branches and loops.
+fn statement_starts(
+    stmts: &[Stmt],
+    start: &impl Fn(swc_common::Span) -> usize,
+    out: &mut Vec<usize>,
+) {
+    for stmt in stmts {
+        out.push(start(stmt.span()));
+        let mut nested = |stmt: &Stmt| statement_starts(std::slice::from_ref(stmt), start, out);
+        match stmt {
+            Stmt::Block(block) => statement_starts(&block.stmts, start, out),
+            Stmt::If(if_stmt) => {
+                nested(&if_stmt.cons);
+                if let Some(alt) = &if_stmt.alt {
+                    nested(alt);
+                }
+            }
+            Stmt::While(while_stmt) => nested(&while_stmt.body),
+            Stmt::DoWhile(do_while) => nested(&do_while.body),
+            Stmt::For(for_stmt) => nested(&for_stmt.body),
+            Stmt::ForIn(for_in) => nested(&for_in.body),
+            Stmt::ForOf(for_of) => nested(&for_of.body),
+            Stmt::Labeled(labeled) => nested(&labeled.body),
+            Stmt::Try(try_stmt) => {
+                statement_starts(&try_stmt.block.stmts, start, out);
+                if let Some(handler) = &try_stmt.handler {
+                    statement_starts(&handler.body.stmts, start, out);
+                }
+                if let Some(finalizer) = &try_stmt.finalizer {
+                    statement_starts(&finalizer.stmts, start, out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The attribute of `@rust_attr("#[inline(always)]")`, checked to be one outer attribute.
+fn rust_attribute(annotation: &Annotation, source: &Preprocessed) -> Result<String> {
+    let args = annotation.args.as_str();
+    let text = serde_json::from_str::<String>(args)
+        .ok()
+        .or_else(|| Some(args.strip_prefix('\'')?.strip_suffix('\'')?.to_string()));
+    match text.as_deref().map(str::trim) {
+        Some(attribute)
+            if attribute.starts_with("#[")
+                && attribute.ends_with(']')
+                && !attribute.contains('\n') =>
+        {
+            Ok(attribute.to_string())
+        }
+        _ => Err(error(
+            "invalid-rust-attr",
+            msg!("invalid-rust-attr", args),
+            annotation,
+            source,
+        )),
+    }
+}
+
+/// `#[...]` lines from the `@rust_attr(...)` annotations on the declaration starting at `span`, each
+/// indented by `indent` and ending with a newline; emitted verbatim above the generated item.
+pub fn rust_attributes(
+    span: swc_common::Span,
+    indent: &str,
+    source: &Preprocessed,
+) -> Result<String> {
+    let mut out = String::new();
+    for annotation in source
+        .annotations_at(source_span(span).start)
+        .filter(|a| a.name == "rust_attr")
+    {
+        out.push_str(indent);
+        out.push_str(&rust_attribute(annotation, source)?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Rust form of a `@cfg(...)` predicate: `debug` is `debug_assertions`, `release` its negation,
+/// `test`, `unix` and `windows` stay, as do `key = "value"` pairs and `not`, `all` and `any`.
+fn cfg_predicate(annotation: &Annotation, source: &Preprocessed) -> Result<String> {
+    let mut rest = annotation.args.as_str();
+    match parse_cfg(&mut rest) {
+        Some(predicate) if rest.trim().is_empty() => Ok(predicate),
+        _ => Err(error(
+            "invalid-cfg",
+            msg!("invalid-cfg", annotation.args),
+            annotation,
+            source,
+        )),
+    }
+}
+
+/// Parses one predicate off the front of `input`.
+fn parse_cfg(input: &mut &str) -> Option<String> {
+    let text = input.trim_start();
+    let len = text
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(text.len());
+    let (name, after) = text.split_at(len);
+    let after = after.trim_start();
+    if let Some(value) = after.strip_prefix('=') {
+        let value = value.trim_start().strip_prefix('"')?;
+        let close = value.find('"')?;
+        if !CFG_KEYS.contains(&name) || close == 0 || value[..close].contains('\\') {
+            return None;
+        }
+        *input = &value[close + 1..];
+        return Some(format!("{} = \"{}\"", name, &value[..close]));
+    }
+    if let Some(mut list) = after.strip_prefix('(') {
+        let mut predicates = Vec::new();
+        loop {
+            list = list.trim_start();
+            if let Some(rest) = list.strip_prefix(')') {
+                list = rest;
+                break;
+            }
+            predicates.push(parse_cfg(&mut list)?);
+            list = list.trim_start();
+            list = list.strip_prefix(',').unwrap_or(list);
+        }
+        let arity_ok = if name == "not" {
+            predicates.len() == 1
+        } else {
+            matches!(name, "all" | "any")
+        };
+        *input = list;
+        return arity_ok.then(|| format!("{}({})", name, predicates.join(", ")));
+    }
+    *input = after;
+    match name {
+        "debug" => Some("debug_assertions".to_string()),
+        "release" => Some("not(debug_assertions)".to_string()),
+        "test" | "unix" | "windows" => Some(name.to_string()),
+        _ => None,
+    }
+}
+
+/// `#[cfg(...)]` for the `@cfg(...)` annotations on the declaration or statement starting at `span`
+/// (several must all hold), or `None` without any.
+pub fn cfg_attribute(span: swc_common::Span, source: &Preprocessed) -> Result<Option<String>> {
+    let mut predicates = Vec::new();
+    for annotation in source
+        .annotations_at(source_span(span).start)
+        .filter(|a| a.name == "cfg")
+    {
+        predicates.push(cfg_predicate(annotation, source)?);
+    }
+    Ok(match predicates.len() {
+        0 => None,
+        1 => Some(format!("#[cfg({})]", predicates[0])),
+        _ => Some(format!("#[cfg(all({}))]", predicates.join(", "))),
+    })
+}
+
+/// `code` with `cfg`, if any, on the line above it, indented by `indent`.
+pub fn with_cfg(code: String, cfg: Option<&str>, indent: &str) -> String {
+    match cfg {
+        Some(cfg) => format!("{}{}\n{}", indent, cfg, code),
+        None => code,
+    }
+}
+
+/// [`with_cfg`] for each top-level item of a generated struct or enum, which are separated by
+/// blank lines (a struct comes with its `Display` impl, an enum with its utilities).
+pub fn with_cfg_per_item(code: String, cfg: Option<&str>) -> String {
+    match cfg {
+        Some(_) => code
+            .split("\n\n")
+            .map(|item| with_cfg(item.to_string(), cfg, ""))
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        None => code,
+    }
+}
+
+/// Traits derived for the declaration starting at `span`: `base`, then the project's
+/// `compilerOptions.derive`, then its `@derive(...)`, plus the traits those require (`Eq` needs
+/// `PartialEq`, `Copy` needs `Clone`, ...).
+pub fn derives(
+    base: &[&str],
+    span: swc_common::Span,
+    options: &CompileOptions,
+    source: &Preprocessed,
+) -> Result<Vec<String>> {
+    if let Some(name) = options
+        .derives
+        .iter()
+        .find(|name| !DERIVABLE.contains(&name.as_str()))
+    {
+        return Err(Diagnostic::error(
+            "unknown-derive",
+            msg!("unknown-derive.option", name, DERIVABLE.join(", ")),
+        )
+        .into());
+    }
+    let mut requested: Vec<String> = options.derives.clone();
+    for annotation in source
+        .annotations_at(source_span(span).start)
+        .filter(|a| a.name == "derive")
+    {
+        for name in annotation
+            .args
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            if !DERIVABLE.contains(&name) {
+                return Err(error(
+                    "unknown-derive",
+                    msg!("unknown-derive", name, DERIVABLE.join(", ")),
+                    annotation,
+                    source,
+                ));
+            }
+            requested.push(name.to_string());
+        }
+    }
+
+    let mut derives: Vec<String> = base.iter().map(|name| name.to_string()).collect();
+    let mut add = |name: &str| {
+        if !derives.iter().any(|derived| derived == name) {
+            derives.push(name.to_string());
+        }
+    };
+    for name in &requested {
+        match name.as_str() {
+            "Copy" => add("Clone"),
+            "Eq" | "PartialOrd" => add("PartialEq"),
+            "Ord" => {
+                add("PartialEq");
+                add("Eq");
+                add("PartialOrd");
+            }
+            _ => {}
+        }
+        add(name);
+    }
+    Ok(derives)
+}
@@ -0,0 +1,223 @@
+//! Callback methods on arrays whose element type is known (`val users: User[] = [...]`):
+//! `users.map(u => u.name)`, `filter`, `find`, `some`, `every` and `forEach` hand each callback a
+//! copy of the element, typed in its scope, so `u.name.toUpperCase()` resolves like it would on a
+//! `User` variable. `sort()`, `sort((a, b) => a.age - b.age)` and `sortBy(u => u.name)` sort the
+//! array in place, and the methods strings have too (`includes`, `slice`, ...) get their array
+//! lowering whenever the receiver is known to be an array.
+
+use super::expressions::infer_rust_type;
+use super::iterators;
+use super::ownership::is_owned_type;
+use super::scope::Scope;
+use crate::diagnostics::Diagnostic;
+use crate::messages::msg;
+use crate::parser::source_span;
+use anyhow::Result;
+use swc_ecma_ast::*;
+
+/// Methods whose callback takes the element as its first parameter.
+const CALLBACK_METHODS: &[&str] = &[
+    "map", "filter", "find", "some", "every", "forEach", "sortBy",
+];
+
+const EQUAL: &str = "std::cmp::Ordering::Equal";
+
+/// `Vec<T>` → `T`, unless `T` is unknown (`_`).
+pub fn element_type(ty: &str) -> Option<&str> {
+    ty.strip_prefix("Vec<")?
+        .strip_suffix('>')
+        .filter(|element| *element != "_")
+}
+
+/// Types of the parameters of `method`'s callback on `element`s: the element, or two of them for a
+/// `sort` comparator (borrowed when `element` is not `Copy`, as `sort_by` hands out references);
+/// none when `method` takes no such callback.
+pub fn callback_params(method: &str, element: &str) -> Vec<String> {
+    match method {
+        "sort" if is_owned_type(element) => vec![format!("&{}", element); 2],
+        "sort" => vec![element.to_string(); 2],
+        _ if CALLBACK_METHODS.contains(&method) => vec![element.to_string()],
+        _ => Vec::new(),
+    }
+}
+
+/// `users.map(f)` → `users.iter().cloned().map(f).collect::<Vec<_>>()`: the lazy chain `iter(users)`
+/// runs, collected right away for `map` and `filter`.
+pub fn transpile_method(
+    obj: &str,
+    method: &str,
+    element: &str,
+    arg_strs: &[String],
+) -> Option<String> {
+    if !CALLBACK_METHODS.contains(&method) || method == "sortBy" {
+        return None;
+    }
+    let call = iterators::transpile_method(
+        &format!("{}.iter().cloned()", obj),
+        method,
+        element,
+        arg_strs,
+    )?;
+    Some(match method {
+        "map" | "filter" => format!("{}.collect::<Vec<_>>()", call),
+        _ => call,
+    })
+}
+
+/// `includes`, `indexOf`, `lastIndexOf`, `slice`, `at` and `concat`, which strings have too, on an
+/// array: `slice` counts negative indexes from the end and `at` is `null` out of bounds, as for strings.
+pub fn transpile_shared_method(obj: &str, method: &str, arg_strs: &[String]) -> Option<String> {
+    // A negative `index` counts from the end; the result is clamped to `0..=len`.
+    let position = |index: &str| {
+        format!(
+            "{{ let __trust_i = ({}) as isize; (if __trust_i < 0 {{ (__trust_len + __trust_i).max(0) }} else {{ __trust_i.min(__trust_len) }}) as usize }}",
+            index
+        )
+    };
+    Some(match (method, arg_strs) {
+        ("includes", [value]) => format!("{}.contains(&{})", obj, value),
+        ("indexOf", [value]) => format!(
+            "{}.iter().position(|__trust_item| *__trust_item == {}).map(|i| i as i32).unwrap_or(-1)",
+            obj, value
+        ),
+        ("lastIndexOf", [value]) => format!(
+            "{}.iter().rposition(|__trust_item| *__trust_item == {}).map(|i| i as i32).unwrap_or(-1)",
+            obj, value
+        ),
+        ("slice", [start]) => format!(
+            "{{ let __trust_items = &{}; let __trust_len = __trust_items.len() as isize; __trust_items[{}..].to_vec() }}",
+            obj,
+            position(start)
+        ),
+        ("slice", [start, end]) => format!(
+            "{{ let __trust_items = &{}; let __trust_len = __trust_items.len() as isize; let __trust_from = {}; let __trust_to = {}; __trust_items[__trust_from..__trust_to.max(__trust_from)].to_vec() }}",
+            obj,
+            position(start),
+            position(end)
+        ),
+        ("at", [index]) => format!(
+            "{{ let __trust_items = &{}; let __trust_i = ({}) as isize; let __trust_pos = if __trust_i < 0 {{ __trust_items.len() as isize + __trust_i }} else {{ __trust_i }}; if __trust_pos < 0 {{ None }} else {{ __trust_items.get(__trust_pos as usize).cloned() }} }}",
+            obj, index
+        ),
+        ("concat", [other]) => format!(
+            "{{ let mut __trust_items = {}.clone(); __trust_items.extend(({}).iter().cloned()); __trust_items }}",
+            obj, other
+        ),
+        _ => return None,
+    })
+}
+
+/// Rust type returned by an array method on `element`s.
+pub fn method_return_type(
+    method: &str,
+    element: &str,
+    args: &[ExprOrSpread],
+    scope: &Scope,
+) -> Option<String> {
+    match method {
+        "includes" => Some("bool".to_string()),
+        "indexOf" | "lastIndexOf" => Some("i32".to_string()),
+        "slice" | "concat" => Some(format!("Vec<{}>", element)),
+        "at" => Some(format!("Option<{}>", element)),
+        "map" => {
+            let mapped = iterators::mapped_item(args.first()?, element, scope);
+            (mapped != "_").then(|| format!("Vec<{}>", mapped))
+        }
+        "filter" => Some(format!("Vec<{}>", element)),
+        "find" => Some(format!("Option<{}>", element)),
+        "some" | "every" => Some("bool".to_string()),
+        _ => None,
+    }
+}
+
+fn is_float(ty: &str) -> bool {
+    matches!(ty, "f32" | "f64")
+}
+
+/// Rust type the comparator `f` returns for two `element`s, when it can be told.
+fn comparator_type(f: &Expr, element: &str, scope: &Scope) -> Option<String> {
+    match f {
+        Expr::Arrow(arrow) => {
+            let BlockStmtOrExpr::Expr(body) = &*arrow.body else {
+                return None;
+            };
+            let mut scope = scope.clone();
+            scope.push();
+            for param in &arrow.params {
+                if let Pat::Ident(ident) = param {
+                    scope.insert(ident.id.sym.to_string(), element.to_string());
+                }
+            }
+            infer_rust_type(body, &scope)
+        }
+        Expr::Ident(ident) => scope
+            .symbols()
+            .function(ident.sym.as_ref())
+            .map(|sig| sig.return_type.clone()),
+        _ => None,
+    }
+}
+
+/// Whether comparator `f` is an arrow whose parameters take their types from the array, i.e.
+/// the `&T` of [`callback_params`].
+fn borrows_elements(f: &Expr) -> bool {
+    match f {
+        Expr::Arrow(arrow) => arrow
+            .params
+            .iter()
+            .all(|param| matches!(param, Pat::Ident(ident) if ident.type_ann.is_none())),
+        _ => false,
+    }
+}
+
+/// `xs.sort()` → `xs.sort()` (`partial_cmp` for floats); `xs.sort(f)` → `xs.sort_by(...)` with the
+/// comparator's number turned into an `Ordering` (negative: less); `xs.sortBy(key)` compares the
+/// keys of both elements. Structs and enums have no natural order, so they need one of the latter.
+pub fn transpile_sort(
+    member: &MemberExpr,
+    obj: &str,
+    method: &str,
+    element: &str,
+    args: &[ExprOrSpread],
+    arg_strs: &[String],
+    scope: &Scope,
+) -> Result<Option<String>> {
+    Ok(Some(match (method, arg_strs) {
+        ("sort", []) if is_float(element) => {
+            format!("{}.sort_by(|__trust_a, __trust_b| __trust_a.partial_cmp(__trust_b).unwrap_or({}))", obj, EQUAL)
+        }
+        ("sort", []) if scope.symbols().is_struct(element) || scope.symbols().enums.contains_key(element) => {
+            return Err(Diagnostic::error(
+                "sort-needs-comparator",
+                msg!("sort-needs-comparator", element),
+            )
+            .with_span(source_span(member.span))
+            .into());
+        }
+        ("sort", []) => format!("{}.sort()", obj),
+        ("sort", [f]) => {
+            let order = match comparator_type(&args[0].expr, element, scope) {
+                Some(ty) if is_float(&ty) => format!("partial_cmp(&0.0).unwrap_or({})", EQUAL),
+                _ => "cmp(&0)".to_string(),
+            };
+            // An arrow's parameters are typed by `callback_params`; a function or an annotated
+            // arrow takes the elements by value.
+            let (a, b) = if !is_owned_type(element) {
+                ("*__trust_a", "*__trust_b")
+            } else if borrows_elements(&args[0].expr) {
+                ("__trust_a", "__trust_b")
+            } else {
+                ("__trust_a.clone()", "__trust_b.clone()")
+            };
+            format!(
+                "{{ let __trust_compare = {}; {}.sort_by(|__trust_a, __trust_b| __trust_compare({}, {}).{}) }}",
+                f, obj, a, b, order
+            )
+        }
+        ("sortBy", [key]) => format!(
+            "{{ let __trust_key = {}; {}.sort_by(|__trust_a, __trust_b| __trust_key(__trust_a.clone()).partial_cmp(&__trust_key(__trust_b.clone())).unwrap_or({})) }}",
+            key, obj, EQUAL
+        ),
+        _ => return Ok(None),
+    }))
+}
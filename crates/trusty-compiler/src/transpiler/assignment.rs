@@ -0,0 +1,386 @@
+//! Definite-assignment checking for declarations without an initializer (`val x: int32;`): every
+//! read must follow an assignment on all paths, and a `val` is assigned at most once.
+
+use crate::diagnostics::Diagnostic;
+use crate::messages::msg;
+use crate::parser::source_span;
+use anyhow::Result;
+use swc_ecma_ast::*;
+
+/// How far a binding is assigned at a point of the function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    Unassigned,
+    /// Assigned on some paths only.
+    Maybe,
+    Assigned,
+    /// No path reaches this point (after `return`, `throw`, `break`, `continue`).
+    Diverged,
+}
+
+impl State {
+    fn merge(self, other: State) -> State {
+        match (self, other) {
+            (State::Diverged, other) | (other, State::Diverged) => other,
+            (a, b) if a == b => a,
+            _ => State::Maybe,
+        }
+    }
+}
+
+/// Checks every uninitialized declaration in `body`.
+pub fn check_function_body(body: &BlockStmt) -> Result<()> {
+    check_stmts(&body.stmts)
+}
+
+/// Finds uninitialized declarations in `stmts` (and nested blocks) and checks what follows them.
+fn check_stmts(stmts: &[Stmt]) -> Result<()> {
+    for (i, stmt) in stmts.iter().enumerate() {
+        if let Stmt::Decl(Decl::Var(var_decl)) = stmt {
+            for decl in var_decl.decls.iter().filter(|decl| decl.init.is_none()) {
+                if let Pat::Ident(binding) = &decl.name {
+                    let mut flow = Flow {
+                        name: binding.id.sym.as_ref(),
+                        is_val: var_decl.kind != VarDeclKind::Var,
+                        breaks: Vec::new(),
+                    };
+                    flow.stmts(&stmts[i + 1..], State::Unassigned)?;
+                }
+            }
+        }
+        for block in nested_blocks(stmt) {
+            check_stmts(block)?;
+        }
+    }
+    Ok(())
+}
+
+/// Statement lists directly inside `stmt`.
+fn nested_blocks(stmt: &Stmt) -> Vec<&[Stmt]> {
+    fn of(stmt: &Stmt) -> &[Stmt] {
+        match stmt {
+            Stmt::Block(block) => &block.stmts,
+            other => std::slice::from_ref(other),
+        }
+    }
+    match stmt {
+        Stmt::Block(block) => vec![&block.stmts],
+        Stmt::If(if_stmt) => std::iter::once(of(&if_stmt.cons))
+            .chain(if_stmt.alt.as_deref().map(of))
+            .collect(),
+        Stmt::While(s) => vec![of(&s.body)],
+        Stmt::DoWhile(s) => vec![of(&s.body)],
+        Stmt::For(s) => vec![of(&s.body)],
+        Stmt::ForIn(s) => vec![of(&s.body)],
+        Stmt::ForOf(s) => vec![of(&s.body)],
+        Stmt::Labeled(s) => vec![of(&s.body)],
+        Stmt::Try(s) => std::iter::once(&s.block.stmts[..])
+            .chain(s.handler.as_ref().map(|h| &h.body.stmts[..]))
+            .chain(s.finalizer.as_ref().map(|f| &f.stmts[..]))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Assignment state of one binding along the statements after its declaration.
+struct Flow<'a> {
+    name: &'a str,
+    is_val: bool,
+    /// States at the `break`s of each enclosing loop, innermost last.
+    breaks: Vec<Vec<State>>,
+}
+
+impl Flow<'_> {
+    fn stmts(&mut self, stmts: &[Stmt], mut state: State) -> Result<State> {
+        for stmt in stmts {
+            if let Stmt::Decl(Decl::Var(var_decl)) = stmt {
+                for decl in &var_decl.decls {
+                    if let Some(init) = &decl.init {
+                        state = self.expr(init, state)?;
+                    }
+                }
+                // A redeclaration shadows the binding for the rest of the block.
+                if var_decl
+                    .decls
+                    .iter()
+                    .any(|decl| matches!(&decl.name, Pat::Ident(b) if b.id.sym == *self.name))
+                {
+                    return Ok(state);
+                }
+                continue;
+            }
+            state = self.stmt(stmt, state)?;
+        }
+        Ok(state)
+    }
+
+    fn stmt(&mut self, stmt: &Stmt, state: State) -> Result<State> {
+        match stmt {
+            Stmt::Expr(expr_stmt) => self.expr(&expr_stmt.expr, state),
+            Stmt::Return(ret) => {
+                if let Some(arg) = &ret.arg {
+                    self.expr(arg, state)?;
+                }
+                Ok(State::Diverged)
+            }
+            Stmt::Throw(throw_stmt) => {
+                self.expr(&throw_stmt.arg, state)?;
+                Ok(State::Diverged)
+            }
+            Stmt::Break(_) => {
+                if let Some(breaks) = self.breaks.last_mut() {
+                    breaks.push(state);
+                }
+                Ok(State::Diverged)
+            }
+            Stmt::Continue(_) => Ok(State::Diverged),
+            Stmt::Block(block) => self.stmts(&block.stmts, state),
+            Stmt::Labeled(labeled) => self.stmt(&labeled.body, state),
+            Stmt::If(if_stmt) => {
+                let state = self.expr(&if_stmt.test, state)?;
+                let cons = self.stmt(&if_stmt.cons, state)?;
+                let alt = match &if_stmt.alt {
+                    Some(alt) => self.stmt(alt, state)?,
+                    None => state,
+                };
+                Ok(cons.merge(alt))
+            }
+            Stmt::While(while_stmt) => {
+                // `loop { ... }` is `while (true)`: it only ends at a `break`.
+                let infinite = matches!(&*while_stmt.test, Expr::Lit(Lit::Bool(b)) if b.value);
+                let state = self.expr(&while_stmt.test, state)?;
+                self.looped(&while_stmt.body, None, state, infinite)
+            }
+            Stmt::DoWhile(do_while) => {
+                self.breaks.push(Vec::new());
+                let first = self.stmt(&do_while.body, state)?;
+                let first = self.expr(&do_while.test, first)?;
+                self.stmt(&do_while.body, first)?;
+                let breaks = self.breaks.pop().unwrap_or_default();
+                Ok(breaks.into_iter().fold(first, State::merge))
+            }
+            Stmt::For(for_stmt) => {
+                let mut state = state;
+                match &for_stmt.init {
+                    Some(VarDeclOrExpr::VarDecl(var_decl)) => {
+                        for init in var_decl
+                            .decls
+                            .iter()
+                            .filter_map(|decl| decl.init.as_deref())
+                        {
+                            state = self.expr(init, state)?;
+                        }
+                    }
+                    Some(VarDeclOrExpr::Expr(expr)) => state = self.expr(expr, state)?,
+                    None => {}
+                }
+                if let Some(test) = &for_stmt.test {
+                    state = self.expr(test, state)?;
+                }
+                self.looped(
+                    &for_stmt.body,
+                    for_stmt.update.as_deref(),
+                    state,
+                    for_stmt.test.is_none(),
+                )
+            }
+            Stmt::ForIn(for_in) => {
+                let state = self.expr(&for_in.right, state)?;
+                self.looped(&for_in.body, None, state, false)
+            }
+            Stmt::ForOf(for_of) => {
+                let state = self.expr(&for_of.right, state)?;
+                self.looped(&for_of.body, None, state, false)
+            }
+            Stmt::Try(try_stmt) => {
+                let tried = self.stmts(&try_stmt.block.stmts, state)?;
+                // The handler may run after any part of the `try` block.
+                let caught = match &try_stmt.handler {
+                    Some(handler) => self.stmts(&handler.body.stmts, state.merge(tried))?,
+                    None => tried,
+                };
+                let state = tried.merge(caught);
+                match &try_stmt.finalizer {
+                    Some(finalizer) => self.stmts(&finalizer.stmts, state),
+                    None => Ok(state),
+                }
+            }
+            _ => Ok(state),
+        }
+    }
+
+    /// A loop body run twice: the second pass sees what the first one assigned, so reads and
+    /// `val` assignments that are only valid on the first iteration are reported.
+    fn looped(
+        &mut self,
+        body: &Stmt,
+        update: Option<&Expr>,
+        state: State,
+        infinite: bool,
+    ) -> Result<State> {
+        self.breaks.push(Vec::new());
+        let mut first = self.stmt(body, state)?;
+        if let Some(update) = update {
+            first = self.expr(update, first)?;
+        }
+        let again = state.merge(first);
+        let mut second = self.stmt(body, again)?;
+        if let Some(update) = update {
+            second = self.expr(update, second)?;
+        }
+        let breaks = self.breaks.pop().unwrap_or_default();
+        let exit = if infinite {
+            State::Diverged
+        } else {
+            again.merge(second)
+        };
+        Ok(breaks.into_iter().fold(exit, State::merge))
+    }
+
+    fn read(&self, ident: &Ident, state: State) -> Result<()> {
+        if ident.sym == *self.name && matches!(state, State::Unassigned | State::Maybe) {
+            return Err(
+                Diagnostic::error("unassigned-read", msg!("unassigned-read", self.name))
+                    .with_span(source_span(ident.span))
+                    .into(),
+            );
+        }
+        Ok(())
+    }
+
+    fn assign(&self, ident: &Ident, state: State) -> Result<State> {
+        if ident.sym != *self.name {
+            return Ok(state);
+        }
+        if self.is_val && matches!(state, State::Maybe | State::Assigned) {
+            return Err(
+                Diagnostic::error("val-reassigned", msg!("val-reassigned", self.name))
+                    .with_span(source_span(ident.span))
+                    .into(),
+            );
+        }
+        Ok(if state == State::Diverged {
+            state
+        } else {
+            State::Assigned
+        })
+    }
+
+    fn expr(&mut self, expr: &Expr, state: State) -> Result<State> {
+        match expr {
+            Expr::Ident(ident) => {
+                self.read(ident, state)?;
+                Ok(state)
+            }
+            Expr::Assign(assign) => {
+                let state = self.expr(&assign.right, state)?;
+                match &assign.left {
+                    AssignTarget::Simple(SimpleAssignTarget::Ident(binding)) => {
+                        if assign.op != AssignOp::Assign {
+                            self.read(&binding.id, state)?;
+                        }
+                        self.assign(&binding.id, state)
+                    }
+                    AssignTarget::Simple(SimpleAssignTarget::Member(member)) => {
+                        self.member(member, state)
+                    }
+                    _ => Ok(state),
+                }
+            }
+            Expr::Update(update) => {
+                if let Expr::Ident(ident) = &*update.arg {
+                    self.read(ident, state)?;
+                    return self.assign(ident, state);
+                }
+                self.expr(&update.arg, state)
+            }
+            Expr::Bin(bin) => {
+                let left = self.expr(&bin.left, state)?;
+                let right = self.expr(&bin.right, left)?;
+                // The right operand of `&&`/`||`/`??` may not run.
+                Ok(match bin.op {
+                    BinaryOp::LogicalAnd | BinaryOp::LogicalOr | BinaryOp::NullishCoalescing => {
+                        left.merge(right)
+                    }
+                    _ => right,
+                })
+            }
+            Expr::Cond(cond) => {
+                let state = self.expr(&cond.test, state)?;
+                let cons = self.expr(&cond.cons, state)?;
+                let alt = self.expr(&cond.alt, state)?;
+                Ok(cons.merge(alt))
+            }
+            Expr::Unary(unary) => self.expr(&unary.arg, state),
+            Expr::Paren(paren) => self.expr(&paren.expr, state),
+            Expr::Await(await_expr) => self.expr(&await_expr.arg, state),
+            Expr::TsAs(ts_as) => self.expr(&ts_as.expr, state),
+            Expr::TsNonNull(non_null) => self.expr(&non_null.expr, state),
+            Expr::Member(member) => self.member(member, state),
+            Expr::Call(call) => {
+                let mut state = match &call.callee {
+                    Callee::Expr(callee) => self.expr(callee, state)?,
+                    _ => state,
+                };
+                for arg in &call.args {
+                    state = self.expr(&arg.expr, state)?;
+                }
+                Ok(state)
+            }
+            Expr::New(new_expr) => {
+                let mut state = state;
+                for arg in new_expr.args.iter().flatten() {
+                    state = self.expr(&arg.expr, state)?;
+                }
+                Ok(state)
+            }
+            Expr::Seq(seq) => seq
+                .exprs
+                .iter()
+                .try_fold(state, |state, e| self.expr(e, state)),
+            Expr::Array(array) => array
+                .elems
+                .iter()
+                .flatten()
+                .try_fold(state, |state, e| self.expr(&e.expr, state)),
+            Expr::Tpl(tpl) => tpl
+                .exprs
+                .iter()
+                .try_fold(state, |state, e| self.expr(e, state)),
+            Expr::Object(obj) => {
+                let mut state = state;
+                for prop in &obj.props {
+                    if let PropOrSpread::Prop(prop) = prop {
+                        match &**prop {
+                            Prop::KeyValue(kv) => state = self.expr(&kv.value, state)?,
+                            Prop::Shorthand(ident) => self.read(ident, state)?,
+                            _ => {}
+                        }
+                    }
+                }
+                Ok(state)
+            }
+            // A closure reads the binding when it is created; its own assignments are not tracked.
+            Expr::Arrow(arrow) => {
+                match &*arrow.body {
+                    BlockStmtOrExpr::Expr(body) => {
+                        self.expr(body, state)?;
+                    }
+                    BlockStmtOrExpr::BlockStmt(block) => {
+                        self.stmts(&block.stmts, state)?;
+                    }
+                }
+                Ok(state)
+            }
+            _ => Ok(state),
+        }
+    }
+
+    fn member(&mut self, member: &MemberExpr, state: State) -> Result<State> {
+        let state = self.expr(&member.obj, state)?;
+        match &member.prop {
+            MemberProp::Computed(computed) => self.expr(&computed.expr, state),
+            _ => Ok(state),
+        }
+    }
+}
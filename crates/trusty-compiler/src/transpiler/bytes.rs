@@ -0,0 +1,242 @@
+//! `bytes`: binary data as a `Vec<u8>`, with `b"..."` / `hex"..."` literals and text encodings.
+
+use crate::diagnostics::Diagnostic;
+use crate::messages::{msg, Message};
+use crate::parser::source_span;
+use anyhow::Result;
+use swc_ecma_ast::*;
+
+/// Rust type of a TRUST `bytes` value.
+pub const BYTES: &str = "Vec<u8>";
+
+/// Preprocessor markers for `b"..."` and `hex"..."`: `__trust_bytes("...")`, `__trust_hex("...")`.
+pub const BYTES_CALLEE: &str = "__trust_bytes";
+pub const HEX_CALLEE: &str = "__trust_hex";
+
+/// Encodings `toString(encoding)` and `bytes.fromString(text, encoding)` accept.
+pub const ENCODINGS: [&str; 4] = ["utf8", "hex", "base64", "latin1"];
+
+/// Conversions between bytes and text, emitted when a program uses them.
+pub const RUNTIME: &str = r#"fn __trust_bytes_to_string(bytes: &[u8], encoding: &str) -> String {
+    match encoding {
+        "hex" => bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+        "base64" => {
+            const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+            let mut out = String::new();
+            for chunk in bytes.chunks(3) {
+                let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+                for i in 0..4 {
+                    if i <= chunk.len() {
+                        out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+                    } else {
+                        out.push('=');
+                    }
+                }
+            }
+            out
+        }
+        "latin1" => bytes.iter().map(|b| *b as char).collect(),
+        _ => String::from_utf8_lossy(bytes).to_string(),
+    }
+}
+
+fn __trust_bytes_from_string(text: &str, encoding: &str) -> Vec<u8> {
+    match encoding {
+        "hex" => {
+            let digits: Vec<u8> = text.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+            if digits.len() % 2 != 0 {
+                return Vec::new();
+            }
+            digits
+                .chunks(2)
+                .map(|pair| std::str::from_utf8(pair).ok().and_then(|p| u8::from_str_radix(p, 16).ok()))
+                .collect::<Option<Vec<u8>>>()
+                .unwrap_or_default()
+        }
+        "base64" => {
+            let mut out = Vec::new();
+            let (mut n, mut bits) = (0u32, 0);
+            for c in text.bytes().filter(|c| !c.is_ascii_whitespace() && *c != b'=') {
+                let value = match c {
+                    b'A'..=b'Z' => c - b'A',
+                    b'a'..=b'z' => c - b'a' + 26,
+                    b'0'..=b'9' => c - b'0' + 52,
+                    b'+' | b'-' => 62,
+                    b'/' | b'_' => 63,
+                    _ => return Vec::new(),
+                };
+                n = n << 6 | value as u32;
+                bits += 6;
+                if bits >= 8 {
+                    bits -= 8;
+                    out.push((n >> bits & 0xff) as u8);
+                }
+            }
+            out
+        }
+        "latin1" => text.chars().map(|c| if (c as u32) < 256 { c as u8 } else { b'?' }).collect(),
+        _ => text.as_bytes().to_vec(),
+    }
+}"#;
+
+fn error(message: Message, span: swc_common::Span) -> anyhow::Error {
+    Diagnostic::error("bytes-literal", message)
+        .with_span(source_span(span))
+        .into()
+}
+
+/// `__trust_bytes("...")` / `__trust_hex("...")` → a `b"...".to_vec()` with the literal's bytes.
+pub fn transpile_literal(callee: &str, call: &CallExpr) -> Result<String> {
+    let Some(Expr::Lit(Lit::Str(s))) = call.args.first().map(|arg| &*arg.expr) else {
+        return Err(error(msg!("bytes-literal"), call.span));
+    };
+    let bytes = if callee == HEX_CALLEE {
+        let digits: String = s
+            .value
+            .to_string_lossy()
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        if !digits.len().is_multiple_of(2) || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(error(
+                msg!("bytes-literal.hex", s.value.to_string_lossy()),
+                call.span,
+            ));
+        }
+        (0..digits.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).unwrap_or_default())
+            .collect()
+    } else {
+        match &s.raw {
+            Some(raw) => {
+                unescape(raw).ok_or_else(|| error(msg!("bytes-literal.escape", raw), call.span))?
+            }
+            None => s.value.to_string_lossy().as_bytes().to_vec(),
+        }
+    };
+    Ok(format!("b\"{}\".to_vec()", escape(&bytes)))
+}
+
+/// Bytes of a quoted literal as written: `\xHH` is one byte, other characters are UTF-8.
+fn unescape(raw: &str) -> Option<Vec<u8>> {
+    let body = raw.get(1..raw.len().checked_sub(1)?)?;
+    let mut out = Vec::new();
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+            continue;
+        }
+        match chars.next()? {
+            'n' => out.push(b'\n'),
+            'r' => out.push(b'\r'),
+            't' => out.push(b'\t'),
+            '0' => out.push(0),
+            'x' => {
+                let hex: String = chars.by_ref().take(2).collect();
+                out.push(u8::from_str_radix(&hex, 16).ok()?);
+            }
+            other => out.extend_from_slice(other.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+    Some(out)
+}
+
+/// Body of a Rust byte string literal holding `bytes`.
+fn escape(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&b| match b {
+            b'"' => "\\\"".to_string(),
+            b'\\' => "\\\\".to_string(),
+            b' '..=b'~' => (b as char).to_string(),
+            _ => format!("\\x{:02x}", b),
+        })
+        .collect()
+}
+
+/// Rejects a literal `encoding` argument that is not one of [`ENCODINGS`].
+fn check_encoding(arg: &ExprOrSpread) -> Result<()> {
+    if let Expr::Lit(Lit::Str(s)) = &*arg.expr {
+        let encoding = s.value.to_string_lossy();
+        if !ENCODINGS.contains(&encoding.as_ref()) {
+            return Err(Diagnostic::error(
+                "unknown-encoding",
+                msg!("unknown-encoding", encoding, ENCODINGS.join(", ")),
+            )
+            .with_span(source_span(s.span))
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// `bytes.fromString(text)` / `bytes.fromString(text, encoding)`; malformed hex or base64 gives
+/// empty bytes.
+pub fn transpile_from_string(args: &[ExprOrSpread], arg_strs: &[String]) -> Result<Option<String>> {
+    match arg_strs {
+        [text] => Ok(Some(format!("({}).as_bytes().to_vec()", text))),
+        [text, encoding] => {
+            check_encoding(&args[1])?;
+            Ok(Some(format!(
+                "__trust_bytes_from_string(&{}, &{})",
+                text, encoding
+            )))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// `data.slice(1, 3)`, `data.toString("hex")`, ... on `bytes`; `slice` takes negative indexes
+/// from the end, like strings do.
+pub fn transpile_method(
+    obj: &str,
+    method: &str,
+    args: &[ExprOrSpread],
+    arg_strs: &[String],
+) -> Result<Option<String>> {
+    Ok(match (method, arg_strs) {
+        ("slice", [start]) => Some(slice(obj, start, None)),
+        ("slice", [start, end]) => Some(slice(obj, start, Some(end))),
+        ("toString", []) => Some(format!("String::from_utf8_lossy(&{}).to_string()", obj)),
+        ("toString", [encoding]) => {
+            check_encoding(&args[0])?;
+            Some(format!("__trust_bytes_to_string(&{}, &{})", obj, encoding))
+        }
+        ("concat", [other]) => Some(format!(
+            "[{}.as_slice(), {}.as_slice()].concat()",
+            obj, other
+        )),
+        _ => None,
+    })
+}
+
+fn slice(obj: &str, start: &str, end: Option<&str>) -> String {
+    let bound = |name: &str, value: &str| {
+        format!(
+            "let {} = ({}) as isize; let {} = if {} < 0 {{ (__trust_len + {}).max(0) }} else {{ {}.min(__trust_len) }} as usize;",
+            name, value, name, name, name, name
+        )
+    };
+    let end_bound = match end {
+        Some(end) => bound("__trust_to", end),
+        None => "let __trust_to = __trust_len as usize;".to_string(),
+    };
+    format!(
+        "{{ let __trust_len = {}.len() as isize; {} {} {}[__trust_from..__trust_to.max(__trust_from)].to_vec() }}",
+        obj,
+        bound("__trust_from", start),
+        end_bound,
+        obj
+    )
+}
+
+/// Rust type returned by a `bytes` method.
+pub fn method_return_type(method: &str) -> Option<&'static str> {
+    match method {
+        "slice" | "concat" => Some(BYTES),
+        "toString" => Some("String"),
+        _ => None,
+    }
+}
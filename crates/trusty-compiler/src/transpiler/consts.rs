@@ -0,0 +1,379 @@
+use super::expressions::check_arity;
+use super::scope::Scope;
+use super::strings::{self, rust_string_literal};
+use super::symbols::ProgramSymbols;
+use super::types::transpile_type_annotation;
+use crate::diagnostics::Diagnostic;
+use crate::messages::msg;
+use crate::parser::source_span;
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use swc_common::Spanned;
+use swc_ecma_ast::*;
+
+/// A compile-time value rendered as a Rust const expression.
+#[derive(Clone, Debug)]
+struct ConstValue {
+    code: String,
+    ty: String,
+    /// A bare number literal, which takes the type of the other operand.
+    literal: bool,
+}
+
+/// What a const expression may refer to: earlier global consts (name → type) or, inside a
+/// `const fn`, its parameters.
+#[derive(Clone, Debug, Default)]
+pub struct ConstEnv {
+    values: HashMap<String, String>,
+}
+
+fn is_integer(ty: &str) -> bool {
+    matches!(
+        ty,
+        "i8" | "i16" | "i32" | "i64" | "u8" | "u16" | "u32" | "u64" | "isize" | "usize"
+    )
+}
+
+fn is_float(ty: &str) -> bool {
+    matches!(ty, "f32" | "f64")
+}
+
+fn is_numeric(ty: &str) -> bool {
+    is_integer(ty) || is_float(ty)
+}
+
+fn not_const(span: swc_common::Span) -> anyhow::Error {
+    Diagnostic::error("non-const-expression", msg!("non-const-expression"))
+        .with_span(source_span(span))
+        .into()
+}
+
+fn value(code: String, ty: &str) -> ConstValue {
+    ConstValue {
+        code,
+        ty: ty.to_string(),
+        literal: false,
+    }
+}
+
+/// `value` as type `ty`: literals adapt (`2` → `2.0` for floats), other numbers are cast.
+fn coerce(value: &ConstValue, ty: &str) -> String {
+    if value.ty == ty || !is_numeric(&value.ty) || !is_numeric(ty) {
+        value.code.clone()
+    } else if value.literal && is_float(ty) && !value.code.contains(['.', 'e']) {
+        format!("{}.0", value.code)
+    } else if value.literal && is_integer(ty) && is_integer(&value.ty) {
+        value.code.clone()
+    } else if value.code.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        format!("({} as {})", value.code, ty)
+    } else {
+        format!("(({}) as {})", value.code, ty)
+    }
+}
+
+/// Common type of two numeric operands: a typed operand wins over a literal, floats over integers.
+fn unify(left: &ConstValue, right: &ConstValue) -> String {
+    match (left.literal, right.literal) {
+        (true, false) if !(is_float(&left.ty) && is_integer(&right.ty)) => right.ty.clone(),
+        (false, true) if !(is_float(&right.ty) && is_integer(&left.ty)) => left.ty.clone(),
+        _ if is_float(&left.ty) => left.ty.clone(),
+        _ if is_float(&right.ty) => right.ty.clone(),
+        _ => left.ty.clone(),
+    }
+}
+
+fn eval(expr: &Expr, env: &ConstEnv, scope: &Scope) -> Result<ConstValue> {
+    match expr {
+        Expr::Lit(Lit::Num(n)) => {
+            let ty = if n.value.fract() != 0.0 {
+                "f64"
+            } else if n.value.abs() > i32::MAX as f64 {
+                "i64"
+            } else {
+                "i32"
+            };
+            Ok(ConstValue {
+                code: n.value.to_string(),
+                ty: ty.to_string(),
+                literal: true,
+            })
+        }
+        Expr::Lit(Lit::Bool(b)) => Ok(value(b.value.to_string(), "bool")),
+        Expr::Lit(Lit::Str(s)) => Ok(value(
+            rust_string_literal(&s.value.to_string_lossy()),
+            "&'static str",
+        )),
+        Expr::Ident(ident) => match env.values.get(ident.sym.as_ref()) {
+            Some(ty) => Ok(value(ident.sym.to_string(), ty)),
+            None => Err(Diagnostic::error(
+                "non-const-expression",
+                msg!("non-const-expression.name", ident.sym),
+            )
+            .with_span(source_span(ident.span))
+            .into()),
+        },
+        Expr::Paren(paren) => {
+            let inner = eval(&paren.expr, env, scope)?;
+            Ok(ConstValue {
+                code: format!("({})", inner.code),
+                ..inner
+            })
+        }
+        Expr::Unary(unary) => {
+            let arg = eval(&unary.arg, env, scope)?;
+            match unary.op {
+                UnaryOp::Minus if is_numeric(&arg.ty) => Ok(ConstValue {
+                    code: format!("-{}", arg.code),
+                    ..arg
+                }),
+                UnaryOp::Plus if is_numeric(&arg.ty) => Ok(arg),
+                UnaryOp::Bang if arg.ty == "bool" => Ok(value(format!("!{}", arg.code), "bool")),
+                UnaryOp::Tilde if is_integer(&arg.ty) => {
+                    Ok(value(format!("!{}", arg.code), &arg.ty))
+                }
+                _ => Err(not_const(unary.span)),
+            }
+        }
+        Expr::Bin(bin) => eval_binary(bin, env, scope),
+        Expr::Cond(cond) => {
+            let test = eval(&cond.test, env, scope)?;
+            let cons = eval(&cond.cons, env, scope)?;
+            let alt = eval(&cond.alt, env, scope)?;
+            if test.ty != "bool" {
+                return Err(not_const(cond.test.span()));
+            }
+            let ty = if is_numeric(&cons.ty) && is_numeric(&alt.ty) {
+                unify(&cons, &alt)
+            } else {
+                cons.ty.clone()
+            };
+            Ok(ConstValue {
+                code: format!(
+                    "if {} {{ {} }} else {{ {} }}",
+                    test.code,
+                    coerce(&cons, &ty),
+                    coerce(&alt, &ty)
+                ),
+                ty,
+                literal: cons.literal && alt.literal,
+            })
+        }
+        Expr::Call(call) => {
+            let Callee::Expr(callee) = &call.callee else {
+                return Err(not_const(call.span));
+            };
+            let Expr::Ident(ident) = &**callee else {
+                return Err(not_const(call.span));
+            };
+            let name = ident.sym.as_ref();
+            if let Some(text) = (name == strings::RAW_CALLEE)
+                .then(|| strings::raw_text(call))
+                .flatten()
+            {
+                return Ok(value(
+                    strings::rust_raw_string_literal(&text),
+                    "&'static str",
+                ));
+            }
+            let sig = match scope.symbols().function(name) {
+                Some(sig) if scope.symbols().const_fns.contains(name) => sig,
+                _ => return Err(not_const(call.span)),
+            };
+            check_arity(&format!("`{}`", name), sig, &call.args, call.span)?;
+            let args = call
+                .args
+                .iter()
+                .zip(&sig.params)
+                .map(|(arg, (_, ty))| Ok(coerce(&eval(&arg.expr, env, scope)?, ty)))
+                .collect::<Result<Vec<_>>>()?;
+            Ok(value(
+                format!("{}({})", name, args.join(", ")),
+                &sig.return_type,
+            ))
+        }
+        _ => Err(not_const(expr.span())),
+    }
+}
+
+fn eval_binary(bin: &BinExpr, env: &ConstEnv, scope: &Scope) -> Result<ConstValue> {
+    let left = eval(&bin.left, env, scope)?;
+    let right = eval(&bin.right, env, scope)?;
+    let op = bin.op.as_str();
+    let numeric = is_numeric(&left.ty) && is_numeric(&right.ty);
+    let ty = unify(&left, &right);
+    let operands = || (coerce(&left, &ty), coerce(&right, &ty));
+    match bin.op {
+        BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod
+            if numeric =>
+        {
+            let (l, r) = operands();
+            // `checked_add` and `saturating_add` are `const fn`s too, so the overflow mode holds.
+            let code = scope
+                .options()
+                .overflow
+                .lower(bin.op, &l, &r, &ty)
+                .unwrap_or_else(|| format!("{} {} {}", l, op, r));
+            Ok(ConstValue {
+                code,
+                ty,
+                literal: left.literal && right.literal,
+            })
+        }
+        BinaryOp::Exp if numeric && is_integer(&ty) => {
+            let (l, r) = operands();
+            Ok(value(format!("{}::pow({}, {} as u32)", ty, l, r), &ty))
+        }
+        BinaryOp::BitAnd
+        | BinaryOp::BitOr
+        | BinaryOp::BitXor
+        | BinaryOp::LShift
+        | BinaryOp::RShift
+            if is_integer(&left.ty) && is_integer(&right.ty) =>
+        {
+            let (l, r) = operands();
+            Ok(ConstValue {
+                code: format!("{} {} {}", l, op, r),
+                ty,
+                literal: left.literal && right.literal,
+            })
+        }
+        BinaryOp::Lt | BinaryOp::LtEq | BinaryOp::Gt | BinaryOp::GtEq if numeric => {
+            let (l, r) = operands();
+            Ok(value(format!("{} {} {}", l, op, r), "bool"))
+        }
+        BinaryOp::EqEq | BinaryOp::EqEqEq | BinaryOp::NotEq | BinaryOp::NotEqEq
+            if numeric || left.ty == "bool" && right.ty == "bool" =>
+        {
+            let rust_op = if matches!(bin.op, BinaryOp::EqEq | BinaryOp::EqEqEq) {
+                "=="
+            } else {
+                "!="
+            };
+            let (l, r) = operands();
+            Ok(value(format!("{} {} {}", l, rust_op, r), "bool"))
+        }
+        BinaryOp::LogicalAnd | BinaryOp::LogicalOr if left.ty == "bool" && right.ty == "bool" => {
+            Ok(value(
+                format!("{} {} {}", left.code, op, right.code),
+                "bool",
+            ))
+        }
+        _ => Err(not_const(bin.span)),
+    }
+}
+
+/// `const AREA = WIDTH * HEIGHT;` → `const AREA: i32 = WIDTH * HEIGHT;`. The type is inferred from
+/// the expression unless annotated; each const becomes visible to the ones after it.
+pub fn transpile_global_const(
+    var_decl: &VarDecl,
+    env: &mut ConstEnv,
+    scope: &Scope,
+) -> Result<Vec<String>> {
+    let mut parts = Vec::new();
+    for decl in &var_decl.decls {
+        let Pat::Ident(ident) = &decl.name else {
+            continue;
+        };
+        let Some(init) = &decl.init else {
+            continue;
+        };
+        let name = ident.id.sym.to_string();
+        let value = eval(init, env, scope)?;
+        let ty = match ident.type_ann.as_deref().map(transpile_type_annotation) {
+            Some(ty) if ty == "String" => "&'static str".to_string(),
+            Some(ty) => ty,
+            None => value.ty.clone(),
+        };
+        parts.push(format!("const {}: {} = {};", name, ty, coerce(&value, &ty)));
+        env.values.insert(name, ty);
+    }
+    Ok(parts)
+}
+
+/// Returned expression and return type of a function that can be a `const fn`: numeric or boolean
+/// parameters and result, and a body that only returns an expression over its parameters.
+fn const_body<'a>(func: &'a FnDecl, env: &mut ConstEnv) -> Option<(&'a Expr, String)> {
+    let function = &func.function;
+    let scalar = |ty: &str| is_numeric(ty) || ty == "bool";
+    if function.is_async || function.is_generator || function.type_params.is_some() {
+        return None;
+    }
+    for param in &function.params {
+        let Pat::Ident(ident) = &param.pat else {
+            return None;
+        };
+        let ty = transpile_type_annotation(ident.type_ann.as_deref()?);
+        if !scalar(&ty) {
+            return None;
+        }
+        env.values.insert(ident.id.sym.to_string(), ty);
+    }
+    let return_type = transpile_type_annotation(function.return_type.as_deref()?);
+    if !scalar(&return_type) {
+        return None;
+    }
+    match function.body.as_ref()?.stmts.as_slice() {
+        [Stmt::Return(ReturnStmt { arg: Some(arg), .. })] => Some((arg, return_type)),
+        _ => None,
+    }
+}
+
+/// Functions that compile to `const fn` and so may be called from consts, including ones that
+/// call each other.
+pub fn const_functions(module: &Module, symbols: &ProgramSymbols) -> HashSet<String> {
+    let functions: Vec<&FnDecl> = module
+        .body
+        .iter()
+        .filter_map(|item| match item {
+            ModuleItem::Stmt(Stmt::Decl(Decl::Fn(func))) if func.ident.sym.as_ref() != "main" => {
+                Some(func)
+            }
+            _ => None,
+        })
+        .collect();
+    let mut symbols = symbols.clone();
+    loop {
+        let scope = Scope::for_program(
+            std::rc::Rc::new(symbols.clone()),
+            Default::default(),
+            Default::default(),
+        );
+        let found: HashSet<String> = functions
+            .iter()
+            .filter(|func| {
+                let mut env = ConstEnv::default();
+                const_body(func, &mut env).is_some_and(|(body, _)| eval(body, &env, &scope).is_ok())
+            })
+            .map(|func| func.ident.sym.to_string())
+            .collect();
+        if found == symbols.const_fns {
+            return found;
+        }
+        symbols.const_fns = found;
+    }
+}
+
+/// `function area(w: int32, h: int32): int32 { return w * h; }` → `const fn area(...)`.
+pub fn transpile_const_fn(func: &FnDecl, scope: &Scope) -> Result<String> {
+    let mut env = ConstEnv::default();
+    let (body, return_type) =
+        const_body(func, &mut env).ok_or_else(|| not_const(func.function.span))?;
+    let value = eval(body, &env, scope)?;
+    let params = scope
+        .symbols()
+        .function(func.ident.sym.as_ref())
+        .map(|sig| {
+            sig.params
+                .iter()
+                .map(|(name, ty)| format!("{}: {}", name, ty))
+                .collect::<Vec<_>>()
+                .join(", ")
+        });
+    Ok(format!(
+        "const fn {}({}) -> {} {{\n    {}\n}}",
+        func.ident.sym,
+        params.unwrap_or_default(),
+        return_type,
+        coerce(&value, &return_type)
+    ))
+}
@@ -0,0 +1,153 @@
+//! `decimal`: exact base-10 numbers backed by `rust_decimal`, for money and other values a
+//! `float64` would round.
+
+use super::expressions::{infer_rust_type, is_numeric_rust_type};
+use super::scope::Scope;
+use crate::diagnostics::Diagnostic;
+use crate::messages::msg;
+use crate::parser::source_span;
+use anyhow::Result;
+use swc_common::Spanned;
+use swc_ecma_ast::*;
+
+/// Rust type of a TRUST `decimal`.
+pub const DECIMAL: &str = "rust_decimal::Decimal";
+
+/// Preprocessor marker for a `12.34d` literal: `__trust_decimal("12.34")`.
+pub const DECIMAL_CALLEE: &str = "__trust_decimal";
+
+/// Digits of a `12.34d` number token, or `None` for any other number (`0x1d` is hex).
+pub fn literal_digits(token: &str) -> Option<&str> {
+    let digits = token.strip_suffix('d')?;
+    let valid = digits.starts_with(|c: char| c.is_ascii_digit())
+        && digits
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '_' || c == '.')
+        && digits.matches('.').count() <= 1;
+    valid.then_some(digits)
+}
+
+/// `12.34` → `Decimal::from_i128_with_scale(1234, 2)`, or `None` when it does not fit a
+/// `Decimal` (a 96-bit mantissa with at most 28 digits after the point).
+fn render_digits(digits: &str) -> Option<String> {
+    let digits = digits.replace('_', "");
+    let (whole, fraction) = digits.split_once('.').unwrap_or((&digits, ""));
+    let mantissa: i128 = format!("{}{}", whole, fraction).parse().ok()?;
+    let scale = fraction.len();
+    (scale <= 28 && mantissa < 1 << 96)
+        .then(|| format!("{}::from_i128_with_scale({}, {})", DECIMAL, mantissa, scale))
+}
+
+/// `__trust_decimal("12.34")` → the exact `Decimal` constant.
+pub fn transpile_literal(call: &CallExpr) -> Result<String> {
+    let digits = match call.args.first().map(|arg| &*arg.expr) {
+        Some(Expr::Lit(Lit::Str(s))) => s.value.to_string_lossy().to_string(),
+        _ => String::new(),
+    };
+    render_digits(&digits).ok_or_else(|| {
+        Diagnostic::error("decimal-literal", msg!("decimal-literal", digits))
+            .with_span(source_span(call.span))
+            .into()
+    })
+}
+
+/// A number used where a `decimal` is expected: literals become exact constants and integers
+/// widen; floats are refused, since they may already have lost digits.
+pub fn convert(expr: &Expr, rendered: String, scope: &Scope) -> Result<String> {
+    if let Expr::Lit(Lit::Num(num)) = expr {
+        if let Some(literal) = render_digits(&num.value.to_string()) {
+            return Ok(literal);
+        }
+    }
+    match infer_rust_type(expr, scope).as_deref() {
+        Some("f32" | "f64") => Err(Diagnostic::error("decimal-float", msg!("decimal-float"))
+            .with_span(source_span(expr.span()))
+            .into()),
+        Some(ty) if is_numeric_rust_type(ty) => Ok(format!("{}::from({})", DECIMAL, rendered)),
+        _ => Ok(rendered),
+    }
+}
+
+/// Operands of `a + b`, `a < b`, ... with the non-`decimal` side converted when the other is one.
+pub fn convert_operands(
+    bin: &BinExpr,
+    left: String,
+    right: String,
+    scope: &Scope,
+) -> Result<(String, String)> {
+    let left_decimal = infer_rust_type(&bin.left, scope).as_deref() == Some(DECIMAL);
+    let right_decimal = infer_rust_type(&bin.right, scope).as_deref() == Some(DECIMAL);
+    Ok(match (left_decimal, right_decimal) {
+        (true, false) => (left, convert(&bin.right, right, scope)?),
+        (false, true) => (convert(&bin.left, left, scope)?, right),
+        _ => (left, right),
+    })
+}
+
+/// `decimal(x)`: parses strings (0 when invalid), widens integers and rounds floats.
+pub fn transpile_cast(arg: &Expr, rendered: String, scope: &Scope) -> String {
+    if let Expr::Lit(Lit::Num(num)) = arg {
+        if let Some(literal) = render_digits(&num.value.to_string()) {
+            return literal;
+        }
+    }
+    match infer_rust_type(arg, scope).as_deref() {
+        Some("String") => format!("({}).parse::<{}>().unwrap_or_default()", rendered, DECIMAL),
+        Some("f32" | "f64") => format!(
+            "{}::try_from(({}) as f64).unwrap_or_default()",
+            DECIMAL, rendered
+        ),
+        Some(DECIMAL) => rendered,
+        _ => format!("{}::from({})", DECIMAL, rendered),
+    }
+}
+
+/// `int32(d)`, `float64(d)`, ...: integers drop the fraction.
+pub fn transpile_number_cast(rendered: &str, rust_num: &str) -> String {
+    let (to, zero) = if rust_num.starts_with('f') {
+        ("to_f64", "0.0")
+    } else {
+        ("to_i64", "0")
+    };
+    format!(
+        "(rust_decimal::prelude::ToPrimitive::{}(&{}).unwrap_or({})) as {}",
+        to, rendered, zero, rust_num
+    )
+}
+
+/// `price.toFixed(2)`, `price.round(2)`, ... on a `decimal`; halves round away from zero.
+pub fn transpile_method(obj: &str, method: &str, args: &[String]) -> Option<String> {
+    let round = |digits: &str| {
+        format!(
+            "{}.round_dp_with_strategy(({}) as u32, rust_decimal::RoundingStrategy::MidpointAwayFromZero)",
+            obj, digits
+        )
+    };
+    match (method, args) {
+        ("toFixed", [digits]) => Some(format!(
+            "format!(\"{{:.*}}\", ({}) as usize, {})",
+            digits,
+            round(digits)
+        )),
+        ("round", []) => Some(round("0")),
+        ("round", [digits]) => Some(round(digits)),
+        ("toFloat", []) => Some(format!(
+            "rust_decimal::prelude::ToPrimitive::to_f64(&{}).unwrap_or(0.0)",
+            obj
+        )),
+        ("abs" | "floor" | "ceil" | "trunc", []) => Some(format!("{}.{}()", obj, method)),
+        ("isZero", []) => Some(format!("{}.is_zero()", obj)),
+        _ => None,
+    }
+}
+
+/// Rust type returned by a `decimal` method.
+pub fn method_return_type(method: &str) -> Option<&'static str> {
+    match method {
+        "toFixed" => Some("String"),
+        "toFloat" => Some("f64"),
+        "isZero" => Some("bool"),
+        "round" | "abs" | "floor" | "ceil" | "trunc" => Some(DECIMAL),
+        _ => None,
+    }
+}
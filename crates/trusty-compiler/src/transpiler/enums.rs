@@ -1,3 +1,4 @@
+use super::strings::rust_string_literal;
 use anyhow::Result;
 use swc_ecma_ast::*;
 
@@ -7,9 +8,15 @@ enum Discriminant {
     None,
 }
 
-pub fn transpile_enum(decl: &TsEnumDecl) -> Result<String> {
+/// Methods `compilerOptions.enumUtilities` adds to every enum.
+pub const UTILITY_METHODS: [&str; 3] = ["values", "fromString", "count"];
+
+/// `enum Color { ... }` → a Rust enum deriving `derives`; string enums get `as_str()` and `Display`,
+/// and with `utilities` every enum gets `values()`, `fromString(s)` and `count()`.
+pub fn transpile_enum(decl: &TsEnumDecl, derives: &[String], utilities: bool) -> Result<String> {
     let name = &decl.id.sym;
     let mut variants = Vec::new();
+    let mut names = Vec::new();
     let mut string_values: Vec<(String, String)> = Vec::new();
 
     for member in &decl.members {
@@ -17,6 +24,7 @@ pub fn transpile_enum(decl: &TsEnumDecl) -> Result<String> {
             TsEnumMemberId::Ident(ident) => ident.sym.to_string(),
             TsEnumMemberId::Str(s) => s.value.to_string_lossy().into_owned(),
         };
+        names.push(variant_name.clone());
 
         let discriminant = match &member.init {
             Some(init) => match &**init {
@@ -37,20 +45,41 @@ pub fn transpile_enum(decl: &TsEnumDecl) -> Result<String> {
         }
     }
 
+    // A derived `Default` is the first variant.
+    if derives.iter().any(|derive| derive == "Default") {
+        if let Some(first) = variants.first_mut() {
+            *first = format!("    #[default]\n{}", first);
+        }
+    }
     let enum_def = format!(
-        "#[derive(Debug, Clone)]\nenum {} {{\n{},\n}}",
+        "#[derive({})]\nenum {} {{\n{},\n}}",
+        derives.join(", "),
         name,
         variants.join(",\n")
     );
 
+    let enum_def = if utilities {
+        format!(
+            "{}\n\n{}",
+            enum_def,
+            utility_impl(name, &names, &string_values)
+        )
+    } else {
+        enum_def
+    };
+
     if string_values.is_empty() {
-        return Ok(enum_def);
+        // Plain enums print their variant name.
+        return Ok(format!(
+            "{}\n\nimpl std::fmt::Display for {} {{\n    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {{\n        write!(f, \"{{:?}}\", self)\n    }}\n}}",
+            enum_def, name
+        ));
     }
 
     // Generate as_str() and Display impl for string enums
     let as_str_arms: String = string_values
         .iter()
-        .map(|(v, s)| format!("            {}::{} => \"{}\"", name, v, s))
+        .map(|(v, s)| format!("            {}::{} => {}", name, v, rust_string_literal(s)))
         .collect::<Vec<_>>()
         .join(",\n");
 
@@ -61,3 +90,32 @@ pub fn transpile_enum(decl: &TsEnumDecl) -> Result<String> {
 
     Ok(format!("{}\n\n{}", enum_def, impl_block))
 }
+
+/// `values()`, `fromString(s)` and `count()`. `fromString` of a string enum parses its values
+/// (`"red"` → `Color::Red`), otherwise the variant names.
+fn utility_impl(name: &str, variants: &[String], string_values: &[(String, String)]) -> String {
+    let values = variants
+        .iter()
+        .map(|v| format!("{}::{}", name, v))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let from_string_arms: String = variants
+        .iter()
+        .map(|v| {
+            let text = string_values
+                .iter()
+                .find(|(variant, _)| variant == v)
+                .map_or(v.as_str(), |(_, s)| s.as_str());
+            format!(
+                "            {} => Some({}::{}),\n",
+                rust_string_literal(text),
+                name,
+                v
+            )
+        })
+        .collect();
+    format!(
+        "#[allow(non_snake_case, dead_code)]\nimpl {name} {{\n    pub fn values() -> Vec<{name}> {{\n        vec![{values}]\n    }}\n\n    pub fn fromString(s: &str) -> Option<{name}> {{\n        match s {{\n{from_string_arms}            _ => None,\n        }}\n    }}\n\n    pub fn count() -> i32 {{\n        {count}\n    }}\n}}",
+        count = variants.len(),
+    )
+}
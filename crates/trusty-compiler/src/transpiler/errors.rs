@@ -0,0 +1,332 @@
+//! Thrown values and the generated `TrustError` enum.
+//!
+//! `throw "msg"` stays a `String` error. Throwing a struct or enum value (`throw NetworkError({ ... })`)
+//! inside a `try` with a typed catch, or in a function returning `Result<T, TrustError>`, wraps it in a
+//! `TrustError` variant named after its type, so one `Result` can carry every thrown type.
+
+use super::expressions::{infer_rust_type, transpile_expression};
+use super::scope::{result_error_type, Scope};
+use super::statements::transpile_statement;
+use super::strings::format_string_text;
+use super::symbols::ProgramSymbols;
+use super::types::transpile_type_annotation;
+use crate::diagnostics::Diagnostic;
+use crate::messages::msg;
+use crate::parser::source_span;
+use anyhow::{bail, Result};
+use swc_ecma_ast::*;
+
+pub const TRUST_ERROR: &str = "TrustError";
+
+/// Preprocessor marker for `try { ... } catch (e) { ... }` used as an expression.
+pub const TRY_CALLEE: &str = "__trust_try";
+
+/// Variant holding `throw "msg"` / `throw new Error("msg")` in a `TrustError`.
+const MESSAGE_VARIANT: &str = "Message";
+
+/// `throw value` → `return Err(...)`, shaped for the error type in scope.
+pub fn transpile_throw(throw_stmt: &ThrowStmt, scope: &Scope) -> Result<String> {
+    if scope.error_type().is_none() {
+        return Err(
+            Diagnostic::error("throw-outside-result", msg!("throw-outside-result"))
+                .with_span(source_span(throw_stmt.span))
+                .into(),
+        );
+    }
+    let arg = &*throw_stmt.arg;
+    let error = match (thrown_type(arg, scope), scope.error_type()) {
+        (Some(ty), Some(target)) if ty == target => transpile_expression(arg, scope)?,
+        (Some(ty), Some(TRUST_ERROR)) => format!(
+            "{}::{}({})",
+            TRUST_ERROR,
+            ty,
+            transpile_expression(arg, scope)?
+        ),
+        (_, Some(TRUST_ERROR)) => format!(
+            "{}::{}({})",
+            TRUST_ERROR,
+            MESSAGE_VARIANT,
+            throw_message(throw_stmt, scope)?
+        ),
+        _ => throw_message(throw_stmt, scope)?,
+    };
+    Ok(format!("return Err({});", error))
+}
+
+/// `throw new Error("msg")` → `"msg"`; anything else is rendered as is. With location tracking the
+/// message ends with the TRUST location of the `throw`.
+fn throw_message(throw_stmt: &ThrowStmt, scope: &Scope) -> Result<String> {
+    let message = match &*throw_stmt.arg {
+        Expr::New(new_expr) => match new_expr.args.as_deref().and_then(|args| args.first()) {
+            Some(first) => transpile_expression(&first.expr, scope)?,
+            None => "\"error\".to_string()".to_string(),
+        },
+        other => transpile_expression(other, scope)?,
+    };
+    if !scope.options().track_locations {
+        return Ok(message);
+    }
+    Ok(format!(
+        "format!(\"{{}} ({})\", {})",
+        format_string_text(&format!("at {}", scope.location(throw_stmt.span))),
+        message
+    ))
+}
+
+/// Declared struct or enum type of a thrown value, or `TrustError` when rethrowing one.
+fn thrown_type(arg: &Expr, scope: &Scope) -> Option<String> {
+    let ty = infer_rust_type(arg, scope)?;
+    let symbols = scope.symbols();
+    (ty == TRUST_ERROR || symbols.is_struct(&ty) || symbols.enum_variants(&ty).is_some())
+        .then_some(ty)
+}
+
+/// True if a `try` block needs `TrustError`: its catch names a type, or its body throws a struct or enum.
+pub fn try_uses_typed_errors(try_stmt: &TryStmt, scope: &Scope) -> bool {
+    let catch_param = try_stmt
+        .handler
+        .as_ref()
+        .and_then(|handler| handler.param.as_ref());
+    uses_typed_errors(&try_stmt.block.stmts, catch_param, scope)
+}
+
+fn uses_typed_errors(stmts: &[Stmt], catch_param: Option<&Pat>, scope: &Scope) -> bool {
+    let typed_catch = matches!(catch_param, Some(Pat::Ident(ident)) if ident.type_ann.is_some());
+    typed_catch || stmts.iter().any(|stmt| throws_typed_value(stmt, scope))
+}
+
+fn throws_typed_value(stmt: &Stmt, scope: &Scope) -> bool {
+    match stmt {
+        Stmt::Throw(throw_stmt) => thrown_type(&throw_stmt.arg, scope).is_some(),
+        Stmt::Block(block) => block.stmts.iter().any(|s| throws_typed_value(s, scope)),
+        Stmt::If(if_stmt) => {
+            throws_typed_value(&if_stmt.cons, scope)
+                || if_stmt
+                    .alt
+                    .as_deref()
+                    .is_some_and(|alt| throws_typed_value(alt, scope))
+        }
+        Stmt::While(while_stmt) => throws_typed_value(&while_stmt.body, scope),
+        Stmt::DoWhile(do_while) => throws_typed_value(&do_while.body, scope),
+        Stmt::For(for_stmt) => throws_typed_value(&for_stmt.body, scope),
+        Stmt::ForIn(for_in) => throws_typed_value(&for_in.body, scope),
+        Stmt::ForOf(for_of) => throws_typed_value(&for_of.body, scope),
+        Stmt::Labeled(labeled) => throws_typed_value(&labeled.body, scope),
+        _ => false,
+    }
+}
+
+/// `__trust_try(() => { ...; value }, (e) => { ...; fallback })` → a `match` on a closure returning
+/// `Result<T, E>`. The block's last expression is its value; a `Result` there is unwrapped with `?`.
+pub fn transpile_try_expression(call: &CallExpr, scope: &Scope) -> Result<String> {
+    let (Some(body), Some(handler)) = (call.args.first(), call.args.get(1)) else {
+        bail!("`try` expression needs a `catch` block");
+    };
+    let (Expr::Arrow(body), Expr::Arrow(handler)) = (&*body.expr, &*handler.expr) else {
+        bail!("malformed `try` expression");
+    };
+    let (BlockStmtOrExpr::BlockStmt(body), BlockStmtOrExpr::BlockStmt(handler_body)) =
+        (&*body.body, &*handler.body)
+    else {
+        bail!("malformed `try` expression");
+    };
+    let catch_param = handler.params.first();
+    let error_type = if uses_typed_errors(&body.stmts, catch_param, scope) {
+        TRUST_ERROR.to_string()
+    } else {
+        block_tail(&body.stmts)
+            .and_then(|tail| infer_rust_type(tail, scope))
+            .and_then(|ty| result_error_type(&ty).map(str::to_string))
+            .unwrap_or_else(|| "String".to_string())
+    };
+
+    let mut body_scope = scope.clone();
+    body_scope.push();
+    body_scope.set_error_type(Some(error_type.clone()));
+    let (stmts, tail) = transpile_valued_block(&body.stmts, &mut body_scope)?;
+    let tail_ty = block_tail(&body.stmts).and_then(|tail| infer_rust_type(tail, &body_scope));
+    let tail_is_result = tail_ty
+        .as_deref()
+        .is_some_and(|ty| result_error_type(ty).is_some());
+    let value_ty = match tail_ty.as_deref() {
+        Some(ty) if tail_is_result => ty
+            .strip_prefix("Result<")
+            .and_then(|inner| {
+                inner.strip_suffix(&format!(", {}>", result_error_type(ty).unwrap_or_default()))
+            })
+            .unwrap_or("_")
+            .to_string(),
+        Some(ty) => ty.to_string(),
+        None => "_".to_string(),
+    };
+    let value = match tail {
+        Some(tail) if tail_is_result => format!("Ok({}?)", tail),
+        Some(tail) => format!("Ok({})", tail),
+        None => "Ok(())".to_string(),
+    };
+
+    let (catch_name, catch_type) = match catch_param {
+        Some(Pat::Ident(ident)) => (
+            ident.id.sym.to_string(),
+            ident.type_ann.as_deref().map(transpile_type_annotation),
+        ),
+        _ => ("_err".to_string(), None),
+    };
+    let mut handler_scope = scope.clone();
+    handler_scope.push();
+    handler_scope.insert(
+        catch_name.clone(),
+        catch_type.clone().unwrap_or_else(|| error_type.clone()),
+    );
+    let (handler_stmts, handler_tail) =
+        transpile_valued_block(&handler_body.stmts, &mut handler_scope)?;
+    let fallback = block_expression(&handler_stmts, handler_tail);
+
+    let mut out = format!(
+        "match (|| -> Result<{}, {}> {{\n{}    {}\n}})() {{\n    Ok(__trust_value) => __trust_value,\n",
+        value_ty,
+        error_type,
+        stmts.iter().map(|s| format!("    {}\n", s)).collect::<String>(),
+        value
+    );
+    match &catch_type {
+        Some(ty) => {
+            let rethrow = if scope.error_type() == Some(TRUST_ERROR) {
+                "return Err(__trust_error)"
+            } else {
+                "panic!(\"{}\", __trust_error)"
+            };
+            out.push_str(&format!(
+                "    Err({}::{}({})) => {},\n    Err(__trust_error) => {},\n}}",
+                TRUST_ERROR,
+                catch_variant(ty),
+                catch_name,
+                fallback,
+                rethrow
+            ));
+        }
+        None => out.push_str(&format!("    Err({}) => {},\n}}", catch_name, fallback)),
+    }
+    Ok(out)
+}
+
+/// Value type of a `try` expression: its block's last expression, unwrapped if it is a `Result`.
+pub fn infer_try_expression_type(args: &[ExprOrSpread], scope: &Scope) -> Option<String> {
+    let Expr::Arrow(body) = &*args.first()?.expr else {
+        return None;
+    };
+    let BlockStmtOrExpr::BlockStmt(block) = &*body.body else {
+        return None;
+    };
+    let ty = infer_rust_type(block_tail(&block.stmts)?, scope)?;
+    match result_error_type(&ty) {
+        Some(error) => ty
+            .strip_prefix("Result<")?
+            .strip_suffix(&format!(", {}>", error))
+            .map(str::to_string),
+        None => Some(ty),
+    }
+}
+
+/// Last statement of a block when it is a bare expression (the block's value).
+fn block_tail(stmts: &[Stmt]) -> Option<&Expr> {
+    match stmts.last()? {
+        Stmt::Expr(expr_stmt) => Some(&expr_stmt.expr),
+        _ => None,
+    }
+}
+
+/// Transpiles a block's statements, returning the last bare expression separately as its value.
+fn transpile_valued_block(
+    stmts: &[Stmt],
+    scope: &mut Scope,
+) -> Result<(Vec<String>, Option<String>)> {
+    let (init, tail) = match block_tail(stmts) {
+        Some(tail) => (&stmts[..stmts.len() - 1], Some(tail)),
+        None => (stmts, None),
+    };
+    let rendered = init
+        .iter()
+        .map(|stmt| transpile_statement(stmt, scope))
+        .collect::<Result<Vec<_>>>()?;
+    let tail = tail
+        .map(|tail| transpile_expression(tail, scope))
+        .transpose()?;
+    Ok((rendered, tail))
+}
+
+/// `{ stmts; value }`, or just `value` when there are no statements.
+fn block_expression(stmts: &[String], value: Option<String>) -> String {
+    let value = value.unwrap_or_else(|| "()".to_string());
+    if stmts.is_empty() {
+        return value;
+    }
+    format!(
+        "{{\n{}        {}\n    }}",
+        stmts
+            .iter()
+            .map(|s| format!("        {}\n", s))
+            .collect::<String>(),
+        value
+    )
+}
+
+/// `TrustError` variant matched by `catch (e: T)`: `Message` for `string`, else the type's name.
+pub fn catch_variant(rust_type: &str) -> &str {
+    if rust_type == "String" {
+        MESSAGE_VARIANT
+    } else {
+        rust_type
+    }
+}
+
+/// The `TrustError` enum, with a variant for every `TrustError::Name` used in `code`; `None` when unused.
+pub fn trust_error_enum(code: &str, symbols: &ProgramSymbols) -> Option<String> {
+    if !code.contains(TRUST_ERROR) {
+        return None;
+    }
+    let prefix = format!("{}::", TRUST_ERROR);
+    let mut variants: Vec<&str> = Vec::new();
+    for (pos, _) in code.match_indices(&prefix) {
+        let rest = &code[pos + prefix.len()..];
+        let name = &rest[..rest
+            .find(|c: char| !c.is_alphanumeric() && c != '_')
+            .unwrap_or(rest.len())];
+        let declared = symbols.is_struct(name) || symbols.enum_variants(name).is_some();
+        if declared && !variants.contains(&name) {
+            variants.push(name);
+        }
+    }
+
+    let mut defs = vec![format!("    {}(String)", MESSAGE_VARIANT)];
+    let mut arms = vec![format!(
+        "            {}::{}(message) => write!(f, \"{{}}\", message)",
+        TRUST_ERROR, MESSAGE_VARIANT
+    )];
+    // `?` converts any thrown type into a `TrustError`.
+    let mut conversions = vec![from_impl("String", MESSAGE_VARIANT)];
+    for variant in variants {
+        defs.push(format!("    {}({})", variant, variant));
+        arms.push(format!(
+            "            {}::{}(error) => write!(f, \"{{:?}}\", error)",
+            TRUST_ERROR, variant
+        ));
+        conversions.push(from_impl(variant, variant));
+    }
+    Some(format!(
+        "#[derive(Debug, Clone)]\nenum {name} {{\n{},\n}}\n\nimpl std::fmt::Display for {name} {{\n    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {{\n        match self {{\n{},\n        }}\n    }}\n}}\n\n{}",
+        defs.join(",\n"),
+        arms.join(",\n"),
+        conversions.join("\n\n"),
+        name = TRUST_ERROR
+    ))
+}
+
+fn from_impl(source_type: &str, variant: &str) -> String {
+    format!(
+        "impl From<{source}> for {name} {{\n    fn from(error: {source}) -> Self {{\n        {name}::{variant}(error)\n    }}\n}}",
+        source = source_type,
+        name = TRUST_ERROR,
+        variant = variant
+    )
+}
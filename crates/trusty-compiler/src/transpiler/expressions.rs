@@ -1,15 +1,83 @@
-use super::scope::{is_module_alias_binding, is_pointer, is_threaded, Scope};
+use super::arrays;
+use super::bytes::{self, BYTES, BYTES_CALLEE, HEX_CALLEE};
+use super::decimal::{self, DECIMAL, DECIMAL_CALLEE};
+use super::enums::UTILITY_METHODS;
+use super::errors::{infer_try_expression_type, transpile_try_expression, TRY_CALLEE};
+use super::iterators;
+use super::matching::{infer_match_type, transpile_match, MATCH_CALLEE};
+use super::nullish;
+use super::overflow::Overflow;
+use super::ownership::{is_owned_type, AutoClone};
+use super::scope::{
+    is_module_alias_binding, is_pointer, is_threaded, is_weak_pointer, referent_type, Scope,
+};
 use super::statements::transpile_block_stmt;
-use super::types::transpile_type;
+use super::strings::{self, format_string_text, rust_string_literal, STRING_BUILDER};
+use super::symbols::FunctionSig;
+use super::tasks;
+use super::types::{check_annotation, transpile_type, transpile_type_annotation};
+use crate::diagnostics::Diagnostic;
+use crate::messages::msg;
+use crate::parser::source_span;
+use crate::stdlib;
+use crate::stdlib::color as stdlib_color;
+use crate::stdlib::http as stdlib_http;
+use crate::stdlib::math as stdlib_math;
+use crate::stdlib::rand as stdlib_rand;
 use crate::stdlib::time as stdlib_time;
 use anyhow::Result;
+use swc_common::Spanned;
 use swc_ecma_ast::*;
 
+/// Preprocessor markers for `start..end` / `start..=end` in `for` headers.
+pub const RANGE_CALLEE: &str = "__trust_range";
+pub const RANGE_INCLUSIVE_CALLEE: &str = "__trust_range_inclusive";
+
 pub fn transpile_expression(expr: &Expr, scope: &Scope) -> Result<String> {
     match expr {
+        Expr::Bin(bin_expr) if bin_expr.op == BinaryOp::NullishCoalescing => {
+            nullish::transpile_coalesce(bin_expr, scope)
+        }
         Expr::Bin(bin_expr) => {
-            let left = transpile_expression(&bin_expr.left, scope)?;
-            let right = transpile_expression(&bin_expr.right, scope)?;
+            let left = binding_value(
+                &bin_expr.left,
+                transpile_expression(&bin_expr.left, scope)?,
+                scope,
+            );
+            let right = binding_value(
+                &bin_expr.right,
+                transpile_expression(&bin_expr.right, scope)?,
+                scope,
+            );
+            if matches!(
+                bin_expr.op,
+                BinaryOp::EqEq | BinaryOp::EqEqEq | BinaryOp::NotEq | BinaryOp::NotEqEq
+            ) {
+                // Struct and enum operands get a derived `PartialEq`; through `Pointer<T>`, `Rc`'s
+                // `==` compares the values inside.
+                let operand_ty = infer_rust_type(&bin_expr.left, scope)
+                    .or_else(|| infer_rust_type(&bin_expr.right, scope));
+                if let Some(ty) = operand_ty {
+                    scope.record_comparison(&ty);
+                }
+            }
+            let (left, right) = decimal::convert_operands(bin_expr, left, right, scope)?;
+            let overflow = scope.options().overflow;
+            if overflow != Overflow::Wrap {
+                let ty = infer_binary_type(bin_expr, scope).unwrap_or_default();
+                if let Some(lowered) = overflow.lower(bin_expr.op, &left, &right, &ty) {
+                    return Ok(lowered);
+                }
+            }
+            // `(a + 1) * 3`: parentheses the source groups an operand with are kept.
+            let grouped = |expr: &Expr, rendered: String| match expr {
+                Expr::Paren(_) => format!("({})", rendered),
+                _ => rendered,
+            };
+            let (left, right) = (
+                grouped(&bin_expr.left, left),
+                grouped(&bin_expr.right, right),
+            );
             match bin_expr.op {
                 BinaryOp::Add => Ok(format!("{} + {}", left, right)),
                 BinaryOp::Sub => Ok(format!("{} - {}", left, right)),
@@ -24,11 +92,21 @@ pub fn transpile_expression(expr: &Expr, scope: &Scope) -> Result<String> {
                 BinaryOp::NotEq | BinaryOp::NotEqEq => Ok(format!("{} != {}", left, right)),
                 BinaryOp::LogicalAnd => Ok(format!("{} && {}", left, right)),
                 BinaryOp::LogicalOr => Ok(format!("{} || {}", left, right)),
-                BinaryOp::Exp => transpile_exponentiation(&bin_expr.left, &bin_expr.right, &left, &right, scope),
-                _ => Ok("?".to_string()),
+                BinaryOp::Exp => {
+                    transpile_exponentiation(&bin_expr.left, &bin_expr.right, &left, &right, scope)
+                }
+                op => Err(Diagnostic::error(
+                    "unsupported-operator",
+                    msg!("unsupported-operator", op.as_str()),
+                )
+                .with_span(source_span(bin_expr.span))
+                .into()),
             }
         }
-        Expr::Ident(ident) => Ok(ident.sym.to_string()),
+        Expr::Ident(ident) => Ok(match scope.static_var(ident.sym.as_ref()) {
+            Some(var) => var.read(ident.sym.as_ref()),
+            None => ident.sym.to_string(),
+        }),
         Expr::This(_) => Ok("self".to_string()),
         Expr::Lit(lit) => match lit {
             Lit::Num(num) => Ok(num.value.to_string()),
@@ -38,10 +116,13 @@ pub fn transpile_expression(expr: &Expr, scope: &Scope) -> Result<String> {
             )),
             Lit::Bool(b) => Ok(b.value.to_string()),
             Lit::Null(_) => Ok("None".to_string()),
-            _ => Ok("unknown_literal".to_string()),
+            _ => unsupported_expression(lit.span(), "unknown_literal", scope),
         },
         Expr::Tpl(tpl) => transpile_template_literal(tpl, scope),
-        Expr::Call(call) => transpile_call_expression(call, scope),
+        Expr::Call(call) => match static_read(expr, scope)? {
+            Some(read) => Ok(read),
+            None => transpile_call_expression(call, scope),
+        },
         Expr::Array(array_lit) => {
             let elems: Result<Vec<String>> = array_lit
                 .elems
@@ -57,15 +138,39 @@ pub fn transpile_expression(expr: &Expr, scope: &Scope) -> Result<String> {
             let alt = transpile_expression(&cond.alt, scope)?;
             Ok(format!("if {} {{ {} }} else {{ {} }}", test, cons, alt))
         }
-        Expr::Member(member) => transpile_member_access(member, scope),
+        Expr::Member(member) => match static_read(expr, scope)? {
+            Some(read) => Ok(read),
+            None => transpile_member_access(member, scope),
+        },
+        Expr::OptChain(chain) => nullish::transpile_opt_chain(chain, scope),
         Expr::Assign(assign) => transpile_assign(assign, scope),
-        Expr::Arrow(arrow) => transpile_arrow(arrow, scope),
+        Expr::Arrow(arrow) => transpile_arrow(arrow, &[], scope),
         Expr::Fn(fn_expr) => transpile_fn_expr(fn_expr, scope),
         Expr::Await(await_expr) => {
             let awaited = transpile_expression(&await_expr.arg, scope)?;
             Ok(format!("({}).join().unwrap()", awaited))
         }
         Expr::Paren(paren) => transpile_expression(&paren.expr, scope),
+        Expr::Unary(unary)
+            if matches!(
+                unary.op,
+                UnaryOp::Minus | UnaryOp::Plus | UnaryOp::Bang | UnaryOp::Tilde
+            ) =>
+        {
+            let arg = transpile_expression(&unary.arg, scope)?;
+            // `-(a + b)`, `!(a && b)`: a compound operand keeps its parentheses.
+            let arg = match &*unary.arg {
+                Expr::Paren(_) | Expr::Bin(_) | Expr::Cond(_) | Expr::Assign(_) => {
+                    format!("({})", arg)
+                }
+                _ => arg,
+            };
+            Ok(match unary.op {
+                UnaryOp::Minus => format!("-{}", arg),
+                UnaryOp::Bang | UnaryOp::Tilde => format!("!{}", arg),
+                _ => arg,
+            })
+        }
         Expr::Object(obj) => transpile_object_literal(obj, scope),
         Expr::New(new_expr) => {
             if let Expr::Ident(ident) = &*new_expr.callee {
@@ -75,9 +180,48 @@ pub fn transpile_expression(expr: &Expr, scope: &Scope) -> Result<String> {
                     _ => {}
                 }
             }
-            Ok("unknown_new".to_string())
+            unsupported_expression(new_expr.span, "unknown_new", scope)
+        }
+        _ => unsupported_expression(expr.span(), "unknown_expr", scope),
+    }
+}
+
+/// Reports that the expression at `span` has no Rust translation; with `--allow-unknown`,
+/// `placeholder` stands in for it.
+fn unsupported_expression(
+    span: swc_common::Span,
+    placeholder: &str,
+    scope: &Scope,
+) -> Result<String> {
+    scope.unsupported(
+        "unsupported-expression",
+        msg!("unsupported-expression", scope.snippet(span)),
+        span,
+    )?;
+    Ok(placeholder.to_string())
+}
+
+/// Name bound by `pat`. Destructuring is reported as unsupported; with `--allow-unknown` the
+/// pattern binds `placeholder`.
+pub(super) fn binding_name(pat: &Pat, placeholder: &str, scope: &Scope) -> Result<String> {
+    match pat {
+        Pat::Ident(ident) => Ok(ident.id.sym.to_string()),
+        _ => {
+            scope.unsupported(
+                "unsupported-pattern",
+                msg!("unsupported-pattern", scope.snippet(pat.span())),
+                pat.span(),
+            )?;
+            Ok(placeholder.to_string())
         }
-        _ => Ok("unknown_expr".to_string()),
+    }
+}
+
+/// Field or method name of `obj.name`; `obj.#name` is reported as unsupported.
+fn member_name(member: &MemberExpr, scope: &Scope) -> Result<String> {
+    match &member.prop {
+        MemberProp::Ident(ident) => Ok(ident.sym.to_string()),
+        prop => unsupported_expression(prop.span(), "unknown", scope),
     }
 }
 
@@ -107,9 +251,13 @@ fn transpile_exponentiation(
     Ok(out)
 }
 
-fn infer_rust_type(expr: &Expr, scope: &Scope) -> Option<String> {
+/// Best-effort Rust type of `expr`, from literals, scope bindings and the program's declarations.
+///
+/// Used to type unannotated `val`/`var` bindings so later member calls pick the right lowering.
+pub fn infer_rust_type(expr: &Expr, scope: &Scope) -> Option<String> {
     match expr {
-        Expr::Ident(ident) => scope.get(&ident.sym.to_string()).cloned(),
+        Expr::Ident(ident) => scope.get(ident.sym.as_ref()).cloned(),
+        Expr::This(_) => scope.get("self").cloned(),
         Expr::Lit(Lit::Num(n)) => {
             if n.value.fract() == 0.0 {
                 Some("i32".to_string())
@@ -117,18 +265,44 @@ fn infer_rust_type(expr: &Expr, scope: &Scope) -> Option<String> {
                 Some("f64".to_string())
             }
         }
+        Expr::Lit(Lit::Str(_)) | Expr::Tpl(_) => Some("String".to_string()),
+        Expr::Lit(Lit::Bool(_)) => Some("bool".to_string()),
         Expr::Paren(paren) => infer_rust_type(&paren.expr, scope),
+        Expr::Unary(unary) => match unary.op {
+            UnaryOp::Minus | UnaryOp::Plus | UnaryOp::Tilde => infer_rust_type(&unary.arg, scope),
+            UnaryOp::Bang => Some("bool".to_string()),
+            _ => None,
+        },
+        Expr::Bin(bin) if bin.op == BinaryOp::NullishCoalescing => {
+            nullish::coalesce_type(bin, scope)
+        }
+        Expr::Bin(bin) => infer_binary_type(bin, scope),
+        Expr::OptChain(chain) => nullish::opt_chain_type(chain, scope),
+        Expr::Cond(cond) => {
+            infer_rust_type(&cond.cons, scope).or_else(|| infer_rust_type(&cond.alt, scope))
+        }
+        Expr::Array(array_lit) => {
+            let first = array_lit.elems.iter().flatten().next()?;
+            infer_rust_type(&first.expr, scope).map(|elem| format!("Vec<{}>", elem))
+        }
+        Expr::Object(obj) => scope.symbols().object_struct(obj.span).cloned(),
+        Expr::New(new_expr) => match &*new_expr.callee {
+            Expr::Ident(ident) if scope.symbols().is_struct(ident.sym.as_ref()) => {
+                Some(ident.sym.to_string())
+            }
+            _ => None,
+        },
+        Expr::Await(await_expr) => {
+            let task = infer_rust_type(&await_expr.arg, scope)?;
+            tasks::task_result_type(&task).map(str::to_string)
+        }
+        Expr::Member(member) => infer_member_type(member, scope),
         Expr::Call(call) => match &call.callee {
             Callee::Expr(callee) => match &**callee {
-                Expr::Ident(ident) => match ident.sym.as_ref() {
-                    "int8" => Some("i8".to_string()),
-                    "int16" => Some("i16".to_string()),
-                    "int32" | "int" | "number" | "number32" => Some("i32".to_string()),
-                    "int64" | "number64" => Some("i64".to_string()),
-                    "float32" => Some("f32".to_string()),
-                    "float64" | "float" => Some("f64".to_string()),
-                    _ => None,
-                },
+                Expr::Ident(ident) => {
+                    infer_function_call_type(ident.sym.as_ref(), &call.args, scope)
+                }
+                Expr::Member(member) => infer_method_call_type(member, &call.args, scope),
                 _ => None,
             },
             _ => None,
@@ -137,113 +311,724 @@ fn infer_rust_type(expr: &Expr, scope: &Scope) -> Option<String> {
     }
 }
 
+fn infer_binary_type(bin: &BinExpr, scope: &Scope) -> Option<String> {
+    match bin.op {
+        BinaryOp::Lt
+        | BinaryOp::LtEq
+        | BinaryOp::Gt
+        | BinaryOp::GtEq
+        | BinaryOp::EqEq
+        | BinaryOp::EqEqEq
+        | BinaryOp::NotEq
+        | BinaryOp::NotEqEq
+        | BinaryOp::LogicalAnd
+        | BinaryOp::LogicalOr => Some("bool".to_string()),
+        BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => {
+            let left = infer_rust_type(&bin.left, scope);
+            let right = infer_rust_type(&bin.right, scope);
+            match (left.as_deref(), right.as_deref()) {
+                (Some("String"), _) if bin.op == BinaryOp::Add => Some("String".to_string()),
+                (Some(l), Some(r)) if l == r => left,
+                (Some(DECIMAL), Some(other)) | (Some(other), Some(DECIMAL))
+                    if is_numeric_rust_type(other) =>
+                {
+                    Some(DECIMAL.to_string())
+                }
+                (Some("f64"), Some(r)) | (Some(r), Some("f64")) if is_numeric_rust_type(r) => {
+                    Some("f64".to_string())
+                }
+                (Some(l), None) if is_numeric_rust_type(l) => left,
+                (None, Some(r)) if is_numeric_rust_type(r) => right,
+                _ => None,
+            }
+        }
+        BinaryOp::Exp => match infer_rust_type(&bin.left, scope).as_deref() {
+            Some(t) if is_numeric_rust_type(t) && !t.starts_with('f') => Some(t.to_string()),
+            Some("f32") => Some("f32".to_string()),
+            _ => Some("f64".to_string()),
+        },
+        _ => None,
+    }
+}
+
+fn infer_function_call_type(
+    func_name: &str,
+    args: &[ExprOrSpread],
+    scope: &Scope,
+) -> Option<String> {
+    if func_name == MATCH_CALLEE {
+        return infer_match_type(args, scope);
+    }
+    if func_name == TRY_CALLEE {
+        return infer_try_expression_type(args, scope);
+    }
+    if func_name == DECIMAL_CALLEE {
+        return Some(DECIMAL.to_string());
+    }
+    if func_name == BYTES_CALLEE || func_name == HEX_CALLEE {
+        return Some(BYTES.to_string());
+    }
+    if func_name == strings::RAW_CALLEE {
+        return Some("String".to_string());
+    }
+    if func_name == RANGE_CALLEE || func_name == RANGE_INCLUSIVE_CALLEE {
+        let ty = range_element_type(&args[0].expr, &args[1].expr, scope);
+        return Some(if func_name == RANGE_CALLEE {
+            format!("std::ops::Range<{}>", ty)
+        } else {
+            format!("std::ops::RangeInclusive<{}>", ty)
+        });
+    }
+    if let Some(sig) = scope.symbols().function(func_name) {
+        return Some(sig.return_type.clone());
+    }
+    // Call-style struct constructor: `Point({ x: 1, y: 2 })`
+    if scope.symbols().is_struct(func_name)
+        && args.len() == 1
+        && matches!(&*args[0].expr, Expr::Object(_))
+    {
+        return Some(func_name.to_string());
+    }
+    if let ("iter", [arg]) = (func_name, args) {
+        return Some(iterators::iter_call_type(&arg.expr, scope));
+    }
+    if let Some(ty) = tasks::call_type(func_name, args, scope) {
+        return Some(ty);
+    }
+    if scope.symbols().function(func_name).is_none() && args.len() == 1 {
+        let arg_ty = infer_rust_type(&args[0].expr, scope);
+        match (func_name, arg_ty.as_deref()) {
+            ("downgrade", Some(t)) if is_pointer(t) => {
+                return Some(format!("Weak{}", &t["Rc".len()..]))
+            }
+            ("upgrade", Some(t)) if is_weak_pointer(t) => {
+                return Some(format!("Option<Rc{}>", &t["Weak".len()..]))
+            }
+            _ => {}
+        }
+    }
+    let cast = match func_name {
+        "int8" => Some("i8"),
+        "int16" => Some("i16"),
+        "int32" | "int" | "number" | "number32" => Some("i32"),
+        "int64" | "number64" => Some("i64"),
+        "float32" => Some("f32"),
+        "float64" | "float" => Some("f64"),
+        "decimal" => Some(DECIMAL),
+        "string" => Some("String"),
+        "boolean" => Some("bool"),
+        _ => None,
+    };
+    if let Some(ty) = cast {
+        return Some(ty.to_string());
+    }
+    match func_name {
+        "abs" | "min" | "max" | "clamp" | "sign" | "gcd" | "lcm" | "shuffle" => {
+            args.first().and_then(|a| infer_rust_type(&a.expr, scope))
+        }
+        "chooseOne" => args
+            .first()
+            .and_then(|a| infer_rust_type(&a.expr, scope))
+            .and_then(|ty| choose_one_type(&ty)),
+        _ => stdlib_math::function_return_type(func_name)
+            .or_else(|| stdlib_rand::function_return_type(func_name))
+            .or_else(|| stdlib_time::function_return_type(func_name))
+            .or_else(|| stdlib_http::function_return_type(func_name))
+            .or_else(|| stdlib_color::function_return_type(func_name))
+            .map(str::to_string),
+    }
+}
+
+/// `chooseOne(items)` picks an element, if there is one.
+fn choose_one_type(items: &str) -> Option<String> {
+    let element = items.strip_prefix("Vec<")?.strip_suffix('>')?;
+    Some(format!("Option<{}>", element))
+}
+
+fn infer_member_type(member: &MemberExpr, scope: &Scope) -> Option<String> {
+    if let Some((enum_name, _)) = enum_variant(member, scope) {
+        return Some(enum_name.to_string());
+    }
+    let obj_ty = infer_rust_type(&member.obj, scope);
+    if let MemberProp::Computed(_) = &member.prop {
+        let obj_ty = obj_ty?;
+        return obj_ty
+            .strip_prefix("Vec<")
+            .and_then(|t| t.strip_suffix('>'))
+            .map(str::to_string);
+    }
+    let MemberProp::Ident(prop) = &member.prop else {
+        return None;
+    };
+    let obj_ty = obj_ty?;
+    if prop.sym.as_ref() == "length" {
+        return Some("i32".to_string());
+    }
+    let struct_name = struct_of(&obj_ty);
+    if scope.symbols().is_struct(struct_name) {
+        return scope
+            .symbols()
+            .field_type(struct_name, prop.sym.as_ref())
+            .cloned();
+    }
+    // A field of a stdlib struct (`options.headers`).
+    stdlib::field_type(struct_name, prop.sym.as_ref())
+}
+
+fn infer_method_call_type(
+    member: &MemberExpr,
+    args: &[ExprOrSpread],
+    scope: &Scope,
+) -> Option<String> {
+    let MemberProp::Ident(prop) = &member.prop else {
+        return None;
+    };
+    let method = prop.sym.as_ref();
+
+    if let Expr::Ident(obj) = &*member.obj {
+        let obj_name = obj.sym.as_ref();
+        if obj_name == "console" && method == "read" {
+            return Some("String".to_string());
+        }
+        // `math.sqrt(x)` through a module alias.
+        if scope
+            .get(obj_name)
+            .map(|t| is_module_alias_binding(t))
+            .unwrap_or(false)
+        {
+            return infer_function_call_type(method, args, scope);
+        }
+        if let Some(ty) = stdlib_time::static_method_return_type(obj_name, method) {
+            return Some(ty);
+        }
+        // `Date.fromYmd(...)`: a stdlib type the program does not shadow.
+        if scope.get(obj_name).is_none() && !scope.symbols().is_struct(obj_name) {
+            if let Some(ty) = stdlib::method_return_type(obj_name, method, false) {
+                return Some(ty);
+            }
+        }
+        if obj_name == STRING_BUILDER && method == "new" && strings::is_builtin(scope) {
+            return Some(STRING_BUILDER.to_string());
+        }
+        if obj_name == "bytes" && method == "fromString" && scope.get(obj_name).is_none() {
+            return Some(BYTES.to_string());
+        }
+        if obj_name == "Rng" && scope.get(obj_name).is_none() {
+            if let Some(ty) = stdlib_rand::method_return_type(obj_name, method) {
+                return Some(ty.to_string());
+            }
+        }
+        if let Some(enum_name) = enum_utility(member, scope) {
+            return match method {
+                "values" => Some(format!("Vec<{}>", enum_name)),
+                "fromString" => Some(format!("Option<{}>", enum_name)),
+                _ => Some("i32".to_string()),
+            };
+        }
+    }
+
+    let obj_ty = infer_rust_type(&member.obj, scope);
+    if let Some(ty) = stdlib_http::method_return_type(obj_ty.as_deref().map(struct_of), method) {
+        return Some(ty.to_string());
+    }
+    let obj_ty = obj_ty?;
+    if method == "unwrap" {
+        return obj_ty
+            .strip_prefix("Option<")
+            .and_then(|t| t.strip_suffix('>'))
+            .map(str::to_string);
+    }
+    let receiver = struct_of(&obj_ty);
+    if let Some(sig) = scope.symbols().method(receiver, method) {
+        return Some(sig.return_type.clone());
+    }
+    if !scope.symbols().is_struct(receiver) {
+        if let Some(ty) = stdlib::method_return_type(receiver, method, true) {
+            return Some(ty);
+        }
+    }
+    if let Some(ty) = stdlib_rand::method_return_type(receiver, method) {
+        return Some(ty.to_string());
+    }
+    if receiver == "Rng" && matches!(method, "shuffle" | "chooseOne") {
+        let items = args
+            .first()
+            .and_then(|arg| infer_rust_type(&arg.expr, scope))?;
+        return if method == "shuffle" {
+            Some(items)
+        } else {
+            choose_one_type(&items)
+        };
+    }
+    if method == "toString" {
+        return Some("String".to_string());
+    }
+    if receiver == DECIMAL {
+        return decimal::method_return_type(method).map(str::to_string);
+    }
+    if receiver == BYTES {
+        return bytes::method_return_type(method).map(str::to_string);
+    }
+    if let Some(item) = iterators::item_type(&obj_ty) {
+        return iterators::method_return_type(method, item, args, scope);
+    }
+    if receiver == STRING_BUILDER && strings::is_builtin(scope) {
+        return strings::method_return_type(method).map(str::to_string);
+    }
+    if let Some(element) = arrays::element_type(&obj_ty) {
+        return arrays::method_return_type(method, element, args, scope);
+    }
+    if receiver == "String" {
+        return match method {
+            "toUpperCase" | "toLowerCase" | "trim" | "trimStart" | "trimEnd" | "repeat"
+            | "charAt" | "at" | "slice" | "substring" | "substr" | "concat" | "replace"
+            | "replaceAll" => Some("String".to_string()),
+            "startsWith" | "endsWith" | "includes" => Some("bool".to_string()),
+            "indexOf" | "lastIndexOf" => Some("i32".to_string()),
+            "split" => Some("Vec<String>".to_string()),
+            _ => None,
+        };
+    }
+    None
+}
+
+/// Handle expressions borrowed mutably (or locked) in `rendered`: `p.next.borrow_mut()` → `p.next`.
+fn exclusive_borrows(rendered: &str) -> Vec<&str> {
+    let mut paths = Vec::new();
+    for marker in [".borrow_mut()", ".lock()"] {
+        let mut rest = rendered;
+        let mut offset = 0;
+        while let Some(idx) = rest.find(marker) {
+            let end = offset + idx;
+            let start = handle_path_start(&rendered[..end]);
+            if start < end {
+                paths.push(&rendered[start..end]);
+            }
+            offset = end + marker.len();
+            rest = &rendered[offset..];
+        }
+    }
+    paths
+}
+
+/// Start of the path expression (`a.b[i as usize].c()`) that ends `prefix`.
+fn handle_path_start(prefix: &str) -> usize {
+    let mut depth = 0i32;
+    for (i, c) in prefix.char_indices().rev() {
+        match c {
+            ')' | ']' => depth += 1,
+            '(' | '[' if depth > 0 => depth -= 1,
+            _ if depth > 0 => {}
+            c if c.is_alphanumeric() || c == '_' || c == '.' || c == ':' => {}
+            _ => return i + c.len_utf8(),
+        }
+    }
+    0
+}
+
+/// True if `value` borrows a handle that `target` holds exclusively. The `Ref`/`MutexGuard`
+/// temporaries of one statement live until its end, so this would panic (or deadlock) at runtime.
+fn conflicts_with(target: &str, value: &str) -> bool {
+    exclusive_borrows(target).iter().any(|path| {
+        [".borrow()", ".borrow_mut()", ".lock()"]
+            .iter()
+            .any(|marker| value.contains(&format!("{}{}", path, marker)))
+    })
+}
+
+/// Evaluates arguments that borrow the receiver's handle into temporaries before the call.
+fn hoist_conflicting_arguments(receiver: &str, args: Vec<String>) -> (String, Vec<String>) {
+    let mut prelude = String::new();
+    let args = args
+        .into_iter()
+        .enumerate()
+        .map(|(i, arg)| {
+            if !conflicts_with(receiver, &arg) {
+                return arg;
+            }
+            let temp = format!("__trust_arg{}", i);
+            prelude.push_str(&format!("let {} = {}; ", temp, arg));
+            temp
+        })
+        .collect();
+    (prelude, args)
+}
+
+fn with_prelude(prelude: &str, expr: String) -> String {
+    if prelude.is_empty() {
+        expr
+    } else {
+        format!("{{ {}{} }}", prelude, expr)
+    }
+}
+
+/// Array/Map/Set methods that need a mutable receiver.
+fn is_mutating_method(method: &str) -> bool {
+    matches!(
+        method,
+        "push" | "pop" | "reverse" | "set" | "delete" | "add" | "clear" | "sort" | "sortBy"
+    )
+}
+
+/// `&T` / `Rc<RefCell<T>>` / `Arc<Mutex<T>>` → `T`; other types unchanged.
+fn struct_of(ty: &str) -> &str {
+    let ty = referent_type(ty);
+    ty.strip_prefix("Rc<RefCell<")
+        .or_else(|| ty.strip_prefix("Arc<Mutex<"))
+        .and_then(|t| t.strip_suffix(">>"))
+        .unwrap_or(ty)
+}
+
+/// `Color.Red` where `Color` is a declared enum not shadowed by a binding → `("Color", "Red")`.
+pub(super) fn enum_variant<'a>(
+    member: &'a MemberExpr,
+    scope: &Scope,
+) -> Option<(&'a str, &'a str)> {
+    let (Expr::Ident(obj), MemberProp::Ident(prop)) = (&*member.obj, &member.prop) else {
+        return None;
+    };
+    let enum_name = obj.sym.as_ref();
+    if scope.get(enum_name).is_some() || scope.symbols().enum_variants(enum_name).is_none() {
+        return None;
+    }
+    Some((enum_name, prop.sym.as_ref()))
+}
+
+/// `Color.values()`-style call of an enum utility method → the enum's name.
+fn enum_utility<'a>(member: &'a MemberExpr, scope: &Scope) -> Option<&'a str> {
+    let (Expr::Ident(obj), MemberProp::Ident(prop)) = (&*member.obj, &member.prop) else {
+        return None;
+    };
+    let enum_name = obj.sym.as_ref();
+    let declared =
+        scope.get(enum_name).is_none() && scope.symbols().enum_variants(enum_name).is_some();
+    (declared && UTILITY_METHODS.contains(&prop.sym.as_ref())).then_some(enum_name)
+}
+
 /// Field access: transparent borrow for Pointer<T> and Threaded<T>
+/// `("a" + b).length`: a parenthesized receiver keeps its parentheses.
+fn receiver(obj: &Expr, rendered: String) -> String {
+    match obj {
+        Expr::Paren(_) => format!("({})", rendered),
+        _ => rendered,
+    }
+}
+
 fn transpile_member_access(member: &MemberExpr, scope: &Scope) -> Result<String> {
-    let obj_str = transpile_expression(&member.obj, scope)?;
+    transpile_member_chain(member, false, scope)
+}
+
+/// Renders `obj.prop` / `obj[i]`, borrowing every shared handle met along the chain
+/// (`p.next.value` → `p.borrow().next.borrow().value`). With `mutable`, the chain is an
+/// assignment target and handles are borrowed with `borrow_mut()`.
+fn transpile_member_chain(member: &MemberExpr, mutable: bool, scope: &Scope) -> Result<String> {
+    if let Some((enum_name, variant)) = enum_variant(member, scope) {
+        return Ok(format!("{}::{}", enum_name, variant));
+    }
+    let obj_str = match &*member.obj {
+        Expr::Member(inner) if mutable => transpile_member_chain(inner, true, scope)?,
+        Expr::Ident(ident) if mutable => match static_guard(ident, scope) {
+            Some(guard) => guard,
+            None => transpile_expression(&member.obj, scope)?,
+        },
+        other => receiver(other, transpile_expression(other, scope)?),
+    };
+    let obj_ty = infer_rust_type(&member.obj, scope);
+    let shared_obj = |obj_str: &str| match obj_ty.as_deref().map(referent_type) {
+        Some(t) if is_pointer(t) => {
+            format!(
+                "{}.{}",
+                obj_str,
+                if mutable { "borrow_mut()" } else { "borrow()" }
+            )
+        }
+        Some(t) if is_threaded(t) => format!("{}.lock().unwrap()", obj_str),
+        _ => obj_str.to_string(),
+    };
 
     // arr[i] → arr[i as usize]
     if let MemberProp::Computed(computed) = &member.prop {
         let idx = transpile_expression(&computed.expr, scope)?;
-        return Ok(format!("{}[{} as usize]", obj_str, idx));
+        return Ok(format!("{}[{} as usize]", shared_obj(&obj_str), idx));
     }
 
-    let prop = match &member.prop {
-        MemberProp::Ident(ident) => ident.sym.to_string(),
-        _ => "unknown".to_string(),
-    };
-    let module_alias_obj = ident_name(&member.obj)
-        .and_then(|n| scope.get(&n).map(|t| is_module_alias_binding(t)))
-        .unwrap_or(false);
-    if module_alias_obj {
+    let prop = member_name(member, scope)?;
+    if obj_ty
+        .as_deref()
+        .map(is_module_alias_binding)
+        .unwrap_or(false)
+    {
         return Ok(format!("{}::{}", obj_str, prop));
     }
     // .length
     if prop == "length" {
-        if let Some(name) = ident_name(&member.obj) {
-            if let Some(ty) = scope.get(&name) {
-                if is_pointer(ty) {
-                    if ty == "Rc<RefCell<String>>" {
-                        return Ok(format!("{}.borrow().chars().count() as i32", obj_str));
-                    }
-                    return Ok(format!("{}.borrow().len()", obj_str));
-                }
-                if is_threaded(ty) {
-                    if ty == "Arc<Mutex<String>>" {
-                        return Ok(format!("{}.lock().unwrap().chars().count() as i32", obj_str));
-                    }
-                    return Ok(format!("{}.lock().unwrap().len()", obj_str));
-                }
-                if ty == "String" {
-                    return Ok(format!("{}.chars().count() as i32", obj_str));
-                }
-            }
-        }
-        match &*member.obj {
-            Expr::Lit(Lit::Str(_)) | Expr::Tpl(_) => {
-                return Ok(format!("{}.chars().count() as i32", obj_str));
-            }
-            _ => {}
+        let is_string = match obj_ty.as_deref().map(struct_of) {
+            Some(t) => t == "String",
+            None => matches!(&*member.obj, Expr::Lit(Lit::Str(_)) | Expr::Tpl(_)),
+        };
+        if is_string {
+            return Ok(format!("{}.chars().count() as i32", shared_obj(&obj_str)));
         }
-        return Ok(format!("{}.len()", obj_str));
+        return Ok(format!("{}.len()", shared_obj(&obj_str)));
     }
 
-    if let Some(name) = ident_name(&member.obj) {
-        if let Some(ty) = scope.get(&name) {
-            if is_pointer(ty) {
-                return Ok(format!("{}.borrow().{}", obj_str, prop));
-            }
-            if is_threaded(ty) {
-                return Ok(format!("{}.lock().unwrap().{}", obj_str, prop));
-            }
-        }
+    Ok(format!("{}.{}", shared_obj(&obj_str), prop))
+}
+
+/// The locked value of the `static var` `ident` names, for writes through it.
+fn static_guard(ident: &Ident, scope: &Scope) -> Option<String> {
+    scope
+        .static_var(ident.sym.as_ref())
+        .map(|var| var.guard(ident.sym.as_ref()))
+}
+
+/// `CONFIG.name` / `NAMES.length` / `NAMES.includes(x)` on a `static var`: the read runs
+/// inside the lock (`.lock().map(|__trust_locked| __trust_locked.len())`), so only the field
+/// or result leaves it instead of a clone of the whole value. `None` when `expr` does not
+/// read through a static, mutates it, or names the same static again in its arguments
+/// (the lock would be taken twice); those keep the whole-value read.
+fn static_read(expr: &Expr, scope: &Scope) -> Result<Option<String>> {
+    let chain = match expr {
+        Expr::Call(CallExpr {
+            callee: Callee::Expr(callee),
+            ..
+        }) => match &**callee {
+            Expr::Member(member) if !mutates_receiver(member, scope) => &member.obj,
+            _ => return Ok(None),
+        },
+        Expr::Member(member) => &member.obj,
+        _ => return Ok(None),
+    };
+    let Some(root) = chain_root(chain) else {
+        return Ok(None);
+    };
+    let name = root.sym.to_string();
+    let Some(var) = scope.static_var(&name) else {
+        return Ok(None);
+    };
+    let mut locked = expr.clone();
+    if let Some(ident) = match &mut locked {
+        Expr::Call(CallExpr {
+            callee: Callee::Expr(callee),
+            ..
+        }) => match &mut **callee {
+            Expr::Member(member) => chain_root_mut(&mut member.obj),
+            _ => None,
+        },
+        Expr::Member(member) => chain_root_mut(&mut member.obj),
+        _ => None,
+    } {
+        ident.sym = LOCKED.into();
+    }
+    let mut inner = scope.clone();
+    inner.push();
+    inner.insert(LOCKED.to_string(), var.rust_type.clone());
+    let mut body = transpile_expression(&locked, &inner)?;
+    let handle = var.handle(&name);
+    if body.contains(&handle) {
+        return Ok(None);
+    }
+    if matches!(expr, Expr::Member(_))
+        && infer_rust_type(&locked, &inner).is_some_and(|ty| is_owned_type(&ty))
+    {
+        body.push_str(".clone()");
+    }
+    Ok(Some(format!(
+        "{}.lock().map(|{}| {}).unwrap()",
+        handle, LOCKED, body
+    )))
+}
+
+/// Binding for the locked value inside [`static_read`].
+const LOCKED: &str = "__trust_locked";
+
+/// The identifier a member chain starts from (`a` in `a.b[0].c`).
+fn chain_root(expr: &Expr) -> Option<&Ident> {
+    match expr {
+        Expr::Ident(ident) => Some(ident),
+        Expr::Member(member) => chain_root(&member.obj),
+        _ => None,
     }
-    Ok(format!("{}.{}", obj_str, prop))
 }
 
-/// Assignment: transparent borrow_mut for Pointer<T> and Threaded<T>
+fn chain_root_mut(expr: &mut Expr) -> Option<&mut Ident> {
+    match expr {
+        Expr::Ident(ident) => Some(ident),
+        Expr::Member(member) => chain_root_mut(&mut member.obj),
+        _ => None,
+    }
+}
+
+/// Whether calling `member` needs its receiver mutably (a `mut self` method, `push`, ...).
+fn mutates_receiver(member: &MemberExpr, scope: &Scope) -> bool {
+    let Ok(prop) = member_name(member, scope) else {
+        return true;
+    };
+    infer_rust_type(&member.obj, scope)
+        .and_then(|ty| scope.symbols().method(struct_of(&ty), &prop))
+        .map(|sig| sig.mut_self)
+        .unwrap_or_else(|| is_mutating_method(&prop))
+}
+
 fn transpile_assign(assign: &AssignExpr, scope: &Scope) -> Result<String> {
-    let value = transpile_expression(&assign.right, scope)?;
+    if let AssignTarget::Simple(SimpleAssignTarget::Member(member)) = &assign.left {
+        check_readonly(member, scope)?;
+    }
+    if let Some(lowered) = nullish::transpile_logical_assign(assign, scope, transpile_assign)? {
+        return Ok(lowered);
+    }
+    let target_ty = match &assign.left {
+        AssignTarget::Simple(SimpleAssignTarget::Member(member)) => {
+            infer_member_type(member, scope)
+        }
+        AssignTarget::Simple(SimpleAssignTarget::Ident(ident)) => {
+            scope.get(ident.id.sym.as_ref()).cloned()
+        }
+        _ => None,
+    };
+    if target_ty.as_deref().map(referent_type) == Some("String") {
+        let target = match &assign.left {
+            AssignTarget::Simple(SimpleAssignTarget::Member(member)) => {
+                Some(transpile_member_chain(member, true, scope)?)
+            }
+            AssignTarget::Simple(SimpleAssignTarget::Ident(ident))
+                if static_guard(&ident.id, scope).is_none() =>
+            {
+                Some(ident.id.sym.to_string())
+            }
+            _ => None,
+        };
+        if let Some(target) = target {
+            if let Some(append) = strings::lower_append(assign, &target, scope)? {
+                return Ok(append);
+            }
+        }
+    }
+    let value = match &target_ty {
+        Some(ty) => transpile_expression_as(&assign.right, ty, scope)?,
+        None => transpile_expression(&assign.right, scope)?,
+    };
+    let op = match assign.op {
+        AssignOp::AddAssign => "+=",
+        AssignOp::SubAssign => "-=",
+        AssignOp::MulAssign => "*=",
+        AssignOp::DivAssign => "/=",
+        AssignOp::ModAssign => "%=",
+        _ => "=",
+    };
+    let overflow = scope.options().overflow;
+    let ty = referent_type(target_ty.as_deref().unwrap_or_default());
     match &assign.left {
         AssignTarget::Simple(SimpleAssignTarget::Member(member)) => {
-            let obj_str = transpile_expression(&member.obj, scope)?;
-            let prop = match &member.prop {
-                MemberProp::Ident(ident) => ident.sym.to_string(),
-                _ => "unknown".to_string(),
-            };
-            if let Some(name) = ident_name(&member.obj) {
-                if let Some(ty) = scope.get(&name) {
-                    if is_pointer(ty) {
-                        return Ok(format!("{}.borrow_mut().{} = {}", obj_str, prop, value));
+            let target = transpile_member_chain(member, true, scope)?;
+            if let Some(lowered) = overflow.lower_assign(assign.op, &target, &value, ty, false) {
+                return Ok(lowered);
+            }
+            // `p.value = p.value + 1` on a Pointer: read into a temporary first, so the shared
+            // borrow ends before `borrow_mut()`.
+            if conflicts_with(&target, &value) {
+                return Ok(format!(
+                    "{{ let __trust_value = {}; {} {} __trust_value; }}",
+                    value, target, op
+                ));
+            }
+            Ok(format!("{} {} {}", target, op, value))
+        }
+        AssignTarget::Simple(SimpleAssignTarget::Ident(ident)) => {
+            match static_guard(&ident.id, scope) {
+                Some(guard) => {
+                    if let Some(lowered) =
+                        overflow.lower_assign(assign.op, &format!("*{}", guard), &value, ty, false)
+                    {
+                        return Ok(lowered);
                     }
-                    if is_threaded(ty) {
-                        return Ok(format!("{}.lock().unwrap().{} = {}", obj_str, prop, value));
+                    // The value is read before the lock is taken: it may read the static too.
+                    if conflicts_with(&guard, &value) {
+                        return Ok(format!(
+                            "{{ let __trust_value = {}; *{} {} __trust_value; }}",
+                            value, guard, op
+                        ));
                     }
+                    Ok(format!("*{} {} {}", guard, op, value))
+                }
+                None => {
+                    // A `mut ref` parameter is assigned through: `*n = n + 1`.
+                    let target = match target_ty.as_deref() {
+                        Some(target_ty) if target_ty.starts_with('&') => {
+                            format!("*{}", ident.id.sym)
+                        }
+                        _ => ident.id.sym.to_string(),
+                    };
+                    Ok(overflow
+                        .lower_assign(assign.op, &target, &value, ty, true)
+                        .unwrap_or_else(|| format!("{} {} {}", target, op, value)))
                 }
             }
-            Ok(format!("{}.{} = {}", obj_str, prop, value))
         }
-        AssignTarget::Simple(SimpleAssignTarget::Ident(ident)) => {
-            Ok(format!("{} = {}", ident.id.sym, value))
+        _ => {
+            let target = assign.left.span();
+            scope.unsupported(
+                "unsupported-assignment",
+                msg!("unsupported-assignment", scope.snippet(target)),
+                target,
+            )?;
+            Ok("// unsupported assignment left out".to_string())
         }
-        _ => Ok("// assignment non supporté".to_string()),
     }
 }
 
-/// Arrow function: `() => expr` or `(x) => expr` → `move || expr`
-fn transpile_arrow(arrow: &ArrowExpr, scope: &Scope) -> Result<String> {
-    let params: Vec<String> = arrow
-        .params
-        .iter()
-        .map(|p| match p {
-            Pat::Ident(ident) => ident.id.sym.to_string(),
-            _ => "_".to_string(),
-        })
-        .collect();
+/// `p.id = ...` on a `readonly id` field is an error: it is only set when the struct is built.
+fn check_readonly(member: &MemberExpr, scope: &Scope) -> Result<()> {
+    let (MemberProp::Ident(prop), Some(obj_ty)) =
+        (&member.prop, infer_rust_type(&member.obj, scope))
+    else {
+        return Ok(());
+    };
+    let struct_name = struct_of(&obj_ty);
+    if !scope.symbols().is_readonly(struct_name, prop.sym.as_ref()) {
+        return Ok(());
+    }
+    Err(Diagnostic::error(
+        "readonly-field",
+        msg!("readonly-field", struct_name, prop.sym),
+    )
+    .with_span(source_span(member.span))
+    .into())
+}
+
+/// Arrow function: `() => expr` or `(x) => expr` → `move || expr`. A parameter has the type it is
+/// annotated with, else the one at its position in `param_types` (array elements, for callbacks);
+/// a typed parameter is annotated in the closure too, so rustc need not infer it.
+fn transpile_arrow(arrow: &ArrowExpr, param_types: &[&str], scope: &Scope) -> Result<String> {
+    // Closure parameters shadow outer bindings of the same name.
+    let mut inner_scope = scope.clone();
+    inner_scope.push();
+    let mut params = Vec::new();
+    for (k, pat) in arrow.params.iter().enumerate() {
+        let Pat::Ident(ident) = pat else {
+            params.push(binding_name(pat, "_", scope)?);
+            continue;
+        };
+        let name = ident.id.sym.to_string();
+        if let Some(ann) = ident.type_ann.as_deref() {
+            check_annotation(ann)?;
+        }
+        let ty = ident.type_ann.as_deref().map(transpile_type_annotation);
+        match ty.or_else(|| param_types.get(k).map(|ty| ty.to_string())) {
+            Some(ty) => {
+                params.push(format!("{}: {}", name, ty));
+                inner_scope.insert(name, ty);
+            }
+            None => {
+                params.push(name.clone());
+                inner_scope.declare_untyped(name);
+            }
+        }
+    }
 
     let body = match &*arrow.body {
-        BlockStmtOrExpr::Expr(expr) => transpile_expression(expr, scope)?,
+        BlockStmtOrExpr::Expr(expr) => transpile_expression(expr, &inner_scope)?,
         BlockStmtOrExpr::BlockStmt(block) => {
-            let mut inner_scope = scope.clone();
             let stmts = transpile_block_stmt(block, "    ", &mut inner_scope)?;
             format!("{{\n{}\n}}", stmts)
         }
@@ -257,18 +1042,19 @@ fn transpile_arrow(arrow: &ArrowExpr, scope: &Scope) -> Result<String> {
 }
 
 fn transpile_fn_expr(fn_expr: &FnExpr, scope: &Scope) -> Result<String> {
-    let params: Vec<String> = fn_expr
+    let params = fn_expr
         .function
         .params
         .iter()
-        .map(|p| match &p.pat {
-            Pat::Ident(ident) => ident.id.sym.to_string(),
-            _ => "_".to_string(),
-        })
-        .collect();
+        .map(|p| binding_name(&p.pat, "_", scope))
+        .collect::<Result<Vec<_>>>()?;
 
     let body = if let Some(block) = &fn_expr.function.body {
         let mut inner_scope = scope.clone();
+        inner_scope.push();
+        for param in &params {
+            inner_scope.declare_untyped(param.clone());
+        }
         let stmts = transpile_block_stmt(block, "    ", &mut inner_scope)?;
         format!("{{\n{}\n}}", stmts)
     } else {
@@ -283,6 +1069,15 @@ fn transpile_fn_expr(fn_expr: &FnExpr, scope: &Scope) -> Result<String> {
 }
 
 fn transpile_object_literal(obj: &ObjectLit, scope: &Scope) -> Result<String> {
+    if let Some(name) = scope.symbols().object_struct(obj.span) {
+        let args = [ExprOrSpread {
+            spread: None,
+            expr: Box::new(Expr::Object(obj.clone())),
+        }];
+        if let Some(constructed) = transpile_struct_constructor_call(name, &args, scope)? {
+            return Ok(constructed);
+        }
+    }
     let mut fields = Vec::new();
     for prop in &obj.props {
         let PropOrSpread::Prop(prop) = prop else {
@@ -298,11 +1093,11 @@ fn transpile_object_literal(obj: &ObjectLit, scope: &Scope) -> Result<String> {
                     PropName::Computed(_) => continue,
                 };
                 let value = transpile_expression(&kv.value, scope)?;
-                fields.push(format!("\"{}\": {}", key, value));
+                fields.push(format!("{}: {}", rust_string_literal(&key), value));
             }
             Prop::Shorthand(i) => {
                 let name = i.sym.to_string();
-                fields.push(format!("\"{}\": {}", name, name));
+                fields.push(format!("{}: {}", rust_string_literal(&name), name));
             }
             _ => {}
         }
@@ -333,59 +1128,179 @@ fn ident_name(expr: &Expr) -> Option<String> {
     }
 }
 
-fn rust_string_literal(value: &str) -> String {
-    format!("{:?}", value)
-}
-
 fn transpile_template_literal(tpl: &Tpl, scope: &Scope) -> Result<String> {
+    // `cooked` has the escapes resolved; it is missing only after an invalid escape.
+    let text = |quasi: &TplElement| match &quasi.cooked {
+        Some(cooked) => cooked.to_string_lossy().into_owned(),
+        None => quasi.raw.to_string(),
+    };
+    if tpl.exprs.is_empty() {
+        let text: String = tpl.quasis.iter().map(text).collect();
+        return Ok(format!("{}.to_string()", rust_string_literal(&text)));
+    }
+
     let mut format_str = String::new();
     let mut args = Vec::new();
+    for (i, quasi) in tpl.quasis.iter().enumerate() {
+        format_str.push_str(&format_string_text(&text(quasi)));
+        if i < tpl.exprs.len() {
+            format_str.push_str("{}");
+            args.push(transpile_expression(&tpl.exprs[i], scope)?);
+        }
+    }
+    Ok(format!("format!(\"{}\", {})", format_str, args.join(", ")))
+}
+
+/// Arguments of the `println!` behind `console.write(...)`: a leading string literal with `{}`
+/// placeholders is the format string for the rest, otherwise every argument is printed, separated
+/// by spaces. Values without `Display` (arrays, maps, options, ...) print with `{:?}`.
+fn console_format_args(args: &[ExprOrSpread], scope: &Scope) -> Result<String> {
+    if args.is_empty() {
+        return Ok(String::new());
+    }
+    let placeholder = |arg: &ExprOrSpread| match infer_rust_type(&arg.expr, scope) {
+        Some(ty) if !has_display(&ty) => "{:?}",
+        _ => "{}",
+    };
+
+    let (format_str, values) = match &*args[0].expr {
+        Expr::Lit(Lit::Str(literal))
+            if args.len() > 1 && count_placeholders(&literal.value.to_string_lossy()) > 0 =>
+        {
+            let text = literal.value.to_string_lossy();
+            let count = count_placeholders(&text);
+            if count != args.len() - 1 {
+                return Err(Diagnostic::error(
+                    "format-arguments",
+                    msg!("format-arguments", count, args.len() - 1),
+                )
+                .with_span(source_span(literal.span))
+                .into());
+            }
+            let quoted = rust_string_literal(&text);
+            (quoted[1..quoted.len() - 1].to_string(), &args[1..])
+        }
+        _ => (
+            args.iter().map(placeholder).collect::<Vec<_>>().join(" "),
+            args,
+        ),
+    };
+
+    let mut parts = vec![format!("\"{}\"", format_str)];
+    for arg in values {
+        parts.push(transpile_expression(&arg.expr, scope)?);
+    }
+    Ok(parts.join(", "))
+}
 
-    for (i, quasi) in tpl.quasis.iter().enumerate() {
-        format_str.push_str(&quasi.raw.to_string());
-        if i < tpl.exprs.len() {
-            format_str.push_str("{}");
-            let expr = transpile_expression(&tpl.exprs[i], scope)?;
-            args.push(expr);
+/// `{}` and `{:...}` placeholders in a format string; `{{` and `}}` are literal braces.
+fn count_placeholders(text: &str) -> usize {
+    let mut count = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+            }
+            '{' if chars.by_ref().any(|c| c == '}') => count += 1,
+            _ => {}
         }
     }
+    count
+}
 
-    if args.is_empty() {
-        Ok(format!("\"{}\" .to_string()", format_str))
-    } else {
-        Ok(format!("format!(\"{}\", {})", format_str, args.join(", ")))
-    }
+fn has_display(ty: &str) -> bool {
+    !(ty.starts_with("Vec<")
+        || ty.starts_with("HashMap<")
+        || ty.starts_with("HashSet<")
+        || ty.starts_with("std::collections::")
+        || ty.starts_with("Option<")
+        || ty.starts_with('(')
+        || ty.starts_with('['))
 }
 
 fn transpile_call_expression(call: &CallExpr, scope: &Scope) -> Result<String> {
     match &call.callee {
         Callee::Expr(expr) => match &**expr {
-            Expr::Member(member) => {
-                transpile_member_call(member, &call.args, call.type_args.as_deref(), scope)
-            }
+            Expr::Member(member) => transpile_member_call(
+                member,
+                &call.args,
+                call.type_args.as_deref(),
+                call.span,
+                scope,
+            ),
             Expr::Ident(ident) => {
                 let func_name = ident.sym.to_string();
-                if let Some(ctor_expr) = transpile_struct_constructor_call(&func_name, &call.args, scope)? {
+                if func_name == MATCH_CALLEE {
+                    return transpile_match(call, scope);
+                }
+                if func_name == TRY_CALLEE {
+                    return transpile_try_expression(call, scope);
+                }
+                if func_name == DECIMAL_CALLEE {
+                    return decimal::transpile_literal(call);
+                }
+                if func_name == BYTES_CALLEE || func_name == HEX_CALLEE {
+                    return bytes::transpile_literal(&func_name, call);
+                }
+                if let Some(text) = (func_name == strings::RAW_CALLEE)
+                    .then(|| strings::raw_text(call))
+                    .flatten()
+                {
+                    return Ok(format!(
+                        "{}.to_string()",
+                        strings::rust_raw_string_literal(&text)
+                    ));
+                }
+                if let Some(bounds) = call_range_bounds(call) {
+                    return Ok(format!("({})", render_range(bounds, scope)?));
+                }
+                if let Some(ctor_expr) =
+                    transpile_struct_constructor_call(&func_name, &call.args, scope)?
+                {
                     return Ok(ctor_expr);
                 }
-                if let Some(cast_expr) = transpile_builtin_cast_call(&func_name, &call.args, scope)? {
+                if let Some(cast_expr) = transpile_builtin_cast_call(&func_name, &call.args, scope)?
+                {
                     return Ok(cast_expr);
                 }
+                if let Some(weak_expr) = transpile_weak_pointer_call(&func_name, &call.args, scope)?
+                {
+                    return Ok(weak_expr);
+                }
+                if let Some(task_expr) = tasks::transpile_call(&func_name, &call.args, scope)? {
+                    return Ok(task_expr);
+                }
+                if let Some(abort_expr) = transpile_abort_call(&func_name, call, scope)? {
+                    return Ok(abort_expr);
+                }
 
                 // Typed object-literal support for JSON APIs:
                 // toJSON<MyType>({ ... }) -> toJSON::<MyType>(MyType { ... })
                 // stringify<MyType>({ ... }) -> stringify::<MyType>(MyType { ... })
-                if (func_name == "toJSON" || func_name == "stringify")
-                    && call.args.len() == 1
-                {
-                    if let Some(type_name) = extract_single_type_ref_name(call.type_args.as_deref()) {
+                if (func_name == "toJSON" || func_name == "stringify") && call.args.len() == 1 {
+                    if let Some(type_name) = extract_single_type_ref_name(call.type_args.as_deref())
+                    {
                         if let Expr::Object(obj) = &*call.args[0].expr {
-                            let typed_lit = transpile_object_as_named_struct_literal(&type_name, obj, scope)?;
+                            let typed_lit =
+                                transpile_object_as_named_struct_literal(&type_name, obj, scope)?;
                             return Ok(format!("{}::<{}>({})", func_name, type_name, typed_lit));
                         }
                     }
                 }
 
+                if let Some(sig) = scope.symbols().function(&func_name) {
+                    check_arity(&format!("`{}`", func_name), sig, &call.args, call.span)?;
+                    let args = transpile_arguments(&call.args, sig, scope)?;
+                    let turbofish = render_turbofish(call.type_args.as_deref());
+                    return Ok(format!("{}{}({})", func_name, turbofish, args.join(", ")));
+                }
+
+                if let ("iter", [arg]) = (func_name.as_str(), call.args.as_slice()) {
+                    let rendered = transpile_expression(&arg.expr, scope)?;
+                    return Ok(iterators::transpile_iter_call(&arg.expr, &rendered, scope));
+                }
+
                 let args: Result<Vec<String>> = call
                     .args
                     .iter()
@@ -398,13 +1313,48 @@ fn transpile_call_expression(call: &CallExpr, scope: &Scope) -> Result<String> {
                 let turbofish = render_turbofish(call.type_args.as_deref());
                 Ok(format!("{}{}({})", func_name, turbofish, args.join(", ")))
             }
-            _ => Ok("unknown_call".to_string()),
+            _ => unsupported_expression(call.span, "unknown_call", scope),
         },
-        _ => Ok("unknown_callee".to_string()),
+        _ => unsupported_expression(call.span, "unknown_callee", scope),
+    }
+}
+
+/// `panic(msg)`, `unreachable()` and `todo()` → the Rust macro, with the TRUST line and column in
+/// the message. A program function of the same name takes precedence.
+fn transpile_abort_call(func_name: &str, call: &CallExpr, scope: &Scope) -> Result<Option<String>> {
+    let macro_name = match func_name {
+        "panic" => "panic",
+        "unreachable" => "unreachable",
+        "todo" => "todo",
+        _ => return Ok(None),
+    };
+    if scope.symbols().function(func_name).is_some() {
+        return Ok(None);
     }
+    if call.args.len() > 1 {
+        let message = msg!("arity-mismatch.abort", func_name, call.args.len());
+        return Err(Diagnostic::error("arity-mismatch", message)
+            .with_span(source_span(call.span))
+            .into());
+    }
+    let location = format_string_text(&format!("at {}", scope.location(call.span)));
+    Ok(Some(match call.args.first() {
+        Some(message) => format!(
+            "{}!(\"{{}} ({})\", {})",
+            macro_name,
+            location,
+            transpile_expression(&message.expr, scope)?
+        ),
+        None if macro_name == "panic" => format!("panic!(\"explicit panic ({})\")", location),
+        None => format!("{}!(\"{}\")", macro_name, location),
+    }))
 }
 
-fn transpile_struct_constructor_call(func_name: &str, args: &[ExprOrSpread], scope: &Scope) -> Result<Option<String>> {
+fn transpile_struct_constructor_call(
+    func_name: &str,
+    args: &[ExprOrSpread],
+    scope: &Scope,
+) -> Result<Option<String>> {
     let Some(first) = func_name.chars().next() else {
         return Ok(None);
     };
@@ -429,21 +1379,81 @@ fn transpile_struct_constructor_call(func_name: &str, args: &[ExprOrSpread], sco
                     PropName::Num(n) => n.value.to_string(),
                     _ => return Ok(None),
                 };
-                let val = transpile_expression(&kv.value, scope)?;
-                fields.push(format!("{}: {}", key, val));
+                fields.push(format!(
+                    "{}: {}",
+                    key,
+                    struct_field_value(func_name, &key, &kv.value, scope)?
+                ));
             }
             Prop::Shorthand(id) => {
                 let key = id.sym.to_string();
-                fields.push(format!("{}: {}", key, key));
+                let value = match scope.symbols().field_type(func_name, &key) {
+                    Some(field_ty) if needs_clone(&Expr::Ident(id.clone()), field_ty, scope) => {
+                        format!("{}.clone()", key)
+                    }
+                    _ => key.clone(),
+                };
+                fields.push(format!("{}: {}", key, value));
             }
             _ => return Ok(None),
         }
     }
+    fields.extend(omitted_optional_fields(func_name, obj, scope));
 
     Ok(Some(format!("{} {{ {} }}", func_name, fields.join(", "))))
 }
 
-fn transpile_object_as_named_struct_literal(type_name: &str, obj: &ObjectLit, scope: &Scope) -> Result<String> {
+/// `value` for the field `key` of struct `type_name`, converted to the field's type; a variable
+/// the caller still uses afterwards is cloned, as for call arguments (see [`AutoClone`]).
+fn struct_field_value(type_name: &str, key: &str, value: &Expr, scope: &Scope) -> Result<String> {
+    match scope.symbols().field_type(type_name, key) {
+        Some(field_ty) if needs_clone(value, field_ty, scope) => {
+            Ok(format!("{}.clone()", transpile_expression(value, scope)?))
+        }
+        Some(field_ty) => transpile_expression_as(value, field_ty, scope),
+        None => transpile_expression(value, scope),
+    }
+}
+
+/// `field: None` for each optional field of struct `name` that `obj` leaves out.
+fn omitted_optional_fields(name: &str, obj: &ObjectLit, scope: &Scope) -> Vec<String> {
+    let given: Vec<&str> = obj
+        .props
+        .iter()
+        .filter_map(|prop| match prop {
+            PropOrSpread::Prop(prop) => match &**prop {
+                Prop::KeyValue(KeyValueProp {
+                    key: PropName::Ident(id),
+                    ..
+                }) => Some(id.sym.as_ref()),
+                Prop::KeyValue(KeyValueProp {
+                    key: PropName::Str(s),
+                    ..
+                }) => s.value.as_str(),
+                Prop::Shorthand(id) => Some(id.sym.as_ref()),
+                _ => None,
+            },
+            PropOrSpread::Spread(_) => None,
+        })
+        .collect();
+    scope
+        .symbols()
+        .structs
+        .get(name)
+        .into_iter()
+        .flatten()
+        .filter(|(field, ty)| {
+            nullish::option_inner(ty).is_some() && !given.contains(&field.as_str())
+        })
+        .map(|(field, _)| format!("{}: None", field))
+        .collect()
+}
+
+fn transpile_object_as_named_struct_literal(
+    type_name: &str,
+    obj: &ObjectLit,
+    scope: &Scope,
+) -> Result<String> {
     let mut fields = Vec::new();
     for prop in &obj.props {
         let PropOrSpread::Prop(prop) = prop else {
@@ -457,16 +1467,26 @@ fn transpile_object_as_named_struct_literal(type_name: &str, obj: &ObjectLit, sc
                     PropName::Num(n) => n.value.to_string(),
                     _ => continue,
                 };
-                let val = transpile_expression(&kv.value, scope)?;
-                fields.push(format!("{}: {}", key, val));
+                fields.push(format!(
+                    "{}: {}",
+                    key,
+                    struct_field_value(type_name, &key, &kv.value, scope)?
+                ));
             }
             Prop::Shorthand(id) => {
                 let key = id.sym.to_string();
-                fields.push(format!("{}: {}", key, key));
+                let value = match scope.symbols().field_type(type_name, &key) {
+                    Some(field_ty) if needs_clone(&Expr::Ident(id.clone()), field_ty, scope) => {
+                        format!("{}.clone()", key)
+                    }
+                    _ => key.clone(),
+                };
+                fields.push(format!("{}: {}", key, value));
             }
             _ => {}
         }
     }
+    fields.extend(omitted_optional_fields(type_name, obj, scope));
     Ok(format!("{} {{ {} }}", type_name, fields.join(", ")))
 }
 
@@ -484,7 +1504,174 @@ fn extract_single_type_ref_name(type_args: Option<&TsTypeParamInstantiation>) ->
     }
 }
 
-fn transpile_builtin_cast_call(func_name: &str, args: &[ExprOrSpread], scope: &Scope) -> Result<Option<String>> {
+/// `__trust_range(a, b)` → `a..b` and `__trust_range_inclusive(a, b)` → `a..=b`, unparenthesized.
+///
+/// `arr.length` bounds are cast to the element type, since they render as `usize` `.len()`.
+pub fn transpile_range(expr: &Expr, scope: &Scope) -> Result<Option<String>> {
+    match expr {
+        Expr::Call(call) => call_range_bounds(call)
+            .map(|bounds| render_range(bounds, scope))
+            .transpose(),
+        _ => Ok(None),
+    }
+}
+
+fn render_range((start, end, inclusive): (&Expr, &Expr, bool), scope: &Scope) -> Result<String> {
+    let ty = range_element_type(start, end, scope);
+    let bound = |bound: &Expr| -> Result<String> {
+        let rendered = transpile_expression(bound, scope)?;
+        Ok(match bound {
+            Expr::Member(member) if matches!(&member.prop, MemberProp::Ident(p) if p.sym.as_ref() == "length") =>
+            {
+                format!("({}) as {}", rendered, ty)
+            }
+            _ => rendered,
+        })
+    };
+    let op = if inclusive { "..=" } else { ".." };
+    Ok(format!("{}{}{}", bound(start)?, op, bound(end)?))
+}
+
+/// `(start, end, inclusive)` of a range marker call.
+fn call_range_bounds(call: &CallExpr) -> Option<(&Expr, &Expr, bool)> {
+    let Callee::Expr(callee) = &call.callee else {
+        return None;
+    };
+    let Expr::Ident(ident) = &**callee else {
+        return None;
+    };
+    let inclusive = match ident.sym.as_ref() {
+        RANGE_CALLEE => false,
+        RANGE_INCLUSIVE_CALLEE => true,
+        _ => return None,
+    };
+    match call.args.as_slice() {
+        [start, end] => Some((&start.expr, &end.expr, inclusive)),
+        _ => None,
+    }
+}
+
+/// Integer type of a range's values: the first bound with a known integer type, else `i32`.
+pub fn range_element_type(start: &Expr, end: &Expr, scope: &Scope) -> String {
+    [start, end]
+        .into_iter()
+        .filter_map(|bound| infer_rust_type(bound, scope))
+        .find(|ty| is_numeric_rust_type(ty) && !ty.starts_with('f'))
+        .unwrap_or_else(|| "i32".to_string())
+}
+
+/// `downgrade(p)` → `Rc::downgrade(&p)` and `upgrade(w)` → `w.upgrade()` (an `Option` of the Pointer).
+fn transpile_weak_pointer_call(
+    func_name: &str,
+    args: &[ExprOrSpread],
+    scope: &Scope,
+) -> Result<Option<String>> {
+    if args.len() != 1 || scope.symbols().function(func_name).is_some() {
+        return Ok(None);
+    }
+    match func_name {
+        "downgrade" => {
+            let arg = transpile_expression(&args[0].expr, scope)?;
+            Ok(Some(format!("Rc::downgrade(&{})", arg)))
+        }
+        "upgrade" => {
+            let arg = transpile_expression(&args[0].expr, scope)?;
+            Ok(Some(format!("{}.upgrade()", arg)))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// `null` where a `WeakPointer<T>` is expected → an empty `Weak::new()`.
+pub fn transpile_expression_as(expr: &Expr, expected_ty: &str, scope: &Scope) -> Result<String> {
+    if is_weak_pointer(expected_ty) && matches!(expr, Expr::Lit(Lit::Null(_))) {
+        return Ok("Weak::new()".to_string());
+    }
+    if expected_ty == DECIMAL {
+        return decimal::convert(expr, transpile_expression(expr, scope)?, scope);
+    }
+    // An object literal in a struct slot (`val p: P = { ... }`) builds the struct, and so does
+    // each one of an array literal in a `P[]` slot.
+    if let Expr::Object(obj) = expr {
+        if scope.symbols().is_struct(expected_ty) {
+            return struct_literal(expected_ty, obj, scope);
+        }
+    }
+    if let (Expr::Array(array), Some(element)) = (expr, arrays::element_type(expected_ty)) {
+        let spread = array.elems.iter().flatten().any(|e| e.spread.is_some());
+        if scope.symbols().is_struct(element) && !spread {
+            let elements = array
+                .elems
+                .iter()
+                .flatten()
+                .map(|e| transpile_expression_as(&e.expr, element, scope))
+                .collect::<Result<Vec<_>>>()?;
+            return Ok(format!("vec![{}]", elements.join(", ")));
+        }
+    }
+    // A value known not to be optional fills an `Option<T>` slot as `Some(value)`.
+    if let Some(inner) = nullish::option_inner(expected_ty) {
+        let plain = !matches!(expr, Expr::Lit(Lit::Null(_)))
+            && (matches!(expr, Expr::Object(_)) && scope.symbols().is_struct(inner)
+                || infer_rust_type(expr, scope)
+                    .is_some_and(|ty| nullish::option_inner(&ty).is_none() && ty != "()"));
+        if plain {
+            return Ok(format!(
+                "Some({})",
+                transpile_expression_as(expr, inner, scope)?
+            ));
+        }
+    }
+    transpile_expression(expr, scope)
+}
+
+/// `P { ... }` for an object literal typed as struct `type_name`; a key that is not one of its
+/// fields, or a spread or computed key, is an error rather than a silent `serde_json` value.
+fn struct_literal(type_name: &str, obj: &ObjectLit, scope: &Scope) -> Result<String> {
+    let fields: Vec<&str> = scope
+        .symbols()
+        .structs
+        .get(type_name)
+        .into_iter()
+        .flatten()
+        .map(|(field, _)| field.as_str())
+        .collect();
+    for prop in &obj.props {
+        let key = match prop {
+            PropOrSpread::Prop(prop) => match &**prop {
+                Prop::KeyValue(KeyValueProp {
+                    key: PropName::Ident(id),
+                    ..
+                }) => Some((id.sym.to_string(), id.span)),
+                Prop::KeyValue(KeyValueProp {
+                    key: PropName::Str(s),
+                    ..
+                }) => Some((s.value.to_string_lossy().to_string(), s.span)),
+                Prop::Shorthand(id) => Some((id.sym.to_string(), id.span)),
+                _ => None,
+            },
+            PropOrSpread::Spread(_) => None,
+        };
+        let (key, span) = key.unwrap_or_else(|| (String::new(), prop.span()));
+        if !fields.contains(&key.as_str()) {
+            let message = match crate::diagnostics::closest(&key, &fields) {
+                _ if key.is_empty() => msg!("unknown-struct-field.key", type_name),
+                Some(close) => msg!("unknown-struct-field.suggestion", type_name, key, close),
+                None => msg!("unknown-struct-field", type_name, key, fields.join(", ")),
+            };
+            return Err(Diagnostic::error("unknown-struct-field", message)
+                .with_span(source_span(span))
+                .into());
+        }
+    }
+    transpile_object_as_named_struct_literal(type_name, obj, scope)
+}
+
+fn transpile_builtin_cast_call(
+    func_name: &str,
+    args: &[ExprOrSpread],
+    scope: &Scope,
+) -> Result<Option<String>> {
     if args.len() != 1 {
         return Ok(None);
     }
@@ -492,16 +1679,25 @@ fn transpile_builtin_cast_call(func_name: &str, args: &[ExprOrSpread], scope: &S
     let arg_expr = &args[0].expr;
     let arg_rendered = transpile_expression(arg_expr, scope)?;
     let arg_type = match &**arg_expr {
-        Expr::Ident(ident) => scope.get(&ident.sym.to_string()).cloned(),
+        Expr::Ident(ident) => scope.get(ident.sym.as_ref()).cloned(),
         _ => None,
     };
 
+    if func_name == "decimal" {
+        return Ok(Some(decimal::transpile_cast(arg_expr, arg_rendered, scope)));
+    }
+
     if func_name == "string" {
         let out = match arg_type.as_deref() {
             Some("Rc<RefCell<String>>") => format!("{}.borrow().to_string()", arg_rendered),
             Some("Arc<Mutex<String>>") => format!("{}.lock().unwrap().to_string()", arg_rendered),
-            Some(t) if t.starts_with("Rc<RefCell<") => format!("(*{}.borrow()).to_string()", arg_rendered),
-            Some(t) if t.starts_with("Arc<Mutex<") => format!("(*{}.lock().unwrap()).to_string()", arg_rendered),
+            Some(BYTES) => format!("String::from_utf8_lossy(&{}).to_string()", arg_rendered),
+            Some(t) if t.starts_with("Rc<RefCell<") => {
+                format!("(*{}.borrow()).to_string()", arg_rendered)
+            }
+            Some(t) if t.starts_with("Arc<Mutex<") => {
+                format!("(*{}.lock().unwrap()).to_string()", arg_rendered)
+            }
             _ => format!("({}).to_string()", arg_rendered),
         };
         return Ok(Some(out));
@@ -518,6 +1714,7 @@ fn transpile_builtin_cast_call(func_name: &str, args: &[ExprOrSpread], scope: &S
 
         let out = match arg_type.as_deref() {
             Some("bool") | Some("Rc<RefCell<bool>>") | Some("Arc<Mutex<bool>>") => value_expr,
+            Some(DECIMAL) => format!("!{}.is_zero()", value_expr),
             Some("String" | "Rc<RefCell<String>>" | "Arc<Mutex<String>>") => {
                 format!("!({}).is_empty()", value_expr)
             }
@@ -555,6 +1752,12 @@ fn transpile_builtin_cast_call(func_name: &str, args: &[ExprOrSpread], scope: &S
     let Some(rust_num) = rust_num else {
         return Ok(None);
     };
+    if infer_rust_type(arg_expr, scope).as_deref() == Some(DECIMAL) {
+        return Ok(Some(decimal::transpile_number_cast(
+            &arg_rendered,
+            rust_num,
+        )));
+    }
 
     let value_expr = match arg_type.as_deref() {
         Some("Rc<RefCell<String>>") => format!("{}.borrow()", arg_rendered),
@@ -564,11 +1767,16 @@ fn transpile_builtin_cast_call(func_name: &str, args: &[ExprOrSpread], scope: &S
         _ => arg_rendered.clone(),
     };
 
-    let string_like = matches!(arg_type.as_deref(), Some("String" | "Rc<RefCell<String>>" | "Arc<Mutex<String>>"))
-        || matches!(&**arg_expr, Expr::Lit(Lit::Str(_)) | Expr::Tpl(_));
+    let string_like = matches!(
+        arg_type.as_deref(),
+        Some("String" | "Rc<RefCell<String>>" | "Arc<Mutex<String>>")
+    ) || matches!(&**arg_expr, Expr::Lit(Lit::Str(_)) | Expr::Tpl(_));
 
     let out = if string_like {
-        format!("({}).parse::<{}>().unwrap_or_default()", value_expr, rust_num)
+        format!(
+            "({}).parse::<{}>().unwrap_or_default()",
+            value_expr, rust_num
+        )
     } else {
         format!("({}) as {}", value_expr, rust_num)
     };
@@ -579,12 +1787,50 @@ fn transpile_member_call(
     member: &MemberExpr,
     args: &[ExprOrSpread],
     type_args: Option<&TsTypeParamInstantiation>,
+    call_span: swc_common::Span,
     scope: &Scope,
 ) -> Result<String> {
-    let obj = transpile_expression(&member.obj, scope)?;
-    let prop = match &member.prop {
-        MemberProp::Ident(ident) => ident.sym.to_string(),
-        _ => "unknown".to_string(),
+    let prop = member_name(member, scope)?;
+    if let Some(enum_name) = enum_utility(member, scope) {
+        if !scope.options().enum_utilities {
+            return Err(Diagnostic::error(
+                "enum-utilities-disabled",
+                msg!("enum-utilities-disabled", enum_name, prop),
+            )
+            .with_span(source_span(call_span))
+            .into());
+        }
+        if prop == "fromString" {
+            let [arg] = args else {
+                return Err(Diagnostic::error(
+                    "arity-mismatch",
+                    msg!("arity-mismatch.from-string", enum_name, args.len()),
+                )
+                .with_span(source_span(call_span))
+                .into());
+            };
+            return Ok(format!(
+                "{}::fromString(&{})",
+                enum_name,
+                transpile_expression(&arg.expr, scope)?
+            ));
+        }
+    }
+    let receiver_ty = infer_rust_type(&member.obj, scope).filter(|t| !is_module_alias_binding(t));
+    let method_sig = receiver_ty
+        .as_deref()
+        .and_then(|t| scope.symbols().method(struct_of(t), &prop));
+    let mutating = method_sig
+        .map(|sig| sig.mut_self)
+        .unwrap_or_else(|| is_mutating_method(&prop));
+    // `root.children.push(x)` mutates through every handle in the chain.
+    let obj = match &*member.obj {
+        Expr::Member(inner) if mutating => transpile_member_chain(inner, true, scope)?,
+        Expr::Ident(ident) if mutating => match static_guard(ident, scope) {
+            Some(guard) => guard,
+            None => transpile_expression(&member.obj, scope)?,
+        },
+        other => receiver(other, transpile_expression(other, scope)?),
     };
 
     // Thread.run(fn) → std::thread::spawn(fn)
@@ -597,11 +1843,15 @@ fn transpile_member_call(
     }
 
     if obj == "console" && prop == "write" {
-        let arg_strs: Result<Vec<String>> = args
-            .iter()
-            .map(|arg| transpile_expression(&arg.expr, scope))
-            .collect();
-        return Ok(format!("println!(\"{{}}\", {})", arg_strs?.join(", ")));
+        return Ok(format!("println!({})", console_format_args(args, scope)?));
+    }
+
+    // console.writeInline(...) → print! without the newline, flushed so it shows up right away.
+    if obj == "console" && prop == "writeInline" {
+        return Ok(format!(
+            "{{ print!({}); std::io::Write::flush(&mut std::io::stdout()).ok(); }}",
+            console_format_args(args, scope)?
+        ));
     }
 
     if obj == "console" && prop == "read" {
@@ -619,25 +1869,142 @@ fn transpile_member_call(
         ));
     }
 
+    // Shared receivers (`Pointer<T>`, `Threaded<T>`, at any depth of a member chain) are borrowed
+    // first; `borrow_mut()` when the method mutates.
+    let (obj, member_type) = match receiver_ty.as_deref().map(referent_type) {
+        Some(t) if is_pointer(t) => {
+            let borrow = if mutating { "borrow_mut()" } else { "borrow()" };
+            (
+                format!("{}.{}", obj, borrow),
+                Some(struct_of(t).to_string()),
+            )
+        }
+        Some(t) if is_threaded(t) => (
+            format!("{}.lock().unwrap()", obj),
+            Some(struct_of(t).to_string()),
+        ),
+        other => (obj, other.map(str::to_string)),
+    };
+
+    // Methods from an `implements` block.
+    if let Some(sig) = method_sig {
+        let receiver = member_type.clone().unwrap_or_default();
+        check_arity(&format!("`{}.{}`", receiver, prop), sig, args, call_span)?;
+        let arg_strs = transpile_arguments(args, sig, scope)?;
+        let (prelude, arg_strs) = hoist_conflicting_arguments(&obj, arg_strs);
+        return Ok(with_prelude(
+            &prelude,
+            format!("{}.{}({})", obj, prop, arg_strs.join(", ")),
+        ));
+    }
+
+    // `x.toString()` without a `toString()` of its own: the `Display` form.
+    if prop == "toString" && args.is_empty() && member_type.as_deref() != Some(BYTES) {
+        return Ok(format!("{}.to_string()", obj));
+    }
+
+    // `users.map(u => ...)`: the callback's parameter has the element type.
+    let callback_params = match member_type
+        .as_deref()
+        .and_then(|t| arrays::element_type(t).or_else(|| iterators::item_type(t)))
+    {
+        Some(element) if element != "_" => arrays::callback_params(&prop, element),
+        _ => Vec::new(),
+    };
+    let callback_params: Vec<&str> = callback_params.iter().map(String::as_str).collect();
     let arg_strs: Result<Vec<String>> = args
         .iter()
-        .map(|arg| transpile_expression(&arg.expr, scope))
+        .map(|arg| match &*arg.expr {
+            Expr::Arrow(arrow) if !callback_params.is_empty() => {
+                transpile_arrow(arrow, &callback_params, scope)
+            }
+            _ => transpile_expression(&arg.expr, scope),
+        })
         .collect();
-    let arg_strs = arg_strs?;
-    let member_type = ident_name(&member.obj).and_then(|n| scope.get(&n).cloned());
-    let string_obj = match member_type.as_deref() {
-        Some("Rc<RefCell<String>>") => format!("{}.borrow()", obj),
-        Some("Arc<Mutex<String>>") => format!("{}.lock().unwrap()", obj),
-        _ => obj.clone(),
-    };
-    let is_string = match &*member.obj {
-        Expr::Lit(Lit::Str(_)) | Expr::Tpl(_) => true,
-        Expr::Ident(ident) => scope
-            .get(&ident.sym.to_string())
-            .map(|t| t == "String" || t == "Rc<RefCell<String>>" || t == "Arc<Mutex<String>>")
-            .unwrap_or(false),
-        _ => false,
+    let (prelude, arg_strs) = hoist_conflicting_arguments(&obj, arg_strs?);
+    let call = render_member_call(member, args, type_args, obj, member_type, arg_strs, scope)?;
+    Ok(with_prelude(&prelude, call))
+}
+
+/// Methods a `string` has in TRUST; anything else on a string is emitted as a Rust method call.
+const STRING_METHODS: &[&str] = &[
+    "toUpperCase",
+    "toLowerCase",
+    "startsWith",
+    "endsWith",
+    "includes",
+    "indexOf",
+    "lastIndexOf",
+    "replace",
+    "replaceAll",
+    "trim",
+    "trimStart",
+    "trimEnd",
+    "repeat",
+    "charAt",
+    "at",
+    "split",
+    "slice",
+    "substring",
+    "substr",
+    "concat",
+    "toString",
+];
+
+/// Warns about `name.toUppercase()`: a camelCase method no string has. Rust's own methods are
+/// snake_case, so `name.chars()` passes.
+fn check_string_method(member: &MemberExpr, method: &str, scope: &Scope) {
+    let MemberProp::Ident(ident) = &member.prop else {
+        return;
+    };
+    if STRING_METHODS.contains(&method) || !method.contains(char::is_uppercase) {
+        return;
+    }
+    let message = match crate::diagnostics::closest(method, STRING_METHODS) {
+        Some(suggestion) => msg!("unknown-method.suggestion", method, suggestion),
+        None => msg!("unknown-method", method),
     };
+    scope.warn(Diagnostic::warning("unknown-method", message).with_span(source_span(ident.span)));
+}
+
+/// Library and fallback lowering of `obj.prop(args)` once receiver and arguments are rendered.
+fn render_member_call(
+    member: &MemberExpr,
+    args: &[ExprOrSpread],
+    type_args: Option<&TsTypeParamInstantiation>,
+    obj: String,
+    member_type: Option<String>,
+    arg_strs: Vec<String>,
+    scope: &Scope,
+) -> Result<String> {
+    let prop = member_name(member, scope)?;
+    if member_type.as_deref() == Some(DECIMAL) {
+        if let Some(call) = decimal::transpile_method(&obj, &prop, &arg_strs) {
+            return Ok(call);
+        }
+    }
+    if member_type.as_deref() == Some(BYTES) {
+        if let Some(call) = bytes::transpile_method(&obj, &prop, args, &arg_strs)? {
+            return Ok(call);
+        }
+    }
+    if let Some(item) = member_type.as_deref().and_then(iterators::item_type) {
+        if let Some(call) = iterators::transpile_method(&obj, &prop, item, &arg_strs) {
+            return Ok(call);
+        }
+    }
+    if member_type.as_deref() == Some(STRING_BUILDER) && strings::is_builtin(scope) {
+        if let Some(call) = strings::transpile_method(&obj, &prop, args, &arg_strs) {
+            return Ok(call);
+        }
+    }
+    if obj == "bytes" && prop == "fromString" && scope.get("bytes").is_none() {
+        if let Some(call) = bytes::transpile_from_string(args, &arg_strs)? {
+            return Ok(call);
+        }
+    }
+    let string_obj = obj.clone();
+    let is_string = member_type.as_deref() == Some("String");
 
     let is_map = member_type
         .as_deref()
@@ -653,7 +2020,9 @@ fn transpile_member_call(
         "set" if is_map && arg_strs.len() == 2 => {
             return Ok(format!("{}.insert({}, {})", obj, arg_strs[0], arg_strs[1]))
         }
-        "get" if is_map && arg_strs.len() == 1 => return Ok(format!("{}.get(&{})", obj, arg_strs[0])),
+        "get" if is_map && arg_strs.len() == 1 => {
+            return Ok(format!("{}.get(&{})", obj, arg_strs[0]))
+        }
         "has" if arg_strs.len() == 1 && (is_map || is_set) => {
             if is_set {
                 return Ok(format!("{}.contains(&{})", obj, arg_strs[0]));
@@ -664,17 +2033,45 @@ fn transpile_member_call(
             return Ok(format!("{}.remove(&{})", obj, arg_strs[0]))
         }
         // Set methods
-        "add" if is_set && arg_strs.len() == 1 => return Ok(format!("{}.insert({})", obj, arg_strs[0])),
+        "add" if is_set && arg_strs.len() == 1 => {
+            return Ok(format!("{}.insert({})", obj, arg_strs[0]))
+        }
         _ => {}
     }
 
+    // Methods arrays share with strings go by the receiver's type; an unknown one gets the string
+    // lowering (the array one for `includes` and `indexOf`).
+    if member_type
+        .as_deref()
+        .is_some_and(|t| t.starts_with("Vec<"))
+    {
+        if let Some(call) = arrays::transpile_shared_method(&obj, &prop, &arg_strs) {
+            return Ok(call);
+        }
+    }
+
     // String methods
     match prop.as_str() {
         "toUpperCase" => return Ok(format!("{}.to_uppercase()", string_obj)),
         "toLowerCase" => return Ok(format!("{}.to_lowercase()", string_obj)),
-        "startsWith" if arg_strs.len() == 1 => return Ok(format!("{}.starts_with(({}).as_str())", string_obj, arg_strs[0])),
-        "endsWith" if arg_strs.len() == 1 => return Ok(format!("{}.ends_with(({}).as_str())", string_obj, arg_strs[0])),
-        "includes" if is_string && arg_strs.len() == 1 => return Ok(format!("{}.contains(({}).as_str())", string_obj, arg_strs[0])),
+        "startsWith" if arg_strs.len() == 1 => {
+            return Ok(format!(
+                "{}.starts_with(({}).as_str())",
+                string_obj, arg_strs[0]
+            ))
+        }
+        "endsWith" if arg_strs.len() == 1 => {
+            return Ok(format!(
+                "{}.ends_with(({}).as_str())",
+                string_obj, arg_strs[0]
+            ))
+        }
+        "includes" if is_string && arg_strs.len() == 1 => {
+            return Ok(format!(
+                "{}.contains(({}).as_str())",
+                string_obj, arg_strs[0]
+            ))
+        }
         "indexOf" if is_string && arg_strs.len() == 1 => {
             return Ok(format!(
                 "{{ let __trust_s = &{}; match __trust_s.find(({}).as_str()) {{ Some(__trust_byte) => __trust_s.char_indices().take_while(|(i, _)| *i < __trust_byte).count() as i32, None => -1 }} }}",
@@ -688,15 +2085,26 @@ fn transpile_member_call(
             ));
         }
         "replace" if arg_strs.len() == 2 => {
-            return Ok(format!("{}.replacen(({}).as_str(), ({}).as_str(), 1)", string_obj, arg_strs[0], arg_strs[1]));
+            return Ok(format!(
+                "{}.replacen(({}).as_str(), ({}).as_str(), 1)",
+                string_obj, arg_strs[0], arg_strs[1]
+            ));
         }
         "replaceAll" if arg_strs.len() == 2 => {
-            return Ok(format!("{}.replace(({}).as_str(), ({}).as_str())", string_obj, arg_strs[0], arg_strs[1]));
+            return Ok(format!(
+                "{}.replace(({}).as_str(), ({}).as_str())",
+                string_obj, arg_strs[0], arg_strs[1]
+            ));
         }
         "trim" => return Ok(format!("{}.trim().to_string()", string_obj)),
         "trimStart" => return Ok(format!("{}.trim_start().to_string()", string_obj)),
         "trimEnd" => return Ok(format!("{}.trim_end().to_string()", string_obj)),
-        "repeat" if arg_strs.len() == 1 => return Ok(format!("{}.repeat(({}).max(0) as usize)", string_obj, arg_strs[0])),
+        "repeat" if arg_strs.len() == 1 => {
+            return Ok(format!(
+                "{}.repeat(({}).max(0) as usize)",
+                string_obj, arg_strs[0]
+            ))
+        }
         "charAt" if arg_strs.len() == 1 => {
             return Ok(format!(
                 "{{ let __trust_i = ({}) as isize; if __trust_i < 0 {{ String::new() }} else {{ {}.chars().nth(__trust_i as usize).map(|c| c.to_string()).unwrap_or_default() }} }}",
@@ -765,17 +2173,60 @@ fn transpile_member_call(
     }
 
     // Array methods
+    if let Some(element) = member_type.as_deref().and_then(arrays::element_type) {
+        if let Some(call) = arrays::transpile_method(&obj, &prop, element, &arg_strs) {
+            return Ok(call);
+        }
+        if let Some(call) =
+            arrays::transpile_sort(member, &obj, &prop, element, args, &arg_strs, scope)?
+        {
+            return Ok(call);
+        }
+    }
     match prop.as_str() {
+        "push" if args.len() == 1 => {
+            if let Some(element) = member_type.as_deref().and_then(arrays::element_type) {
+                if matches!(&*args[0].expr, Expr::Object(_)) {
+                    let value = transpile_expression_as(&args[0].expr, element, scope)?;
+                    return Ok(format!("{}.push({})", obj, value));
+                }
+            }
+            let elem_ty = infer_rust_type(&args[0].expr, scope).unwrap_or_default();
+            let value = if needs_clone(&args[0].expr, &elem_ty, scope) {
+                format!("{}.clone()", arg_strs[0])
+            } else {
+                arg_strs[0].clone()
+            };
+            return Ok(format!("{}.push({})", obj, value));
+        }
         "push" => return Ok(format!("{}.push({})", obj, arg_strs.join(", "))),
         "pop" => return Ok(format!("{}.pop()", obj)),
         "len" => return Ok(format!("{}.len()", obj)),
-        "map" => return Ok(format!("{}.iter().map({}).collect::<Vec<_>>()", obj, arg_strs.join(", "))),
-        "filter" => return Ok(format!("{}.iter().filter({}).collect::<Vec<_>>()", obj, arg_strs.join(", "))),
+        "map" => {
+            return Ok(format!(
+                "{}.iter().map({}).collect::<Vec<_>>()",
+                obj,
+                arg_strs.join(", ")
+            ))
+        }
+        "filter" => {
+            return Ok(format!(
+                "{}.iter().filter({}).collect::<Vec<_>>()",
+                obj,
+                arg_strs.join(", ")
+            ))
+        }
         "forEach" => return Ok(format!("{}.iter().for_each({})", obj, arg_strs.join(", "))),
         "includes" => return Ok(format!("{}.contains(&{})", obj, arg_strs.join(", "))),
         "join" => return Ok(format!("{}.join({})", obj, arg_strs.join(", "))),
         "reverse" => return Ok(format!("{{ {}.reverse(); {} }}", obj, obj)),
-        "indexOf" => return Ok(format!("{}.iter().position(|r| r == &{}).map(|i| i as i32).unwrap_or(-1)", obj, arg_strs.join(", "))),
+        "indexOf" => {
+            return Ok(format!(
+                "{}.iter().position(|r| r == &{}).map(|i| i as i32).unwrap_or(-1)",
+                obj,
+                arg_strs.join(", ")
+            ))
+        }
         _ => {}
     }
 
@@ -791,11 +2242,23 @@ fn transpile_member_call(
         return Ok(format!("{}.{}()", obj, rust_method));
     }
 
+    // ── trusty:http — methods named after Rust keywords ──────────────────────
+    if let Some(rust_method) = stdlib_http::keyword_method(&prop) {
+        return Ok(format!("{}.{}({})", obj, rust_method, arg_strs.join(", ")));
+    }
+
+    if is_string {
+        check_string_method(member, &prop, scope);
+    }
+
     let module_alias_obj = ident_name(&member.obj)
         .and_then(|n| scope.get(&n).map(|t| is_module_alias_binding(t)))
         .unwrap_or(false);
     if module_alias_obj && prop == "log" && arg_strs.len() == 2 {
-        return Ok(format!("{}::log_base({}, {})", obj, arg_strs[0], arg_strs[1]));
+        return Ok(format!(
+            "{}::log_base({}, {})",
+            obj, arg_strs[0], arg_strs[1]
+        ));
     }
 
     // Uppercase identifier object = Rust type → use `::` (e.g. Instant::now(), Server::http())
@@ -804,7 +2267,16 @@ fn transpile_member_call(
         "::"
     } else {
         match &*member.obj {
-            Expr::Ident(ident) if ident.sym.chars().next().map(|c| c.is_uppercase()).unwrap_or(false) => "::",
+            Expr::Ident(ident)
+                if ident
+                    .sym
+                    .chars()
+                    .next()
+                    .map(|c| c.is_uppercase())
+                    .unwrap_or(false) =>
+            {
+                "::"
+            }
             _ => ".",
         }
     };
@@ -819,8 +2291,160 @@ fn transpile_member_call(
     ))
 }
 
-fn is_numeric_rust_type(ty: &str) -> bool {
-    matches!(ty, "i8" | "i16" | "i32" | "i64" | "u8" | "u16" | "u32" | "u64" | "isize" | "usize" | "f32" | "f64")
+/// Reports a call to a declared function or method with the wrong number of arguments.
+pub(super) fn check_arity(
+    callee: &str,
+    sig: &FunctionSig,
+    args: &[ExprOrSpread],
+    span: swc_common::Span,
+) -> Result<()> {
+    if args.len() == sig.params.len() {
+        return Ok(());
+    }
+    let message = msg!("arity-mismatch", callee, sig.params.len(), args.len());
+    Err(Diagnostic::error("arity-mismatch", message)
+        .with_span(source_span(span))
+        .into())
+}
+
+/// Renders call arguments, converting each to its declared parameter type.
+fn transpile_arguments(
+    args: &[ExprOrSpread],
+    sig: &FunctionSig,
+    scope: &Scope,
+) -> Result<Vec<String>> {
+    args.iter()
+        .zip(&sig.params)
+        .map(|(arg, (_, param_ty))| {
+            let rendered = transpile_expression(&arg.expr, scope)?;
+            if param_ty == DECIMAL {
+                return decimal::convert(&arg.expr, rendered, scope);
+            }
+            if needs_clone(&arg.expr, param_ty, scope) {
+                return Ok(format!("{}.clone()", rendered));
+            }
+            Ok(convert_argument(&arg.expr, rendered, param_ty, scope))
+        })
+        .collect()
+}
+
+/// An owned variable passed by value that the caller may still need (see [`AutoClone`]).
+fn needs_clone(arg: &Expr, param_ty: &str, scope: &Scope) -> bool {
+    let Expr::Ident(ident) = arg else {
+        return false;
+    };
+    let Some(arg_ty) = scope.get(ident.sym.as_ref()) else {
+        return false;
+    };
+    if arg_ty != param_ty || !is_owned_type(arg_ty) || is_module_alias_binding(arg_ty) {
+        return false;
+    }
+    match scope.options().auto_clone {
+        AutoClone::Always => true,
+        AutoClone::Reused => scope.uses().used_after(ident.sym.as_ref(), ident.span.lo.0),
+        AutoClone::Never => false,
+    }
+}
+
+/// A `ref T` / `mut ref T` binding read as a whole value: `*n`, or `n.clone()` for a referent that
+/// is not `Copy`. Other expressions are returned as rendered; field and method access need no `*`.
+pub fn binding_value(expr: &Expr, rendered: String, scope: &Scope) -> String {
+    let Expr::Ident(ident) = expr else {
+        return rendered;
+    };
+    match scope.get(ident.sym.as_ref()) {
+        Some(ty) if ty.starts_with('&') && is_owned_type(referent_type(ty)) => {
+            format!("{}.clone()", rendered)
+        }
+        Some(ty) if ty.starts_with('&') => format!("*{}", rendered),
+        _ => rendered,
+    }
+}
+
+/// Adapts an argument to its parameter type: borrows for `ref T` / `mut ref T` parameters,
+/// numeric casts, `String` → `&str`, and unwrapping `Pointer<T>`/`Threaded<T>` into a `T` copy.
+fn convert_argument(arg: &Expr, rendered: String, param_ty: &str, scope: &Scope) -> String {
+    let arg_ty = infer_rust_type(arg, scope);
+    if let Some(referent) = param_ty.strip_prefix('&') {
+        return borrow_argument(arg, rendered, referent, arg_ty.as_deref());
+    }
+    if arg_ty.as_deref().is_some_and(|ty| ty.starts_with('&')) {
+        return binding_value(arg, rendered, scope);
+    }
+    let Some(arg_ty) = arg_ty else {
+        return rendered;
+    };
+    if arg_ty == param_ty {
+        return rendered;
+    }
+    if is_numeric_rust_type(&arg_ty) && is_numeric_rust_type(param_ty) {
+        // Integer literals already adapt to any integer parameter.
+        let int_literal = matches!(arg, Expr::Lit(Lit::Num(_))) && !arg_ty.starts_with('f');
+        if int_literal && !param_ty.starts_with('f') {
+            return rendered;
+        }
+        return format!("({}) as {}", rendered, param_ty);
+    }
+    if arg_ty == format!("Rc<RefCell<{}>>", param_ty) {
+        return format!("{}.borrow().clone()", rendered);
+    }
+    if arg_ty == format!("Arc<Mutex<{}>>", param_ty) {
+        return format!("{}.lock().unwrap().clone()", rendered);
+    }
+    rendered
+}
+
+/// Call-site `&` / `&mut` for a reference parameter (`referent` is the type after the `&`).
+fn borrow_argument(arg: &Expr, rendered: String, referent: &str, arg_ty: Option<&str>) -> String {
+    if arg_ty.map(|t| t.starts_with('&')).unwrap_or(false) {
+        return rendered;
+    }
+    let (mutable, referent) = match referent.strip_prefix("mut ") {
+        Some(inner) => (true, inner),
+        None => (false, referent),
+    };
+    let lock = match arg_ty {
+        Some(t)
+            if t == format!("Rc<RefCell<{}>>", referent)
+                || t == "Rc<RefCell<String>>" && referent == "str" =>
+        {
+            Some(if mutable { "borrow_mut()" } else { "borrow()" })
+        }
+        Some(t)
+            if t == format!("Arc<Mutex<{}>>", referent)
+                || t == "Arc<Mutex<String>>" && referent == "str" =>
+        {
+            Some("lock().unwrap()")
+        }
+        _ => None,
+    };
+    let prefix = if mutable { "&mut " } else { "&" };
+    if let Some(lock) = lock {
+        return format!("{}*{}.{}", prefix, rendered, lock);
+    }
+    match arg {
+        Expr::Ident(_) | Expr::Member(_) | Expr::Call(_) | Expr::Lit(_) | Expr::Paren(_) => {
+            format!("{}{}", prefix, rendered)
+        }
+        _ => format!("{}({})", prefix, rendered),
+    }
+}
+
+pub(super) fn is_numeric_rust_type(ty: &str) -> bool {
+    matches!(
+        ty,
+        "i8" | "i16"
+            | "i32"
+            | "i64"
+            | "u8"
+            | "u16"
+            | "u32"
+            | "u64"
+            | "isize"
+            | "usize"
+            | "f32"
+            | "f64"
+    )
 }
 
 fn is_boolean_like_expr(expr: &Expr) -> bool {
@@ -846,7 +2470,10 @@ fn is_boolean_like_expr(expr: &Expr) -> bool {
                 Expr::Ident(ident) => ident.sym == "boolean",
                 Expr::Member(member) => match &member.prop {
                     MemberProp::Ident(ident) => {
-                        matches!(ident.sym.as_ref(), "includes" | "startsWith" | "endsWith" | "has")
+                        matches!(
+                            ident.sym.as_ref(),
+                            "includes" | "startsWith" | "endsWith" | "has"
+                        )
                     }
                     _ => false,
                 },
@@ -1,33 +1,114 @@
-use super::scope::{Scope, MODULE_ALIAS_MARKER};
+use super::expressions::binding_name;
+use super::locations::INSTALL_PANIC_HOOK;
+use super::scope::{result_error_type, Scope};
 use super::statements::transpile_block_stmt;
+use super::tasks::TASK;
 use super::types::*;
+use crate::diagnostics::Diagnostic;
+use crate::messages::msg;
+use crate::parser::source_span;
 use anyhow::{bail, Result};
 use swc_ecma_ast::*;
 
-pub fn transpile_function(func: &FnDecl, module_aliases: &[String]) -> Result<String> {
+pub fn transpile_function(func: &FnDecl, base_scope: &Scope) -> Result<String> {
     let name = &func.ident.sym;
-    let mut scope = base_scope(module_aliases);
+    if base_scope.symbols().const_fns.contains(name.as_ref()) {
+        return super::consts::transpile_const_fn(func, base_scope);
+    }
+    let mut scope = base_scope.clone();
+    if let Some(body) = &func.function.body {
+        scope.enter_function(body);
+        super::assignment::check_function_body(body)?;
+    }
     let params = transpile_params(&func.function.params, &mut scope)?;
     let return_type = transpile_return_type(&func.function.return_type)?;
+    scope.set_error_type(result_error_type(&return_type).map(str::to_string));
     if func.function.is_async {
         let body = transpile_async_block(&func.function.body, &mut scope)?;
         return Ok(format!(
-            "fn {}({}) -> std::thread::JoinHandle<{}> {{\n    std::thread::spawn(move || {{\n{}\n    }})\n}}",
-            name, params, return_type, body
+            "fn {}({}) -> {}<{}> {{\n    {}::spawn(move || {{\n{}\n    }})\n}}",
+            name, params, TASK, return_type, TASK, body
+        ));
+    }
+
+    let mut body = transpile_block(&func.function.body, &mut scope)?;
+    if name == "main" && scope.options().track_locations {
+        body = format!("    {}\n{}", INSTALL_PANIC_HOOK, body);
+    }
+    if name == "main" && return_type == "i32" {
+        // `function main(): int32` returns the process exit code.
+        return Ok(format!(
+            "fn __trust_main() -> i32 {{\n{}\n}}\n\nfn main() {{\n    let code = __trust_main();\n    std::io::Write::flush(&mut std::io::stdout()).ok();\n    std::process::exit(code);\n}}",
+            body
         ));
     }
+    Ok(format!(
+        "fn {}({}) -> {} {{\n{}\n}}",
+        name, params, return_type, body
+    ))
+}
+
+/// Checks the program's `main`: at most one, no parameters, not `async`, returning `void` or `int32`.
+/// With `required`, a program with neither `main` nor top-level statements is an error too.
+pub fn validate_entry_point(module: &Module, has_script: bool, required: bool) -> Result<()> {
+    let mains: Vec<&FnDecl> = module
+        .body
+        .iter()
+        .filter_map(|item| match item {
+            ModuleItem::Stmt(Stmt::Decl(Decl::Fn(func_decl)))
+                if func_decl.ident.sym.as_ref() == "main" =>
+            {
+                Some(func_decl)
+            }
+            _ => None,
+        })
+        .collect();
+
+    let Some(main) = mains.first() else {
+        if required && !has_script {
+            return Err(Diagnostic::error("missing-main", msg!("missing-main")).into());
+        }
+        return Ok(());
+    };
+    if let Some(duplicate) = mains.get(1) {
+        return Err(Diagnostic::error("duplicate-main", msg!("duplicate-main"))
+            .with_span(source_span(duplicate.ident.span))
+            .into());
+    }
+    let return_type = transpile_return_type(&main.function.return_type)?;
+    if !main.function.params.is_empty()
+        || main.function.is_async
+        || !matches!(return_type.as_str(), "()" | "i32")
+    {
+        return Err(Diagnostic::error("invalid-main", msg!("invalid-main"))
+            .with_span(source_span(main.ident.span))
+            .into());
+    }
+    Ok(())
+}
 
-    let body = transpile_block(&func.function.body, &mut scope)?;
-    Ok(format!("fn {}({}) -> {} {{\n{}\n}}", name, params, return_type, body))
+/// `main` for a script: the file's top-level statements, in order.
+pub fn implicit_main(stmts: Vec<Stmt>) -> FnDecl {
+    FnDecl {
+        ident: Ident::new_no_ctxt("main".into(), swc_common::DUMMY_SP),
+        declare: false,
+        function: Box::new(Function {
+            body: Some(BlockStmt {
+                stmts,
+                ..BlockStmt::default()
+            }),
+            ..Function::default()
+        }),
+    }
 }
 
-pub fn transpile_impl_block(class_decl: &ClassDecl, module_aliases: &[String]) -> Result<Option<String>> {
+pub fn transpile_impl_block(class_decl: &ClassDecl, base_scope: &Scope) -> Result<Option<String>> {
     let name = class_decl.ident.sym.to_string();
     let mut methods = Vec::new();
 
     for member in &class_decl.class.body {
         if let ClassMember::Method(method) = member {
-            if let Some(code) = transpile_impl_method(method, module_aliases)? {
+            if let Some(code) = transpile_impl_method(method, &name, base_scope)? {
                 methods.push(code);
             }
         }
@@ -37,10 +118,22 @@ pub fn transpile_impl_block(class_decl: &ClassDecl, module_aliases: &[String]) -
         return Ok(None);
     }
 
-    Ok(Some(format!("impl {} {{\n{}\n}}", name, methods.join("\n\n"))))
+    // Methods clone fields they hand out, so type parameters must be `Clone`.
+    let params = class_decl.class.type_params.as_deref();
+    Ok(Some(format!(
+        "impl{} {}{} {{\n{}\n}}",
+        super::structs::type_params(params, ": Clone"),
+        name,
+        super::structs::type_params(params, ""),
+        methods.join("\n\n")
+    )))
 }
 
-fn transpile_impl_method(method: &ClassMethod, module_aliases: &[String]) -> Result<Option<String>> {
+fn transpile_impl_method(
+    method: &ClassMethod,
+    struct_name: &str,
+    base_scope: &Scope,
+) -> Result<Option<String>> {
     if method.is_static {
         return Ok(None);
     }
@@ -53,9 +146,15 @@ fn transpile_impl_method(method: &ClassMethod, module_aliases: &[String]) -> Res
         _ => return Ok(None),
     };
 
-    let mut scope = base_scope(module_aliases);
+    let mut scope = base_scope.clone();
+    if let Some(body) = &method.function.body {
+        scope.enter_function(body);
+        super::assignment::check_function_body(body)?;
+    }
+    scope.insert("self".to_string(), struct_name.to_string());
     let params = transpile_params(&method.function.params, &mut scope)?;
     let return_type = transpile_return_type(&method.function.return_type)?;
+    scope.set_error_type(result_error_type(&return_type).map(str::to_string));
     let body = transpile_block(&method.function.body, &mut scope)?;
     let self_param = if method_needs_mut_self(&method.function) {
         "&mut self".to_string()
@@ -68,28 +167,38 @@ fn transpile_impl_method(method: &ClassMethod, module_aliases: &[String]) -> Res
         format!("{}, {}", self_param, params)
     };
 
-    Ok(Some(format!(
-        "    fn {}({}) -> {} {{\n{}\n    }}",
-        name, signature_params, return_type, body
+    let code = format!(
+        "{}    fn {}({}) -> {} {{\n{}\n    }}",
+        super::annotations::rust_attributes(method.span, "    ", scope.source())?,
+        name,
+        signature_params,
+        return_type,
+        body
+    );
+    let cfg = super::annotations::cfg_attribute(method.span, scope.source())?;
+    Ok(Some(super::annotations::with_cfg(
+        code,
+        cfg.as_deref(),
+        "    ",
     )))
 }
 
 fn transpile_params(params: &[Param], scope: &mut Scope) -> Result<String> {
-    let param_strs: Vec<String> = params
+    let param_strs = params
         .iter()
         .map(|p| {
-            let name = match &p.pat {
-                Pat::Ident(ident) => ident.id.sym.to_string(),
-                _ => "unknown".to_string(),
-            };
+            let name = binding_name(&p.pat, "unknown", scope)?;
+            if let Some(ann) = param_type_annotation(&p.pat) {
+                check_annotation(ann)?;
+            }
             let type_str = param_type_annotation(&p.pat)
                 .map(transpile_type_annotation)
                 .unwrap_or_else(|| "i32".to_string());
 
             scope.insert(name.clone(), type_str.clone());
-            format!("{}: {}", name, type_str)
+            Ok(format!("{}: {}", name, type_str))
         })
-        .collect();
+        .collect::<Result<Vec<_>>>()?;
 
     Ok(param_strs.join(", "))
 }
@@ -106,6 +215,7 @@ fn param_type_annotation(pat: &Pat) -> Option<&TsTypeAnn> {
 
 fn transpile_return_type(return_type: &Option<Box<TsTypeAnn>>) -> Result<String> {
     if let Some(type_ann) = return_type {
+        check_annotation(type_ann)?;
         Ok(transpile_type(&type_ann.type_ann))
     } else {
         Ok("()".to_string())
@@ -128,7 +238,7 @@ fn transpile_async_block(block: &Option<BlockStmt>, scope: &mut Scope) -> Result
     }
 }
 
-fn method_needs_mut_self(function: &Function) -> bool {
+pub(super) fn method_needs_mut_self(function: &Function) -> bool {
     function
         .body
         .as_ref()
@@ -143,14 +253,22 @@ fn stmt_mutates_this(stmt: &Stmt) -> bool {
         Stmt::If(if_stmt) => {
             expr_mutates_this(&if_stmt.test)
                 || stmt_mutates_this(&if_stmt.cons)
-                || if_stmt.alt.as_ref().map(|s| stmt_mutates_this(s)).unwrap_or(false)
+                || if_stmt
+                    .alt
+                    .as_ref()
+                    .map(|s| stmt_mutates_this(s))
+                    .unwrap_or(false)
         }
         Stmt::Decl(Decl::Var(var_decl)) => var_decl
             .decls
             .iter()
             .filter_map(|d| d.init.as_ref())
             .any(|expr| expr_mutates_this(expr)),
-        Stmt::Return(ret) => ret.arg.as_ref().map(|e| expr_mutates_this(e)).unwrap_or(false),
+        Stmt::Return(ret) => ret
+            .arg
+            .as_ref()
+            .map(|e| expr_mutates_this(e))
+            .unwrap_or(false),
         _ => false,
     }
 }
@@ -195,11 +313,3 @@ fn expr_is_this_member(expr: &Expr) -> bool {
 fn this_member(member: &MemberExpr) -> bool {
     matches!(&*member.obj, Expr::This(_))
 }
-
-fn base_scope(module_aliases: &[String]) -> Scope {
-    let mut scope = Scope::new();
-    for alias in module_aliases {
-        scope.insert(alias.clone(), MODULE_ALIAS_MARKER.to_string());
-    }
-    scope
-}
@@ -1,3 +1,7 @@
+use crate::compiler::CompileOptions;
+use crate::diagnostics::{closest, Diagnostic};
+use crate::messages::msg;
+use crate::parser::source_span;
 use crate::stdlib;
 use anyhow::{bail, Result};
 use swc_ecma_ast::*;
@@ -7,11 +11,15 @@ pub struct ImportInfo {
     pub use_statements: Vec<String>,
     /// External crate name(s) that must appear in Cargo.toml (empty for std / trusty:* std-only).
     pub required_crates: Vec<String>,
+    /// `(crate, version)` the stdlib module is written against, for the crates it requires.
+    pub crate_versions: Vec<(String, String)>,
     /// Names that should be treated as module aliases (`alias.member` => `alias::member` in Rust).
     pub module_aliases: Vec<String>,
+    /// Features of the runtime crate a linked `trusty:*` module needs.
+    pub runtime_features: Vec<String>,
 }
 
-pub fn transpile_import(import: &ImportDecl) -> Result<ImportInfo> {
+pub fn transpile_import(import: &ImportDecl, options: &CompileOptions) -> Result<ImportInfo> {
     let src = import.src.value.to_string_lossy();
     let default_alias = import.specifiers.iter().find_map(|spec| match spec {
         ImportSpecifier::Default(def) => Some(def.local.sym.to_string()),
@@ -27,42 +35,61 @@ pub fn transpile_import(import: &ImportDecl) -> Result<ImportInfo> {
         if default_alias.is_some() && has_non_default_specifier {
             bail!("Mixed default + named imports are not supported for trusty:* modules.");
         }
-        if let Some(stdlib_mod) = stdlib::resolve(module_name) {
+        let resolved = if options.runtime_crate {
+            stdlib::resolve_linked(module_name, &options.dependencies)
+        } else {
+            stdlib::resolve(module_name, &options.dependencies)
+        };
+        let resolved =
+            resolved.map_err(|diagnostic| diagnostic.with_span(source_span(import.src.span)))?;
+        if let Some(stdlib_mod) = resolved {
+            check_stdlib_names(import, module_name)?;
             if let Some(alias) = default_alias {
                 if module_name != "math" {
-                    bail!(
-                        "Default import alias is currently supported only for \"trusty:math\"."
-                    );
+                    bail!("Default import alias is currently supported only for \"trusty:math\".");
                 }
-                let wrapped_module = format!(
-                    "mod __trusty_{module_name} {{\n{}\n}}\nuse __trusty_{module_name} as {alias};",
-                    stdlib_mod.use_statements.join("\n")
-                );
+                let wrapped_module = if options.runtime_crate {
+                    format!("use {}::{module_name} as {alias};", stdlib::RUNTIME_CRATE)
+                } else {
+                    format!(
+                        "mod __trusty_{module_name} {{\n{}\n}}\nuse __trusty_{module_name} as {alias};",
+                        stdlib_mod.use_statements.join("\n")
+                    )
+                };
                 return Ok(ImportInfo {
                     use_statements: vec![wrapped_module],
                     required_crates: stdlib_mod
                         .required_crates
-                        .into_iter()
-                        .map(|(name, _version)| name)
+                        .iter()
+                        .map(|(name, _)| name.clone())
                         .collect(),
+                    crate_versions: stdlib_mod.required_crates,
                     module_aliases: vec![alias],
+                    runtime_features: stdlib_mod.runtime_features,
                 });
             }
             return Ok(ImportInfo {
                 use_statements: stdlib_mod.use_statements,
                 required_crates: stdlib_mod
                     .required_crates
-                    .into_iter()
-                    .map(|(name, _version)| name)
+                    .iter()
+                    .map(|(name, _)| name.clone())
                     .collect(),
+                crate_versions: stdlib_mod.required_crates,
                 module_aliases: vec![],
+                runtime_features: stdlib_mod.runtime_features,
             });
         }
         // Unknown trusty: module — emit a comment so the user knows
         return Ok(ImportInfo {
-            use_statements: vec![format!("// trusty:{} — module not yet implemented", module_name)],
+            use_statements: vec![format!(
+                "// trusty:{} — module not yet implemented",
+                module_name
+            )],
             required_crates: vec![],
+            crate_versions: vec![],
             module_aliases: vec![],
+            runtime_features: vec![],
         });
     }
 
@@ -74,7 +101,9 @@ pub fn transpile_import(import: &ImportDecl) -> Result<ImportInfo> {
         return Ok(ImportInfo {
             use_statements: vec![],
             required_crates: vec![],
+            crate_versions: vec![],
             module_aliases: vec![],
+            runtime_features: vec![],
         });
     }
 
@@ -94,7 +123,9 @@ pub fn transpile_import(import: &ImportDecl) -> Result<ImportInfo> {
         return Ok(ImportInfo {
             use_statements: vec![format!("use {} as {};", module_path, alias)],
             required_crates: crate_name.into_iter().collect(),
+            crate_versions: vec![],
             module_aliases: vec![alias],
+            runtime_features: vec![],
         });
     }
 
@@ -125,6 +156,34 @@ pub fn transpile_import(import: &ImportDecl) -> Result<ImportInfo> {
     Ok(ImportInfo {
         use_statements: vec![use_statement],
         required_crates: crate_name.into_iter().collect(),
+        crate_versions: vec![],
         module_aliases: vec![],
+        runtime_features: vec![],
     })
 }
+
+/// Rejects a named import `trusty:<module_name>` does not export, suggesting a close name.
+fn check_stdlib_names(import: &ImportDecl, module_name: &str) -> Result<()> {
+    let exports = stdlib::exports(module_name).unwrap_or_default();
+    for spec in &import.specifiers {
+        let ImportSpecifier::Named(named) = spec else {
+            continue;
+        };
+        let name = match &named.imported {
+            Some(ModuleExportName::Ident(ident)) => ident.sym.to_string(),
+            Some(ModuleExportName::Str(s)) => s.value.to_string_lossy().into_owned(),
+            None => named.local.sym.to_string(),
+        };
+        if exports.contains(&name.as_str()) {
+            continue;
+        }
+        let message = match closest(&name, &exports) {
+            Some(close) => msg!("unknown-import.suggestion", module_name, name, close),
+            None => msg!("unknown-import", module_name, name),
+        };
+        return Err(Diagnostic::error("unknown-import", message)
+            .with_span(source_span(named.span))
+            .into());
+    }
+    Ok(())
+}
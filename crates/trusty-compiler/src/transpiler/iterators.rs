@@ -0,0 +1,220 @@
+//! Iterators: `implements X: Iterable<T>` blocks become Rust `Iterator`s, and `iter(items)` starts
+//! a lazy `map`/`filter`/`take` chain that only builds a `Vec` at `collect()`.
+
+use super::expressions::infer_rust_type;
+use super::scope::Scope;
+use super::symbols::ProgramSymbols;
+use crate::diagnostics::Diagnostic;
+use crate::messages::{msg, Message};
+use crate::parser::source_span;
+use anyhow::Result;
+use swc_ecma_ast::*;
+
+/// The one trait an `implements` block can name.
+pub const ITERABLE: &str = "Iterable";
+
+/// Rust type of a lazy chain yielding `item`; `_` when the item type is not known.
+pub fn iterator_type(item: &str) -> String {
+    format!("impl Iterator<Item = {}>", item)
+}
+
+/// Item type of a lazy chain type made by [`iterator_type`].
+pub fn item_type(ty: &str) -> Option<&str> {
+    ty.strip_prefix("impl Iterator<Item = ")?.strip_suffix('>')
+}
+
+fn error(code: &'static str, message: Message, span: swc_common::Span) -> anyhow::Error {
+    Diagnostic::error(code, message)
+        .with_span(source_span(span))
+        .into()
+}
+
+/// `impl Iterator for X` for an `implements X: Iterable<T>` block: `next()` yields
+/// `Some(self.next())` while `self.hasNext()` holds.
+pub fn transpile_iterator_impl(
+    class_decl: &ClassDecl,
+    symbols: &ProgramSymbols,
+) -> Result<Option<String>> {
+    let name = class_decl.ident.sym.to_string();
+    for implemented in &class_decl.class.implements {
+        let trait_name = match &*implemented.expr {
+            Expr::Ident(ident) => ident.sym.to_string(),
+            _ => String::new(),
+        };
+        if trait_name != ITERABLE {
+            return Err(error(
+                "unknown-trait",
+                msg!("unknown-trait", trait_name),
+                implemented.span,
+            ));
+        }
+        if implemented
+            .type_args
+            .as_ref()
+            .map_or(0, |args| args.params.len())
+            != 1
+        {
+            return Err(error(
+                "unknown-trait",
+                msg!("unknown-trait.item"),
+                implemented.span,
+            ));
+        }
+    }
+    let Some(item) = symbols.iterable_item(&name) else {
+        return Ok(None);
+    };
+    let has_next = symbols.method(&name, "hasNext");
+    let next = symbols.method(&name, "next");
+    let valid = has_next.is_some_and(|sig| sig.params.is_empty() && sig.return_type == "bool")
+        && next.is_some_and(|sig| sig.params.is_empty() && sig.return_type == *item);
+    if !valid {
+        return Err(error(
+            "iterable-methods",
+            msg!("iterable-methods", name),
+            class_decl.ident.span,
+        ));
+    }
+    let params = class_decl.class.type_params.as_deref();
+    Ok(Some(format!(
+        "impl{} Iterator for {}{} {{\n    type Item = {};\n\n    fn next(&mut self) -> Option<{}> {{\n        if {}::hasNext(self) {{ Some({}::next(self)) }} else {{ None }}\n    }}\n}}",
+        super::structs::type_params(params, ": Clone"),
+        name,
+        super::structs::type_params(params, ""),
+        item,
+        item,
+        name,
+        name
+    )))
+}
+
+/// `iter(items)` → a lazy iterator over clones of the elements.
+pub fn transpile_iter_call(arg: &Expr, rendered: &str, scope: &Scope) -> String {
+    source(arg, rendered, scope).0
+}
+
+/// Type of `iter(items)`.
+pub fn iter_call_type(arg: &Expr, scope: &Scope) -> String {
+    iterator_type(&source(arg, "", scope).1)
+}
+
+/// The iterator `iter(items)` renders to, and its item type.
+fn source(arg: &Expr, rendered: &str, scope: &Scope) -> (String, String) {
+    let ty = infer_rust_type(arg, scope).unwrap_or_default();
+    if let Some(item) = ty.strip_prefix("Vec<").and_then(|t| t.strip_suffix('>')) {
+        return (format!("({}).iter().cloned()", rendered), item.to_string());
+    }
+    if ty == "String" {
+        return (format!("({}).chars().map(|c| c.to_string())", rendered), ty);
+    }
+    if let Some(item) = item_type(&ty) {
+        return (rendered.to_string(), item.to_string());
+    }
+    if let Some(item) = scope.symbols().iterable_item(&ty) {
+        return (format!("({}).clone()", rendered), item.clone());
+    }
+    if let Some(item) = ty
+        .strip_prefix("std::ops::Range<")
+        .or_else(|| ty.strip_prefix("std::ops::RangeInclusive<"))
+        .and_then(|t| t.strip_suffix('>'))
+    {
+        return (rendered.to_string(), item.to_string());
+    }
+    (format!("({}).into_iter()", rendered), "_".to_string())
+}
+
+/// What a `for (x of items)` loop iterates when `items` is a lazy chain or an `Iterable` value,
+/// with the loop variable's type.
+pub fn for_of(
+    ty: &str,
+    rendered: &str,
+    symbols: &ProgramSymbols,
+) -> Option<(String, Option<String>)> {
+    let known = |item: &str| (item != "_").then(|| item.to_string());
+    if let Some(item) = item_type(ty) {
+        return Some((rendered.to_string(), known(item)));
+    }
+    let item = symbols.iterable_item(ty)?;
+    Some((format!("({}).clone()", rendered), known(item)))
+}
+
+/// `.map(f)`, `.filter(f)`, `.take(n)`, ... on a lazy chain yielding `item`; only `collect()`,
+/// `reduce()` and the other terminal methods run it.
+pub fn transpile_method(
+    obj: &str,
+    method: &str,
+    item: &str,
+    arg_strs: &[String],
+) -> Option<String> {
+    // `filter` and `find` see `&T`; TRUST callbacks take the element itself.
+    let by_value = |f: &str| format!("|__trust_item| ({})(__trust_item.clone())", f);
+    // Methods taking `&mut self` run on a mutable copy of the chain.
+    let consume = |call: String| {
+        format!(
+            "{{ let mut __trust_iter = {}; __trust_iter.{} }}",
+            obj, call
+        )
+    };
+    Some(match (method, arg_strs) {
+        ("map", [f]) => format!("{}.map({})", obj, f),
+        ("filter", [f]) => format!("{}.filter({})", obj, by_value(f)),
+        ("take" | "skip", [n]) => format!("{}.{}(({}) as usize)", obj, method, n),
+        ("collect", []) => format!("{}.collect::<Vec<_>>()", obj),
+        ("forEach", [f]) => format!("{}.for_each({})", obj, f),
+        ("count", []) => format!("({}.count() as i32)", obj),
+        ("sum", []) => format!(
+            "{}.sum::<{}>()",
+            obj,
+            if item == "_" { "i32" } else { item }
+        ),
+        ("reduce", [f, init]) => format!("{}.fold({}, {})", obj, init, f),
+        ("first", []) => consume("next()".to_string()),
+        ("find", [f]) => consume(format!("find({})", by_value(f))),
+        ("some", [f]) => consume(format!("any({})", f)),
+        ("every", [f]) => consume(format!("all({})", f)),
+        _ => return None,
+    })
+}
+
+/// Rust type returned by a lazy chain method.
+pub fn method_return_type(
+    method: &str,
+    item: &str,
+    args: &[ExprOrSpread],
+    scope: &Scope,
+) -> Option<String> {
+    match method {
+        "map" => Some(iterator_type(&mapped_item(args.first()?, item, scope))),
+        "filter" | "take" | "skip" => Some(iterator_type(item)),
+        "collect" => (item != "_").then(|| format!("Vec<{}>", item)),
+        "count" => Some("i32".to_string()),
+        "sum" => Some(if item == "_" { "i32" } else { item }.to_string()),
+        "reduce" => infer_rust_type(&args.get(1)?.expr, scope),
+        "first" | "find" => (item != "_").then(|| format!("Option<{}>", item)),
+        "some" | "every" => Some("bool".to_string()),
+        _ => None,
+    }
+}
+
+/// Item type after `.map(f)`: the callback's return type, when it can be told.
+pub fn mapped_item(f: &ExprOrSpread, item: &str, scope: &Scope) -> String {
+    let inferred = match &*f.expr {
+        Expr::Arrow(arrow) => match (arrow.params.as_slice(), &*arrow.body) {
+            ([Pat::Ident(param)], BlockStmtOrExpr::Expr(body)) => {
+                let mut scope = scope.clone();
+                scope.push();
+                if item != "_" {
+                    scope.insert(param.id.sym.to_string(), item.to_string());
+                }
+                infer_rust_type(body, &scope)
+            }
+            _ => None,
+        },
+        Expr::Ident(ident) => scope
+            .symbols()
+            .function(ident.sym.as_ref())
+            .map(|sig| sig.return_type.clone()),
+        _ => None,
+    };
+    inferred.unwrap_or_else(|| "_".to_string())
+}
@@ -0,0 +1,103 @@
+//! Optional runtime location tracking ([`CompileOptions::track_locations`]).
+//!
+//! Before each statement runs, the generated code records its TRUST location in a thread-local, and
+//! `main` installs a panic hook that reports the panic message at that location rather than at a line
+//! of the generated Rust.
+//!
+//! The same markers give the [`line_map`] from generated Rust lines back to TRUST locations, used by
+//! `trusty build --debug` to set breakpoints by TRUST line.
+//!
+//! [`CompileOptions::track_locations`]: crate::CompileOptions::track_locations
+
+use super::scope::Scope;
+use swc_common::Spanned;
+use swc_ecma_ast::*;
+
+/// Records the current TRUST location; emitted before each tracked statement.
+const TRACK_CALLEE: &str = "__trust_at";
+
+/// First statement of `main` when tracking.
+pub const INSTALL_PANIC_HOOK: &str = "__trust_install_panic_hook();";
+
+/// `__trust_at("main.trs:3:5");` for a statement that runs code, when tracking is enabled.
+pub fn statement_marker(stmt: &Stmt, scope: &Scope) -> Option<String> {
+    if !scope.options().track_locations
+        || matches!(stmt, Stmt::Block(_) | Stmt::Empty(_) | Stmt::Labeled(_))
+    {
+        return None;
+    }
+    Some(format!(
+        "{}({:?});",
+        TRACK_CALLEE,
+        scope.location(stmt.span())
+    ))
+}
+
+/// A generated Rust line and the TRUST statement it runs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineMapping {
+    /// 1-based line in the generated Rust source.
+    pub rust_line: usize,
+    /// Module the statement comes from; `None` for source compiled without bundling.
+    pub file: Option<String>,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Maps lines of Rust generated with location tracking back to the TRUST statements they run.
+pub fn line_map(rust_code: &str) -> Vec<LineMapping> {
+    let prefix = format!("{}(\"", TRACK_CALLEE);
+    rust_code
+        .lines()
+        .enumerate()
+        .filter_map(|(index, text)| {
+            let marker = text.trim_start().strip_prefix(&prefix)?;
+            let location = &marker[..marker.find("\");")?];
+            let (rest, column) = location.rsplit_once(':')?;
+            let (file, line) = match rest.strip_prefix("line ") {
+                Some(line) => (None, line),
+                None => {
+                    let (file, line) = rest.rsplit_once(':')?;
+                    (Some(file.replace("\\\\", "\\")), line)
+                }
+            };
+            Some(LineMapping {
+                rust_line: index + 1,
+                file,
+                line: line.parse().ok()?,
+                column: column.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// Thread-local location, its setter and the panic hook, included once per tracked program.
+pub fn runtime() -> String {
+    format!(
+        r#"thread_local! {{
+    static __TRUST_LOCATION: std::cell::Cell<&'static str> = const {{ std::cell::Cell::new("") }};
+}}
+
+fn {track}(location: &'static str) {{
+    __TRUST_LOCATION.with(|current| current.set(location));
+}}
+
+fn __trust_install_panic_hook() {{
+    std::panic::set_hook(Box::new(|info| {{
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "explicit panic".to_string());
+        let location = __TRUST_LOCATION.with(|current| current.get());
+        if location.is_empty() {{
+            eprintln!("error: {{}}", message);
+        }} else {{
+            eprintln!("error: {{}}\n  --> {{}}", message, location);
+        }}
+    }}));
+}}"#,
+        track = TRACK_CALLEE
+    )
+}
@@ -0,0 +1,211 @@
+//! Lowering of `match (subject) { pattern => value, ... }`.
+//!
+//! The preprocessor rewrites each match into a call of [`MATCH_CALLEE`]: the subject followed by one
+//! `[pattern, value]` array per arm, with `default` spelled [`DEFAULT_PATTERN`]. A subject whose type
+//! is a declared enum becomes a Rust `match` checked for exhaustiveness here; any other subject is
+//! compared arm by arm.
+
+use super::expressions::{enum_variant, infer_rust_type, transpile_expression};
+use super::scope::{referent_type, Scope};
+use crate::diagnostics::Diagnostic;
+use crate::messages::msg;
+use crate::parser::source_span;
+use anyhow::{bail, Result};
+use swc_common::Spanned;
+use swc_ecma_ast::*;
+
+pub const MATCH_CALLEE: &str = "__trust_match";
+pub const DEFAULT_PATTERN: &str = "__trust_default";
+
+/// Binding holding the subject in the equality-chain lowering.
+const SUBJECT_BINDING: &str = "__trust_match";
+
+struct Arm<'a> {
+    /// `None` for the `default` arm.
+    pattern: Option<&'a Expr>,
+    value: &'a Expr,
+}
+
+pub fn transpile_match(call: &CallExpr, scope: &Scope) -> Result<String> {
+    let Some((subject, arms)) = call.args.split_first() else {
+        bail!("`match` needs a subject");
+    };
+    let arms = arms
+        .iter()
+        .map(|arg| parse_arm(&arg.expr))
+        .collect::<Result<Vec<_>>>()?;
+
+    if let Some(subject_ty) = infer_rust_type(&subject.expr, scope) {
+        let enum_name = referent_type(&subject_ty);
+        if let Some(variants) = scope.symbols().enum_variants(enum_name) {
+            return transpile_enum_match(
+                &subject.expr,
+                enum_name,
+                variants,
+                &arms,
+                call.span,
+                scope,
+            );
+        }
+    }
+    transpile_equality_chain(&subject.expr, &arms, scope)
+}
+
+/// Type of the first arm's value, which every other arm must share.
+pub fn infer_match_type(args: &[ExprOrSpread], scope: &Scope) -> Option<String> {
+    args.iter()
+        .skip(1)
+        .filter_map(|arg| parse_arm(&arg.expr).ok())
+        .find_map(|arm| infer_rust_type(arm.value, scope))
+}
+
+fn parse_arm(expr: &Expr) -> Result<Arm<'_>> {
+    let Expr::Array(array) = expr else {
+        bail!("malformed `match` arm");
+    };
+    let [Some(pattern), Some(value)] = array.elems.as_slice() else {
+        bail!("malformed `match` arm");
+    };
+    let is_default =
+        matches!(&*pattern.expr, Expr::Ident(ident) if ident.sym.as_ref() == DEFAULT_PATTERN);
+    Ok(Arm {
+        pattern: (!is_default).then_some(&*pattern.expr),
+        value: &value.expr,
+    })
+}
+
+/// `match c { Color::Red => ..., Color::Green | Color::Blue => ... }`; every variant must be covered
+/// unless there is a `default` arm.
+fn transpile_enum_match(
+    subject: &Expr,
+    enum_name: &str,
+    variants: &[String],
+    arms: &[Arm],
+    span: swc_common::Span,
+    scope: &Scope,
+) -> Result<String> {
+    let mut covered: Vec<&str> = Vec::new();
+    let mut has_default = false;
+    let mut rendered = Vec::new();
+
+    for arm in arms {
+        let pattern = match arm.pattern {
+            None => {
+                has_default = true;
+                "_".to_string()
+            }
+            Some(Expr::Array(alternatives)) => alternatives
+                .elems
+                .iter()
+                .flatten()
+                .map(|alt| enum_pattern(&alt.expr, enum_name, variants, &mut covered, scope))
+                .collect::<Result<Vec<_>>>()?
+                .join(" | "),
+            Some(pattern) => enum_pattern(pattern, enum_name, variants, &mut covered, scope)?,
+        };
+        rendered.push(format!(
+            "{} => {}",
+            pattern,
+            transpile_expression(arm.value, scope)?
+        ));
+    }
+
+    if !has_default {
+        let missing: Vec<String> = variants
+            .iter()
+            .filter(|v| !covered.contains(&v.as_str()))
+            .map(|v| format!("`{}.{}`", enum_name, v))
+            .collect();
+        if !missing.is_empty() {
+            let message = msg!("non-exhaustive-match", enum_name, missing.join(", "));
+            return Err(Diagnostic::error("non-exhaustive-match", message)
+                .with_span(source_span(span))
+                .into());
+        }
+    }
+
+    Ok(format!(
+        "match {} {{ {} }}",
+        transpile_expression(subject, scope)?,
+        rendered.join(", ")
+    ))
+}
+
+/// One `Color.Red` alternative → `Color::Red`, recording the variant as covered.
+fn enum_pattern<'a>(
+    pattern: &'a Expr,
+    enum_name: &str,
+    variants: &[String],
+    covered: &mut Vec<&'a str>,
+    scope: &Scope,
+) -> Result<String> {
+    let variant = match pattern {
+        Expr::Member(member) => match enum_variant(member, scope) {
+            Some((name, variant)) if name == enum_name => variant,
+            _ => return Err(enum_pattern_error(pattern, enum_name)),
+        },
+        _ => return Err(enum_pattern_error(pattern, enum_name)),
+    };
+    if !variants.iter().any(|v| v == variant) {
+        return Err(Diagnostic::error(
+            "unknown-variant",
+            msg!("unknown-variant", variant, enum_name),
+        )
+        .with_span(source_span(pattern.span()))
+        .into());
+    }
+    covered.push(variant);
+    Ok(format!("{}::{}", enum_name, variant))
+}
+
+fn enum_pattern_error(pattern: &Expr, enum_name: &str) -> anyhow::Error {
+    Diagnostic::error(
+        "invalid-match-pattern",
+        msg!("invalid-match-pattern", enum_name),
+    )
+    .with_span(source_span(pattern.span()))
+    .into()
+}
+
+/// `{ let __trust_match = s; if __trust_match == (p) { v } else if [a, b].contains(&__trust_match) { w } else { d } }`
+fn transpile_equality_chain(subject: &Expr, arms: &[Arm], scope: &Scope) -> Result<String> {
+    let mut out = format!(
+        "{{ let {} = {}; ",
+        SUBJECT_BINDING,
+        transpile_expression(subject, scope)?
+    );
+    let mut default = None;
+    let mut branches = 0;
+    for arm in arms {
+        let value = transpile_expression(arm.value, scope)?;
+        let Some(pattern) = arm.pattern else {
+            default = Some(value);
+            continue;
+        };
+        let cond = match pattern {
+            Expr::Array(_) => format!(
+                "{}.contains(&{})",
+                transpile_expression(pattern, scope)?,
+                SUBJECT_BINDING
+            ),
+            _ => format!(
+                "{} == ({})",
+                SUBJECT_BINDING,
+                transpile_expression(pattern, scope)?
+            ),
+        };
+        if branches > 0 {
+            out.push_str("else ");
+        }
+        out.push_str(&format!("if {} {{ {} }} ", cond, value));
+        branches += 1;
+    }
+    match (branches, default) {
+        (0, Some(d)) => out.push_str(&d),
+        (0, None) => out.push_str("panic!(\"non-exhaustive match\")"),
+        (_, Some(d)) => out.push_str(&format!("else {{ {} }}", d)),
+        (_, None) => out.push_str("else { panic!(\"non-exhaustive match\") }"),
+    }
+    out.push_str(" }");
+    Ok(out)
+}
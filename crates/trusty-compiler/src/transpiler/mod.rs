@@ -1,38 +1,103 @@
+pub mod annotations;
+pub mod arrays;
+pub mod assignment;
+pub mod bytes;
+pub mod consts;
+pub mod decimal;
 pub mod enums;
+pub mod errors;
 pub mod expressions;
 pub mod functions;
 pub mod imports;
+pub mod iterators;
+pub mod locations;
+pub mod matching;
+pub mod nullish;
+pub mod objects;
+pub mod overflow;
+pub mod ownership;
 pub mod scope;
 pub mod statements;
+pub mod statics;
+pub mod strings;
 pub mod structs;
+pub mod symbols;
+pub mod tasks;
+pub mod treeshake;
 pub mod types;
 
+use crate::compiler::CompileOptions;
+use crate::diagnostics::Diagnostic;
+use crate::messages::msg;
+use crate::parser::source_span;
+use crate::preprocessor::Preprocessed;
 use anyhow::Result;
+use scope::{Scope, MODULE_ALIAS_MARKER};
+use std::collections::HashMap;
+use std::rc::Rc;
+use swc_common::Spanned;
 use swc_ecma_ast::*;
 
 pub struct TranspileOutput {
     pub rust_code: String,
     /// External crate names required (from import declarations)
     pub required_crates: Vec<String>,
+    /// `(crate, version)` pinned by `trusty:*` modules for some of `required_crates`.
+    pub crate_versions: Vec<(String, String)>,
+    /// Features of the `trusty_runtime` crate to enable, when it is among `required_crates`.
+    pub runtime_features: Vec<String>,
+    /// Warnings about code that transpiles but likely fails `rustc`.
+    pub warnings: Vec<Diagnostic>,
 }
 
-pub fn transpile_to_rust(module: &Module) -> Result<TranspileOutput> {
+/// `export struct`/`export function` in a single file compile like their plain forms (the bundler
+/// strips `export` from imported modules).
+fn without_exports(module: &Module) -> Module {
+    let mut module = module.clone();
+    for item in &mut module.body {
+        if let ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(export)) = item {
+            *item = ModuleItem::Stmt(Stmt::Decl(export.decl.clone()));
+        }
+    }
+    module
+}
+
+/// Transpiles `module`, parsed from the `source` preprocessed text.
+pub fn transpile_to_rust(
+    module: &Module,
+    source: &Preprocessed,
+    options: &CompileOptions,
+) -> Result<TranspileOutput> {
+    let module = &without_exports(module);
     let mut use_statements: Vec<String> = Vec::new();
+    let mut stdlib_blocks: Vec<String> = Vec::new(); // `trusty:*` code, tree-shaken at the end
     let mut type_decls: Vec<String> = Vec::new(); // structs + enums
     let mut impl_blocks: Vec<String> = Vec::new();
     let mut global_consts: Vec<String> = Vec::new();
+    let mut global_statics: Vec<String> = Vec::new();
     let mut function_code: Vec<String> = Vec::new();
     let mut required_crates: Vec<String> = Vec::new();
+    let mut crate_versions: Vec<(String, String)> = Vec::new();
+    let mut runtime_features: Vec<String> = Vec::new();
     let mut module_aliases: Vec<String> = Vec::new();
+    let mut script_stmts: Vec<Stmt> = Vec::new();
     let mut json_enabled = false;
 
     // Pass 1: imports
     for item in &module.body {
         if let ModuleItem::ModuleDecl(ModuleDecl::Import(import_decl)) = item {
-            let info = imports::transpile_import(import_decl)?;
+            let info = imports::transpile_import(import_decl, options)?;
+            let source_module = import_decl.src.value.to_string_lossy();
+            // A default import wraps the module in a `mod`, which is kept whole.
+            let shaken = source_module.starts_with("trusty:") && info.module_aliases.is_empty();
+            let statements = if shaken {
+                &mut stdlib_blocks
+            } else {
+                &mut use_statements
+            };
             for stmt in info.use_statements {
-                if !use_statements.contains(&stmt) {
-                    use_statements.push(stmt);
+                if !statements.contains(&stmt) {
+                    statements.push(stmt);
                 }
             }
             for name in info.required_crates {
@@ -40,88 +105,332 @@ pub fn transpile_to_rust(module: &Module) -> Result<TranspileOutput> {
                     required_crates.push(name);
                 }
             }
+            for (name, version) in info.crate_versions {
+                if !crate_versions.iter().any(|(pinned, _)| *pinned == name) {
+                    crate_versions.push((name, version));
+                }
+            }
+            for feature in info.runtime_features {
+                if !runtime_features.contains(&feature) {
+                    runtime_features.push(feature);
+                }
+            }
             for alias in info.module_aliases {
                 if !module_aliases.contains(&alias) {
                     module_aliases.push(alias);
                 }
             }
-            if import_decl.src.value.to_string_lossy().as_ref() == "trusty:json" {
+            if source_module.as_ref() == "trusty:json" {
                 json_enabled = true;
             }
         }
     }
 
+    // Every function body starts from the module aliases, the program's declarations and the options.
+    let mut symbols = symbols::ProgramSymbols::collect(module);
+    symbols.const_fns = consts::const_functions(module, &symbols);
+    let declarations = Scope::for_program(Rc::new(symbols.clone()), Rc::default(), Rc::default());
+    for item in &module.body {
+        if let ModuleItem::Stmt(Stmt::Decl(Decl::Var(var_decl))) = item {
+            if source.is_static(source_span(var_decl.span).start) {
+                symbols
+                    .statics
+                    .extend(statics::collect(var_decl, &declarations));
+            }
+        }
+    }
+    objects::infer_structs(module, &mut symbols, options, source);
+    let mut base_scope = Scope::for_program(
+        Rc::new(symbols),
+        Rc::new(options.clone()),
+        Rc::new(source.clone()),
+    );
+    for alias in &module_aliases {
+        base_scope.insert(alias.clone(), MODULE_ALIAS_MARKER.to_string());
+    }
+
     // Pass 2: declarations and executable code
+    annotations::check_placement(module, source)?;
+    let struct_base: &[&str] = if json_enabled {
+        &[
+            "Debug",
+            "Clone",
+            "serde_derive::Serialize",
+            "serde_derive::Deserialize",
+        ]
+    } else {
+        &["Debug", "Clone"]
+    };
+    let mut const_env = consts::ConstEnv::default();
+    // `@cfg` on a struct or enum also covers its `implements` block.
+    let mut type_cfgs: HashMap<String, String> = HashMap::new();
     for item in &module.body {
-        match item {
-            ModuleItem::ModuleDecl(ModuleDecl::Import(_)) => {}
-            ModuleItem::Stmt(Stmt::Decl(Decl::TsInterface(interface_decl))) => {
-                let struct_code = structs::transpile_interface(interface_decl, json_enabled)?;
-                type_decls.push(struct_code);
+        let (name, span) = match item {
+            ModuleItem::Stmt(Stmt::Decl(Decl::TsInterface(decl))) => {
+                (decl.id.sym.to_string(), decl.span)
             }
-            ModuleItem::Stmt(Stmt::Decl(Decl::TsEnum(enum_decl))) => {
-                let enum_code = enums::transpile_enum(enum_decl)?;
-                type_decls.push(enum_code);
+            ModuleItem::Stmt(Stmt::Decl(Decl::TsEnum(decl))) => {
+                (decl.id.sym.to_string(), decl.span)
             }
+            _ => continue,
+        };
+        if let Some(cfg) = annotations::cfg_attribute(span, source)? {
+            type_cfgs.insert(name, cfg);
+        }
+    }
+    for item in &module.body {
+        match item {
+            ModuleItem::ModuleDecl(ModuleDecl::Import(_)) => {}
+            // Transpiled after the code using them, which decides what they derive.
+            ModuleItem::Stmt(Stmt::Decl(Decl::TsInterface(_) | Decl::TsEnum(_))) => {}
             ModuleItem::Stmt(Stmt::Decl(Decl::Fn(func_decl))) => {
-                let func_code = functions::transpile_function(func_decl, &module_aliases)?;
-                function_code.push(func_code);
+                let cfg = annotations::cfg_attribute(func_decl.function.span, source)?;
+                let attributes = annotations::rust_attributes(func_decl.function.span, "", source)?;
+                let func_code = functions::transpile_function(func_decl, &base_scope)?;
+                function_code.push(annotations::with_cfg(
+                    format!("{}{}", attributes, func_code),
+                    cfg.as_deref(),
+                    "",
+                ));
             }
             ModuleItem::Stmt(Stmt::Decl(Decl::Class(class_decl))) => {
-                if let Some(impl_code) = functions::transpile_impl_block(class_decl, &module_aliases)? {
-                    impl_blocks.push(impl_code);
+                let cfg = type_cfgs
+                    .get(class_decl.ident.sym.as_ref())
+                    .map(String::as_str);
+                if let Some(impl_code) = functions::transpile_impl_block(class_decl, &base_scope)? {
+                    impl_blocks.push(annotations::with_cfg(impl_code, cfg, ""));
+                }
+                if let Some(iterator_impl) =
+                    iterators::transpile_iterator_impl(class_decl, base_scope.symbols())?
+                {
+                    impl_blocks.push(annotations::with_cfg(iterator_impl, cfg, ""));
+                }
+            }
+            ModuleItem::Stmt(Stmt::Decl(Decl::Var(var_decl)))
+                if var_decl.kind == VarDeclKind::Const =>
+            {
+                let cfg = annotations::cfg_attribute(var_decl.span, source)?;
+                for code in consts::transpile_global_const(var_decl, &mut const_env, &base_scope)? {
+                    global_consts.push(annotations::with_cfg(code, cfg.as_deref(), ""));
+                }
+            }
+            ModuleItem::Stmt(Stmt::Decl(Decl::Var(var_decl)))
+                if source.is_static(source_span(var_decl.span).start) =>
+            {
+                let cfg = annotations::cfg_attribute(var_decl.span, source)?;
+                for code in statics::transpile_static(var_decl, &base_scope)? {
+                    global_statics.push(annotations::with_cfg(code, cfg.as_deref(), ""));
+                }
+            }
+            // `val`/`var` and executable statements outside functions make up a script's `main`.
+            ModuleItem::Stmt(stmt @ Stmt::Decl(Decl::Var(_))) => script_stmts.push(stmt.clone()),
+            ModuleItem::Stmt(
+                stmt @ Stmt::Decl(Decl::TsTypeAlias(_) | Decl::TsModule(_) | Decl::Using(_)),
+            ) => {
+                let construct = statements::statement_keyword(stmt);
+                base_scope.unsupported(
+                    "unsupported-statement",
+                    msg!("unsupported-statement", construct),
+                    stmt.span(),
+                )?;
+            }
+            ModuleItem::Stmt(Stmt::Empty(_)) => {}
+            ModuleItem::Stmt(stmt) => script_stmts.push(stmt.clone()),
+            // Imports are resolved above and `export` declarations unwrapped by `without_exports`.
+            ModuleItem::ModuleDecl(decl) => {
+                let construct = if matches!(decl, ModuleDecl::TsImportEquals(_)) {
+                    "import ="
+                } else {
+                    "export"
+                };
+                base_scope.unsupported(
+                    "unsupported-statement",
+                    msg!("unsupported-statement", construct),
+                    decl.span(),
+                )?;
+            }
+        }
+    }
+
+    functions::validate_entry_point(module, !script_stmts.is_empty(), options.require_main)?;
+    if let Some(first) = script_stmts.first() {
+        if base_scope.symbols().function("main").is_some() {
+            return Err(
+                Diagnostic::error("top-level-statement", msg!("top-level-statement"))
+                    .with_span(source_span(first.span()))
+                    .into(),
+            );
+        }
+        let main = functions::implicit_main(script_stmts);
+        function_code.push(functions::transpile_function(&main, &base_scope)?);
+    }
+
+    // Pass 3: structs and enums, with `PartialEq` for the ones compared with `==`
+    let compared = base_scope
+        .symbols()
+        .user_types_in(&base_scope.compared_types());
+    for item in &module.body {
+        match item {
+            ModuleItem::Stmt(Stmt::Decl(Decl::TsInterface(interface_decl))) => {
+                let mut base = struct_base.to_vec();
+                if compared.contains(interface_decl.id.sym.as_ref()) {
+                    base.push("PartialEq");
                 }
+                let derives = annotations::derives(&base, interface_decl.span, options, source)?;
+                let name = interface_decl.id.sym.as_ref();
+                let own_to_string = base_scope.symbols().method(name, "toString").is_some();
+                let attributes = annotations::rust_attributes(interface_decl.span, "", source)?;
+                let code = structs::transpile_interface(interface_decl, &derives, own_to_string)?;
+                let cfg = type_cfgs.get(name).map(String::as_str);
+                type_decls.push(annotations::with_cfg_per_item(
+                    format!("{}{}", attributes, code),
+                    cfg,
+                ));
             }
-            ModuleItem::Stmt(Stmt::Decl(Decl::Var(var_decl))) => {
-                global_consts.extend(statements::transpile_global_const(var_decl)?);
+            ModuleItem::Stmt(Stmt::Decl(Decl::TsEnum(enum_decl))) => {
+                let mut base = vec!["Debug", "Clone"];
+                if compared.contains(enum_decl.id.sym.as_ref()) {
+                    base.push("PartialEq");
+                }
+                let derives = annotations::derives(&base, enum_decl.span, options, source)?;
+                let attributes = annotations::rust_attributes(enum_decl.span, "", source)?;
+                let code = enums::transpile_enum(enum_decl, &derives, options.enum_utilities)?;
+                let cfg = type_cfgs.get(enum_decl.id.sym.as_ref()).map(String::as_str);
+                type_decls.push(annotations::with_cfg_per_item(
+                    format!("{}{}", attributes, code),
+                    cfg,
+                ));
             }
             _ => {}
         }
     }
 
+    for name in &base_scope.symbols().inferred_structs {
+        let mut base = struct_base.to_vec();
+        if compared.contains(name) {
+            base.push("PartialEq");
+        }
+        // At the first literal's span, which carries no `@derive`: the `compilerOptions.derive` traits.
+        let literal = base_scope
+            .symbols()
+            .object_structs
+            .iter()
+            .filter(|(_, n)| *n == name)
+            .map(|(lo, _)| *lo)
+            .min();
+        let at = swc_common::BytePos(literal.unwrap_or_default());
+        let derives = annotations::derives(&base, swc_common::Span::new(at, at), options, source)?;
+        let fields = &base_scope.symbols().structs[name];
+        type_decls.push(structs::transpile_inferred(name, fields, &derives));
+    }
+
+    let thrown_code: String = impl_blocks
+        .iter()
+        .chain(function_code.iter())
+        .cloned()
+        .collect();
+    if let Some(error_enum) = errors::trust_error_enum(&thrown_code, base_scope.symbols()) {
+        type_decls.push(error_enum);
+    }
+    if options.track_locations {
+        type_decls.push(locations::runtime());
+    }
+    if thrown_code.contains("__trust_bytes_")
+        || global_statics.iter().any(|s| s.contains("__trust_bytes_"))
+    {
+        type_decls.push(bytes::RUNTIME.to_string());
+    }
+    if thrown_code.contains("TrustTask::spawn(") {
+        type_decls.push(tasks::RUNTIME.to_string());
+    }
+    let uses_string_builder = |code: &String| code.contains("StringBuilder::new()");
+    if !base_scope.symbols().is_struct(strings::STRING_BUILDER)
+        && (uses_string_builder(&thrown_code) || global_statics.iter().any(uses_string_builder))
+    {
+        type_decls.push(strings::RUNTIME.to_string());
+    }
+
+    let program: String = use_statements
+        .iter()
+        .chain(type_decls.iter())
+        .chain(impl_blocks.iter())
+        .chain(global_consts.iter())
+        .chain(global_statics.iter())
+        .chain(function_code.iter())
+        .cloned()
+        .collect::<Vec<_>>()
+        .join("\n");
+    let stdlib_code = treeshake::shake(&stdlib_blocks, &program);
+    if !stdlib_code.is_empty() {
+        use_statements.push(stdlib_code);
+    }
+
     let all_code: String = use_statements
         .iter()
         .chain(type_decls.iter())
         .chain(impl_blocks.iter())
         .chain(global_consts.iter())
+        .chain(global_statics.iter())
         .chain(function_code.iter())
         .cloned()
         .collect();
 
+    // `use` lines of the stdlib count too: the tree-shaken code keeps them all.
+    let declared = |statements: &[String], line: &str| {
+        statements.iter().chain(&stdlib_blocks).any(|s| s == line)
+    };
+
     // Auto-inject Rc/RefCell if Pointer<T> is used
     if all_code.contains("Rc<RefCell<") {
-        if !use_statements.contains(&"use std::rc::Rc;".to_string()) {
+        if !declared(&use_statements, "use std::rc::Rc;") {
             use_statements.insert(0, "use std::rc::Rc;".to_string());
         }
-        if !use_statements.contains(&"use std::cell::RefCell;".to_string()) {
+        if !declared(&use_statements, "use std::cell::RefCell;") {
             use_statements.insert(1, "use std::cell::RefCell;".to_string());
         }
     }
 
+    // Auto-inject Weak when the code names it (`Weak<RefCell<T>>`, `Weak::new()`); a bare
+    // `Rc::downgrade(&p)` into an inferred binding does not.
+    if (all_code.contains("Weak<") || all_code.contains("Weak::"))
+        && !declared(&use_statements, "use std::rc::Weak;")
+    {
+        use_statements.insert(0, "use std::rc::Weak;".to_string());
+    }
+
     // Auto-inject Arc/Mutex if Threaded<T> is used
-    if all_code.contains("Arc<Mutex<") {
-        if !use_statements.contains(&"use std::sync::{Arc, Mutex};".to_string()) {
-            use_statements.insert(0, "use std::sync::{Arc, Mutex};".to_string());
-        }
+    if all_code.contains("Arc<Mutex<") && !declared(&use_statements, "use std::sync::{Arc, Mutex};")
+    {
+        use_statements.insert(0, "use std::sync::{Arc, Mutex};".to_string());
     }
 
     // Auto-inject HashMap if Map<> is used
-    if all_code.contains("HashMap<") || all_code.contains("HashMap::new()") {
-        if !use_statements.contains(&"use std::collections::HashMap;".to_string()) {
-            use_statements.insert(0, "use std::collections::HashMap;".to_string());
-        }
+    if (all_code.contains("HashMap<") || all_code.contains("HashMap::new()"))
+        && !declared(&use_statements, "use std::collections::HashMap;")
+    {
+        use_statements.insert(0, "use std::collections::HashMap;".to_string());
     }
 
     // Auto-inject HashSet if Set<> is used
-    if all_code.contains("HashSet<") || all_code.contains("HashSet::new()") {
-        if !use_statements.contains(&"use std::collections::HashSet;".to_string()) {
-            use_statements.insert(0, "use std::collections::HashSet;".to_string());
-        }
+    if (all_code.contains("HashSet<") || all_code.contains("HashSet::new()"))
+        && !declared(&use_statements, "use std::collections::HashSet;")
+    {
+        use_statements.insert(0, "use std::collections::HashSet;".to_string());
     }
 
     // Auto-require serde_json when object literals are lowered to `serde_json::json!(...)`.
-    if all_code.contains("serde_json::json!") && !required_crates.contains(&"serde_json".to_string()) {
+    if all_code.contains("serde_json::json!")
+        && !required_crates.contains(&"serde_json".to_string())
+    {
         required_crates.push("serde_json".to_string());
+        crate_versions.push(("serde_json".to_string(), "1".to_string()));
+    }
+
+    // Auto-require rust_decimal for `decimal` values.
+    if all_code.contains("rust_decimal::") && !required_crates.contains(&"rust_decimal".to_string())
+    {
+        required_crates.push("rust_decimal".to_string());
+        crate_versions.push(("rust_decimal".to_string(), "1".to_string()));
     }
 
     let mut rust_code = String::new();
@@ -145,6 +454,10 @@ pub fn transpile_to_rust(module: &Module) -> Result<TranspileOutput> {
         rust_code.push_str(global_const);
         rust_code.push_str("\n\n");
     }
+    for global_static in &global_statics {
+        rust_code.push_str(global_static);
+        rust_code.push_str("\n\n");
+    }
     for func in &function_code {
         rust_code.push_str(func);
         rust_code.push_str("\n\n");
@@ -153,5 +466,8 @@ pub fn transpile_to_rust(module: &Module) -> Result<TranspileOutput> {
     Ok(TranspileOutput {
         rust_code: rust_code.trim().to_string(),
         required_crates,
+        crate_versions,
+        runtime_features,
+        warnings: base_scope.warnings(),
     })
 }
@@ -0,0 +1,267 @@
+//! Optional values (`Option<T>`) with the operators TypeScript uses for nullable ones: `a ?? b`
+//! (`a` unless it is `null`), `a?.b` / `a?.m()` (`null` when `a` is) and `a ??= b`, plus the
+//! boolean `a ||= b` / `a &&= b`.
+
+use super::expressions::{infer_rust_type, transpile_expression, transpile_expression_as};
+use super::ownership::is_owned_type;
+use super::scope::Scope;
+use crate::diagnostics::Diagnostic;
+use crate::messages::msg;
+use crate::parser::source_span;
+use anyhow::Result;
+use swc_common::{Spanned, DUMMY_SP};
+use swc_ecma_ast::*;
+
+/// Binding for the present value inside the closure an optional chain maps over.
+const VALUE: &str = "__trust_value";
+
+/// `Option<T>` → `T`.
+pub fn option_inner(ty: &str) -> Option<&str> {
+    ty.strip_prefix("Option<").and_then(|t| t.strip_suffix('>'))
+}
+
+fn non_optional(
+    code: &'static str,
+    operator: &str,
+    ty: &str,
+    span: swc_common::Span,
+) -> anyhow::Error {
+    Diagnostic::error(code, msg!("non-optional", operator, ty))
+        .with_span(source_span(span))
+        .into()
+}
+
+/// Rust type of `a ?? b`: `T` for an `a: Option<T>`, or `Option<T>` again when `b` is optional too.
+pub fn coalesce_type(bin: &BinExpr, scope: &Scope) -> Option<String> {
+    let left = infer_rust_type(&bin.left, scope)?;
+    let inner = option_inner(&left)?;
+    if infer_rust_type(&bin.right, scope).as_deref() == Some(left.as_str()) {
+        return Some(left.clone());
+    }
+    Some(inner.strip_prefix('&').unwrap_or(inner).to_string())
+}
+
+/// `a ?? b` → `a.unwrap_or_else(|| b)`: `b` is only evaluated when `a` is `None`. A borrowed
+/// `Option<&T>` (from `map.get(k)`) is copied out first, and an optional `b` keeps the result optional.
+pub fn transpile_coalesce(bin: &BinExpr, scope: &Scope) -> Result<String> {
+    let left_ty = infer_rust_type(&bin.left, scope);
+    let inner = match left_ty.as_deref() {
+        Some(ty) => Some(
+            option_inner(ty)
+                .ok_or_else(|| non_optional("nullish-non-optional", "??", ty, bin.left.span()))?,
+        ),
+        None => None,
+    };
+    let mut left = transpile_expression(&bin.left, scope)?;
+    if matches!(&*bin.left, Expr::Ident(_) | Expr::Member(_)) && inner.is_some_and(is_owned_type) {
+        left = format!("{}.clone()", left);
+    }
+    if inner.is_some_and(|t| t.starts_with('&')) {
+        left = format!("{}.cloned()", left);
+    }
+    if left_ty.is_some() && infer_rust_type(&bin.right, scope) == left_ty {
+        let right = transpile_expression(&bin.right, scope)?;
+        return Ok(format!("{}.or_else(|| {})", left, right));
+    }
+    let mut right = match inner {
+        Some(ty) => transpile_expression_as(&bin.right, ty.strip_prefix('&').unwrap_or(ty), scope)?,
+        None => transpile_expression(&bin.right, scope)?,
+    };
+    // The fallback is read, not moved: `user.nickname ?? user.name` keeps `user` whole.
+    if matches!(&*bin.right, Expr::Ident(_) | Expr::Member(_)) && inner.is_some_and(is_owned_type) {
+        right = format!("{}.clone()", right);
+    }
+    Ok(format!("{}.unwrap_or_else(|| {})", left, right))
+}
+
+/// The optional receiver of `chain` and the access applied to its value: `a?.b` is `a` and `.b`,
+/// `a?.m(x)` is `a` and `.m(x)`; in `a?.b.c` the receiver is the chain `a?.b`.
+fn split_chain(chain: &OptChainExpr) -> Result<(&Expr, Expr)> {
+    let value = || Box::new(Expr::Ident(Ident::new_no_ctxt(VALUE.into(), DUMMY_SP)));
+    match &*chain.base {
+        OptChainBase::Member(member) => Ok((
+            &member.obj,
+            Expr::Member(MemberExpr {
+                obj: value(),
+                ..member.clone()
+            }),
+        )),
+        OptChainBase::Call(call) => {
+            let member = match &*call.callee {
+                Expr::OptChain(OptChainExpr {
+                    base,
+                    optional: true,
+                    ..
+                }) => match &**base {
+                    OptChainBase::Member(member) => Some(member),
+                    OptChainBase::Call(_) => None,
+                },
+                _ => None,
+            };
+            let Some(member) = member.filter(|_| !chain.optional) else {
+                return Err(Diagnostic::error(
+                    "unsupported-optional-call",
+                    msg!("unsupported-optional-call"),
+                )
+                .with_span(source_span(chain.span))
+                .into());
+            };
+            let method = Expr::Member(MemberExpr {
+                obj: value(),
+                ..member.clone()
+            });
+            Ok((
+                &member.obj,
+                Expr::Call(CallExpr {
+                    span: call.span,
+                    ctxt: call.ctxt,
+                    callee: Callee::Expr(Box::new(method)),
+                    args: call.args.clone(),
+                    type_args: call.type_args.clone(),
+                }),
+            ))
+        }
+    }
+}
+
+/// Scope inside the closure of an optional chain on a receiver of type `receiver_ty`.
+fn value_scope(receiver_ty: Option<&str>, scope: &Scope) -> Scope {
+    let mut inner = scope.clone();
+    inner.push();
+    match receiver_ty.and_then(option_inner) {
+        Some(ty) => inner.insert(
+            VALUE.to_string(),
+            ty.strip_prefix('&').unwrap_or(ty).to_string(),
+        ),
+        None => inner.declare_untyped(VALUE.to_string()),
+    }
+    inner
+}
+
+/// Rust type of `a?.b`: `Option<U>` for an access of type `U` (`U` itself when it is optional).
+pub fn opt_chain_type(chain: &OptChainExpr, scope: &Scope) -> Option<String> {
+    let (receiver, access) = split_chain(chain).ok()?;
+    let receiver_ty = infer_rust_type(receiver, scope)?;
+    option_inner(&receiver_ty)?;
+    let access_ty = infer_rust_type(&access, &value_scope(Some(&receiver_ty), scope))?;
+    if option_inner(&access_ty).is_some() {
+        return Some(access_ty);
+    }
+    Some(format!("Option<{}>", access_ty))
+}
+
+/// `a?.b` → `a.as_ref().map(|v| v.b.clone())`, `a?.m(x)` → `a.as_ref().map(|v| v.m(x))`;
+/// `and_then` instead of `map` when the access is optional itself, so chains stay one `Option`.
+pub fn transpile_opt_chain(chain: &OptChainExpr, scope: &Scope) -> Result<String> {
+    let (receiver, access) = split_chain(chain)?;
+    let receiver_ty = infer_rust_type(receiver, scope);
+    if let Some(ty) = receiver_ty
+        .as_deref()
+        .filter(|ty| option_inner(ty).is_none())
+    {
+        return Err(non_optional(
+            "optional-chain-non-optional",
+            "?.",
+            ty,
+            receiver.span(),
+        ));
+    }
+    let inner = value_scope(receiver_ty.as_deref(), scope);
+    let access_ty = infer_rust_type(&access, &inner);
+    let mut rendered = transpile_expression(&access, &inner)?;
+    if matches!(access, Expr::Member(_)) && access_ty.as_deref().is_some_and(is_owned_type) {
+        rendered = format!("{}.clone()", rendered);
+    }
+    let combinator = if access_ty.as_deref().and_then(option_inner).is_some() {
+        "and_then"
+    } else {
+        "map"
+    };
+    Ok(format!(
+        "{}.as_ref().{}(|{}| {})",
+        transpile_expression(receiver, scope)?,
+        combinator,
+        VALUE,
+        rendered
+    ))
+}
+
+/// `a ||= b` / `a &&= b` on a `bool` → `a = a || b` / `a = a && b`; `a ??= b` on an `Option<T>`
+/// → `if a.is_none() { a = Some(b) }`. `None` for any other assignment.
+pub fn transpile_logical_assign(
+    assign: &AssignExpr,
+    scope: &Scope,
+    transpile_assign: impl Fn(&AssignExpr, &Scope) -> Result<String>,
+) -> Result<Option<String>> {
+    let (operator, op) = match assign.op {
+        AssignOp::OrAssign => ("||=", BinaryOp::LogicalOr),
+        AssignOp::AndAssign => ("&&=", BinaryOp::LogicalAnd),
+        AssignOp::NullishAssign => ("??=", BinaryOp::NullishCoalescing),
+        _ => return Ok(None),
+    };
+    let target = match &assign.left {
+        AssignTarget::Simple(SimpleAssignTarget::Ident(ident)) => Expr::Ident(ident.id.clone()),
+        AssignTarget::Simple(SimpleAssignTarget::Member(member)) => Expr::Member(member.clone()),
+        _ => {
+            return Err(Diagnostic::error(
+                "unsupported-assignment",
+                msg!("unsupported-assignment.operator", operator),
+            )
+            .with_span(source_span(assign.span))
+            .into())
+        }
+    };
+    let target_ty = infer_rust_type(&target, scope);
+    let plain = |right: Expr| AssignExpr {
+        op: AssignOp::Assign,
+        right: Box::new(right),
+        ..assign.clone()
+    };
+
+    if op == BinaryOp::NullishCoalescing {
+        if let Some(ty) = target_ty.as_deref().filter(|ty| option_inner(ty).is_none()) {
+            return Err(non_optional(
+                "nullish-non-optional",
+                operator,
+                ty,
+                target.span(),
+            ));
+        }
+        let some = Expr::Call(CallExpr {
+            span: assign.right.span(),
+            callee: Callee::Expr(Box::new(Expr::Ident(Ident::new_no_ctxt(
+                "Some".into(),
+                DUMMY_SP,
+            )))),
+            args: vec![ExprOrSpread {
+                spread: None,
+                expr: assign.right.clone(),
+            }],
+            ..CallExpr::default()
+        });
+        return Ok(Some(format!(
+            "if {}.is_none() {{ {}; }}",
+            transpile_expression(&target, scope)?,
+            transpile_assign(&plain(some), scope)?
+        )));
+    }
+
+    if let Some(ty) = target_ty.as_deref().filter(|ty| *ty != "bool") {
+        return Err(Diagnostic::error(
+            "logical-assign-non-bool",
+            msg!("logical-assign-non-bool", operator, ty),
+        )
+        .with_span(source_span(target.span()))
+        .into());
+    }
+    let combined = Expr::Bin(BinExpr {
+        span: assign.span,
+        op,
+        left: Box::new(target),
+        right: Box::new(Expr::Paren(ParenExpr {
+            span: assign.right.span(),
+            expr: assign.right.clone(),
+        })),
+    });
+    transpile_assign(&plain(combined), scope).map(Some)
+}
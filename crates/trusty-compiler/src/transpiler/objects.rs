@@ -0,0 +1,281 @@
+//! Structs inferred for object literals: `val config = { host: "x", port: 8080 };` declares a
+//! `struct Config { host: String, port: i32 }` (named after the binding) and builds one.
+//!
+//! This applies to an unannotated `val`/`var` initialized with a literal whose keys are all
+//! identifiers and whose values all have a type the compiler can infer; a nested literal becomes a
+//! struct of its own (`ConfigServer` for the `server` field). A literal where a declared struct is
+//! expected (`val p: Person = { ... }`, an element of a `Person[]`) builds that struct; any other
+//! object literal stays a `serde_json::json!` value.
+
+use super::expressions::infer_rust_type;
+use super::scope::Scope;
+use super::symbols::ProgramSymbols;
+use super::types::transpile_type_annotation;
+use crate::compiler::CompileOptions;
+use crate::lint::upper_camel_case;
+use crate::preprocessor::Preprocessed;
+use std::rc::Rc;
+use swc_ecma_ast::*;
+
+/// Walks every function body, method and top-level statement in order, typing bindings as the
+/// transpiler will, and records a struct for each object literal that qualifies in `symbols`
+/// (`structs`, `inferred_structs` and `object_structs`).
+pub fn infer_structs(
+    module: &Module,
+    symbols: &mut ProgramSymbols,
+    options: &CompileOptions,
+    source: &Preprocessed,
+) {
+    let mut inference = Inference {
+        symbols,
+        options: Rc::new(options.clone()),
+        source: Rc::new(source.clone()),
+    };
+    let mut script = inference.scope();
+    for item in &module.body {
+        match item {
+            ModuleItem::Stmt(Stmt::Decl(Decl::Fn(func_decl))) => {
+                let mut scope = inference.scope();
+                inference.function(&func_decl.function, &mut scope);
+            }
+            ModuleItem::Stmt(Stmt::Decl(Decl::Class(class_decl))) => {
+                for member in &class_decl.class.body {
+                    if let ClassMember::Method(method) = member {
+                        let mut scope = inference.scope();
+                        scope.insert("self".to_string(), class_decl.ident.sym.to_string());
+                        inference.function(&method.function, &mut scope);
+                    }
+                }
+            }
+            ModuleItem::Stmt(Stmt::Decl(Decl::Var(var_decl)))
+                if var_decl.kind == VarDeclKind::Const => {}
+            ModuleItem::Stmt(stmt) => inference.statement(stmt, &mut script),
+            _ => {}
+        }
+    }
+}
+
+/// Fields of a struct being inferred, as `(name, Rust type)`.
+type Fields = Vec<(String, String)>;
+
+struct Inference<'a> {
+    symbols: &'a mut ProgramSymbols,
+    options: Rc<CompileOptions>,
+    source: Rc<Preprocessed>,
+}
+
+impl Inference<'_> {
+    /// A scope seeing the structs inferred so far.
+    fn scope(&self) -> Scope {
+        Scope::for_program(
+            Rc::new(self.symbols.clone()),
+            self.options.clone(),
+            self.source.clone(),
+        )
+    }
+
+    fn function(&mut self, function: &Function, scope: &mut Scope) {
+        for param in &function.params {
+            if let Pat::Ident(ident) = &param.pat {
+                match ident.type_ann.as_deref() {
+                    Some(ann) => {
+                        scope.insert(ident.id.sym.to_string(), transpile_type_annotation(ann))
+                    }
+                    None => scope.declare_untyped(ident.id.sym.to_string()),
+                }
+            }
+        }
+        if let Some(body) = &function.body {
+            for stmt in &body.stmts {
+                self.statement(stmt, scope);
+            }
+        }
+    }
+
+    fn block(&mut self, stmt: &Stmt, scope: &mut Scope) {
+        scope.push();
+        self.statement(stmt, scope);
+        scope.pop();
+    }
+
+    fn statement(&mut self, stmt: &Stmt, scope: &mut Scope) {
+        match stmt {
+            Stmt::Decl(Decl::Var(var_decl)) => {
+                for decl in &var_decl.decls {
+                    self.declarator(decl, scope);
+                }
+            }
+            Stmt::Block(block) => {
+                scope.push();
+                for stmt in &block.stmts {
+                    self.statement(stmt, scope);
+                }
+                scope.pop();
+            }
+            Stmt::If(if_stmt) => {
+                self.block(&if_stmt.cons, scope);
+                if let Some(alt) = &if_stmt.alt {
+                    self.block(alt, scope);
+                }
+            }
+            Stmt::While(while_stmt) => self.block(&while_stmt.body, scope),
+            Stmt::DoWhile(do_while) => self.block(&do_while.body, scope),
+            Stmt::For(for_stmt) => {
+                scope.push();
+                if let Some(VarDeclOrExpr::VarDecl(var_decl)) = &for_stmt.init {
+                    for decl in &var_decl.decls {
+                        self.declarator(decl, scope);
+                    }
+                }
+                self.block(&for_stmt.body, scope);
+                scope.pop();
+            }
+            Stmt::ForOf(ForOfStmt { left, body, .. })
+            | Stmt::ForIn(ForInStmt { left, body, .. }) => {
+                scope.push();
+                if let ForHead::VarDecl(var_decl) = left {
+                    for decl in &var_decl.decls {
+                        if let Pat::Ident(ident) = &decl.name {
+                            scope.declare_untyped(ident.id.sym.to_string());
+                        }
+                    }
+                }
+                self.block(body, scope);
+                scope.pop();
+            }
+            Stmt::Labeled(labeled) => self.statement(&labeled.body, scope),
+            Stmt::Try(try_stmt) => {
+                self.block(&Stmt::Block(try_stmt.block.clone()), scope);
+                if let Some(handler) = &try_stmt.handler {
+                    scope.push();
+                    if let Some(Pat::Ident(ident)) = &handler.param {
+                        scope.declare_untyped(ident.id.sym.to_string());
+                    }
+                    self.block(&Stmt::Block(handler.body.clone()), scope);
+                    scope.pop();
+                }
+                if let Some(finalizer) = &try_stmt.finalizer {
+                    self.block(&Stmt::Block(finalizer.clone()), scope);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn declarator(&mut self, decl: &VarDeclarator, scope: &mut Scope) {
+        let Pat::Ident(ident) = &decl.name else {
+            return;
+        };
+        let name = ident.id.sym.to_string();
+        let ty = match (ident.type_ann.as_deref(), decl.init.as_deref()) {
+            (Some(ann), _) => Some(transpile_type_annotation(ann)),
+            (None, Some(Expr::Object(obj))) => self.object(obj, &upper_camel_case(&name), scope),
+            (None, Some(init)) => infer_rust_type(init, scope),
+            (None, None) => None,
+        };
+        match ty {
+            Some(ty) => scope.insert(name, ty),
+            None => scope.declare_untyped(name),
+        }
+    }
+
+    /// Records the struct for `obj` (and its nested literals) named after `hint`, or nothing when a
+    /// key is not an identifier or a value's type is unknown.
+    fn object(&mut self, obj: &ObjectLit, hint: &str, scope: &mut Scope) -> Option<String> {
+        let mut pending = Vec::new();
+        let fields = self.fields(obj, hint, scope, &mut pending)?;
+        let mut name = String::new();
+        for (object, struct_name, fields) in
+            pending
+                .into_iter()
+                .chain([(obj.span.lo.0, hint.to_string(), fields)])
+        {
+            name = self.declare(&struct_name, fields);
+            self.symbols.object_structs.insert(object, name.clone());
+        }
+        // Later statements see the new structs' fields.
+        scope.set_symbols(Rc::new(self.symbols.clone()));
+        Some(name)
+    }
+
+    fn fields(
+        &mut self,
+        obj: &ObjectLit,
+        hint: &str,
+        scope: &Scope,
+        pending: &mut Vec<(u32, String, Fields)>,
+    ) -> Option<Fields> {
+        let mut fields = Vec::new();
+        for prop in &obj.props {
+            let PropOrSpread::Prop(prop) = prop else {
+                return None;
+            };
+            let (key, ty) = match &**prop {
+                Prop::KeyValue(KeyValueProp {
+                    key: PropName::Ident(key),
+                    value,
+                }) => {
+                    let ty = match &**value {
+                        Expr::Object(nested) => {
+                            let nested_hint = format!("{}{}", hint, upper_camel_case(&key.sym));
+                            let nested_fields =
+                                self.fields(nested, &nested_hint, scope, pending)?;
+                            pending.push((nested.span.lo.0, nested_hint.clone(), nested_fields));
+                            // The name `declare` will pick.
+                            self.struct_name(&nested_hint, &pending.last()?.2)
+                        }
+                        value => infer_rust_type(value, scope)?,
+                    };
+                    (key.sym.to_string(), ty)
+                }
+                Prop::Shorthand(ident) => (
+                    ident.sym.to_string(),
+                    scope.get(ident.sym.as_ref())?.clone(),
+                ),
+                _ => return None,
+            };
+            if fields.iter().any(|(field, _)| *field == key) {
+                return None;
+            }
+            fields.push((key, ty));
+        }
+        (!fields.is_empty()).then_some(fields)
+    }
+
+    /// `hint`, or `hint2`, `hint3`... when a type of that name exists.
+    fn available_name(&self, hint: &str) -> String {
+        let mut name = hint.to_string();
+        let mut n = 1;
+        loop {
+            if !self.symbols.enums.contains_key(&name) && !self.symbols.structs.contains_key(&name)
+            {
+                return name;
+            }
+            n += 1;
+            name = format!("{}{}", hint, n);
+        }
+    }
+
+    /// The struct already inferred with exactly `fields`, so that literals of the same shape share a
+    /// type (and compare with `==`), or else an available name after `hint`.
+    fn struct_name(&self, hint: &str, fields: &[(String, String)]) -> String {
+        let same_shape = self
+            .symbols
+            .inferred_structs
+            .iter()
+            .find(|name| self.symbols.structs[*name] == fields);
+        same_shape
+            .cloned()
+            .unwrap_or_else(|| self.available_name(hint))
+    }
+
+    /// Declares a struct for `fields` named after `hint`, unless one was inferred for that shape.
+    fn declare(&mut self, hint: &str, fields: Fields) -> String {
+        let name = self.struct_name(hint, &fields);
+        if !self.symbols.structs.contains_key(&name) {
+            self.symbols.structs.insert(name.clone(), fields);
+            self.symbols.inferred_structs.push(name.clone());
+        }
+        name
+    }
+}
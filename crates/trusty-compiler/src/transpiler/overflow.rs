@@ -0,0 +1,100 @@
+//! Integer overflow handling for `+`, `-` and `*` (`compilerOptions.overflow`).
+
+use swc_ecma_ast::{AssignOp, BinaryOp};
+
+/// How integer `+`, `-` and `*` (and `+=`, `-=`, `*=`) behave when the result does not fit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// Plain Rust operators: a panic in debug builds, two's-complement wrapping in release builds.
+    #[default]
+    Wrap,
+    /// `checked_add` and friends: a panic in every build.
+    Checked,
+    /// `saturating_add` and friends: the result sticks at the type's minimum or maximum.
+    Saturating,
+}
+
+impl Overflow {
+    /// Parses a `compilerOptions.overflow` value (`wrap`, `checked`, `saturating`).
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "wrap" => Some(Overflow::Wrap),
+            "checked" => Some(Overflow::Checked),
+            "saturating" => Some(Overflow::Saturating),
+            _ => None,
+        }
+    }
+
+    /// `left op right` on integers of type `ty`, or `None` when plain operators apply.
+    pub fn lower(self, op: BinaryOp, left: &str, right: &str, ty: &str) -> Option<String> {
+        let name = match op {
+            BinaryOp::Add => "add",
+            BinaryOp::Sub => "sub",
+            BinaryOp::Mul => "mul",
+            _ => return None,
+        };
+        if self == Overflow::Wrap || !is_integer_type(ty) {
+            return None;
+        }
+        Some(self.call(ty, name, left, right))
+    }
+
+    /// `target op= value` on an integer target of type `ty`; `is_local` targets are plain
+    /// variables, others (fields, `static var`s) are updated through one `&mut` borrow.
+    pub fn lower_assign(
+        self,
+        op: AssignOp,
+        target: &str,
+        value: &str,
+        ty: &str,
+        is_local: bool,
+    ) -> Option<String> {
+        let name = match op {
+            AssignOp::AddAssign => "add",
+            AssignOp::SubAssign => "sub",
+            AssignOp::MulAssign => "mul",
+            _ => return None,
+        };
+        if self == Overflow::Wrap || !is_integer_type(ty) {
+            return None;
+        }
+        if is_local {
+            return Some(format!(
+                "{} = {}",
+                target,
+                self.call(ty, name, target, value)
+            ));
+        }
+        Some(format!(
+            "{{ let __trust_value = {}; let __trust_place = &mut {}; *__trust_place = {}; }}",
+            value,
+            target,
+            self.call(ty, name, "*__trust_place", "__trust_value")
+        ))
+    }
+
+    /// `i32::checked_add(a, b)`: the type is spelled out, so untyped literals still resolve.
+    fn call(self, ty: &str, name: &str, left: &str, right: &str) -> String {
+        match self {
+            Overflow::Checked => {
+                let verb = match name {
+                    "add" => "add",
+                    "sub" => "subtract",
+                    _ => "multiply",
+                };
+                format!(
+                    "{}::checked_{}({}, {}).expect(\"attempt to {} with overflow\")",
+                    ty, name, left, right, verb
+                )
+            }
+            _ => format!("{}::saturating_{}({}, {})", ty, name, left, right),
+        }
+    }
+}
+
+fn is_integer_type(ty: &str) -> bool {
+    matches!(
+        ty,
+        "i8" | "i16" | "i32" | "i64" | "u8" | "u16" | "u32" | "u64" | "isize" | "usize"
+    )
+}
@@ -0,0 +1,261 @@
+//! Call-site ownership analysis: which by-value arguments must be cloned so the caller can keep
+//! using them after the call (`greet(name); console.write(name);`).
+
+use std::collections::HashMap;
+use swc_ecma_ast::*;
+
+/// When owned (non-`Copy`) variables passed by value, or given as struct field values, are cloned.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AutoClone {
+    /// Clone only when the variable is used again later in the function, or the call is in a loop.
+    #[default]
+    Reused,
+    /// Clone every owned variable passed by value.
+    Always,
+    /// Never clone; arguments are moved as written.
+    Never,
+}
+
+impl AutoClone {
+    /// Parses a `compilerOptions.autoClone` value (`reused`, `always`, `never`).
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "reused" => Some(AutoClone::Reused),
+            "always" => Some(AutoClone::Always),
+            "never" => Some(AutoClone::Never),
+            _ => None,
+        }
+    }
+}
+
+/// Positions (`BytePos`) of every identifier reference and declaration in a function body.
+#[derive(Clone, Debug, Default)]
+pub struct IdentUses {
+    uses: HashMap<String, Vec<u32>>,
+    decls: HashMap<String, Vec<u32>>,
+    loops: Vec<(u32, u32)>,
+}
+
+impl IdentUses {
+    pub fn collect(block: &BlockStmt) -> Self {
+        let mut uses = Self::default();
+        for stmt in &block.stmts {
+            uses.stmt(stmt);
+        }
+        uses
+    }
+
+    /// True if `name`, referenced at `pos`, may be read again afterwards: a later reference,
+    /// or an enclosing loop that does not declare `name` itself.
+    pub fn used_after(&self, name: &str, pos: u32) -> bool {
+        let later = self
+            .uses
+            .get(name)
+            .map(|positions| positions.iter().any(|&p| p > pos))
+            .unwrap_or(false);
+        if later {
+            return true;
+        }
+        let decls = self.decls.get(name).map(Vec::as_slice).unwrap_or(&[]);
+        self.loops
+            .iter()
+            .filter(|&&(lo, hi)| lo <= pos && pos < hi)
+            .any(|&(lo, _)| !decls.iter().any(|&d| lo <= d && d < pos))
+    }
+
+    fn ident(&mut self, ident: &Ident) {
+        self.uses
+            .entry(ident.sym.to_string())
+            .or_default()
+            .push(ident.span.lo.0);
+    }
+
+    fn pat(&mut self, pat: &Pat) {
+        if let Pat::Ident(binding) = pat {
+            self.decls
+                .entry(binding.id.sym.to_string())
+                .or_default()
+                .push(binding.id.span.lo.0);
+        }
+    }
+
+    fn var_decl(&mut self, var_decl: &VarDecl) {
+        for decl in &var_decl.decls {
+            self.pat(&decl.name);
+            if let Some(init) = &decl.init {
+                self.expr(init);
+            }
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Expr(expr_stmt) => self.expr(&expr_stmt.expr),
+            Stmt::Decl(Decl::Var(var_decl)) => self.var_decl(var_decl),
+            Stmt::Return(ret) => {
+                if let Some(arg) = &ret.arg {
+                    self.expr(arg);
+                }
+            }
+            Stmt::Throw(throw_stmt) => self.expr(&throw_stmt.arg),
+            Stmt::Block(block) => block.stmts.iter().for_each(|s| self.stmt(s)),
+            Stmt::If(if_stmt) => {
+                self.expr(&if_stmt.test);
+                self.stmt(&if_stmt.cons);
+                if let Some(alt) = &if_stmt.alt {
+                    self.stmt(alt);
+                }
+            }
+            Stmt::While(while_stmt) => {
+                self.loops
+                    .push((while_stmt.span.lo.0, while_stmt.span.hi.0));
+                self.expr(&while_stmt.test);
+                self.stmt(&while_stmt.body);
+            }
+            Stmt::DoWhile(do_while) => {
+                self.loops.push((do_while.span.lo.0, do_while.span.hi.0));
+                self.stmt(&do_while.body);
+                self.expr(&do_while.test);
+            }
+            Stmt::For(for_stmt) => {
+                self.loops.push((for_stmt.span.lo.0, for_stmt.span.hi.0));
+                match &for_stmt.init {
+                    Some(VarDeclOrExpr::VarDecl(var_decl)) => self.var_decl(var_decl),
+                    Some(VarDeclOrExpr::Expr(expr)) => self.expr(expr),
+                    None => {}
+                }
+                for expr in for_stmt.test.iter().chain(for_stmt.update.iter()) {
+                    self.expr(expr);
+                }
+                self.stmt(&for_stmt.body);
+            }
+            Stmt::ForOf(for_of) => {
+                self.expr(&for_of.right);
+                self.loops.push((for_of.span.lo.0, for_of.span.hi.0));
+                self.for_head(&for_of.left);
+                self.stmt(&for_of.body);
+            }
+            Stmt::ForIn(for_in) => {
+                self.expr(&for_in.right);
+                self.loops.push((for_in.span.lo.0, for_in.span.hi.0));
+                self.for_head(&for_in.left);
+                self.stmt(&for_in.body);
+            }
+            Stmt::Try(try_stmt) => {
+                try_stmt.block.stmts.iter().for_each(|s| self.stmt(s));
+                if let Some(handler) = &try_stmt.handler {
+                    handler.body.stmts.iter().for_each(|s| self.stmt(s));
+                }
+                if let Some(finalizer) = &try_stmt.finalizer {
+                    finalizer.stmts.iter().for_each(|s| self.stmt(s));
+                }
+            }
+            Stmt::Labeled(labeled) => self.stmt(&labeled.body),
+            _ => {}
+        }
+    }
+
+    fn for_head(&mut self, head: &ForHead) {
+        match head {
+            ForHead::VarDecl(var_decl) => self.var_decl(var_decl),
+            ForHead::Pat(pat) => self.pat(pat),
+            _ => {}
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Ident(ident) => self.ident(ident),
+            Expr::Bin(bin) => {
+                self.expr(&bin.left);
+                self.expr(&bin.right);
+            }
+            Expr::Unary(unary) => self.expr(&unary.arg),
+            Expr::Update(update) => self.expr(&update.arg),
+            Expr::Assign(assign) => {
+                if let AssignTarget::Simple(SimpleAssignTarget::Member(member)) = &assign.left {
+                    self.member(member);
+                }
+                self.expr(&assign.right);
+            }
+            Expr::Call(call) => {
+                if let Callee::Expr(callee) = &call.callee {
+                    self.expr(callee);
+                }
+                call.args.iter().for_each(|a| self.expr(&a.expr));
+            }
+            Expr::New(new_expr) => {
+                for arg in new_expr.args.iter().flatten() {
+                    self.expr(&arg.expr);
+                }
+            }
+            Expr::Member(member) => self.member(member),
+            Expr::Cond(cond) => {
+                self.expr(&cond.test);
+                self.expr(&cond.cons);
+                self.expr(&cond.alt);
+            }
+            Expr::Paren(paren) => self.expr(&paren.expr),
+            Expr::Seq(seq) => seq.exprs.iter().for_each(|e| self.expr(e)),
+            Expr::Await(await_expr) => self.expr(&await_expr.arg),
+            Expr::Array(array) => array
+                .elems
+                .iter()
+                .flatten()
+                .for_each(|e| self.expr(&e.expr)),
+            Expr::Tpl(tpl) => tpl.exprs.iter().for_each(|e| self.expr(e)),
+            Expr::Object(obj) => {
+                for prop in &obj.props {
+                    if let PropOrSpread::Prop(prop) = prop {
+                        match &**prop {
+                            Prop::KeyValue(kv) => self.expr(&kv.value),
+                            Prop::Shorthand(ident) => self.ident(ident),
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            Expr::Arrow(arrow) => match &*arrow.body {
+                BlockStmtOrExpr::Expr(body) => self.expr(body),
+                BlockStmtOrExpr::BlockStmt(block) => block.stmts.iter().for_each(|s| self.stmt(s)),
+            },
+            Expr::Fn(fn_expr) => {
+                if let Some(body) = &fn_expr.function.body {
+                    body.stmts.iter().for_each(|s| self.stmt(s));
+                }
+            }
+            Expr::TsAs(ts_as) => self.expr(&ts_as.expr),
+            Expr::TsNonNull(non_null) => self.expr(&non_null.expr),
+            _ => {}
+        }
+    }
+
+    fn member(&mut self, member: &MemberExpr) {
+        self.expr(&member.obj);
+        if let MemberProp::Computed(computed) = &member.prop {
+            self.expr(&computed.expr);
+        }
+    }
+}
+
+/// Rust types that are moved, not copied, when passed by value.
+pub fn is_owned_type(ty: &str) -> bool {
+    !matches!(
+        ty,
+        "i8" | "i16"
+            | "i32"
+            | "i64"
+            | "u8"
+            | "u16"
+            | "u32"
+            | "u64"
+            | "isize"
+            | "usize"
+            | "f32"
+            | "f64"
+            | "bool"
+            | "char"
+            | "()"
+    ) && !ty.starts_with('&')
+        && !ty.is_empty()
+}
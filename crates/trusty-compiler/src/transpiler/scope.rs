@@ -1,7 +1,224 @@
-use std::collections::HashMap;
+use super::ownership::IdentUses;
+use super::statics::StaticVar;
+use super::symbols::ProgramSymbols;
+use crate::compiler::CompileOptions;
+use crate::diagnostics::Diagnostic;
+use crate::messages::Message;
+use crate::preprocessor::Preprocessed;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
 
 /// Maps variable/parameter names to their Rust type strings within a function.
-pub type Scope = HashMap<String, String>;
+///
+/// Bindings live in a stack of frames, one per block: lookups walk from the innermost frame
+/// outwards, so an inner `val x` shadows an outer one and is dropped when its block is popped.
+/// Program-wide declarations (function signatures, struct fields), compile options and the source
+/// text are shared by every scope; `uses` covers the enclosing function body.
+#[derive(Clone, Debug)]
+pub struct Scope {
+    frames: Vec<HashMap<String, String>>,
+    symbols: Rc<ProgramSymbols>,
+    options: Rc<CompileOptions>,
+    /// Preprocessed source the module was parsed from, for source locations in generated code.
+    source: Rc<Preprocessed>,
+    uses: Rc<IdentUses>,
+    error_type: Option<String>,
+    /// Types of values compared with `==`/`!=` anywhere in the program, shared by every scope.
+    compared: Rc<RefCell<HashSet<String>>>,
+    /// Warnings raised while transpiling, shared by every scope.
+    warnings: Rc<RefCell<Vec<Diagnostic>>>,
+}
+
+/// Type recorded for bindings declared without a known type; hides outer bindings of the same name.
+const UNKNOWN_TYPE: &str = "";
+
+impl Default for Scope {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scope {
+    pub fn new() -> Self {
+        Self::for_program(Rc::default(), Rc::default(), Rc::default())
+    }
+
+    pub fn for_program(
+        symbols: Rc<ProgramSymbols>,
+        options: Rc<CompileOptions>,
+        source: Rc<Preprocessed>,
+    ) -> Self {
+        Self {
+            frames: vec![HashMap::new()],
+            symbols,
+            options,
+            source,
+            uses: Rc::default(),
+            error_type: None,
+            compared: Rc::default(),
+            warnings: Rc::default(),
+        }
+    }
+
+    pub fn symbols(&self) -> &ProgramSymbols {
+        &self.symbols
+    }
+
+    /// Swaps in `symbols`, keeping the bindings: for passes that declare types as they go.
+    pub fn set_symbols(&mut self, symbols: Rc<ProgramSymbols>) {
+        self.symbols = symbols;
+    }
+
+    pub fn options(&self) -> &CompileOptions {
+        &self.options
+    }
+
+    pub fn source(&self) -> &Preprocessed {
+        &self.source
+    }
+
+    /// TRUST source location of `span`: `file:line:column` in a bundled module, else `line:column`.
+    pub fn location(&self, span: swc_common::Span) -> String {
+        self.source.location(crate::parser::source_span(span).start)
+    }
+
+    /// Source text of `span` for a message: its first line, shortened to 40 characters.
+    pub fn snippet(&self, span: swc_common::Span) -> String {
+        let span = crate::parser::source_span(span);
+        let text = self
+            .source
+            .text
+            .get(span.start..span.end)
+            .unwrap_or_default();
+        let line = text.lines().next().unwrap_or_default().trim_end();
+        match line.char_indices().nth(40) {
+            Some((end, _)) => format!("{}...", &line[..end]),
+            None if line.len() < text.trim_end().len() => format!("{}...", line),
+            None => line.to_string(),
+        }
+    }
+
+    /// Records that values of Rust type `ty` are compared, so the user types in it need `PartialEq`.
+    pub fn record_comparison(&self, ty: &str) {
+        self.compared.borrow_mut().insert(ty.to_string());
+    }
+
+    /// Every type recorded by [`Scope::record_comparison`] so far.
+    pub fn compared_types(&self) -> HashSet<String> {
+        self.compared.borrow().clone()
+    }
+
+    /// Reports a warning; the code is still generated. A construct transpiled twice warns once.
+    pub fn warn(&self, warning: Diagnostic) {
+        let mut warnings = self.warnings.borrow_mut();
+        if !warnings
+            .iter()
+            .any(|w| w.code == warning.code && w.span == warning.span)
+        {
+            warnings.push(warning);
+        }
+    }
+
+    /// Reports a construct with no Rust translation: an error, or a warning when
+    /// [`CompileOptions::allow_unknown`] lets the caller emit a placeholder for it.
+    pub fn unsupported(
+        &self,
+        code: &'static str,
+        message: Message,
+        span: swc_common::Span,
+    ) -> anyhow::Result<()> {
+        let span = crate::parser::source_span(span);
+        if self.options.allow_unknown {
+            self.warn(Diagnostic::warning(code, message).with_span(span));
+            return Ok(());
+        }
+        Err(Diagnostic::error(code, message).with_span(span).into())
+    }
+
+    /// Every warning reported by [`Scope::warn`] so far.
+    pub fn warnings(&self) -> Vec<Diagnostic> {
+        self.warnings.borrow().clone()
+    }
+
+    /// Identifier positions of the function being transpiled, for ownership decisions.
+    pub fn uses(&self) -> &IdentUses {
+        &self.uses
+    }
+
+    /// Starts a function body: records where its identifiers are used.
+    pub fn enter_function(&mut self, body: &swc_ecma_ast::BlockStmt) {
+        self.uses = Rc::new(IdentUses::collect(body));
+    }
+
+    /// Rust error type a `throw` here returns: the enclosing `try` block's, else the function's
+    /// `Result<_, E>`; `None` when neither applies.
+    pub fn error_type(&self) -> Option<&str> {
+        self.error_type.as_deref()
+    }
+
+    /// Sets the error type for `throw`, returning the previous one so it can be restored.
+    pub fn set_error_type(&mut self, error_type: Option<String>) -> Option<String> {
+        std::mem::replace(&mut self.error_type, error_type)
+    }
+
+    /// Enters a block (`{ ... }`, loop body, catch clause, closure).
+    pub fn push(&mut self) {
+        self.frames.push(HashMap::new());
+    }
+
+    /// Leaves the innermost block. The function-level frame is never popped.
+    pub fn pop(&mut self) {
+        if self.frames.len() > 1 {
+            self.frames.pop();
+        }
+    }
+
+    /// Runs `f` inside a fresh block frame.
+    pub fn scoped<T>(&mut self, f: impl FnOnce(&mut Scope) -> T) -> T {
+        self.push();
+        let result = f(self);
+        self.pop();
+        result
+    }
+
+    /// Declares `name` with Rust type `ty` in the innermost block.
+    pub fn insert(&mut self, name: String, ty: String) {
+        self.frames
+            .last_mut()
+            .expect("scope always has a frame")
+            .insert(name, ty);
+    }
+
+    /// Declares `name` without a known type, shadowing any outer binding.
+    pub fn declare_untyped(&mut self, name: String) {
+        self.insert(name, UNKNOWN_TYPE.to_string());
+    }
+
+    /// Type of the innermost visible binding for `name`, if it has one. Module `static var`s are
+    /// visible unless a binding shadows them.
+    pub fn get(&self, name: &str) -> Option<&String> {
+        match self.frames.iter().rev().find_map(|frame| frame.get(name)) {
+            Some(ty) => Some(ty).filter(|ty| ty.as_str() != UNKNOWN_TYPE),
+            None => self.symbols.statics.get(name).map(|var| &var.rust_type),
+        }
+    }
+
+    /// The `static var` that `name` refers to here, unless a binding shadows it.
+    pub fn static_var(&self, name: &str) -> Option<&StaticVar> {
+        if self.frames.iter().any(|frame| frame.contains_key(name)) {
+            return None;
+        }
+        self.symbols.statics.get(name)
+    }
+
+    /// The declaration at `span` was written `static var`.
+    pub fn is_static_decl(&self, span: swc_common::Span) -> bool {
+        self.source
+            .is_static(crate::parser::source_span(span).start)
+    }
+}
+
 pub const MODULE_ALIAS_MARKER: &str = "__trust_module_alias__";
 
 /// Returns true if the Rust type string represents a Pointer<T> (Rc<RefCell<T>>).
@@ -9,11 +226,39 @@ pub fn is_pointer(type_str: &str) -> bool {
     type_str.starts_with("Rc<RefCell<")
 }
 
+/// Returns true if the Rust type string represents a WeakPointer<T> (Weak<RefCell<T>>).
+pub fn is_weak_pointer(type_str: &str) -> bool {
+    type_str.starts_with("Weak<RefCell<")
+}
+
 /// Returns true if the Rust type string represents a Threaded<T> (Arc<Mutex<T>>).
 pub fn is_threaded(type_str: &str) -> bool {
     type_str.starts_with("Arc<Mutex<")
 }
 
+/// Type behind a `ref T` / `mut ref T` binding (`&T` / `&mut T` → `T`); other types unchanged.
+pub fn referent_type(type_str: &str) -> &str {
+    type_str
+        .strip_prefix("&mut ")
+        .or_else(|| type_str.strip_prefix('&'))
+        .unwrap_or(type_str)
+}
+
+/// `E` of a `Result<T, E>` type string.
+pub fn result_error_type(type_str: &str) -> Option<&str> {
+    let inner = type_str.strip_prefix("Result<")?.strip_suffix('>')?;
+    let mut depth = 0i32;
+    for (i, c) in inner.char_indices() {
+        match c {
+            '<' | '(' => depth += 1,
+            '>' | ')' => depth -= 1,
+            ',' if depth == 0 => return Some(inner[i + 1..].trim()),
+            _ => {}
+        }
+    }
+    None
+}
+
 /// Returns true if the scope entry is a module alias marker.
 pub fn is_module_alias_binding(type_str: &str) -> bool {
     type_str == MODULE_ALIAS_MARKER
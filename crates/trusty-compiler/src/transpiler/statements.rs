@@ -1,14 +1,40 @@
-use super::expressions::transpile_expression;
+use super::annotations::cfg_attribute;
+use super::errors::{catch_variant, transpile_throw, try_uses_typed_errors, TRUST_ERROR};
+use super::expressions::{
+    binding_name, binding_value, infer_rust_type, transpile_expression, transpile_expression_as,
+    transpile_range,
+};
+use super::iterators;
+use super::locations::statement_marker;
 use super::scope::{is_pointer, is_threaded, Scope};
-use super::types::transpile_type_annotation;
-use anyhow::Result;
+use super::types::{check_annotation, transpile_type_annotation};
+use crate::diagnostics::Diagnostic;
+use crate::messages::msg;
+use crate::parser::source_span;
+use anyhow::{bail, Result};
+use swc_common::Spanned;
 use swc_ecma_ast::*;
 
 pub fn transpile_statement(stmt: &Stmt, scope: &mut Scope) -> Result<String> {
+    let mut code = transpile_statement_kind(stmt, scope)?;
+    if let Some(cfg) = cfg_attribute(stmt.span(), scope.source())? {
+        // A block's statements are emitted without braces; `#[cfg]` needs them to apply to all of them.
+        code = match stmt {
+            Stmt::Block(_) => format!("{} {{\n{}\n}}", cfg, code),
+            _ => format!("{} {}", cfg, code),
+        };
+    }
+    Ok(match statement_marker(stmt, scope) {
+        Some(marker) => format!("{} {}", marker, code),
+        None => code,
+    })
+}
+
+fn transpile_statement_kind(stmt: &Stmt, scope: &mut Scope) -> Result<String> {
     match stmt {
         Stmt::Return(return_stmt) => {
             if let Some(arg) = &return_stmt.arg {
-                let expr = transpile_expression(arg, scope)?;
+                let expr = binding_value(arg, transpile_expression(arg, scope)?, scope);
                 Ok(format!("return {};", expr))
             } else {
                 Ok("return;".to_string())
@@ -24,37 +50,51 @@ pub fn transpile_statement(stmt: &Stmt, scope: &mut Scope) -> Result<String> {
             let cons = transpile_statement(&if_stmt.cons, scope)?;
             if let Some(alt) = &if_stmt.alt {
                 let alt_str = transpile_statement(alt, scope)?;
-                Ok(format!("if {} {{\n{}\n}} else {{\n{}\n}}", cond, cons, alt_str))
+                Ok(format!(
+                    "if {} {{\n{}\n}} else {{\n{}\n}}",
+                    cond, cons, alt_str
+                ))
             } else {
                 Ok(format!("if {} {{\n{}\n}}", cond, cons))
             }
         }
-        Stmt::While(while_stmt) => transpile_while_stmt(while_stmt, scope),
-        Stmt::For(for_stmt) => transpile_for_stmt(for_stmt, scope),
-        Stmt::ForIn(for_in_stmt) => transpile_for_in_stmt(for_in_stmt, scope),
-        Stmt::ForOf(for_of_stmt) => transpile_for_of_stmt(for_of_stmt, scope),
+        Stmt::While(while_stmt) => transpile_while_stmt(while_stmt, None, scope),
+        Stmt::DoWhile(do_while) => transpile_do_while_stmt(do_while, None, scope),
+        Stmt::For(for_stmt) => transpile_for_stmt(for_stmt, None, scope),
+        Stmt::ForIn(for_in_stmt) => transpile_for_in_stmt(for_in_stmt, None, scope),
+        Stmt::ForOf(for_of_stmt) => transpile_for_of_stmt(for_of_stmt, None, scope),
+        Stmt::Labeled(labeled) => transpile_labeled_stmt(labeled, scope),
         Stmt::Try(try_stmt) => transpile_try_stmt(try_stmt, scope),
-        Stmt::Break(_) => Ok("break;".to_string()),
-        Stmt::Continue(_) => Ok("continue;".to_string()),
+        Stmt::Break(break_stmt) => Ok(match &break_stmt.label {
+            Some(label) => format!("break '{};", label.sym),
+            None => "break;".to_string(),
+        }),
+        Stmt::Continue(continue_stmt) => Ok(match &continue_stmt.label {
+            Some(label) => format!("continue '{};", label.sym),
+            None => "continue;".to_string(),
+        }),
         Stmt::Decl(Decl::Var(var_decl)) => {
+            if scope.is_static_decl(var_decl.span) {
+                return Err(Diagnostic::error("nested-static", msg!("nested-static"))
+                    .with_span(source_span(var_decl.span))
+                    .into());
+            }
             let is_mut = matches!(var_decl.kind, VarDeclKind::Var);
             let binding = if is_mut { "let mut" } else { "let" };
             let mut parts = Vec::new();
-            for decl in &var_decl.decls {
-                let name = match &decl.name {
-                    Pat::Ident(ident) => ident.id.sym.to_string(),
-                    _ => "unknown".to_string(),
-                };
-                let type_ann = match &decl.name {
-                    Pat::Ident(ident) => ident
-                        .type_ann
-                        .as_deref()
-                        .map(|ann| transpile_type_annotation(ann)),
-                    _ => None,
-                };
+            for (decl, type_ann) in var_decl.decls.iter().zip(declared_types(var_decl)) {
+                if let Pat::Ident(BindingIdent {
+                    type_ann: Some(ann),
+                    ..
+                }) = &decl.name
+                {
+                    check_annotation(ann)?;
+                }
+                let name = binding_name(&decl.name, "unknown", scope)?;
 
                 let declared_as_pointer = type_ann.as_ref().map(|t| is_pointer(t)).unwrap_or(false);
-                let declared_as_threaded = type_ann.as_ref().map(|t| is_threaded(t)).unwrap_or(false);
+                let declared_as_threaded =
+                    type_ann.as_ref().map(|t| is_threaded(t)).unwrap_or(false);
 
                 if let Some(init) = &decl.init {
                     // `let p2 = p` where p is already a Pointer or Threaded → clone
@@ -62,7 +102,11 @@ pub fn transpile_statement(stmt: &Stmt, scope: &mut Scope) -> Result<String> {
                         Expr::Ident(ident) => {
                             let n = ident.sym.to_string();
                             let ty = scope.get(&n).map(|t| t.as_str()).unwrap_or("");
-                            if is_pointer(ty) || is_threaded(ty) { Some(n) } else { None }
+                            if is_pointer(ty) || is_threaded(ty) {
+                                Some(n)
+                            } else {
+                                None
+                            }
                         }
                         _ => None,
                     };
@@ -80,14 +124,23 @@ pub fn transpile_statement(stmt: &Stmt, scope: &mut Scope) -> Result<String> {
                     } else if let Some(src) = &init_shared_name {
                         // `let p2 = p` → clone, inherit type
                         let shared_type = scope.get(src).cloned().unwrap();
-                        let clone_fn = if is_threaded(&shared_type) { "Arc::clone" } else { "Rc::clone" };
+                        let clone_fn = if is_threaded(&shared_type) {
+                            "Arc::clone"
+                        } else {
+                            "Rc::clone"
+                        };
                         scope.insert(name.clone(), shared_type);
                         format!("{}(&{})", clone_fn, src)
                     } else {
-                        let expr_str = transpile_expression(init, scope)?;
-                        // Register all typed variables in scope for method dispatch
-                        if let Some(ty) = &type_ann {
-                            scope.insert(name.clone(), ty.clone());
+                        let expr_str = match &type_ann {
+                            Some(ty) => transpile_expression_as(init, ty, scope)?,
+                            None => transpile_expression(init, scope)?,
+                        };
+                        // Register all typed variables in scope for method dispatch; unannotated
+                        // bindings take the inferred type of their initializer.
+                        match type_ann.clone().or_else(|| infer_rust_type(init, scope)) {
+                            Some(ty) => scope.insert(name.clone(), ty),
+                            None => scope.declare_untyped(name.clone()),
                         }
                         expr_str
                     };
@@ -96,98 +149,175 @@ pub fn transpile_statement(stmt: &Stmt, scope: &mut Scope) -> Result<String> {
                         Some(ty) => parts.push(format!("{} {}: {} = {};", binding, name, ty, val)),
                         None => parts.push(format!("{} {} = {};", binding, name, val)),
                     }
+                } else {
+                    // Assigned later; `assignment::check_function_body` has checked every read.
+                    match &type_ann {
+                        Some(ty) => {
+                            scope.insert(name.clone(), ty.clone());
+                            parts.push(format!("{} {}: {};", binding, name, ty));
+                        }
+                        None => {
+                            scope.declare_untyped(name.clone());
+                            parts.push(format!("{} {};", binding, name));
+                        }
+                    }
                 }
             }
             Ok(parts.join("\n"))
         }
-        Stmt::Throw(throw_stmt) => {
-            // throw new Error("msg") → return Err("msg".to_string())
-            let msg = match &*throw_stmt.arg {
-                Expr::New(new_expr) => {
-                    if let Some(args) = &new_expr.args {
-                        if let Some(first) = args.first() {
-                            transpile_expression(&first.expr, scope)?
-                        } else {
-                            "\"error\".to_string()".to_string()
-                        }
-                    } else {
-                        "\"error\".to_string()".to_string()
-                    }
-                }
-                other => transpile_expression(other, scope)?,
-            };
-            Ok(format!("return Err({});", msg))
+        Stmt::Throw(throw_stmt) => transpile_throw(throw_stmt, scope),
+        Stmt::Empty(_) => Ok(String::new()),
+        _ => {
+            let construct = statement_keyword(stmt);
+            scope.unsupported(
+                "unsupported-statement",
+                msg!("unsupported-statement", construct),
+                stmt.span(),
+            )?;
+            Ok(format!("// unsupported `{}` left out", construct))
         }
-        _ => Ok("// Statement non supporté".to_string()),
     }
 }
 
-pub fn transpile_block_stmt(block: &BlockStmt, indent: &str, scope: &mut Scope) -> Result<String> {
-    let mut result = Vec::new();
-    for s in &block.stmts {
-        let stmt_str = transpile_statement(s, scope)?;
-        result.push(format!("{}{}", indent, stmt_str));
+/// Keyword naming the kind of `stmt`, for messages about it.
+pub(super) fn statement_keyword(stmt: &Stmt) -> &'static str {
+    match stmt {
+        Stmt::Switch(_) => "switch",
+        Stmt::With(_) => "with",
+        Stmt::Debugger(_) => "debugger",
+        Stmt::Decl(Decl::Class(_)) => "class",
+        Stmt::Decl(Decl::Fn(_)) => "function",
+        Stmt::Decl(Decl::Using(_)) => "using",
+        Stmt::Decl(Decl::TsInterface(_)) => "interface",
+        Stmt::Decl(Decl::TsTypeAlias(_)) => "type",
+        Stmt::Decl(Decl::TsEnum(_)) => "enum",
+        Stmt::Decl(Decl::TsModule(_)) => "namespace",
+        _ => "statement",
     }
-    Ok(result.join("\n"))
 }
 
-pub fn transpile_global_const(var_decl: &VarDecl) -> Result<Vec<String>> {
-    if !matches!(var_decl.kind, VarDeclKind::Const) {
-        return Ok(Vec::new());
-    }
-
-    let scope = Scope::new();
-    let mut parts = Vec::new();
-    for decl in &var_decl.decls {
-        let (name, type_ann) = match &decl.name {
-            Pat::Ident(ident) => (
-                ident.id.sym.to_string(),
-                ident
-                    .type_ann
-                    .as_deref()
-                    .map(|ann| transpile_type_annotation(ann)),
-            ),
-            _ => continue,
-        };
-
-        let Some(init) = &decl.init else {
-            continue;
+/// Rust type of each declarator of `var_decl`. In `var a, b: int32;` the uninitialized `a` takes the
+/// type of the next annotated declarator.
+fn declared_types(var_decl: &VarDecl) -> Vec<Option<String>> {
+    let mut types = vec![None; var_decl.decls.len()];
+    let mut next_type: Option<String> = None;
+    for (i, decl) in var_decl.decls.iter().enumerate().rev() {
+        let annotated = match &decl.name {
+            Pat::Ident(ident) => ident.type_ann.as_deref().map(transpile_type_annotation),
+            _ => None,
         };
-        let val = transpile_const_value(init, &scope)?;
-
-        match type_ann {
-            Some(ty) if ty == "String" => parts.push(format!("const {}: &'static str = {};", name, val)),
-            Some(ty) => parts.push(format!("const {}: {} = {};", name, ty, val)),
-            None => parts.push(format!("const {}: i32 = {};", name, val)),
+        if annotated.is_some() {
+            next_type = annotated.clone();
+            types[i] = annotated;
+        } else if decl.init.is_some() {
+            next_type = None;
+        } else {
+            types[i] = next_type.clone();
         }
     }
-    Ok(parts)
+    types
 }
 
-fn transpile_const_value(expr: &Expr, scope: &Scope) -> Result<String> {
-    match expr {
-        Expr::Lit(Lit::Str(s)) => Ok(format!("{:?}", s.value.to_string_lossy())),
-        Expr::Lit(Lit::Num(n)) => Ok(n.value.to_string()),
-        Expr::Lit(Lit::Bool(b)) => Ok(b.value.to_string()),
-        Expr::Unary(unary) if matches!(unary.op, UnaryOp::Minus) => {
-            let inner = transpile_const_value(&unary.arg, scope)?;
-            Ok(format!("-{}", inner))
+/// Transpiles a `{ ... }` block in its own scope frame; its bindings are dropped afterwards.
+pub fn transpile_block_stmt(block: &BlockStmt, indent: &str, scope: &mut Scope) -> Result<String> {
+    scope.scoped(|scope| {
+        let mut result = Vec::new();
+        for s in &block.stmts {
+            let stmt_str = transpile_statement(s, scope)?;
+            result.push(format!("{}{}", indent, stmt_str));
         }
-        _ => transpile_expression(expr, scope),
+        Ok(result.join("\n"))
+    })
+}
+
+/// `outer: for (...) { ... }` → `'outer: while ... { ... }`; a labeled block becomes a Rust labeled block.
+fn transpile_labeled_stmt(labeled: &LabeledStmt, scope: &mut Scope) -> Result<String> {
+    let label = labeled.label.sym.as_ref();
+    match &*labeled.body {
+        Stmt::While(while_stmt) => transpile_while_stmt(while_stmt, Some(label), scope),
+        Stmt::DoWhile(do_while) => transpile_do_while_stmt(do_while, Some(label), scope),
+        Stmt::For(for_stmt) => transpile_for_stmt(for_stmt, Some(label), scope),
+        Stmt::ForIn(for_in) => transpile_for_in_stmt(for_in, Some(label), scope),
+        Stmt::ForOf(for_of) => transpile_for_of_stmt(for_of, Some(label), scope),
+        Stmt::Block(block) => {
+            let body = transpile_block_stmt(block, "    ", scope)?;
+            Ok(format!("'{}: {{\n{}\n}}", label, body))
+        }
+        _ => bail!("label `{}` must be placed on a loop or a block", label),
     }
 }
 
-fn transpile_while_stmt(while_stmt: &WhileStmt, scope: &mut Scope) -> Result<String> {
+/// `'label: ` prefix for a loop header.
+fn loop_label(label: Option<&str>) -> String {
+    label.map(|l| format!("'{}: ", l)).unwrap_or_default()
+}
+
+fn transpile_while_stmt(
+    while_stmt: &WhileStmt,
+    label: Option<&str>,
+    scope: &mut Scope,
+) -> Result<String> {
     let cond = transpile_expression(&while_stmt.test, scope)?;
     let body = transpile_statement(&while_stmt.body, scope)?;
-    Ok(format!("while {} {{\n{}\n}}", cond, indent_block(&body, "    ")))
+    if matches!(&*while_stmt.test, Expr::Lit(Lit::Bool(b)) if b.value) {
+        return Ok(format!(
+            "{}loop {{\n{}\n}}",
+            loop_label(label),
+            indent_block(&body, "    ")
+        ));
+    }
+    Ok(format!(
+        "{}while {} {{\n{}\n}}",
+        loop_label(label),
+        cond,
+        indent_block(&body, "    ")
+    ))
 }
 
-fn transpile_for_stmt(for_stmt: &ForStmt, scope: &mut Scope) -> Result<String> {
-    let init = match &for_stmt.init {
-        Some(VarDeclOrExpr::VarDecl(var_decl)) => {
-            transpile_statement(&Stmt::Decl(Decl::Var(Box::new((**var_decl).clone()))), scope)?
-        }
+/// `do { ... } loop (cond);` → `loop { ...; if !(cond) { break; } }`. When the body can `continue`,
+/// the test must still run afterwards, so the first pass is forced with a flag instead.
+fn transpile_do_while_stmt(
+    do_while: &DoWhileStmt,
+    label: Option<&str>,
+    scope: &mut Scope,
+) -> Result<String> {
+    let body = transpile_statement(&do_while.body, scope)?;
+    let cond = transpile_expression(&do_while.test, scope)?;
+    if continues_loop(&do_while.body, label, false) {
+        return Ok(format!(
+            "let mut __trust_first = true;\n{}while __trust_first || ({}) {{\n    __trust_first = false;\n{}\n}}",
+            loop_label(label),
+            cond,
+            indent_block(&body, "    ")
+        ));
+    }
+    Ok(format!(
+        "{}loop {{\n{}\n    if !({}) {{\n        break;\n    }}\n}}",
+        loop_label(label),
+        indent_block(&body, "    "),
+        cond
+    ))
+}
+
+fn transpile_for_stmt(
+    for_stmt: &ForStmt,
+    label: Option<&str>,
+    scope: &mut Scope,
+) -> Result<String> {
+    // The loop counter is only visible inside the loop.
+    scope.scoped(|scope| transpile_for_stmt_in_scope(for_stmt, label, scope))
+}
+
+fn transpile_for_stmt_in_scope(
+    for_stmt: &ForStmt,
+    label: Option<&str>,
+    scope: &mut Scope,
+) -> Result<String> {
+    let mut init = match &for_stmt.init {
+        Some(VarDeclOrExpr::VarDecl(var_decl)) => transpile_statement(
+            &Stmt::Decl(Decl::Var(Box::new((**var_decl).clone()))),
+            scope,
+        )?,
         Some(VarDeclOrExpr::Expr(expr)) => format!("{};", transpile_expression(expr, scope)?),
         None => String::new(),
     };
@@ -204,14 +334,29 @@ fn transpile_for_stmt(for_stmt: &ForStmt, scope: &mut Scope) -> Result<String> {
 
     let body = transpile_statement(&for_stmt.body, scope)?;
     let mut while_body = indent_block(&body, "    ");
-    if let Some(update) = update {
-        if !while_body.is_empty() {
-            while_body.push('\n');
+    let cond = match update {
+        // `continue` must still run the update: run it before re-testing instead of at the end of the body.
+        Some(update) if continues_loop(&for_stmt.body, label, false) => {
+            if !init.is_empty() {
+                init.push('\n');
+            }
+            init.push_str("let mut __trust_first = true;");
+            format!(
+                "{{ if !__trust_first {{ {} }} __trust_first = false; {} }}",
+                update, cond
+            )
         }
-        while_body.push_str("    ");
-        while_body.push_str(&update);
-    }
-    let while_code = format!("while {} {{\n{}\n}}", cond, while_body);
+        Some(update) => {
+            if !while_body.is_empty() {
+                while_body.push('\n');
+            }
+            while_body.push_str("    ");
+            while_body.push_str(&update);
+            cond
+        }
+        None => cond,
+    };
+    let while_code = format!("{}while {} {{\n{}\n}}", loop_label(label), cond, while_body);
 
     if init.is_empty() {
         Ok(while_code)
@@ -220,31 +365,114 @@ fn transpile_for_stmt(for_stmt: &ForStmt, scope: &mut Scope) -> Result<String> {
     }
 }
 
-fn transpile_for_in_stmt(for_in: &ForInStmt, scope: &mut Scope) -> Result<String> {
-    let (binding, prelude) = transpile_for_head_binding(&for_in.left, scope)?;
-    let right = transpile_expression(&for_in.right, scope)?;
-    let body = transpile_statement(&for_in.body, scope)?;
-    let for_code = format!(
-        "for {} in ({}).iter().cloned() {{\n{}\n}}",
-        binding,
-        right,
-        indent_block(&body, "    ")
-    );
-    if prelude.is_empty() {
-        Ok(for_code)
-    } else {
-        Ok(format!("{}\n{}", prelude, for_code))
+/// True if `stmt` contains a `continue` that targets the loop whose body it is: an unlabeled one
+/// outside nested loops, or one naming `label`.
+fn continues_loop(stmt: &Stmt, label: Option<&str>, nested: bool) -> bool {
+    match stmt {
+        Stmt::Continue(continue_stmt) => match &continue_stmt.label {
+            Some(target) => Some(target.sym.as_ref()) == label,
+            None => !nested,
+        },
+        Stmt::Block(block) => block.stmts.iter().any(|s| continues_loop(s, label, nested)),
+        Stmt::If(if_stmt) => {
+            continues_loop(&if_stmt.cons, label, nested)
+                || if_stmt
+                    .alt
+                    .as_deref()
+                    .is_some_and(|alt| continues_loop(alt, label, nested))
+        }
+        Stmt::Labeled(labeled) => continues_loop(&labeled.body, label, nested),
+        Stmt::Try(try_stmt) => {
+            try_stmt
+                .block
+                .stmts
+                .iter()
+                .any(|s| continues_loop(s, label, nested))
+                || try_stmt.handler.as_ref().is_some_and(|h| {
+                    h.body
+                        .stmts
+                        .iter()
+                        .any(|s| continues_loop(s, label, nested))
+                })
+                || try_stmt
+                    .finalizer
+                    .as_ref()
+                    .is_some_and(|f| f.stmts.iter().any(|s| continues_loop(s, label, nested)))
+        }
+        Stmt::While(while_stmt) => continues_loop(&while_stmt.body, label, true),
+        Stmt::DoWhile(do_while) => continues_loop(&do_while.body, label, true),
+        Stmt::For(for_stmt) => continues_loop(&for_stmt.body, label, true),
+        Stmt::ForIn(for_in) => continues_loop(&for_in.body, label, true),
+        Stmt::ForOf(for_of) => continues_loop(&for_of.body, label, true),
+        _ => false,
     }
 }
 
-fn transpile_for_of_stmt(for_of: &ForOfStmt, scope: &mut Scope) -> Result<String> {
-    let (binding, prelude) = transpile_for_head_binding(&for_of.left, scope)?;
-    let right = transpile_expression(&for_of.right, scope)?;
-    let body = transpile_statement(&for_of.body, scope)?;
+fn transpile_for_in_stmt(
+    for_in: &ForInStmt,
+    label: Option<&str>,
+    scope: &mut Scope,
+) -> Result<String> {
+    transpile_for_each(&for_in.left, &for_in.right, &for_in.body, label, scope)
+}
+
+fn transpile_for_of_stmt(
+    for_of: &ForOfStmt,
+    label: Option<&str>,
+    scope: &mut Scope,
+) -> Result<String> {
+    transpile_for_each(&for_of.left, &for_of.right, &for_of.body, label, scope)
+}
+
+/// `for (x in items)` / `for (x of items)` → `for x in (items).iter().cloned()`; over a range
+/// (`for (i in 0..n)`) → `for i in 0..n`.
+fn transpile_for_each(
+    left: &ForHead,
+    right: &Expr,
+    body: &Stmt,
+    label: Option<&str>,
+    scope: &mut Scope,
+) -> Result<String> {
+    // The iterable is evaluated outside the loop binding's scope.
+    let range = transpile_range(right, scope)?;
+    let right_ty = infer_rust_type(right, scope);
+    let (iterable, element_ty) = match &range {
+        Some(range) => {
+            let element_ty = right_ty.as_deref().and_then(|ty| {
+                ty.strip_prefix("std::ops::Range<")
+                    .or_else(|| ty.strip_prefix("std::ops::RangeInclusive<"))
+                    .and_then(|t| t.strip_suffix('>'))
+                    .map(str::to_string)
+            });
+            (range.clone(), element_ty)
+        }
+        None => {
+            let rendered = transpile_expression(right, scope)?;
+            // Lazy chains and `Iterable` values are iterated as they are.
+            match right_ty
+                .as_deref()
+                .and_then(|ty| iterators::for_of(ty, &rendered, scope.symbols()))
+            {
+                Some(iterated) => iterated,
+                None => (format!("({}).iter().cloned()", rendered), None),
+            }
+        }
+    };
+    scope.push();
+    let head = transpile_for_head_binding(left, scope);
+    if let (Ok((binding, _)), Some(ty)) = (&head, element_ty) {
+        if scope.get(binding).is_none() {
+            scope.insert(binding.clone(), ty);
+        }
+    }
+    let body = head.and_then(|head| Ok((head, transpile_statement(body, scope)?)));
+    scope.pop();
+    let ((binding, prelude), body) = body?;
     let for_code = format!(
-        "for {} in ({}).iter().cloned() {{\n{}\n}}",
+        "{}for {} in {} {{\n{}\n}}",
+        loop_label(label),
         binding,
-        right,
+        iterable,
         indent_block(&body, "    ")
     );
     if prelude.is_empty() {
@@ -255,12 +483,22 @@ fn transpile_for_of_stmt(for_of: &ForOfStmt, scope: &mut Scope) -> Result<String
 }
 
 fn transpile_try_stmt(try_stmt: &TryStmt, scope: &mut Scope) -> Result<String> {
-    let mut try_scope = scope.clone();
-    let try_body = transpile_block_stmt(&try_stmt.block, "            ", &mut try_scope)?;
+    let error_type = if try_uses_typed_errors(try_stmt, scope) {
+        TRUST_ERROR
+    } else {
+        "String"
+    };
+    let outer_error_type = scope.set_error_type(Some(error_type.to_string()));
+    let try_body = transpile_block_stmt(&try_stmt.block, "            ", scope);
+    scope.set_error_type(outer_error_type);
+    let try_body = try_body?;
 
     let mut out = String::new();
     out.push_str("{\n");
-    out.push_str("    let __trust_try_result: Result<(), String> = (|| -> Result<(), String> {\n");
+    out.push_str(&format!(
+        "    let __trust_try_result: Result<(), {}> = (|| -> Result<(), {}> {{\n",
+        error_type, error_type
+    ));
     out.push_str(&try_body);
     if !try_body.is_empty() {
         out.push('\n');
@@ -269,30 +507,64 @@ fn transpile_try_stmt(try_stmt: &TryStmt, scope: &mut Scope) -> Result<String> {
     out.push_str("    })();\n");
 
     if let Some(handler) = &try_stmt.handler {
-        let catch_name = match &handler.param {
-            Some(pat) => match pat {
-                Pat::Ident(ident) => ident.id.sym.to_string(),
-                _ => "_err".to_string(),
-            },
-            None => "_err".to_string(),
+        let (catch_name, catch_type) = match &handler.param {
+            Some(Pat::Ident(ident)) => (
+                ident.id.sym.to_string(),
+                ident.type_ann.as_deref().map(transpile_type_annotation),
+            ),
+            _ => ("_err".to_string(), None),
         };
 
-        let mut catch_scope = scope.clone();
-        catch_scope.insert(catch_name.clone(), "String".to_string());
-        let catch_body = transpile_block_stmt(&handler.body, "        ", &mut catch_scope)?;
-        out.push_str(&format!("    if let Err({}) = __trust_try_result {{\n", catch_name));
-        out.push_str(&catch_body);
-        if !catch_body.is_empty() {
-            out.push('\n');
+        let catch_body = scope.scoped(|scope| {
+            scope.insert(
+                catch_name.clone(),
+                catch_type.clone().unwrap_or_else(|| error_type.to_string()),
+            );
+            transpile_block_stmt(&handler.body, "        ", scope)
+        })?;
+        match &catch_type {
+            // `catch (e: NetworkError)` handles that variant; other errors keep propagating.
+            Some(ty) => {
+                let rethrow = if scope.error_type() == Some(TRUST_ERROR) {
+                    "return Err(__trust_error)"
+                } else {
+                    "panic!(\"{}\", __trust_error)"
+                };
+                out.push_str("    match __trust_try_result {\n        Ok(()) => {}\n");
+                out.push_str(&format!(
+                    "        Err({}::{}({})) => {{\n",
+                    TRUST_ERROR,
+                    catch_variant(ty),
+                    catch_name
+                ));
+                out.push_str(&catch_body);
+                if !catch_body.is_empty() {
+                    out.push('\n');
+                }
+                out.push_str("        }\n");
+                out.push_str(&format!(
+                    "        Err(__trust_error) => {},\n    }}\n",
+                    rethrow
+                ));
+            }
+            None => {
+                out.push_str(&format!(
+                    "    if let Err({}) = __trust_try_result {{\n",
+                    catch_name
+                ));
+                out.push_str(&catch_body);
+                if !catch_body.is_empty() {
+                    out.push('\n');
+                }
+                out.push_str("    }\n");
+            }
         }
-        out.push_str("    }\n");
     } else {
         out.push_str("    let _ = __trust_try_result;\n");
     }
 
     if let Some(finalizer) = &try_stmt.finalizer {
-        let mut final_scope = scope.clone();
-        let final_body = transpile_block_stmt(finalizer, "    ", &mut final_scope)?;
+        let final_body = transpile_block_stmt(finalizer, "    ", scope)?;
         out.push_str(&final_body);
         if !final_body.is_empty() {
             out.push('\n');
@@ -309,9 +581,9 @@ fn transpile_for_head_binding(head: &ForHead, scope: &mut Scope) -> Result<(Stri
             if let Some(first) = var_decl.decls.first() {
                 if let Pat::Ident(ident) = &first.name {
                     let name = ident.id.sym.to_string();
-                    if let Some(ann) = ident.type_ann.as_deref() {
-                        let ty = transpile_type_annotation(ann);
-                        scope.insert(name.clone(), ty);
+                    match ident.type_ann.as_deref() {
+                        Some(ann) => scope.insert(name.clone(), transpile_type_annotation(ann)),
+                        None => scope.declare_untyped(name.clone()),
                     }
                     if let Some(init) = &first.init {
                         let init_expr = transpile_expression(init, scope)?;
@@ -326,9 +598,9 @@ fn transpile_for_head_binding(head: &ForHead, scope: &mut Scope) -> Result<(Stri
         ForHead::Pat(pat) => match &**pat {
             Pat::Ident(ident) => {
                 let name = ident.id.sym.to_string();
-                if let Some(ann) = ident.type_ann.as_deref() {
-                    let ty = transpile_type_annotation(ann);
-                    scope.insert(name.clone(), ty);
+                match ident.type_ann.as_deref() {
+                    Some(ann) => scope.insert(name.clone(), transpile_type_annotation(ann)),
+                    None => scope.declare_untyped(name.clone()),
                 }
                 Ok((name, String::new()))
             }
@@ -0,0 +1,132 @@
+use super::expressions::{infer_rust_type, transpile_expression_as};
+use super::scope::{is_pointer, is_weak_pointer, Scope};
+use super::types::{check_annotation, transpile_type_annotation};
+use crate::diagnostics::Diagnostic;
+use crate::messages::msg;
+use crate::parser::source_span;
+use anyhow::Result;
+use swc_ecma_ast::*;
+
+/// A module-level `static var`, shared by every function behind a `Mutex`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StaticVar {
+    pub rust_type: String,
+    /// Initialized on first use through `OnceLock` (its initializer is not a constant).
+    pub lazy: bool,
+}
+
+impl StaticVar {
+    /// Expression for the `&'static Mutex<T>` holding `name`.
+    pub fn handle(&self, name: &str) -> String {
+        if self.lazy {
+            format!("{}()", static_ident(name))
+        } else {
+            static_ident(name)
+        }
+    }
+
+    /// Reads the current value; the lock is released before the rest of the statement runs.
+    pub fn read(&self, name: &str) -> String {
+        format!(
+            "{}.lock().map(|value| value.clone()).unwrap()",
+            self.handle(name)
+        )
+    }
+
+    /// The locked value, for assignments and mutating method calls.
+    pub fn guard(&self, name: &str) -> String {
+        format!("{}.lock().unwrap()", self.handle(name))
+    }
+}
+
+fn static_ident(name: &str) -> String {
+    format!("__trust_static_{}", name)
+}
+
+/// Name and storage of each declarator of a `static var` declaration; unannotated types are
+/// inferred in `scope`.
+pub fn collect(var_decl: &VarDecl, scope: &Scope) -> Vec<(String, StaticVar)> {
+    var_decl
+        .decls
+        .iter()
+        .filter_map(|decl| {
+            let Pat::Ident(ident) = &decl.name else {
+                return None;
+            };
+            let rust_type = ident
+                .type_ann
+                .as_deref()
+                .map(transpile_type_annotation)
+                .or_else(|| {
+                    decl.init
+                        .as_deref()
+                        .and_then(|init| infer_rust_type(init, scope))
+                })
+                .unwrap_or_else(|| "i32".to_string());
+            let lazy = !decl.init.as_deref().map(is_constant).unwrap_or(false);
+            Some((ident.id.sym.to_string(), StaticVar { rust_type, lazy }))
+        })
+        .collect()
+}
+
+/// Numbers and booleans can initialize a `static` directly; anything else runs on first use.
+fn is_constant(expr: &Expr) -> bool {
+    match expr {
+        Expr::Lit(Lit::Num(_) | Lit::Bool(_)) => true,
+        Expr::Unary(unary) => matches!(unary.op, UnaryOp::Minus) && is_constant(&unary.arg),
+        Expr::Paren(paren) => is_constant(&paren.expr),
+        _ => false,
+    }
+}
+
+/// `static var count: int32 = 0;` → a `static` `Mutex`, or a `OnceLock` accessor for initializers
+/// that are not constants.
+pub fn transpile_static(var_decl: &VarDecl, scope: &Scope) -> Result<Vec<String>> {
+    let mut parts = Vec::new();
+    for decl in &var_decl.decls {
+        let Pat::Ident(ident) = &decl.name else {
+            continue;
+        };
+        if let Some(ann) = ident.type_ann.as_deref() {
+            check_annotation(ann)?;
+        }
+        let name = ident.id.sym.to_string();
+        let Some(var) = scope.symbols().statics.get(&name) else {
+            continue;
+        };
+        let Some(init) = &decl.init else {
+            return Err(
+                Diagnostic::error("static-initializer", msg!("static-initializer", name))
+                    .with_span(source_span(ident.id.span))
+                    .into(),
+            );
+        };
+        if is_pointer(&var.rust_type) || is_weak_pointer(&var.rust_type) {
+            return Err(
+                Diagnostic::error("static-pointer", msg!("static-pointer", name))
+                    .with_span(source_span(ident.id.span))
+                    .into(),
+            );
+        }
+
+        let value = transpile_expression_as(init, &var.rust_type, scope)?;
+        let mutex = format!("std::sync::Mutex<{}>", var.rust_type);
+        if var.lazy {
+            parts.push(format!(
+                "#[allow(non_snake_case)]\nfn {}() -> &'static {} {{\n    static CELL: std::sync::OnceLock<{}> = std::sync::OnceLock::new();\n    CELL.get_or_init(|| std::sync::Mutex::new({}))\n}}",
+                static_ident(&name),
+                mutex,
+                mutex,
+                value
+            ));
+        } else {
+            parts.push(format!(
+                "#[allow(non_upper_case_globals)]\nstatic {}: {} = std::sync::Mutex::new({});",
+                static_ident(&name),
+                mutex,
+                value
+            ));
+        }
+    }
+    Ok(parts)
+}
@@ -0,0 +1,180 @@
+//! String literals in generated Rust (including raw strings), and building strings without copying them: the
+//! `StringBuilder` type, and `s += part` / `s = s + a + b` on a `string` appended in place.
+
+use super::expressions::{infer_rust_type, transpile_expression};
+use super::scope::Scope;
+use anyhow::Result;
+use swc_ecma_ast::*;
+
+/// Rust type of a TRUST `StringBuilder`.
+pub const STRING_BUILDER: &str = "StringBuilder";
+
+/// The `StringBuilder` type, emitted when a program uses it. It is shared like `Rng`: appending
+/// through a `val` works.
+pub const RUNTIME: &str = r#"#[derive(Debug, Clone, Default)]
+struct StringBuilder {
+    text: std::cell::RefCell<String>,
+}
+
+#[allow(dead_code, non_snake_case)]
+impl StringBuilder {
+    fn new() -> StringBuilder {
+        StringBuilder::default()
+    }
+
+    fn push(&self, part: impl std::fmt::Display) -> &StringBuilder {
+        use std::fmt::Write as _;
+        let _ = write!(self.text.borrow_mut(), "{}", part);
+        self
+    }
+
+    fn pushLine(&self, part: impl std::fmt::Display) -> &StringBuilder {
+        use std::fmt::Write as _;
+        let _ = writeln!(self.text.borrow_mut(), "{}", part);
+        self
+    }
+
+    fn len(&self) -> i32 {
+        self.text.borrow().chars().count() as i32
+    }
+
+    fn isEmpty(&self) -> bool {
+        self.text.borrow().is_empty()
+    }
+
+    fn clear(&self) {
+        self.text.borrow_mut().clear();
+    }
+}
+
+impl std::fmt::Display for StringBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&self.text.borrow())
+    }
+}"#;
+
+/// `value` as a quoted Rust string literal, with quotes, backslashes and control characters escaped.
+/// Every TRUST string emitted into generated code goes through here (or [`format_string_text`]).
+pub fn rust_string_literal(value: &str) -> String {
+    format!("{:?}", value)
+}
+
+/// `text` escaped for use inside a Rust format string literal: as [`rust_string_literal`], without
+/// the quotes, and with `{` / `}` doubled so they print literally.
+pub fn format_string_text(text: &str) -> String {
+    let quoted = rust_string_literal(&text.replace('{', "{{").replace('}', "}}"));
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+/// Preprocessor marker for a raw string: `r"C:\dir"` → `__trust_raw("C:\\dir")`.
+pub const RAW_CALLEE: &str = "__trust_raw";
+
+/// `value` as a Rust raw string literal, with as few `#` as the quotes in it allow.
+pub fn rust_raw_string_literal(value: &str) -> String {
+    let hashes = (0..)
+        .find(|&n| !value.contains(&format!("\"{}", "#".repeat(n))))
+        .unwrap_or(0);
+    let hashes = "#".repeat(hashes);
+    format!("r{}\"{}\"{}", hashes, value, hashes)
+}
+
+/// Text of a `__trust_raw("...")` marker.
+pub fn raw_text(call: &CallExpr) -> Option<String> {
+    match call.args.first().map(|arg| &*arg.expr) {
+        Some(Expr::Lit(Lit::Str(s))) => Some(s.value.to_string_lossy().into_owned()),
+        _ => None,
+    }
+}
+
+/// Whether `StringBuilder` means the built-in type rather than a program's own struct.
+pub fn is_builtin(scope: &Scope) -> bool {
+    !scope.symbols().is_struct(STRING_BUILDER) && scope.get(STRING_BUILDER).is_none()
+}
+
+/// `sb.push(part)`, `sb.pushLine()`, ... on a `StringBuilder`; string literals are appended
+/// without allocating a `String` first.
+pub fn transpile_method(
+    obj: &str,
+    method: &str,
+    args: &[ExprOrSpread],
+    arg_strs: &[String],
+) -> Option<String> {
+    match (method, arg_strs) {
+        ("push" | "pushLine", [part]) => Some(format!(
+            "{}.{}({})",
+            obj,
+            method,
+            borrowed(&args[0].expr, part)
+        )),
+        ("pushLine", []) => Some(format!("{}.push('\\n')", obj)),
+        ("isEmpty" | "clear", []) => Some(format!("{}.{}()", obj, method)),
+        _ => None,
+    }
+}
+
+/// Rust type returned by a `StringBuilder` method; `push` returns the builder, for chaining.
+pub fn method_return_type(method: &str) -> Option<&'static str> {
+    match method {
+        "push" | "pushLine" => Some(STRING_BUILDER),
+        "isEmpty" => Some("bool"),
+        "toString" => Some("String"),
+        _ => None,
+    }
+}
+
+/// `"x".to_string()` → `"x"`; other values unchanged.
+fn borrowed(expr: &Expr, rendered: &str) -> String {
+    match expr {
+        Expr::Lit(Lit::Str(_)) => rendered
+            .strip_suffix(".to_string()")
+            .unwrap_or(rendered)
+            .to_string(),
+        _ => rendered.to_string(),
+    }
+}
+
+/// `s += part` or `s = s + a + b` on a `string` target → `push_str` calls that extend `s` in
+/// place, or `None` for any other assignment. Like `+` in TypeScript, non-string parts are
+/// appended as their text.
+pub fn lower_append(assign: &AssignExpr, target: &str, scope: &Scope) -> Result<Option<String>> {
+    let parts = match assign.op {
+        AssignOp::AddAssign => vec![&*assign.right],
+        AssignOp::Assign => {
+            let mut parts = Vec::new();
+            let mut rest = &*assign.right;
+            while let Expr::Bin(bin) = rest {
+                if bin.op != BinaryOp::Add {
+                    break;
+                }
+                parts.push(&*bin.right);
+                rest = &bin.left;
+            }
+            let same_target = match (rest, &assign.left) {
+                (Expr::Ident(ident), AssignTarget::Simple(SimpleAssignTarget::Ident(target))) => {
+                    ident.sym == target.id.sym
+                }
+                _ => false,
+            };
+            if parts.is_empty() || !same_target {
+                return Ok(None);
+            }
+            parts.reverse();
+            parts
+        }
+        _ => return Ok(None),
+    };
+    let mut pushes = Vec::new();
+    for part in parts {
+        let rendered = transpile_expression(part, scope)?;
+        let arg = match (part, infer_rust_type(part, scope).as_deref()) {
+            (Expr::Lit(Lit::Str(_)), _) => borrowed(part, &rendered),
+            (_, Some("String")) => format!("&{}", rendered),
+            _ => format!("&({}).to_string()", rendered),
+        };
+        pushes.push(format!("{}.push_str({})", target, arg));
+    }
+    Ok(Some(match pushes.as_slice() {
+        [single] => single.clone(),
+        _ => format!("{{ {}; }}", pushes.join("; ")),
+    }))
+}
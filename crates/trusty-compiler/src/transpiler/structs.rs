@@ -1,9 +1,46 @@
-use super::types::transpile_type_annotation;
+use super::nullish::option_inner;
+use super::types::{check_annotation, transpile_type_annotation};
 use anyhow::Result;
 use swc_ecma_ast::*;
 
-pub fn transpile_interface(decl: &TsInterfaceDecl, json_enabled: bool) -> Result<String> {
+/// `<T, U>` for a generic declaration, each parameter followed by `bound` (e.g. `: Clone`); empty otherwise.
+pub fn type_params(params: Option<&TsTypeParamDecl>, bound: &str) -> String {
+    let names: Vec<String> = params
+        .into_iter()
+        .flat_map(|decl| &decl.params)
+        .map(|param| format!("{}{}", param.name.sym, bound))
+        .collect();
+    if names.is_empty() {
+        String::new()
+    } else {
+        format!("<{}>", names.join(", "))
+    }
+}
+
+/// Rust type of a struct field; an optional one, `nickname?: string`, is an `Option<String>`.
+pub fn field_type(prop: &TsPropertySignature) -> String {
+    let ty = prop
+        .type_ann
+        .as_deref()
+        .map(transpile_type_annotation)
+        .unwrap_or_else(|| "i32".to_string());
+    if prop.optional && option_inner(&ty).is_none() {
+        format!("Option<{}>", ty)
+    } else {
+        ty
+    }
+}
+
+/// `struct Point { ... }` → a Rust struct deriving `derives`, with a `Display` impl for `string(p)`,
+/// templates and `console.write(p)`: the struct's own `toString()` when `own_to_string`, else the
+/// `Debug` form (`Point { x: 1, y: 2 }`).
+pub fn transpile_interface(
+    decl: &TsInterfaceDecl,
+    derives: &[String],
+    own_to_string: bool,
+) -> Result<String> {
     let name = decl.id.sym.to_string();
+    let generics = type_params(decl.type_params.as_deref(), "");
     let mut fields = Vec::new();
 
     for member in &decl.body.body {
@@ -12,28 +49,53 @@ pub fn transpile_interface(decl: &TsInterfaceDecl, json_enabled: bool) -> Result
                 Expr::Ident(ident) => ident.sym.to_string(),
                 _ => continue,
             };
-            let field_type = prop
-                .type_ann
-                .as_deref()
-                .map(|ann| transpile_type_annotation(ann))
-                .unwrap_or_else(|| "i32".to_string());
+            if let Some(ann) = prop.type_ann.as_deref() {
+                check_annotation(ann)?;
+            }
+            let field_type = field_type(prop);
 
             // Recursive field: wrap in Box to avoid infinite-size type
-            let field_type = if field_type == name {
-                format!("Box<{}>", field_type)
-            } else {
-                field_type
+            let recursive = |ty: &str| ty == name || ty == format!("{}{}", name, generics);
+            let field_type = match option_inner(&field_type) {
+                Some(inner) if recursive(inner) => format!("Option<Box<{}>>", inner),
+                _ if recursive(&field_type) => format!("Box<{}>", field_type),
+                _ => field_type,
             };
 
-            fields.push(format!("    {}: {}", field_name, field_type));
+            fields.push(format!("    {}: {},\n", field_name, field_type));
         }
     }
 
-    let derives = if json_enabled {
-        "#[derive(Debug, Clone, serde_derive::Serialize, serde_derive::Deserialize)]"
+    let params = decl.type_params.as_deref();
+    let display = if own_to_string {
+        "f.write_str(&self.toString())"
     } else {
-        "#[derive(Debug, Clone)]"
+        "write!(f, \"{:?}\", self)"
     };
+    Ok(format!(
+        "#[derive({})]\nstruct {}{} {{\n{}}}\n\nimpl{} std::fmt::Display for {}{} {{\n    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {{\n        {}\n    }}\n}}",
+        derives.join(", "),
+        name,
+        generics,
+        fields.concat(),
+        type_params(params, if own_to_string { ": Clone" } else { ": std::fmt::Debug" }),
+        name,
+        generics,
+        display
+    ))
+}
 
-    Ok(format!("{}\nstruct {} {{\n{},\n}}", derives, name, fields.join(",\n")))
+/// Struct inferred for object literals (see [`super::objects`]), printed in its `Debug` form.
+pub fn transpile_inferred(name: &str, fields: &[(String, String)], derives: &[String]) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|(field, ty)| format!("    {}: {}", field, ty))
+        .collect();
+    format!(
+        "#[derive({})]\nstruct {} {{\n{},\n}}\n\nimpl std::fmt::Display for {} {{\n    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {{\n        write!(f, \"{{:?}}\", self)\n    }}\n}}",
+        derives.join(", "),
+        name,
+        fields.join(",\n"),
+        name
+    )
 }
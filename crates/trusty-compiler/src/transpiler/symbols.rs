@@ -0,0 +1,247 @@
+use super::functions::method_needs_mut_self;
+use super::statics::StaticVar;
+use super::tasks::TASK;
+use super::types::{transpile_type, transpile_type_annotation};
+use std::collections::{HashMap, HashSet};
+use swc_ecma_ast::*;
+
+/// Rust-level signature of a TRUST function or method.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FunctionSig {
+    /// `(name, rust_type)` per parameter, `self` excluded.
+    pub params: Vec<(String, String)>,
+    /// Rust return type as emitted (`()` when unannotated, `TrustTask<T>` for `async`).
+    pub return_type: String,
+    /// Method takes `&mut self` (its body assigns to `this` fields).
+    pub mut_self: bool,
+}
+
+/// Program-wide declarations collected before any function body is transpiled,
+/// so expressions can be typed regardless of declaration order.
+#[derive(Clone, Debug, Default)]
+pub struct ProgramSymbols {
+    pub functions: HashMap<String, FunctionSig>,
+    /// Struct name → `(field, rust_type)` in declaration order.
+    pub structs: HashMap<String, Vec<(String, String)>>,
+    /// Struct name → its `readonly` fields, which are only set when the struct is built.
+    pub readonly_fields: HashMap<String, HashSet<String>>,
+    /// Struct name → methods from its `implements` block.
+    pub methods: HashMap<String, HashMap<String, FunctionSig>>,
+    /// Enum name → variant names in declaration order.
+    pub enums: HashMap<String, Vec<String>>,
+    /// `static var` name → its type and storage.
+    pub statics: HashMap<String, StaticVar>,
+    /// Functions emitted as `const fn`, callable from global consts.
+    pub const_fns: HashSet<String>,
+    /// Struct name → item type of the `Iterable<T>` its `implements` block declares.
+    pub iterables: HashMap<String, String>,
+    /// Structs inferred for object literals (also in `structs`), in the order they were found.
+    pub inferred_structs: Vec<String>,
+    /// Start of an object literal → the inferred struct it builds.
+    pub object_structs: HashMap<u32, String>,
+}
+
+impl ProgramSymbols {
+    pub fn collect(module: &Module) -> Self {
+        let mut symbols = Self::default();
+        for item in &module.body {
+            match item {
+                ModuleItem::Stmt(Stmt::Decl(Decl::Fn(func_decl))) => {
+                    symbols.functions.insert(
+                        func_decl.ident.sym.to_string(),
+                        function_sig(&func_decl.function),
+                    );
+                }
+                ModuleItem::Stmt(Stmt::Decl(Decl::TsInterface(interface_decl))) => {
+                    symbols.structs.insert(
+                        interface_decl.id.sym.to_string(),
+                        struct_fields(interface_decl),
+                    );
+                    let readonly = readonly_fields(interface_decl);
+                    if !readonly.is_empty() {
+                        symbols
+                            .readonly_fields
+                            .insert(interface_decl.id.sym.to_string(), readonly);
+                    }
+                }
+                ModuleItem::Stmt(Stmt::Decl(Decl::TsEnum(enum_decl))) => {
+                    let variants = enum_decl
+                        .members
+                        .iter()
+                        .map(|member| match &member.id {
+                            TsEnumMemberId::Ident(ident) => ident.sym.to_string(),
+                            TsEnumMemberId::Str(s) => s.value.to_string_lossy().into_owned(),
+                        })
+                        .collect();
+                    symbols.enums.insert(enum_decl.id.sym.to_string(), variants);
+                }
+                ModuleItem::Stmt(Stmt::Decl(Decl::Class(class_decl))) => {
+                    let methods = symbols
+                        .methods
+                        .entry(class_decl.ident.sym.to_string())
+                        .or_default();
+                    for member in &class_decl.class.body {
+                        if let ClassMember::Method(method) = member {
+                            if let PropName::Ident(ident) = &method.key {
+                                let mut sig = function_sig(&method.function);
+                                sig.mut_self = method_needs_mut_self(&method.function);
+                                methods.insert(ident.sym.to_string(), sig);
+                            }
+                        }
+                    }
+                    if let Some(item) = iterable_item(&class_decl.class) {
+                        symbols
+                            .iterables
+                            .insert(class_decl.ident.sym.to_string(), item);
+                    }
+                }
+                _ => {}
+            }
+        }
+        symbols
+    }
+
+    pub fn function(&self, name: &str) -> Option<&FunctionSig> {
+        self.functions.get(name)
+    }
+
+    pub fn is_struct(&self, name: &str) -> bool {
+        self.structs.contains_key(name)
+    }
+
+    pub fn field_type(&self, struct_name: &str, field: &str) -> Option<&String> {
+        self.structs
+            .get(struct_name)?
+            .iter()
+            .find(|(name, _)| name == field)
+            .map(|(_, ty)| ty)
+    }
+
+    pub fn is_readonly(&self, struct_name: &str, field: &str) -> bool {
+        self.readonly_fields
+            .get(struct_name)
+            .is_some_and(|fields| fields.contains(field))
+    }
+
+    /// Struct inferred for the object literal starting at `span`, if any.
+    pub fn object_struct(&self, span: swc_common::Span) -> Option<&String> {
+        self.object_structs.get(&span.lo.0)
+    }
+
+    pub fn enum_variants(&self, name: &str) -> Option<&[String]> {
+        self.enums.get(name).map(Vec::as_slice)
+    }
+
+    /// Structs and enums named in the Rust types `roots`, and those named in their fields, recursively.
+    pub fn user_types_in<'a>(
+        &self,
+        roots: impl IntoIterator<Item = &'a String>,
+    ) -> HashSet<String> {
+        let mut found = HashSet::new();
+        let mut pending: Vec<String> = roots.into_iter().cloned().collect();
+        while let Some(ty) = pending.pop() {
+            for name in ty.split(|c: char| !(c.is_alphanumeric() || c == '_')) {
+                if !(self.is_struct(name) || self.enums.contains_key(name))
+                    || !found.insert(name.to_string())
+                {
+                    continue;
+                }
+                pending.extend(
+                    self.structs
+                        .get(name)
+                        .into_iter()
+                        .flatten()
+                        .map(|(_, field_ty)| field_ty.clone()),
+                );
+            }
+        }
+        found
+    }
+
+    pub fn method(&self, struct_name: &str, method: &str) -> Option<&FunctionSig> {
+        self.methods.get(struct_name)?.get(method)
+    }
+
+    pub fn iterable_item(&self, struct_name: &str) -> Option<&String> {
+        self.iterables.get(struct_name)
+    }
+}
+
+/// Rust item type `T` of an `implements X: Iterable<T>` block.
+fn iterable_item(class: &Class) -> Option<String> {
+    class.implements.iter().find_map(|implemented| {
+        match (&*implemented.expr, implemented.type_args.as_deref()) {
+            (Expr::Ident(ident), Some(args))
+                if ident.sym.as_ref() == super::iterators::ITERABLE && args.params.len() == 1 =>
+            {
+                Some(transpile_type(&args.params[0]))
+            }
+            _ => None,
+        }
+    })
+}
+
+/// Parameter and return types exactly as `functions::transpile_function` emits them.
+fn function_sig(function: &Function) -> FunctionSig {
+    let params = function
+        .params
+        .iter()
+        .map(|p| match &p.pat {
+            Pat::Ident(ident) => (
+                ident.id.sym.to_string(),
+                ident
+                    .type_ann
+                    .as_deref()
+                    .map(transpile_type_annotation)
+                    .unwrap_or_else(|| "i32".to_string()),
+            ),
+            _ => ("unknown".to_string(), "i32".to_string()),
+        })
+        .collect();
+    let return_type = function
+        .return_type
+        .as_deref()
+        .map(|ann| transpile_type(&ann.type_ann))
+        .unwrap_or_else(|| "()".to_string());
+    let return_type = if function.is_async {
+        format!("{}<{}>", TASK, return_type)
+    } else {
+        return_type
+    };
+    FunctionSig {
+        params,
+        return_type,
+        mut_self: false,
+    }
+}
+
+fn struct_fields(decl: &TsInterfaceDecl) -> Vec<(String, String)> {
+    decl.body
+        .body
+        .iter()
+        .filter_map(|member| match member {
+            TsTypeElement::TsPropertySignature(prop) => match &*prop.key {
+                Expr::Ident(ident) => {
+                    Some((ident.sym.to_string(), super::structs::field_type(prop)))
+                }
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// Fields declared `readonly id: int32`.
+fn readonly_fields(decl: &TsInterfaceDecl) -> HashSet<String> {
+    decl.body
+        .body
+        .iter()
+        .filter_map(|member| match member {
+            TsTypeElement::TsPropertySignature(prop) if prop.readonly => match &*prop.key {
+                Expr::Ident(ident) => Some(ident.sym.to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
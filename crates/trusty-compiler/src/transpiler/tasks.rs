@@ -0,0 +1,127 @@
+//! `async function` calls run on their own thread and return a `TrustTask<T>`: awaited with
+//! `await`/`wait`, `awaitAll(tasks)` or `awaitTimeout(task, ms)`, and cancelled cooperatively with
+//! `task.cancel()`, which the body sees through `isCancelled()`.
+
+use super::expressions::{infer_rust_type, transpile_expression};
+use super::scope::Scope;
+use anyhow::Result;
+use swc_ecma_ast::*;
+
+/// Rust type of the handle an `async function` returns.
+pub const TASK: &str = "TrustTask";
+
+/// The `TrustTask` type and the task builtins, emitted when a program has an `async function`.
+/// The body's result comes back through a channel, so `awaitTimeout` can give up waiting.
+pub const RUNTIME: &str = r#"thread_local! {
+    static __TRUST_CANCEL_TOKEN: std::cell::RefCell<Option<std::sync::Arc<std::sync::atomic::AtomicBool>>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+struct TrustTask<T> {
+    result: std::sync::mpsc::Receiver<std::thread::Result<T>>,
+    cancelled: std::sync::Arc<std::sync::atomic::AtomicBool>,
+}
+
+#[allow(dead_code, non_snake_case)]
+impl<T: Send + 'static> TrustTask<T> {
+    fn spawn(body: impl FnOnce() -> T + Send + 'static) -> TrustTask<T> {
+        let (sender, result) = std::sync::mpsc::channel();
+        let cancelled = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let token = cancelled.clone();
+        std::thread::spawn(move || {
+            __TRUST_CANCEL_TOKEN.with(|slot| *slot.borrow_mut() = Some(token));
+            let _ = sender.send(std::panic::catch_unwind(std::panic::AssertUnwindSafe(body)));
+        });
+        TrustTask { result, cancelled }
+    }
+
+    /// Waits for the body; `Err` when it panicked.
+    fn join(self) -> std::thread::Result<T> {
+        self.result.recv().unwrap_or_else(|_| Err(Box::new("the task's result was already taken")))
+    }
+
+    /// Asks the body to stop: `isCancelled()` turns true inside it.
+    fn cancel(&self) {
+        self.cancelled.store(true, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    fn isCancelled(&self) -> bool {
+        self.cancelled.load(std::sync::atomic::Ordering::SeqCst)
+    }
+}
+
+/// Whether the task running this code was cancelled; always false outside an `async function`.
+#[allow(dead_code, non_snake_case)]
+fn isCancelled() -> bool {
+    __TRUST_CANCEL_TOKEN.with(|slot| {
+        slot.borrow()
+            .as_ref()
+            .is_some_and(|token| token.load(std::sync::atomic::Ordering::SeqCst))
+    })
+}
+
+/// The task's result if it finishes within `ms` milliseconds; the task keeps running otherwise.
+#[allow(dead_code, non_snake_case)]
+fn awaitTimeout<T>(task: &TrustTask<T>, ms: i32) -> Option<T> {
+    match task.result.recv_timeout(std::time::Duration::from_millis(ms.max(0) as u64)) {
+        Ok(Ok(value)) => Some(value),
+        Ok(Err(panic)) => std::panic::resume_unwind(panic),
+        Err(_) => None,
+    }
+}"#;
+
+/// `TrustTask<T>` → `T`.
+pub fn task_result_type(ty: &str) -> Option<&str> {
+    ty.strip_prefix("TrustTask<")
+        .and_then(|t| t.strip_suffix('>'))
+}
+
+/// Rust type of an `awaitAll`, `awaitTimeout` or `isCancelled` call, unless the program defines
+/// a function of that name.
+pub fn call_type(func_name: &str, args: &[ExprOrSpread], scope: &Scope) -> Option<String> {
+    if scope.symbols().function(func_name).is_some() {
+        return None;
+    }
+    match (func_name, args) {
+        ("isCancelled", []) => Some("bool".to_string()),
+        ("awaitAll", [tasks]) => {
+            let tasks = infer_rust_type(&tasks.expr, scope)?;
+            let task = tasks
+                .strip_prefix("Vec<")
+                .and_then(|t| t.strip_suffix('>'))?;
+            task_result_type(task).map(|result| format!("Vec<{}>", result))
+        }
+        ("awaitTimeout", [task, _]) => {
+            let task = infer_rust_type(&task.expr, scope)?;
+            task_result_type(&task).map(|result| format!("Option<{}>", result))
+        }
+        _ => None,
+    }
+}
+
+/// `awaitAll(tasks)` → joins every task of the list in order and collects their results;
+/// `awaitTimeout(task, ms)` borrows the task, so it can still be cancelled or awaited after.
+pub fn transpile_call(
+    func_name: &str,
+    args: &[ExprOrSpread],
+    scope: &Scope,
+) -> Result<Option<String>> {
+    if scope.symbols().function(func_name).is_some() {
+        return Ok(None);
+    }
+    match (func_name, args) {
+        ("awaitAll", [tasks]) => {
+            let tasks = transpile_expression(&tasks.expr, scope)?;
+            Ok(Some(format!(
+                "{}.into_iter().map(|__trust_task| __trust_task.join().unwrap()).collect::<Vec<_>>()",
+                tasks
+            )))
+        }
+        ("awaitTimeout", [task, ms]) => {
+            let task = transpile_expression(&task.expr, scope)?;
+            let ms = transpile_expression(&ms.expr, scope)?;
+            Ok(Some(format!("awaitTimeout(&{}, ({}) as i32)", task, ms)))
+        }
+        _ => Ok(None),
+    }
+}
@@ -0,0 +1,313 @@
+//! Tree-shaking of the `trusty:*` stdlib text: only the items a program reaches are emitted, so
+//! rustc does not warn about the helpers it never calls.
+
+use std::collections::HashSet;
+
+/// Token of the Rust lexer below; literals and comments produce none.
+#[derive(Clone, Copy, PartialEq)]
+enum Token<'a> {
+    /// An identifier, with a trailing `!` for a macro name (`format!`, `macro_rules!`).
+    Ident(&'a str, bool),
+    Punct(char),
+}
+
+/// Byte offset and token of every identifier and punctuation character in `text`.
+fn tokenize(text: &str) -> Vec<(usize, Token<'_>)> {
+    let bytes = text.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        let rest = &text[i..];
+        if c.is_ascii_whitespace() {
+            i += 1;
+        } else if rest.starts_with("//") {
+            i += rest.find('\n').unwrap_or(rest.len());
+        } else if rest.starts_with("/*") {
+            i += rest.find("*/").map_or(rest.len(), |end| end + 2);
+        } else if let Some(len) = string_literal(rest) {
+            i += len;
+        } else if c == b'\'' {
+            // `'x'` and `'\n'` are chars; `'a` in `&'a str` is a lifetime.
+            let mut chars = rest[1..].chars();
+            match (chars.next(), chars.next()) {
+                (Some('\\'), _) => {
+                    i += 1 + rest[1..].find('\'').map_or(rest.len() - 1, |end| end + 1)
+                }
+                (Some(ch), Some('\'')) => i += 2 + ch.len_utf8(),
+                _ => {
+                    i += 1 + rest[1..]
+                        .find(|ch: char| !is_ident_char(ch))
+                        .unwrap_or(rest.len() - 1)
+                }
+            }
+        } else if c.is_ascii_digit() {
+            i += 1;
+            while i < bytes.len()
+                && (is_ident_char(bytes[i] as char)
+                    || bytes[i] == b'.' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit))
+            {
+                i += 1;
+            }
+        } else if is_ident_char(c as char) {
+            let len = rest
+                .find(|ch: char| !is_ident_char(ch))
+                .unwrap_or(rest.len());
+            let macro_name = rest[len..].starts_with('!') && !rest[len..].starts_with("!=");
+            tokens.push((i, Token::Ident(&rest[..len], macro_name)));
+            i += len;
+        } else {
+            let ch = rest.chars().next().unwrap_or(' ');
+            tokens.push((i, Token::Punct(ch)));
+            i += ch.len_utf8();
+        }
+    }
+    tokens
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Length of the string literal (`"..."`, `b"..."`, `r#"..."#`) starting `text`, if any.
+fn string_literal(text: &str) -> Option<usize> {
+    let after_b = text.strip_prefix('b').unwrap_or(text);
+    let prefix = text.len() - after_b.len();
+    if let Some(raw) = after_b.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let body = raw[hashes..].strip_prefix('"')?;
+        let close = format!("\"{}", "#".repeat(hashes));
+        let end = body
+            .find(&close)
+            .map_or(body.len(), |end| end + close.len());
+        return Some(prefix + 1 + hashes + 1 + end);
+    }
+    let body = after_b.strip_prefix('"')?;
+    let mut escaped = false;
+    for (offset, ch) in body.char_indices() {
+        match ch {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return Some(prefix + 1 + offset + 1),
+            _ => escaped = false,
+        }
+    }
+    Some(text.len())
+}
+
+/// What a top-level item of the stdlib text is, for deciding whether it is reachable.
+enum ItemKind<'a> {
+    /// `fn`, `struct`, `enum`, `const`, `static`, `type`, `mod` or `macro_rules!` with its name.
+    Named(String),
+    /// A `trait`, also needed when one of its methods (`.name` references) is called.
+    Trait(String, Vec<String>),
+    /// An `impl`, needed when every stdlib type or trait in its header is.
+    Impl(Vec<&'a str>),
+    /// A `use`, always kept.
+    Use,
+    Other,
+}
+
+/// One top-level item: its text (with the comments and attributes before it) and tokens.
+struct Item<'a> {
+    text: &'a str,
+    tokens: Vec<Token<'a>>,
+    kind: ItemKind<'a>,
+}
+
+/// Splits `text` into top-level items: each ends with a `;` or a `}` outside any brackets.
+fn items(text: &str) -> Vec<Item<'_>> {
+    let tokens = tokenize(text);
+    let mut items = Vec::new();
+    let (mut start, mut first, mut depth) = (0, 0, 0i32);
+    for (k, &(offset, token)) in tokens.iter().enumerate() {
+        let end = match token {
+            Token::Punct('(' | '[' | '{') => {
+                depth += 1;
+                continue;
+            }
+            Token::Punct(')' | ']') => {
+                depth -= 1;
+                continue;
+            }
+            Token::Punct('}') => {
+                depth -= 1;
+                // `const ORIGIN: Point = Point { x: 0 };` ends at the `;`.
+                if depth != 0 || matches!(tokens.get(k + 1), Some((_, Token::Punct(';')))) {
+                    continue;
+                }
+                offset + 1
+            }
+            Token::Punct(';') if depth == 0 => offset + 1,
+            _ => continue,
+        };
+        let item_tokens: Vec<Token> = tokens[first..=k].iter().map(|&(_, token)| token).collect();
+        items.push(Item {
+            text: &text[start..end],
+            kind: item_kind(&item_tokens),
+            tokens: item_tokens,
+        });
+        start = end;
+        first = k + 1;
+    }
+    if !text[start..].trim().is_empty() {
+        items.push(Item {
+            text: &text[start..],
+            tokens: Vec::new(),
+            kind: ItemKind::Other,
+        });
+    }
+    items
+}
+
+fn item_kind<'a>(tokens: &[Token<'a>]) -> ItemKind<'a> {
+    let mut k = 0;
+    while k < tokens.len() {
+        match tokens[k] {
+            // `#[allow(non_snake_case)]`
+            Token::Punct('#') => {
+                let mut depth = 0;
+                k += 1;
+                while k < tokens.len() {
+                    match tokens[k] {
+                        Token::Punct('[') => depth += 1,
+                        Token::Punct(']') => depth -= 1,
+                        _ => {}
+                    }
+                    k += 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                continue;
+            }
+            Token::Ident("use", _) => return ItemKind::Use,
+            Token::Ident("impl", _) => {
+                let header = tokens[k + 1..]
+                    .iter()
+                    .take_while(|token| **token != Token::Punct('{'));
+                return ItemKind::Impl(
+                    header
+                        .filter_map(|token| match token {
+                            Token::Ident(name, false) => Some(*name),
+                            _ => None,
+                        })
+                        .collect(),
+                );
+            }
+            Token::Ident("macro_rules", true) => {
+                return match tokens.get(k + 2) {
+                    Some(Token::Ident(name, _)) => ItemKind::Named(format!("{}!", name)),
+                    _ => ItemKind::Other,
+                };
+            }
+            Token::Ident("const", _) if tokens.get(k + 1) == Some(&Token::Ident("fn", false)) => {
+                k += 1
+            }
+            Token::Ident("trait", _) => {
+                let Some(Token::Ident(name, _)) = tokens.get(k + 1) else {
+                    return ItemKind::Other;
+                };
+                let methods = tokens.windows(2).filter_map(|pair| match pair {
+                    [Token::Ident("fn", _), Token::Ident(method, _)] => {
+                        Some(format!(".{}", method))
+                    }
+                    _ => None,
+                });
+                return ItemKind::Trait(name.to_string(), methods.collect());
+            }
+            Token::Ident(
+                "fn" | "struct" | "enum" | "const" | "static" | "type" | "mod" | "union",
+                _,
+            ) => {
+                return match tokens.get(k + 1) {
+                    Some(Token::Ident(name, _)) => ItemKind::Named(name.to_string()),
+                    _ => ItemKind::Other,
+                };
+            }
+            Token::Ident("pub" | "unsafe" | "async" | "extern", _) => k += 1,
+            // `pub(crate)`
+            Token::Punct('(' | ')') | Token::Ident("crate" | "super", _) => k += 1,
+            _ => return ItemKind::Other,
+        }
+    }
+    ItemKind::Other
+}
+
+/// The names `tokens` refer to. Macros carry their `!` and methods or fields a leading `.`, so
+/// neither `format!` nor `d.format(...)` is the `format` function; the names `fn` defines are none.
+/// After `..` (`Options { ..defaults() }`, `0..len`) a name is not a method.
+fn references<'a>(tokens: &'a [Token<'a>]) -> impl Iterator<Item = String> + 'a {
+    tokens.iter().enumerate().filter_map(|(k, token)| {
+        let previous = k.checked_sub(1).map(|k| tokens[k]);
+        let range = k >= 2 && tokens[k - 2] == Token::Punct('.');
+        match (previous, token) {
+            (Some(Token::Ident("fn", _)), _) => None,
+            (_, Token::Ident(name, true)) => Some(format!("{}!", name)),
+            (Some(Token::Punct('.')), Token::Ident(name, false)) if !range => {
+                Some(format!(".{}", name))
+            }
+            (_, Token::Ident(name, false)) => Some(name.to_string()),
+            (_, Token::Punct(_)) => None,
+        }
+    })
+}
+
+/// The items of the stdlib `blocks` that `program` uses, directly or through other kept items,
+/// in their original order.
+pub fn shake(blocks: &[String], program: &str) -> String {
+    let text = blocks.join("\n");
+    let items = items(&text);
+    let defined: HashSet<&str> = items
+        .iter()
+        .filter_map(|item| match &item.kind {
+            ItemKind::Named(name) | ItemKind::Trait(name, _) => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    let program_tokens: Vec<Token> = tokenize(program)
+        .into_iter()
+        .map(|(_, token)| token)
+        .collect();
+    let mut used: HashSet<String> = references(&program_tokens).collect();
+    let mut kept = vec![false; items.len()];
+    loop {
+        let mut changed = false;
+        for (k, item) in items.iter().enumerate() {
+            let reached = match &item.kind {
+                ItemKind::Named(name) => used.contains(name),
+                ItemKind::Trait(name, methods) => {
+                    used.contains(name) || methods.iter().any(|method| used.contains(method))
+                }
+                ItemKind::Impl(header) => header
+                    .iter()
+                    .filter(|name| defined.contains(*name))
+                    .all(|name| used.contains(*name)),
+                ItemKind::Use => false,
+                ItemKind::Other => true,
+            };
+            if reached && !kept[k] {
+                kept[k] = true;
+                changed = true;
+                used.extend(references(&item.tokens));
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let mut out = String::new();
+    for (k, item) in items.iter().enumerate() {
+        if let ItemKind::Use = item.kind {
+            // Traits imported for their methods are never named, so imports all stay.
+            let declaration = item.text.trim_start();
+            out.push_str(&item.text[..item.text.len() - declaration.len()]);
+            out.push_str("#[allow(unused_imports)]\n");
+            out.push_str(declaration);
+        } else if kept[k] {
+            out.push_str(item.text);
+        }
+    }
+    out.trim().to_string()
+}
@@ -1,3 +1,7 @@
+use crate::diagnostics::Diagnostic;
+use crate::messages::msg;
+use crate::parser::source_span;
+use anyhow::Result;
 use swc_ecma_ast::*;
 
 pub fn transpile_type(ts_type: &TsType) -> String {
@@ -35,25 +39,61 @@ pub fn transpile_type(ts_type: &TsType) -> String {
                     "float32" => "f32".to_string(),
                     "float64" => "f64".to_string(),
                     "float" => "f64".to_string(),
+                    // Binary data
+                    "bytes" => super::bytes::BYTES.to_string(),
+                    // Exact base-10 numbers
+                    "decimal" => super::decimal::DECIMAL.to_string(),
                     // Backward-compat alias
                     "number" => "i32".to_string(),
                     // Pointer<T> → Rc<RefCell<T>>  (shared mutable reference, single-thread)
                     "Pointer" => {
-                        let inner = type_args.first().cloned().unwrap_or_else(|| "()".to_string());
+                        let inner = type_args
+                            .first()
+                            .cloned()
+                            .unwrap_or_else(|| "()".to_string());
                         format!("Rc<RefCell<{}>>", inner)
                     }
+                    // WeakPointer<T> → Weak<RefCell<T>>  (non-owning Pointer<T>, breaks reference cycles)
+                    "WeakPointer" => {
+                        let inner = type_args
+                            .first()
+                            .cloned()
+                            .unwrap_or_else(|| "()".to_string());
+                        format!("Weak<RefCell<{}>>", inner)
+                    }
                     // Threaded<T> → Arc<Mutex<T>>  (shared mutable reference, multi-thread)
                     "Threaded" => {
-                        let inner = type_args.first().cloned().unwrap_or_else(|| "()".to_string());
+                        let inner = type_args
+                            .first()
+                            .cloned()
+                            .unwrap_or_else(|| "()".to_string());
                         format!("Arc<Mutex<{}>>", inner)
                     }
+                    // Ref<T> / MutRef<T> (from `ref T` / `mut ref T`) → &T / &mut T
+                    "Ref" => {
+                        let inner = type_args
+                            .first()
+                            .cloned()
+                            .unwrap_or_else(|| "()".to_string());
+                        format!("&{}", inner)
+                    }
+                    "MutRef" => {
+                        let inner = type_args
+                            .first()
+                            .cloned()
+                            .unwrap_or_else(|| "()".to_string());
+                        format!("&mut {}", inner)
+                    }
                     // Map<K, V> → HashMap<K, V>
                     "Map" => {
                         format!("HashMap<{}>", type_args.join(", "))
                     }
                     // Set<T> → HashSet<T>
                     "Set" => {
-                        let inner = type_args.first().cloned().unwrap_or_else(|| "()".to_string());
+                        let inner = type_args
+                            .first()
+                            .cloned()
+                            .unwrap_or_else(|| "()".to_string());
                         format!("HashSet<{}>", inner)
                     }
                     // Pass-through generics: Box<T>, Vec<T>, Rc<T>, Arc<T>, …
@@ -77,7 +117,86 @@ pub fn transpile_type_annotation(type_ann: &TsTypeAnn) -> String {
     transpile_type(&type_ann.type_ann)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Rejects `T | null` (or `T | undefined`) anywhere in `type_ann`: an optional value is an
+/// `Option<T>` in TRUST, and the union would otherwise become a `()` that `??` and `?.` then
+/// complain about.
+pub fn check_annotation(type_ann: &TsTypeAnn) -> Result<()> {
+    check_type(&type_ann.type_ann)
+}
+
+fn check_type(ts_type: &TsType) -> Result<()> {
+    match ts_type {
+        TsType::TsUnionOrIntersectionType(TsUnionOrIntersectionType::TsUnionType(union)) => {
+            let (nullish, rest): (Vec<&TsType>, Vec<&TsType>) = union
+                .types
+                .iter()
+                .map(|t| &**t)
+                .partition(|t| is_nullish(t));
+            if let ([_, ..], [inner]) = (nullish.as_slice(), rest.as_slice()) {
+                return Err(Diagnostic::error(
+                    "nullable-union",
+                    msg!("nullable-union", type_source(ts_type), type_source(inner)),
+                )
+                .with_span(source_span(union.span))
+                .into());
+            }
+            union.types.iter().try_for_each(|t| check_type(t))
+        }
+        TsType::TsArrayType(arr) => check_type(&arr.elem_type),
+        TsType::TsParenthesizedType(paren) => check_type(&paren.type_ann),
+        TsType::TsTypeRef(type_ref) => type_ref
+            .type_params
+            .iter()
+            .flat_map(|params| &params.params)
+            .try_for_each(|t| check_type(t)),
+        _ => Ok(()),
+    }
+}
+
+fn is_nullish(ts_type: &TsType) -> bool {
+    matches!(
+        ts_type,
+        TsType::TsKeywordType(TsKeywordType {
+            kind: TsKeywordTypeKind::TsNullKeyword | TsKeywordTypeKind::TsUndefinedKeyword,
+            ..
+        })
+    )
+}
+
+/// `ts_type` as written in TRUST, for messages.
+fn type_source(ts_type: &TsType) -> String {
+    match ts_type {
+        TsType::TsKeywordType(keyword) => match keyword.kind {
+            TsKeywordTypeKind::TsNumberKeyword => "number",
+            TsKeywordTypeKind::TsStringKeyword => "string",
+            TsKeywordTypeKind::TsBooleanKeyword => "boolean",
+            TsKeywordTypeKind::TsNullKeyword => "null",
+            TsKeywordTypeKind::TsUndefinedKeyword => "undefined",
+            TsKeywordTypeKind::TsVoidKeyword => "void",
+            _ => "_",
+        }
+        .to_string(),
+        TsType::TsTypeRef(type_ref) => {
+            let name = match &type_ref.type_name {
+                TsEntityName::Ident(ident) => ident.sym.to_string(),
+                TsEntityName::TsQualifiedName(_) => "_".to_string(),
+            };
+            match &type_ref.type_params {
+                Some(params) => {
+                    let args: Vec<String> = params.params.iter().map(|t| type_source(t)).collect();
+                    format!("{}<{}>", name, args.join(", "))
+                }
+                None => name,
+            }
+        }
+        TsType::TsArrayType(arr) => format!("{}[]", type_source(&arr.elem_type)),
+        TsType::TsParenthesizedType(paren) => format!("({})", type_source(&paren.type_ann)),
+        TsType::TsUnionOrIntersectionType(TsUnionOrIntersectionType::TsUnionType(union)) => union
+            .types
+            .iter()
+            .map(|t| type_source(t))
+            .collect::<Vec<_>>()
+            .join(" | "),
+        _ => "_".to_string(),
+    }
 }
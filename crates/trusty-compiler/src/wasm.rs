@@ -0,0 +1,19 @@
+//! wasm-bindgen entry points for the web playground (`--features wasm`).
+//!
+//! Build with `wasm-pack build crates/trusty-compiler --no-default-features --features wasm`.
+
+use wasm_bindgen::prelude::*;
+
+/// Transpiles TRUST source to Rust. Errors are returned as a JS string with the full cause chain.
+#[wasm_bindgen]
+pub fn compile_to_rust(source: &str) -> Result<String, JsValue> {
+    crate::compile(source).map_err(|err| JsValue::from_str(&format!("{:#}", err)))
+}
+
+/// External crates the generated Rust needs (e.g. `rand`), for display next to the output.
+#[wasm_bindgen]
+pub fn required_crates(source: &str) -> Result<Vec<String>, JsValue> {
+    crate::compile_full(source)
+        .map(|output| output.required_crates)
+        .map_err(|err| JsValue::from_str(&format!("{:#}", err)))
+}
@@ -0,0 +1,184 @@
+//! Compile-fail tests: each `tests/compile-fail/<name>.trs` is an invalid program annotated with
+//! the diagnostics it must produce, so error codes and spans stop regressing.
+//!
+//! An annotation is a comment on the line the diagnostic's span starts on:
+//!
+//! ```text
+//! while (x) {  //~ ERROR unsupported-while `while`
+//! ```
+//!
+//! `//~^` points at the line above (one `^` per line), `//~?` at a diagnostic without a span. The
+//! severity is `ERROR` or `WARNING`, then the code, then optionally the exact spanned text in
+//! backticks. Every diagnostic must be annotated and every annotation matched.
+//! `cargo test -p trusty-compiler --test compile_fail -- <filter>` only runs matching fixtures.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use trusty_compiler::{Compiler, Diagnostic};
+
+#[derive(Debug, PartialEq)]
+struct Expected {
+    /// 1-based line, `None` for a diagnostic without a span.
+    line: Option<usize>,
+    severity: String,
+    code: String,
+    text: Option<String>,
+}
+
+fn parse_annotations(source: &str) -> Result<Vec<Expected>, String> {
+    let mut expected = Vec::new();
+    for (index, line) in source.lines().enumerate() {
+        let Some((_, annotation)) = line.split_once("//~") else {
+            continue;
+        };
+        let (line, rest) = if let Some(rest) = annotation.strip_prefix('?') {
+            (None, rest)
+        } else {
+            let up = annotation.chars().take_while(|&c| c == '^').count();
+            let target = (index + 1)
+                .checked_sub(up)
+                .filter(|&line| line > 0)
+                .ok_or_else(|| {
+                    format!(
+                        "line {}: `//~{}` points before the file",
+                        index + 1,
+                        "^".repeat(up)
+                    )
+                })?;
+            (Some(target), &annotation[up..])
+        };
+        let mut words = rest.trim().splitn(3, ' ');
+        let severity = words.next().unwrap_or_default().to_string();
+        if severity != "ERROR" && severity != "WARNING" {
+            return Err(format!(
+                "line {}: expected `ERROR` or `WARNING` after `//~`",
+                index + 1
+            ));
+        }
+        let code = words.next().unwrap_or_default().to_string();
+        let text = match words.next().map(str::trim) {
+            None | Some("") => None,
+            Some(text) => Some(
+                text.strip_prefix('`')
+                    .and_then(|text| text.strip_suffix('`'))
+                    .ok_or_else(|| {
+                        format!("line {}: spanned text must be in backticks", index + 1)
+                    })?
+                    .to_string(),
+            ),
+        };
+        expected.push(Expected {
+            line,
+            severity,
+            code,
+            text,
+        });
+    }
+    Ok(expected)
+}
+
+fn actual(source: &str, diagnostic: &Diagnostic) -> Expected {
+    Expected {
+        line: diagnostic.span.map(|span| span.line_col(source).0),
+        severity: diagnostic.severity.as_str().to_uppercase(),
+        code: diagnostic.code.to_string(),
+        text: diagnostic
+            .span
+            .map(|span| source[span.start..span.end].to_string()),
+    }
+}
+
+fn describe(expected: &Expected) -> String {
+    let line = expected
+        .line
+        .map_or("no span".to_string(), |line| format!("line {}", line));
+    let text = expected
+        .text
+        .as_ref()
+        .map(|text| format!(" `{}`", text))
+        .unwrap_or_default();
+    format!("{}: {} {}{}", line, expected.severity, expected.code, text)
+}
+
+fn test_fixture(path: &Path) -> Result<(), String> {
+    let source = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let mut unmatched = parse_annotations(&source)?;
+    if unmatched
+        .iter()
+        .all(|expected| expected.severity != "ERROR")
+    {
+        return Err("a compile-fail fixture needs at least one `//~ ERROR` annotation".to_string());
+    }
+
+    let output = Compiler::new().compile(&source);
+    let mut unexpected = Vec::new();
+    for diagnostic in &output.diagnostics {
+        let got = actual(&source, diagnostic);
+        let position = unmatched.iter().position(|expected| {
+            expected.line == got.line
+                && expected.severity == got.severity
+                && expected.code == got.code
+                && expected
+                    .text
+                    .as_ref()
+                    .is_none_or(|text| Some(text) == got.text.as_ref())
+        });
+        match position {
+            Some(index) => {
+                unmatched.remove(index);
+            }
+            None => unexpected.push(format!(
+                "  unexpected {}\n    {}",
+                describe(&got),
+                diagnostic.message
+            )),
+        }
+    }
+
+    let missing: Vec<String> = unmatched
+        .iter()
+        .map(|expected| format!("  missing {}", describe(expected)))
+        .collect();
+    if unexpected.is_empty() && missing.is_empty() {
+        return Ok(());
+    }
+    Err(unexpected
+        .into_iter()
+        .chain(missing)
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+fn main() {
+    let filter: Option<String> = std::env::args().skip(1).find(|arg| !arg.starts_with("--"));
+    let dir: PathBuf = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("compile-fail");
+    let mut fixtures: Vec<PathBuf> = fs::read_dir(&dir)
+        .expect("tests/compile-fail exists")
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "trs"))
+        .filter(|path| {
+            filter
+                .as_ref()
+                .is_none_or(|filter| path.to_string_lossy().contains(filter.as_str()))
+        })
+        .collect();
+    fixtures.sort();
+
+    let mut failures = 0;
+    for fixture in &fixtures {
+        let name = fixture.file_stem().unwrap_or_default().to_string_lossy();
+        match test_fixture(fixture) {
+            Ok(()) => println!("compile-fail {} ... ok", name),
+            Err(message) => {
+                failures += 1;
+                println!("compile-fail {} ... FAILED\n{}", name, message);
+            }
+        }
+    }
+    println!("\n{} fixture(s), {} failed", fixtures.len(), failures);
+    if failures > 0 {
+        std::process::exit(1);
+    }
+}
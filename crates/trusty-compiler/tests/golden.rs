@@ -0,0 +1,143 @@
+//! Golden-file tests: each `tests/golden/<name>.trs` is compiled and the generated Rust compared
+//! with `<name>.rs`. Programs without external crates are also built with `rustc` and run, and
+//! their stdout compared with `<name>.stdout` (skipped when `rustc` is not on the path). A
+//! `<name>.json` next to the fixture is read like its `trusty.json`, for its compiler options.
+//!
+//! `cargo test -p trusty-compiler --test golden -- --bless` rewrites the snapshots instead, so a
+//! codegen change shows up as a reviewable diff. Any other argument only runs fixtures whose name
+//! contains it.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use trusty_compiler::Compiler;
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+}
+
+/// Stdout of the program in `rust_code`, or `None` when `rustc` is unavailable.
+fn run(name: &str, rust_code: &str) -> Result<Option<String>, String> {
+    if Command::new("rustc").arg("--version").output().is_err() {
+        return Ok(None);
+    }
+    let dir = std::env::temp_dir().join(format!("trusty-golden-{}-{}", std::process::id(), name));
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let source = dir.join("main.rs");
+    let binary = dir.join("main");
+    fs::write(&source, rust_code).map_err(|e| e.to_string())?;
+
+    let built = Command::new("rustc")
+        .arg(&source)
+        .arg("-o")
+        .arg(&binary)
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !built.status.success() {
+        return Err(format!(
+            "rustc failed:\n{}",
+            String::from_utf8_lossy(&built.stderr)
+        ));
+    }
+    let ran = Command::new(&binary).output().map_err(|e| e.to_string())?;
+    fs::remove_dir_all(&dir).ok();
+    if !ran.status.success() {
+        return Err(format!(
+            "exited with {}:\n{}",
+            ran.status,
+            String::from_utf8_lossy(&ran.stderr)
+        ));
+    }
+    Ok(Some(String::from_utf8_lossy(&ran.stdout).into_owned()))
+}
+
+/// Compares `actual` with the snapshot at `path`, or writes it when blessing.
+fn check(path: &Path, actual: &str, bless: bool) -> Result<(), String> {
+    if bless {
+        return fs::write(path, actual).map_err(|e| e.to_string());
+    }
+    let expected =
+        fs::read_to_string(path).map_err(|_| format!("missing snapshot {}", path.display()))?;
+    if expected == actual {
+        return Ok(());
+    }
+    let line = expected
+        .lines()
+        .zip(actual.lines())
+        .position(|(e, a)| e != a)
+        .unwrap_or_else(|| expected.lines().count().min(actual.lines().count()));
+    Err(format!(
+        "{} differs from line {}:\n  expected: {:?}\n  actual:   {:?}",
+        path.display(),
+        line + 1,
+        expected.lines().nth(line).unwrap_or("<end of file>"),
+        actual.lines().nth(line).unwrap_or("<end of file>"),
+    ))
+}
+
+fn test_fixture(source_path: &Path, bless: bool) -> Result<(), String> {
+    let source = fs::read_to_string(source_path).map_err(|e| e.to_string())?;
+    let options = match fs::read_to_string(source_path.with_extension("json")) {
+        Ok(manifest) => {
+            let json = serde_json::from_str(&manifest).map_err(|e| e.to_string())?;
+            trusty_compiler::manifest::compile_options(&json)
+        }
+        Err(_) => Default::default(),
+    };
+    let output = Compiler::new().options(options).compile(&source);
+    if output.has_errors() {
+        return Err(format!("does not compile: {:?}", output.diagnostics));
+    }
+    let rust_code = output.rust_code.unwrap_or_default();
+    check(&source_path.with_extension("rs"), &rust_code, bless)?;
+
+    if output.required_crates.is_empty() {
+        let name = source_path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy();
+        if let Some(stdout) = run(&name, &rust_code)? {
+            check(&source_path.with_extension("stdout"), &stdout, bless)?;
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let bless = args.iter().any(|arg| arg == "--bless");
+    let filter = args.iter().find(|arg| !arg.starts_with("--"));
+
+    let mut fixtures: Vec<PathBuf> = fs::read_dir(fixtures_dir())
+        .expect("tests/golden exists")
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "trs"))
+        .filter(|path| filter.is_none_or(|filter| path.to_string_lossy().contains(filter.as_str())))
+        .collect();
+    fixtures.sort();
+
+    let mut failures = 0;
+    for fixture in &fixtures {
+        let name = fixture.file_stem().unwrap_or_default().to_string_lossy();
+        match test_fixture(fixture, bless) {
+            Ok(()) => println!(
+                "golden {} ... {}",
+                name,
+                if bless { "blessed" } else { "ok" }
+            ),
+            Err(message) => {
+                failures += 1;
+                println!("golden {} ... FAILED\n{}", name, message);
+            }
+        }
+    }
+    println!("\n{} fixture(s), {} failed", fixtures.len(), failures);
+    if failures > 0 {
+        println!(
+            "run `cargo test -p trusty-compiler --test golden -- --bless` to accept the new output"
+        );
+        std::process::exit(1);
+    }
+}
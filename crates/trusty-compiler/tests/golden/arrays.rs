@@ -0,0 +1,36 @@
+#[derive(Debug, Clone)]
+struct User {
+    name: String,
+    age: i32,
+}
+
+impl std::fmt::Display for User {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+fn main() -> () {
+    let users: Vec<User> = vec![User { name: "ada".to_string(), age: 36 }, User { name: "bob".to_string(), age: 40 }];
+    let names = users.iter().cloned().map(move |u: User| u.name).collect::<Vec<_>>();
+    let shouted = users.iter().cloned().map(move |u: User| u.name.to_uppercase()).collect::<Vec<_>>();
+    let older = users.iter().cloned().filter(|__trust_item| (move |u: User| u.age > 37)(__trust_item.clone())).collect::<Vec<_>>();
+    println!("{:?} {:?} {}", names, shouted, older.len());
+    let bob = { let mut __trust_iter = users.iter().cloned(); __trust_iter.find(|__trust_item| (move |u: User| u.name == "bob".to_string())(__trust_item.clone())) };
+    println!("{:?} {} {}", bob, { let mut __trust_iter = users.iter().cloned(); __trust_iter.any(move |u: User| u.age > 39) }, { let mut __trust_iter = users.iter().cloned(); __trust_iter.all(move |u: User| u.name.chars().count() as i32 == 3) });
+    users.iter().cloned().for_each(move |u: User| println!("{}", format!("{} is {}", u.name, u.age)));
+    let mut team: Vec<User> = vec![User { name: "cy".to_string(), age: 29 }, User { name: "ada".to_string(), age: 36 }, User { name: "bob".to_string(), age: 40 }];
+    { let __trust_compare = move |a: &User, b: &User| b.age - a.age; team.sort_by(|__trust_a, __trust_b| __trust_compare(__trust_a, __trust_b).cmp(&0)) };
+    println!("{:?}", team.iter().cloned().map(move |u: User| u.name).collect::<Vec<_>>());
+    { let __trust_key = move |u: User| u.name; team.sort_by(|__trust_a, __trust_b| __trust_key(__trust_a.clone()).partial_cmp(&__trust_key(__trust_b.clone())).unwrap_or(std::cmp::Ordering::Equal)) };
+    println!("{:?}", team.iter().cloned().map(move |u: User| u.name).collect::<Vec<_>>());
+    let mut scores = vec![2.5, -1.5, 0.5];
+    scores.sort_by(|__trust_a, __trust_b| __trust_a.partial_cmp(__trust_b).unwrap_or(std::cmp::Ordering::Equal));
+    let mut ranks = vec![3, 1, 2];
+    { let __trust_compare = move |a: i32, b: i32| -(a - b); ranks.sort_by(|__trust_a, __trust_b| __trust_compare(*__trust_a, *__trust_b).cmp(&0)) };
+    println!("{:?} {:?}", scores, ranks);
+    let xs = vec![1, 2, 3, 2];
+    println!("{} {} {} {:?} {:?} {:?} {:?}", xs.contains(&2), xs.iter().position(|__trust_item| *__trust_item == 2).map(|i| i as i32).unwrap_or(-1), xs.iter().rposition(|__trust_item| *__trust_item == 2).map(|i| i as i32).unwrap_or(-1), { let __trust_items = &xs; let __trust_len = __trust_items.len() as isize; __trust_items[{ let __trust_i = (1) as isize; (if __trust_i < 0 { (__trust_len + __trust_i).max(0) } else { __trust_i.min(__trust_len) }) as usize }..].to_vec() }, { let __trust_items = &xs; let __trust_len = __trust_items.len() as isize; let __trust_from = { let __trust_i = (-3) as isize; (if __trust_i < 0 { (__trust_len + __trust_i).max(0) } else { __trust_i.min(__trust_len) }) as usize }; let __trust_to = { let __trust_i = (-1) as isize; (if __trust_i < 0 { (__trust_len + __trust_i).max(0) } else { __trust_i.min(__trust_len) }) as usize }; __trust_items[__trust_from..__trust_to.max(__trust_from)].to_vec() }, { let __trust_items = &xs; let __trust_i = (-1) as isize; let __trust_pos = if __trust_i < 0 { __trust_items.len() as isize + __trust_i } else { __trust_i }; if __trust_pos < 0 { None } else { __trust_items.get(__trust_pos as usize).cloned() } }, { let __trust_items = &xs; let __trust_i = (9) as isize; let __trust_pos = if __trust_i < 0 { __trust_items.len() as isize + __trust_i } else { __trust_i }; if __trust_pos < 0 { None } else { __trust_items.get(__trust_pos as usize).cloned() } });
+    let words = "a b c".to_string().split((" ".to_string()).as_str()).map(|s| s.to_string()).collect::<Vec<String>>();
+    println!("{:?} {:?} {} {}", { let mut __trust_items = words.clone(); __trust_items.extend((vec!["d".to_string()]).iter().cloned()); __trust_items }, { let __trust_items = &words; let __trust_len = __trust_items.len() as isize; __trust_items[{ let __trust_i = (1) as isize; (if __trust_i < 0 { (__trust_len + __trust_i).max(0) } else { __trust_i.min(__trust_len) }) as usize }..].to_vec() }, words.contains(&"b".to_string()), words[0 as usize].contains(("a".to_string()).as_str()));
+}
\ No newline at end of file
@@ -0,0 +1,31 @@
+#[cfg(feature = "extras")]
+#[derive(Debug, Clone)]
+struct Extra {
+    name: String,
+}
+
+#[cfg(feature = "extras")]
+impl std::fmt::Display for Extra {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[cfg(debug_assertions)]
+fn mode() -> String {
+    return "debug".to_string();
+}
+
+#[cfg(not(debug_assertions))]
+fn mode() -> String {
+    return "release".to_string();
+}
+
+fn main() -> () {
+    println!("{}", mode());
+    #[cfg(feature = "extras")] {
+    let extra = Extra { name: "x".to_string() };
+    println!("{}", extra.name);
+}
+    #[cfg(any(debug_assertions, test))] println!("{}", "checks on".to_string());
+}
\ No newline at end of file
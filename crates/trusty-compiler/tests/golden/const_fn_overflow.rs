@@ -0,0 +1,15 @@
+const LIMIT: i32 = 2147483000;
+
+const CAPPED: i32 = add(LIMIT, 1000);
+
+const fn add(a: i32, b: i32) -> i32 {
+    i32::saturating_add(a, b)
+}
+
+const fn scale(a: i32) -> i32 {
+    i32::saturating_sub(i32::saturating_mul(a, 4), 1)
+}
+
+fn main() -> () {
+    println!("{} {} {} {}", CAPPED, add(LIMIT, 5), scale(LIMIT), scale(-LIMIT));
+}
\ No newline at end of file
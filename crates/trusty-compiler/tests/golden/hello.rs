@@ -0,0 +1,9 @@
+fn greet(name: String) -> String {
+    return format!("Hello, {}!", name);
+}
+
+fn main() -> () {
+    println!("{}", greet("TRUST".to_string()));
+    let answer: i32 = 6 * 7;
+    println!("{}", format!("answer = {}", answer));
+}
\ No newline at end of file
@@ -0,0 +1,15 @@
+fn main() -> () {
+    let numbers: Vec<i32> = vec![1, 2, 3, 4, 5];
+    let mut sum: i32 = 0;
+    for n in (numbers).iter().cloned() {
+        sum += n;
+}
+    println!("{}", format!("sum = {}", sum));
+    let squares = numbers.iter().cloned().map(move |n: i32| n * n).collect::<Vec<_>>();
+    println!("{}", format!("squares = {}", squares.len()));
+    let mut i: i32 = 0;
+    while i < 3 {
+        println!("{}", format!("i = {}", i));
+        i = i + 1;
+}
+}
\ No newline at end of file
@@ -0,0 +1,42 @@
+#[derive(Debug, Clone)]
+struct User {
+    name: String,
+    nick: Option<String>,
+}
+
+impl std::fmt::Display for User {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl User {
+    fn greet(&self) -> String {
+    return format!("hi {}", self.name);
+    }
+}
+
+fn find(id: i32) -> Option<User> {
+    if id == 1 {
+    return Some(User { name: "ada".to_string(), nick: None });
+}
+    return None;
+}
+
+fn main() -> () {
+    let missing: Option<i32> = None;
+    let total = missing.unwrap_or_else(|| 3);
+    println!("{} {}", total, (total + 1) * 3);
+    let user = find(1);
+    let name = user.as_ref().map(|__trust_value| __trust_value.name.clone()).unwrap_or_else(|| "nobody".to_string());
+    println!("{} {}", name, find(2).as_ref().map(|__trust_value| __trust_value.name.clone()).unwrap_or_else(|| "nobody".to_string()));
+    println!("{} {}", user.as_ref().map(|__trust_value| __trust_value.greet()).unwrap_or_else(|| "-".to_string()), user.as_ref().and_then(|__trust_value| __trust_value.nick.clone()).unwrap_or_else(|| "no nick".to_string()));
+    let mut ready = false;
+    ready = ready || (total > 2);
+    ready = ready && (true || false);
+    println!("{}", ready);
+    let mut cached: Option<String> = None;
+    if cached.is_none() { cached = Some("first".to_string()); };
+    if cached.is_none() { cached = Some("second".to_string()); };
+    println!("{}", cached.clone().unwrap_or_else(|| "".to_string()));
+}
\ No newline at end of file
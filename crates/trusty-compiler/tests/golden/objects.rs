@@ -0,0 +1,49 @@
+#[derive(Debug, Clone)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+impl std::fmt::Display for Point {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ConfigTls {
+    enabled: bool,
+    retries: i32,
+}
+
+impl std::fmt::Display for ConfigTls {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Config {
+    host: String,
+    port: i32,
+    tls: ConfigTls,
+}
+
+impl std::fmt::Display for Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+fn origin() -> String {
+    let point = Point { x: 0, y: 0 };
+    return format!("{},{}", point.x, point.y);
+}
+
+fn main() -> () {
+    let host = "localhost".to_string();
+    let config = Config { host: host, port: 8080, tls: ConfigTls { enabled: false, retries: 3 } };
+    println!("{} {} {} {}", config.host, config.port, config.tls.enabled, config.tls.retries);
+    println!("{}", config);
+    println!("{}", origin());
+}
\ No newline at end of file
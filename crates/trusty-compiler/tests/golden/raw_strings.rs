@@ -0,0 +1,13 @@
+const PATTERN: &'static str = r"\d+\.\d+";
+
+fn main() -> () {
+    let path = r"C:\Users\trust\".to_string();
+    let json = r#"{ "name": "trust", "tags": ["a\n", "b"] }"#.to_string();
+    let block = r"line one
+  line two \n".to_string();
+    println!("{}", PATTERN);
+    println!("{}", path);
+    println!("{}", json);
+    println!("{}", block);
+    println!("{}", format!("{} {}", path.chars().count() as i32, block.chars().count() as i32));
+}
\ No newline at end of file
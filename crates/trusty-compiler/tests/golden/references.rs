@@ -0,0 +1,35 @@
+fn bump(n: &mut i32) -> () {
+    *n = *n + 1;
+    *n += 2;
+}
+
+fn twice(n: &i32) -> i32 {
+    if *n > 10 {
+    return *n;
+}
+    return *n * 2;
+}
+
+fn shout(s: &mut String, times: &i32) -> () {
+    s.push_str("!");
+    s.push_str("?");
+    println!("{} {}", s.chars().count() as i32, times);
+}
+
+const fn square(x: i32) -> i32 {
+    x * x
+}
+
+fn relay(n: &mut i32) -> i32 {
+    bump(n);
+    return square(*n);
+}
+
+fn main() -> () {
+    let mut x = 1;
+    bump(&mut x);
+    let mut s = "hi".to_string();
+    shout(&mut s, &x);
+    let r = relay(&mut x);
+    println!("{} {} {} {}", x, twice(&x), r, s);
+}
\ No newline at end of file
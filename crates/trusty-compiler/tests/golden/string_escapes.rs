@@ -0,0 +1,32 @@
+#[derive(Debug, Clone)]
+enum Quote {
+    Open,
+    Close,
+}
+
+impl Quote {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Quote::Open => "{\"",
+            Quote::Close => "\\}"
+        }
+    }
+}
+
+impl std::fmt::Display for Quote {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+const BRACES: &'static str = "{} \"x\"";
+
+fn main() -> () {
+    let n = 1;
+    println!("{}", format!("{{n}} = {}, \"quoted\" \\ ` tab:\t.", n));
+    println!("{}", "{plain} \"text\"".to_string());
+    println!("{}", Quote::Open);
+    println!("{}", Quote::Close);
+    println!("{}", BRACES);
+    println!("a {} \"b\"", n);
+}
\ No newline at end of file
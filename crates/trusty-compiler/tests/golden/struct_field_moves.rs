@@ -0,0 +1,37 @@
+#[derive(Debug, Clone)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+impl std::fmt::Display for Point {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Line {
+    a: Point,
+    b: Point,
+    label: String,
+}
+
+impl std::fmt::Display for Line {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+fn length(line: Line) -> i32 {
+    return line.b.x - line.a.x + line.b.y - line.a.y;
+}
+
+fn main() -> () {
+    let p1 = Point { x: 1, y: 2 };
+    let p2 = Point { x: 4, y: 6 };
+    let label = "first".to_string();
+    let first = Line { a: p1.clone(), b: p2, label: label.clone() };
+    let second = Line { a: p1, b: Point { x: 0, y: 0 }, label: label.clone() };
+    println!("{} {} {} {}", length(first), second.a.x, second.label, label);
+}
\ No newline at end of file
@@ -0,0 +1,42 @@
+#[derive(Debug, Clone)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+impl std::fmt::Display for Point {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Shape {
+    Dot,
+    Square,
+}
+
+impl std::fmt::Display for Shape {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Point {
+    fn manhattan(&self) -> i32 {
+    return self.x + self.y;
+    }
+}
+
+fn describe(shape: Shape) -> String {
+    return match shape { Shape::Dot => "dot".to_string(), Shape::Square => "square".to_string() };
+}
+
+fn main() -> () {
+    let p = Point { x: 3, y: 4 };
+    println!("{}", format!("manhattan = {}", p.manhattan()));
+    println!("{}", describe(Shape::Square));
+    if p.x > 0 && p.y > 0 {
+    println!("{}", "first quadrant".to_string());
+}
+}
\ No newline at end of file
@@ -0,0 +1,91 @@
+#[allow(unused_imports)]
+use std::time::{Instant, Duration, SystemTime as RustSystemTime};
+#[allow(unused_imports)]
+use std::thread::sleep;
+
+thread_local! {
+    static __TRUST_CANCEL_TOKEN: std::cell::RefCell<Option<std::sync::Arc<std::sync::atomic::AtomicBool>>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+struct TrustTask<T> {
+    result: std::sync::mpsc::Receiver<std::thread::Result<T>>,
+    cancelled: std::sync::Arc<std::sync::atomic::AtomicBool>,
+}
+
+#[allow(dead_code, non_snake_case)]
+impl<T: Send + 'static> TrustTask<T> {
+    fn spawn(body: impl FnOnce() -> T + Send + 'static) -> TrustTask<T> {
+        let (sender, result) = std::sync::mpsc::channel();
+        let cancelled = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let token = cancelled.clone();
+        std::thread::spawn(move || {
+            __TRUST_CANCEL_TOKEN.with(|slot| *slot.borrow_mut() = Some(token));
+            let _ = sender.send(std::panic::catch_unwind(std::panic::AssertUnwindSafe(body)));
+        });
+        TrustTask { result, cancelled }
+    }
+
+    /// Waits for the body; `Err` when it panicked.
+    fn join(self) -> std::thread::Result<T> {
+        self.result.recv().unwrap_or_else(|_| Err(Box::new("the task's result was already taken")))
+    }
+
+    /// Asks the body to stop: `isCancelled()` turns true inside it.
+    fn cancel(&self) {
+        self.cancelled.store(true, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    fn isCancelled(&self) -> bool {
+        self.cancelled.load(std::sync::atomic::Ordering::SeqCst)
+    }
+}
+
+/// Whether the task running this code was cancelled; always false outside an `async function`.
+#[allow(dead_code, non_snake_case)]
+fn isCancelled() -> bool {
+    __TRUST_CANCEL_TOKEN.with(|slot| {
+        slot.borrow()
+            .as_ref()
+            .is_some_and(|token| token.load(std::sync::atomic::Ordering::SeqCst))
+    })
+}
+
+/// The task's result if it finishes within `ms` milliseconds; the task keeps running otherwise.
+#[allow(dead_code, non_snake_case)]
+fn awaitTimeout<T>(task: &TrustTask<T>, ms: i32) -> Option<T> {
+    match task.result.recv_timeout(std::time::Duration::from_millis(ms.max(0) as u64)) {
+        Ok(Ok(value)) => Some(value),
+        Ok(Err(panic)) => std::panic::resume_unwind(panic),
+        Err(_) => None,
+    }
+}
+
+fn slow(n: i32) -> TrustTask<i32> {
+    TrustTask::spawn(move || {
+        sleep(Duration::from_millis((100) as u64));
+        return n;
+    })
+}
+
+fn worker() -> TrustTask<String> {
+    TrustTask::spawn(move || {
+        loop {
+        if isCancelled() {
+        return "stopped".to_string();
+    }
+        sleep(Duration::from_millis((5) as u64));
+}
+    })
+}
+
+fn main() -> () {
+    let task = slow(7);
+    println!("{:?}", awaitTimeout(&task, (5) as i32));
+    println!("{:?}", awaitTimeout(&task, (1000) as i32));
+    let background = worker();
+    background.cancel();
+    println!("{}", (background).join().unwrap());
+    println!("{}", isCancelled());
+    println!("{}", vec![slow(1), slow(2)].into_iter().map(|__trust_task| __trust_task.join().unwrap()).collect::<Vec<_>>().len());
+}
\ No newline at end of file
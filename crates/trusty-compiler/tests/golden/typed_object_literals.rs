@@ -0,0 +1,23 @@
+#[derive(Debug, Clone)]
+struct Person {
+    name: String,
+    age: i32,
+    nickname: Option<String>,
+}
+
+impl std::fmt::Display for Person {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+fn main() -> () {
+    let ada: Person = Person { name: "Ada".to_string(), age: 36, nickname: None };
+    let mut people: Vec<Person> = vec![ada, Person { name: "Alan".to_string(), age: 41, nickname: Some("Prof".to_string()) }];
+    people.push(Person { name: "Grace".to_string(), age: 45, nickname: None });
+    let maybe: Option<Person> = Some(Person { name: "Linus".to_string(), age: 28, nickname: None });
+    for person in (people).iter().cloned() {
+        println!("{} {}", person.name, person.age);
+}
+    println!("{} {}", people.len(), maybe.as_ref().map(|__trust_value| __trust_value.name.clone()).unwrap_or_else(|| "nobody".to_string()));
+}
\ No newline at end of file
@@ -0,0 +1,22 @@
+#[derive(Debug, Clone)]
+struct Café {
+    année: i32,
+    名前: String,
+}
+
+impl std::fmt::Display for Café {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+const fn ñandú(x: i32) -> i32 {
+    x * 2
+}
+
+fn main() -> () {
+    let café = Café { année: 2024, 名前: "東京".to_string() };
+    let été = café.année > 2000 && café.année < 3000;
+    let _dollar_total = ñandú(café.année);
+    println!("{}", format!("{} {} {}", café.名前, été, _dollar_total));
+}
\ No newline at end of file
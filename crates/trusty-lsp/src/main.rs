@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use tokio::sync::RwLock;
@@ -6,22 +7,6 @@ use tower_lsp::jsonrpc::Result;
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer, LspService, Server};
 
-const TRUSTY_MODULES: &[&str] = &["trusty:time", "trusty:math", "trusty:rand"];
-
-fn trusty_module_exports(module_path: &str) -> &'static [&'static str] {
-    match module_path {
-        "trusty:math" => &[
-            "PI", "E", "sqrt", "pow", "log", "abs", "min", "max", "clamp", "sin", "cos", "tan", "asin", "acos", "atan",
-        ],
-        "trusty:time" => &[
-            "Instant", "Duration", "sleep", "Date", "Time", "DateTime", "SystemTime", "compare", "addSeconds", "addMinutes", "addDays",
-            "addMonths", "addYears", "subSeconds", "subMinutes", "subDays", "subMonths", "subYears",
-        ],
-        "trusty:rand" => &["random", "randomInt", "randomFloat", "bernoulli", "weightedIndex", "chooseOne", "shuffle"],
-        _ => &[],
-    }
-}
-
 struct Backend {
     client: Client,
     docs: Arc<RwLock<HashMap<Url, String>>>,
@@ -36,33 +21,244 @@ impl Backend {
     }
 
     async fn publish_diagnostics(&self, uri: Url, text: &str) {
-        let diagnostics = match trusty_compiler::compile(text) {
-            Ok(_) => Vec::new(),
-            Err(err) => {
-                let message = err.to_string();
-                let range = Self::range_from_error_message(text, &message).unwrap_or(Range {
-                    start: Position::new(0, 0),
-                    end: Position::new(0, 1),
-                });
-                vec![Diagnostic {
-                    range,
-                    severity: Some(DiagnosticSeverity::ERROR),
-                    code: None,
-                    code_description: None,
-                    source: Some("trusty-compiler".to_string()),
-                    message,
-                    related_information: None,
-                    tags: None,
-                    data: None,
-                }]
+        let mut diagnostics: Vec<Diagnostic> = Self::compiler_diagnostics(&uri, text)
+            .iter()
+            .map(|(diag, source)| Self::lsp_diagnostic(text, diag, source))
+            .collect();
+        if Self::manifest_root(&uri).is_none() {
+            if let Some(import_error) = self.import_diagnostic(&uri, text).await {
+                diagnostics.push(import_error);
             }
+        }
+
+        self.client
+            .publish_diagnostics(uri, diagnostics, None)
+            .await;
+    }
+
+    /// Compiler diagnostics and lint findings for `text`, each with its LSP `source`; for a
+    /// `trusty.json`, the manifest checks instead.
+    fn compiler_diagnostics(
+        uri: &Url,
+        text: &str,
+    ) -> Vec<(trusty_compiler::Diagnostic, &'static str)> {
+        if let Some(root) = Self::manifest_root(uri) {
+            return trusty_compiler::manifest::check(text, &root)
+                .into_iter()
+                .map(|diag| (diag, "trusty-manifest"))
+                .collect();
+        }
+        let output = trusty_compiler::Compiler::new()
+            .options(Self::compile_options(uri))
+            .compile(text);
+        let mut diagnostics: Vec<_> = output
+            .diagnostics
+            .into_iter()
+            .map(|diag| (diag, "trusty-compiler"))
+            .collect();
+        let lint_config = Self::lint_config(uri);
+        // A parse error is already among the compiler's diagnostics.
+        diagnostics.extend(
+            trusty_compiler::lint::lint(text, &lint_config)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|diag| (diag, "trusty-lint")),
+        );
+        diagnostics
+    }
+
+    /// Project directory of a `trusty.json` document; `None` for any other file.
+    fn manifest_root(uri: &Url) -> Option<PathBuf> {
+        let path = uri.to_file_path().ok()?;
+        if path.file_name()? != "trusty.json" {
+            return None;
+        }
+        path.parent().map(Path::to_path_buf)
+    }
+
+    /// Quick fix applying `diag.fix`, if it has one.
+    fn code_action(
+        uri: &Url,
+        text: &str,
+        diag: &trusty_compiler::Diagnostic,
+        source: &str,
+    ) -> Option<CodeAction> {
+        let fix = diag.fix.as_ref()?;
+        let edits = fix
+            .edits
+            .iter()
+            .map(|edit| TextEdit {
+                range: Self::range_from_span(text, edit.span.start, edit.span.end),
+                new_text: edit.replacement.clone(),
+            })
+            .collect();
+        Some(CodeAction {
+            title: fix.title.clone(),
+            kind: Some(CodeActionKind::QUICKFIX),
+            diagnostics: Some(vec![Self::lsp_diagnostic(text, diag, source)]),
+            edit: Some(WorkspaceEdit {
+                changes: Some(HashMap::from([(uri.clone(), edits)])),
+                ..WorkspaceEdit::default()
+            }),
+            is_preferred: Some(true),
+            ..CodeAction::default()
+        })
+    }
+
+    fn lsp_diagnostic(text: &str, diag: &trusty_compiler::Diagnostic, source: &str) -> Diagnostic {
+        let range = diag
+            .span
+            .map(|span| Self::range_from_span(text, span.start, span.end))
+            .or_else(|| Self::range_from_error_message(text, &diag.message))
+            .unwrap_or(Range {
+                start: Position::new(0, 0),
+                end: Position::new(0, 1),
+            });
+        let severity = match diag.severity {
+            trusty_compiler::Severity::Error => DiagnosticSeverity::ERROR,
+            trusty_compiler::Severity::Warning => DiagnosticSeverity::WARNING,
+        };
+        Diagnostic {
+            range,
+            severity: Some(severity),
+            code: Some(NumberOrString::String(diag.code.to_string())),
+            code_description: None,
+            source: Some(source.to_string()),
+            message: diag.message.clone(),
+            related_information: None,
+            tags: None,
+            data: None,
+        }
+    }
+
+    /// The nearest `trusty.json` above the document, parsed; `null` when there is none or it is
+    /// not valid JSON.
+    fn project_manifest(uri: &Url) -> serde_json::Value {
+        let Ok(path) = uri.to_file_path() else {
+            return serde_json::Value::Null;
         };
+        path.ancestors()
+            .skip(1)
+            .map(|dir| dir.join("trusty.json"))
+            .find(|m| m.exists())
+            .and_then(|manifest| std::fs::read_to_string(manifest).ok())
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    /// Compiler options of the document's project, so the editor reports what `trusty build` does.
+    fn compile_options(uri: &Url) -> trusty_compiler::CompileOptions {
+        trusty_compiler::manifest::compile_options(&Self::project_manifest(uri))
+    }
 
-        self.client.publish_diagnostics(uri, diagnostics, None).await;
+    /// Lint levels from the `lint` block of the nearest `trusty.json`; invalid entries are skipped
+    /// (`trusty lint` reports them).
+    fn lint_config(uri: &Url) -> trusty_compiler::LintConfig {
+        let mut config = trusty_compiler::LintConfig::default();
+        let json = Self::project_manifest(uri);
+        for (key, value) in json
+            .get("lint")
+            .and_then(|lint| lint.as_object())
+            .into_iter()
+            .flatten()
+        {
+            let value = match value {
+                serde_json::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            let _ = config.configure(key, &value);
+        }
+        config
+    }
+
+    /// Bundles the document's `./imports`, reading open (possibly unsaved) buffers before the disk,
+    /// and reports the first resolution failure on the offending import line.
+    async fn import_diagnostic(&self, uri: &Url, text: &str) -> Option<Diagnostic> {
+        let path = uri.to_file_path().ok()?;
+        let mut buffers: HashMap<PathBuf, String> = self
+            .docs
+            .read()
+            .await
+            .iter()
+            .filter_map(|(doc_uri, doc_text)| {
+                Some((doc_uri.to_file_path().ok()?, doc_text.clone()))
+            })
+            .collect();
+        buffers.insert(path.clone(), text.to_string());
+
+        let loader = move |file: &Path| match buffers.get(file) {
+            Some(buffer) => Ok(buffer.clone()),
+            None => std::fs::read_to_string(file),
+        };
+        let err = trusty_compiler::Compiler::new()
+            .file_loader(loader)
+            .bundle(&path)
+            .err()?;
+        let message = format!("{:#}", err);
+        let imports = err
+            .downcast_ref::<trusty_compiler::modules::ImportChainError>()
+            .map(|chained| chained.imports.as_slice())
+            .unwrap_or_default();
+        let site_range = |site: &trusty_compiler::modules::ImportSite| Range {
+            start: Position::new(site.line as u32, 0),
+            end: Position::new(site.line as u32, site.width as u32),
+        };
+
+        // The chain starts at this file's import; the imports after it, in other modules, lead to
+        // the one that failed.
+        let range = match imports.first() {
+            Some(site) => site_range(site),
+            None => {
+                let line = text
+                    .lines()
+                    .position(|line| {
+                        trusty_compiler::modules::parse_local_import_path(line).is_some()
+                    })
+                    .unwrap_or(0);
+                let width = text
+                    .lines()
+                    .nth(line)
+                    .map(|l| l.chars().count())
+                    .unwrap_or(1);
+                Range {
+                    start: Position::new(line as u32, 0),
+                    end: Position::new(line as u32, width as u32),
+                }
+            }
+        };
+        let related: Vec<DiagnosticRelatedInformation> = imports
+            .iter()
+            .skip(1)
+            .filter_map(|site| {
+                Some(DiagnosticRelatedInformation {
+                    location: Location::new(
+                        Url::from_file_path(&site.module).ok()?,
+                        site_range(site),
+                    ),
+                    message: format!("imports `{}`", site.specifier),
+                })
+            })
+            .collect();
+
+        Some(Diagnostic {
+            range,
+            severity: Some(DiagnosticSeverity::ERROR),
+            code: Some(NumberOrString::String("module-error".to_string())),
+            code_description: None,
+            source: Some("trusty-compiler".to_string()),
+            message,
+            related_information: (!related.is_empty()).then_some(related),
+            tags: None,
+            data: None,
+        })
     }
 
     fn range_from_error_message(text: &str, message: &str) -> Option<Range> {
         let (start, end) = Self::extract_byte_span(message)?;
+        Some(Self::range_from_span(text, start, end))
+    }
+
+    fn range_from_span(text: &str, start: usize, end: usize) -> Range {
         let text_len = text.len();
         let start = start.min(text_len);
         let end = end.max(start.saturating_add(1)).min(text_len);
@@ -71,10 +267,10 @@ impl Backend {
         if end_pos == start_pos {
             end_pos.character = end_pos.character.saturating_add(1);
         }
-        Some(Range {
+        Range {
             start: start_pos,
             end: end_pos,
-        })
+        }
     }
 
     fn extract_byte_span(message: &str) -> Option<(usize, usize)> {
@@ -135,15 +331,76 @@ impl Backend {
 
     fn completion_items() -> Vec<CompletionItem> {
         let keywords = [
-            "function", "struct", "enum", "implements", "import", "export", "from", "val", "var", "const",
-            "if", "else", "match", "default", "try", "catch", "finally", "for", "in", "of", "loop",
-            "break", "continue", "return", "throw", "and", "or", "async", "await",
+            "function",
+            "struct",
+            "enum",
+            "implements",
+            "import",
+            "export",
+            "from",
+            "val",
+            "var",
+            "const",
+            "if",
+            "else",
+            "match",
+            "default",
+            "try",
+            "catch",
+            "finally",
+            "for",
+            "in",
+            "of",
+            "loop",
+            "do",
+            "break",
+            "continue",
+            "return",
+            "throw",
+            "and",
+            "or",
+            "async",
+            "await",
         ];
         let types = [
-            "int", "int8", "int16", "int32", "int64", "float", "float32", "float64", "string", "boolean",
-            "Pointer", "Threaded", "Map", "Set", "Result",
+            "int",
+            "int8",
+            "int16",
+            "int32",
+            "int64",
+            "float",
+            "float32",
+            "float64",
+            "decimal",
+            "bytes",
+            "string",
+            "boolean",
+            "StringBuilder",
+            "Pointer",
+            "WeakPointer",
+            "Threaded",
+            "Map",
+            "Set",
+            "Result",
+            "TrustError",
+        ];
+        let builtins = [
+            "string",
+            "boolean",
+            "int32",
+            "float64",
+            "console.write",
+            "console.writeInline",
+            "downgrade",
+            "upgrade",
+            "panic",
+            "unreachable",
+            "todo",
+            "iter",
+            "awaitAll",
+            "awaitTimeout",
+            "isCancelled",
         ];
-        let builtins = ["string", "boolean", "int32", "float64", "console.write"];
 
         let mut out = Vec::new();
         for kw in keywords {
@@ -170,9 +427,20 @@ impl Backend {
         out
     }
 
+    /// Byte offset in `line` of the UTF-16 column `character` that LSP positions use.
+    fn byte_column(line: &str, character: usize) -> usize {
+        let mut units = 0;
+        for (i, ch) in line.char_indices() {
+            if units >= character {
+                return i;
+            }
+            units += ch.len_utf16();
+        }
+        line.len()
+    }
+
     fn line_prefix(line: &str, col: usize) -> &str {
-        let end = col.min(line.len());
-        &line[..end]
+        &line[..Self::byte_column(line, col)]
     }
 
     fn completion_for_import_path(line: &str, col: usize) -> Option<Vec<CompletionItem>> {
@@ -187,9 +455,9 @@ impl Backend {
         }
 
         let mut out = Vec::new();
-        for m in TRUSTY_MODULES {
+        for m in trusty_compiler::stdlib::MODULES {
             out.push(CompletionItem {
-                label: (*m).to_string(),
+                label: format!("trusty:{}", m),
                 kind: Some(CompletionItemKind::MODULE),
                 detail: Some("TRUST stdlib module".to_string()),
                 ..CompletionItem::default()
@@ -226,19 +494,16 @@ impl Backend {
         }
         let open = prefix.find('{')?;
         let close = prefix.find('}').unwrap_or(prefix.len());
-        if col < open + 1 || col > close {
+        if prefix.len() < open + 1 || prefix.len() > close {
             return None;
         }
         let module = Self::parse_trusty_import_symbols_line(line)?;
-        let exports = trusty_module_exports(&module);
-        if exports.is_empty() {
-            return None;
-        }
+        let exports = trusty_compiler::stdlib::exports(module.strip_prefix("trusty:")?)?;
 
         let mut out = Vec::new();
         for sym in exports {
             out.push(CompletionItem {
-                label: (*sym).to_string(),
+                label: sym.to_string(),
                 kind: Some(CompletionItemKind::VARIABLE),
                 detail: Some(format!("export from {}", module)),
                 ..CompletionItem::default()
@@ -290,7 +555,7 @@ impl Backend {
     }
 
     fn is_ident(ch: char) -> bool {
-        ch.is_ascii_alphanumeric() || ch == '_'
+        ch.is_alphanumeric() || ch == '_' || ch == '$'
     }
 
     fn parse_var_decl_type(trimmed: &str) -> Option<(String, String)> {
@@ -337,7 +602,7 @@ impl Backend {
             if ctor
                 .chars()
                 .next()
-                .map(|c| c.is_ascii_uppercase())
+                .map(|c| c.is_uppercase())
                 .unwrap_or(false)
             {
                 return Some((name, ctor));
@@ -377,7 +642,11 @@ impl Backend {
         Some(name.chars().rev().collect())
     }
 
-    fn completion_for_member_access(text: &str, line: usize, col: usize) -> Option<Vec<CompletionItem>> {
+    fn completion_for_member_access(
+        text: &str,
+        line: usize,
+        col: usize,
+    ) -> Option<Vec<CompletionItem>> {
         let lines: Vec<&str> = text.lines().collect();
         let current = lines.get(line)?;
         let target = Self::member_target_before_cursor(current, col)?;
@@ -405,13 +674,18 @@ impl Backend {
             "var" => Some("`var`: mutable local variable."),
             "const" => Some("`const`: global constant."),
             "match" => Some("`match (x) { pat => expr, default => expr }`: expression match."),
-            "loop" => Some("`loop (cond) { ... }`: conditional loop."),
+            "loop" => Some("`loop (cond) { ... }`: conditional loop; `loop { ... }` runs until `break`."),
+            "do" => Some("`do { ... } loop (cond);`: loop that tests its condition after each pass."),
             "async" => Some("`async function`: runs the function body in a thread and returns a handle."),
             "await" => Some("`await handle`: waits for a spawned async handle (`join().unwrap()`)."),
             "string" => Some("`string(...)`: cast value to TRUST string."),
             "boolean" => Some("`boolean(...)`: cast value to TRUST boolean."),
             "int32" => Some("`int32`: 32-bit signed integer."),
             "float64" => Some("`float64`: 64-bit floating point."),
+            "bytes" => Some("`bytes`: binary data (`Vec<u8>`); literals are written `b\"...\"` or `hex\"...\"`."),
+            "decimal" => Some("`decimal`: exact base-10 number for money math; literals are written `12.34d`."),
+            "StringBuilder" => Some("`StringBuilder.new()`: appends with `push(x)`/`pushLine(x)`; `toString()` gives the text."),
+            "iter" => Some("`iter(items)`: lazy iterator; chain `map`/`filter`/`take`, then `collect()`."),
             "Point" => Some("Struct constructor style: `Point({ x: 1, y: 2 })`."),
             _ => None,
         }
@@ -427,12 +701,20 @@ impl LanguageServer for Backend {
                 version: Some(env!("CARGO_PKG_VERSION").to_string()),
             }),
             capabilities: ServerCapabilities {
-                text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+                text_document_sync: Some(TextDocumentSyncCapability::Kind(
+                    TextDocumentSyncKind::FULL,
+                )),
                 completion_provider: Some(CompletionOptions {
-                    trigger_characters: Some(vec![".".to_string(), "\"".to_string(), "{".to_string(), ",".to_string()]),
+                    trigger_characters: Some(vec![
+                        ".".to_string(),
+                        "\"".to_string(),
+                        "{".to_string(),
+                        ",".to_string(),
+                    ]),
                     ..CompletionOptions::default()
                 }),
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
+                code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
                 ..ServerCapabilities::default()
             },
         })
@@ -499,6 +781,28 @@ impl LanguageServer for Backend {
         Ok(Some(CompletionResponse::Array(Self::completion_items())))
     }
 
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let uri = params.text_document.uri;
+        let docs = self.docs.read().await;
+        let Some(text) = docs.get(&uri) else {
+            return Ok(None);
+        };
+
+        let requested = params.range;
+        let actions: Vec<CodeActionOrCommand> = Self::compiler_diagnostics(&uri, text)
+            .iter()
+            .filter(|(diag, _)| {
+                diag.span.is_some_and(|span| {
+                    let range = Self::range_from_span(text, span.start, span.end);
+                    range.start <= requested.end && requested.start <= range.end
+                })
+            })
+            .filter_map(|(diag, source)| Self::code_action(&uri, text, diag, source))
+            .map(CodeActionOrCommand::CodeAction)
+            .collect();
+        Ok((!actions.is_empty()).then_some(actions))
+    }
+
     async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
         let text_doc = params.text_document_position_params.text_document;
         let position = params.text_document_position_params.position;
@@ -512,30 +816,16 @@ impl LanguageServer for Backend {
         let Some(line) = lines.get(position.line as usize) else {
             return Ok(None);
         };
-        let col = position.character as usize;
-        if col > line.len() {
-            return Ok(None);
-        }
-
-        let bytes = line.as_bytes();
-        let mut start = col;
-        while start > 0 {
-            let c = bytes[start - 1] as char;
-            if c.is_ascii_alphanumeric() || c == '_' {
-                start -= 1;
-            } else {
-                break;
-            }
-        }
-        let mut end = col;
-        while end < bytes.len() {
-            let c = bytes[end] as char;
-            if c.is_ascii_alphanumeric() || c == '_' {
-                end += 1;
-            } else {
-                break;
-            }
-        }
+        let col = Self::byte_column(line, position.character as usize);
+        let start = line[..col]
+            .char_indices()
+            .rev()
+            .take_while(|&(_, c)| Self::is_ident(c))
+            .last()
+            .map_or(col, |(i, _)| i);
+        let end = line[col..]
+            .find(|c| !Self::is_ident(c))
+            .map_or(line.len(), |n| col + n);
         if start >= end {
             return Ok(None);
         }
@@ -548,8 +838,8 @@ impl LanguageServer for Backend {
         Ok(Some(Hover {
             contents: HoverContents::Scalar(MarkedString::String(doc.to_string())),
             range: Some(Range {
-                start: Position::new(position.line, start as u32),
-                end: Position::new(position.line, end as u32),
+                start: Position::new(position.line, line[..start].encode_utf16().count() as u32),
+                end: Position::new(position.line, line[..end].encode_utf16().count() as u32),
             }),
         }))
     }
@@ -0,0 +1,171 @@
+//! Runtime of the `trusty:*` standard library. Each module is pasted into the programs that
+//! import it, or linked from here with `compilerOptions.runtimeCrate`: the generated code then
+//! only holds `use trusty_runtime::time::*;`. Modules with external crates sit behind a feature
+//! of the same name. The sources live in `trusty-compiler/runtime/`, which the compiler embeds.
+
+// Runtime names follow TRUST's camelCase.
+#![allow(non_snake_case)]
+
+// The compiler pastes these sources as written, reading their `use` lines one by one.
+#[path = "../../trusty-compiler/runtime/math.rs"]
+#[rustfmt::skip]
+pub mod math;
+// A module's leading `use` lines are also the imports of the programs it is pasted into, which
+// call `sleep` through `use std::thread::sleep;`.
+#[allow(unused_imports)]
+#[path = "../../trusty-compiler/runtime/time.rs"]
+#[rustfmt::skip]
+pub mod time;
+#[path = "../../trusty-compiler/runtime/args.rs"]
+#[rustfmt::skip]
+pub mod args;
+#[path = "../../trusty-compiler/runtime/color.rs"]
+#[rustfmt::skip]
+pub mod color;
+#[path = "../../trusty-compiler/runtime/tcp.rs"]
+#[rustfmt::skip]
+pub mod tcp;
+#[path = "../../trusty-compiler/runtime/udp.rs"]
+#[rustfmt::skip]
+pub mod udp;
+#[cfg(feature = "json")]
+#[path = "../../trusty-compiler/runtime/json.rs"]
+#[rustfmt::skip]
+pub mod json;
+#[cfg(feature = "http")]
+#[path = "../../trusty-compiler/runtime/http.rs"]
+#[rustfmt::skip]
+pub mod http;
+#[cfg(feature = "rand")]
+#[path = "../../trusty-compiler/runtime/rand.rs"]
+#[rustfmt::skip]
+pub mod rand;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_math_runtime() {
+        assert_eq!(math::clamp(15, 0, 10), 10);
+        assert_eq!(math::sign(-3.5), -1.0);
+        assert_eq!(math::abs(-7i64), 7);
+        assert_eq!(math::pow(2, 10), 1024.0);
+        assert!(math::isNaN(f64::NAN));
+    }
+
+    #[test]
+    fn test_time_runtime() {
+        let leap = time::Date::fromYmd(2024, 2, 31);
+        assert_eq!(leap.toIsoString(), "2024-02-29");
+        assert_eq!(leap.addDays(1).toIsoString(), "2024-03-01");
+        let parsed =
+            time::DateTime::parseIso("2026-03-01T10:30:15+02:00").expect("valid ISO date-time");
+        assert_eq!(parsed.format("YYYY-MM-DD HH:mm:ss"), "2026-03-01 08:30:15");
+        assert_eq!(
+            time::Date::parseIso("1970-01-01").map(|date| date.toUnixDays()),
+            Some(0)
+        );
+        assert_eq!(time::Date::parseIso("2026-13-45"), None);
+    }
+
+    #[test]
+    fn test_args_runtime() {
+        let parser = args::ArgParser::create("serve".to_string())
+            .flag("verbose".to_string())
+            .option("port".to_string(), "8080".to_string())
+            .help("Port to listen on".to_string())
+            .positional("dir".to_string());
+        let words = |line: &str| {
+            line.split_whitespace()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        };
+
+        let parsed = parser.parseFrom(words("--port=9000 site --verbose -- --extra -1"));
+        assert!(parsed.flag("verbose".to_string()));
+        assert_eq!(parsed.getInt("port".to_string()), 9000);
+        assert_eq!(parsed.get("dir".to_string()), "site");
+        assert_eq!(parsed.rest(), words("--extra -1"));
+
+        let parsed = parser.parseFrom(words("public"));
+        assert!(!parsed.flag("verbose".to_string()));
+        assert_eq!(parsed.get("port".to_string()), "8080");
+
+        let usage = parser.usage();
+        assert!(
+            usage.starts_with("Usage: serve [options] <dir>"),
+            "{}",
+            usage
+        );
+        assert!(
+            usage.contains("  --port <value>  Port to listen on (default: 8080)"),
+            "{}",
+            usage
+        );
+    }
+
+    #[test]
+    fn test_color_runtime() {
+        color::setColorEnabled(true);
+        let mut warn = color::style();
+        warn.color = "yellow".to_string();
+        warn.bold = true;
+        assert_eq!(
+            color::styled("careful".to_string(), warn),
+            "\x1b[1;33mcareful\x1b[22;39m"
+        );
+        let nested = color::red(format!("a {} c", color::bold("b".to_string())));
+        assert_eq!(nested, "\x1b[31ma \x1b[1mb\x1b[22m c\x1b[39m");
+        assert_eq!(color::stripColors(nested), "a b c");
+
+        color::setColorEnabled(false);
+        assert_eq!(color::green("ok".to_string()), "ok");
+        assert_eq!(color::styled("plain".to_string(), color::style()), "plain");
+    }
+
+    #[test]
+    fn test_tcp_runtime() {
+        let listener = tcp::TcpListener::bindOn("127.0.0.1:0".to_string());
+        assert!(listener.ok, "{}", listener.error);
+        let client = tcp::TcpStream::connect("127.0.0.1".to_string(), listener.port);
+        let conn = listener.accept();
+        assert!(client.ok && conn.ok);
+
+        assert!(client.write("HELO a\r\nHELO b\nrest".to_string()));
+        client.close();
+        assert_eq!(conn.readLine(), "HELO a");
+        assert_eq!(conn.readLine(), "HELO b");
+        assert_eq!(conn.readLine(), "rest");
+        assert_eq!(conn.readLine(), "");
+        assert!(conn.isClosed());
+
+        let idle = tcp::TcpStream::connect("127.0.0.1".to_string(), listener.port);
+        assert!(idle.setTimeout(20));
+        assert_eq!(idle.read(), "");
+        assert_eq!(idle.lastError(), "timed out");
+        assert!(!idle.isClosed());
+
+        let refused = tcp::TcpStream::connect("127.0.0.1".to_string(), 70_000);
+        assert!(!refused.ok);
+        assert_eq!(refused.error, "invalid port 70000");
+    }
+
+    #[test]
+    fn test_udp_runtime() {
+        let server = udp::UdpSocket::bindOn("127.0.0.1:0".to_string());
+        let client = udp::UdpSocket::bindOn("127.0.0.1:0".to_string());
+        assert!(server.ok && client.ok);
+
+        assert!(client.sendTo("ping".to_string(), "127.0.0.1".to_string(), server.port));
+        let packet = server.receive();
+        assert!(packet.ok);
+        assert_eq!((packet.text.as_str(), packet.port), ("ping", client.port));
+        assert!(server.sendBytesTo(vec![1, 2, 3], packet.host, packet.port));
+        assert_eq!(client.receive().bytes, vec![1, 2, 3]);
+
+        assert!(server.setTimeout(20));
+        assert!(!server.receive().ok);
+        assert_eq!(server.lastError(), "timed out");
+    }
+}
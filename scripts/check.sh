@@ -0,0 +1,13 @@
+#!/usr/bin/env sh
+# Checks to run before pushing: build, lints and tests of the workspace, every runtime feature,
+# the LSP, and the playground build of the compiler.
+set -eu
+cd "$(dirname "$0")/.."
+
+cargo build --workspace
+cargo clippy --workspace --all-targets -- -D warnings
+cargo clippy -p trusty-runtime --all-features --all-targets -- -D warnings
+cargo test --workspace
+cargo build --manifest-path crates/trusty-lsp/Cargo.toml
+# The playground compiles in the browser: needs `rustup target add wasm32-unknown-unknown`.
+cargo clippy -p trusty-compiler --target wasm32-unknown-unknown --no-default-features --features wasm -- -D warnings
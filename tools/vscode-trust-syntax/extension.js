@@ -71,7 +71,8 @@ function makeClientOptions() {
   return {
     documentSelector: [
       { scheme: "file", language: "trust" },
-      { scheme: "untitled", language: "trust" }
+      { scheme: "untitled", language: "trust" },
+      { scheme: "file", pattern: "**/trusty.json" }
     ],
     synchronize: {
       fileEvents: vscode.workspace.createFileSystemWatcher("**/*.trs")